@@ -1,4 +1,5 @@
 use agentropic_runtime::prelude::*;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), RuntimeError> {
@@ -49,7 +50,7 @@ async fn main() -> Result<(), RuntimeError> {
     println!("\n✓ Runtime stopped");
 
     // Shutdown
-    runtime.shutdown().await?;
+    runtime.shutdown(Duration::from_secs(5)).await?;
     println!("✓ Runtime shutdown complete");
 
     Ok(())
@@ -0,0 +1,39 @@
+use agentropic_runtime::prelude::*;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Stand-in for a pager integration such as PagerDuty
+struct Pager;
+
+#[async_trait]
+impl Notifier for Pager {
+    async fn notify(&self, event: &RuntimeEvent) -> Result<(), RuntimeError> {
+        println!("PAGE: {} ({})", event.kind(), event.severity());
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    println!("=== Notifications Example ===\n");
+
+    // Page on critical events only; print everything
+    let router = NotificationRouter::new()
+        .route(Route::new(Arc::new(Pager)).with_min_severity(Severity::Critical))
+        .route(Route::new(Arc::new(StdoutNotifier)));
+
+    let mut supervisor = Supervisor::new("notifying_supervisor").with_notifier(Arc::new(router));
+
+    let agent = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+    supervisor.add_child(ChildSpec::new(agent, policy, |ctx| async move {
+        println!("Attempt {}", ctx.attempt());
+        Err(RuntimeError::ExecutionFailed("upstream unavailable".into()))
+    }));
+
+    let exit = supervisor.run_child(&agent).await.unwrap();
+    supervisor.flush_notifications().await;
+    println!("\nExit: {:?}", exit);
+}
@@ -0,0 +1,71 @@
+#ifndef AGENTROPIC_RUNTIME_H
+#define AGENTROPIC_RUNTIME_H
+
+/* Generated by cbindgen from src/capi.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// No event was available
+#define AGENTROPIC_EVENT_NONE 0
+
+// A task is ready to run on the host
+#define AGENTROPIC_EVENT_TASK_READY 1
+
+// Opaque runtime handle
+typedef struct AgentropicRuntime AgentropicRuntime;
+
+// Event returned by [`agentropic_runtime_poll_event`]
+typedef struct AgentropicEvent {
+  // Event kind, one of the `AGENTROPIC_EVENT_*` constants
+  uint32_t kind;
+  // Task ID returned by [`agentropic_runtime_submit`]
+  uint64_t task_id;
+  // Host agent handle the task was submitted for
+  uint64_t agent;
+  // Task priority
+  uint32_t priority;
+  // Task payload, valid until the next poll or shutdown
+  const uint8_t *payload;
+  // Payload length in bytes
+  size_t payload_len;
+} AgentropicEvent;
+
+// Create a runtime, returning null on failure
+struct AgentropicRuntime *agentropic_runtime_new(void);
+
+// Submit a task for a host agent, returning its task ID or 0 on failure
+//
+// The payload is copied.
+//
+// # Safety
+//
+// `runtime` must come from [`agentropic_runtime_new`], and `payload` must
+// point to `payload_len` readable bytes (or be null if `payload_len` is 0).
+uint64_t agentropic_runtime_submit(struct AgentropicRuntime *runtime,
+                                   uint64_t agent,
+                                   uint32_t priority,
+                                   const uint8_t *payload,
+                                   size_t payload_len);
+
+// Poll for the next event, returning 1 if `event` was filled and 0 otherwise
+//
+// # Safety
+//
+// `runtime` must come from [`agentropic_runtime_new`] and `event` must be
+// valid for writes.
+int32_t agentropic_runtime_poll_event(struct AgentropicRuntime *runtime,
+                                      struct AgentropicEvent *event);
+
+// Stop the runtime and free it
+//
+// # Safety
+//
+// `runtime` must come from [`agentropic_runtime_new`] and must not be used
+// afterwards.
+void agentropic_runtime_shutdown(struct AgentropicRuntime *runtime);
+
+#endif  /* AGENTROPIC_RUNTIME_H */
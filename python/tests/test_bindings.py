@@ -0,0 +1,85 @@
+import asyncio
+
+import pytest
+
+import agentropic_runtime as ar
+
+
+def test_scheduler_submit_and_next_task():
+    scheduler = ar.Scheduler("fcfs")
+    task_id = scheduler.submit("planner", priority=2, payload={"goal": "plan"})
+
+    task = scheduler.next_task()
+    assert task.id == task_id
+    assert task.agent == "planner"
+    assert task.payload == {"goal": "plan"}
+    assert scheduler.next_task() is None
+
+
+def test_reordered_tasks_keep_their_payloads():
+    scheduler = ar.Scheduler("priority")
+    low = scheduler.submit("planner", priority=1, payload="low")
+    high = scheduler.submit("planner", priority=5, payload="high")
+
+    first, second = scheduler.next_task(), scheduler.next_task()
+    assert (first.id, first.payload) == (high, "high")
+    assert (second.id, second.payload) == (low, "low")
+
+
+def test_completed_tasks_resolve_their_waiters():
+    scheduler = ar.Scheduler("fcfs")
+    task_id = scheduler.submit("planner", payload="plan")
+
+    async def main():
+        waiter = asyncio.ensure_future(scheduler.wait(task_id))
+        task = scheduler.next_task()
+        scheduler.complete(task.id, result={"steps": 3})
+        return await waiter
+
+    assert asyncio.run(main()) == {"steps": 3}
+
+    with pytest.raises(ValueError):
+        scheduler.complete(task_id)
+
+
+def test_failed_tasks_raise_in_their_waiters():
+    scheduler = ar.Scheduler("fcfs")
+    task_id = scheduler.submit("planner")
+    scheduler.complete(scheduler.next_task().id, error="planner crashed")
+
+    async def main():
+        return await scheduler.wait(task_id)
+
+    with pytest.raises(RuntimeError, match="planner crashed"):
+        asyncio.run(main())
+
+
+def test_supervisor_restarts_until_success():
+    calls = []
+
+    def flaky():
+        calls.append(1)
+        if len(calls) < 3:
+            raise ValueError("flaky")
+
+    supervisor = ar.Supervisor("main", max_retries=3, backoff_seconds=0)
+    assert supervisor.run(flaky) == 3
+
+    with pytest.raises(RuntimeError):
+        ar.Supervisor("main", max_retries=0, backoff_seconds=0).run(lambda: 1 / 0)
+
+
+def test_supervisor_awaits_coroutines():
+    calls = []
+
+    async def flaky():
+        calls.append(1)
+        await asyncio.sleep(0)
+        if len(calls) < 2:
+            raise ValueError("flaky")
+
+    async def main():
+        supervisor = ar.Supervisor("main", max_retries=3, backoff_seconds=0)
+        return await supervisor.run_async(flaky)
+
+    assert asyncio.run(main()) == 2
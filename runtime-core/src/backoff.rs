@@ -0,0 +1,196 @@
+use core::fmt;
+use core::time::Duration;
+
+/// Curve of delays between retries
+///
+/// Implement this to plug custom curves into restart policies.
+pub trait BackoffStrategy: fmt::Debug + Send + Sync {
+    /// Get the delay before the given retry (1 for the first retry)
+    fn delay(&self, retry: u32) -> Duration;
+}
+
+/// Randomization applied to exponential delays
+///
+/// Jitter spreads out retries from many clients failing at once. The modes
+/// follow the AWS architecture blog's "Exponential Backoff and Jitter".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Jitter {
+    /// Use the exponential delay as is
+    #[default]
+    None,
+    /// Pick uniformly between zero and the exponential delay
+    Full,
+    /// Keep half the exponential delay and randomize the other half
+    Equal,
+    /// Pick between the initial delay and three times the previous delay
+    Decorrelated,
+}
+
+/// Exponential backoff
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    initial_delay: Duration,
+    current_delay: Duration,
+    previous_delay: Duration,
+    max_delay: Duration,
+    multiplier: f64,
+    jitter: Jitter,
+    max_total: Option<Duration>,
+    total: Duration,
+    rng: u64,
+    retries: u32,
+}
+
+impl ExponentialBackoff {
+    /// Create a new exponential backoff
+    pub fn new(initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            current_delay: initial_delay,
+            previous_delay: initial_delay,
+            max_delay,
+            multiplier: 2.0,
+            jitter: Jitter::None,
+            max_total: None,
+            total: Duration::ZERO,
+            rng: 0x9E37_79B9_7F4A_7C15,
+            retries: 0,
+        }
+    }
+
+    /// Set factor each delay grows by
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Randomize delays
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Seed the jitter; give each client its own seed so retries spread out
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        self.rng = seed.max(1);
+        self
+    }
+
+    /// Limit the sum of all delays; see [`ExponentialBackoff::try_next_delay`]
+    pub fn with_max_total(mut self, budget: Duration) -> Self {
+        self.max_total = Some(budget);
+        self
+    }
+
+    /// Get next delay
+    pub fn next_delay(&mut self) -> Duration {
+        let base = self.current_delay;
+        self.current_delay = Duration::from_secs_f64(
+            (self.current_delay.as_secs_f64() * self.multiplier).min(self.max_delay.as_secs_f64()),
+        );
+        let delay = match self.jitter {
+            Jitter::None => base,
+            Jitter::Full => base.mul_f64(self.random()),
+            Jitter::Equal => base / 2 + (base / 2).mul_f64(self.random()),
+            Jitter::Decorrelated => {
+                let low = self.initial_delay.as_secs_f64();
+                let high = (self.previous_delay.as_secs_f64() * 3.0).max(low);
+                let delay = low + (high - low) * self.random();
+                Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+            }
+        };
+        self.previous_delay = delay;
+        self.total = self.total.saturating_add(delay);
+        self.retries += 1;
+        delay
+    }
+
+    /// Get next delay, or `None` once it would exceed the total delay budget
+    ///
+    /// A refused delay is not counted, so the backoff stays exhausted.
+    pub fn try_next_delay(&mut self) -> Option<Duration> {
+        let before = self.clone();
+        let delay = self.next_delay();
+        if self.max_total.is_some_and(|budget| self.total > budget) {
+            *self = before;
+            return None;
+        }
+        Some(delay)
+    }
+
+    /// Reset backoff
+    pub fn reset(&mut self) {
+        self.current_delay = self.initial_delay;
+        self.previous_delay = self.initial_delay;
+        self.total = Duration::ZERO;
+        self.retries = 0;
+    }
+
+    /// Get retry count
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Get sum of delays handed out since the last reset
+    pub fn total_delay(&self) -> Duration {
+        self.total
+    }
+
+    /// Uniform random number in `[0, 1)`
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(60))
+    }
+}
+
+impl BackoffStrategy for ExponentialBackoff {
+    /// Delay of the exponential curve, without jitter
+    fn delay(&self, retry: u32) -> Duration {
+        let max = self.max_delay.as_secs_f64();
+        let mut seconds = self.initial_delay.as_secs_f64();
+        // Growth past the cap needs no more steps
+        for _ in 1..retry.min(1024) {
+            if seconds >= max {
+                break;
+            }
+            seconds *= self.multiplier;
+        }
+        Duration::from_secs_f64(seconds.min(max))
+    }
+}
+
+/// Delays following the Fibonacci sequence, growing slower than doubling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FibonacciBackoff {
+    unit: Duration,
+    max_delay: Duration,
+}
+
+impl FibonacciBackoff {
+    /// Create a backoff of 1, 1, 2, 3, 5, … units, capped at `max_delay`
+    pub fn new(unit: Duration, max_delay: Duration) -> Self {
+        Self { unit, max_delay }
+    }
+}
+
+impl BackoffStrategy for FibonacciBackoff {
+    fn delay(&self, retry: u32) -> Duration {
+        let (mut previous, mut current) = (0u32, 1u32);
+        for _ in 1..retry {
+            (previous, current) = (current, previous.saturating_add(current));
+            if current == u32::MAX || self.unit.saturating_mul(current) >= self.max_delay {
+                break;
+            }
+        }
+        self.unit.saturating_mul(current).min(self.max_delay)
+    }
+}
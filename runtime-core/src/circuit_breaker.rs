@@ -0,0 +1,395 @@
+use crate::{Clock, ErrorClass};
+use alloc::boxed::Box;
+use core::fmt;
+use core::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitState {
+    /// Closed - normal operation
+    Closed,
+    /// Open - failing, rejecting requests
+    Open,
+    /// Half-Open - testing if recovered
+    HalfOpen,
+}
+
+/// Change of a circuit breaker's state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitTransition {
+    /// State before the change
+    pub from: CircuitState,
+    /// State after the change
+    pub to: CircuitState,
+    /// Consecutive failures at the time of the change
+    pub failure_count: u32,
+}
+
+/// Counts of circuit breaker transitions and rejections, for metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitStats {
+    /// Times the circuit opened
+    pub opened: u64,
+    /// Times the circuit went half-open
+    pub half_opened: u64,
+    /// Times the circuit closed after being open or half-open
+    pub closed: u64,
+    /// Requests rejected while open or out of half-open probes
+    pub rejected: u64,
+}
+
+/// Serializable state and configuration of a [`CircuitBreaker`]
+///
+/// Transition hooks are not captured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitSnapshot {
+    /// State at the time of capture
+    pub state: CircuitState,
+    /// Consecutive failures
+    pub failure_count: u32,
+    /// Cooldown left before an open circuit goes half-open
+    pub cooldown_remaining: Option<Duration>,
+    /// Failures that open the circuit
+    pub failure_threshold: u32,
+    /// Time the circuit stays open after a failure
+    pub timeout: Duration,
+    /// Successful probes that close a half-open circuit
+    pub success_threshold: u32,
+    /// Concurrent trial calls allowed while half-open
+    pub half_open_max_calls: Option<u32>,
+    /// Lowest priority admitted while half-open
+    pub half_open_min_priority: u32,
+    /// Trial calls admitted each time the circuit goes half-open
+    pub half_open_probes: Option<u32>,
+    /// Transition and rejection counts
+    pub stats: CircuitStats,
+}
+
+/// Callback invoked on a state transition
+type TransitionHook = Box<dyn FnMut(&CircuitTransition) + Send>;
+
+/// Circuit breaker for fault tolerance, timed by an injected clock
+pub struct CircuitBreaker<C> {
+    clock: C,
+    state: CircuitState,
+    failure_count: u32,
+    failure_threshold: u32,
+    timeout: Duration,
+    cooldown: Duration,
+    last_failure: Option<Duration>,
+    half_open_max_calls: Option<u32>,
+    half_open_in_flight: u32,
+    half_open_min_priority: u32,
+    half_open_probes: Option<u32>,
+    half_open_admitted: u32,
+    half_open_successes: u32,
+    success_threshold: u32,
+    stats: CircuitStats,
+    on_open: Option<TransitionHook>,
+    on_half_open: Option<TransitionHook>,
+    on_close: Option<TransitionHook>,
+}
+
+impl<C> fmt::Debug for CircuitBreaker<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("state", &self.state)
+            .field("failure_count", &self.failure_count)
+            .field("failure_threshold", &self.failure_threshold)
+            .field("success_threshold", &self.success_threshold)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: Clock + Default> CircuitBreaker<C> {
+    /// Create a new circuit breaker
+    pub fn new(failure_threshold: u32, timeout: Duration) -> Self {
+        Self::with_clock(failure_threshold, timeout, C::default())
+    }
+
+    /// Rebuild a circuit breaker captured by [`CircuitBreaker::snapshot`]
+    pub fn from_snapshot(snapshot: &CircuitSnapshot) -> Self {
+        Self::from_snapshot_with_clock(snapshot, C::default())
+    }
+}
+
+impl<C: Clock> CircuitBreaker<C> {
+    /// Create a new circuit breaker reading time from the given clock
+    pub fn with_clock(failure_threshold: u32, timeout: Duration, clock: C) -> Self {
+        Self {
+            clock,
+            state: CircuitState::Closed,
+            failure_count: 0,
+            failure_threshold,
+            timeout,
+            cooldown: timeout,
+            last_failure: None,
+            half_open_max_calls: None,
+            half_open_in_flight: 0,
+            half_open_min_priority: 0,
+            half_open_probes: None,
+            half_open_admitted: 0,
+            half_open_successes: 0,
+            success_threshold: 1,
+            stats: CircuitStats::default(),
+            on_open: None,
+            on_half_open: None,
+            on_close: None,
+        }
+    }
+
+    /// Rebuild a circuit breaker reading time from the given clock
+    ///
+    /// An open circuit keeps the cooldown it had left when captured.
+    pub fn from_snapshot_with_clock(snapshot: &CircuitSnapshot, clock: C) -> Self {
+        let mut breaker = Self::with_clock(snapshot.failure_threshold, snapshot.timeout, clock);
+        breaker.state = snapshot.state;
+        breaker.failure_count = snapshot.failure_count;
+        breaker.success_threshold = snapshot.success_threshold;
+        breaker.half_open_max_calls = snapshot.half_open_max_calls;
+        breaker.half_open_min_priority = snapshot.half_open_min_priority;
+        breaker.half_open_probes = snapshot.half_open_probes;
+        breaker.stats = snapshot.stats;
+        if let Some(remaining) = snapshot.cooldown_remaining {
+            breaker.last_failure = Some(breaker.clock.now());
+            breaker.cooldown = remaining;
+        }
+        breaker
+    }
+
+    /// Read time from another clock from now on
+    ///
+    /// An open circuit keeps the cooldown it had left on the old clock.
+    pub fn set_clock(&mut self, clock: C) {
+        let remaining = self.snapshot().cooldown_remaining;
+        self.clock = clock;
+        if let Some(remaining) = remaining {
+            self.last_failure = Some(self.clock.now());
+            self.cooldown = remaining;
+        }
+    }
+
+    /// Capture state and configuration
+    pub fn snapshot(&self) -> CircuitSnapshot {
+        let cooldown_remaining = match (self.state, self.last_failure) {
+            (CircuitState::Open, Some(last_failure)) => {
+                let elapsed = self.clock.now().saturating_sub(last_failure);
+                Some(self.cooldown.saturating_sub(elapsed))
+            }
+            _ => None,
+        };
+        CircuitSnapshot {
+            state: self.state,
+            failure_count: self.failure_count,
+            cooldown_remaining,
+            failure_threshold: self.failure_threshold,
+            timeout: self.timeout,
+            success_threshold: self.success_threshold,
+            half_open_max_calls: self.half_open_max_calls,
+            half_open_min_priority: self.half_open_min_priority,
+            half_open_probes: self.half_open_probes,
+            stats: self.stats,
+        }
+    }
+
+    /// Limit concurrent trial calls while half-open
+    pub fn with_half_open_max_calls(mut self, calls: u32) -> Self {
+        self.half_open_max_calls = Some(calls.max(1));
+        self
+    }
+
+    /// Shed calls below this priority while half-open
+    ///
+    /// Scarce trial capacity during recovery then goes to interactive work.
+    pub fn with_half_open_min_priority(mut self, priority: u32) -> Self {
+        self.half_open_min_priority = priority;
+        self
+    }
+
+    /// Admit at most `probes` trial calls each time the circuit goes half-open
+    ///
+    /// Once they are used up, further calls are rejected until the probes
+    /// succeed or one fails.
+    pub fn with_half_open_probes(mut self, probes: u32) -> Self {
+        self.half_open_probes = Some(probes.max(1));
+        self
+    }
+
+    /// Require `successes` successful probes before a half-open circuit closes
+    ///
+    /// The threshold is capped at the probe count, if one is set.
+    pub fn with_success_threshold(mut self, successes: u32) -> Self {
+        self.success_threshold = successes.max(1);
+        self
+    }
+
+    /// Call `hook` whenever the circuit opens
+    pub fn on_open(mut self, hook: impl FnMut(&CircuitTransition) + Send + 'static) -> Self {
+        self.on_open = Some(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` whenever the circuit goes half-open
+    pub fn on_half_open(mut self, hook: impl FnMut(&CircuitTransition) + Send + 'static) -> Self {
+        self.on_half_open = Some(Box::new(hook));
+        self
+    }
+
+    /// Call `hook` whenever the circuit closes after being open or half-open
+    pub fn on_close(mut self, hook: impl FnMut(&CircuitTransition) + Send + 'static) -> Self {
+        self.on_close = Some(Box::new(hook));
+        self
+    }
+
+    /// Move to `to`, updating stats and calling the matching hook
+    fn transition(&mut self, to: CircuitState) {
+        let from = self.state;
+        if from == to {
+            return;
+        }
+        self.state = to;
+        let (count, hook) = match to {
+            CircuitState::Open => (&mut self.stats.opened, &mut self.on_open),
+            CircuitState::HalfOpen => {
+                self.half_open_in_flight = 0;
+                self.half_open_admitted = 0;
+                self.half_open_successes = 0;
+                (&mut self.stats.half_opened, &mut self.on_half_open)
+            }
+            CircuitState::Closed => (&mut self.stats.closed, &mut self.on_close),
+        };
+        *count += 1;
+        if let Some(hook) = hook {
+            hook(&CircuitTransition {
+                from,
+                to,
+                failure_count: self.failure_count,
+            });
+        }
+    }
+
+    /// Record success
+    ///
+    /// A half-open circuit closes once the success threshold is reached.
+    pub fn record_success(&mut self) {
+        if self.state == CircuitState::HalfOpen {
+            self.half_open_in_flight = self.half_open_in_flight.saturating_sub(1);
+            self.half_open_successes += 1;
+            let threshold = self
+                .half_open_probes
+                .map_or(self.success_threshold, |probes| {
+                    self.success_threshold.min(probes)
+                });
+            if self.half_open_successes < threshold {
+                return;
+            }
+        }
+        self.failure_count = 0;
+        self.last_failure = None;
+        self.transition(CircuitState::Closed);
+    }
+
+    /// Record failure
+    pub fn record_failure(&mut self) {
+        self.failure_count += 1;
+        self.last_failure = Some(self.clock.now());
+        self.cooldown = self.timeout;
+
+        if self.state == CircuitState::HalfOpen || self.failure_count >= self.failure_threshold {
+            self.transition(CircuitState::Open);
+        }
+    }
+
+    /// Open the circuit as if the failure threshold was just reached
+    pub fn trip(&mut self) {
+        self.failure_count = self.failure_count.max(self.failure_threshold);
+        self.last_failure = Some(self.clock.now());
+        self.cooldown = self.timeout;
+        self.transition(CircuitState::Open);
+    }
+
+    /// Record a classified failure
+    ///
+    /// Permanent errors are caller mistakes and do not count against the
+    /// downstream. Rate limits with a suggested delay open the circuit
+    /// immediately for at least that long.
+    pub fn record_error(&mut self, class: &ErrorClass) {
+        if !class.counts_as_failure() {
+            return;
+        }
+
+        self.record_failure();
+        if let Some(delay) = class.suggested_delay() {
+            self.transition(CircuitState::Open);
+            self.cooldown = delay.max(self.timeout);
+        }
+    }
+
+    /// Check if request is allowed
+    pub fn is_allowed(&mut self) -> bool {
+        self.is_allowed_for(u32::MAX)
+    }
+
+    /// Check if a request of the given priority is allowed
+    pub fn is_allowed_for(&mut self, priority: u32) -> bool {
+        if self.state == CircuitState::Open {
+            match self.last_failure {
+                Some(last_failure)
+                    if self.clock.now().saturating_sub(last_failure) >= self.cooldown =>
+                {
+                    self.transition(CircuitState::HalfOpen);
+                }
+                _ => {
+                    self.stats.rejected += 1;
+                    return false;
+                }
+            }
+        }
+
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                if priority < self.half_open_min_priority {
+                    return false;
+                }
+                if self
+                    .half_open_max_calls
+                    .is_some_and(|max| self.half_open_in_flight >= max)
+                    || self
+                        .half_open_probes
+                        .is_some_and(|probes| self.half_open_admitted >= probes)
+                {
+                    self.stats.rejected += 1;
+                    return false;
+                }
+                self.half_open_in_flight += 1;
+                self.half_open_admitted += 1;
+                true
+            }
+        }
+    }
+
+    /// Get state
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Get failure count
+    pub fn failure_count(&self) -> u32 {
+        self.failure_count
+    }
+
+    /// Get transition and rejection counts
+    pub fn stats(&self) -> CircuitStats {
+        self.stats
+    }
+}
+
+impl<C: Clock + Default> Default for CircuitBreaker<C> {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(60))
+    }
+}
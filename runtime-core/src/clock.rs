@@ -0,0 +1,50 @@
+use core::cell::Cell;
+use core::time::Duration;
+
+/// Monotonic time source
+pub trait Clock {
+    /// Get time elapsed since the clock's origin
+    fn now(&self) -> Duration;
+}
+
+/// Clock advanced by hand, for targets without a timer and for tests
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+impl ManualClock {
+    /// Create a new clock at its origin
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    /// Set the current time
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<C: Clock + ?Sized> Clock for alloc::sync::Arc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
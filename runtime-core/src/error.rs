@@ -0,0 +1,52 @@
+use core::time::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Classification of an error for restart and retry decisions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorClass {
+    /// Temporary failure, safe to retry
+    Transient,
+    /// Failure that will repeat on every attempt
+    Permanent,
+    /// Out of quota or other capacity
+    ResourceExhausted,
+    /// Exceeded a memory limit
+    OutOfMemory,
+    /// Throttled by a provider, optionally with a suggested delay
+    RateLimited {
+        /// Delay suggested by the provider
+        retry_after: Option<Duration>,
+    },
+    /// Deadline passed or can no longer be met
+    DeadlineExceeded,
+    /// Unrecoverable failure
+    Fatal,
+}
+
+impl ErrorClass {
+    /// Check if another attempt may succeed
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            ErrorClass::Permanent | ErrorClass::DeadlineExceeded | ErrorClass::Fatal
+        )
+    }
+
+    /// Get the provider-suggested delay, if any
+    pub fn suggested_delay(&self) -> Option<Duration> {
+        match self {
+            ErrorClass::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Adjust a base retry delay for this class
+    pub fn retry_delay(&self, base: Duration) -> Duration {
+        self.suggested_delay().map_or(base, |delay| delay.max(base))
+    }
+
+    /// Check if the error indicates an unhealthy downstream
+    pub fn counts_as_failure(&self) -> bool {
+        !matches!(self, ErrorClass::Permanent)
+    }
+}
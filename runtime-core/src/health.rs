@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// Health status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthStatus {
+    /// Healthy
+    Healthy,
+    /// Unhealthy
+    Unhealthy,
+    /// Unknown
+    Unknown,
+}
@@ -0,0 +1,29 @@
+//! `no_std` scheduling and fault-tolerance primitives for agents.
+//!
+//! Only `core` and `alloc` are used, and time is read through the [`Clock`]
+//! trait, so these types run unchanged on embedded and edge targets.
+//! `agentropic-runtime` layers its std engine on top of them.
+
+#![no_std]
+#![allow(missing_docs)]
+
+extern crate alloc;
+
+pub mod backoff;
+pub mod circuit_breaker;
+pub mod clock;
+pub mod error;
+pub mod health;
+pub mod policy;
+pub mod queue;
+
+// Re-exports
+pub use backoff::{BackoffStrategy, ExponentialBackoff, FibonacciBackoff, Jitter};
+pub use circuit_breaker::{
+    CircuitBreaker, CircuitSnapshot, CircuitState, CircuitStats, CircuitTransition,
+};
+pub use clock::{Clock, ManualClock};
+pub use error::ErrorClass;
+pub use health::HealthStatus;
+pub use policy::{PolicyType, SchedulingPolicy};
+pub use queue::{BoundedTaskQueue, OverflowPolicy, PushOutcome, TaskQueue};
@@ -0,0 +1,159 @@
+use alloc::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+
+/// FIFO task queue
+#[derive(Debug)]
+pub struct TaskQueue<T> {
+    tasks: VecDeque<T>,
+}
+
+impl<T> TaskQueue<T> {
+    /// Create a new task queue
+    pub fn new() -> Self {
+        Self {
+            tasks: VecDeque::new(),
+        }
+    }
+
+    /// Push a task
+    pub fn push(&mut self, task: T) {
+        self.tasks.push_back(task);
+    }
+
+    /// Pop a task
+    pub fn pop(&mut self) -> Option<T> {
+        self.tasks.pop_front()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Get queue length
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Remove the task at `index`
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        self.tasks.remove(index)
+    }
+
+    /// Iterate over queued tasks in order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tasks.iter()
+    }
+
+    /// Clear the queue
+    pub fn clear(&mut self) {
+        self.tasks.clear();
+    }
+}
+
+impl<T> Default for TaskQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a [`BoundedTaskQueue`] does with a task pushed while it is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OverflowPolicy {
+    /// Refuse the task so the producer can wait for room and try again
+    #[default]
+    Block,
+    /// Evict the oldest task to make room
+    DropOldest,
+    /// Discard the pushed task
+    DropNewest,
+    /// Refuse the task as an error
+    Reject,
+}
+
+/// Result of pushing onto a [`BoundedTaskQueue`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum PushOutcome<T> {
+    /// The task was queued
+    Queued,
+    /// The task was queued after evicting the oldest task
+    Evicted(T),
+    /// The queue was full and the task was discarded
+    Dropped(T),
+    /// The queue was full and the task was refused, under
+    /// [`OverflowPolicy::Block`] or [`OverflowPolicy::Reject`]
+    Full(T),
+}
+
+/// FIFO task queue holding at most a fixed number of tasks
+#[derive(Debug)]
+pub struct BoundedTaskQueue<T> {
+    tasks: TaskQueue<T>,
+    capacity: usize,
+    overflow: OverflowPolicy,
+}
+
+impl<T> BoundedTaskQueue<T> {
+    /// Create a queue holding at most `capacity` tasks
+    pub fn new(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            tasks: TaskQueue::new(),
+            capacity: capacity.max(1),
+            overflow,
+        }
+    }
+
+    /// Push a task, applying the overflow policy if the queue is full
+    pub fn push(&mut self, task: T) -> PushOutcome<T> {
+        if !self.is_full() {
+            self.tasks.push(task);
+            return PushOutcome::Queued;
+        }
+        match self.overflow {
+            OverflowPolicy::DropOldest => match self.tasks.pop() {
+                Some(oldest) => {
+                    self.tasks.push(task);
+                    PushOutcome::Evicted(oldest)
+                }
+                None => PushOutcome::Full(task),
+            },
+            OverflowPolicy::DropNewest => PushOutcome::Dropped(task),
+            OverflowPolicy::Block | OverflowPolicy::Reject => PushOutcome::Full(task),
+        }
+    }
+
+    /// Pop a task
+    pub fn pop(&mut self) -> Option<T> {
+        self.tasks.pop()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Check if full
+    pub fn is_full(&self) -> bool {
+        self.tasks.len() >= self.capacity
+    }
+
+    /// Get queue length
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Get capacity
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get overflow policy
+    pub fn overflow(&self) -> OverflowPolicy {
+        self.overflow
+    }
+
+    /// Iterate over queued tasks in order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.tasks.iter()
+    }
+}
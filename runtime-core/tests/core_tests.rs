@@ -0,0 +1,82 @@
+use agentropic_runtime_core::{
+    BoundedTaskQueue, CircuitBreaker, CircuitState, ExponentialBackoff, Jitter, ManualClock,
+    OverflowPolicy, PushOutcome, TaskQueue,
+};
+use std::time::Duration;
+
+#[test]
+fn breaker_with_manual_clock() {
+    let clock = ManualClock::new();
+    let mut breaker = CircuitBreaker::with_clock(1, Duration::from_secs(10), &clock);
+
+    breaker.record_failure();
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert!(!breaker.is_allowed());
+
+    clock.advance(Duration::from_secs(10));
+    assert!(breaker.is_allowed());
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+}
+
+#[test]
+fn generic_task_queue() {
+    let mut queue = TaskQueue::new();
+    queue.push(1u32);
+    queue.push(2u32);
+
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.len(), 1);
+}
+
+#[test]
+fn bounded_queue_overflow_policies() {
+    let mut queue = BoundedTaskQueue::new(2, OverflowPolicy::DropOldest);
+    assert_eq!(queue.push(1u32), PushOutcome::Queued);
+    assert_eq!(queue.push(2), PushOutcome::Queued);
+    assert_eq!(queue.push(3), PushOutcome::Evicted(1));
+    assert_eq!(queue.iter().copied().collect::<Vec<_>>(), [2, 3]);
+
+    let mut queue = BoundedTaskQueue::new(1, OverflowPolicy::DropNewest);
+    queue.push(1u32);
+    assert_eq!(queue.push(2), PushOutcome::Dropped(2));
+
+    let mut queue = BoundedTaskQueue::new(1, OverflowPolicy::Reject);
+    queue.push(1u32);
+    assert_eq!(queue.push(2), PushOutcome::Full(2));
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(2), PushOutcome::Queued);
+}
+
+#[test]
+fn jittered_backoff_stays_in_bounds() {
+    let initial = Duration::from_millis(100);
+    let max = Duration::from_secs(5);
+    for jitter in [Jitter::Full, Jitter::Equal, Jitter::Decorrelated] {
+        let mut backoff = ExponentialBackoff::new(initial, max)
+            .with_jitter(jitter)
+            .with_seed(42);
+        let mut ceiling = initial;
+        let mut previous = initial;
+        for _ in 0..20 {
+            let delay = backoff.next_delay();
+            match jitter {
+                Jitter::Full => assert!(delay <= ceiling),
+                Jitter::Equal => assert!(delay >= ceiling / 2 && delay <= ceiling),
+                _ => assert!(delay >= initial && delay <= (previous * 3).min(max)),
+            }
+            previous = delay;
+            ceiling = (ceiling * 2).min(max);
+        }
+    }
+}
+
+#[test]
+fn backoff_total_budget() {
+    let mut backoff = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(60))
+        .with_max_total(Duration::from_secs(10));
+
+    let delays: Vec<_> = core::iter::from_fn(|| backoff.try_next_delay()).collect();
+    assert_eq!(delays.len(), 3);
+    assert_eq!(backoff.total_delay(), Duration::from_secs(7));
+    assert_eq!(backoff.try_next_delay(), None);
+}
@@ -0,0 +1,166 @@
+//! Admin HTTP endpoint of a running runtime
+//!
+//! Started by [`Runtime::start`](crate::Runtime::start) when the runtime was
+//! built with [`RuntimeBuilder::with_admin`](crate::RuntimeBuilder::with_admin),
+//! and stopped by its shutdown. It answers plain HTTP/1.1 `GET` requests:
+//!
+//! | Path       | Response                                                    |
+//! |------------|-------------------------------------------------------------|
+//! | `/health`  | `ok` while the runtime runs, 503 otherwise                  |
+//! | `/agents`  | the [`Registry`] listing as JSON                            |
+//! | `/metrics` | scheduler and supervisor metrics, Prometheus text or JSON   |
+//!
+//! Metrics are in the Prometheus text format with the `prometheus` feature
+//! and JSON otherwise. The endpoint has no authentication, so bind it to a
+//! private interface.
+
+use crate::metrics::{MetricsExporter, MetricsRegistry};
+#[cfg(feature = "scheduler")]
+use crate::scheduler::Scheduler;
+use crate::supervisor::Supervisor;
+use crate::{Registry, RegistryFilter};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
+
+/// Largest request head read before the request is refused
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// Subsystems the admin endpoint reports on
+#[derive(Clone)]
+pub(crate) struct Admin {
+    pub(crate) registry: Registry,
+    pub(crate) running: Arc<RwLock<bool>>,
+    #[cfg(feature = "scheduler")]
+    pub(crate) scheduler: Option<Arc<Mutex<Scheduler>>>,
+    pub(crate) supervisor: Option<Arc<Mutex<Supervisor>>>,
+}
+
+impl Admin {
+    /// Answer requests on `listener` until `shutdown` is cancelled
+    pub(crate) async fn serve(self, listener: TcpListener, shutdown: CancellationToken) {
+        loop {
+            let stream = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(error) => {
+                        tracing::warn!("Admin endpoint failed to accept a connection: {}", error);
+                        continue;
+                    }
+                },
+            };
+            let admin = self.clone();
+            tokio::spawn(async move {
+                if let Err(error) = admin.answer(stream).await {
+                    tracing::debug!("Admin request failed: {}", error);
+                }
+            });
+        }
+    }
+
+    async fn answer(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut head = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+            if head.len() > MAX_REQUEST {
+                return respond(&mut stream, 431, "text/plain", "request too large").await;
+            }
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            head.extend_from_slice(&chunk[..read]);
+        }
+        let head = String::from_utf8_lossy(&head);
+        let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+        let (method, path) = (request_line.next(), request_line.next());
+        if method != Some("GET") {
+            return respond(&mut stream, 405, "text/plain", "method not allowed").await;
+        }
+        match path.map(|path| path.split('?').next().unwrap_or(path)) {
+            Some("/health") => match *self.running.read().await {
+                true => respond(&mut stream, 200, "text/plain", "ok").await,
+                false => respond(&mut stream, 503, "text/plain", "stopped").await,
+            },
+            Some("/agents") => {
+                let agents = self.agents().await.to_string();
+                respond(&mut stream, 200, "application/json", &agents).await
+            }
+            Some("/metrics") => {
+                let (content_type, body) = self.metrics().await;
+                respond(&mut stream, 200, content_type, &body).await
+            }
+            _ => respond(&mut stream, 404, "text/plain", "not found").await,
+        }
+    }
+
+    async fn agents(&self) -> serde_json::Value {
+        let agents = self.registry.list(&RegistryFilter::new()).await;
+        agents
+            .iter()
+            .map(|info| {
+                #[cfg_attr(not(feature = "scheduler"), allow(unused_mut))]
+                let mut agent = json!({
+                    "id": info.id.to_string(),
+                    "name": info.name,
+                    "tags": info.tags,
+                    "state": info.state,
+                    "restarts": info.restarts,
+                });
+                #[cfg(feature = "scheduler")]
+                {
+                    agent["current_task"] = json!(info.current_task.map(|id| id.as_u64()));
+                }
+                agent
+            })
+            .collect()
+    }
+
+    async fn metrics(&self) -> (&'static str, String) {
+        let mut registry = MetricsRegistry::new();
+        crate::runtime::collect_metrics(
+            #[cfg(feature = "scheduler")]
+            self.scheduler.as_ref(),
+            self.supervisor.as_ref(),
+            &mut registry,
+        )
+        .await;
+        let exporter = MetricsExporter::new(registry);
+        #[cfg(feature = "prometheus")]
+        return ("text/plain; version=0.0.4", exporter.export_prometheus());
+        #[cfg(not(feature = "prometheus"))]
+        (
+            "application/json",
+            exporter.export_json().unwrap_or_else(|_| "[]".to_string()),
+        )
+    }
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Service Unavailable",
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,153 @@
+//! Blocking facade for non-async applications
+//!
+//! Each wrapper owns a single-threaded tokio runtime and blocks the calling
+//! thread on it. Do not use these types from inside an async context.
+
+#[cfg(feature = "scheduler")]
+use crate::scheduler::{CpuBudget, Scheduler, Task, TaskFailure, TaskHandle};
+#[cfg(feature = "supervisor")]
+use crate::supervisor::{ChildExit, ChildSpec, Supervisor};
+use crate::RuntimeError;
+#[cfg(feature = "supervisor")]
+use agentropic_core::AgentId;
+use futures::Future;
+use tokio::runtime::{Builder, Runtime};
+
+fn build_runtime() -> Result<Runtime, RuntimeError> {
+    Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| RuntimeError::Other(format!("failed to start executor: {}", e)))
+}
+
+/// Blocking wrapper around [`Scheduler`]
+#[cfg(feature = "scheduler")]
+pub struct BlockingScheduler {
+    scheduler: Scheduler,
+    runtime: Runtime,
+}
+
+#[cfg(feature = "scheduler")]
+impl BlockingScheduler {
+    /// Wrap a scheduler
+    pub fn new(scheduler: Scheduler) -> Result<Self, RuntimeError> {
+        Ok(Self {
+            scheduler,
+            runtime: build_runtime()?,
+        })
+    }
+
+    /// Submit a task, enforcing the scheduler's payload limit
+    pub fn submit(&mut self, task: Task) -> Result<TaskHandle, RuntimeError> {
+        self.scheduler.submit(task)
+    }
+
+    /// Run the next dispatchable task, blocking until it completes
+    ///
+    /// CPU time used is recorded against the agent's runtime cap and
+    /// execution totals, and the outcome against any deployment involving
+    /// the agent.
+    pub fn run_next<F, Fut>(&mut self, run: F) -> Option<(Task, Result<(), RuntimeError>)>
+    where
+        F: FnOnce(&Task) -> Fut,
+        Fut: Future<Output = Result<(), RuntimeError>>,
+    {
+        let mut task = self.scheduler.next_task()?;
+        task.record_attempt();
+        let budget = task.cpu_budget().unwrap_or_else(CpuBudget::unlimited);
+        let mut execution = budget.enforce(run(&task));
+        let result = self.runtime.block_on(&mut execution);
+        let used = execution.used();
+        self.scheduler.record_runtime(*task.agent_id(), used);
+        self.scheduler.record_execution(&task, used, result.is_ok());
+        self.scheduler
+            .record_outcome(task.agent_id(), result.is_ok());
+        let stored = match &result {
+            Ok(()) => Ok(Vec::new()),
+            Err(error) => Err(TaskFailure::from(error)),
+        };
+        self.scheduler.store_result(task.id(), stored);
+        Some((task, result))
+    }
+
+    /// Run dispatchable tasks until none are left, blocking until all complete
+    pub fn run_until_idle<F, Fut>(&mut self, mut run: F) -> Vec<(Task, Result<(), RuntimeError>)>
+    where
+        F: FnMut(&Task) -> Fut,
+        Fut: Future<Output = Result<(), RuntimeError>>,
+    {
+        let mut results = Vec::new();
+        while let Some(result) = self.run_next(&mut run) {
+            results.push(result);
+        }
+        results
+    }
+
+    /// Block on any future using the internal executor
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Get the wrapped scheduler
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    /// Get the wrapped scheduler mutably
+    pub fn scheduler_mut(&mut self) -> &mut Scheduler {
+        &mut self.scheduler
+    }
+
+    /// Unwrap the scheduler
+    pub fn into_inner(self) -> Scheduler {
+        self.scheduler
+    }
+}
+
+/// Blocking wrapper around [`Supervisor`]
+#[cfg(feature = "supervisor")]
+pub struct BlockingSupervisor {
+    supervisor: Supervisor,
+    runtime: Runtime,
+}
+
+#[cfg(feature = "supervisor")]
+impl BlockingSupervisor {
+    /// Wrap a supervisor
+    pub fn new(supervisor: Supervisor) -> Result<Self, RuntimeError> {
+        Ok(Self {
+            supervisor,
+            runtime: build_runtime()?,
+        })
+    }
+
+    /// Add a child with a start function to supervision
+    pub fn add_child(&mut self, spec: ChildSpec) {
+        self.supervisor.add_child(spec);
+    }
+
+    /// Run a child until its restart policy stops restarting it, blocking until then
+    pub fn run_child(&mut self, agent_id: &AgentId) -> Result<ChildExit, RuntimeError> {
+        self.runtime.block_on(self.supervisor.run_child(agent_id))
+    }
+
+    /// Block on any future using the internal executor
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Get the wrapped supervisor
+    pub fn supervisor(&self) -> &Supervisor {
+        &self.supervisor
+    }
+
+    /// Get the wrapped supervisor mutably
+    pub fn supervisor_mut(&mut self) -> &mut Supervisor {
+        &mut self.supervisor
+    }
+
+    /// Unwrap the supervisor
+    pub fn into_inner(self) -> Supervisor {
+        self.supervisor
+    }
+}
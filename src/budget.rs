@@ -0,0 +1,32 @@
+//! Token and cost spend
+//!
+//! [`BudgetUsage`] is what an agent spends on calls such as to a language
+//! model. The scheduler counts it against the agent's `CostBudget`, and the
+//! supervisor reports an exhausted budget as a
+//! `SupervisorEvent::BudgetExceeded`.
+
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// Tokens and cost spent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    /// Tokens consumed
+    pub tokens: u64,
+    /// Cost in caller-defined units, such as micro-dollars
+    pub cost: u64,
+}
+
+impl BudgetUsage {
+    /// Create usage of `tokens` costing `cost`
+    pub fn new(tokens: u64, cost: u64) -> Self {
+        Self { tokens, cost }
+    }
+}
+
+impl AddAssign for BudgetUsage {
+    fn add_assign(&mut self, other: BudgetUsage) {
+        self.tokens = self.tokens.saturating_add(other.tokens);
+        self.cost = self.cost.saturating_add(other.cost);
+    }
+}
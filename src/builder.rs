@@ -0,0 +1,146 @@
+//! One-stop wiring of the runtime and its subsystems
+
+use crate::lifecycle::AgentLifecycle;
+#[cfg(feature = "scheduler")]
+use crate::scheduler::{Scheduler, SchedulingPolicy, Task};
+#[cfg(feature = "supervisor")]
+use crate::supervisor::{ChildSpec, Supervisor};
+use crate::timer::SharedClock;
+#[cfg(feature = "scheduler")]
+use crate::RuntimeError;
+use crate::{Runtime, RuntimeConfig};
+use agentropic_core::AgentId;
+#[cfg(feature = "scheduler")]
+use futures::future::{BoxFuture, Future, FutureExt};
+use std::sync::Arc;
+
+/// Task handler the scheduler loop started by [`Runtime::start`] dispatches to
+#[cfg(feature = "scheduler")]
+pub type TaskExecutor =
+    Arc<dyn Fn(&Task) -> BoxFuture<'static, Result<Vec<u8>, RuntimeError>> + Send + Sync>;
+
+/// Builder composing a [`Runtime`] with its scheduler, supervisor, and storage
+///
+/// The built runtime owns every subsystem, so [`Runtime::start`] and
+/// [`Runtime::shutdown`] manage them together.
+#[derive(Default)]
+pub struct RuntimeBuilder {
+    pub(crate) config: RuntimeConfig,
+    pub(crate) agents: Vec<(AgentId, String)>,
+    pub(crate) lifecycle: Option<Arc<AgentLifecycle>>,
+    pub(crate) clock: Option<SharedClock>,
+    #[cfg(feature = "scheduler")]
+    pub(crate) scheduler: Option<Scheduler>,
+    #[cfg(feature = "scheduler")]
+    pub(crate) executor: Option<TaskExecutor>,
+    #[cfg(feature = "supervisor")]
+    pub(crate) supervisor: Option<Supervisor>,
+    #[cfg(feature = "persistence")]
+    pub(crate) state_path: Option<std::path::PathBuf>,
+    #[cfg(feature = "admin")]
+    pub(crate) admin: Option<std::net::SocketAddr>,
+}
+
+impl RuntimeBuilder {
+    /// Create a builder with default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set runtime configuration
+    pub fn with_config(mut self, config: RuntimeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Register an agent when the runtime starts
+    pub fn with_agent(mut self, agent_id: AgentId, name: impl Into<String>) -> Self {
+        self.agents.push((agent_id, name.into()));
+        self
+    }
+
+    /// Use a scheduler; its tuning is taken from the configuration
+    #[cfg(feature = "scheduler")]
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Use a new scheduler with the given policy
+    #[cfg(feature = "scheduler")]
+    pub fn with_policy(self, policy: SchedulingPolicy) -> Self {
+        self.with_scheduler(Scheduler::new(policy))
+    }
+
+    /// Run the scheduler's tasks with `execute` between start and shutdown
+    ///
+    /// Without an executor the scheduler is not driven by the runtime, and
+    /// the caller runs [`Scheduler::run`] itself.
+    #[cfg(feature = "scheduler")]
+    pub fn with_executor<F, Fut>(mut self, execute: F) -> Self
+    where
+        F: Fn(&Task) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<u8>, RuntimeError>> + Send + 'static,
+    {
+        self.executor = Some(Arc::new(move |task: &Task| execute(task).boxed()));
+        self
+    }
+
+    /// Use a supervisor
+    #[cfg(feature = "supervisor")]
+    pub fn with_supervisor(mut self, supervisor: Supervisor) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Supervise a child, creating a supervisor named "runtime" if none was set
+    ///
+    /// The child's agent is registered when the runtime starts.
+    #[cfg(feature = "supervisor")]
+    pub fn with_child(mut self, spec: ChildSpec) -> Self {
+        self.agents
+            .push((*spec.agent_id(), spec.agent_id().to_string()));
+        self.supervisor
+            .get_or_insert_with(|| Supervisor::new("runtime"))
+            .add_child(spec);
+        self
+    }
+
+    /// Share an agent lifecycle between the scheduler and supervisor
+    pub fn with_lifecycle(mut self, lifecycle: AgentLifecycle) -> Self {
+        self.lifecycle = Some(Arc::new(lifecycle));
+        self
+    }
+
+    /// Drive the scheduler's and supervisor's time from a clock
+    ///
+    /// Replaces any clock set on them directly.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Restore scheduler state from `path` on start and save it there on shutdown
+    ///
+    /// A supervisor snapshot is kept next to it, in `<stem>.supervisor.json`.
+    #[cfg(feature = "persistence")]
+    pub fn with_state_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.state_path = Some(path.into());
+        self
+    }
+
+    /// Serve the admin endpoint at `address` between start and shutdown
+    ///
+    /// See [`crate::admin`]; bind a port of 0 and read the chosen one from
+    /// [`Runtime::admin_address`].
+    #[cfg(feature = "admin")]
+    pub fn with_admin(mut self, address: std::net::SocketAddr) -> Self {
+        self.admin = Some(address);
+        self
+    }
+
+    /// Build the runtime
+    pub fn build(self) -> Runtime {
+        Runtime::from_builder(self)
+    }
+}
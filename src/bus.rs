@@ -0,0 +1,405 @@
+//! Topic-based publish/subscribe between agents
+//!
+//! Agents publish serde payloads to named topics on an [`EventBus`]. Each
+//! subscriber gets the [`Event`] as a [`Message`] in its mailbox, so a
+//! scheduler built with `Scheduler::with_mailboxes` runs a task for it like
+//! for any other mail; the task turns the message back into an event with
+//! [`Event::from_message`].
+//!
+//! Every topic has a [`TopicConfig`]. Its retention keeps the latest events
+//! for subscribers that join later. Its [`DeliveryMode`] chooses between
+//! fire-and-forget delivery, where an event that does not fit a mailbox is
+//! dropped, and delivery that keeps each event until the subscriber
+//! [acknowledges](EventBus::ack) it, resending it on
+//! [`EventBus::redeliver`].
+
+use crate::mailbox::{Mailboxes, Message};
+use crate::{QueueError, RuntimeError};
+use agentropic_core::AgentId;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// How a topic's events reach subscribers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryMode {
+    /// Deliver once; events that do not fit a mailbox are dropped
+    #[default]
+    AtMostOnce,
+    /// Keep events until acknowledged, resending them on redelivery
+    AtLeastOnce,
+}
+
+/// Retention and delivery of a topic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopicConfig {
+    retention: usize,
+    delivery: DeliveryMode,
+}
+
+impl TopicConfig {
+    /// Create a config retaining no events, delivered at most once
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the latest `retention` events for late subscribers
+    pub fn with_retention(mut self, retention: usize) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Set delivery mode
+    pub fn with_delivery(mut self, delivery: DeliveryMode) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// Get number of events retained
+    pub fn retention(&self) -> usize {
+        self.retention
+    }
+
+    /// Get delivery mode
+    pub fn delivery(&self) -> DeliveryMode {
+        self.delivery
+    }
+}
+
+/// Event published to a topic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    topic: String,
+    sequence: u64,
+    publisher: Option<AgentId>,
+    payload: serde_json::Value,
+}
+
+impl Event {
+    /// Read the event carried by a mailbox message
+    pub fn from_message(message: &Message) -> Result<Self, RuntimeError> {
+        serde_json::from_slice(message.payload())
+            .map_err(|e| QueueError::InvalidEvent(e.to_string()).into())
+    }
+
+    /// Get topic
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Get sequence number, increasing by one per event of the topic
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Get publishing agent
+    pub fn publisher(&self) -> Option<&AgentId> {
+        self.publisher.as_ref()
+    }
+
+    /// Decode the payload
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, RuntimeError> {
+        T::deserialize(&self.payload).map_err(|e| QueueError::InvalidEvent(e.to_string()).into())
+    }
+
+    fn to_message(&self, to: AgentId) -> Message {
+        // Encoding a JSON value cannot fail
+        let payload = serde_json::to_vec(self).unwrap_or_default();
+        let message = Message::new(to, payload);
+        match self.publisher {
+            Some(publisher) => message.with_sender(publisher),
+            None => message,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Topic {
+    config: TopicConfig,
+    next_sequence: u64,
+    retained: VecDeque<Event>,
+    subscribers: Vec<AgentId>,
+    /// Events each subscriber has not acknowledged, by sequence number
+    unacked: HashMap<AgentId, BTreeMap<u64, Event>>,
+    dropped: u64,
+}
+
+impl Topic {
+    fn new(config: TopicConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    fn retain(&mut self, event: &Event) {
+        if self.config.retention == 0 {
+            return;
+        }
+        if self.retained.len() >= self.config.retention {
+            self.retained.pop_front();
+        }
+        self.retained.push_back(event.clone());
+    }
+
+    /// Record events a subscriber must acknowledge, if delivered at least once
+    fn track(&mut self, subscriber: AgentId, events: &[Event]) {
+        if self.config.delivery == DeliveryMode::AtLeastOnce {
+            let unacked = self.unacked.entry(subscriber).or_default();
+            for event in events {
+                unacked.insert(event.sequence, event.clone());
+            }
+        }
+    }
+}
+
+/// Topic-based publish/subscribe over agent mailboxes
+pub struct EventBus {
+    mailboxes: Arc<Mailboxes>,
+    default_config: TopicConfig,
+    topics: Mutex<HashMap<String, Topic>>,
+}
+
+impl EventBus {
+    /// Create a bus delivering to `mailboxes`
+    pub fn new(mailboxes: Arc<Mailboxes>) -> Self {
+        Self {
+            mailboxes,
+            default_config: TopicConfig::default(),
+            topics: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set config of topics not configured through [`EventBus::configure`]
+    pub fn with_default_config(mut self, config: TopicConfig) -> Self {
+        self.default_config = config;
+        self
+    }
+
+    /// Set a topic's retention and delivery
+    ///
+    /// Retained events beyond the new retention are dropped.
+    pub fn configure(&self, topic: impl Into<String>, config: TopicConfig) {
+        let mut topics = self.lock_topics();
+        let topic = topics
+            .entry(topic.into())
+            .or_insert_with(|| Topic::new(config));
+        topic.config = config;
+        while topic.retained.len() > config.retention {
+            topic.retained.pop_front();
+        }
+    }
+
+    /// Get a topic's config
+    pub fn config(&self, topic: &str) -> TopicConfig {
+        self.lock_topics()
+            .get(topic)
+            .map_or(self.default_config, |topic| topic.config)
+    }
+
+    /// Subscribe an agent to a topic, returning the number of retained
+    /// events delivered to it
+    pub fn subscribe(&self, agent_id: AgentId, topic: impl Into<String>) -> usize {
+        let topic = topic.into();
+        let replay = {
+            let mut topics = self.lock_topics();
+            let entry = self.topic(&mut topics, &topic);
+            if entry.subscribers.contains(&agent_id) {
+                return 0;
+            }
+            entry.subscribers.push(agent_id);
+            let replay: Vec<_> = entry.retained.iter().cloned().collect();
+            entry.track(agent_id, &replay);
+            replay
+        };
+        let replayed = replay.len();
+        self.deliver(&topic, agent_id, replay);
+        replayed
+    }
+
+    /// Unsubscribe an agent from a topic, forgetting events it did not
+    /// acknowledge
+    pub fn unsubscribe(&self, agent_id: &AgentId, topic: &str) -> bool {
+        let mut topics = self.lock_topics();
+        let Some(topic) = topics.get_mut(topic) else {
+            return false;
+        };
+        topic.unacked.remove(agent_id);
+        let before = topic.subscribers.len();
+        topic
+            .subscribers
+            .retain(|subscriber| subscriber != agent_id);
+        topic.subscribers.len() < before
+    }
+
+    /// Get agents subscribed to a topic
+    pub fn subscribers(&self, topic: &str) -> Vec<AgentId> {
+        self.lock_topics()
+            .get(topic)
+            .map(|topic| topic.subscribers.clone())
+            .unwrap_or_default()
+    }
+
+    /// Publish an event, returning its sequence number
+    pub fn publish<T: Serialize>(
+        &self,
+        topic: impl Into<String>,
+        payload: &T,
+    ) -> Result<u64, RuntimeError> {
+        self.publish_event(None, topic.into(), payload)
+    }
+
+    /// Publish an event on behalf of an agent, returning its sequence number
+    pub fn publish_as<T: Serialize>(
+        &self,
+        publisher: AgentId,
+        topic: impl Into<String>,
+        payload: &T,
+    ) -> Result<u64, RuntimeError> {
+        self.publish_event(Some(publisher), topic.into(), payload)
+    }
+
+    fn publish_event<T: Serialize>(
+        &self,
+        publisher: Option<AgentId>,
+        topic: String,
+        payload: &T,
+    ) -> Result<u64, RuntimeError> {
+        let payload =
+            serde_json::to_value(payload).map_err(|e| QueueError::InvalidEvent(e.to_string()))?;
+        let (event, subscribers) = {
+            let mut topics = self.lock_topics();
+            let entry = self.topic(&mut topics, &topic);
+            let event = Event {
+                topic: topic.clone(),
+                sequence: entry.next_sequence,
+                publisher,
+                payload,
+            };
+            entry.next_sequence += 1;
+            entry.retain(&event);
+            let subscribers = entry.subscribers.clone();
+            for subscriber in &subscribers {
+                entry.track(*subscriber, std::slice::from_ref(&event));
+            }
+            (event, subscribers)
+        };
+        let sequence = event.sequence;
+        // Deliver without the topic lock, so mail observers may publish
+        for subscriber in subscribers {
+            self.deliver(&topic, subscriber, vec![event.clone()]);
+        }
+        Ok(sequence)
+    }
+
+    /// Acknowledge an event delivered at least once, returning false if it
+    /// was not awaiting acknowledgement
+    pub fn ack(&self, agent_id: &AgentId, topic: &str, sequence: u64) -> bool {
+        self.lock_topics()
+            .get_mut(topic)
+            .and_then(|topic| topic.unacked.get_mut(agent_id))
+            .is_some_and(|unacked| unacked.remove(&sequence).is_some())
+    }
+
+    /// Get number of events an agent has not acknowledged
+    pub fn unacked(&self, agent_id: &AgentId) -> usize {
+        self.lock_topics()
+            .values()
+            .filter_map(|topic| topic.unacked.get(agent_id))
+            .map(BTreeMap::len)
+            .sum()
+    }
+
+    /// Resend every unacknowledged event, returning how many were resent
+    ///
+    /// Subscribers may see an event again if they had received it but not
+    /// acknowledged it yet.
+    pub fn redeliver(&self) -> usize {
+        let pending: Vec<(String, AgentId, Vec<Event>)> = self
+            .lock_topics()
+            .iter()
+            .flat_map(|(name, topic)| {
+                topic.unacked.iter().map(|(subscriber, events)| {
+                    (
+                        name.clone(),
+                        *subscriber,
+                        events.values().cloned().collect(),
+                    )
+                })
+            })
+            .collect();
+        let mut resent = 0;
+        for (topic, subscriber, events) in pending {
+            resent += events.len();
+            self.deliver(&topic, subscriber, events);
+        }
+        resent
+    }
+
+    /// Get retained events of a topic, oldest first
+    pub fn retained(&self, topic: &str) -> Vec<Event> {
+        self.lock_topics()
+            .get(topic)
+            .map(|topic| topic.retained.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get number of events of a topic dropped for not fitting a mailbox
+    pub fn dropped(&self, topic: &str) -> u64 {
+        self.lock_topics()
+            .get(topic)
+            .map_or(0, |topic| topic.dropped)
+    }
+
+    /// Get names of known topics
+    pub fn topics(&self) -> Vec<String> {
+        self.lock_topics().keys().cloned().collect()
+    }
+
+    fn deliver(&self, topic: &str, subscriber: AgentId, events: Vec<Event>) {
+        let mut dropped = 0;
+        for event in events {
+            if let Err(e) = self.mailboxes.try_send(event.to_message(subscriber)) {
+                tracing::debug!(
+                    "Could not deliver event {} of topic {} to agent {}: {}",
+                    event.sequence,
+                    topic,
+                    subscriber,
+                    e
+                );
+                dropped += 1;
+            }
+        }
+        if dropped == 0 {
+            return;
+        }
+        if let Some(topic) = self.lock_topics().get_mut(topic) {
+            // Events delivered at least once stay unacknowledged instead
+            if topic.config.delivery == DeliveryMode::AtMostOnce {
+                topic.dropped += dropped;
+            }
+        }
+    }
+
+    fn topic<'a>(&self, topics: &'a mut HashMap<String, Topic>, name: &str) -> &'a mut Topic {
+        topics
+            .entry(name.to_string())
+            .or_insert_with(|| Topic::new(self.default_config))
+    }
+
+    fn lock_topics(&self) -> MutexGuard<'_, HashMap<String, Topic>> {
+        self.topics
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("topics", &self.lock_topics().len())
+            .field("default_config", &self.default_config)
+            .finish()
+    }
+}
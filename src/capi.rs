@@ -0,0 +1,179 @@
+//! C ABI for embedding the runtime in non-Rust agent hosts
+//!
+//! The host submits tasks with opaque byte payloads and polls for events
+//! telling it which task to run next. The generated header lives in
+//! `include/agentropic_runtime.h`.
+
+use crate::scheduler::{PolicyType, Scheduler, SchedulingPolicy, Task};
+use crate::Runtime;
+use agentropic_core::AgentId;
+use std::collections::HashMap;
+use std::ptr;
+use std::time::Duration;
+
+/// No event was available
+pub const AGENTROPIC_EVENT_NONE: u32 = 0;
+/// A task is ready to run on the host
+pub const AGENTROPIC_EVENT_TASK_READY: u32 = 1;
+
+/// Opaque runtime handle
+pub struct AgentropicRuntime {
+    executor: tokio::runtime::Runtime,
+    runtime: Runtime,
+    scheduler: Scheduler,
+    agents: HashMap<u64, AgentId>,
+    hosts: HashMap<AgentId, u64>,
+    // Last polled task, whose payload is valid until the next poll
+    current: Option<Task>,
+}
+
+/// Event returned by [`agentropic_runtime_poll_event`]
+#[repr(C)]
+pub struct AgentropicEvent {
+    /// Event kind, one of the `AGENTROPIC_EVENT_*` constants
+    pub kind: u32,
+    /// Task ID returned by [`agentropic_runtime_submit`]
+    pub task_id: u64,
+    /// Host agent handle the task was submitted for
+    pub agent: u64,
+    /// Task priority
+    pub priority: u32,
+    /// Task payload, valid until the next poll or shutdown
+    pub payload: *const u8,
+    /// Payload length in bytes
+    pub payload_len: usize,
+}
+
+impl AgentropicRuntime {
+    fn new() -> Option<Self> {
+        let executor = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .ok()?;
+        let runtime = Runtime::new();
+        executor.block_on(runtime.start()).ok()?;
+
+        Some(Self {
+            executor,
+            runtime,
+            scheduler: Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)),
+            agents: HashMap::new(),
+            hosts: HashMap::new(),
+            current: None,
+        })
+    }
+
+    fn submit(&mut self, agent: u64, priority: u32, payload: Vec<u8>) -> u64 {
+        let agent_id = *self.agents.entry(agent).or_default();
+        self.hosts.insert(agent_id, agent);
+
+        let task = Task::new(agent_id, priority).with_payload(payload);
+        match self.scheduler.submit(task) {
+            Ok(handle) => handle.id().as_u64(),
+            Err(error) => {
+                tracing::warn!("Rejected task for host agent {}: {}", agent, error);
+                0
+            }
+        }
+    }
+
+    fn poll(&mut self) -> Option<AgentropicEvent> {
+        let task = self.current.insert(self.scheduler.next_task()?);
+
+        Some(AgentropicEvent {
+            kind: AGENTROPIC_EVENT_TASK_READY,
+            task_id: task.id().as_u64(),
+            agent: self.hosts.get(task.agent_id()).copied().unwrap_or_default(),
+            priority: task.priority(),
+            payload: task.payload().as_ptr(),
+            payload_len: task.payload().len(),
+        })
+    }
+}
+
+/// Create a runtime, returning null on failure
+#[no_mangle]
+pub extern "C" fn agentropic_runtime_new() -> *mut AgentropicRuntime {
+    match AgentropicRuntime::new() {
+        Some(runtime) => Box::into_raw(Box::new(runtime)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Submit a task for a host agent, returning its task ID or 0 on failure
+///
+/// The payload is copied.
+///
+/// # Safety
+///
+/// `runtime` must come from [`agentropic_runtime_new`], and `payload` must
+/// point to `payload_len` readable bytes (or be null if `payload_len` is 0).
+#[no_mangle]
+pub unsafe extern "C" fn agentropic_runtime_submit(
+    runtime: *mut AgentropicRuntime,
+    agent: u64,
+    priority: u32,
+    payload: *const u8,
+    payload_len: usize,
+) -> u64 {
+    let Some(runtime) = runtime.as_mut() else {
+        return 0;
+    };
+    let payload = match payload_len {
+        0 => Vec::new(),
+        _ if payload.is_null() => return 0,
+        _ => std::slice::from_raw_parts(payload, payload_len).to_vec(),
+    };
+    runtime.submit(agent, priority, payload)
+}
+
+/// Poll for the next event, returning 1 if `event` was filled and 0 otherwise
+///
+/// # Safety
+///
+/// `runtime` must come from [`agentropic_runtime_new`] and `event` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn agentropic_runtime_poll_event(
+    runtime: *mut AgentropicRuntime,
+    event: *mut AgentropicEvent,
+) -> i32 {
+    let (Some(runtime), false) = (runtime.as_mut(), event.is_null()) else {
+        return 0;
+    };
+    match runtime.poll() {
+        Some(next) => {
+            event.write(next);
+            1
+        }
+        None => {
+            event.write(AgentropicEvent {
+                kind: AGENTROPIC_EVENT_NONE,
+                task_id: 0,
+                agent: 0,
+                priority: 0,
+                payload: ptr::null(),
+                payload_len: 0,
+            });
+            0
+        }
+    }
+}
+
+/// Stop the runtime and free it
+///
+/// # Safety
+///
+/// `runtime` must come from [`agentropic_runtime_new`] and must not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn agentropic_runtime_shutdown(runtime: *mut AgentropicRuntime) {
+    if runtime.is_null() {
+        return;
+    }
+    let runtime = Box::from_raw(runtime);
+    let AgentropicRuntime {
+        executor, runtime, ..
+    } = *runtime;
+    let _ = executor.block_on(runtime.shutdown(Duration::ZERO));
+}
@@ -0,0 +1,291 @@
+//! Fault injection for testing supervision
+//!
+//! A [`Chaos`] injects failures at the points where real ones surface:
+//! executions wrapped with [`Chaos::wrap`] fail or start late, health
+//! results passed through [`Chaos::record_health`] flip,
+//! [`Chaos::trip_breaker`] opens circuit breakers, and [`Chaos::kill_child`]
+//! kills supervised children through their shutdown hooks. Each fault fires with
+//! its probability from a [`ChaosConfig`]; with a seed, the same sequence
+//! of calls injects the same faults, so restart policies and escalation
+//! paths can be tested against reproducible failure storms.
+
+use crate::rng::SplitMix64;
+#[cfg(feature = "scheduler")]
+use crate::scheduler::Task;
+use crate::supervisor::{CircuitBreaker, HealthCheck, StopHandle};
+use crate::timer::{system_clock, SharedClock};
+#[cfg(feature = "scheduler")]
+use crate::RuntimeError;
+#[cfg(feature = "scheduler")]
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "scheduler")]
+use std::future::Future;
+#[cfg(feature = "scheduler")]
+use std::sync::Arc;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Probabilities of each injected fault
+///
+/// Probabilities are clamped to `[0, 1]`; all are zero by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    seed: Option<u64>,
+    task_failure: f64,
+    dispatch_delay: f64,
+    max_delay: Duration,
+    health_flip: f64,
+    breaker_trip: f64,
+    #[serde(default)]
+    child_kill: f64,
+}
+
+impl ChaosConfig {
+    /// Create a config injecting no faults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw faults from a fixed seed, making them reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Fail wrapped executions with a probability
+    pub fn with_task_failure(mut self, probability: f64) -> Self {
+        self.task_failure = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay wrapped executions with a probability, by up to `max`
+    pub fn with_dispatch_delay(mut self, probability: f64, max: Duration) -> Self {
+        self.dispatch_delay = probability.clamp(0.0, 1.0);
+        self.max_delay = max;
+        self
+    }
+
+    /// Invert recorded health results with a probability
+    pub fn with_health_flip(mut self, probability: f64) -> Self {
+        self.health_flip = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Trip circuit breakers with a probability
+    pub fn with_breaker_trip(mut self, probability: f64) -> Self {
+        self.breaker_trip = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Kill running children with a probability
+    pub fn with_child_kill(mut self, probability: f64) -> Self {
+        self.child_kill = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Get seed
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Get task failure probability
+    pub fn task_failure(&self) -> f64 {
+        self.task_failure
+    }
+
+    /// Get dispatch delay probability and maximum delay
+    pub fn dispatch_delay(&self) -> (f64, Duration) {
+        (self.dispatch_delay, self.max_delay)
+    }
+
+    /// Get health flip probability
+    pub fn health_flip(&self) -> f64 {
+        self.health_flip
+    }
+
+    /// Get breaker trip probability
+    pub fn breaker_trip(&self) -> f64 {
+        self.breaker_trip
+    }
+
+    /// Get child kill probability
+    pub fn child_kill(&self) -> f64 {
+        self.child_kill
+    }
+}
+
+/// Faults injected so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChaosStats {
+    /// Executions failed
+    pub tasks_failed: u64,
+    /// Executions delayed
+    pub dispatches_delayed: u64,
+    /// Health results inverted
+    pub checks_flipped: u64,
+    /// Circuit breakers tripped
+    pub breakers_tripped: u64,
+    /// Children killed
+    #[serde(default)]
+    pub children_killed: u64,
+}
+
+#[derive(Debug)]
+struct ChaosState {
+    rng: SplitMix64,
+    stats: ChaosStats,
+}
+
+/// Fault injector
+#[derive(Debug)]
+pub struct Chaos {
+    config: ChaosConfig,
+    state: Mutex<ChaosState>,
+    clock: SharedClock,
+}
+
+impl Chaos {
+    /// Create an injector timing delays by the system clock
+    pub fn new(config: ChaosConfig) -> Self {
+        let rng = config
+            .seed
+            .map_or_else(SplitMix64::new, SplitMix64::with_seed);
+        Self {
+            config,
+            state: Mutex::new(ChaosState {
+                rng,
+                stats: ChaosStats::default(),
+            }),
+            clock: system_clock(),
+        }
+    }
+
+    /// Time delays with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get config
+    pub fn config(&self) -> &ChaosConfig {
+        &self.config
+    }
+
+    /// Get faults injected so far
+    pub fn stats(&self) -> ChaosStats {
+        self.lock_state().stats
+    }
+
+    /// Decide whether to fail an execution
+    pub fn should_fail(&self) -> bool {
+        let mut state = self.lock_state();
+        let fail = state.rng.next_unit() < self.config.task_failure;
+        if fail {
+            state.stats.tasks_failed += 1;
+        }
+        fail
+    }
+
+    /// Draw a delay to apply before an execution, if any
+    pub fn dispatch_delay(&self) -> Option<Duration> {
+        let mut state = self.lock_state();
+        if state.rng.next_unit() >= self.config.dispatch_delay {
+            return None;
+        }
+        state.stats.dispatches_delayed += 1;
+        Some(self.config.max_delay.mul_f64(state.rng.next_unit()))
+    }
+
+    /// Pass a health result through, inverting it with the flip probability
+    pub fn flip_health(&self, healthy: bool) -> bool {
+        let mut state = self.lock_state();
+        if state.rng.next_unit() >= self.config.health_flip {
+            return healthy;
+        }
+        state.stats.checks_flipped += 1;
+        tracing::debug!("Chaos flipped health result to {}", !healthy);
+        !healthy
+    }
+
+    /// Record a health result in a check, inverting it with the flip probability
+    pub fn record_health(&self, check: &mut HealthCheck, healthy: bool) {
+        if self.flip_health(healthy) {
+            check.record_healthy();
+        } else {
+            check.record_unhealthy();
+        }
+    }
+
+    /// Trip a circuit breaker with the trip probability, returning whether it was
+    pub fn trip_breaker(&self, breaker: &mut CircuitBreaker) -> bool {
+        let mut state = self.lock_state();
+        if state.rng.next_unit() >= self.config.breaker_trip {
+            return false;
+        }
+        state.stats.breakers_tripped += 1;
+        drop(state);
+        tracing::debug!("Chaos tripped a circuit breaker");
+        breaker.trip();
+        true
+    }
+
+    /// Kill a child's running attempt with the kill probability, returning
+    /// whether it was
+    ///
+    /// The kill goes through [`StopHandle::kill`], so the child's shutdown
+    /// hooks run and its restart policy decides what follows.
+    pub fn kill_child(&self, stop: &StopHandle) -> bool {
+        let mut state = self.lock_state();
+        if state.rng.next_unit() >= self.config.child_kill {
+            return false;
+        }
+        state.stats.children_killed += 1;
+        drop(state);
+        tracing::debug!("Chaos killed a child");
+        stop.kill();
+        true
+    }
+
+    /// Wrap an execution function for [`crate::scheduler::Scheduler::run`]
+    /// so its executions may start late or fail without running
+    #[cfg(feature = "scheduler")]
+    pub fn wrap<F, Fut>(
+        self: &Arc<Self>,
+        execute: F,
+    ) -> impl Fn(&Task) -> BoxFuture<'static, Result<(), RuntimeError>>
+    where
+        F: Fn(&Task) -> Fut,
+        Fut: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+    {
+        let chaos = self.clone();
+        move |task: &Task| {
+            let delay = chaos.dispatch_delay();
+            let fail = chaos.should_fail();
+            let execution = (!fail).then(|| execute(task));
+            let chaos = chaos.clone();
+            let id = task.id();
+            Box::pin(async move {
+                if let Some(delay) = delay {
+                    chaos.clock.sleep(delay).await;
+                }
+                match execution {
+                    Some(execution) => execution.await,
+                    None => {
+                        tracing::debug!("Chaos failed task {}", id);
+                        Err(RuntimeError::ExecutionFailed(format!(
+                            "chaos: injected failure of task {}",
+                            id
+                        )))
+                    }
+                }
+            })
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, ChaosState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
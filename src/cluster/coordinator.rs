@@ -0,0 +1,429 @@
+use super::{WorkerNode, WorkerTransport};
+use crate::scheduler::state::Now;
+use crate::scheduler::{Scheduler, Task, TaskRecord};
+use crate::timer::{self, SharedClock};
+use crate::RuntimeError;
+use futures::future::BoxFuture;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::{AbortHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+
+/// Task running on a worker
+struct Assignment {
+    task: Task,
+    abort: AbortHandle,
+}
+
+/// Worker with its liveness and running tasks
+struct Worker {
+    node: WorkerNode,
+    last_heartbeat: Instant,
+    assigned: HashMap<u64, Assignment>,
+}
+
+impl Worker {
+    fn free(&self) -> usize {
+        self.node.capacity().saturating_sub(self.assigned.len())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    workers: BTreeMap<String, Worker>,
+    /// Tasks of lost workers, to be queued again
+    orphaned: Vec<(u64, Task)>,
+    /// Workers that left, were lost, or were cordoned, whose affinity keys to unpin
+    departed: Vec<String>,
+    /// Workers taking no new tasks, kept across rejoins
+    cordoned: HashSet<String>,
+    reassigned: u64,
+}
+
+/// Worker as seen by the coordinator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerStatus {
+    /// Worker ID
+    pub id: String,
+    /// Tasks the worker runs at once
+    pub capacity: usize,
+    /// Tasks running on the worker
+    pub running: usize,
+    /// Time since the worker's last heartbeat
+    pub since_heartbeat: Duration,
+    /// Whether the worker is cordoned
+    pub cordoned: bool,
+}
+
+/// Coordinator dispatching a scheduler's tasks to worker nodes
+pub struct Cluster {
+    state: std::sync::Mutex<State>,
+    heartbeat_interval: Duration,
+    heartbeat_timeout: Duration,
+    clock: SharedClock,
+}
+
+impl Cluster {
+    /// Create a cluster with no workers, sending heartbeats every second and
+    /// dropping workers silent for 3 seconds
+    pub fn new() -> Self {
+        Self {
+            state: std::sync::Mutex::new(State::default()),
+            heartbeat_interval: Duration::from_secs(1),
+            heartbeat_timeout: Duration::from_secs(3),
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Set how often workers are sent heartbeats
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Set how long a worker may go without a heartbeat before it is dropped
+    pub fn with_heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.heartbeat_timeout = timeout;
+        self
+    }
+
+    /// Time heartbeats with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Add a worker, or update one that rejoins with the same ID
+    pub fn join(&self, node: WorkerNode) {
+        let now = self.clock.instant();
+        let mut state = self.lock_state();
+        match state.workers.get_mut(node.id()) {
+            Some(worker) => {
+                worker.node = node;
+                worker.last_heartbeat = now;
+            }
+            None => {
+                tracing::info!("Worker {} joined the cluster", node.id());
+                state.workers.insert(
+                    node.id().to_string(),
+                    Worker {
+                        node,
+                        last_heartbeat: now,
+                        assigned: HashMap::new(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Remove a worker, queueing its running tasks again
+    ///
+    /// Returns false if the worker is unknown.
+    pub fn leave(&self, id: &str) -> bool {
+        let mut state = self.lock_state();
+        let Some(worker) = state.workers.remove(id) else {
+            return false;
+        };
+        Self::orphan(&mut state.orphaned, worker.assigned);
+        state.departed.push(id.to_string());
+        true
+    }
+
+    /// Record a heartbeat from a worker
+    ///
+    /// Returns false if the worker is unknown.
+    pub fn heartbeat(&self, id: &str) -> bool {
+        let now = self.clock.instant();
+        match self.lock_state().workers.get_mut(id) {
+            Some(worker) => {
+                worker.last_heartbeat = now;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop dispatching new tasks to a worker node, e.g. for maintenance
+    ///
+    /// Tasks already running on the worker finish; tasks pinned to it by
+    /// their affinity key move to other workers. The worker stays cordoned
+    /// if it leaves and rejoins, until [`Cluster::uncordon`].
+    pub fn cordon(&self, id: &str) {
+        let mut state = self.lock_state();
+        if state.cordoned.insert(id.to_string()) {
+            tracing::info!("Cordoned worker {}", id);
+            state.departed.push(id.to_string());
+        }
+    }
+
+    /// Resume dispatching to a cordoned worker node
+    pub fn uncordon(&self, id: &str) {
+        if self.lock_state().cordoned.remove(id) {
+            tracing::info!("Uncordoned worker {}", id);
+        }
+    }
+
+    /// Check if a worker node is cordoned
+    pub fn is_cordoned(&self, id: &str) -> bool {
+        self.lock_state().cordoned.contains(id)
+    }
+
+    /// Drop workers whose last heartbeat is older than the timeout, returning their IDs
+    ///
+    /// Their running tasks are queued again by [`Cluster::run`].
+    pub fn expire(&self) -> Vec<String> {
+        let now = self.clock.instant();
+        let timeout = self.heartbeat_timeout;
+        let mut state = self.lock_state();
+        let lost: Vec<String> = state
+            .workers
+            .iter()
+            .filter(|(_, worker)| now.saturating_duration_since(worker.last_heartbeat) > timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &lost {
+            if let Some(worker) = state.workers.remove(id) {
+                tracing::warn!(
+                    "Worker {} missed its heartbeats, reassigning {} tasks",
+                    id,
+                    worker.assigned.len()
+                );
+                Self::orphan(&mut state.orphaned, worker.assigned);
+                state.departed.push(id.clone());
+            }
+        }
+        lost
+    }
+
+    /// Stop waiting for tasks and set them aside to be queued again
+    fn orphan(orphaned: &mut Vec<(u64, Task)>, assigned: HashMap<u64, Assignment>) {
+        for (flight, assignment) in assigned {
+            assignment.abort.abort();
+            orphaned.push((flight, assignment.task));
+        }
+    }
+
+    /// Get status of all workers, ordered by ID
+    pub fn workers(&self) -> Vec<WorkerStatus> {
+        let now = self.clock.instant();
+        let state = self.lock_state();
+        state
+            .workers
+            .iter()
+            .map(|(id, worker)| WorkerStatus {
+                id: id.clone(),
+                capacity: worker.node.capacity(),
+                running: worker.assigned.len(),
+                since_heartbeat: now.saturating_duration_since(worker.last_heartbeat),
+                cordoned: state.cordoned.contains(id),
+            })
+            .collect()
+    }
+
+    /// Get number of free task slots across workers that are not cordoned
+    pub fn free_capacity(&self) -> usize {
+        let state = self.lock_state();
+        state
+            .workers
+            .iter()
+            .filter(|(id, _)| !state.cordoned.contains(*id))
+            .map(|(_, worker)| worker.free())
+            .sum()
+    }
+
+    /// Get number of tasks queued again after their worker was lost
+    pub fn reassigned(&self) -> u64 {
+        self.lock_state().reassigned
+    }
+
+    /// List workers with free slots that are not cordoned, most free slots first
+    fn candidates(&self) -> Vec<(String, Arc<dyn WorkerTransport>)> {
+        let state = self.lock_state();
+        let mut free: Vec<_> = state
+            .workers
+            .iter()
+            .filter(|(id, worker)| worker.free() > 0 && !state.cordoned.contains(*id))
+            .collect();
+        free.sort_by(|(a_id, a), (b_id, b)| b.free().cmp(&a.free()).then(a_id.cmp(b_id)));
+        free.into_iter()
+            .map(|(id, worker)| (id.clone(), worker.node.transport().clone()))
+            .collect()
+    }
+
+    /// Take a task for the first candidate worker that has one
+    ///
+    /// Tasks pinned to a busy worker by their affinity key wait for it.
+    fn pick(&self, scheduler: &mut Scheduler) -> Option<(String, Arc<dyn WorkerTransport>, Task)> {
+        self.candidates().into_iter().find_map(|(id, transport)| {
+            let task = scheduler.next_task_on(&id)?;
+            Some((id, transport, task))
+        })
+    }
+
+    /// Take back a finished task, unless its worker was lost in the meantime
+    fn unassign(&self, id: &str, flight: u64) -> Option<Task> {
+        let mut state = self.lock_state();
+        let assignment = state.workers.get_mut(id)?.assigned.remove(&flight)?;
+        Some(assignment.task)
+    }
+
+    /// Send every worker a heartbeat, waiting at most one interval for each
+    async fn beat(&self) {
+        let workers: Vec<_> = self
+            .lock_state()
+            .workers
+            .iter()
+            .map(|(id, worker)| (id.clone(), worker.node.transport().clone()))
+            .collect();
+        let checks = workers.into_iter().map(|(id, transport)| async move {
+            let alive = tokio::select! {
+                result = transport.heartbeat() => result.is_ok(),
+                _ = self.clock.sleep(self.heartbeat_interval) => false,
+            };
+            (id, alive)
+        });
+        for (id, alive) in futures::future::join_all(checks).await {
+            if alive {
+                self.heartbeat(&id);
+            }
+        }
+    }
+
+    /// Send tasks to workers while both are available
+    fn dispatch(
+        &self,
+        scheduler: &mut Scheduler,
+        running: &mut JoinSet<(String, u64, Result<(), RuntimeError>, Duration)>,
+    ) {
+        while let Some((id, transport, mut task)) = self.pick(scheduler) {
+            let flight = scheduler.begin_flight(&mut task);
+            task.record_attempt();
+            let record = TaskRecord::at(&task, Now::of(&*self.clock));
+            let clock = self.clock.clone();
+            let worker = id.clone();
+            let abort = running.spawn(async move {
+                let started = clock.instant();
+                let result = transport.execute(record).await;
+                (
+                    worker,
+                    flight,
+                    result,
+                    clock.instant().saturating_duration_since(started),
+                )
+            });
+            if let Some(worker) = self.lock_state().workers.get_mut(&id) {
+                worker.assigned.insert(flight, Assignment { task, abort });
+            }
+        }
+    }
+
+    /// Queue the tasks of lost workers again, unpinning their affinity keys
+    fn requeue_orphans(&self, scheduler: &mut Scheduler) {
+        let (orphaned, departed) = {
+            let mut state = self.lock_state();
+            state.reassigned += state.orphaned.len() as u64;
+            (
+                std::mem::take(&mut state.orphaned),
+                std::mem::take(&mut state.departed),
+            )
+        };
+        for id in departed {
+            scheduler.unpin_worker(&id);
+        }
+        for (flight, task) in orphaned {
+            scheduler.reassign(flight, task);
+        }
+    }
+
+    /// Dispatch the scheduler's tasks to workers until `shutdown` is cancelled
+    ///
+    /// Outcomes are recorded on the scheduler as with [`Scheduler::run`], so
+    /// failed tasks retry and dependents are released. After
+    /// [`Scheduler::drain`] the call returns once running tasks finish; on
+    /// shutdown, running tasks are abandoned and queued again.
+    pub async fn run(&self, scheduler: Arc<Mutex<Scheduler>>, shutdown: CancellationToken) {
+        let wakeup = scheduler.lock().await.wakeup();
+        let mut running = JoinSet::new();
+        let mut next_beat = self.clock.instant() + self.heartbeat_interval;
+        // Heartbeats in progress, polled alongside completions so a slow
+        // worker does not hold up the results of the others
+        let mut beating: Option<BoxFuture<'_, ()>> = None;
+
+        loop {
+            let wake_at = {
+                let mut scheduler = scheduler.lock().await;
+                self.requeue_orphans(&mut scheduler);
+                if scheduler.is_draining() {
+                    if running.is_empty() {
+                        break;
+                    }
+                } else {
+                    self.dispatch(&mut scheduler, &mut running);
+                }
+                scheduler.next_wakeup_at()
+            };
+
+            let idle = beating.is_none();
+            let beaten = async {
+                match beating.as_mut() {
+                    Some(beat) => beat.await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => break,
+                Some(joined) = running.join_next(), if !running.is_empty() => {
+                    let Ok((id, flight, result, used)) = joined else {
+                        continue;
+                    };
+                    if let Some(task) = self.unassign(&id, flight) {
+                        scheduler.lock().await.settle(flight, task, result, used);
+                    }
+                }
+                _ = beaten => {
+                    beating = None;
+                    self.expire();
+                    next_beat = self.clock.instant() + self.heartbeat_interval;
+                }
+                _ = self.clock.sleep_until(next_beat), if idle => {
+                    beating = Some(Box::pin(self.beat()));
+                }
+                _ = wakeup.wait(wake_at) => {}
+            }
+        }
+
+        running.abort_all();
+        let mut abandoned = Vec::new();
+        for worker in self.lock_state().workers.values_mut() {
+            Self::orphan(&mut abandoned, std::mem::take(&mut worker.assigned));
+        }
+        let mut scheduler = scheduler.lock().await;
+        self.requeue_orphans(&mut scheduler);
+        for (flight, task) in abandoned {
+            scheduler.reassign(flight, task);
+        }
+    }
+}
+
+impl Default for Cluster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Cluster {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cluster")
+            .field("workers", &self.workers())
+            .field("heartbeat_interval", &self.heartbeat_interval)
+            .field("heartbeat_timeout", &self.heartbeat_timeout)
+            .finish()
+    }
+}
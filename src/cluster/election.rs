@@ -0,0 +1,536 @@
+//! Lease-based leader election
+//!
+//! Instances campaign for a lease in a shared [`LeaseStore`]. The holder
+//! renews it every renew interval; if it misses renewals until the lease
+//! expires, the next standby to campaign takes it with a higher term. The
+//! leader checkpoints its scheduler's queue to the store on each renewal,
+//! and a standby taking over warm-starts from the latest checkpoint. The
+//! store rejects checkpoints from earlier terms, so a leader that was
+//! replaced cannot overwrite its successor's state.
+//!
+//! [`InMemoryLeaseStore`] serves instances in one process; instances in
+//! separate processes share a [`FileLeaseStore`] directory, on one host or a
+//! shared filesystem that honours exclusive file creation.
+
+use crate::scheduler::{Scheduler, SchedulerState};
+use crate::timer::{self, SharedClock};
+use crate::{RuntimeError, SchedulerError};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{watch, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Leadership lease
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    /// Instance holding the lease
+    pub holder: String,
+    /// Term, increased every time the lease changes hands
+    pub term: u64,
+    /// Time the lease lapses unless renewed
+    pub expires_at: SystemTime,
+}
+
+/// Storage shared by all candidates, such as a database or coordination service
+#[async_trait]
+pub trait LeaseStore: Send + Sync {
+    /// Take the lease for `candidate` if it is free or expired, or renew it
+    /// if `candidate` holds it, returning the lease as it stands afterwards
+    async fn acquire(
+        &self,
+        candidate: &str,
+        ttl: Duration,
+        now: SystemTime,
+    ) -> Result<Lease, RuntimeError>;
+
+    /// Give up the lease if `holder` holds it
+    async fn release(&self, holder: &str) -> Result<(), RuntimeError>;
+
+    /// Store the leader's scheduler state, failing if `term` is not the current term
+    async fn save_state(&self, term: u64, state: SchedulerState) -> Result<(), RuntimeError>;
+
+    /// Load the latest stored scheduler state
+    async fn load_state(&self) -> Result<Option<SchedulerState>, RuntimeError>;
+}
+
+#[derive(Debug, Default)]
+struct Stored {
+    lease: Option<Lease>,
+    term: u64,
+    state: Option<SchedulerState>,
+}
+
+impl Stored {
+    /// Take the lease for `candidate` if it is free or expired, or renew it
+    fn acquire(&mut self, candidate: &str, ttl: Duration, now: SystemTime) -> Lease {
+        match &mut self.lease {
+            Some(lease) if lease.holder == candidate => {
+                lease.expires_at = now + ttl;
+                lease.clone()
+            }
+            Some(lease) if lease.expires_at > now => lease.clone(),
+            _ => {
+                self.term += 1;
+                let lease = Lease {
+                    holder: candidate.to_string(),
+                    term: self.term,
+                    expires_at: now + ttl,
+                };
+                self.lease = Some(lease.clone());
+                lease
+            }
+        }
+    }
+
+    /// Give up the lease if `holder` holds it
+    fn release(&mut self, holder: &str) {
+        if self
+            .lease
+            .as_ref()
+            .is_some_and(|lease| lease.holder == holder)
+        {
+            self.lease = None;
+        }
+    }
+}
+
+/// Lease store in memory, for instances in one process and tests
+#[derive(Debug, Default)]
+pub struct InMemoryLeaseStore {
+    stored: std::sync::Mutex<Stored>,
+}
+
+impl InMemoryLeaseStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock_stored(&self) -> std::sync::MutexGuard<'_, Stored> {
+        self.stored.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+#[async_trait]
+impl LeaseStore for InMemoryLeaseStore {
+    async fn acquire(
+        &self,
+        candidate: &str,
+        ttl: Duration,
+        now: SystemTime,
+    ) -> Result<Lease, RuntimeError> {
+        Ok(self.lock_stored().acquire(candidate, ttl, now))
+    }
+
+    async fn release(&self, holder: &str) -> Result<(), RuntimeError> {
+        self.lock_stored().release(holder);
+        Ok(())
+    }
+
+    async fn save_state(&self, term: u64, state: SchedulerState) -> Result<(), RuntimeError> {
+        let mut stored = self.lock_stored();
+        if stored.term != term {
+            return Err(SchedulerError::NotLeader { term }.into());
+        }
+        stored.state = Some(state);
+        Ok(())
+    }
+
+    async fn load_state(&self) -> Result<Option<SchedulerState>, RuntimeError> {
+        Ok(self.lock_stored().state.clone())
+    }
+}
+
+/// Lease and term as written to a [`FileLeaseStore`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LeaseFile {
+    lease: Option<Lease>,
+    term: u64,
+}
+
+/// Lease store in a directory shared by instances in separate processes
+///
+/// The lease and the checkpointed state are JSON files, replaced through a
+/// temporary file so readers never see a partial write. Each operation holds
+/// a lock file, created exclusively; a lock left behind by a crashed
+/// process is broken once it is older than the stale lock timeout.
+#[derive(Debug, Clone)]
+pub struct FileLeaseStore {
+    dir: PathBuf,
+    stale_lock: Duration,
+}
+
+impl FileLeaseStore {
+    /// Create a store in `dir`, creating the directory if needed
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, RuntimeError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| store_error("create", &dir, e))?;
+        Ok(Self {
+            dir,
+            stale_lock: Duration::from_secs(5),
+        })
+    }
+
+    /// Set how old a lock file must be before it is considered abandoned
+    pub fn with_stale_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.stale_lock = timeout;
+        self
+    }
+
+    /// Get the directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Wait for the lock file, breaking it if it is stale
+    async fn lock(&self) -> Result<FileLock, RuntimeError> {
+        let path = self.path("lease.lock");
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(FileLock(path)),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > self.stale_lock);
+                    if stale {
+                        tracing::warn!("Breaking stale lease lock {}", path.display());
+                        let _ = std::fs::remove_file(&path);
+                    } else {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                }
+                Err(e) => return Err(store_error("lock", &path, e)),
+            }
+        }
+    }
+
+    fn read<T: serde::de::DeserializeOwned + Default>(
+        &self,
+        name: &str,
+    ) -> Result<T, RuntimeError> {
+        let path = self.path(name);
+        let json = match std::fs::read(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(T::default()),
+            Err(e) => return Err(store_error("read", &path, e)),
+        };
+        serde_json::from_slice(&json).map_err(|e| store_error("decode", &path, e))
+    }
+
+    fn write<T: Serialize>(&self, name: &str, value: &T) -> Result<(), RuntimeError> {
+        let path = self.path(name);
+        let json = serde_json::to_vec(value).map_err(|e| store_error("encode", &path, e))?;
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, json)
+            .and_then(|_| std::fs::rename(&temp, &path))
+            .map_err(|e| store_error("write", &path, e))
+    }
+
+    fn read_stored(&self) -> Result<Stored, RuntimeError> {
+        let file: LeaseFile = self.read("lease.json")?;
+        Ok(Stored {
+            lease: file.lease,
+            term: file.term,
+            state: None,
+        })
+    }
+
+    fn write_stored(&self, stored: Stored) -> Result<(), RuntimeError> {
+        self.write(
+            "lease.json",
+            &LeaseFile {
+                lease: stored.lease,
+                term: stored.term,
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl LeaseStore for FileLeaseStore {
+    async fn acquire(
+        &self,
+        candidate: &str,
+        ttl: Duration,
+        now: SystemTime,
+    ) -> Result<Lease, RuntimeError> {
+        let _lock = self.lock().await?;
+        let mut stored = self.read_stored()?;
+        let lease = stored.acquire(candidate, ttl, now);
+        self.write_stored(stored)?;
+        Ok(lease)
+    }
+
+    async fn release(&self, holder: &str) -> Result<(), RuntimeError> {
+        let _lock = self.lock().await?;
+        let mut stored = self.read_stored()?;
+        stored.release(holder);
+        self.write_stored(stored)
+    }
+
+    async fn save_state(&self, term: u64, state: SchedulerState) -> Result<(), RuntimeError> {
+        let _lock = self.lock().await?;
+        if self.read_stored()?.term != term {
+            return Err(SchedulerError::NotLeader { term }.into());
+        }
+        self.write("state.json", &Some(state))
+    }
+
+    async fn load_state(&self) -> Result<Option<SchedulerState>, RuntimeError> {
+        let _lock = self.lock().await?;
+        self.read("state.json")
+    }
+}
+
+/// Lock file of a [`FileLeaseStore`], removed on drop
+#[derive(Debug)]
+struct FileLock(PathBuf);
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn store_error(action: &str, path: &Path, error: impl std::fmt::Display) -> RuntimeError {
+    SchedulerError::State(format!(
+        "failed to {} lease store {}: {}",
+        action,
+        path.display(),
+        error
+    ))
+    .into()
+}
+
+/// Role of an instance in the election
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElectionRole {
+    /// Holds the lease and should dispatch tasks
+    Leader {
+        /// Term of the lease
+        term: u64,
+    },
+    /// Waits to take over
+    Standby {
+        /// Current leader, if known
+        leader: Option<String>,
+    },
+}
+
+impl ElectionRole {
+    /// Check if leader
+    pub fn is_leader(&self) -> bool {
+        matches!(self, ElectionRole::Leader { .. })
+    }
+}
+
+/// Candidate in a leader election between runtime instances
+///
+/// Only the leader should dispatch tasks; watch the role with
+/// [`LeaderElection::watch`] to start and stop [`Scheduler::run`] or a
+/// [`super::Cluster`].
+pub struct LeaderElection {
+    id: String,
+    store: Arc<dyn LeaseStore>,
+    ttl: Duration,
+    renew_interval: Option<Duration>,
+    clock: SharedClock,
+    role: watch::Sender<ElectionRole>,
+}
+
+impl LeaderElection {
+    /// Create a candidate with a 10 second lease, renewed every third of it
+    pub fn new(id: impl Into<String>, store: Arc<dyn LeaseStore>) -> Self {
+        Self {
+            id: id.into(),
+            store,
+            ttl: Duration::from_secs(10),
+            renew_interval: None,
+            clock: timer::system_clock(),
+            role: watch::Sender::new(ElectionRole::Standby { leader: None }),
+        }
+    }
+
+    /// Set how long the lease lasts without renewal
+    pub fn with_lease_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set how often to campaign, a third of the lease by default
+    pub fn with_renew_interval(mut self, interval: Duration) -> Self {
+        self.renew_interval = Some(interval);
+        self
+    }
+
+    /// Time leases with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get current role
+    pub fn role(&self) -> ElectionRole {
+        self.role.borrow().clone()
+    }
+
+    /// Check if this instance leads
+    pub fn is_leader(&self) -> bool {
+        self.role.borrow().is_leader()
+    }
+
+    /// Watch the role change
+    pub fn watch(&self) -> watch::Receiver<ElectionRole> {
+        self.role.subscribe()
+    }
+
+    fn renew_interval(&self) -> Duration {
+        self.renew_interval.unwrap_or(self.ttl / 3)
+    }
+
+    fn set_role(&self, role: ElectionRole) {
+        self.role.send_if_modified(|current| {
+            if *current == role {
+                return false;
+            }
+            match &role {
+                ElectionRole::Leader { term } => {
+                    tracing::info!("{} became leader for term {}", self.id, term)
+                }
+                ElectionRole::Standby { leader } if current.is_leader() => {
+                    tracing::warn!("{} lost leadership to {:?}", self.id, leader)
+                }
+                ElectionRole::Standby { .. } => {}
+            }
+            *current = role;
+            true
+        });
+    }
+
+    /// Take or renew the lease once
+    ///
+    /// An instance that cannot reach the store steps down.
+    pub async fn campaign(&self) -> Result<ElectionRole, RuntimeError> {
+        let lease = match self
+            .store
+            .acquire(&self.id, self.ttl, self.clock.wall_time())
+            .await
+        {
+            Ok(lease) => lease,
+            Err(error) => {
+                self.set_role(ElectionRole::Standby { leader: None });
+                return Err(error);
+            }
+        };
+        let role = if lease.holder == self.id {
+            ElectionRole::Leader { term: lease.term }
+        } else {
+            ElectionRole::Standby {
+                leader: Some(lease.holder),
+            }
+        };
+        self.set_role(role.clone());
+        Ok(role)
+    }
+
+    /// Campaign once and hand the scheduler's work over accordingly
+    ///
+    /// A newly elected leader warm-starts from the stored state, and a
+    /// leader checkpoints its state; a leader that stepped down drops its
+    /// queued work, which its successor now owns.
+    pub async fn step(&self, scheduler: &Mutex<Scheduler>) -> ElectionRole {
+        let was_leader = self.is_leader();
+        let role = match self.campaign().await {
+            Ok(role) => role,
+            Err(error) => {
+                tracing::warn!("{} could not reach the lease store: {}", self.id, error);
+                self.role()
+            }
+        };
+        match role {
+            ElectionRole::Leader { term } => {
+                if !was_leader {
+                    match self.store.load_state().await {
+                        Ok(Some(state)) => scheduler.lock().await.warm_start(state),
+                        Ok(None) => {}
+                        Err(error) => {
+                            tracing::warn!("{} could not load scheduler state: {}", self.id, error)
+                        }
+                    }
+                }
+                self.checkpoint(term, scheduler).await;
+            }
+            ElectionRole::Standby { .. } if was_leader => scheduler.lock().await.relinquish(),
+            ElectionRole::Standby { .. } => {}
+        }
+        self.role()
+    }
+
+    async fn checkpoint(&self, term: u64, scheduler: &Mutex<Scheduler>) {
+        let state = {
+            let scheduler = scheduler.lock().await;
+            let mut state = scheduler.state();
+            for task in scheduler.in_flight() {
+                state.add_in_flight(task);
+            }
+            state
+        };
+        if let Err(error) = self.store.save_state(term, state).await {
+            tracing::warn!(
+                "{} could not checkpoint scheduler state: {}",
+                self.id,
+                error
+            );
+        }
+    }
+
+    /// Campaign every renew interval until `shutdown` is cancelled
+    ///
+    /// On shutdown, a leader checkpoints once more and gives up the lease so
+    /// a standby can take over without waiting for it to expire.
+    pub async fn run(&self, scheduler: Arc<Mutex<Scheduler>>, shutdown: CancellationToken) {
+        loop {
+            self.step(&scheduler).await;
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = self.clock.sleep(self.renew_interval()) => {}
+            }
+        }
+        if let ElectionRole::Leader { term } = self.role() {
+            self.checkpoint(term, &scheduler).await;
+            if let Err(error) = self.store.release(&self.id).await {
+                tracing::warn!("{} could not release the lease: {}", self.id, error);
+            }
+            self.set_role(ElectionRole::Standby { leader: None });
+        }
+    }
+}
+
+impl std::fmt::Debug for LeaderElection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LeaderElection")
+            .field("id", &self.id)
+            .field("role", &self.role())
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
@@ -0,0 +1,42 @@
+//! Distributed scheduling across worker nodes
+//!
+//! A [`Cluster`] takes tasks from a coordinator [`Scheduler`] and dispatches
+//! them to [`WorkerNode`]s, each reached through a [`WorkerTransport`]. The
+//! coordinator sends each worker a heartbeat every interval; a worker that
+//! misses them for longer than the heartbeat timeout is dropped, and the
+//! tasks it was running are queued again for the remaining workers. A
+//! worker cordoned with [`Cluster::cordon`] finishes its running tasks but
+//! is sent no new ones.
+//!
+//! Two coordinators can run hot/standby with [`LeaderElection`]: the
+//! standby takes over the leader's queue when the leader stops renewing
+//! its lease.
+//!
+//! [`WorkerServer`] answers coordinators over TCP, with a shared token, or
+//! with the `quic` feature over QUIC with mutual TLS. Requests are
+//! length-prefixed JSON frames with a size limit. Other transports plug in
+//! by implementing [`WorkerTransport`].
+//!
+//! [`Scheduler`]: crate::scheduler::Scheduler
+
+/// Coordinator dispatching tasks to workers
+pub mod coordinator;
+/// Lease-based leader election
+pub mod election;
+mod protocol;
+/// QUIC transport with mutual TLS
+#[cfg(feature = "quic")]
+pub mod quic;
+/// TCP transport and worker server
+pub mod tcp;
+/// Worker nodes and transports
+pub mod worker;
+
+pub use coordinator::{Cluster, WorkerStatus};
+pub use election::{
+    ElectionRole, FileLeaseStore, InMemoryLeaseStore, LeaderElection, Lease, LeaseStore,
+};
+#[cfg(feature = "quic")]
+pub use quic::QuicTransport;
+pub use tcp::{TcpTransport, WorkerServer};
+pub use worker::{InProcessTransport, WorkerNode, WorkerTransport};
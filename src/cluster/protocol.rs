@@ -0,0 +1,238 @@
+//! Wire protocol shared by the TCP and QUIC transports
+//!
+//! Each request is one frame on a fresh connection or stream, answered by
+//! one frame. A frame is a 4-byte big-endian length followed by that many
+//! bytes of JSON, and frames longer than the receiver's limit are refused
+//! before they are read.
+
+use crate::scheduler::{Task, TaskRecord};
+use crate::timer::SharedClock;
+use crate::{ClusterError, RuntimeError};
+use futures::Future;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Default size limit of a frame
+pub(super) const DEFAULT_MAX_FRAME: usize = 16 * 1024 * 1024;
+
+/// Default time a worker keeps running tasks without hearing a heartbeat
+pub(super) const DEFAULT_FENCE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct Envelope {
+    pub(super) token: Option<String>,
+    pub(super) request: Request,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) enum Request {
+    Execute(Box<TaskRecord>),
+    Heartbeat,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) enum Reply {
+    Done,
+    Failed(String),
+    Unauthorized,
+}
+
+fn disconnected(peer: &str, error: std::io::Error) -> ClusterError {
+    ClusterError::Disconnected {
+        worker: peer.to_string(),
+        reason: error.to_string(),
+    }
+}
+
+/// Write one frame to `peer`
+pub(super) async fn write_frame<W, T>(
+    writer: &mut W,
+    peer: &str,
+    value: &T,
+) -> Result<(), ClusterError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let body = serde_json::to_vec(value).map_err(|e| ClusterError::Malformed(e.to_string()))?;
+    let len = u32::try_from(body.len()).map_err(|_| ClusterError::FrameTooLarge {
+        size: body.len(),
+        limit: u32::MAX as usize,
+    })?;
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(&body);
+    writer
+        .write_all(&frame)
+        .await
+        .map_err(|e| disconnected(peer, e))?;
+    writer.flush().await.map_err(|e| disconnected(peer, e))
+}
+
+/// Read one frame of at most `limit` bytes from `peer`
+pub(super) async fn read_frame<R, T>(
+    reader: &mut R,
+    peer: &str,
+    limit: usize,
+) -> Result<T, ClusterError>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut len = [0; 4];
+    reader
+        .read_exact(&mut len)
+        .await
+        .map_err(|e| disconnected(peer, e))?;
+    let size = u32::from_be_bytes(len) as usize;
+    if size > limit {
+        return Err(ClusterError::FrameTooLarge { size, limit });
+    }
+    let mut body = vec![0; size];
+    reader
+        .read_exact(&mut body)
+        .await
+        .map_err(|e| disconnected(peer, e))?;
+    serde_json::from_slice(&body).map_err(|e| ClusterError::Malformed(e.to_string()))
+}
+
+/// Compare tokens in time independent of where they differ
+fn same_token(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Time of the last heartbeat a worker heard
+///
+/// A worker whose coordinator has gone quiet for the fence timeout stops its
+/// running tasks, because the coordinator will soon consider it lost and run
+/// them elsewhere.
+pub(super) struct Fence {
+    last_heartbeat: Mutex<Instant>,
+    timeout: Option<Duration>,
+    clock: SharedClock,
+}
+
+impl Fence {
+    pub(super) fn new(timeout: Option<Duration>, clock: SharedClock) -> Self {
+        Self {
+            last_heartbeat: Mutex::new(clock.instant()),
+            timeout,
+            clock,
+        }
+    }
+
+    fn beat(&self) {
+        *self.lock() = self.clock.instant();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Instant> {
+        self.last_heartbeat
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Wait until no heartbeat came for the fence timeout
+    async fn expired(&self) {
+        let Some(timeout) = self.timeout else {
+            return std::future::pending().await;
+        };
+        loop {
+            let deadline = *self.lock() + timeout;
+            if self.clock.instant() >= deadline {
+                return;
+            }
+            self.clock.sleep_until(deadline).await;
+        }
+    }
+}
+
+/// Worker side of the protocol
+pub(super) struct Responder<F> {
+    pub(super) execute: Arc<F>,
+    pub(super) token: Option<String>,
+    pub(super) max_frame: usize,
+    pub(super) fence: Arc<Fence>,
+}
+
+impl<F> Clone for Responder<F> {
+    fn clone(&self) -> Self {
+        Self {
+            execute: self.execute.clone(),
+            token: self.token.clone(),
+            max_frame: self.max_frame,
+            fence: self.fence.clone(),
+        }
+    }
+}
+
+impl<F, Fut> Responder<F>
+where
+    F: Fn(Task) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+{
+    /// Answer one request from `peer`
+    ///
+    /// A task stops early, without a reply, if the future `closed` makes of
+    /// the reader resolves because the coordinator gave up on the task, or
+    /// if the fence expires.
+    pub(super) async fn answer<R, W, C>(
+        &self,
+        mut reader: R,
+        writer: &mut W,
+        peer: &str,
+        closed: impl FnOnce(R) -> C,
+    ) -> Result<(), ClusterError>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+        C: Future<Output = ()>,
+    {
+        let envelope: Envelope = match read_frame(&mut reader, peer, self.max_frame).await {
+            Ok(envelope) => envelope,
+            Err(error) => {
+                write_frame(writer, peer, &Reply::Failed(error.to_string())).await?;
+                return Err(error);
+            }
+        };
+        if let Some(expected) = &self.token {
+            let given = envelope.token.as_deref().unwrap_or_default();
+            if !same_token(expected, given) {
+                tracing::warn!("Rejected a request from {} with a bad token", peer);
+                return write_frame(writer, peer, &Reply::Unauthorized).await;
+            }
+        }
+        let reply = match envelope.request {
+            Request::Heartbeat => {
+                self.fence.beat();
+                Reply::Done
+            }
+            Request::Execute(record) => {
+                let task = record.into_task();
+                let id = task.id();
+                tokio::select! {
+                    result = (self.execute)(task) => match result {
+                        Ok(()) => Reply::Done,
+                        Err(error) => Reply::Failed(error.to_string()),
+                    },
+                    _ = closed(reader) => {
+                        tracing::warn!("Coordinator abandoned task {}, stopping it", id);
+                        return Ok(());
+                    }
+                    _ = self.fence.expired() => {
+                        tracing::warn!("Coordinator went quiet, stopping task {}", id);
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        write_frame(writer, peer, &reply).await
+    }
+}
@@ -0,0 +1,274 @@
+//! [`QuicTransport`] keeps one QUIC connection to a worker and opens a
+//! stream per request, answered by [`WorkerServer::serve_quic`]. Both ends
+//! prove who they are with certificates issued by the cluster's CA, set up
+//! with [`client_config`] and [`server_config`]. Requests use the same
+//! frames as the TCP transport, and a task stops if the coordinator drops
+//! its stream.
+
+use super::protocol::{self, Envelope, Reply, Request, DEFAULT_MAX_FRAME};
+use super::{WorkerServer, WorkerTransport};
+use crate::scheduler::{Task, TaskRecord};
+use crate::{ClusterError, RuntimeError};
+use async_trait::async_trait;
+use futures::Future;
+use quinn::crypto::rustls::{QuicClientConfig, QuicServerConfig};
+use quinn::rustls::crypto::CryptoProvider;
+use quinn::rustls::server::WebPkiClientVerifier;
+use quinn::rustls::{self, RootCertStore};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+pub use quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+/// Protocol name negotiated by both ends
+const ALPN: &[u8] = b"agentropic-cluster/1";
+
+fn tls_error(error: impl std::fmt::Display) -> RuntimeError {
+    ClusterError::Tls(error.to_string()).into()
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn roots(ca: &[CertificateDer<'static>]) -> Result<RootCertStore, RuntimeError> {
+    let mut roots = RootCertStore::empty();
+    for cert in ca {
+        roots.add(cert.clone()).map_err(tls_error)?;
+    }
+    Ok(roots)
+}
+
+/// Build a worker's server config, presenting `chain` and accepting only
+/// coordinators with a certificate issued by `client_ca`
+pub fn server_config(
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    client_ca: &[CertificateDer<'static>],
+) -> Result<quinn::ServerConfig, RuntimeError> {
+    let verifier =
+        WebPkiClientVerifier::builder_with_provider(Arc::new(roots(client_ca)?), provider())
+            .build()
+            .map_err(tls_error)?;
+    let mut crypto = rustls::ServerConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(tls_error)?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(chain, key)
+        .map_err(tls_error)?;
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    let crypto = QuicServerConfig::try_from(crypto).map_err(tls_error)?;
+    Ok(quinn::ServerConfig::with_crypto(Arc::new(crypto)))
+}
+
+/// Build a coordinator's client config, presenting `chain` and trusting
+/// only workers with a certificate issued by `server_ca`
+pub fn client_config(
+    server_ca: &[CertificateDer<'static>],
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<quinn::ClientConfig, RuntimeError> {
+    let mut crypto = rustls::ClientConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .map_err(tls_error)?
+        .with_root_certificates(roots(server_ca)?)
+        .with_client_auth_cert(chain, key)
+        .map_err(tls_error)?;
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    let crypto = QuicClientConfig::try_from(crypto).map_err(tls_error)?;
+    Ok(quinn::ClientConfig::new(Arc::new(crypto)))
+}
+
+/// Transport reaching a [`WorkerServer`] over QUIC
+#[derive(Debug)]
+pub struct QuicTransport {
+    endpoint: quinn::Endpoint,
+    config: quinn::ClientConfig,
+    address: SocketAddr,
+    server_name: String,
+    timeout: Duration,
+    token: Option<String>,
+    max_frame: usize,
+    connection: tokio::sync::Mutex<Option<quinn::Connection>>,
+}
+
+impl QuicTransport {
+    /// Create a transport for a worker at `address`, whose certificate is
+    /// issued for `server_name`
+    ///
+    /// `endpoint` is a client endpoint, which may be shared by the
+    /// transports of all workers.
+    pub fn new(
+        endpoint: quinn::Endpoint,
+        config: quinn::ClientConfig,
+        address: SocketAddr,
+        server_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            endpoint,
+            config,
+            address,
+            server_name: server_name.into(),
+            timeout: Duration::from_secs(5),
+            token: None,
+            max_frame: DEFAULT_MAX_FRAME,
+            connection: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Set timeout for connecting and for heartbeats
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a shared token the worker checks before answering
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Set the size limit of replies, 16 MiB by default
+    pub fn with_max_frame(mut self, bytes: usize) -> Self {
+        self.max_frame = bytes;
+        self
+    }
+
+    /// Get address
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    fn peer(&self) -> String {
+        self.address.to_string()
+    }
+
+    /// Get the open connection, connecting again if it was closed
+    async fn connection(&self) -> Result<quinn::Connection, ClusterError> {
+        let mut cached = self.connection.lock().await;
+        if let Some(connection) = cached.as_ref().filter(|c| c.close_reason().is_none()) {
+            return Ok(connection.clone());
+        }
+        let unreachable = |reason: String| ClusterError::Unreachable {
+            worker: self.peer(),
+            reason,
+        };
+        let connecting = self
+            .endpoint
+            .connect_with(self.config.clone(), self.address, &self.server_name)
+            .map_err(|e| unreachable(e.to_string()))?;
+        let connection = match timeout(self.timeout, connecting).await {
+            Ok(Ok(connection)) => connection,
+            Ok(Err(error)) => return Err(unreachable(error.to_string())),
+            Err(_) => return Err(unreachable("timed out".to_string())),
+        };
+        *cached = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// Send one request on a fresh stream and read its reply
+    async fn exchange(&self, request: Request) -> Result<Reply, ClusterError> {
+        let peer = self.peer();
+        let disconnected = |reason: String| ClusterError::Disconnected {
+            worker: peer.clone(),
+            reason,
+        };
+        let connection = self.connection().await?;
+        let (mut send, mut recv) = connection
+            .open_bi()
+            .await
+            .map_err(|e| disconnected(e.to_string()))?;
+        let envelope = Envelope {
+            token: self.token.clone(),
+            request,
+        };
+        protocol::write_frame(&mut send, &peer, &envelope).await?;
+        send.finish().map_err(|e| disconnected(e.to_string()))?;
+        match protocol::read_frame(&mut recv, &peer, self.max_frame).await? {
+            Reply::Unauthorized => Err(ClusterError::Unauthorized { worker: peer }),
+            reply => Ok(reply),
+        }
+    }
+}
+
+#[async_trait]
+impl WorkerTransport for QuicTransport {
+    async fn execute(&self, task: TaskRecord) -> Result<(), RuntimeError> {
+        match self.exchange(Request::Execute(Box::new(task))).await? {
+            Reply::Failed(error) => Err(RuntimeError::ExecutionFailed(error)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn heartbeat(&self) -> Result<(), RuntimeError> {
+        let reason = match timeout(self.timeout, self.exchange(Request::Heartbeat)).await {
+            Ok(Ok(Reply::Failed(error))) => error,
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(error @ ClusterError::Unauthorized { .. })) => return Err(error.into()),
+            Ok(Err(error)) => error.to_string(),
+            Err(_) => "timed out".to_string(),
+        };
+        Err(ClusterError::HeartbeatFailed {
+            worker: self.peer(),
+            reason,
+        }
+        .into())
+    }
+}
+
+impl<F, Fut> WorkerServer<F>
+where
+    F: Fn(Task) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+{
+    /// Answer requests arriving at a QUIC server `endpoint` until `shutdown`
+    /// is cancelled
+    ///
+    /// Build the endpoint with [`server_config`] so only coordinators with a
+    /// certificate from the cluster's CA get through.
+    pub async fn serve_quic(&self, endpoint: quinn::Endpoint, shutdown: CancellationToken) {
+        let responder = self.responder();
+        loop {
+            let incoming = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                incoming = endpoint.accept() => match incoming {
+                    Some(incoming) => incoming,
+                    None => break,
+                },
+            };
+            let responder = responder.clone();
+            tokio::spawn(async move {
+                let connection = match incoming.await {
+                    Ok(connection) => connection,
+                    Err(error) => {
+                        tracing::warn!("Worker refused a connection: {}", error);
+                        return;
+                    }
+                };
+                let peer = connection.remote_address().to_string();
+                while let Ok((mut send, recv)) = connection.accept_bi().await {
+                    let (responder, peer) = (responder.clone(), peer.clone());
+                    tokio::spawn(async move {
+                        // The coordinator stops the stream when it gives up
+                        let stopped = send.stopped();
+                        let closed = |_| async move {
+                            let _ = stopped.await;
+                        };
+                        match responder.answer(recv, &mut send, &peer, closed).await {
+                            Ok(()) => {
+                                let _ = send.finish();
+                            }
+                            Err(error) => {
+                                tracing::warn!("Worker failed to answer a request: {}", error)
+                            }
+                        }
+                    });
+                }
+            });
+        }
+        endpoint.close(0u32.into(), b"shutdown");
+    }
+}
@@ -0,0 +1,221 @@
+//! Each request is one length-prefixed JSON frame on a fresh connection,
+//! answered by one frame: a task to run or a heartbeat, and whether it
+//! succeeded. Requests carry the cluster's shared token, and frames over the
+//! size limit are refused before they are read.
+
+use super::protocol::{
+    self, Envelope, Fence, Reply, Request, Responder, DEFAULT_FENCE_TIMEOUT, DEFAULT_MAX_FRAME,
+};
+use super::WorkerTransport;
+use crate::scheduler::{Task, TaskRecord};
+use crate::timer::{self, SharedClock};
+use crate::{ClusterError, RuntimeError};
+use async_trait::async_trait;
+use futures::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+/// Transport reaching a [`WorkerServer`] over TCP
+#[derive(Debug, Clone)]
+pub struct TcpTransport {
+    address: String,
+    timeout: Duration,
+    token: Option<String>,
+    max_frame: usize,
+}
+
+impl TcpTransport {
+    /// Create a transport for a worker listening at `address`, e.g. `"10.0.0.2:7400"`
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            timeout: Duration::from_secs(5),
+            token: None,
+            max_frame: DEFAULT_MAX_FRAME,
+        }
+    }
+
+    /// Set timeout for connecting and for heartbeats
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a shared token the worker checks before answering
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Set the size limit of replies, 16 MiB by default
+    pub fn with_max_frame(mut self, bytes: usize) -> Self {
+        self.max_frame = bytes;
+        self
+    }
+
+    /// Get address
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    async fn connect(&self) -> Result<TcpStream, ClusterError> {
+        let reason = match timeout(self.timeout, TcpStream::connect(&self.address)).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(error)) => error.to_string(),
+            Err(_) => "timed out".to_string(),
+        };
+        Err(ClusterError::Unreachable {
+            worker: self.address.clone(),
+            reason,
+        })
+    }
+
+    /// Send one request and read its reply
+    async fn exchange(&self, request: Request) -> Result<Reply, ClusterError> {
+        let mut stream = self.connect().await?;
+        let envelope = Envelope {
+            token: self.token.clone(),
+            request,
+        };
+        protocol::write_frame(&mut stream, &self.address, &envelope).await?;
+        match protocol::read_frame(&mut stream, &self.address, self.max_frame).await? {
+            Reply::Unauthorized => Err(ClusterError::Unauthorized {
+                worker: self.address.clone(),
+            }),
+            reply => Ok(reply),
+        }
+    }
+}
+
+#[async_trait]
+impl WorkerTransport for TcpTransport {
+    async fn execute(&self, task: TaskRecord) -> Result<(), RuntimeError> {
+        match self.exchange(Request::Execute(Box::new(task))).await? {
+            Reply::Failed(error) => Err(RuntimeError::ExecutionFailed(error)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn heartbeat(&self) -> Result<(), RuntimeError> {
+        let reason = match timeout(self.timeout, self.exchange(Request::Heartbeat)).await {
+            Ok(Ok(Reply::Failed(error))) => error,
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(error @ ClusterError::Unauthorized { .. })) => return Err(error.into()),
+            Ok(Err(error)) => error.to_string(),
+            Err(_) => "timed out".to_string(),
+        };
+        Err(ClusterError::HeartbeatFailed {
+            worker: self.address.clone(),
+            reason,
+        }
+        .into())
+    }
+}
+
+/// Server side of [`TcpTransport`], run on each worker machine
+///
+/// A task stops if its coordinator closes the connection, or if no
+/// heartbeat arrives for the fence timeout. Keep the fence timeout no longer
+/// than the coordinator's heartbeat timeout, so a worker cut off from the
+/// coordinator stops its tasks before they are reassigned elsewhere.
+pub struct WorkerServer<F> {
+    execute: Arc<F>,
+    token: Option<String>,
+    max_frame: usize,
+    fence_timeout: Option<Duration>,
+    clock: SharedClock,
+}
+
+impl<F, Fut> WorkerServer<F>
+where
+    F: Fn(Task) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+{
+    /// Create a server running received tasks with `execute`
+    pub fn new(execute: F) -> Self {
+        Self {
+            execute: Arc::new(execute),
+            token: None,
+            max_frame: DEFAULT_MAX_FRAME,
+            fence_timeout: Some(DEFAULT_FENCE_TIMEOUT),
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Answer only requests carrying this shared token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Set the size limit of requests, 16 MiB by default
+    pub fn with_max_frame(mut self, bytes: usize) -> Self {
+        self.max_frame = bytes;
+        self
+    }
+
+    /// Set how long tasks keep running without a heartbeat, 3 seconds by
+    /// default, or `None` to never stop them
+    pub fn with_fence_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.fence_timeout = timeout;
+        self
+    }
+
+    /// Time the fence with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub(super) fn responder(&self) -> Responder<F> {
+        Responder {
+            execute: self.execute.clone(),
+            token: self.token.clone(),
+            max_frame: self.max_frame,
+            fence: Arc::new(Fence::new(self.fence_timeout, self.clock.clone())),
+        }
+    }
+
+    /// Answer requests on `listener` until `shutdown` is cancelled
+    pub async fn serve(&self, listener: TcpListener, shutdown: CancellationToken) {
+        let responder = self.responder();
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(error) => {
+                        tracing::warn!("Worker failed to accept a connection: {}", error);
+                        continue;
+                    }
+                },
+            };
+            let responder = responder.clone();
+            tokio::spawn(async move {
+                let peer = peer.to_string();
+                let (reader, mut writer) = stream.into_split();
+                // The coordinator sends nothing after its request, so a
+                // read returns only once it hangs up
+                let closed = |mut reader: tokio::net::tcp::OwnedReadHalf| async move {
+                    let _ = reader.read(&mut [0; 1]).await;
+                };
+                if let Err(error) = responder.answer(reader, &mut writer, &peer, closed).await {
+                    tracing::warn!("Worker failed to answer a request: {}", error);
+                }
+            });
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for WorkerServer<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkerServer")
+            .field("max_frame", &self.max_frame)
+            .field("fence_timeout", &self.fence_timeout)
+            .finish_non_exhaustive()
+    }
+}
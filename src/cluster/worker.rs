@@ -0,0 +1,136 @@
+use crate::scheduler::{Task, TaskRecord};
+use crate::{ClusterError, RuntimeError};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::Future;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Connection from the coordinator to one worker node
+#[async_trait]
+pub trait WorkerTransport: Send + Sync {
+    /// Run a task on the worker, returning once it finished
+    async fn execute(&self, task: TaskRecord) -> Result<(), RuntimeError>;
+
+    /// Check that the worker is reachable
+    async fn heartbeat(&self) -> Result<(), RuntimeError>;
+}
+
+/// Worker node known to a [`super::Cluster`]
+#[derive(Clone)]
+pub struct WorkerNode {
+    id: String,
+    capacity: usize,
+    transport: Arc<dyn WorkerTransport>,
+}
+
+impl WorkerNode {
+    /// Create a worker running up to `capacity` tasks at once
+    pub fn new(
+        id: impl Into<String>,
+        capacity: usize,
+        transport: Arc<dyn WorkerTransport>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            capacity: capacity.max(1),
+            transport,
+        }
+    }
+
+    /// Get ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get number of tasks the worker runs at once
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get transport
+    pub fn transport(&self) -> &Arc<dyn WorkerTransport> {
+        &self.transport
+    }
+}
+
+impl fmt::Debug for WorkerNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerNode")
+            .field("id", &self.id)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+type Execute = dyn Fn(Task) -> BoxFuture<'static, Result<(), RuntimeError>> + Send + Sync;
+
+/// Transport running tasks in the coordinator's process
+///
+/// Useful for single-machine setups and tests; [`InProcessTransport::disconnect`]
+/// makes the worker unreachable, as if its machine went away.
+#[derive(Clone)]
+pub struct InProcessTransport {
+    execute: Arc<Execute>,
+    connected: Arc<AtomicBool>,
+}
+
+impl InProcessTransport {
+    /// Create a transport running tasks with `execute`
+    pub fn new<F, Fut>(execute: F) -> Self
+    where
+        F: Fn(Task) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+    {
+        Self {
+            execute: Arc::new(move |task| Box::pin(execute(task))),
+            connected: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Stop answering; tasks sent from now on never finish
+    pub fn disconnect(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+    }
+
+    /// Answer again
+    pub fn reconnect(&self) {
+        self.connected.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if connected
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl WorkerTransport for InProcessTransport {
+    async fn execute(&self, task: TaskRecord) -> Result<(), RuntimeError> {
+        if !self.is_connected() {
+            return std::future::pending().await;
+        }
+        (self.execute)(task.into_task()).await
+    }
+
+    async fn heartbeat(&self) -> Result<(), RuntimeError> {
+        if self.is_connected() {
+            Ok(())
+        } else {
+            Err(ClusterError::HeartbeatFailed {
+                worker: "in-process".to_string(),
+                reason: "disconnected".to_string(),
+            }
+            .into())
+        }
+    }
+}
+
+impl fmt::Debug for InProcessTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InProcessTransport")
+            .field("connected", &self.is_connected())
+            .finish()
+    }
+}
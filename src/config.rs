@@ -1,7 +1,177 @@
+//! Runtime configuration
+//!
+//! [`RuntimeConfig`] can be built in code or loaded from a TOML or YAML
+//! file with [`RuntimeConfig::load`]. A loaded configuration is validated
+//! before use. [`Runtime::apply_config`](crate::Runtime::apply_config)
+//! diffs a new configuration against the running one and applies the
+//! changes live; a [`ConfigWatcher`] notices when the file changes.
+
+use crate::executor::PoolConfig;
+#[cfg(feature = "scheduler")]
+use crate::scheduler::{OverflowPolicy, PolicyType, Scheduler, SchedulingPolicy};
+#[cfg(feature = "supervisor")]
+use crate::supervisor::{ProbeConfig, RestartPolicy};
+use crate::timer::{self, SharedClock};
+use crate::RuntimeError;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "supervisor")]
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Engine tuning profile
+///
+/// | Profile      | Batch | Wakeup              | Time slice | Trade-off                         |
+/// |--------------|-------|---------------------|------------|-----------------------------------|
+/// | `Latency`    | 1     | immediate           | 10 ms      | lowest queue wait, most wakeups   |
+/// | `Balanced`   | 16    | coalesced over 1 ms | 50 ms      | default                           |
+/// | `Throughput` | 128   | coalesced over 5 ms | 200 ms     | fewest wakeups, higher tail wait  |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EngineProfile {
+    /// Dispatch each task as soon as possible
+    Latency,
+    /// Middle ground between latency and throughput
+    #[default]
+    Balanced,
+    /// Amortize scheduling overhead over large batches
+    Throughput,
+}
+
+impl EngineProfile {
+    /// Get the tuning knobs for this profile
+    pub fn tuning(&self) -> EngineTuning {
+        match self {
+            EngineProfile::Latency => EngineTuning {
+                batch_size: 1,
+                wakeup: WakeupStrategy::Immediate,
+                time_slice: Duration::from_millis(10),
+            },
+            EngineProfile::Balanced => EngineTuning {
+                batch_size: 16,
+                wakeup: WakeupStrategy::Coalesced(Duration::from_millis(1)),
+                time_slice: Duration::from_millis(50),
+            },
+            EngineProfile::Throughput => EngineTuning {
+                batch_size: 128,
+                wakeup: WakeupStrategy::Coalesced(Duration::from_millis(5)),
+                time_slice: Duration::from_millis(200),
+            },
+        }
+    }
+}
+
+/// How the engine wakes up after new work arrives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WakeupStrategy {
+    /// Wake on every submission
+    Immediate,
+    /// Wait out the window for more work before dispatching
+    Coalesced(Duration),
+}
+
+/// Low-level engine knobs derived from an [`EngineProfile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineTuning {
+    /// Maximum tasks taken from the queue per dispatch round
+    pub batch_size: usize,
+    /// Wakeup strategy
+    pub wakeup: WakeupStrategy,
+    /// Run time after which a task becomes eligible for preemption
+    pub time_slice: Duration,
+}
+
+impl Default for EngineTuning {
+    fn default() -> Self {
+        EngineProfile::default().tuning()
+    }
+}
+
+/// Scheduler settings of a [`RuntimeConfig`]
+#[cfg(feature = "scheduler")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// Built-in scheduling policy
+    pub policy: PolicyType,
+    /// Maximum number of queued tasks, unbounded if unset
+    pub queue_capacity: Option<usize>,
+    /// What happens to tasks submitted while the queue is full
+    pub overflow: OverflowPolicy,
+}
+
+#[cfg(feature = "scheduler")]
+impl SchedulerConfig {
+    /// Build a scheduler with these settings
+    pub fn build(&self) -> Scheduler {
+        let scheduler = Scheduler::new(SchedulingPolicy::new(self.policy));
+        match self.queue_capacity {
+            Some(capacity) => scheduler.with_queue_capacity(capacity, self.overflow),
+            None => scheduler,
+        }
+    }
+
+    fn bound(&self) -> Option<(usize, OverflowPolicy)> {
+        self.queue_capacity
+            .map(|capacity| (capacity, self.overflow))
+    }
+}
+
+#[cfg(feature = "scheduler")]
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            policy: PolicyType::FCFS,
+            queue_capacity: None,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+/// Difference between two configurations, as applied by
+/// [`Runtime::apply_config`](crate::Runtime::apply_config)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigChange {
+    /// Engine tuning profile changed
+    Profile {
+        /// Previous profile
+        from: EngineProfile,
+        /// New profile
+        to: EngineProfile,
+    },
+    /// Scheduling policy changed
+    #[cfg(feature = "scheduler")]
+    Policy {
+        /// Previous policy
+        from: PolicyType,
+        /// New policy
+        to: PolicyType,
+    },
+    /// Queue capacity or overflow policy changed
+    #[cfg(feature = "scheduler")]
+    QueueCapacity {
+        /// Previous bound
+        from: Option<(usize, OverflowPolicy)>,
+        /// New bound
+        to: Option<(usize, OverflowPolicy)>,
+    },
+    /// An agent's restart policy was added, changed, or removed
+    #[cfg(feature = "supervisor")]
+    RestartPolicy {
+        /// Agent name
+        agent: String,
+    },
+    /// A setting that only takes effect when the runtime is rebuilt changed
+    RequiresRestart {
+        /// Setting name
+        field: &'static str,
+    },
+}
 
 /// Runtime configuration
+///
+/// Settings missing from a configuration file take their default values.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RuntimeConfig {
     /// Maximum number of worker threads
     pub max_workers: usize,
@@ -14,6 +184,31 @@ pub struct RuntimeConfig {
 
     /// Default timeout in milliseconds
     pub default_timeout_ms: u64,
+
+    /// Health probes keyed by agent name
+    #[cfg(feature = "supervisor")]
+    #[serde(default)]
+    pub health_probes: HashMap<String, ProbeConfig>,
+
+    /// Engine tuning profile
+    #[serde(default)]
+    pub profile: EngineProfile,
+
+    /// Executor pools with optional CPU affinity
+    #[serde(default)]
+    pub executor_pools: Vec<PoolConfig>,
+
+    /// Scheduler settings
+    #[cfg(feature = "scheduler")]
+    pub scheduler: SchedulerConfig,
+
+    /// Restart policies keyed by agent name
+    #[cfg(feature = "supervisor")]
+    pub restart_policies: HashMap<String, RestartPolicy>,
+
+    /// Interval between health checks in milliseconds
+    #[cfg(feature = "supervisor")]
+    pub health_interval_ms: u64,
 }
 
 impl RuntimeConfig {
@@ -24,9 +219,160 @@ impl RuntimeConfig {
             enable_metrics: true,
             enable_tracing: true,
             default_timeout_ms: 30000,
+            #[cfg(feature = "supervisor")]
+            health_probes: HashMap::new(),
+            profile: EngineProfile::default(),
+            executor_pools: Vec::new(),
+            #[cfg(feature = "scheduler")]
+            scheduler: SchedulerConfig::default(),
+            #[cfg(feature = "supervisor")]
+            restart_policies: HashMap::new(),
+            #[cfg(feature = "supervisor")]
+            health_interval_ms: 10_000,
+        }
+    }
+
+    /// Load and validate a configuration file, picking the format by extension
+    ///
+    /// `.toml` files need the `config-toml` feature and `.yaml` or `.yml`
+    /// files the `config-yaml` feature.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RuntimeError> {
+        let path = path.as_ref();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "config-toml")]
+            Some("toml") => Self::from_toml(&read_config(path)?),
+            #[cfg(feature = "config-yaml")]
+            Some("yaml" | "yml") => Self::from_yaml(&read_config(path)?),
+            _ => Err(RuntimeError::ConfigError(format!(
+                "unsupported configuration file {}",
+                path.display()
+            ))),
         }
     }
 
+    /// Parse and validate a TOML configuration
+    #[cfg(feature = "config-toml")]
+    pub fn from_toml(text: &str) -> Result<Self, RuntimeError> {
+        let config: Self =
+            toml::from_str(text).map_err(|error| RuntimeError::ConfigError(error.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse and validate a YAML configuration
+    #[cfg(feature = "config-yaml")]
+    pub fn from_yaml(text: &str) -> Result<Self, RuntimeError> {
+        let config: Self = serde_yaml::from_str(text)
+            .map_err(|error| RuntimeError::ConfigError(error.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that settings are in range
+    pub fn validate(&self) -> Result<(), RuntimeError> {
+        let invalid = |message: &str| Err(RuntimeError::ConfigError(message.to_string()));
+        if self.max_workers == 0 {
+            return invalid("max_workers must be at least 1");
+        }
+        if self.default_timeout_ms == 0 {
+            return invalid("default_timeout_ms must be positive");
+        }
+        #[cfg(feature = "scheduler")]
+        if self.scheduler.queue_capacity == Some(0) {
+            return invalid("scheduler.queue_capacity must be at least 1");
+        }
+        #[cfg(feature = "supervisor")]
+        if self.health_interval_ms == 0 {
+            return invalid("health_interval_ms must be positive");
+        }
+        Ok(())
+    }
+
+    /// List the changes that turn this configuration into `new`
+    pub fn diff(&self, new: &RuntimeConfig) -> Vec<ConfigChange> {
+        let mut changes = Vec::new();
+        if self.profile != new.profile {
+            changes.push(ConfigChange::Profile {
+                from: self.profile,
+                to: new.profile,
+            });
+        }
+        #[cfg(feature = "scheduler")]
+        {
+            if self.scheduler.policy != new.scheduler.policy {
+                changes.push(ConfigChange::Policy {
+                    from: self.scheduler.policy,
+                    to: new.scheduler.policy,
+                });
+            }
+            if self.scheduler.bound() != new.scheduler.bound() {
+                changes.push(ConfigChange::QueueCapacity {
+                    from: self.scheduler.bound(),
+                    to: new.scheduler.bound(),
+                });
+            }
+        }
+        #[cfg(feature = "supervisor")]
+        {
+            let mut agents: Vec<&String> = self
+                .restart_policies
+                .keys()
+                .chain(new.restart_policies.keys())
+                .collect();
+            agents.sort();
+            agents.dedup();
+            for agent in agents {
+                if self.restart_policies.get(agent) != new.restart_policies.get(agent) {
+                    changes.push(ConfigChange::RestartPolicy {
+                        agent: agent.clone(),
+                    });
+                }
+            }
+            if self.health_interval_ms != new.health_interval_ms {
+                changes.push(ConfigChange::RequiresRestart {
+                    field: "health_interval_ms",
+                });
+            }
+            if self.health_probes != new.health_probes {
+                changes.push(ConfigChange::RequiresRestart {
+                    field: "health_probes",
+                });
+            }
+        }
+        if self.default_timeout_ms != new.default_timeout_ms {
+            changes.push(ConfigChange::RequiresRestart {
+                field: "default_timeout_ms",
+            });
+        }
+        if self.max_workers != new.max_workers {
+            changes.push(ConfigChange::RequiresRestart {
+                field: "max_workers",
+            });
+        }
+        if self.enable_metrics != new.enable_metrics {
+            changes.push(ConfigChange::RequiresRestart {
+                field: "enable_metrics",
+            });
+        }
+        if self.enable_tracing != new.enable_tracing {
+            changes.push(ConfigChange::RequiresRestart {
+                field: "enable_tracing",
+            });
+        }
+        if self.executor_pools != new.executor_pools {
+            changes.push(ConfigChange::RequiresRestart {
+                field: "executor_pools",
+            });
+        }
+        changes
+    }
+
+    /// Get the interval between health checks
+    #[cfg(feature = "supervisor")]
+    pub fn health_interval(&self) -> Duration {
+        Duration::from_millis(self.health_interval_ms)
+    }
+
     /// Set maximum workers
     pub fn with_max_workers(mut self, workers: usize) -> Self {
         self.max_workers = workers;
@@ -50,6 +396,30 @@ impl RuntimeConfig {
         self.default_timeout_ms = timeout_ms;
         self
     }
+
+    /// Set engine tuning profile
+    pub fn with_profile(mut self, profile: EngineProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Get engine tuning derived from the profile
+    pub fn tuning(&self) -> EngineTuning {
+        self.profile.tuning()
+    }
+
+    /// Add an executor pool
+    pub fn with_executor_pool(mut self, pool: PoolConfig) -> Self {
+        self.executor_pools.push(pool);
+        self
+    }
+
+    /// Add a health probe for the named agent
+    #[cfg(feature = "supervisor")]
+    pub fn with_health_probe(mut self, agent_name: impl Into<String>, probe: ProbeConfig) -> Self {
+        self.health_probes.insert(agent_name.into(), probe);
+        self
+    }
 }
 
 impl Default for RuntimeConfig {
@@ -57,3 +427,72 @@ impl Default for RuntimeConfig {
         Self::new()
     }
 }
+
+#[cfg(any(feature = "config-toml", feature = "config-yaml"))]
+fn read_config(path: &Path) -> Result<String, RuntimeError> {
+    std::fs::read_to_string(path).map_err(|error| {
+        RuntimeError::ConfigError(format!("reading {}: {}", path.display(), error))
+    })
+}
+
+/// Polls a configuration file for changes
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    interval: Duration,
+    clock: SharedClock,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    /// Watch a file, checking it every second
+    ///
+    /// Only changes after this call are reported.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            modified: modified_at(&path),
+            path,
+            interval: Duration::from_secs(1),
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Set how often the file is checked
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Time checks with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the watched path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait until the file changes, then load it
+    ///
+    /// A change that fails to load or validate is returned as an error; the
+    /// next call waits for a further change.
+    pub async fn changed(&mut self) -> Result<RuntimeConfig, RuntimeError> {
+        loop {
+            self.clock.sleep(self.interval).await;
+            let modified = modified_at(&self.path);
+            if modified.is_some() && modified != self.modified {
+                self.modified = modified;
+                return RuntimeConfig::load(&self.path);
+            }
+        }
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -1,7 +1,18 @@
+use crate::lifecycle::LifecycleState;
+#[cfg(feature = "supervisor")]
+use crate::supervisor::BudgetReport;
+use agentropic_core::AgentId;
+use std::time::Duration;
 use thiserror::Error;
 
+pub use agentropic_runtime_core::ErrorClass;
+
 /// Runtime errors
+///
+/// Scheduler, supervisor, and queue failures are nested in their own types.
+/// Every error has a stable [`RuntimeError::code`] for programmatic handling.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum RuntimeError {
     #[error("Agent not found: {0}")]
     AgentNotFound(String),
@@ -24,6 +35,309 @@ pub enum RuntimeError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
 
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Circuit open: {0}")]
+    CircuitOpen(String),
+
+    #[cfg(feature = "supervisor")]
+    #[error("Deadline exceeded: {0}")]
+    DeadlineExceeded(BudgetReport),
+
+    #[error("Memory limit exceeded: {used} of {limit} bytes")]
+    MemoryLimitExceeded { used: u64, limit: u64 },
+
+    #[error("Payload too large: {size} bytes exceeds limit of {limit}")]
+    PayloadTooLarge { size: usize, limit: usize },
+
+    #[error("Resource exhausted: {0}")]
+    ResourceExhausted(String),
+
+    #[error("Agent {agent_id} cannot move from {from:?} to {to:?}")]
+    InvalidTransition {
+        agent_id: AgentId,
+        from: Option<LifecycleState>,
+        to: LifecycleState,
+    },
+
+    #[error(transparent)]
+    Scheduler(#[from] SchedulerError),
+
+    #[error(transparent)]
+    Supervisor(#[from] SupervisorError),
+
+    #[error(transparent)]
+    Queue(#[from] QueueError),
+
+    #[error(transparent)]
+    Cluster(#[from] ClusterError),
+
     #[error("Runtime error: {0}")]
     Other(String),
 }
+
+impl RuntimeError {
+    /// Get a stable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::AgentNotFound(_) => "agent_not_found",
+            RuntimeError::SpawnFailed(_) => "spawn_failed",
+            RuntimeError::ExecutionFailed(_) => "execution_failed",
+            RuntimeError::SchedulingError(_) => "scheduling_error",
+            RuntimeError::SupervisionError(_) => "supervision_error",
+            RuntimeError::IsolationError(_) => "isolation_error",
+            RuntimeError::ConfigError(_) => "config_error",
+            RuntimeError::RateLimited { .. } => "rate_limited",
+            RuntimeError::CircuitOpen(_) => "circuit_open",
+            #[cfg(feature = "supervisor")]
+            RuntimeError::DeadlineExceeded(_) => "deadline_exceeded",
+            RuntimeError::MemoryLimitExceeded { .. } => "memory_limit_exceeded",
+            RuntimeError::PayloadTooLarge { .. } => "payload_too_large",
+            RuntimeError::ResourceExhausted(_) => "resource_exhausted",
+            RuntimeError::InvalidTransition { .. } => "invalid_transition",
+            RuntimeError::Scheduler(error) => error.code(),
+            RuntimeError::Supervisor(error) => error.code(),
+            RuntimeError::Queue(error) => error.code(),
+            RuntimeError::Cluster(error) => error.code(),
+            RuntimeError::Other(_) => "other",
+        }
+    }
+}
+
+/// Scheduler errors
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SchedulerError {
+    #[error("No agent compatible with version {requirement} for {agent}")]
+    NoCompatibleAgent { agent: String, requirement: String },
+
+    #[error("Budget exceeded for {agent}: spent {spent} of {limit}")]
+    BudgetExceeded {
+        agent: String,
+        spent: u64,
+        limit: u64,
+    },
+
+    #[error("CPU budget of {budget:?} exceeded after {used:?}")]
+    CpuBudgetExceeded { used: Duration, budget: Duration },
+
+    #[error("Task exceeded its maximum run time of {max_runtime:?}")]
+    MaxRuntimeExceeded { max_runtime: Duration },
+
+    #[error("Scheduler is draining and accepts no new tasks")]
+    Draining,
+
+    #[error("Task was preempted by a higher-priority task")]
+    Preempted,
+
+    #[error("Another replica of the task succeeded first")]
+    SpeculationLost,
+
+    #[error("Task {task} would wait for itself through its dependencies")]
+    DependencyCycle { task: String },
+
+    #[error("Lease for term {term} is no longer held")]
+    NotLeader { term: u64 },
+
+    #[error("Scheduler state error: {0}")]
+    State(String),
+}
+
+impl SchedulerError {
+    /// Get a stable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            SchedulerError::NoCompatibleAgent { .. } => "scheduler.no_compatible_agent",
+            SchedulerError::BudgetExceeded { .. } => "scheduler.budget_exceeded",
+            SchedulerError::CpuBudgetExceeded { .. } => "scheduler.cpu_budget_exceeded",
+            SchedulerError::MaxRuntimeExceeded { .. } => "scheduler.max_runtime_exceeded",
+            SchedulerError::Draining => "scheduler.draining",
+            SchedulerError::Preempted => "scheduler.preempted",
+            SchedulerError::SpeculationLost => "scheduler.speculation_lost",
+            SchedulerError::DependencyCycle { .. } => "scheduler.dependency_cycle",
+            SchedulerError::NotLeader { .. } => "scheduler.not_leader",
+            SchedulerError::State(_) => "scheduler.state",
+        }
+    }
+}
+
+/// Supervisor errors
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SupervisorError {
+    #[error("Child not supervised: {0}")]
+    ChildNotFound(String),
+
+    #[error("Agent not reloadable: {0}")]
+    NotReloadable(String),
+
+    #[error("Failed to load behavior: {0}")]
+    LoadFailed(String),
+
+    #[error("Supervisor state error: {0}")]
+    State(String),
+
+    #[error("Watchdog of agent {agent} expired after {interval:?} without a pet")]
+    WatchdogExpired { agent: String, interval: Duration },
+
+    #[error("Bulkhead {group} is full with {max_waiting} callers waiting")]
+    BulkheadFull { group: String, max_waiting: usize },
+
+    #[error("Gave up waiting for bulkhead {group} after {waited:?}")]
+    BulkheadTimeout { group: String, waited: Duration },
+
+    #[error("Child {0} was killed")]
+    Killed(String),
+}
+
+impl SupervisorError {
+    /// Get a stable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            SupervisorError::ChildNotFound(_) => "supervisor.child_not_found",
+            SupervisorError::NotReloadable(_) => "supervisor.not_reloadable",
+            SupervisorError::LoadFailed(_) => "supervisor.load_failed",
+            SupervisorError::State(_) => "supervisor.state",
+            SupervisorError::WatchdogExpired { .. } => "supervisor.watchdog_expired",
+            SupervisorError::BulkheadFull { .. } => "supervisor.bulkhead_full",
+            SupervisorError::BulkheadTimeout { .. } => "supervisor.bulkhead_timeout",
+            SupervisorError::Killed(_) => "supervisor.killed",
+        }
+    }
+}
+
+/// Task queue errors
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum QueueError {
+    #[error("Priority {priority} queue quota of {capacity} reached")]
+    PriorityFull { priority: u32, capacity: usize },
+
+    #[error("Task queue is full at {capacity} tasks")]
+    Full { capacity: usize },
+
+    #[error("Mailbox of agent {agent_id} is full at {capacity} messages")]
+    MailboxFull { agent_id: AgentId, capacity: usize },
+
+    #[error("Queue storage error: {0}")]
+    Storage(String),
+
+    #[error("Invalid bus event: {0}")]
+    InvalidEvent(String),
+}
+
+impl QueueError {
+    /// Get a stable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            QueueError::PriorityFull { .. } => "queue.priority_full",
+            QueueError::Full { .. } => "queue.full",
+            QueueError::MailboxFull { .. } => "queue.mailbox_full",
+            QueueError::Storage(_) => "queue.storage",
+            QueueError::InvalidEvent(_) => "queue.invalid_event",
+        }
+    }
+}
+
+/// Errors talking to cluster worker nodes
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ClusterError {
+    #[error("Worker {worker} unreachable: {reason}")]
+    Unreachable { worker: String, reason: String },
+
+    #[error("Worker {worker} dropped the request: {reason}")]
+    Disconnected { worker: String, reason: String },
+
+    #[error("Worker {worker} missed its heartbeat: {reason}")]
+    HeartbeatFailed { worker: String, reason: String },
+
+    #[error("Worker {worker} rejected the coordinator's credentials")]
+    Unauthorized { worker: String },
+
+    #[error("Frame of {size} bytes exceeds limit of {limit}")]
+    FrameTooLarge { size: usize, limit: usize },
+
+    #[error("Malformed cluster message: {0}")]
+    Malformed(String),
+
+    #[error("Invalid TLS configuration: {0}")]
+    Tls(String),
+}
+
+impl ClusterError {
+    /// Get a stable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            ClusterError::Unreachable { .. } => "cluster.unreachable",
+            ClusterError::Disconnected { .. } => "cluster.disconnected",
+            ClusterError::HeartbeatFailed { .. } => "cluster.heartbeat_failed",
+            ClusterError::Unauthorized { .. } => "cluster.unauthorized",
+            ClusterError::FrameTooLarge { .. } => "cluster.frame_too_large",
+            ClusterError::Malformed(_) => "cluster.malformed",
+            ClusterError::Tls(_) => "cluster.tls",
+        }
+    }
+}
+
+/// Classifies errors into [`ErrorClass`]es
+pub trait ErrorClassifier: Send + Sync {
+    /// Classify an error
+    fn classify(&self, error: &RuntimeError) -> ErrorClass;
+}
+
+/// Default classifier based on the `RuntimeError` variant
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultClassifier;
+
+impl ErrorClassifier for DefaultClassifier {
+    fn classify(&self, error: &RuntimeError) -> ErrorClass {
+        match error {
+            RuntimeError::AgentNotFound(_)
+            | RuntimeError::ConfigError(_)
+            | RuntimeError::PayloadTooLarge { .. }
+            | RuntimeError::InvalidTransition { .. }
+            | RuntimeError::Cluster(
+                ClusterError::Unauthorized { .. }
+                | ClusterError::FrameTooLarge { .. }
+                | ClusterError::Tls(_),
+            )
+            | RuntimeError::Supervisor(
+                SupervisorError::ChildNotFound(_) | SupervisorError::NotReloadable(_),
+            ) => ErrorClass::Permanent,
+            RuntimeError::RateLimited { retry_after, .. } => ErrorClass::RateLimited {
+                retry_after: *retry_after,
+            },
+            RuntimeError::ResourceExhausted(_)
+            | RuntimeError::Queue(
+                QueueError::PriorityFull { .. } | QueueError::MailboxFull { .. },
+            )
+            | RuntimeError::Supervisor(
+                SupervisorError::BulkheadFull { .. } | SupervisorError::BulkheadTimeout { .. },
+            )
+            | RuntimeError::Scheduler(
+                SchedulerError::BudgetExceeded { .. } | SchedulerError::CpuBudgetExceeded { .. },
+            ) => ErrorClass::ResourceExhausted,
+            RuntimeError::MemoryLimitExceeded { .. } => ErrorClass::OutOfMemory,
+            #[cfg(feature = "supervisor")]
+            RuntimeError::DeadlineExceeded(_) => ErrorClass::DeadlineExceeded,
+            _ => ErrorClass::Transient,
+        }
+    }
+}
+
+/// Extract a readable message from a panic payload
+#[cfg(any(feature = "scheduler", feature = "supervisor"))]
+pub(crate) fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// Set of CPU cores
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuSet {
+    cpus: Vec<usize>,
+}
+
+impl CpuSet {
+    /// Create a CPU set from core indices
+    pub fn new(cpus: impl IntoIterator<Item = usize>) -> Self {
+        let mut cpus: Vec<usize> = cpus.into_iter().collect();
+        cpus.sort_unstable();
+        cpus.dedup();
+        Self { cpus }
+    }
+
+    /// Parse a Linux CPU list such as `0-3,8,10-11`
+    ///
+    /// Returns `None` for malformed lists, including reversed ranges like `3-1`.
+    pub fn parse(list: &str) -> Option<Self> {
+        let mut cpus = Vec::new();
+        for part in list.trim().split(',').filter(|part| !part.is_empty()) {
+            match part.split_once('-') {
+                Some((start, end)) => {
+                    let start: usize = start.trim().parse().ok()?;
+                    let end: usize = end.trim().parse().ok()?;
+                    if start > end {
+                        return None;
+                    }
+                    cpus.extend(start..=end);
+                }
+                None => cpus.push(part.trim().parse().ok()?),
+            }
+        }
+        Some(Self::new(cpus))
+    }
+
+    /// Get core indices
+    pub fn cpus(&self) -> &[usize] {
+        &self.cpus
+    }
+
+    /// Check if the set contains a core
+    pub fn contains(&self, cpu: usize) -> bool {
+        self.cpus.binary_search(&cpu).is_ok()
+    }
+
+    /// Check if the sets share a core
+    pub fn intersects(&self, other: &CpuSet) -> bool {
+        self.cpus.iter().any(|cpu| other.contains(*cpu))
+    }
+
+    /// Get cores in this set but not in `other`
+    pub fn difference(&self, other: &CpuSet) -> CpuSet {
+        CpuSet::new(
+            self.cpus
+                .iter()
+                .copied()
+                .filter(|cpu| !other.contains(*cpu)),
+        )
+    }
+
+    /// Get cores in either set
+    pub fn union(&self, other: &CpuSet) -> CpuSet {
+        CpuSet::new(self.cpus.iter().chain(other.cpus()).copied())
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_empty()
+    }
+
+    /// Get number of cores
+    pub fn len(&self) -> usize {
+        self.cpus.len()
+    }
+}
+
+/// Pin the calling thread to a CPU set
+///
+/// Returns `false` if pinning is unsupported on this platform or failed.
+pub fn pin_current_thread(cpus: &CpuSet) -> bool {
+    if cpus.is_empty() {
+        return false;
+    }
+    imp::pin_current_thread(cpus)
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::CpuSet;
+
+    pub(super) fn pin_current_thread(cpus: &CpuSet) -> bool {
+        // SAFETY: cpu_set_t is plain data; CPU_ZERO/CPU_SET only write inside it
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_ZERO(&mut set);
+            for &cpu in cpus.cpus() {
+                if cpu < libc::CPU_SETSIZE as usize {
+                    libc::CPU_SET(cpu, &mut set);
+                }
+            }
+            libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::CpuSet;
+
+    pub(super) fn pin_current_thread(_cpus: &CpuSet) -> bool {
+        false
+    }
+}
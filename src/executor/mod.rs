@@ -1,3 +1,23 @@
+//! Task execution and worker placement
+
+/// CPU affinity
+pub mod affinity;
+/// NUMA topology
+pub mod numa;
+/// Executor pools
+pub mod pool;
+/// WebAssembly agent sandbox
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use affinity::{pin_current_thread, CpuSet};
+pub use numa::{NumaNode, NumaTopology};
+pub use pool::PoolConfig;
+#[cfg(feature = "executor")]
+pub use pool::{ExecutorPool, PoolSet};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmExecutor;
+
 use futures::Future;
 use tokio::task::JoinHandle;
 
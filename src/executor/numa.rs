@@ -0,0 +1,96 @@
+use super::CpuSet;
+
+/// NUMA node and its CPU cores
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaNode {
+    id: usize,
+    cpus: CpuSet,
+}
+
+impl NumaNode {
+    /// Create a new NUMA node
+    pub fn new(id: usize, cpus: CpuSet) -> Self {
+        Self { id, cpus }
+    }
+
+    /// Get node ID
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Get CPU cores of the node
+    pub fn cpus(&self) -> &CpuSet {
+        &self.cpus
+    }
+}
+
+/// NUMA topology of the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaTopology {
+    nodes: Vec<NumaNode>,
+}
+
+impl NumaTopology {
+    /// Create a topology from nodes
+    pub fn new(nodes: Vec<NumaNode>) -> Self {
+        Self { nodes }
+    }
+
+    /// Single-node topology spanning all CPUs
+    pub fn single_node() -> Self {
+        Self::new(vec![NumaNode::new(0, CpuSet::new(0..num_cpus::get()))])
+    }
+
+    /// Detect the host topology, falling back to a single node
+    pub fn detect() -> Self {
+        Self::from_sysfs("/sys/devices/system/node").unwrap_or_else(Self::single_node)
+    }
+
+    /// Read the topology from a Linux sysfs node directory
+    fn from_sysfs(root: &str) -> Option<Self> {
+        let mut nodes = Vec::new();
+        for entry in std::fs::read_dir(root).ok()?.flatten() {
+            let name = entry.file_name();
+            let Some(id) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("node"))
+                .and_then(|id| id.parse().ok())
+            else {
+                continue;
+            };
+            let list = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            let cpus = CpuSet::parse(&list)?;
+            if !cpus.is_empty() {
+                nodes.push(NumaNode::new(id, cpus));
+            }
+        }
+
+        nodes.sort_by_key(NumaNode::id);
+        (!nodes.is_empty()).then(|| Self::new(nodes))
+    }
+
+    /// Get nodes
+    pub fn nodes(&self) -> &[NumaNode] {
+        &self.nodes
+    }
+
+    /// Get node by ID
+    pub fn node(&self, id: usize) -> Option<&NumaNode> {
+        self.nodes.iter().find(|node| node.id() == id)
+    }
+
+    /// Get node owning a CPU core
+    pub fn node_of_cpu(&self, cpu: usize) -> Option<&NumaNode> {
+        self.nodes.iter().find(|node| node.cpus().contains(cpu))
+    }
+
+    /// Get number of nodes
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
@@ -0,0 +1,264 @@
+use super::CpuSet;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "executor")]
+use {
+    super::{pin_current_thread, NumaTopology},
+    crate::RuntimeError,
+    futures::Future,
+    tokio::runtime::{Builder, Handle, Runtime},
+    tokio::task::JoinHandle,
+};
+
+/// Executor pool configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// Pool name, used for thread names
+    pub name: String,
+    /// Number of worker threads
+    pub workers: usize,
+    /// NUMA node to pin the workers to
+    pub numa_node: Option<usize>,
+    /// Explicit CPU cores to pin the workers to, overriding the NUMA node
+    pub cpus: Option<CpuSet>,
+    /// Reserve the pinned cores for this pool alone
+    #[serde(default)]
+    pub exclusive: bool,
+}
+
+impl PoolConfig {
+    /// Create a new pool configuration
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            workers: 1,
+            numa_node: None,
+            cpus: None,
+            exclusive: false,
+        }
+    }
+
+    /// Set number of worker threads
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Pin workers to a NUMA node
+    pub fn with_numa_node(mut self, node: usize) -> Self {
+        self.numa_node = Some(node);
+        self
+    }
+
+    /// Pin workers to CPU cores
+    pub fn with_cpus(mut self, cpus: CpuSet) -> Self {
+        self.cpus = Some(cpus);
+        self
+    }
+
+    /// Reserve the pinned cores, keeping other pools off them
+    pub fn with_exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+}
+
+#[cfg(feature = "executor")]
+/// Pool of worker threads, optionally pinned to CPU cores
+pub struct ExecutorPool {
+    name: String,
+    numa_node: Option<usize>,
+    cpus: Option<CpuSet>,
+    runtime: Option<Runtime>,
+}
+
+#[cfg(feature = "executor")]
+impl ExecutorPool {
+    /// Start a pool
+    pub fn start(config: PoolConfig) -> Result<Self, RuntimeError> {
+        Self::start_with_topology(config, &NumaTopology::detect())
+    }
+
+    /// Start a pool, resolving NUMA nodes against the given topology
+    pub fn start_with_topology(
+        config: PoolConfig,
+        topology: &NumaTopology,
+    ) -> Result<Self, RuntimeError> {
+        let cpus = match (&config.cpus, config.numa_node) {
+            (Some(cpus), _) => Some(cpus.clone()),
+            (None, Some(node)) => Some(
+                topology
+                    .node(node)
+                    .ok_or_else(|| {
+                        RuntimeError::ConfigError(format!("Unknown NUMA node {}", node))
+                    })?
+                    .cpus()
+                    .clone(),
+            ),
+            (None, None) => None,
+        };
+
+        let mut builder = Builder::new_multi_thread();
+        builder
+            .worker_threads(config.workers.max(1))
+            .thread_name(config.name.clone())
+            .enable_all();
+        if let Some(cpus) = cpus.clone() {
+            builder.on_thread_start(move || {
+                if !pin_current_thread(&cpus) {
+                    tracing::debug!("CPU pinning unavailable for executor thread");
+                }
+            });
+        }
+
+        let runtime = builder
+            .build()
+            .map_err(|error| RuntimeError::SpawnFailed(error.to_string()))?;
+
+        Ok(Self {
+            name: config.name,
+            numa_node: config.numa_node,
+            cpus,
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Spawn a task on the pool
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.handle().spawn(future)
+    }
+
+    /// Get handle to the pool's runtime
+    pub fn handle(&self) -> &Handle {
+        self.runtime
+            .as_ref()
+            .expect("executor pool runtime is present until drop")
+            .handle()
+    }
+
+    /// Get pool name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get NUMA node
+    pub fn numa_node(&self) -> Option<usize> {
+        self.numa_node
+    }
+
+    /// Get pinned CPU cores
+    pub fn cpus(&self) -> Option<&CpuSet> {
+        self.cpus.as_ref()
+    }
+}
+
+#[cfg(feature = "executor")]
+/// Set of executor pools sharing the host's cores
+///
+/// Exclusive pools (e.g. for interactive voice agents) get their cores to
+/// themselves; every other pool is pinned to the remaining shared cores, and
+/// starting fails if exclusive pools reserved all of its cores.
+pub struct PoolSet {
+    pools: Vec<ExecutorPool>,
+    reserved: CpuSet,
+    shared: CpuSet,
+}
+
+#[cfg(feature = "executor")]
+impl PoolSet {
+    /// Start all configured pools
+    pub fn start(configs: Vec<PoolConfig>) -> Result<Self, RuntimeError> {
+        Self::start_with_topology(configs, &NumaTopology::detect())
+    }
+
+    /// Start all configured pools against the given topology
+    pub fn start_with_topology(
+        configs: Vec<PoolConfig>,
+        topology: &NumaTopology,
+    ) -> Result<Self, RuntimeError> {
+        let mut reserved = CpuSet::default();
+        for config in configs.iter().filter(|config| config.exclusive) {
+            let cpus = config.cpus.as_ref().ok_or_else(|| {
+                RuntimeError::ConfigError(format!("Exclusive pool {} has no CPU set", config.name))
+            })?;
+            if cpus.intersects(&reserved) {
+                return Err(RuntimeError::ConfigError(format!(
+                    "Exclusive pool {} overlaps another exclusive pool",
+                    config.name
+                )));
+            }
+            reserved = reserved.union(cpus);
+        }
+
+        let all = topology
+            .nodes()
+            .iter()
+            .fold(CpuSet::default(), |all, node| all.union(node.cpus()));
+        let shared = all.difference(&reserved);
+
+        let pools = configs
+            .into_iter()
+            .map(|mut config| {
+                if !config.exclusive {
+                    let base = match (&config.cpus, config.numa_node) {
+                        (Some(cpus), _) => cpus.clone(),
+                        (None, Some(node)) => topology
+                            .node(node)
+                            .map_or_else(|| all.clone(), |node| node.cpus().clone()),
+                        (None, None) => all.clone(),
+                    };
+                    let cpus = base.difference(&reserved);
+                    if cpus.is_empty() && !base.is_empty() {
+                        return Err(RuntimeError::ConfigError(format!(
+                            "All CPUs of pool {} are reserved by exclusive pools",
+                            config.name
+                        )));
+                    }
+                    config.cpus = Some(cpus);
+                }
+                ExecutorPool::start_with_topology(config, topology)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            pools,
+            reserved,
+            shared,
+        })
+    }
+
+    /// Get pool by name
+    pub fn get(&self, name: &str) -> Option<&ExecutorPool> {
+        self.pools.iter().find(|pool| pool.name() == name)
+    }
+
+    /// Get all pools
+    pub fn pools(&self) -> &[ExecutorPool] {
+        &self.pools
+    }
+
+    /// Get cores reserved by exclusive pools
+    pub fn reserved_cpus(&self) -> &CpuSet {
+        &self.reserved
+    }
+
+    /// Get cores available to the rest of the runtime
+    ///
+    /// Pin other runtime threads here with [`super::pin_current_thread`].
+    pub fn shared_cpus(&self) -> &CpuSet {
+        &self.shared
+    }
+}
+
+#[cfg(feature = "executor")]
+impl Drop for ExecutorPool {
+    fn drop(&mut self) {
+        // Safe to call from within another async runtime
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
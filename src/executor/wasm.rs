@@ -0,0 +1,370 @@
+//! WebAssembly agent sandbox
+//!
+//! A [`WasmExecutor`] runs agent task handlers compiled to WebAssembly with
+//! wasmtime. Every task gets a fresh instance with no imports, so untrusted
+//! agent code shares no memory with the host or other tasks and cannot reach
+//! the network, filesystem, or clock.
+//!
+//! A module exports `memory`, `alloc(len: i32) -> i32`, and
+//! `handle(ptr: i32, len: i32) -> i64`. The task's payload is copied into
+//! memory returned by `alloc`, then `handle` returns the output's address in
+//! its high 32 bits and its length in the low 32 bits, or a negative code on
+//! failure.
+//!
+//! Tasks burn fuel as they execute. A task that runs out fails with
+//! [`RuntimeError::ResourceExhausted`]. Execution yields after every fuel
+//! slice, so a [`CpuBudget`](crate::scheduler::CpuBudget) is checked and a
+//! task whose [`PreemptionHandle`](crate::scheduler::PreemptionHandle) is
+//! signalled stops at the next slice. A preempted task's instance is kept
+//! suspended and resumes where it stopped when the scheduler runs the task
+//! again. An agent's [`ResourceQuota`] sets the fuel and memory each of its
+//! tasks may use, and other agents get the executor's defaults. Outputs are
+//! read only within the instance's memory and are held to the output limit,
+//! by default the scheduler's result limit. An executor given the scheduler
+//! with [`WasmExecutor::with_scheduler`] reports the fuel each task burned to
+//! [`Scheduler::record_fuel`], counting it against the quota's fuel budget.
+
+use crate::limits::{OversizePolicy, SizeLimit};
+use crate::resources::ResourceQuota;
+use crate::scheduler::{Scheduler, Task, TaskId};
+use crate::{RuntimeError, SchedulerError};
+use agentropic_core::AgentId;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use wasmtime::{
+    Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder, Trap, TypedFunc,
+};
+
+/// Default fuel a task may burn
+const DEFAULT_FUEL: u64 = 1_000_000_000;
+
+/// Default memory a task's instance may grow to
+const DEFAULT_MEMORY: usize = 64 * 1024 * 1024;
+
+/// Default size of a task's output
+const DEFAULT_OUTPUT_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Default fuel burned between checks for preemption
+const DEFAULT_FUEL_SLICE: u64 = 100_000;
+
+/// Exports every module must provide
+const EXPORTS: [&str; 3] = ["memory", "alloc", "handle"];
+
+/// Instance run that resolves once its handler returns
+type Run = BoxFuture<'static, Finished>;
+
+/// Outcome of an instance run and the fuel it burned
+struct Finished {
+    outcome: wasmtime::Result<Result<Vec<u8>, i64>>,
+    fuel: u64,
+    burned: u64,
+}
+
+/// Executor running task handlers compiled to WebAssembly
+pub struct WasmExecutor {
+    engine: Engine,
+    modules: HashMap<AgentId, Module>,
+    quotas: HashMap<AgentId, ResourceQuota>,
+    fuel: u64,
+    memory: usize,
+    output_limit: SizeLimit,
+    fuel_slice: u64,
+    fuel_used: Arc<Mutex<HashMap<AgentId, u64>>>,
+    suspended: Arc<Mutex<HashMap<TaskId, Run>>>,
+    scheduler: Option<Arc<tokio::sync::Mutex<Scheduler>>>,
+}
+
+impl WasmExecutor {
+    /// Create an executor with no modules loaded
+    pub fn new() -> Result<Self, RuntimeError> {
+        let mut config = Config::new();
+        config.async_support(true).consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| {
+            RuntimeError::IsolationError(format!("failed to create wasm engine: {}", e))
+        })?;
+        Ok(Self {
+            engine,
+            modules: HashMap::new(),
+            quotas: HashMap::new(),
+            fuel: DEFAULT_FUEL,
+            memory: DEFAULT_MEMORY,
+            output_limit: SizeLimit::new(DEFAULT_OUTPUT_LIMIT),
+            fuel_slice: DEFAULT_FUEL_SLICE,
+            fuel_used: Arc::new(Mutex::new(HashMap::new())),
+            suspended: Arc::new(Mutex::new(HashMap::new())),
+            scheduler: None,
+        })
+    }
+
+    /// Report fuel burned by each task to the scheduler running the tasks
+    ///
+    /// Outputs are held to the scheduler's result limit, if it has one.
+    pub fn with_scheduler(mut self, scheduler: Arc<tokio::sync::Mutex<Scheduler>>) -> Self {
+        if let Some(limit) = scheduler
+            .try_lock()
+            .ok()
+            .and_then(|scheduler| scheduler.result_limit().copied())
+        {
+            self.output_limit = limit;
+        }
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Set memory a task's instance may grow to, unless its agent's quota
+    /// sets its own
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory = bytes;
+        self
+    }
+
+    /// Limit the size of task outputs
+    pub fn with_output_limit(mut self, limit: SizeLimit) -> Self {
+        self.output_limit = limit;
+        self
+    }
+
+    /// Set fuel a task may burn, unless its agent's quota sets its own
+    pub fn with_fuel_limit(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Set fuel burned between checks for preemption
+    pub fn with_fuel_slice(mut self, fuel: u64) -> Self {
+        self.fuel_slice = fuel.max(1);
+        self
+    }
+
+    /// Limit fuel and memory of an agent's tasks
+    pub fn with_resource_quota(mut self, agent_id: AgentId, quota: ResourceQuota) -> Self {
+        self.set_resource_quota(agent_id, quota);
+        self
+    }
+
+    /// Set an agent's resource quota
+    ///
+    /// Only the fuel and memory limits apply; the scheduler enforces the
+    /// others.
+    pub fn set_resource_quota(&mut self, agent_id: AgentId, quota: ResourceQuota) {
+        self.quotas.insert(agent_id, quota);
+    }
+
+    /// Compile an agent's module from WebAssembly bytes, replacing any
+    /// loaded before
+    pub fn load(&mut self, agent_id: AgentId, wasm: impl AsRef<[u8]>) -> Result<(), RuntimeError> {
+        let module = Module::new(&self.engine, wasm).map_err(|e| {
+            RuntimeError::IsolationError(format!("invalid module for agent {}: {}", agent_id, e))
+        })?;
+        if let Some(missing) = EXPORTS
+            .iter()
+            .find(|name| module.get_export(name).is_none())
+        {
+            return Err(RuntimeError::IsolationError(format!(
+                "module for agent {} does not export {}",
+                agent_id, missing
+            )));
+        }
+        self.modules.insert(agent_id, module);
+        Ok(())
+    }
+
+    /// Check if an agent has a module loaded
+    pub fn is_loaded(&self, agent_id: &AgentId) -> bool {
+        self.modules.contains_key(agent_id)
+    }
+
+    /// Get fuel an agent's tasks burned so far
+    pub fn fuel_used(&self, agent_id: &AgentId) -> u64 {
+        self.lock_fuel_used().get(agent_id).copied().unwrap_or(0)
+    }
+
+    /// Check if a preempted task is suspended, waiting to resume
+    pub fn is_suspended(&self, task_id: &TaskId) -> bool {
+        lock(&self.suspended).contains_key(task_id)
+    }
+
+    /// Drop the suspended instance of a preempted task that will not run again
+    pub fn discard(&self, task_id: &TaskId) -> bool {
+        lock(&self.suspended).remove(task_id).is_some()
+    }
+
+    /// Get fuel a task of an agent may burn
+    pub fn fuel_limit(&self, agent_id: &AgentId) -> u64 {
+        self.quotas
+            .get(agent_id)
+            .and_then(ResourceQuota::max_fuel)
+            .unwrap_or(self.fuel)
+    }
+
+    /// Get memory a task of an agent may grow to
+    pub fn memory_limit(&self, agent_id: &AgentId) -> usize {
+        self.quotas
+            .get(agent_id)
+            .and_then(ResourceQuota::max_memory)
+            .map_or(self.memory, |bytes| {
+                usize::try_from(bytes).unwrap_or(usize::MAX)
+            })
+    }
+
+    /// Run a task with its agent's module, resolving to the task's output
+    ///
+    /// A task preempted earlier resumes its suspended instance. The fuel it
+    /// burned is recorded once it finishes. Pass this to
+    /// [`Scheduler::run_with_results`] to run tasks in the sandbox.
+    pub fn execute(&self, task: &Task) -> BoxFuture<'static, Result<Vec<u8>, RuntimeError>> {
+        let agent_id = *task.agent_id();
+        let task_id = task.id();
+        let resumed = lock(&self.suspended).remove(&task_id);
+        let mut run = match resumed {
+            Some(run) => run,
+            None => match self.start(task) {
+                Ok(run) => run,
+                Err(error) => return Box::pin(async move { Err(error) }),
+            },
+        };
+        let preemption = task.preemption().cloned();
+        let suspended = self.suspended.clone();
+        let fuel_used = self.fuel_used.clone();
+        let scheduler = self.scheduler.clone();
+        Box::pin(async move {
+            let finished = match preemption {
+                Some(preemption) => tokio::select! {
+                    finished = &mut run => finished,
+                    _ = preemption.preempted() => {
+                        tracing::debug!("Suspending preempted wasm task of agent {}", agent_id);
+                        lock(&suspended).insert(task_id, run);
+                        return Err(SchedulerError::Preempted.into());
+                    }
+                },
+                None => run.await,
+            };
+            *lock(&fuel_used).entry(agent_id).or_default() += finished.burned;
+            if let Some(scheduler) = scheduler {
+                scheduler
+                    .lock()
+                    .await
+                    .record_fuel(agent_id, finished.burned);
+            }
+            match finished.outcome {
+                Ok(Ok(output)) => Ok(output),
+                Ok(Err(code)) => Err(RuntimeError::ExecutionFailed(format!(
+                    "agent {} failed with code {}",
+                    agent_id, code
+                ))),
+                Err(error) => Err(wasm_error(agent_id, finished.fuel, error)),
+            }
+        })
+    }
+
+    /// Instantiate a task's module in a fresh store
+    fn start(&self, task: &Task) -> Result<Run, RuntimeError> {
+        let agent_id = *task.agent_id();
+        let Some(module) = self.modules.get(&agent_id).cloned() else {
+            return Err(RuntimeError::IsolationError(format!(
+                "no module loaded for agent {}",
+                agent_id
+            )));
+        };
+        let limits = StoreLimitsBuilder::new()
+            .trap_on_grow_failure(true)
+            .memory_size(self.memory_limit(&agent_id));
+        let fuel = self.fuel_limit(&agent_id);
+        let mut store = Store::new(&self.engine, limits.build());
+        store.limiter(|limits| limits);
+        store
+            .set_fuel(fuel)
+            .and_then(|()| store.fuel_async_yield_interval(Some(self.fuel_slice)))
+            .map_err(|e| wasm_error(agent_id, fuel, e))?;
+        let payload = task.payload().to_vec();
+        let output_limit = self.output_limit;
+        Ok(Box::pin(async move {
+            let outcome = run(&mut store, &module, &payload, output_limit).await;
+            let left = store.get_fuel().unwrap_or(fuel);
+            Finished {
+                outcome,
+                fuel,
+                burned: fuel.saturating_sub(left),
+            }
+        }))
+    }
+
+    fn lock_fuel_used(&self) -> MutexGuard<'_, HashMap<AgentId, u64>> {
+        lock(&self.fuel_used)
+    }
+}
+
+impl std::fmt::Debug for WasmExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmExecutor")
+            .field("modules", &self.modules.len())
+            .field("fuel", &self.fuel)
+            .field("fuel_slice", &self.fuel_slice)
+            .field("suspended", &lock(&self.suspended).len())
+            .finish()
+    }
+}
+
+/// Instantiate a module and hand it a payload, returning its output or
+/// failure code
+///
+/// An output over a truncating limit is read one byte past the limit, so the
+/// scheduler truncates and flags it.
+async fn run(
+    store: &mut Store<StoreLimits>,
+    module: &Module,
+    payload: &[u8],
+    output_limit: SizeLimit,
+) -> wasmtime::Result<Result<Vec<u8>, i64>> {
+    let instance = Instance::new_async(&mut *store, module, &[]).await?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| wasmtime::Error::msg("module does not export memory"))?;
+    let alloc: TypedFunc<i32, i32> = instance.get_typed_func(&mut *store, "alloc")?;
+    let handle: TypedFunc<(i32, i32), i64> = instance.get_typed_func(&mut *store, "handle")?;
+
+    let len = i32::try_from(payload.len())?;
+    let ptr = alloc.call_async(&mut *store, len).await?;
+    memory.write(&mut *store, u32::try_from(ptr)? as usize, payload)?;
+    let packed = handle.call_async(&mut *store, (ptr, len)).await?;
+    if packed < 0 {
+        return Ok(Err(packed));
+    }
+    let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+    let output = memory
+        .data(&*store)
+        .get(ptr..ptr.saturating_add(len))
+        .ok_or_else(|| wasmtime::Error::msg("output lies outside the module's memory"))?;
+    let kept = match output_limit.policy() {
+        OversizePolicy::Reject if len > output_limit.max_bytes() => {
+            return Err(wasmtime::Error::new(RuntimeError::PayloadTooLarge {
+                size: len,
+                limit: output_limit.max_bytes(),
+            }));
+        }
+        OversizePolicy::Reject => len,
+        OversizePolicy::Truncate => len.min(output_limit.max_bytes().saturating_add(1)),
+    };
+    Ok(Ok(output[..kept].to_vec()))
+}
+
+fn wasm_error(agent_id: AgentId, fuel: u64, error: wasmtime::Error) -> RuntimeError {
+    let error = match error.downcast::<RuntimeError>() {
+        Ok(error) => return error,
+        Err(error) => error,
+    };
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => RuntimeError::ResourceExhausted(format!(
+            "agent {} ran out of fuel after {} units",
+            agent_id, fuel
+        )),
+        _ => RuntimeError::ExecutionFailed(format!("agent {} trapped: {:#}", agent_id, error)),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,71 @@
+//! Minimal HTTP/1.1 client for pushing JSON to collectors and webhooks
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Split an `http://` URL into (host:port, host, path)
+pub(crate) fn target(url: &str) -> Option<(String, String, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return None;
+    }
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    Some((address, authority.to_string(), path.to_string()))
+}
+
+/// POST a JSON body, succeeding on a 2xx status
+pub(crate) async fn post_json(
+    url: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> Result<(), String> {
+    let (address, host, path) = target(url).ok_or_else(|| format!("unsupported URL: {}", url))?;
+    let mut stream = TcpStream::connect(address)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        path,
+        host,
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    stream.write_all(body).await.map_err(|e| e.to_string())?;
+
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 256];
+    while !buffer.windows(2).any(|window| window == b"\r\n") {
+        let read = stream.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let status_line = String::from_utf8_lossy(&buffer);
+    match status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+    {
+        Some(status) if (200..300).contains(&status) => Ok(()),
+        Some(status) => Err(format!("status {}", status)),
+        None => Err("malformed response".into()),
+    }
+}
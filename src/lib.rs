@@ -3,22 +3,84 @@
 //#![warn(missing_docs)]
 #![allow(missing_docs)]
 
+#[cfg(feature = "admin")]
+pub mod admin;
+#[cfg(any(feature = "scheduler", feature = "supervisor"))]
+pub mod blocking;
+pub mod budget;
+pub mod builder;
+#[cfg(feature = "bus")]
+pub mod bus;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+#[cfg(feature = "cluster")]
+pub mod cluster;
 pub mod config;
 pub mod error;
 pub mod executor;
 pub mod handle;
+#[cfg(any(feature = "webhooks", feature = "otel"))]
+mod http;
+#[cfg(feature = "isolation")]
 pub mod isolation;
+pub mod lifecycle;
+pub mod limits;
+pub mod link;
+pub mod mailbox;
+#[cfg(feature = "metrics")]
 pub mod metrics;
+pub mod notify;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod profiling;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod registry;
+pub mod resources;
+#[cfg(any(feature = "scheduler", feature = "chaos", feature = "otel"))]
+mod rng;
 pub mod runtime;
+#[cfg(all(feature = "sandbox", unix))]
+pub mod sandbox;
+#[cfg(feature = "scheduler")]
 pub mod scheduler;
+pub mod sim;
+#[cfg(feature = "supervisor")]
 pub mod supervisor;
+pub mod timer;
+#[cfg(feature = "tracing")]
 pub mod tracing;
 
 /// Prelude for convenient imports
 pub mod prelude;
 
 // Re-exports
-pub use config::RuntimeConfig;
-pub use error::RuntimeError;
+pub use agentropic_runtime_core::HealthStatus;
+pub use budget::BudgetUsage;
+pub use builder::RuntimeBuilder;
+#[cfg(feature = "scheduler")]
+pub use builder::TaskExecutor;
+#[cfg(feature = "bus")]
+pub use bus::{DeliveryMode, Event, EventBus, TopicConfig};
+#[cfg(feature = "chaos")]
+pub use chaos::{Chaos, ChaosConfig, ChaosStats};
+#[cfg(feature = "scheduler")]
+pub use config::SchedulerConfig;
+pub use config::{
+    ConfigChange, ConfigWatcher, EngineProfile, EngineTuning, RuntimeConfig, WakeupStrategy,
+};
+pub use error::{
+    ClusterError, DefaultClassifier, ErrorClass, ErrorClassifier, QueueError, RuntimeError,
+    SchedulerError, SupervisorError,
+};
 pub use handle::RuntimeHandle;
-pub use runtime::Runtime;
+pub use lifecycle::{AgentLifecycle, LifecycleHooks, LifecycleState, LifecycleTransition};
+pub use limits::{OversizePolicy, SizeLimit};
+pub use link::{DownEffects, ExitReason, LinkTable, MonitorRef, PeerSignal};
+pub use mailbox::{Backpressure, MailboxConfig, Mailboxes, Message};
+pub use registry::{AgentInfo, Registry, RegistryFilter};
+pub use resources::{QuotaUsage, QuotaViolation, ResourceQuota};
+pub use runtime::{Runtime, ShutdownReport};
+pub use sim::SimRuntime;
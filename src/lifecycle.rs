@@ -0,0 +1,242 @@
+//! Agent lifecycle state machine
+//!
+//! An [`AgentLifecycle`] tracks where each agent is between starting and
+//! stopping and runs [`LifecycleHooks`] on the way. The scheduler only
+//! dispatches to running agents and the supervisor does not restart agents
+//! that were stopped on purpose, so both stay consistent with the lifecycle.
+
+use crate::RuntimeError;
+use agentropic_core::AgentId;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Lifecycle state of an agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LifecycleState {
+    /// Start hooks are running
+    Starting,
+    /// Agent is running and accepts tasks
+    Running,
+    /// Suspend hooks are running
+    Suspending,
+    /// Agent is paused and can be resumed
+    Suspended,
+    /// Stop hooks are running
+    Stopping,
+    /// Agent was stopped
+    Stopped,
+    /// Agent or one of its hooks failed
+    Failed,
+}
+
+impl LifecycleState {
+    /// Check if the state may move to `next`
+    pub fn can_transition_to(self, next: LifecycleState) -> bool {
+        use LifecycleState::*;
+        matches!(
+            (self, next),
+            (Starting, Running | Stopping)
+                | (Running, Suspending | Stopping)
+                | (Suspending, Suspended)
+                | (Suspended, Starting | Stopping)
+                | (Stopping, Stopped)
+                | (Stopped | Failed, Starting | Stopping)
+                | (_, Failed)
+        )
+    }
+
+    /// Check if the agent is stopping or stopped
+    pub fn is_stopped(self) -> bool {
+        matches!(self, LifecycleState::Stopping | LifecycleState::Stopped)
+    }
+}
+
+/// Callbacks run as agents move through their lifecycle
+///
+/// Every hook defaults to doing nothing. A hook error moves the agent to
+/// [`LifecycleState::Failed`].
+#[async_trait]
+pub trait LifecycleHooks: Send + Sync {
+    /// Run before the agent is running, on start, restart, and resume
+    async fn on_start(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Run before the agent is stopped
+    async fn on_stop(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Run before the agent is suspended
+    async fn on_suspend(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+/// Change of an agent's lifecycle state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifecycleTransition {
+    /// Agent that changed state
+    pub agent_id: AgentId,
+    /// Previous state, `None` for an agent's first start
+    pub from: Option<LifecycleState>,
+    /// New state
+    pub to: LifecycleState,
+}
+
+type Observer = Arc<dyn Fn(&LifecycleTransition) + Send + Sync>;
+
+/// Lifecycle states of all agents, shared by the scheduler and supervisor
+#[derive(Default)]
+pub struct AgentLifecycle {
+    states: Mutex<HashMap<AgentId, LifecycleState>>,
+    hooks: Vec<Arc<dyn LifecycleHooks>>,
+    observers: Mutex<Vec<Observer>>,
+}
+
+impl AgentLifecycle {
+    /// Create a lifecycle without hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run hooks on every transition, after hooks added earlier
+    pub fn with_hooks(mut self, hooks: impl LifecycleHooks + 'static) -> Self {
+        self.hooks.push(Arc::new(hooks));
+        self
+    }
+
+    /// Call `observer` after every state change
+    pub fn on_transition(&self, observer: impl Fn(&LifecycleTransition) + Send + Sync + 'static) {
+        self.lock_observers().push(Arc::new(observer));
+    }
+
+    /// Get an agent's state, `None` if it never started
+    pub fn state(&self, agent_id: &AgentId) -> Option<LifecycleState> {
+        self.lock_states().get(agent_id).copied()
+    }
+
+    /// Get the state of every known agent
+    pub fn states(&self) -> Vec<(AgentId, LifecycleState)> {
+        self.lock_states()
+            .iter()
+            .map(|(agent_id, state)| (*agent_id, *state))
+            .collect()
+    }
+
+    /// Check if tasks may be dispatched to an agent
+    ///
+    /// Agents that never started are schedulable, so a lifecycle only
+    /// restricts the agents it tracks.
+    pub fn is_schedulable(&self, agent_id: &AgentId) -> bool {
+        self.state(agent_id)
+            .is_none_or(|state| state == LifecycleState::Running)
+    }
+
+    /// Start or restart an agent, running `on_start` hooks
+    pub async fn start(&self, agent_id: AgentId) -> Result<(), RuntimeError> {
+        self.transition(agent_id, LifecycleState::Starting)?;
+        for hooks in &self.hooks {
+            self.or_fail(agent_id, hooks.on_start(agent_id).await)?;
+        }
+        self.transition(agent_id, LifecycleState::Running)
+    }
+
+    /// Resume a suspended agent, running `on_start` hooks
+    pub async fn resume(&self, agent_id: AgentId) -> Result<(), RuntimeError> {
+        let from = self.state(&agent_id);
+        if from != Some(LifecycleState::Suspended) {
+            return Err(RuntimeError::InvalidTransition {
+                agent_id,
+                from,
+                to: LifecycleState::Starting,
+            });
+        }
+        self.start(agent_id).await
+    }
+
+    /// Suspend a running agent, running `on_suspend` hooks
+    pub async fn suspend(&self, agent_id: AgentId) -> Result<(), RuntimeError> {
+        self.transition(agent_id, LifecycleState::Suspending)?;
+        for hooks in &self.hooks {
+            self.or_fail(agent_id, hooks.on_suspend(agent_id).await)?;
+        }
+        self.transition(agent_id, LifecycleState::Suspended)
+    }
+
+    /// Stop an agent, running `on_stop` hooks
+    pub async fn stop(&self, agent_id: AgentId) -> Result<(), RuntimeError> {
+        self.transition(agent_id, LifecycleState::Stopping)?;
+        for hooks in &self.hooks {
+            self.or_fail(agent_id, hooks.on_stop(agent_id).await)?;
+        }
+        self.transition(agent_id, LifecycleState::Stopped)
+    }
+
+    /// Mark an agent as failed
+    pub fn fail(&self, agent_id: AgentId) {
+        let _ = self.transition(agent_id, LifecycleState::Failed);
+    }
+
+    /// Forget an agent, such as after it was removed from the runtime
+    pub fn remove(&self, agent_id: &AgentId) {
+        self.lock_states().remove(agent_id);
+    }
+
+    fn or_fail(
+        &self,
+        agent_id: AgentId,
+        result: Result<(), RuntimeError>,
+    ) -> Result<(), RuntimeError> {
+        if result.is_err() {
+            self.fail(agent_id);
+        }
+        result
+    }
+
+    fn transition(&self, agent_id: AgentId, to: LifecycleState) -> Result<(), RuntimeError> {
+        let from = {
+            let mut states = self.lock_states();
+            let from = states.get(&agent_id).copied();
+            let allowed = match from {
+                Some(from) => from.can_transition_to(to),
+                None => matches!(to, LifecycleState::Starting | LifecycleState::Failed),
+            };
+            if !allowed {
+                return Err(RuntimeError::InvalidTransition { agent_id, from, to });
+            }
+            states.insert(agent_id, to);
+            from
+        };
+        tracing::debug!("Agent {} lifecycle {:?} -> {:?}", agent_id, from, to);
+        let transition = LifecycleTransition { agent_id, from, to };
+        let observers = self.lock_observers().clone();
+        for observer in observers {
+            observer(&transition);
+        }
+        Ok(())
+    }
+
+    fn lock_states(&self) -> MutexGuard<'_, HashMap<AgentId, LifecycleState>> {
+        self.states
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_observers(&self) -> MutexGuard<'_, Vec<Observer>> {
+        self.observers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for AgentLifecycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentLifecycle")
+            .field("states", &*self.lock_states())
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
@@ -0,0 +1,70 @@
+//! Size limits for data flowing through the runtime
+//!
+//! A [`SizeLimit`] bounds task payloads and results so one agent producing a
+//! huge blob cannot exhaust memory in queues, transport, or persistence. The
+//! scheduler enforces its limits on submitted tasks, results, and tasks
+//! restored from a checkpoint; [`Mailboxes`](crate::mailbox::Mailboxes)
+//! enforce theirs on delivered messages.
+
+use crate::RuntimeError;
+use serde::{Deserialize, Serialize};
+
+/// What to do with data over a [`SizeLimit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OversizePolicy {
+    /// Fail with [`RuntimeError::PayloadTooLarge`]
+    Reject,
+    /// Keep the first `max_bytes` bytes and flag the data as truncated
+    Truncate,
+}
+
+/// Maximum size of a payload or result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeLimit {
+    max_bytes: usize,
+    policy: OversizePolicy,
+}
+
+impl SizeLimit {
+    /// Create a limit that rejects oversized data
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            policy: OversizePolicy::Reject,
+        }
+    }
+
+    /// Truncate oversized data instead of rejecting it
+    pub fn truncate(mut self) -> Self {
+        self.policy = OversizePolicy::Truncate;
+        self
+    }
+
+    /// Get maximum size in bytes
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Get policy for oversized data
+    pub fn policy(&self) -> OversizePolicy {
+        self.policy
+    }
+
+    /// Enforce the limit on `data`, returning whether it was truncated
+    pub fn apply(&self, data: &mut Vec<u8>) -> Result<bool, RuntimeError> {
+        if data.len() <= self.max_bytes {
+            return Ok(false);
+        }
+        match self.policy {
+            OversizePolicy::Reject => Err(RuntimeError::PayloadTooLarge {
+                size: data.len(),
+                limit: self.max_bytes,
+            }),
+            OversizePolicy::Truncate => {
+                data.truncate(self.max_bytes);
+                data.shrink_to_fit();
+                Ok(true)
+            }
+        }
+    }
+}
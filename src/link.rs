@@ -0,0 +1,183 @@
+//! Links and monitors between agents
+//!
+//! Linked agents share fate: when one dies abnormally, its peers are
+//! terminated too, unless they trap exits and receive a
+//! [`PeerSignal::Exit`] instead. A monitor only observes: the watcher gets a
+//! [`PeerSignal::Down`] when the target dies, for any reason. Both work
+//! independently of supervision.
+
+use agentropic_core::AgentId;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Why an agent stopped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitReason {
+    /// The agent finished its work
+    Normal,
+    /// The agent failed
+    Failed(String),
+    /// The agent was stopped from outside
+    Killed,
+    /// The agent was terminated because a linked peer died
+    Linked(AgentId),
+    /// The agent did not exist when it was monitored
+    NoAgent,
+}
+
+impl ExitReason {
+    /// Check if the exit terminates linked peers
+    pub fn is_abnormal(&self) -> bool {
+        *self != ExitReason::Normal
+    }
+}
+
+/// Reference identifying a monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MonitorRef(u64);
+
+/// Signal delivered to an agent about a peer's death
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerSignal {
+    /// A monitored agent stopped
+    Down {
+        /// Monitor that fired
+        monitor: MonitorRef,
+        /// Agent that stopped
+        agent_id: AgentId,
+        /// Why it stopped
+        reason: ExitReason,
+    },
+    /// A linked agent stopped while the receiver traps exits
+    Exit {
+        /// Agent that stopped
+        from: AgentId,
+        /// Why it stopped
+        reason: ExitReason,
+    },
+}
+
+/// Consequences of an agent's death
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownEffects {
+    /// Signals to deliver, by receiving agent
+    pub signals: Vec<(AgentId, PeerSignal)>,
+    /// Linked agents terminated as a result, in cascade order
+    pub terminated: Vec<AgentId>,
+}
+
+/// Links and monitors between agents
+#[derive(Debug, Default)]
+pub struct LinkTable {
+    links: HashMap<AgentId, HashSet<AgentId>>,
+    monitors: HashMap<AgentId, Vec<(MonitorRef, AgentId)>>,
+    trapping: HashSet<AgentId>,
+    next_ref: u64,
+}
+
+impl LinkTable {
+    /// Create an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Link two agents so that either dying abnormally terminates the other
+    pub fn link(&mut self, a: AgentId, b: AgentId) {
+        if a == b {
+            return;
+        }
+        self.links.entry(a).or_default().insert(b);
+        self.links.entry(b).or_default().insert(a);
+    }
+
+    /// Remove a link
+    pub fn unlink(&mut self, a: &AgentId, b: &AgentId) {
+        if let Some(peers) = self.links.get_mut(a) {
+            peers.remove(b);
+        }
+        if let Some(peers) = self.links.get_mut(b) {
+            peers.remove(a);
+        }
+    }
+
+    /// Check if two agents are linked
+    pub fn is_linked(&self, a: &AgentId, b: &AgentId) -> bool {
+        self.links.get(a).is_some_and(|peers| peers.contains(b))
+    }
+
+    /// Notify `watcher` when `target` dies
+    pub fn monitor(&mut self, watcher: AgentId, target: AgentId) -> MonitorRef {
+        self.next_ref += 1;
+        let monitor = MonitorRef(self.next_ref);
+        self.monitors
+            .entry(target)
+            .or_default()
+            .push((monitor, watcher));
+        monitor
+    }
+
+    /// Remove a monitor
+    pub fn demonitor(&mut self, monitor: MonitorRef) {
+        for watchers in self.monitors.values_mut() {
+            watchers.retain(|(existing, _)| *existing != monitor);
+        }
+    }
+
+    /// Deliver exits from linked peers to an agent as signals instead of terminating it
+    pub fn trap_exits(&mut self, agent_id: AgentId, trap: bool) {
+        if trap {
+            self.trapping.insert(agent_id);
+        } else {
+            self.trapping.remove(&agent_id);
+        }
+    }
+
+    /// Record an agent's death, cascading through links
+    pub fn agent_down(&mut self, agent_id: AgentId, reason: ExitReason) -> DownEffects {
+        let mut effects = DownEffects::default();
+        let mut dead = HashSet::new();
+        let mut pending = VecDeque::from([(agent_id, reason)]);
+
+        while let Some((agent_id, reason)) = pending.pop_front() {
+            if !dead.insert(agent_id) {
+                continue;
+            }
+            self.trapping.remove(&agent_id);
+            for watchers in self.monitors.values_mut() {
+                watchers.retain(|(_, watcher)| *watcher != agent_id);
+            }
+            for (monitor, watcher) in self.monitors.remove(&agent_id).unwrap_or_default() {
+                effects.signals.push((
+                    watcher,
+                    PeerSignal::Down {
+                        monitor,
+                        agent_id,
+                        reason: reason.clone(),
+                    },
+                ));
+            }
+
+            for peer in self.links.remove(&agent_id).unwrap_or_default() {
+                if let Some(peers) = self.links.get_mut(&peer) {
+                    peers.remove(&agent_id);
+                }
+                if dead.contains(&peer) {
+                    continue;
+                }
+                if self.trapping.contains(&peer) {
+                    effects.signals.push((
+                        peer,
+                        PeerSignal::Exit {
+                            from: agent_id,
+                            reason: reason.clone(),
+                        },
+                    ));
+                } else if reason.is_abnormal() {
+                    effects.terminated.push(peer);
+                    pending.push_back((peer, ExitReason::Linked(agent_id)));
+                }
+            }
+        }
+        effects
+    }
+}
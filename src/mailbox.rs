@@ -0,0 +1,371 @@
+//! Per-agent mailboxes
+//!
+//! Agents exchange [`Message`]s through [`Mailboxes`], one mailbox per
+//! [`AgentId`]. A bounded mailbox applies its [`Backpressure`] when full.
+//! A scheduler built with `Scheduler::with_mailboxes` queues a task for an
+//! agent whenever mail arrives in its empty mailbox; the task drains the
+//! mailbox with [`Mailboxes::try_recv`]. A payload limit set with
+//! [`Mailboxes::with_payload_limit`] rejects or truncates oversized messages.
+
+use crate::{QueueError, RuntimeError, SizeLimit};
+use agentropic_core::AgentId;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+/// Message sent to an agent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    from: Option<AgentId>,
+    to: AgentId,
+    payload: Vec<u8>,
+    truncated: bool,
+}
+
+impl Message {
+    /// Create a message for an agent
+    pub fn new(to: AgentId, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            from: None,
+            to,
+            payload: payload.into(),
+            truncated: false,
+        }
+    }
+
+    /// Set sending agent
+    pub fn with_sender(mut self, from: AgentId) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Get sending agent
+    pub fn sender(&self) -> Option<&AgentId> {
+        self.from.as_ref()
+    }
+
+    /// Get receiving agent
+    pub fn recipient(&self) -> &AgentId {
+        &self.to
+    }
+
+    /// Get payload
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Take payload
+    pub fn into_payload(self) -> Vec<u8> {
+        self.payload
+    }
+
+    /// Check if the payload was truncated by the mailboxes' size limit
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// What sending to a full mailbox does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    /// [`Mailboxes::send`] waits for room; [`Mailboxes::try_send`] fails
+    #[default]
+    Wait,
+    /// Fail with [`QueueError::MailboxFull`]
+    Reject,
+    /// Drop the oldest message to make room
+    DropOldest,
+}
+
+/// Capacity and backpressure of a mailbox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MailboxConfig {
+    capacity: Option<usize>,
+    backpressure: Backpressure,
+}
+
+impl MailboxConfig {
+    /// Create a mailbox holding at most `capacity` messages
+    pub fn bounded(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity.max(1)),
+            backpressure: Backpressure::Wait,
+        }
+    }
+
+    /// Create a mailbox without a size limit
+    pub fn unbounded() -> Self {
+        Self::default()
+    }
+
+    /// Set what sending to a full mailbox does
+    pub fn with_backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Get capacity, `None` if unbounded
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Get backpressure
+    pub fn backpressure(&self) -> Backpressure {
+        self.backpressure
+    }
+}
+
+/// One agent's mailbox
+struct Inbox {
+    config: MailboxConfig,
+    messages: VecDeque<Message>,
+    dropped: u64,
+    /// Observers were told about mail the agent has not drained yet
+    notified: bool,
+    arrived: Arc<Notify>,
+    space: Arc<Notify>,
+}
+
+impl Inbox {
+    fn new(config: MailboxConfig) -> Self {
+        Self {
+            config,
+            messages: VecDeque::new(),
+            dropped: 0,
+            notified: false,
+            arrived: Arc::new(Notify::new()),
+            space: Arc::new(Notify::new()),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.config
+            .capacity
+            .is_some_and(|capacity| self.messages.len() >= capacity)
+    }
+}
+
+/// Outcome of trying to deliver a message
+enum Delivery {
+    /// Delivered; `first` if the agent had no undrained mail
+    Delivered {
+        first: bool,
+    },
+    Full(Message, Arc<Notify>),
+}
+
+type MailObserver = Arc<dyn Fn(&AgentId) + Send + Sync>;
+
+/// Mailboxes of all agents
+#[derive(Default)]
+pub struct Mailboxes {
+    inboxes: Mutex<HashMap<AgentId, Inbox>>,
+    default_config: Option<MailboxConfig>,
+    payload_limit: Option<SizeLimit>,
+    observers: Mutex<Vec<MailObserver>>,
+}
+
+impl Mailboxes {
+    /// Create mailboxes where only registered agents receive mail
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give agents without a registered mailbox one on their first message
+    pub fn with_default_config(mut self, config: MailboxConfig) -> Self {
+        self.default_config = Some(config);
+        self
+    }
+
+    /// Limit the size of message payloads
+    pub fn with_payload_limit(mut self, limit: SizeLimit) -> Self {
+        self.payload_limit = Some(limit);
+        self
+    }
+
+    /// Create or replace an agent's mailbox, keeping queued messages
+    pub fn register(&self, agent_id: AgentId, config: MailboxConfig) {
+        self.lock_inboxes()
+            .entry(agent_id)
+            .or_insert_with(|| Inbox::new(config))
+            .config = config;
+    }
+
+    /// Remove an agent's mailbox, returning undelivered messages
+    pub fn unregister(&self, agent_id: &AgentId) -> Vec<Message> {
+        let Some(inbox) = self.lock_inboxes().remove(agent_id) else {
+            return Vec::new();
+        };
+        // Wake current waiters, and leave a permit for one about to wait
+        for notify in [&inbox.arrived, &inbox.space] {
+            notify.notify_waiters();
+            notify.notify_one();
+        }
+        inbox.messages.into()
+    }
+
+    /// Check if an agent has a mailbox
+    pub fn contains(&self, agent_id: &AgentId) -> bool {
+        self.lock_inboxes().contains_key(agent_id)
+    }
+
+    /// Call `observer` when mail arrives for an agent that has none waiting
+    ///
+    /// It is called again only after the agent drained its mailbox.
+    pub fn on_mail(&self, observer: impl Fn(&AgentId) + Send + Sync + 'static) {
+        self.lock_observers().push(Arc::new(observer));
+    }
+
+    /// Deliver a message, failing if the mailbox is full
+    ///
+    /// Mailboxes that drop the oldest message never fail for being full.
+    pub fn try_send(&self, message: Message) -> Result<(), RuntimeError> {
+        let agent_id = *message.recipient();
+        match self.deliver(message)? {
+            Delivery::Delivered { first } => {
+                self.announce(&agent_id, first);
+                Ok(())
+            }
+            Delivery::Full(..) => Err(self.full(&agent_id)),
+        }
+    }
+
+    /// Deliver a message, waiting for room in a full mailbox
+    pub async fn send(&self, mut message: Message) -> Result<(), RuntimeError> {
+        let agent_id = *message.recipient();
+        loop {
+            match self.deliver(message)? {
+                Delivery::Delivered { first } => {
+                    self.announce(&agent_id, first);
+                    return Ok(());
+                }
+                Delivery::Full(full, space) => {
+                    message = full;
+                    space.notified().await;
+                }
+            }
+        }
+    }
+
+    /// Take the next message for an agent, if any
+    pub fn try_recv(&self, agent_id: &AgentId) -> Option<Message> {
+        let mut inboxes = self.lock_inboxes();
+        let inbox = inboxes.get_mut(agent_id)?;
+        let message = inbox.messages.pop_front();
+        if message.is_some() {
+            inbox.space.notify_one();
+        }
+        if inbox.messages.is_empty() {
+            inbox.notified = false;
+        }
+        message
+    }
+
+    /// Wait for the next message for an agent
+    ///
+    /// Returns `None` if the agent has no mailbox or it is unregistered.
+    pub async fn recv(&self, agent_id: &AgentId) -> Option<Message> {
+        loop {
+            if let Some(message) = self.try_recv(agent_id) {
+                return Some(message);
+            }
+            let arrived = self.lock_inboxes().get(agent_id)?.arrived.clone();
+            // Deliveries leave a permit, so mail arriving before this wait is not missed
+            arrived.notified().await;
+        }
+    }
+
+    /// Get number of messages waiting for an agent
+    pub fn len(&self, agent_id: &AgentId) -> usize {
+        self.lock_inboxes()
+            .get(agent_id)
+            .map_or(0, |inbox| inbox.messages.len())
+    }
+
+    /// Get number of messages dropped from an agent's full mailbox
+    pub fn dropped(&self, agent_id: &AgentId) -> u64 {
+        self.lock_inboxes()
+            .get(agent_id)
+            .map_or(0, |inbox| inbox.dropped)
+    }
+
+    fn deliver(&self, mut message: Message) -> Result<Delivery, RuntimeError> {
+        if let Some(limit) = &self.payload_limit {
+            message.truncated |= limit.apply(&mut message.payload)?;
+        }
+        let mut inboxes = self.lock_inboxes();
+        let agent_id = *message.recipient();
+        let inbox = match (inboxes.entry(agent_id), self.default_config) {
+            (Entry::Occupied(entry), _) => entry.into_mut(),
+            (Entry::Vacant(entry), Some(config)) => entry.insert(Inbox::new(config)),
+            (Entry::Vacant(_), None) => {
+                return Err(RuntimeError::AgentNotFound(agent_id.to_string()))
+            }
+        };
+        if inbox.is_full() {
+            match inbox.config.backpressure {
+                Backpressure::DropOldest => {
+                    inbox.messages.pop_front();
+                    inbox.dropped += 1;
+                }
+                Backpressure::Wait => return Ok(Delivery::Full(message, inbox.space.clone())),
+                Backpressure::Reject => {
+                    drop(inboxes);
+                    return Err(self.full(&agent_id));
+                }
+            }
+        }
+        inbox.messages.push_back(message);
+        inbox.arrived.notify_one();
+        let first = !inbox.notified;
+        inbox.notified = true;
+        Ok(Delivery::Delivered { first })
+    }
+
+    /// Tell observers about mail for an agent that had none undrained
+    fn announce(&self, agent_id: &AgentId, first: bool) {
+        if !first {
+            return;
+        }
+        let observers = self.lock_observers().clone();
+        for observer in observers {
+            observer(agent_id);
+        }
+    }
+
+    fn full(&self, agent_id: &AgentId) -> RuntimeError {
+        let capacity = self
+            .lock_inboxes()
+            .get(agent_id)
+            .and_then(|inbox| inbox.config.capacity)
+            .unwrap_or_default();
+        QueueError::MailboxFull {
+            agent_id: *agent_id,
+            capacity,
+        }
+        .into()
+    }
+
+    fn lock_inboxes(&self) -> MutexGuard<'_, HashMap<AgentId, Inbox>> {
+        self.inboxes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_observers(&self) -> MutexGuard<'_, Vec<MailObserver>> {
+        self.observers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for Mailboxes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mailboxes")
+            .field("mailboxes", &self.lock_inboxes().len())
+            .field("default_config", &self.default_config)
+            .field("payload_limit", &self.payload_limit)
+            .finish()
+    }
+}
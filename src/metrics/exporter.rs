@@ -30,6 +30,12 @@ impl MetricsExporter {
         serde_json::to_string_pretty(&all_metrics)
     }
 
+    /// Export metrics in the Prometheus text exposition format
+    #[cfg(feature = "prometheus")]
+    pub fn export_prometheus(&self) -> String {
+        self.registry.encode_prometheus()
+    }
+
     /// Get registry
     pub fn registry(&self) -> &MetricsRegistry {
         &self.registry
@@ -0,0 +1,112 @@
+use super::{Collector, Metric, MetricType};
+use crate::RuntimeError;
+use hdrhistogram::serialization::{Deserializer, Serializer, V2Serializer};
+use hdrhistogram::Histogram;
+use std::time::Duration;
+
+/// Quantiles exported by [`LatencyHistogram::collect`]
+const EXPORTED_QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 0.999];
+
+/// HDR histogram of latencies with microsecond resolution
+///
+/// Histograms from several runtimes can be merged, directly or through
+/// [`to_bytes`](Self::to_bytes), to compute cluster-wide percentiles.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    histogram: Histogram<u64>,
+}
+
+impl LatencyHistogram {
+    /// Create an empty histogram with three significant digits
+    pub fn new() -> Self {
+        Self {
+            histogram: Histogram::new(3).expect("three significant digits are valid"),
+        }
+    }
+
+    /// Record a latency
+    pub fn record(&mut self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        // Auto-resizing histograms accept any value
+        let _ = self.histogram.record(micros);
+    }
+
+    /// Get latency at the given quantile (0.0 to 1.0)
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        Duration::from_micros(self.histogram.value_at_quantile(quantile))
+    }
+
+    /// Get number of recorded latencies
+    pub fn count(&self) -> u64 {
+        self.histogram.len()
+    }
+
+    /// Get mean latency
+    pub fn mean(&self) -> Duration {
+        Duration::from_secs_f64(self.histogram.mean() / 1_000_000.0)
+    }
+
+    /// Get smallest recorded latency
+    pub fn min(&self) -> Duration {
+        Duration::from_micros(self.histogram.min())
+    }
+
+    /// Get largest recorded latency
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.histogram.max())
+    }
+
+    /// Add all latencies recorded by another histogram
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        // Auto-resizing histograms accept any range
+        let _ = self.histogram.add(&other.histogram);
+    }
+
+    /// Clear all recorded latencies
+    pub fn reset(&mut self) {
+        self.histogram.reset();
+    }
+
+    /// Encode in the portable HdrHistogram V2 format
+    pub fn to_bytes(&self) -> Result<Vec<u8>, RuntimeError> {
+        let mut bytes = Vec::new();
+        V2Serializer::new()
+            .serialize(&self.histogram, &mut bytes)
+            .map_err(|e| RuntimeError::Other(format!("failed to encode histogram: {:?}", e)))?;
+        Ok(bytes)
+    }
+
+    /// Decode a histogram produced by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RuntimeError> {
+        let mut histogram: Histogram<u64> = Deserializer::new()
+            .deserialize(&mut &bytes[..])
+            .map_err(|e| RuntimeError::Other(format!("failed to decode histogram: {:?}", e)))?;
+        histogram.auto(true);
+        Ok(Self { histogram })
+    }
+
+    /// Record quantiles and count into a collector under `<name>_seconds`
+    pub fn collect(&self, name: &str, collector: &mut Collector) {
+        for quantile in EXPORTED_QUANTILES {
+            collector.record(
+                Metric::new(
+                    format!("{}_seconds", name),
+                    MetricType::Histogram,
+                    self.quantile(quantile).as_secs_f64(),
+                )
+                .with_label("quantile", quantile.to_string()),
+            );
+        }
+        collector.record(Metric::new(
+            format!("{}_seconds_count", name),
+            MetricType::Counter,
+            self.count() as f64,
+        ));
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -4,9 +4,15 @@
 pub mod collector;
 /// Metrics exporter
 pub mod exporter;
+/// Latency histograms
+pub mod histogram;
+/// Prometheus text encoding
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
 /// Metrics registry
 pub mod registry;
 
 pub use collector::{Collector, Metric, MetricType};
 pub use exporter::MetricsExporter;
+pub use histogram::LatencyHistogram;
 pub use registry::MetricsRegistry;
@@ -0,0 +1,90 @@
+//! Prometheus text exposition format
+//!
+//! Metrics are grouped into families by name, each preceded by its `# TYPE`
+//! line. Latency histograms record quantiles, so they are exposed as
+//! summaries with their `_count` series in the same family.
+
+use super::{Metric, MetricType, MetricsRegistry};
+use std::fmt::Write;
+
+/// Encode every metric in a registry, collectors ordered by name
+pub fn encode(registry: &MetricsRegistry) -> String {
+    let mut collectors: Vec<_> = registry.collectors().collect();
+    collectors.sort_by_key(|(name, _)| *name);
+
+    let mut families: Vec<(String, &'static str, Vec<&Metric>)> = Vec::new();
+    for (_, collector) in collectors {
+        for metric in collector.metrics() {
+            let name = sanitize(metric.name());
+            let summary = name
+                .strip_suffix("_count")
+                .filter(|base| summary_family(&families, base));
+            let family = summary.map_or(name.clone(), str::to_string);
+            match families.iter_mut().find(|(other, ..)| *other == family) {
+                Some((_, _, metrics)) => metrics.push(metric),
+                None => families.push((family, type_name(metric.metric_type()), vec![metric])),
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (family, kind, metrics) in families {
+        let _ = writeln!(out, "# TYPE {} {}", family, kind);
+        for metric in metrics {
+            out.push_str(&sanitize(metric.name()));
+            if !metric.labels().is_empty() {
+                let labels: Vec<String> = metric
+                    .labels()
+                    .iter()
+                    .map(|(key, value)| format!("{}=\"{}\"", sanitize(key), escape(value)))
+                    .collect();
+                let _ = write!(out, "{{{}}}", labels.join(","));
+            }
+            let _ = writeln!(out, " {}", format_value(metric.value()));
+        }
+    }
+    out
+}
+
+fn summary_family(families: &[(String, &'static str, Vec<&Metric>)], name: &str) -> bool {
+    families
+        .iter()
+        .any(|(family, kind, _)| family == name && *kind == "summary")
+}
+
+fn type_name(metric_type: MetricType) -> &'static str {
+    match metric_type {
+        MetricType::Counter => "counter",
+        MetricType::Gauge => "gauge",
+        MetricType::Histogram => "summary",
+    }
+}
+
+/// Replace characters not allowed in metric and label names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            'a'..='z' | 'A'..='Z' | '_' | ':' => c,
+            '0'..='9' if i > 0 => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
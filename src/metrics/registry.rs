@@ -33,6 +33,12 @@ impl MetricsRegistry {
     pub fn collectors(&self) -> impl Iterator<Item = (&String, &Collector)> {
         self.collectors.iter()
     }
+
+    /// Encode all metrics in the Prometheus text exposition format
+    #[cfg(feature = "prometheus")]
+    pub fn encode_prometheus(&self) -> String {
+        super::prometheus::encode(self)
+    }
 }
 
 impl Default for MetricsRegistry {
@@ -0,0 +1,124 @@
+use super::Severity;
+use crate::ErrorClass;
+use agentropic_core::AgentId;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Lifecycle or failure event delivered to external systems
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuntimeEvent {
+    /// A supervisor stopped restarting an agent
+    AgentGaveUp {
+        /// Supervisor name
+        supervisor: String,
+        /// Agent that was given up on
+        agent_id: AgentId,
+        /// Number of starts performed
+        attempts: u32,
+        /// Last failure message
+        failure: String,
+        /// Last failure classification
+        class: ErrorClass,
+    },
+    /// A circuit breaker opened
+    BreakerOpened {
+        /// Protected operation name
+        name: String,
+    },
+    /// A service-level objective was violated
+    SloViolation {
+        /// Objective name
+        name: String,
+        /// Agent involved, if any
+        agent_id: Option<AgentId>,
+        /// Observed value
+        observed: Duration,
+        /// Target value
+        target: Duration,
+    },
+    /// An agent's cost budget breaker tripped
+    BudgetTripped {
+        /// Agent over budget
+        agent_id: AgentId,
+        /// Cost spent in the current window
+        spent: u64,
+        /// Budget limit
+        limit: u64,
+    },
+    /// An agent's cost budget breaker closed
+    BudgetReset {
+        /// Agent accepting tasks again
+        agent_id: AgentId,
+    },
+    /// A task was dropped because the scheduler's queue was full
+    TaskDropped {
+        /// Agent the task was queued for
+        agent_id: AgentId,
+        /// Number of the dropped task
+        task: u64,
+    },
+}
+
+impl RuntimeEvent {
+    /// Get the event type name used in payloads and for filtering
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RuntimeEvent::AgentGaveUp { .. } => "agent_gave_up",
+            RuntimeEvent::BreakerOpened { .. } => "breaker_opened",
+            RuntimeEvent::SloViolation { .. } => "slo_violation",
+            RuntimeEvent::BudgetTripped { .. } => "budget_tripped",
+            RuntimeEvent::BudgetReset { .. } => "budget_reset",
+            RuntimeEvent::TaskDropped { .. } => "task_dropped",
+        }
+    }
+
+    /// Get default severity
+    pub fn severity(&self) -> Severity {
+        match self {
+            RuntimeEvent::AgentGaveUp { .. } => Severity::Critical,
+            RuntimeEvent::BreakerOpened { .. }
+            | RuntimeEvent::SloViolation { .. }
+            | RuntimeEvent::BudgetTripped { .. }
+            | RuntimeEvent::TaskDropped { .. } => Severity::Warning,
+            RuntimeEvent::BudgetReset { .. } => Severity::Info,
+        }
+    }
+}
+
+#[cfg(feature = "scheduler")]
+impl From<crate::scheduler::SchedulerEvent> for RuntimeEvent {
+    fn from(event: crate::scheduler::SchedulerEvent) -> Self {
+        match event {
+            crate::scheduler::SchedulerEvent::WaitBoundExceeded {
+                agent_id,
+                waited,
+                bound,
+                ..
+            } => RuntimeEvent::SloViolation {
+                name: "scheduler_max_wait".into(),
+                agent_id: Some(agent_id),
+                observed: waited,
+                target: bound,
+            },
+            crate::scheduler::SchedulerEvent::BudgetTripped {
+                agent_id,
+                spent,
+                limit,
+            } => RuntimeEvent::BudgetTripped {
+                agent_id,
+                spent,
+                limit,
+            },
+            crate::scheduler::SchedulerEvent::BudgetReset { agent_id } => {
+                RuntimeEvent::BudgetReset { agent_id }
+            }
+            crate::scheduler::SchedulerEvent::TaskDropped { agent_id, task, .. } => {
+                RuntimeEvent::TaskDropped {
+                    agent_id,
+                    task: task.as_u64(),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Runtime events and notification sinks
+//!
+//! Implement [`Notifier`] to plug pagers, chat, or email into supervisor
+//! escalations, and use [`NotificationRouter`] to pick destinations by
+//! [`Severity`] and event kind.
+
+/// Runtime events
+pub mod event;
+/// Notifier trait and routing
+pub mod notifier;
+/// Signed JSON webhooks
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+
+pub use event::RuntimeEvent;
+pub use notifier::{NotificationRouter, Notifier, Route, Severity, StdoutNotifier};
+#[cfg(feature = "webhooks")]
+pub use webhook::WebhookSink;
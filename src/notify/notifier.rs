@@ -0,0 +1,220 @@
+use super::RuntimeEvent;
+use crate::RuntimeError;
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+#[cfg(feature = "supervisor")]
+use std::sync::OnceLock;
+#[cfg(feature = "supervisor")]
+use tokio::sync::{mpsc, oneshot};
+
+/// Urgency of a runtime event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Informational
+    Info,
+    /// Needs attention
+    Warning,
+    /// Needs immediate action
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Destination for runtime events, e.g. a pager, chat, or email sender
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Deliver an event
+    async fn notify(&self, event: &RuntimeEvent) -> Result<(), RuntimeError>;
+}
+
+/// Notifier printing events to standard output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutNotifier;
+
+#[async_trait]
+impl Notifier for StdoutNotifier {
+    async fn notify(&self, event: &RuntimeEvent) -> Result<(), RuntimeError> {
+        println!("[{}] {}: {:?}", event.severity(), event.kind(), event);
+        Ok(())
+    }
+}
+
+/// Rule sending matching events to one notifier
+#[derive(Clone)]
+pub struct Route {
+    notifier: Arc<dyn Notifier>,
+    min_severity: Severity,
+    kinds: Option<Vec<String>>,
+}
+
+impl Route {
+    /// Create a route sending all events to `notifier`
+    pub fn new(notifier: Arc<dyn Notifier>) -> Self {
+        Self {
+            notifier,
+            min_severity: Severity::Info,
+            kinds: None,
+        }
+    }
+
+    /// Only route events at or above a severity
+    pub fn with_min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = severity;
+        self
+    }
+
+    /// Only route events of the given kinds, see [`RuntimeEvent::kind`]
+    pub fn with_events<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.kinds = Some(kinds.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check if an event matches this route
+    pub fn matches(&self, event: &RuntimeEvent) -> bool {
+        event.severity() >= self.min_severity
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.iter().any(|kind| kind == event.kind()))
+    }
+}
+
+/// Notifier fanning events out to routes by severity and kind
+#[derive(Clone, Default)]
+pub struct NotificationRouter {
+    routes: Vec<Route>,
+}
+
+impl NotificationRouter {
+    /// Create a router without routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a route
+    pub fn route(mut self, route: Route) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// Get routes
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+}
+
+#[async_trait]
+impl Notifier for NotificationRouter {
+    /// Deliver to every matching route, failing if any delivery failed
+    async fn notify(&self, event: &RuntimeEvent) -> Result<(), RuntimeError> {
+        let mut failures = Vec::new();
+        for route in self.routes.iter().filter(|route| route.matches(event)) {
+            if let Err(error) = route.notifier.notify(event).await {
+                failures.push(error.to_string());
+            }
+        }
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(RuntimeError::Other(format!(
+                "failed to deliver {} event: {}",
+                event.kind(),
+                failures.join("; ")
+            ))),
+        }
+    }
+}
+
+/// Events waiting for delivery before a [`Publisher`] drops new ones
+#[cfg(feature = "supervisor")]
+const QUEUE_CAPACITY: usize = 256;
+
+#[cfg(feature = "supervisor")]
+enum Delivery {
+    Event(RuntimeEvent),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Notifiers fed from a bounded queue by a background task, so slow or
+/// retrying deliveries never hold up the code raising the event
+///
+/// The task is spawned on the first publish and stops once the publisher is
+/// dropped and its queue drained. Events raised while the queue is full are
+/// dropped with a warning.
+#[cfg(feature = "supervisor")]
+#[derive(Default)]
+pub(crate) struct Publisher {
+    notifiers: Vec<Arc<dyn Notifier>>,
+    queue: OnceLock<mpsc::Sender<Delivery>>,
+}
+
+#[cfg(feature = "supervisor")]
+impl Publisher {
+    /// Add a notifier; events already queued go to the previous notifiers
+    pub(crate) fn add(&mut self, notifier: Arc<dyn Notifier>) {
+        self.notifiers.push(notifier);
+        self.queue = OnceLock::new();
+    }
+
+    fn queue(&self) -> &mpsc::Sender<Delivery> {
+        self.queue.get_or_init(|| {
+            let (sender, mut receiver) = mpsc::channel(QUEUE_CAPACITY);
+            let notifiers = self.notifiers.clone();
+            tokio::spawn(async move {
+                while let Some(delivery) = receiver.recv().await {
+                    match delivery {
+                        Delivery::Event(event) => publish(&notifiers, &event).await,
+                        Delivery::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            });
+            sender
+        })
+    }
+
+    /// Queue an event for delivery to every notifier
+    pub(crate) fn publish(&self, event: RuntimeEvent) {
+        if self.notifiers.is_empty() {
+            return;
+        }
+        let kind = event.kind();
+        if let Err(error) = self.queue().try_send(Delivery::Event(event)) {
+            tracing::warn!("Dropped {} event: {}", kind, error);
+        }
+    }
+
+    /// Wait until events queued so far were delivered
+    pub(crate) async fn flush(&self) {
+        if self.notifiers.is_empty() {
+            return;
+        }
+        let (done, delivered) = oneshot::channel();
+        if self.queue().send(Delivery::Flush(done)).await.is_ok() {
+            let _ = delivered.await;
+        }
+    }
+}
+
+/// Deliver an event to every notifier, logging failures
+#[cfg(feature = "supervisor")]
+async fn publish(notifiers: &[Arc<dyn Notifier>], event: &RuntimeEvent) {
+    for notifier in notifiers {
+        if let Err(error) = notifier.notify(event).await {
+            tracing::warn!("Failed to deliver {} event: {}", event.kind(), error);
+        }
+    }
+}
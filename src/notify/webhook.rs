@@ -0,0 +1,164 @@
+//! Signed JSON webhooks
+//!
+//! Each event is POSTed as JSON. With a secret configured, the request
+//! carries `X-Agentropic-Timestamp` and `X-Agentropic-Signature` headers; the
+//! signature is `sha256=` followed by the hex HMAC-SHA256 of
+//! `"{timestamp}.{body}"`, which receivers recompute with
+//! [`WebhookSink::signature`]. Only `http://` URLs are supported.
+
+use super::{Notifier, RuntimeEvent};
+use crate::http;
+use crate::RuntimeError;
+use agentropic_runtime_core::ExponentialBackoff;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::timeout;
+
+/// Body of a webhook request
+#[derive(Serialize)]
+struct Delivery<'a> {
+    timestamp: u64,
+    event: &'a RuntimeEvent,
+}
+
+/// Sink POSTing runtime events to a URL
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    url: String,
+    secret: Option<Vec<u8>>,
+    events: Option<Vec<String>>,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    timeout: Duration,
+}
+
+impl WebhookSink {
+    /// Create a sink sending all events, with up to 3 attempts per event
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+            events: None,
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Sign requests with a shared secret
+    pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Only send events of the given kinds, see [`RuntimeEvent::kind`]
+    pub fn with_events<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.events = Some(kinds.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set maximum delivery attempts per event
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Set exponential backoff between attempts
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Set timeout for each attempt
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Check if events of a kind are sent
+    pub fn accepts(&self, event: &RuntimeEvent) -> bool {
+        self.events
+            .as_ref()
+            .is_none_or(|kinds| kinds.iter().any(|kind| kind == event.kind()))
+    }
+
+    /// Compute the signature header value for a request
+    pub fn signature(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+        // HMAC accepts keys of any length
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC key");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        let digest = mac.finalize().into_bytes();
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("sha256={}", hex)
+    }
+
+    /// Send an event, retrying with backoff until delivered or out of attempts
+    ///
+    /// Events this sink does not accept are skipped.
+    pub async fn send(&self, event: &RuntimeEvent) -> Result<(), RuntimeError> {
+        if !self.accepts(event) {
+            return Ok(());
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let body = serde_json::to_vec(&Delivery { timestamp, event })
+            .map_err(|e| RuntimeError::Other(format!("failed to encode event: {}", e)))?;
+
+        let mut backoff = ExponentialBackoff::new(self.initial_backoff, self.max_backoff);
+        let mut last_error = String::new();
+        for attempt in 1..=self.max_attempts {
+            match timeout(self.timeout, self.post(timestamp, &body)).await {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(error)) => last_error = error,
+                Err(_) => last_error = "timed out".into(),
+            }
+            if attempt < self.max_attempts {
+                tokio::time::sleep(backoff.next_delay()).await;
+            }
+        }
+        Err(RuntimeError::Other(format!(
+            "webhook {} failed after {} attempts: {}",
+            self.url, self.max_attempts, last_error
+        )))
+    }
+
+    async fn post(&self, timestamp: u64, body: &[u8]) -> Result<(), String> {
+        let headers = match &self.secret {
+            Some(secret) => vec![
+                ("X-Agentropic-Timestamp", timestamp.to_string()),
+                (
+                    "X-Agentropic-Signature",
+                    Self::signature(secret, timestamp, body),
+                ),
+            ],
+            None => Vec::new(),
+        };
+        http::post_json(&self.url, &headers, body).await
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookSink {
+    async fn notify(&self, event: &RuntimeEvent) -> Result<(), RuntimeError> {
+        self.send(event).await
+    }
+}
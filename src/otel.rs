@@ -0,0 +1,560 @@
+//! OpenTelemetry export of runtime spans and metrics
+//!
+//! An [`OtlpExporter`] pushes to an OpenTelemetry collector over OTLP/HTTP
+//! with JSON bodies. Its [`OtelLayer`] captures the spans the scheduler and
+//! supervisor open (`task.dispatch`, `task.run`, `agent.restart`) from a
+//! `tracing` subscriber; metrics come from [`Runtime::collect_metrics`].
+//! Both carry resource attributes identifying the runtime instance and its
+//! node, and are named after the conventions in [`semconv`].
+//!
+//! The exporter speaks plain HTTP only: it cannot reach a collector behind
+//! TLS (`https://`). Point it at a local collector or a sidecar proxy that
+//! forwards over TLS instead.
+
+use crate::http;
+use crate::metrics::{Metric, MetricType, MetricsRegistry};
+use crate::rng::SplitMix64;
+use crate::{Runtime, RuntimeError};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Spans from targets outside this crate are ignored
+const TARGET: &str = "agentropic_runtime";
+
+/// Default limit of finished spans waiting for export
+const DEFAULT_MAX_QUEUED_SPANS: usize = 2048;
+
+/// Attribute and metric names
+///
+/// Resource attributes follow the OpenTelemetry semantic conventions; the
+/// runtime's own attributes and metrics live under `agentropic.`.
+pub mod semconv {
+    /// Logical name of the service
+    pub const SERVICE_NAME: &str = "service.name";
+    /// ID of this runtime instance
+    pub const SERVICE_INSTANCE_ID: &str = "service.instance.id";
+    /// Name of the node running the runtime
+    pub const HOST_NAME: &str = "host.name";
+    /// Agent a task or child belongs to
+    pub const AGENT_ID: &str = "agentropic.agent.id";
+    /// Priority of a task
+    pub const TASK_PRIORITY: &str = "agentropic.task.priority";
+    /// Attempt of a task or child, 1 for the first
+    pub const TASK_ATTEMPT: &str = "agentropic.task.attempt";
+    /// Scheduling policy dispatching a task
+    pub const SCHEDULER_POLICY: &str = "agentropic.scheduler.policy";
+    /// Name of a supervisor
+    pub const SUPERVISOR_NAME: &str = "agentropic.supervisor.name";
+    /// Tasks waiting to be dispatched
+    pub const QUEUE_DEPTH: &str = "agentropic.queue.depth";
+    /// Queued tasks held back from dispatch
+    pub const QUEUE_HELD: &str = "agentropic.queue.held";
+    /// Time tasks waited in the queue
+    pub const QUEUE_WAIT_TIME: &str = "agentropic.queue.wait_time";
+    /// Tasks that failed for good
+    pub const QUEUE_DEAD_LETTERS: &str = "agentropic.queue.dead_letters";
+    /// Failed task attempts retried
+    pub const RETRY_COUNT: &str = "agentropic.retry.count";
+    /// Children restarted by their supervisor
+    pub const RESTART_COUNT: &str = "agentropic.restart.count";
+
+    /// Get the exported name of a span field or metric label
+    pub fn attribute_key(field: &str) -> String {
+        match field {
+            "agent_id" => AGENT_ID.into(),
+            "priority" => TASK_PRIORITY.into(),
+            "attempt" => TASK_ATTEMPT.into(),
+            "policy" => SCHEDULER_POLICY.into(),
+            "supervisor" => SUPERVISOR_NAME.into(),
+            "quantile" => "quantile".into(),
+            other => format!("agentropic.{}", other),
+        }
+    }
+
+    /// Get the exported name of a runtime metric
+    pub fn metric_name(name: &str) -> String {
+        match name {
+            "scheduler_queue_depth" => QUEUE_DEPTH.into(),
+            "scheduler_held_tasks" => QUEUE_HELD.into(),
+            "scheduler_queue_wait_seconds" => QUEUE_WAIT_TIME.into(),
+            "scheduler_dead_letters" => QUEUE_DEAD_LETTERS.into(),
+            "scheduler_retries_total" => RETRY_COUNT.into(),
+            "supervisor_restarts_total" => RESTART_COUNT.into(),
+            other => {
+                let base = other.strip_suffix("_total").unwrap_or(other);
+                let base = base.strip_suffix("_seconds").unwrap_or(base);
+                format!("agentropic.{}", base.replace('_', "."))
+            }
+        }
+    }
+
+    /// Get the unit of a runtime metric
+    pub fn metric_unit(name: &str) -> &'static str {
+        if name.ends_with("_seconds") {
+            "s"
+        } else {
+            "1"
+        }
+    }
+}
+
+/// Span field or resource attribute value
+#[derive(Debug, Clone, PartialEq)]
+enum AttributeValue {
+    String(String),
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+impl AttributeValue {
+    fn to_json(&self) -> Value {
+        match self {
+            AttributeValue::String(value) => json!({ "stringValue": value }),
+            AttributeValue::Int(value) => json!({ "intValue": value.to_string() }),
+            AttributeValue::Double(value) => json!({ "doubleValue": value }),
+            AttributeValue::Bool(value) => json!({ "boolValue": value }),
+        }
+    }
+}
+
+fn attributes_json<'a>(
+    attributes: impl IntoIterator<Item = (&'a String, &'a AttributeValue)>,
+) -> Value {
+    attributes
+        .into_iter()
+        .map(|(key, value)| json!({ "key": key, "value": value.to_json() }))
+        .collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Span still open, kept in the subscriber's span extensions
+#[derive(Debug)]
+struct OpenSpan {
+    trace_id: u128,
+    span_id: u64,
+    parent_id: Option<u64>,
+    name: &'static str,
+    start: SystemTime,
+    attributes: BTreeMap<String, AttributeValue>,
+}
+
+/// Span waiting for export
+#[derive(Debug)]
+struct FinishedSpan {
+    span: OpenSpan,
+    end: SystemTime,
+}
+
+impl FinishedSpan {
+    fn to_json(&self) -> Value {
+        let span = &self.span;
+        json!({
+            "traceId": format!("{:032x}", span.trace_id),
+            "spanId": format!("{:016x}", span.span_id),
+            "parentSpanId": span.parent_id.map(|id| format!("{:016x}", id)).unwrap_or_default(),
+            "name": span.name,
+            "kind": 1,
+            "startTimeUnixNano": unix_nanos(span.start),
+            "endTimeUnixNano": unix_nanos(self.end),
+            "attributes": attributes_json(&span.attributes),
+        })
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, AttributeValue>);
+
+impl FieldVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: AttributeValue) {
+        self.0.insert(semconv::attribute_key(field.name()), value);
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, AttributeValue::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, AttributeValue::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(
+            field,
+            AttributeValue::Int(i64::try_from(value).unwrap_or(i64::MAX)),
+        );
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, AttributeValue::Double(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, AttributeValue::Bool(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, AttributeValue::String(format!("{:?}", value)));
+    }
+}
+
+/// State shared by an exporter and its layers
+#[derive(Debug)]
+struct Shared {
+    spans: Mutex<VecDeque<FinishedSpan>>,
+    rng: Mutex<SplitMix64>,
+    max_queued: usize,
+}
+
+impl Shared {
+    fn new(max_queued: usize) -> Self {
+        Self {
+            spans: Mutex::new(VecDeque::new()),
+            rng: Mutex::new(SplitMix64::new()),
+            max_queued: max_queued.max(1),
+        }
+    }
+
+    /// Draw a non-zero ID
+    fn next_id(&self) -> u64 {
+        let mut rng = self
+            .rng
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        loop {
+            let id = rng.next_u64();
+            if id != 0 {
+                return id;
+            }
+        }
+    }
+
+    fn lock_spans(&self) -> MutexGuard<'_, VecDeque<FinishedSpan>> {
+        self.spans
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// `tracing` layer capturing runtime spans for an [`OtlpExporter`]
+///
+/// Add it to the application's subscriber. Once the exporter falls behind
+/// by its span limit, the oldest finished spans are dropped.
+#[derive(Debug, Clone)]
+pub struct OtelLayer {
+    shared: Arc<Shared>,
+}
+
+impl<S> Layer<S> for OtelLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !attrs.metadata().target().starts_with(TARGET) {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<OpenSpan>()
+                .map(|open| (open.trace_id, open.span_id))
+        });
+        let (trace_id, parent_id) = match parent {
+            Some((trace_id, parent_id)) => (trace_id, Some(parent_id)),
+            None => (
+                (u128::from(self.shared.next_id()) << 64) | u128::from(self.shared.next_id()),
+                None,
+            ),
+        };
+        let mut open = OpenSpan {
+            trace_id,
+            span_id: self.shared.next_id(),
+            parent_id,
+            name: attrs.metadata().name(),
+            start: SystemTime::now(),
+            attributes: BTreeMap::new(),
+        };
+        attrs.record(&mut FieldVisitor(&mut open.attributes));
+        span.extensions_mut().insert(open);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(open) = span.extensions_mut().get_mut::<OpenSpan>() {
+                values.record(&mut FieldVisitor(&mut open.attributes));
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(open) = span.extensions_mut().remove::<OpenSpan>() else {
+            return;
+        };
+        let mut spans = self.shared.lock_spans();
+        if spans.len() >= self.shared.max_queued {
+            spans.pop_front();
+        }
+        spans.push_back(FinishedSpan {
+            span: open,
+            end: SystemTime::now(),
+        });
+    }
+}
+
+/// Exporter pushing runtime spans and metrics to an OTLP/HTTP endpoint
+#[derive(Debug, Clone)]
+pub struct OtlpExporter {
+    endpoint: String,
+    resource: BTreeMap<String, AttributeValue>,
+    timeout: Duration,
+    shared: Arc<Shared>,
+}
+
+impl OtlpExporter {
+    /// Create an exporter for a collector's base URL, such as
+    /// `http://localhost:4318`
+    ///
+    /// The URL must be `http://`; pushes to an `https://` endpoint fail. The
+    /// instance ID defaults to a random ID.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        let shared = Arc::new(Shared::new(DEFAULT_MAX_QUEUED_SPANS));
+        let instance_id = format!("{:016x}", shared.next_id());
+        Self {
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            resource: BTreeMap::new(),
+            timeout: Duration::from_secs(10),
+            shared,
+        }
+        .with_service_name("agentropic-runtime")
+        .with_instance_id(instance_id)
+    }
+
+    /// Set the service name resource attribute
+    pub fn with_service_name(self, name: impl Into<String>) -> Self {
+        self.with_resource_attribute(semconv::SERVICE_NAME, name)
+    }
+
+    /// Set the runtime instance ID resource attribute
+    pub fn with_instance_id(self, id: impl Into<String>) -> Self {
+        self.with_resource_attribute(semconv::SERVICE_INSTANCE_ID, id)
+    }
+
+    /// Set the node name resource attribute
+    pub fn with_node_name(self, name: impl Into<String>) -> Self {
+        self.with_resource_attribute(semconv::HOST_NAME, name)
+    }
+
+    /// Add a resource attribute sent with every export
+    pub fn with_resource_attribute(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.resource
+            .insert(key.into(), AttributeValue::String(value.into()));
+        self
+    }
+
+    /// Set timeout for each export request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Keep at most `max` finished spans waiting for export
+    ///
+    /// Set this before creating layers; it starts a new span buffer.
+    pub fn with_max_queued_spans(mut self, max: usize) -> Self {
+        self.shared = Arc::new(Shared::new(max));
+        self
+    }
+
+    /// Get endpoint
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Get a resource attribute
+    pub fn resource_attribute(&self, key: &str) -> Option<&str> {
+        match self.resource.get(key) {
+            Some(AttributeValue::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Get a layer capturing runtime spans for this exporter
+    pub fn layer(&self) -> OtelLayer {
+        OtelLayer {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Get number of finished spans waiting for export
+    pub fn queued_spans(&self) -> usize {
+        self.shared.lock_spans().len()
+    }
+
+    /// Push finished spans, returning how many were sent
+    ///
+    /// Spans are dropped if the collector cannot be reached or rejects them.
+    pub async fn export_spans(&self) -> Result<usize, RuntimeError> {
+        let spans: Vec<_> = self.shared.lock_spans().drain(..).collect();
+        if spans.is_empty() {
+            return Ok(0);
+        }
+        let body = json!({
+            "resourceSpans": [{
+                "resource": self.resource_json(),
+                "scopeSpans": [{
+                    "scope": Self::scope_json(),
+                    "spans": spans.iter().map(FinishedSpan::to_json).collect::<Vec<_>>(),
+                }],
+            }],
+        });
+        self.post("/v1/traces", &body).await?;
+        Ok(spans.len())
+    }
+
+    /// Push the metrics of a registry, returning how many data points were sent
+    pub async fn export_metrics(&self, registry: &MetricsRegistry) -> Result<usize, RuntimeError> {
+        let mut metrics: BTreeMap<String, (MetricType, Vec<&Metric>)> = BTreeMap::new();
+        for (_, collector) in registry.collectors() {
+            for metric in collector.metrics() {
+                metrics
+                    .entry(metric.name().to_string())
+                    .or_insert_with(|| (metric.metric_type(), Vec::new()))
+                    .1
+                    .push(metric);
+            }
+        }
+        let points: usize = metrics.values().map(|(_, points)| points.len()).sum();
+        if points == 0 {
+            return Ok(0);
+        }
+        let now = unix_nanos(SystemTime::now());
+        let metrics: Vec<Value> = metrics
+            .iter()
+            .map(|(name, (metric_type, points))| {
+                let points: Vec<Value> = points
+                    .iter()
+                    .map(|metric| {
+                        let attributes: BTreeMap<String, AttributeValue> = metric
+                            .labels()
+                            .iter()
+                            .map(|(key, value)| {
+                                (
+                                    semconv::attribute_key(key),
+                                    AttributeValue::String(value.clone()),
+                                )
+                            })
+                            .collect();
+                        json!({
+                            "asDouble": metric.value(),
+                            "timeUnixNano": now,
+                            "attributes": attributes_json(&attributes),
+                        })
+                    })
+                    .collect();
+                let mut exported = json!({
+                    "name": semconv::metric_name(name),
+                    "unit": semconv::metric_unit(name),
+                });
+                match metric_type {
+                    MetricType::Counter => {
+                        exported["sum"] = json!({
+                            "dataPoints": points,
+                            "aggregationTemporality": 2,
+                            "isMonotonic": true,
+                        });
+                    }
+                    // Histograms are collected as one point per quantile
+                    MetricType::Gauge | MetricType::Histogram => {
+                        exported["gauge"] = json!({ "dataPoints": points });
+                    }
+                }
+                exported
+            })
+            .collect();
+        let body = json!({
+            "resourceMetrics": [{
+                "resource": self.resource_json(),
+                "scopeMetrics": [{
+                    "scope": Self::scope_json(),
+                    "metrics": metrics,
+                }],
+            }],
+        });
+        self.post("/v1/metrics", &body).await?;
+        Ok(points)
+    }
+
+    /// Push a runtime's current metrics and the spans finished so far
+    pub async fn push(&self, runtime: &Runtime) -> Result<(), RuntimeError> {
+        let mut registry = MetricsRegistry::new();
+        runtime.collect_metrics(&mut registry).await;
+        self.export_metrics(&registry).await?;
+        self.export_spans().await?;
+        Ok(())
+    }
+
+    /// Push every `interval` until shutdown, then once more
+    ///
+    /// Failed pushes are logged and retried at the next interval.
+    pub async fn run(&self, runtime: &Runtime, interval: Duration, shutdown: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+            if let Err(error) = self.push(runtime).await {
+                tracing::warn!("OTLP export failed: {}", error);
+            }
+        }
+        if let Err(error) = self.push(runtime).await {
+            tracing::warn!("Final OTLP export failed: {}", error);
+        }
+    }
+
+    fn resource_json(&self) -> Value {
+        json!({ "attributes": attributes_json(&self.resource) })
+    }
+
+    fn scope_json() -> Value {
+        json!({ "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") })
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<(), RuntimeError> {
+        let url = format!("{}{}", self.endpoint, path);
+        let body = serde_json::to_vec(body)
+            .map_err(|e| RuntimeError::Other(format!("failed to encode export: {}", e)))?;
+        let result =
+            match tokio::time::timeout(self.timeout, http::post_json(&url, &[], &body)).await {
+                Ok(result) => result,
+                Err(_) => Err("timed out".into()),
+            };
+        result.map_err(|error| {
+            RuntimeError::Other(format!("OTLP export to {} failed: {}", url, error))
+        })
+    }
+}
@@ -1,36 +1,149 @@
 //! Prelude for convenient imports
 
 // Runtime
-pub use crate::config::RuntimeConfig;
-pub use crate::executor::Executor;
+pub use crate::builder::RuntimeBuilder;
+#[cfg(feature = "scheduler")]
+pub use crate::config::SchedulerConfig;
+pub use crate::config::{
+    ConfigChange, ConfigWatcher, EngineProfile, EngineTuning, RuntimeConfig, WakeupStrategy,
+};
+#[cfg(feature = "wasm")]
+pub use crate::executor::WasmExecutor;
+pub use crate::executor::{CpuSet, Executor, NumaNode, NumaTopology, PoolConfig};
+#[cfg(feature = "executor")]
+pub use crate::executor::{ExecutorPool, PoolSet};
 pub use crate::handle::RuntimeHandle;
-pub use crate::runtime::Runtime;
+pub use crate::registry::{AgentInfo, Registry, RegistryFilter};
+pub use crate::runtime::{Runtime, ShutdownReport};
+pub use crate::sim::SimRuntime;
+
+// Blocking facade
+#[cfg(feature = "scheduler")]
+pub use crate::blocking::BlockingScheduler;
+#[cfg(feature = "supervisor")]
+pub use crate::blocking::BlockingSupervisor;
 
 // Scheduler
+#[cfg(feature = "scheduler")]
 pub use crate::scheduler::{
-    FairShareScheduler, PolicyType, PriorityScheduler, RoundRobinScheduler, Scheduler,
-    SchedulingPolicy, Task, TaskQueue,
+    AgentPool, AgentStats, BoundedTaskQueue, CancellationToken, CapAction, CostBudget, CpuBudget,
+    CpuBudgetAction, CronExpr, DeadLetter, DeadLetterQueue, DeadLetterReason, Deployment,
+    DeploymentState, DeploymentVerdict, FairShareScheduler, HealthGate, HoldReason,
+    MemoryQueueStore, MisfirePolicy, MlfqDispatch, MlfqScheduler, OverflowPolicy, OverheadProfile,
+    OverrunAction, PersistentTaskQueue, PolicyEngine, PolicyType, PreemptionHandle, PriorityAging,
+    PriorityBand, PriorityQuota, PriorityScheduler, ProviderDemand, ProviderLimits, ProviderUsage,
+    PushOutcome, QueueStore, QuotaOverflow, RateLimit, RateLimiter, RecurringSchedule,
+    RolloutReport, RoundRobinScheduler, RuntimeCap, ScheduleTrigger, Scheduler, SchedulerEvent,
+    SchedulerSnapshot, SchedulerState, SchedulingPolicy, ShadowReport, ShadowScheduler, ShareGroup,
+    ShareUsage, SpeculationStats, Task, TaskFailure, TaskGroup, TaskHandle, TaskId, TaskQueue,
+    TaskRecord, TaskResult, TieBreak, WakeReason, Wakeup, WakeupStats, WorkStealingScheduler,
+    WorkerQueue,
+};
+
+#[cfg(feature = "persistence")]
+pub use crate::scheduler::FileQueueStore;
+
+// Cluster
+#[cfg(feature = "quic")]
+pub use crate::cluster::QuicTransport;
+#[cfg(feature = "cluster")]
+pub use crate::cluster::{
+    Cluster, ElectionRole, FileLeaseStore, InMemoryLeaseStore, InProcessTransport, LeaderElection,
+    Lease, LeaseStore, TcpTransport, WorkerNode, WorkerServer, WorkerStatus, WorkerTransport,
 };
 
 // Isolation
+#[cfg(feature = "isolation")]
 pub use crate::isolation::{
     IsolationConfig, Namespace, ResourceLimits, ResourceMonitor, ResourceUsage, Sandbox,
 };
+#[cfg(all(feature = "sandbox", unix))]
+pub use crate::sandbox::{ProcessOutput, ProcessSandbox};
 
 // Supervisor
+#[cfg(feature = "supervisor")]
 pub use crate::supervisor::{
-    CircuitBreaker, CircuitState, ExponentialBackoff, HealthCheck, HealthStatus, RestartPolicy,
-    RestartStrategy, Supervisor,
+    BackoffStrategy, BreakerClock, BudgetReport, Bulkhead, BulkheadPermit, ChildExit, ChildFailure,
+    ChildSpec, CircuitBreaker, CircuitSnapshot, CircuitState, CircuitStats, CircuitTransition,
+    DeadlineRetry, Decision, DecisionAction, DecisionLog, DefaultFailureClassifier, Escalation,
+    ExponentialBackoff, FailureClassifier, FailureKind, FibonacciBackoff, HealthCheck,
+    HealthCheckRunner, HealthProbe, Hedger, Jitter, MemoryAccount, MemoryLimited,
+    PhiAccrualDetector, ProbeConfig, ProtectedOperation, RestartClause, RestartContext,
+    RestartPolicy, RestartStrategy, ShutdownHooks, ShutdownReason, StopHandle, SupervisionTree,
+    Supervisor, SupervisorEvent, SupervisorSnapshot, TimeBudget, TrackingAllocator, TreeExit,
+    Watchdog,
 };
+#[cfg(feature = "probes")]
+pub use crate::supervisor::{CommandProbe, HttpProbe, TcpProbe};
 
 // Metrics
-pub use crate::metrics::{Collector, Metric, MetricType, MetricsExporter, MetricsRegistry};
+#[cfg(feature = "metrics")]
+pub use crate::metrics::{
+    Collector, LatencyHistogram, Metric, MetricType, MetricsExporter, MetricsRegistry,
+};
+#[cfg(feature = "otel")]
+pub use crate::otel::{OtelLayer, OtlpExporter};
+
+// Profiling
+pub use crate::profiling::{CumulativeCpuTime, Profiled, Profiler, ProfilingHook, SamplerGuard};
+
+// Budgets
+pub use crate::BudgetUsage;
+
+// Resource quotas
+pub use crate::resources::{QuotaUsage, QuotaViolation, ResourceQuota};
+
+// Health
+pub use crate::HealthStatus;
+
+// Timers
+pub use crate::timer::{
+    Clock, ManualClock, RuntimeClock, SharedClock, SystemClock, TimerBackend, TimerService,
+    VirtualClock,
+};
 
 // Tracing
+#[cfg(feature = "tracing")]
 pub use crate::tracing::Tracer;
 
 // Error
-pub use crate::RuntimeError;
+pub use crate::{
+    DefaultClassifier, ErrorClass, ErrorClassifier, QueueError, RuntimeError, SchedulerError,
+    SupervisorError,
+};
+
+// Hot reload
+#[cfg(feature = "hot-reload")]
+pub use crate::supervisor::{
+    Behavior, BehaviorLoader, DylibLoader, LoadedBehavior, ReloadableAgent,
+};
+
+// Lifecycle
+pub use crate::lifecycle::{AgentLifecycle, LifecycleHooks, LifecycleState, LifecycleTransition};
+
+// Limits
+pub use crate::{OversizePolicy, SizeLimit};
+
+// Links and monitors
+pub use crate::{ExitReason, LinkTable, MonitorRef, PeerSignal};
+
+// Mailboxes
+pub use crate::mailbox::{Backpressure, MailboxConfig, Mailboxes, Message};
+
+// Event bus
+#[cfg(feature = "bus")]
+pub use crate::bus::{DeliveryMode, Event, EventBus, TopicConfig};
+
+// Fault injection
+#[cfg(feature = "chaos")]
+pub use crate::chaos::{Chaos, ChaosConfig, ChaosStats};
+
+// Notifications
+#[cfg(feature = "webhooks")]
+pub use crate::notify::WebhookSink;
+pub use crate::notify::{
+    NotificationRouter, Notifier, Route, RuntimeEvent, Severity, StdoutNotifier,
+};
 
 // Re-export from core
 pub use agentropic_core::prelude::*;
@@ -0,0 +1,254 @@
+//! Execution profiling hooks
+//!
+//! Futures wrapped with [`Profiler::instrument`] report start and stop to the
+//! registered [`ProfilingHook`]s and accumulate per-agent CPU time, measured
+//! with the thread CPU clock around every poll. A sampler can additionally
+//! call hooks periodically for every agent that is on-CPU at that moment.
+
+use agentropic_core::AgentId;
+use futures::Future;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::thread::{JoinHandle, ThreadId};
+use std::time::{Duration, Instant};
+
+/// Callbacks invoked around profiled executions
+pub trait ProfilingHook: Send + Sync {
+    /// Called when an execution starts
+    fn on_start(&self, _agent_id: &AgentId) {}
+
+    /// Called when an execution completes or is dropped
+    fn on_stop(&self, _agent_id: &AgentId, _usage: &CumulativeCpuTime) {}
+
+    /// Called by the sampler for each agent currently being polled
+    fn on_sample(&self, _agent_id: &AgentId, _thread: ThreadId) {}
+}
+
+/// CPU and wall time accumulated by executions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CumulativeCpuTime {
+    cpu: Duration,
+    wall: Duration,
+    executions: u64,
+}
+
+impl CumulativeCpuTime {
+    /// Get CPU time
+    ///
+    /// Measured with the thread CPU clock on Unix; elsewhere the wall time
+    /// spent inside polls is used as an upper bound.
+    pub fn cpu(&self) -> Duration {
+        self.cpu
+    }
+
+    /// Get wall time from start to stop
+    pub fn wall(&self) -> Duration {
+        self.wall
+    }
+
+    /// Get number of executions
+    pub fn executions(&self) -> u64 {
+        self.executions
+    }
+
+    fn add(&mut self, other: &CumulativeCpuTime) {
+        self.cpu += other.cpu;
+        self.wall += other.wall;
+        self.executions += other.executions;
+    }
+}
+
+/// Profiler attributing execution time to agents
+#[derive(Default)]
+pub struct Profiler {
+    hooks: Vec<Arc<dyn ProfilingHook>>,
+    stats: Mutex<HashMap<AgentId, CumulativeCpuTime>>,
+    on_cpu: Mutex<HashMap<ThreadId, AgentId>>,
+}
+
+impl Profiler {
+    /// Create a profiler without hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hook
+    pub fn with_hook(mut self, hook: impl ProfilingHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Wrap a future so its execution is attributed to an agent
+    pub fn instrument<F: Future>(self: &Arc<Self>, agent_id: AgentId, future: F) -> Profiled<F> {
+        Profiled {
+            future: Box::pin(future),
+            profiler: self.clone(),
+            agent_id,
+            started: None,
+            usage: CumulativeCpuTime::default(),
+        }
+    }
+
+    /// Get accumulated time for an agent
+    pub fn cpu_time(&self, agent_id: &AgentId) -> CumulativeCpuTime {
+        self.stats
+            .lock()
+            .unwrap()
+            .get(agent_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Get accumulated time for all agents
+    pub fn stats(&self) -> HashMap<AgentId, CumulativeCpuTime> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Call `on_sample` for every agent currently being polled
+    pub fn sample(&self) {
+        let on_cpu: Vec<_> = self
+            .on_cpu
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(thread, agent_id)| (*thread, *agent_id))
+            .collect();
+        for (thread, agent_id) in on_cpu {
+            for hook in &self.hooks {
+                hook.on_sample(&agent_id, thread);
+            }
+        }
+    }
+
+    /// Sample on a background thread every `interval` until the guard is dropped
+    pub fn start_sampler(self: &Arc<Self>, interval: Duration) -> SamplerGuard {
+        let profiler: Weak<Self> = Arc::downgrade(self);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread = std::thread::spawn(move || {
+            while !thread_stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+                match profiler.upgrade() {
+                    Some(profiler) => profiler.sample(),
+                    None => break,
+                }
+            }
+        });
+
+        SamplerGuard {
+            stopped,
+            thread: Some(thread),
+        }
+    }
+
+    fn finish(&self, agent_id: &AgentId, usage: &CumulativeCpuTime) {
+        self.stats
+            .lock()
+            .unwrap()
+            .entry(*agent_id)
+            .or_default()
+            .add(usage);
+        for hook in &self.hooks {
+            hook.on_stop(agent_id, usage);
+        }
+    }
+}
+
+/// Stops the background sampler when dropped
+pub struct SamplerGuard {
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for SamplerGuard {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Future instrumented by a [`Profiler`]
+pub struct Profiled<F> {
+    future: Pin<Box<F>>,
+    profiler: Arc<Profiler>,
+    agent_id: AgentId,
+    started: Option<Instant>,
+    usage: CumulativeCpuTime,
+}
+
+impl<F: Future> Future for Profiled<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = &mut *self;
+        if this.started.is_none() {
+            this.started = Some(Instant::now());
+            for hook in &this.profiler.hooks {
+                hook.on_start(&this.agent_id);
+            }
+        }
+
+        let thread = std::thread::current().id();
+        this.profiler
+            .on_cpu
+            .lock()
+            .unwrap()
+            .insert(thread, this.agent_id);
+        let cpu_before = thread_cpu_time();
+        let wall_before = Instant::now();
+
+        let poll = this.future.as_mut().poll(cx);
+
+        this.usage.cpu += match (cpu_before, thread_cpu_time()) {
+            (Some(before), Some(after)) => after.saturating_sub(before),
+            _ => wall_before.elapsed(),
+        };
+        this.profiler.on_cpu.lock().unwrap().remove(&thread);
+
+        if poll.is_ready() {
+            this.complete();
+        }
+        poll
+    }
+}
+
+impl<F> Profiled<F> {
+    fn complete(&mut self) {
+        let Some(started) = self.started.take() else {
+            return;
+        };
+        self.usage.wall = started.elapsed();
+        self.usage.executions = 1;
+        self.profiler.finish(&self.agent_id, &self.usage);
+    }
+}
+
+impl<F> Drop for Profiled<F> {
+    fn drop(&mut self) {
+        // Cancelled executions still count
+        self.complete();
+    }
+}
+
+/// CPU time consumed by the current thread
+#[cfg(unix)]
+pub(crate) fn thread_cpu_time() -> Option<Duration> {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: clock_gettime only writes to the timespec we pass
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+    (result == 0).then(|| Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+/// CPU time consumed by the current thread
+#[cfg(not(unix))]
+pub(crate) fn thread_cpu_time() -> Option<Duration> {
+    None
+}
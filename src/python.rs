@@ -0,0 +1,296 @@
+//! Python bindings
+//!
+//! Build the extension module with `maturin develop --features python`.
+//! Python code submits tasks to the scheduler, completes the tasks it takes
+//! and awaits their results, and runs callables or coroutines under
+//! supervision, awaiting their outcome.
+
+use crate::scheduler::{PolicyType, Scheduler, SchedulingPolicy, Task, TaskId};
+use crate::supervisor::{ChildExit, ChildSpec, RestartPolicy, RestartStrategy, Supervisor};
+use crate::RuntimeError;
+use agentropic_core::AgentId;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn runtime_error(error: RuntimeError) -> PyErr {
+    PyRuntimeError::new_err(error.to_string())
+}
+
+fn child_error(error: PyErr) -> RuntimeError {
+    RuntimeError::ExecutionFailed(error.to_string())
+}
+
+fn exit_attempts(exit: ChildExit) -> PyResult<u32> {
+    match exit {
+        ChildExit::Completed { attempts } => Ok(attempts),
+        ChildExit::GaveUp {
+            attempts,
+            last_failure,
+        } => Err(PyRuntimeError::new_err(format!(
+            "gave up after {} attempts: {}",
+            attempts,
+            last_failure.message()
+        ))),
+    }
+}
+
+/// Task handed to Python by `Scheduler.next_task`
+#[pyclass(name = "Task", module = "agentropic_runtime", get_all)]
+pub struct PyTask {
+    /// Task ID returned by `Scheduler.submit`
+    id: u64,
+    /// Agent name
+    agent: String,
+    /// Task priority
+    priority: u32,
+    /// Submitted payload
+    payload: PyObject,
+}
+
+/// Pickle a payload so it travels on the task
+fn encode_payload(py: Python<'_>, payload: &PyObject) -> PyResult<Vec<u8>> {
+    py.import("pickle")?
+        .call_method1("dumps", (payload,))?
+        .extract()
+}
+
+/// Unpickle a task's payload, `None` if it has none
+fn decode_payload(py: Python<'_>, payload: &[u8]) -> PyResult<PyObject> {
+    if payload.is_empty() {
+        return Ok(py.None());
+    }
+    Ok(py
+        .import("pickle")?
+        .call_method1("loads", (pyo3::types::PyBytes::new(py, payload),))?
+        .unbind())
+}
+
+/// Scheduler exposed to Python
+#[pyclass(name = "Scheduler", module = "agentropic_runtime")]
+pub struct PyScheduler {
+    scheduler: Scheduler,
+    agents: HashMap<String, AgentId>,
+    names: HashMap<AgentId, String>,
+    /// Tasks taken by `next_task` and not yet completed, with their flight
+    running: HashMap<u64, (u64, Task)>,
+}
+
+#[pymethods]
+impl PyScheduler {
+    #[new]
+    #[pyo3(signature = (policy = "fcfs"))]
+    fn new(policy: &str) -> PyResult<Self> {
+        let policy_type = match policy {
+            "fcfs" => PolicyType::FCFS,
+            "priority" => PolicyType::Priority,
+            "round_robin" => PolicyType::RoundRobin,
+            "fair_share" => PolicyType::FairShare,
+            "edf" => PolicyType::EarliestDeadlineFirst,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown scheduling policy: {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            scheduler: Scheduler::new(SchedulingPolicy::new(policy_type)),
+            agents: HashMap::new(),
+            names: HashMap::new(),
+            running: HashMap::new(),
+        })
+    }
+
+    /// Submit a task for the named agent and return its ID
+    ///
+    /// The payload is pickled onto the task. Raises `RuntimeError` if the
+    /// scheduler rejects the task.
+    #[pyo3(signature = (agent, priority = 0, payload = None))]
+    fn submit(
+        &mut self,
+        py: Python<'_>,
+        agent: String,
+        priority: u32,
+        payload: Option<PyObject>,
+    ) -> PyResult<u64> {
+        let agent_id = *self.agents.entry(agent.clone()).or_default();
+        self.names.insert(agent_id, agent);
+
+        let mut task = Task::new(agent_id, priority);
+        if let Some(payload) = payload {
+            task = task.with_payload(encode_payload(py, &payload)?);
+        }
+        let handle = self.scheduler.submit(task).map_err(runtime_error)?;
+        Ok(handle.id().as_u64())
+    }
+
+    /// Take the next dispatchable task, or `None`
+    ///
+    /// Report how the task ended with `complete`.
+    fn next_task(&mut self, py: Python<'_>) -> PyResult<Option<PyTask>> {
+        let Some(mut task) = self.scheduler.next_task() else {
+            return Ok(None);
+        };
+        let py_task = PyTask {
+            id: task.id().as_u64(),
+            agent: self.names.get(task.agent_id()).cloned().unwrap_or_default(),
+            priority: task.priority(),
+            payload: decode_payload(py, task.payload())?,
+        };
+        let flight = self.scheduler.begin_flight(&mut task);
+        self.running.insert(py_task.id, (flight, task));
+        Ok(Some(py_task))
+    }
+
+    /// Report how a task taken by `next_task` ended
+    ///
+    /// Without an `error`, the task succeeds with `result` pickled as its
+    /// output. With one, it fails and is retried or dead-lettered. Raises
+    /// `ValueError` for a task that is not running.
+    #[pyo3(signature = (task_id, result = None, error = None))]
+    fn complete(
+        &mut self,
+        py: Python<'_>,
+        task_id: u64,
+        result: Option<PyObject>,
+        error: Option<String>,
+    ) -> PyResult<()> {
+        let Some((flight, mut task)) = self.running.remove(&task_id) else {
+            return Err(PyValueError::new_err(format!(
+                "task {} is not running",
+                task_id
+            )));
+        };
+        let outcome = match (error, result) {
+            (Some(error), _) => Err(RuntimeError::ExecutionFailed(error)),
+            (None, Some(result)) => {
+                let output = encode_payload(py, &result)?;
+                self.scheduler.accept_result(&mut task, output)
+            }
+            (None, None) => Ok(()),
+        };
+        self.scheduler.settle(flight, task, outcome, Duration::ZERO);
+        Ok(())
+    }
+
+    /// Await a submitted task's result
+    ///
+    /// Returns an awaitable resolving to the task's unpickled output, or
+    /// raising `RuntimeError` if the task failed for good.
+    fn wait<'py>(&self, py: Python<'py>, task_id: u64) -> PyResult<Bound<'py, PyAny>> {
+        let handle = self.scheduler.task_handle(TaskId::from_u64(task_id));
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            match handle.await {
+                Ok(output) => Python::with_gil(|py| decode_payload(py, &output)),
+                Err(failure) => Err(PyRuntimeError::new_err(failure.message().to_string())),
+            }
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.scheduler.queue().len() + self.scheduler.held_count()
+    }
+}
+
+/// Supervisor exposed to Python
+#[pyclass(name = "Supervisor", module = "agentropic_runtime")]
+pub struct PySupervisor {
+    name: String,
+    policy: RestartPolicy,
+}
+
+#[pymethods]
+impl PySupervisor {
+    #[new]
+    #[pyo3(signature = (name, strategy = "on_failure", max_retries = 3, backoff_seconds = 1))]
+    fn new(name: String, strategy: &str, max_retries: u32, backoff_seconds: u64) -> PyResult<Self> {
+        let strategy = match strategy {
+            "never" => RestartStrategy::Never,
+            "always" => RestartStrategy::Always,
+            "on_failure" => RestartStrategy::OnFailure,
+            "exponential_backoff" => RestartStrategy::ExponentialBackoff,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown restart strategy: {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            name,
+            policy: RestartPolicy::new(strategy)
+                .with_max_retries(max_retries)
+                .with_backoff_seconds(backoff_seconds),
+        })
+    }
+
+    /// Call `target` until it returns without raising, blocking between restarts
+    ///
+    /// Returns the number of attempts, or raises `RuntimeError` on giving up.
+    fn run(&self, py: Python<'_>, target: PyObject) -> PyResult<u32> {
+        let mut supervisor = crate::blocking::BlockingSupervisor::new(Supervisor::new(&self.name))
+            .map_err(runtime_error)?;
+        let agent_id = AgentId::new();
+        let target = Arc::new(target);
+
+        supervisor.add_child(ChildSpec::new(agent_id, self.policy.clone(), move |_| {
+            let target = target.clone();
+            async move {
+                Python::with_gil(|py| target.call0(py))
+                    .map(|_| ())
+                    .map_err(child_error)
+            }
+        }));
+
+        let exit = py
+            .allow_threads(|| supervisor.run_child(&agent_id))
+            .map_err(runtime_error)?;
+        exit_attempts(exit)
+    }
+
+    /// Await coroutines from `factory` until one completes without raising
+    ///
+    /// Returns an awaitable resolving to the number of attempts.
+    fn run_async<'py>(&self, py: Python<'py>, factory: PyObject) -> PyResult<Bound<'py, PyAny>> {
+        let locals = Arc::new(pyo3_async_runtimes::tokio::get_current_locals(py)?);
+        let mut supervisor = Supervisor::new(&self.name);
+        let agent_id = AgentId::new();
+        let factory = Arc::new(factory);
+
+        supervisor.add_child(ChildSpec::new(agent_id, self.policy.clone(), move |_| {
+            let factory = factory.clone();
+            let locals = locals.clone();
+            async move {
+                let attempt = Python::with_gil(|py| {
+                    let awaitable = factory.call0(py)?;
+                    pyo3_async_runtimes::into_future_with_locals(&locals, awaitable.into_bound(py))
+                })
+                .map_err(child_error)?;
+                attempt.await.map(|_| ()).map_err(child_error)
+            }
+        }));
+
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let exit = supervisor
+                .run_child(&agent_id)
+                .await
+                .map_err(runtime_error)?;
+            exit_attempts(exit)
+        })
+    }
+}
+
+/// Python module entry point
+#[pymodule]
+#[pyo3(name = "agentropic_runtime")]
+pub fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyScheduler>()?;
+    module.add_class::<PySupervisor>()?;
+    module.add_class::<PyTask>()?;
+    Ok(())
+}
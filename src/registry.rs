@@ -0,0 +1,239 @@
+//! Agent registry
+//!
+//! [`Registry`] is a read view of the agents a [`Runtime`](crate::Runtime)
+//! knows about. Each entry combines the agent's name and tags with what the
+//! runtime's subsystems know of it: its lifecycle state, the task it is
+//! running, and how often its supervisor restarted it. Operators and other
+//! agents query it with a [`RegistryFilter`] instead of reaching into the
+//! scheduler or supervisor.
+
+use crate::lifecycle::{AgentLifecycle, LifecycleState};
+use crate::runtime::AgentEntry;
+#[cfg(feature = "scheduler")]
+use crate::scheduler::{Scheduler, TaskId};
+#[cfg(feature = "supervisor")]
+use crate::supervisor::Supervisor;
+use agentropic_core::AgentId;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+#[cfg(any(feature = "scheduler", feature = "supervisor"))]
+use tokio::sync::Mutex;
+use tokio::sync::RwLock;
+
+/// What the runtime knows about an agent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentInfo {
+    /// Agent ID
+    pub id: AgentId,
+    /// Name the agent was spawned with
+    pub name: String,
+    /// Tags attached through [`Registry::tag`]
+    pub tags: BTreeSet<String>,
+    /// Lifecycle state, if the runtime tracks the agent's lifecycle
+    pub state: Option<LifecycleState>,
+    /// Task the scheduler is executing for the agent, if any
+    #[cfg(feature = "scheduler")]
+    pub current_task: Option<TaskId>,
+    /// Restarts by the supervisor
+    pub restarts: u32,
+}
+
+impl AgentInfo {
+    /// Check if the agent carries a tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Check if the scheduler is executing a task for the agent
+    pub fn is_busy(&self) -> bool {
+        #[cfg(feature = "scheduler")]
+        return self.current_task.is_some();
+        #[cfg(not(feature = "scheduler"))]
+        false
+    }
+}
+
+/// Criteria agents must all meet to be listed
+///
+/// The default filter matches every agent.
+#[derive(Debug, Clone, Default)]
+pub struct RegistryFilter {
+    name: Option<String>,
+    tags: BTreeSet<String>,
+    state: Option<LifecycleState>,
+    busy: Option<bool>,
+}
+
+impl RegistryFilter {
+    /// Create a filter matching every agent
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match agents whose name starts with a prefix
+    pub fn with_name(mut self, prefix: impl Into<String>) -> Self {
+        self.name = Some(prefix.into());
+        self
+    }
+
+    /// Match agents carrying a tag; repeat to require several
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    /// Match agents in a lifecycle state
+    pub fn with_state(mut self, state: LifecycleState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Match agents that are, or are not, executing a task
+    pub fn with_busy(mut self, busy: bool) -> Self {
+        self.busy = Some(busy);
+        self
+    }
+
+    /// Check if an agent meets every criterion
+    pub fn matches(&self, info: &AgentInfo) -> bool {
+        self.name
+            .as_deref()
+            .is_none_or(|prefix| info.name.starts_with(prefix))
+            && self.tags.is_subset(&info.tags)
+            && self.state.is_none_or(|state| info.state == Some(state))
+            && self.busy.is_none_or(|busy| info.is_busy() == busy)
+    }
+}
+
+/// Queryable view of a runtime's agents
+#[derive(Clone)]
+pub struct Registry {
+    agents: Arc<RwLock<HashMap<AgentId, AgentEntry>>>,
+    lifecycle: Option<Arc<AgentLifecycle>>,
+    #[cfg(feature = "scheduler")]
+    scheduler: Option<Arc<Mutex<Scheduler>>>,
+    #[cfg(feature = "supervisor")]
+    supervisor: Option<Arc<Mutex<Supervisor>>>,
+}
+
+impl Registry {
+    pub(crate) fn new(
+        agents: Arc<RwLock<HashMap<AgentId, AgentEntry>>>,
+        lifecycle: Option<Arc<AgentLifecycle>>,
+    ) -> Self {
+        Self {
+            agents,
+            lifecycle,
+            #[cfg(feature = "scheduler")]
+            scheduler: None,
+            #[cfg(feature = "supervisor")]
+            supervisor: None,
+        }
+    }
+
+    #[cfg(feature = "scheduler")]
+    pub(crate) fn with_scheduler(mut self, scheduler: Option<Arc<Mutex<Scheduler>>>) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    #[cfg(feature = "supervisor")]
+    pub(crate) fn with_supervisor(mut self, supervisor: Option<Arc<Mutex<Supervisor>>>) -> Self {
+        self.supervisor = supervisor;
+        self
+    }
+
+    /// Attach a tag to an agent, returning false if the agent is unknown
+    pub async fn tag(&self, agent_id: &AgentId, tag: impl Into<String>) -> bool {
+        match self.agents.write().await.get_mut(agent_id) {
+            Some(entry) => {
+                entry.tags.insert(tag.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a tag from an agent, returning whether it carried it
+    pub async fn untag(&self, agent_id: &AgentId, tag: &str) -> bool {
+        self.agents
+            .write()
+            .await
+            .get_mut(agent_id)
+            .is_some_and(|entry| entry.tags.remove(tag))
+    }
+
+    /// Get what the runtime knows about an agent
+    pub async fn get(&self, agent_id: &AgentId) -> Option<AgentInfo> {
+        let entry = self.agents.read().await.get(agent_id).cloned()?;
+        self.describe(vec![entry]).await.pop()
+    }
+
+    /// List agents matching a filter, ordered by name
+    pub async fn list(&self, filter: &RegistryFilter) -> Vec<AgentInfo> {
+        let entries = self.agents.read().await.values().cloned().collect();
+        let mut agents: Vec<_> = self
+            .describe(entries)
+            .await
+            .into_iter()
+            .filter(|info| filter.matches(info))
+            .collect();
+        agents.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then_with(|| a.id.to_string().cmp(&b.id.to_string()))
+        });
+        agents
+    }
+
+    /// Get number of registered agents
+    pub async fn len(&self) -> usize {
+        self.agents.read().await.len()
+    }
+
+    /// Check if no agents are registered
+    pub async fn is_empty(&self) -> bool {
+        self.agents.read().await.is_empty()
+    }
+
+    async fn describe(&self, entries: Vec<AgentEntry>) -> Vec<AgentInfo> {
+        #[cfg(feature = "scheduler")]
+        let running: HashMap<AgentId, TaskId> = match &self.scheduler {
+            Some(scheduler) => scheduler
+                .lock()
+                .await
+                .in_flight()
+                .map(|task| (*task.agent_id(), task.id()))
+                .collect(),
+            None => HashMap::new(),
+        };
+        #[cfg(feature = "supervisor")]
+        let supervisor = match &self.supervisor {
+            Some(supervisor) => Some(supervisor.lock().await),
+            None => None,
+        };
+        entries
+            .into_iter()
+            .map(|entry| {
+                #[cfg(feature = "supervisor")]
+                let restarts = supervisor
+                    .as_ref()
+                    .map_or(0, |supervisor| supervisor.restart_count(&entry.id));
+                #[cfg(not(feature = "supervisor"))]
+                let restarts = 0;
+                AgentInfo {
+                    id: entry.id,
+                    state: self
+                        .lifecycle
+                        .as_ref()
+                        .and_then(|lifecycle| lifecycle.state(&entry.id)),
+                    #[cfg(feature = "scheduler")]
+                    current_task: running.get(&entry.id).copied(),
+                    restarts,
+                    name: entry.name,
+                    tags: entry.tags,
+                }
+            })
+            .collect()
+    }
+}
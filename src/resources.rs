@@ -0,0 +1,163 @@
+//! Per-agent resource quotas
+//!
+//! A [`ResourceQuota`] bounds what one agent may use at once: concurrent
+//! tasks, CPU time per window, memory, and WebAssembly fuel per task and per
+//! window. The scheduler enforces the task, CPU, and fuel budget limits at
+//! dispatch time, holding tasks until the agent is back under quota; the
+//! supervisor enforces the memory ceiling by failing a child that exceeds
+//! it. The `wasm` executor enforces the per-task fuel and memory limits
+//! inside its sandbox and reports the fuel it burns to the scheduler.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Resource limits for one agent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceQuota {
+    max_concurrent: Option<usize>,
+    cpu_time: Option<(Duration, Duration)>,
+    max_memory: Option<u64>,
+    #[serde(default)]
+    max_fuel: Option<u64>,
+    #[serde(default)]
+    fuel_budget: Option<(u64, Duration)>,
+}
+
+impl ResourceQuota {
+    /// Create a quota without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit tasks running at once
+    pub fn with_max_concurrent(mut self, tasks: usize) -> Self {
+        self.max_concurrent = Some(tasks);
+        self
+    }
+
+    /// Limit execution time to `budget` per `window`
+    pub fn with_cpu_time(mut self, budget: Duration, window: Duration) -> Self {
+        self.cpu_time = Some((budget, window));
+        self
+    }
+
+    /// Limit memory in bytes
+    pub fn with_max_memory(mut self, bytes: u64) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Limit WebAssembly fuel one task may burn
+    pub fn with_max_fuel(mut self, fuel: u64) -> Self {
+        self.max_fuel = Some(fuel);
+        self
+    }
+
+    /// Limit WebAssembly fuel the agent's tasks may burn to `budget` per `window`
+    pub fn with_fuel_budget(mut self, budget: u64, window: Duration) -> Self {
+        self.fuel_budget = Some((budget, window));
+        self
+    }
+
+    /// Get maximum concurrent tasks
+    pub fn max_concurrent(&self) -> Option<usize> {
+        self.max_concurrent
+    }
+
+    /// Get execution time budget and its window
+    pub fn cpu_time(&self) -> Option<(Duration, Duration)> {
+        self.cpu_time
+    }
+
+    /// Get memory ceiling in bytes
+    pub fn max_memory(&self) -> Option<u64> {
+        self.max_memory
+    }
+
+    /// Get fuel one task may burn
+    pub fn max_fuel(&self) -> Option<u64> {
+        self.max_fuel
+    }
+
+    /// Get fuel budget and its window
+    pub fn fuel_budget(&self) -> Option<(u64, Duration)> {
+        self.fuel_budget
+    }
+
+    /// Find the first limit that `usage` exceeds or is at
+    ///
+    /// Being at the concurrency, CPU time, or fuel budget limit counts, since
+    /// no more work fits; memory only counts once over its ceiling.
+    pub fn check(&self, usage: &QuotaUsage) -> Option<QuotaViolation> {
+        if let Some(limit) = self.max_concurrent.filter(|limit| usage.running >= *limit) {
+            return Some(QuotaViolation::Concurrency {
+                running: usage.running,
+                limit,
+            });
+        }
+        if let Some((limit, _)) = self.cpu_time.filter(|(limit, _)| usage.cpu_time >= *limit) {
+            return Some(QuotaViolation::CpuTime {
+                used: usage.cpu_time,
+                limit,
+            });
+        }
+        if let Some((limit, _)) = self.fuel_budget.filter(|(limit, _)| usage.fuel >= *limit) {
+            return Some(QuotaViolation::Fuel {
+                used: usage.fuel,
+                limit,
+            });
+        }
+        self.max_memory
+            .filter(|limit| usage.memory > *limit)
+            .map(|limit| QuotaViolation::Memory {
+                used: usage.memory,
+                limit,
+            })
+    }
+}
+
+/// Resources an agent is using
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    /// Tasks running
+    pub running: usize,
+    /// Execution time used in the current window
+    pub cpu_time: Duration,
+    /// Memory in bytes
+    pub memory: u64,
+    /// WebAssembly fuel burned in the current fuel budget window
+    pub fuel: u64,
+}
+
+/// Limit of a [`ResourceQuota`] an agent reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaViolation {
+    /// Too many tasks running
+    Concurrency {
+        /// Tasks running
+        running: usize,
+        /// Allowed tasks
+        limit: usize,
+    },
+    /// CPU time budget used up for the window
+    CpuTime {
+        /// Time used in the window
+        used: Duration,
+        /// Allowed time per window
+        limit: Duration,
+    },
+    /// Fuel budget burned for the window
+    Fuel {
+        /// Fuel burned in the window
+        used: u64,
+        /// Allowed fuel per window
+        limit: u64,
+    },
+    /// Memory ceiling exceeded
+    Memory {
+        /// Bytes in use
+        used: u64,
+        /// Allowed bytes
+        limit: u64,
+    },
+}
@@ -0,0 +1,33 @@
+//! Seedable pseudo-random numbers for lotteries and fault injection
+
+use std::hash::{BuildHasher, RandomState};
+
+/// splitmix64 generator
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Create a generator seeded from the process's hash keys
+    pub(crate) fn new() -> Self {
+        Self::with_seed(RandomState::new().hash_one(0u8))
+    }
+
+    pub(crate) fn with_seed(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Next value in [0, 1)
+    pub(crate) fn next_unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -1,8 +1,29 @@
-use crate::{RuntimeConfig, RuntimeError, RuntimeHandle};
+#[cfg(feature = "scheduler")]
+use crate::builder::TaskExecutor;
+use crate::config::ConfigChange;
+use crate::lifecycle::AgentLifecycle;
+use crate::link::{ExitReason, LinkTable, MonitorRef, PeerSignal};
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsRegistry;
+use crate::registry::Registry;
+#[cfg(feature = "scheduler")]
+use crate::scheduler::{Scheduler, Task};
+#[cfg(feature = "supervisor")]
+use crate::supervisor::{ChildExit, DecisionLog, RestartPolicy, Supervisor};
+#[cfg(feature = "supervisor")]
+use crate::SupervisorError;
+use crate::{RuntimeBuilder, RuntimeConfig, RuntimeError, RuntimeHandle};
 use agentropic_core::AgentId;
-use std::collections::HashMap;
+#[cfg(feature = "scheduler")]
+use futures::future::OptionFuture;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+#[cfg(any(feature = "scheduler", feature = "supervisor"))]
+use tokio::task::JoinHandle;
+#[cfg(any(feature = "scheduler", feature = "supervisor"))]
+use tokio_util::sync::CancellationToken;
 
 // Shared AgentEntry struct (make it public for handle.rs)
 #[derive(Clone)]
@@ -10,6 +31,58 @@ use tokio::sync::RwLock;
 pub(crate) struct AgentEntry {
     pub id: AgentId,
     pub name: String,
+    pub tags: BTreeSet<String>,
+}
+
+/// Outcome of [`Runtime::shutdown`]
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    /// Tasks still queued, never dispatched
+    #[cfg(feature = "scheduler")]
+    pub abandoned_queued: Vec<Task>,
+    /// Tasks aborted because they did not finish within the grace period
+    #[cfg(feature = "scheduler")]
+    pub abandoned_in_flight: Vec<Task>,
+    /// Whether the grace period ran out before in-flight tasks or children
+    /// finished
+    pub timed_out: bool,
+    /// Supervisor decisions made up to shutdown
+    #[cfg(feature = "supervisor")]
+    pub decisions: DecisionLog,
+    /// Children that exited within the grace period, and how
+    #[cfg(feature = "supervisor")]
+    pub children: Vec<(AgentId, ChildExit)>,
+    /// Children killed because they did not exit within the grace period
+    #[cfg(feature = "supervisor")]
+    pub killed_children: Vec<AgentId>,
+}
+
+impl ShutdownReport {
+    /// Get number of tasks that were abandoned
+    pub fn abandoned(&self) -> usize {
+        #[cfg(feature = "scheduler")]
+        return self.abandoned_queued.len() + self.abandoned_in_flight.len();
+        #[cfg(not(feature = "scheduler"))]
+        0
+    }
+}
+
+/// How each supervised child exited
+#[cfg(feature = "supervisor")]
+type ChildExits = Vec<(AgentId, Result<ChildExit, RuntimeError>)>;
+
+/// Loops started by [`Runtime::start`] and joined by [`Runtime::shutdown`]
+#[cfg(any(feature = "scheduler", feature = "supervisor"))]
+struct Background {
+    shutdown: CancellationToken,
+    #[cfg(feature = "supervisor")]
+    kill: CancellationToken,
+    #[cfg(feature = "scheduler")]
+    scheduler: Option<JoinHandle<()>>,
+    #[cfg(feature = "supervisor")]
+    supervisor: Option<JoinHandle<ChildExits>>,
+    #[cfg(feature = "admin")]
+    admin: Option<(std::net::SocketAddr, JoinHandle<()>)>,
 }
 
 /// Agent runtime engine
@@ -17,9 +90,77 @@ pub struct Runtime {
     config: RuntimeConfig,
     agents: Arc<RwLock<HashMap<AgentId, AgentEntry>>>,
     running: Arc<RwLock<bool>>,
+    initial_agents: Vec<(AgentId, String)>,
+    links: Arc<Mutex<LinkTable>>,
+    signals: Arc<Mutex<HashMap<AgentId, VecDeque<PeerSignal>>>>,
+    lifecycle: Option<Arc<AgentLifecycle>>,
+    #[cfg(feature = "scheduler")]
+    scheduler: Option<Arc<Mutex<Scheduler>>>,
+    #[cfg(feature = "supervisor")]
+    supervisor: Option<Arc<Mutex<Supervisor>>>,
+    #[cfg(feature = "persistence")]
+    state_path: Option<std::path::PathBuf>,
+    #[cfg(feature = "scheduler")]
+    executor: Option<TaskExecutor>,
+    #[cfg(feature = "admin")]
+    admin: Option<std::net::SocketAddr>,
+    #[cfg(any(feature = "scheduler", feature = "supervisor"))]
+    background: Mutex<Option<Background>>,
+    /// Restart policies agents had before a configuration replaced them
+    #[cfg(feature = "supervisor")]
+    replaced_policies: HashMap<AgentId, RestartPolicy>,
 }
 
 impl Runtime {
+    /// Start building a runtime with its subsystems
+    pub fn builder() -> RuntimeBuilder {
+        RuntimeBuilder::new()
+    }
+
+    pub(crate) fn from_builder(builder: RuntimeBuilder) -> Self {
+        #[cfg(feature = "scheduler")]
+        let tuning = builder.config.tuning();
+        Self {
+            config: builder.config,
+            agents: Arc::new(RwLock::new(HashMap::new())),
+            running: Arc::new(RwLock::new(false)),
+            initial_agents: builder.agents,
+            links: Arc::new(Mutex::new(LinkTable::new())),
+            signals: Arc::new(Mutex::new(HashMap::new())),
+            lifecycle: builder.lifecycle.clone(),
+            #[cfg(feature = "scheduler")]
+            scheduler: builder.scheduler.map(|mut scheduler| {
+                if let Some(lifecycle) = &builder.lifecycle {
+                    scheduler = scheduler.with_lifecycle(lifecycle.clone());
+                }
+                if let Some(clock) = &builder.clock {
+                    scheduler = scheduler.with_clock(clock.clone());
+                }
+                Arc::new(Mutex::new(scheduler.with_tuning(tuning)))
+            }),
+            #[cfg(feature = "supervisor")]
+            supervisor: builder.supervisor.map(|mut supervisor| {
+                if let Some(lifecycle) = &builder.lifecycle {
+                    supervisor = supervisor.with_lifecycle(lifecycle.clone());
+                }
+                if let Some(clock) = &builder.clock {
+                    supervisor = supervisor.with_clock(clock.clone());
+                }
+                Arc::new(Mutex::new(supervisor))
+            }),
+            #[cfg(feature = "persistence")]
+            state_path: builder.state_path,
+            #[cfg(feature = "scheduler")]
+            executor: builder.executor,
+            #[cfg(feature = "admin")]
+            admin: builder.admin,
+            #[cfg(any(feature = "scheduler", feature = "supervisor"))]
+            background: Mutex::new(None),
+            #[cfg(feature = "supervisor")]
+            replaced_policies: HashMap::new(),
+        }
+    }
+
     /// Create a new runtime with default configuration
     pub fn new() -> Self {
         Self::with_config(RuntimeConfig::default())
@@ -27,11 +168,7 @@ impl Runtime {
 
     /// Create a runtime with custom configuration
     pub fn with_config(config: RuntimeConfig) -> Self {
-        Self {
-            config,
-            agents: Arc::new(RwLock::new(HashMap::new())),
-            running: Arc::new(RwLock::new(false)),
-        }
+        RuntimeBuilder::new().with_config(config).build()
     }
 
     /// Get runtime configuration
@@ -39,6 +176,68 @@ impl Runtime {
         &self.config
     }
 
+    /// Validate a new configuration and apply its changes live
+    ///
+    /// The scheduler switches policy, queue bound, and tuning, and the
+    /// supervisor takes the new restart policies of agents registered under
+    /// the configured names; an agent whose policy was removed goes back to
+    /// the policy it had before the configuration set one. Settings marked
+    /// [`ConfigChange::RequiresRestart`], such as health probes and the
+    /// default timeout, are only stored. Returns every change found.
+    pub async fn apply_config(
+        &mut self,
+        config: RuntimeConfig,
+    ) -> Result<Vec<ConfigChange>, RuntimeError> {
+        config.validate()?;
+        let changes = self.config.diff(&config);
+        #[cfg(feature = "scheduler")]
+        if let Some(scheduler) = &self.scheduler {
+            let mut scheduler = scheduler.lock().await;
+            for change in &changes {
+                match change {
+                    ConfigChange::Profile { .. } => scheduler.set_tuning(config.tuning()),
+                    ConfigChange::Policy { to, .. } => {
+                        scheduler.set_policy(crate::scheduler::SchedulingPolicy::new(*to))
+                    }
+                    ConfigChange::QueueCapacity { to, .. } => scheduler.set_queue_capacity(*to),
+                    _ => {}
+                }
+            }
+        }
+        #[cfg(feature = "supervisor")]
+        if let Some(supervisor) = &self.supervisor {
+            let agents = self.agents.read().await;
+            let mut supervisor = supervisor.lock().await;
+            for change in &changes {
+                let ConfigChange::RestartPolicy { agent } = change else {
+                    continue;
+                };
+                for entry in agents.values().filter(|entry| &entry.name == agent) {
+                    let policy = match config.restart_policies.get(agent) {
+                        Some(policy) => {
+                            if let Some(current) = supervisor.get_policy(&entry.id) {
+                                self.replaced_policies
+                                    .entry(entry.id)
+                                    .or_insert_with(|| current.clone());
+                            }
+                            policy.clone()
+                        }
+                        None => match self.replaced_policies.remove(&entry.id) {
+                            Some(original) => original,
+                            None => continue,
+                        },
+                    };
+                    supervisor.set_restart_policy(&entry.id, policy);
+                }
+            }
+        }
+        for change in &changes {
+            tracing::info!("Applied configuration change: {:?}", change);
+        }
+        self.config = config;
+        Ok(changes)
+    }
+
     /// Spawn an agent
     pub async fn spawn(
         &self,
@@ -50,12 +249,137 @@ impl Runtime {
         let entry = AgentEntry {
             id: agent_id,
             name: name.into(),
+            tags: BTreeSet::new(),
         };
 
         agents.insert(agent_id, entry);
         Ok(())
     }
 
+    /// Link two agents so that either dying abnormally terminates the other
+    pub async fn link(&self, a: AgentId, b: AgentId) -> Result<(), RuntimeError> {
+        let agents = self.agents.read().await;
+        for agent_id in [a, b] {
+            if !agents.contains_key(&agent_id) {
+                return Err(RuntimeError::AgentNotFound(agent_id.to_string()));
+            }
+        }
+        self.links.lock().await.link(a, b);
+        Ok(())
+    }
+
+    /// Remove a link between two agents
+    pub async fn unlink(&self, a: &AgentId, b: &AgentId) {
+        self.links.lock().await.unlink(a, b);
+    }
+
+    /// Deliver a down-signal to `watcher` when `target` dies
+    ///
+    /// If `target` does not exist, the signal is delivered immediately with
+    /// [`ExitReason::NoAgent`].
+    pub async fn monitor(&self, watcher: AgentId, target: AgentId) -> MonitorRef {
+        let exists = self.has_agent(&target).await;
+        let mut links = self.links.lock().await;
+        let monitor = links.monitor(watcher, target);
+        if !exists {
+            let effects = links.agent_down(target, ExitReason::NoAgent);
+            drop(links);
+            self.deliver(effects.signals).await;
+        }
+        monitor
+    }
+
+    /// Remove a monitor
+    pub async fn demonitor(&self, monitor: MonitorRef) {
+        self.links.lock().await.demonitor(monitor);
+    }
+
+    /// Receive exits from linked peers as signals instead of being terminated
+    pub async fn trap_exits(&self, agent_id: AgentId, trap: bool) {
+        self.links.lock().await.trap_exits(agent_id, trap);
+    }
+
+    /// Record that an agent stopped, propagating through links and monitors
+    ///
+    /// The agent and any linked peers terminated with it are removed from the
+    /// runtime. Returns the terminated peers.
+    pub async fn exit(&self, agent_id: AgentId, reason: ExitReason) -> Vec<AgentId> {
+        let effects = self.links.lock().await.agent_down(agent_id, reason);
+        {
+            let mut agents = self.agents.write().await;
+            let mut signals = self.signals.lock().await;
+            for dead in std::iter::once(&agent_id).chain(&effects.terminated) {
+                agents.remove(dead);
+                signals.remove(dead);
+            }
+        }
+        self.deliver(effects.signals).await;
+        for peer in &effects.terminated {
+            tracing::info!(agent = %peer, "Agent terminated by linked exit");
+        }
+        effects.terminated
+    }
+
+    /// Take the link and monitor signals delivered to an agent
+    pub async fn take_signals(&self, agent_id: &AgentId) -> Vec<PeerSignal> {
+        self.signals
+            .lock()
+            .await
+            .remove(agent_id)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    async fn deliver(&self, deliveries: Vec<(AgentId, PeerSignal)>) {
+        let agents = self.agents.read().await;
+        let mut signals = self.signals.lock().await;
+        for (agent_id, signal) in deliveries {
+            if agents.contains_key(&agent_id) {
+                signals.entry(agent_id).or_default().push_back(signal);
+            }
+        }
+    }
+
+    /// Get the agent lifecycle, if the runtime was built with one
+    pub fn lifecycle(&self) -> Option<Arc<AgentLifecycle>> {
+        self.lifecycle.clone()
+    }
+
+    /// Get the scheduler, if the runtime was built with one
+    #[cfg(feature = "scheduler")]
+    pub fn scheduler(&self) -> Option<Arc<Mutex<Scheduler>>> {
+        self.scheduler.clone()
+    }
+
+    /// Get the supervisor, if the runtime was built with one
+    #[cfg(feature = "supervisor")]
+    pub fn supervisor(&self) -> Option<Arc<Mutex<Supervisor>>> {
+        self.supervisor.clone()
+    }
+
+    /// Record scheduler and supervisor metrics into a registry
+    #[cfg(feature = "metrics")]
+    pub async fn collect_metrics(&self, registry: &mut MetricsRegistry) {
+        collect_metrics(
+            #[cfg(feature = "scheduler")]
+            self.scheduler.as_ref(),
+            #[cfg(feature = "supervisor")]
+            self.supervisor.as_ref(),
+            registry,
+        )
+        .await
+    }
+
+    /// Get the address the admin endpoint listens on, once started
+    #[cfg(feature = "admin")]
+    pub async fn admin_address(&self) -> Option<std::net::SocketAddr> {
+        let background = self.background.lock().await;
+        background
+            .as_ref()
+            .and_then(|background| background.admin.as_ref())
+            .map(|(address, _)| *address)
+    }
+
     /// Get agent count
     pub async fn agent_count(&self) -> usize {
         self.agents.read().await.len()
@@ -67,13 +391,104 @@ impl Runtime {
     }
 
     /// Start the runtime
+    ///
+    /// Registers agents added to the builder and restores scheduler state
+    /// from the configured state path, if any. The supervisor's children,
+    /// the scheduler loop dispatching to the builder's executor, and the
+    /// admin endpoint then run in the background until [`Runtime::shutdown`].
+    /// Starting a started runtime does nothing.
     pub async fn start(&self) -> Result<(), RuntimeError> {
+        #[cfg(any(feature = "scheduler", feature = "supervisor"))]
+        let mut background = self.background.lock().await;
+        #[cfg(any(feature = "scheduler", feature = "supervisor"))]
+        if background.is_some() {
+            return Ok(());
+        }
+        #[cfg(feature = "persistence")]
+        if let (Some(path), Some(scheduler)) = (&self.state_path, &self.scheduler) {
+            if let Some(state) = crate::scheduler::SchedulerState::load(path)? {
+                scheduler.lock().await.warm_start(state);
+            }
+        }
+        #[cfg(all(feature = "persistence", feature = "supervisor"))]
+        if let (Some(path), Some(supervisor)) = (&self.state_path, &self.supervisor) {
+            let snapshot =
+                crate::supervisor::SupervisorSnapshot::load(supervisor_state_path(path))?;
+            if let Some(snapshot) = snapshot {
+                supervisor.lock().await.restore(snapshot);
+            }
+        }
+        for (agent_id, name) in &self.initial_agents {
+            self.spawn(*agent_id, name.clone()).await?;
+        }
+        #[cfg(any(feature = "scheduler", feature = "supervisor"))]
+        {
+            *background = Some(self.launch().await?);
+        }
         let mut running = self.running.write().await;
         *running = true;
         tracing::info!("Runtime started");
         Ok(())
     }
 
+    /// Spawn the supervisor, scheduler, and admin loops
+    #[cfg(any(feature = "scheduler", feature = "supervisor"))]
+    async fn launch(&self) -> Result<Background, RuntimeError> {
+        let shutdown = CancellationToken::new();
+        #[cfg(feature = "supervisor")]
+        let kill = CancellationToken::new();
+        #[cfg(feature = "admin")]
+        let admin = match self.admin {
+            Some(address) => {
+                let listener = tokio::net::TcpListener::bind(address).await.map_err(|e| {
+                    RuntimeError::ConfigError(format!(
+                        "admin endpoint cannot bind {}: {}",
+                        address, e
+                    ))
+                })?;
+                let address = listener.local_addr().unwrap_or(address);
+                let admin = crate::admin::Admin {
+                    registry: self.registry(),
+                    running: self.running.clone(),
+                    #[cfg(feature = "scheduler")]
+                    scheduler: self.scheduler.clone(),
+                    supervisor: self.supervisor.clone(),
+                };
+                let serving = tokio::spawn(admin.serve(listener, shutdown.clone()));
+                tracing::info!("Admin endpoint listening on {}", address);
+                Some((address, serving))
+            }
+            None => None,
+        };
+        #[cfg(feature = "scheduler")]
+        let scheduler = match (&self.scheduler, &self.executor) {
+            (Some(scheduler), Some(executor)) => {
+                let executor = executor.clone();
+                Some(tokio::spawn(Scheduler::run_with_results(
+                    scheduler.clone(),
+                    move |task: &Task| executor(task),
+                    shutdown.clone(),
+                )))
+            }
+            _ => None,
+        };
+        #[cfg(feature = "supervisor")]
+        let supervisor = self.supervisor.clone().map(|supervisor| {
+            tokio::spawn(Supervisor::run(supervisor, shutdown.clone(), kill.clone()))
+        });
+        Ok(Background {
+            shutdown,
+            #[cfg(feature = "supervisor")]
+            kill,
+            #[cfg(feature = "scheduler")]
+            scheduler,
+            #[cfg(feature = "supervisor")]
+            supervisor,
+            #[cfg(feature = "admin")]
+            admin,
+        })
+    }
+
     /// Stop the runtime
     pub async fn stop(&self) -> Result<(), RuntimeError> {
         let mut running = self.running.write().await;
@@ -87,19 +502,204 @@ impl Runtime {
         *self.running.read().await
     }
 
+    /// Get a queryable view of the runtime's agents
+    pub fn registry(&self) -> Registry {
+        let registry = Registry::new(self.agents.clone(), self.lifecycle.clone());
+        #[cfg(feature = "scheduler")]
+        let registry = registry.with_scheduler(self.scheduler.clone());
+        #[cfg(feature = "supervisor")]
+        let registry = registry.with_supervisor(self.supervisor.clone());
+        registry
+    }
+
     /// Get runtime handle
     pub fn handle(&self) -> RuntimeHandle {
         RuntimeHandle::new(self.agents.clone(), self.running.clone())
     }
 
     /// Shutdown the runtime
-    pub async fn shutdown(self) -> Result<(), RuntimeError> {
+    ///
+    /// The scheduler stops accepting and dispatching tasks, and supervised
+    /// children are stopped through their stop handles. In-flight tasks and
+    /// children both get `grace` to finish before they are aborted.
+    /// Scheduler state, including aborted tasks, is then saved to the
+    /// configured state path, if any, with a supervisor snapshot next to it.
+    pub async fn shutdown(self, grace: Duration) -> Result<ShutdownReport, RuntimeError> {
         self.stop().await?;
+        #[cfg_attr(
+            not(any(feature = "scheduler", feature = "supervisor")),
+            allow(unused_mut)
+        )]
+        let mut report = ShutdownReport::default();
+        #[cfg(any(feature = "scheduler", feature = "supervisor"))]
+        {
+            let mut background = self.background.lock().await.take();
+            if let Some(background) = &background {
+                background.shutdown.cancel();
+            }
+            // Tasks and children settle side by side, each within `grace`
+            #[cfg(feature = "scheduler")]
+            let tasks =
+                self.settle_tasks(grace, background.as_mut().and_then(|b| b.scheduler.take()));
+            #[cfg(not(feature = "scheduler"))]
+            let tasks = async { false };
+            #[cfg(feature = "supervisor")]
+            {
+                let children = self.settle_children(
+                    grace,
+                    background
+                        .as_mut()
+                        .and_then(|b| Some((b.supervisor.take()?, b.kill.clone()))),
+                );
+                let (tasks_timed_out, (exits, children_timed_out)) =
+                    futures::join!(tasks, children);
+                report.timed_out = tasks_timed_out || children_timed_out;
+                for (agent_id, exit) in exits {
+                    match exit {
+                        Ok(exit) => report.children.push((agent_id, exit)),
+                        Err(RuntimeError::Supervisor(SupervisorError::Killed(_))) => {
+                            report.killed_children.push(agent_id)
+                        }
+                        Err(error) => {
+                            tracing::warn!(agent = %agent_id, "Child could not be supervised: {}", error)
+                        }
+                    }
+                }
+            }
+            #[cfg(not(feature = "supervisor"))]
+            {
+                report.timed_out = tasks.await;
+            }
+            #[cfg(feature = "admin")]
+            if let Some((_, serving)) = background.and_then(|b| b.admin) {
+                let _ = serving.await;
+            }
+        }
+        #[cfg(not(any(feature = "scheduler", feature = "supervisor")))]
+        let _ = grace;
+
+        #[cfg(feature = "scheduler")]
+        if let Some(scheduler) = &self.scheduler {
+            let scheduler = scheduler.lock().await;
+            report.abandoned_queued = scheduler.queued().cloned().collect();
+            report.abandoned_in_flight = scheduler.in_flight().cloned().collect();
+            #[cfg(feature = "persistence")]
+            if let Some(path) = &self.state_path {
+                scheduler.state().save(path)?;
+            }
+        }
+        #[cfg(feature = "supervisor")]
+        if let Some(supervisor) = &self.supervisor {
+            let supervisor = supervisor.lock().await;
+            report.decisions = supervisor.decision_log().clone();
+            #[cfg(feature = "persistence")]
+            if let Some(path) = &self.state_path {
+                supervisor.snapshot().save(supervisor_state_path(path))?;
+            }
+        }
+
         let mut agents = self.agents.write().await;
         agents.clear();
-        tracing::info!("Runtime shutdown complete");
-        Ok(())
+        tracing::info!(
+            "Runtime shutdown complete, {} tasks abandoned",
+            report.abandoned()
+        );
+        Ok(report)
+    }
+
+    /// Drain the scheduler and wait up to `grace` for in-flight tasks,
+    /// aborting them afterwards, then wait for the scheduler loop to stop
+    ///
+    /// Returns whether the grace period ran out.
+    #[cfg(feature = "scheduler")]
+    async fn settle_tasks(&self, grace: Duration, running: Option<JoinHandle<()>>) -> bool {
+        let Some(scheduler) = &self.scheduler else {
+            return false;
+        };
+        let (mut flights, clock) = {
+            let mut scheduler = scheduler.lock().await;
+            scheduler.drain();
+            (scheduler.watch_in_flight(), scheduler.clock())
+        };
+        let mut timed_out = false;
+        if *flights.borrow() > 0 {
+            let settled = std::pin::pin!(flights.wait_for(|count| *count == 0));
+            let expired = std::pin::pin!(clock.sleep(grace));
+            if let futures::future::Either::Right(_) =
+                futures::future::select(settled, expired).await
+            {
+                timed_out = true;
+                scheduler.lock().await.abort_in_flight();
+            }
+        }
+        if let Some(Err(error)) = OptionFuture::from(running).await {
+            tracing::error!("Scheduler loop panicked: {}", error);
+        }
+        timed_out
+    }
+
+    /// Wait up to `grace` for stopped children to exit, killing the rest
+    /// through `kill`
+    ///
+    /// Returns how each child exited and whether the grace period ran out.
+    #[cfg(feature = "supervisor")]
+    async fn settle_children(
+        &self,
+        grace: Duration,
+        running: Option<(JoinHandle<ChildExits>, CancellationToken)>,
+    ) -> (ChildExits, bool) {
+        let (Some(supervisor), Some((mut running, kill))) = (&self.supervisor, running) else {
+            return (Vec::new(), false);
+        };
+        let clock = supervisor.lock().await.clock();
+        let mut timed_out = false;
+        let exited = tokio::select! {
+            exited = &mut running => exited,
+            _ = clock.sleep(grace) => {
+                timed_out = true;
+                kill.cancel();
+                running.await
+            }
+        };
+        match exited {
+            Ok(exits) => (exits, timed_out),
+            Err(error) => {
+                tracing::error!("Supervisor loop panicked: {}", error);
+                (Vec::new(), timed_out)
+            }
+        }
+    }
+}
+
+/// Path of the supervisor snapshot saved next to scheduler state at `path`
+#[cfg(all(feature = "persistence", feature = "supervisor"))]
+fn supervisor_state_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".supervisor.json");
+    path.with_file_name(name)
+}
+
+/// Record scheduler and supervisor metrics into a registry
+#[cfg(feature = "metrics")]
+pub(crate) async fn collect_metrics(
+    #[cfg(feature = "scheduler")] scheduler: Option<&Arc<Mutex<Scheduler>>>,
+    #[cfg(feature = "supervisor")] supervisor: Option<&Arc<Mutex<Supervisor>>>,
+    registry: &mut MetricsRegistry,
+) {
+    #[cfg(feature = "scheduler")]
+    if let Some(scheduler) = scheduler {
+        let mut collector = crate::metrics::Collector::new();
+        scheduler.lock().await.collect_metrics(&mut collector);
+        registry.register("scheduler", collector);
+    }
+    #[cfg(feature = "supervisor")]
+    if let Some(supervisor) = supervisor {
+        let mut collector = crate::metrics::Collector::new();
+        supervisor.lock().await.collect_metrics(&mut collector);
+        registry.register("supervisor", collector);
     }
+    #[cfg(not(any(feature = "scheduler", feature = "supervisor")))]
+    let _ = registry;
 }
 
 impl Default for Runtime {
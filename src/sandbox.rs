@@ -0,0 +1,366 @@
+//! Sandboxed process execution for agent tools
+//!
+//! A [`ProcessSandbox`] runs a tool as a separate OS process with resource
+//! limits applied through `setrlimit` and stdio captured. On Linux it also
+//! disables privilege escalation with `no_new_privs`, and can cut the process
+//! off the network and confine it to a filesystem root using unprivileged
+//! user, mount, and network namespaces. Where namespaces are unavailable the
+//! spawn fails, unless isolation is marked best-effort. Other Unix systems
+//! get the resource limits only.
+//!
+//! [`ProcessSandbox::child_spec`] turns a sandbox into a supervised child, so
+//! the process is restarted according to its [`RestartPolicy`].
+
+use crate::isolation::{IsolationConfig, ResourceLimits};
+use crate::supervisor::{ChildSpec, RestartPolicy};
+use crate::RuntimeError;
+use agentropic_core::AgentId;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+
+/// Output of a sandboxed process
+#[derive(Debug, Clone)]
+pub struct ProcessOutput {
+    /// Exit status
+    pub status: ExitStatus,
+    /// Captured standard output, truncated to the output limit
+    pub stdout: Vec<u8>,
+    /// Captured standard error, truncated to the output limit
+    pub stderr: Vec<u8>,
+}
+
+impl ProcessOutput {
+    /// Check if the process exited successfully
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+}
+
+/// Tool process run under resource limits and isolation
+#[derive(Debug, Clone)]
+pub struct ProcessSandbox {
+    program: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    working_dir: Option<PathBuf>,
+    limits: ResourceLimits,
+    cpu_time: Option<Duration>,
+    timeout: Option<Duration>,
+    output_limit: usize,
+    network_isolation: bool,
+    root: Option<PathBuf>,
+    best_effort: bool,
+}
+
+impl ProcessSandbox {
+    /// Create a sandbox for a program
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            env: Vec::new(),
+            working_dir: None,
+            limits: ResourceLimits::default(),
+            cpu_time: None,
+            timeout: None,
+            output_limit: 1024 * 1024,
+            network_isolation: false,
+            root: None,
+            best_effort: false,
+        }
+    }
+
+    /// Add argument
+    pub fn with_arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Set an environment variable; the process inherits no other variables
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Set working directory
+    pub fn with_working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// Set memory and file descriptor limits
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Apply memory limit and network isolation from an isolation configuration
+    pub fn with_isolation(mut self, config: &IsolationConfig) -> Self {
+        if config.enabled {
+            self.limits.max_memory = self.limits.max_memory.min(config.memory_limit);
+            self.network_isolation = config.network_isolation;
+        }
+        self
+    }
+
+    /// Limit CPU time; the kernel kills the process once it is used up
+    pub fn with_cpu_time(mut self, limit: Duration) -> Self {
+        self.cpu_time = Some(limit);
+        self
+    }
+
+    /// Kill the process if it runs longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set maximum bytes captured from each of stdout and stderr
+    pub fn with_output_limit(mut self, bytes: usize) -> Self {
+        self.output_limit = bytes;
+        self
+    }
+
+    /// Run the process in its own network namespace, without network access
+    pub fn with_network_isolation(mut self, enable: bool) -> Self {
+        self.network_isolation = enable;
+        self
+    }
+
+    /// Confine the process to a filesystem root
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Run without namespace isolation where it is unavailable instead of failing
+    pub fn with_best_effort_isolation(mut self, best_effort: bool) -> Self {
+        self.best_effort = best_effort;
+        self
+    }
+
+    /// Get program
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Get resource limits
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Run the process to completion
+    pub async fn run(&self) -> Result<ProcessOutput, RuntimeError> {
+        self.run_with_input(&[]).await
+    }
+
+    /// Run the process to completion, writing `input` to its standard input
+    pub async fn run_with_input(&self, input: &[u8]) -> Result<ProcessOutput, RuntimeError> {
+        let mut command = self.command()?;
+        let mut child = command.spawn().map_err(|e| {
+            RuntimeError::IsolationError(format!("failed to spawn {}: {}", self.program, e))
+        })?;
+
+        let mut stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let run = async {
+            let feed = async {
+                if let Some(stdin) = stdin.as_mut() {
+                    // The process may exit without reading its input
+                    let _ = stdin.write_all(input).await;
+                }
+                drop(stdin);
+            };
+            let (_, stdout, stderr, status) = tokio::join!(
+                feed,
+                capture(stdout, self.output_limit),
+                capture(stderr, self.output_limit),
+                child.wait(),
+            );
+            Ok::<_, io::Error>(ProcessOutput {
+                status: status?,
+                stdout: stdout?,
+                stderr: stderr?,
+            })
+        };
+
+        let output = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, run).await.map_err(|_| {
+                RuntimeError::ExecutionFailed(format!(
+                    "{} timed out after {:?}",
+                    self.program, timeout
+                ))
+            })?,
+            None => run.await,
+        };
+        output.map_err(|e| RuntimeError::ExecutionFailed(format!("{}: {}", self.program, e)))
+    }
+
+    /// Supervise the process as a child, treating an unsuccessful exit as a failure
+    pub fn child_spec(self, agent_id: AgentId, policy: RestartPolicy) -> ChildSpec {
+        let sandbox = Arc::new(self);
+        ChildSpec::new(agent_id, policy, move |_| {
+            let sandbox = sandbox.clone();
+            async move {
+                let output = sandbox.run().await?;
+                if output.success() {
+                    Ok(())
+                } else {
+                    Err(RuntimeError::ExecutionFailed(format!(
+                        "{} exited with {}: {}",
+                        sandbox.program,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim_end()
+                    )))
+                }
+            }
+        })
+    }
+
+    fn command(&self) -> Result<Command, RuntimeError> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .env_clear()
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+
+        let root = self
+            .root
+            .as_ref()
+            .map(|root| CString::new(root.as_os_str().as_bytes()))
+            .transpose()
+            .map_err(|_| RuntimeError::IsolationError("sandbox root contains a NUL byte".into()))?;
+        if root.is_some() && !cfg!(target_os = "linux") && !self.best_effort {
+            return Err(RuntimeError::IsolationError(
+                "filesystem confinement requires Linux namespaces".into(),
+            ));
+        }
+        if self.network_isolation && !cfg!(target_os = "linux") && !self.best_effort {
+            return Err(RuntimeError::IsolationError(
+                "network isolation requires Linux namespaces".into(),
+            ));
+        }
+
+        let limits = Limits {
+            memory: self.limits.max_memory,
+            files: u64::from(self.limits.max_file_descriptors),
+            cpu_seconds: self.cpu_time.map(|cpu| cpu.as_secs().max(1)),
+        };
+        let network_isolation = self.network_isolation;
+        let best_effort = self.best_effort;
+        // SAFETY: the closure runs between fork and exec and only makes
+        // async-signal-safe system calls on pre-allocated data.
+        unsafe {
+            command.pre_exec(move || {
+                limits.apply()?;
+                isolate(network_isolation, root.as_deref(), best_effort)
+            });
+        }
+        Ok(command)
+    }
+}
+
+/// Resource limits applied in the child before exec
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    memory: u64,
+    files: u64,
+    cpu_seconds: Option<u64>,
+}
+
+impl Limits {
+    fn apply(&self) -> io::Result<()> {
+        set_limit(libc::RLIMIT_AS, self.memory)?;
+        set_limit(libc::RLIMIT_NOFILE, self.files)?;
+        if let Some(seconds) = self.cpu_seconds {
+            set_limit(libc::RLIMIT_CPU, seconds)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(target_os = "linux"))]
+type Resource = libc::c_int;
+
+fn set_limit(resource: Resource, value: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call.
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn isolate(network: bool, root: Option<&std::ffi::CStr>, best_effort: bool) -> io::Result<()> {
+    // SAFETY: plain system calls on valid, NUL-terminated arguments.
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if !network && root.is_none() {
+            return Ok(());
+        }
+        let mut flags = libc::CLONE_NEWUSER;
+        if network {
+            flags |= libc::CLONE_NEWNET;
+        }
+        if root.is_some() {
+            flags |= libc::CLONE_NEWNS;
+        }
+        if libc::unshare(flags) != 0 {
+            return if best_effort {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            };
+        }
+        if let Some(root) = root {
+            if libc::chroot(root.as_ptr()) != 0 || libc::chdir(c"/".as_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Nothing to isolate beyond the resource limits; `command` already
+/// refused namespace isolation unless it is best-effort
+#[cfg(not(target_os = "linux"))]
+fn isolate(_network: bool, _root: Option<&std::ffi::CStr>, _best_effort: bool) -> io::Result<()> {
+    Ok(())
+}
+
+/// Read up to `limit` bytes, discarding the rest so the writer never blocks
+async fn capture<R: AsyncRead + Unpin>(reader: Option<R>, limit: usize) -> io::Result<Vec<u8>> {
+    let Some(mut reader) = reader else {
+        return Ok(Vec::new());
+    };
+    let mut captured = Vec::new();
+    (&mut reader)
+        .take(limit as u64)
+        .read_to_end(&mut captured)
+        .await?;
+    tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok(captured)
+}
@@ -0,0 +1,88 @@
+//! Per-agent execution accounting
+//!
+//! The scheduler keeps running totals of every execution it settles: how
+//! many ran, how many failed, and how long they took from submission to
+//! completion. [`super::Scheduler::agent_stats`] reports them in
+//! [`AgentStats`] next to the agent's recorded run time, and a
+//! [`super::FairShareScheduler`] set to charge runtime uses the same
+//! executions to balance agents by time used rather than by dispatch count.
+
+use agentropic_core::AgentId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Execution statistics for one agent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AgentStats {
+    /// Execution time recorded in the current window
+    pub window_runtime: Duration,
+    /// Execution time recorded overall
+    pub total_runtime: Duration,
+    /// Whether the agent has reached its runtime cap
+    pub capped: bool,
+    /// When the current window ends, if the agent has a cap
+    pub window_resets_at: Option<Instant>,
+    /// Tasks rejected because of the cap
+    pub rejected: u64,
+    /// Executions settled, including failed ones
+    pub tasks: u64,
+    /// Executions that failed
+    pub failures: u64,
+    /// Time from submission to the end of each execution, summed
+    pub total_latency: Duration,
+}
+
+impl AgentStats {
+    /// Get mean execution time per settled execution
+    pub fn average_runtime(&self) -> Duration {
+        Self::mean(self.total_runtime, self.tasks)
+    }
+
+    /// Get mean time from submission to the end of an execution
+    pub fn average_latency(&self) -> Duration {
+        Self::mean(self.total_latency, self.tasks)
+    }
+
+    /// Get fraction of executions that failed
+    pub fn failure_rate(&self) -> f64 {
+        if self.tasks == 0 {
+            return 0.0;
+        }
+        self.failures as f64 / self.tasks as f64
+    }
+
+    fn mean(total: Duration, count: u64) -> Duration {
+        match u32::try_from(count) {
+            Ok(0) => Duration::ZERO,
+            Ok(count) => total / count,
+            Err(_) => Duration::from_secs_f64(total.as_secs_f64() / count as f64),
+        }
+    }
+}
+
+/// Execution totals of one agent
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Executions {
+    pub(crate) tasks: u64,
+    pub(crate) failures: u64,
+    pub(crate) total_latency: Duration,
+}
+
+/// Execution totals of every agent
+#[derive(Debug, Default)]
+pub(crate) struct Accounting {
+    agents: HashMap<AgentId, Executions>,
+}
+
+impl Accounting {
+    pub(crate) fn record(&mut self, agent_id: AgentId, latency: Duration, success: bool) {
+        let executions = self.agents.entry(agent_id).or_default();
+        executions.tasks += 1;
+        executions.failures += u64::from(!success);
+        executions.total_latency += latency;
+    }
+
+    pub(crate) fn get(&self, agent_id: &AgentId) -> Executions {
+        self.agents.get(agent_id).copied().unwrap_or_default()
+    }
+}
@@ -0,0 +1,82 @@
+//! Keyed-serial execution of conversations
+//!
+//! Tasks declared with [`Task::with_conversation`] run one at a time per
+//! conversation, in submission order, while tasks of different
+//! conversations still run in parallel. The first task of an idle
+//! conversation is queued as usual; later ones wait in the conversation's
+//! mailbox until the task ahead of them finishes for good, so retries keep
+//! their place at the head.
+
+use super::{Task, TaskId};
+use std::collections::{HashMap, VecDeque};
+
+/// Per-conversation mailboxes of tasks waiting their turn
+#[derive(Debug, Default)]
+pub(crate) struct Conversations {
+    /// Task each busy conversation is running or has queued
+    active: HashMap<String, TaskId>,
+    pending: HashMap<String, VecDeque<Task>>,
+}
+
+impl Conversations {
+    /// Take a submitted task, returning it if it may be queued now
+    ///
+    /// A task of a busy conversation is kept until its turn comes.
+    pub(crate) fn admit(&mut self, task: Task) -> Option<Task> {
+        let Some(key) = task.conversation() else {
+            return Some(task);
+        };
+        match self.active.get(key) {
+            None => {
+                self.active.insert(key.to_string(), task.id());
+                Some(task)
+            }
+            Some(active) if *active == task.id() => Some(task),
+            Some(_) => {
+                self.pending
+                    .entry(key.to_string())
+                    .or_default()
+                    .push_back(task);
+                None
+            }
+        }
+    }
+
+    /// Record that a task finished for good, returning the next task of its
+    /// conversation, if any
+    pub(crate) fn finish(&mut self, id: TaskId) -> Option<Task> {
+        let key = self
+            .active
+            .iter()
+            .find(|(_, active)| **active == id)
+            .map(|(key, _)| key.clone())?;
+        let next = self.pending.get_mut(&key).and_then(VecDeque::pop_front);
+        match &next {
+            Some(task) => {
+                self.active.insert(key.clone(), task.id());
+                if self.pending.get(&key).is_some_and(VecDeque::is_empty) {
+                    self.pending.remove(&key);
+                }
+            }
+            None => {
+                self.active.remove(&key);
+            }
+        }
+        next
+    }
+
+    /// Get the task a conversation is running or has queued
+    pub(crate) fn active(&self, key: &str) -> Option<TaskId> {
+        self.active.get(key).copied()
+    }
+
+    /// Iterate over tasks waiting their turn
+    pub(crate) fn waiting(&self) -> impl Iterator<Item = &Task> {
+        self.pending.values().flatten()
+    }
+
+    /// Get number of tasks waiting their turn
+    pub(crate) fn len(&self) -> usize {
+        self.pending.values().map(VecDeque::len).sum()
+    }
+}
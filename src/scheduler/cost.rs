@@ -0,0 +1,156 @@
+use super::state::{CostRecord, Now};
+use crate::budget::BudgetUsage;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Cost and tokens an agent may spend per window before its budget breaker trips
+///
+/// Cost is in caller-defined units, such as micro-dollars. Once an agent's
+/// spend reaches the cost or token limit, `Scheduler::submit` rejects its
+/// non-critical tasks with `SchedulerError::BudgetExceeded` and its queued
+/// non-critical tasks are held, until the window resets or the limit is
+/// raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostBudget {
+    limit: u64,
+    window: Duration,
+    critical_priority: Option<u32>,
+    #[serde(default)]
+    max_tokens: Option<u64>,
+    #[serde(default)]
+    suspend: bool,
+}
+
+impl CostBudget {
+    /// Create a budget of `limit` cost units per window
+    pub fn new(limit: u64, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            critical_priority: None,
+            max_tokens: None,
+            suspend: false,
+        }
+    }
+
+    /// Keep accepting tasks at or above `priority` while the breaker is tripped
+    pub fn with_critical_priority(mut self, priority: u32) -> Self {
+        self.critical_priority = Some(priority);
+        self
+    }
+
+    /// Limit tokens per window as well
+    pub fn with_max_tokens(mut self, tokens: u64) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Have the supervisor suspend the agent once the breaker trips
+    ///
+    /// See `Supervisor::record_usage`.
+    pub fn with_suspension(mut self) -> Self {
+        self.suspend = true;
+        self
+    }
+
+    /// Get cost allowed per window
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Get window length
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Get tokens allowed per window
+    pub fn max_tokens(&self) -> Option<u64> {
+        self.max_tokens
+    }
+
+    /// Check if the agent is suspended once the breaker trips
+    pub fn suspends(&self) -> bool {
+        self.suspend
+    }
+
+    /// Check if spend reaches the cost or token limit
+    pub fn is_exhausted_by(&self, usage: &BudgetUsage) -> bool {
+        usage.cost >= self.limit || self.max_tokens.is_some_and(|max| usage.tokens >= max)
+    }
+
+    /// Get lowest priority still accepted while tripped
+    pub fn critical_priority(&self) -> Option<u32> {
+        self.critical_priority
+    }
+
+    /// Check if tasks with a priority bypass the breaker
+    pub fn is_critical(&self, priority: u32) -> bool {
+        self.critical_priority
+            .is_some_and(|critical| priority >= critical)
+    }
+}
+
+/// Cost and tokens spent by an agent in its current window
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CostWindow {
+    started: Instant,
+    spent: BudgetUsage,
+}
+
+impl CostWindow {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            spent: BudgetUsage::default(),
+        }
+    }
+
+    /// Start a new window if the current one has ended
+    fn roll(&mut self, window: Duration, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= window {
+            let windows = elapsed.as_nanos() / window.as_nanos().max(1);
+            self.started += window.saturating_mul(windows.min(u32::MAX as u128) as u32);
+            self.spent = BudgetUsage::default();
+        }
+    }
+
+    pub(crate) fn record(&mut self, usage: BudgetUsage, window: Option<Duration>, now: Instant) {
+        if let Some(window) = window {
+            self.roll(window, now);
+        }
+        self.spent += usage;
+    }
+
+    pub(crate) fn to_record(self, now: Now) -> CostRecord {
+        CostRecord {
+            window_elapsed: now.instant.saturating_duration_since(self.started),
+            spent: self.spent.cost,
+            tokens: self.spent.tokens,
+            captured_at: Some(now.wall),
+        }
+    }
+
+    pub(crate) fn from_record(record: CostRecord, now: Now) -> Self {
+        let elapsed = record.window_elapsed + now.since(record.captured_at);
+        Self {
+            started: now.instant.checked_sub(elapsed).unwrap_or(now.instant),
+            spent: BudgetUsage::new(record.tokens, record.spent),
+        }
+    }
+
+    /// Spend in the window that is current now
+    pub(crate) fn spent(&self, window: Option<Duration>, now: Instant) -> BudgetUsage {
+        match window {
+            Some(window) if now.saturating_duration_since(self.started) >= window => {
+                BudgetUsage::default()
+            }
+            _ => self.spent,
+        }
+    }
+
+    /// Get when the current window ends
+    pub(crate) fn resets_at(&self, window: Duration) -> Instant {
+        self.started + window
+    }
+}
@@ -0,0 +1,170 @@
+use crate::profiling::thread_cpu_time;
+use crate::{RuntimeError, SchedulerError};
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// What happens to a task that exceeds its CPU budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CpuBudgetAction {
+    /// Stop polling the task and fail it with `ResourceExhausted`
+    Cancel,
+    /// Keep running the task at a lower priority, yielding to other tasks
+    /// before every poll
+    Demote,
+}
+
+/// CPU-time budget for a task
+///
+/// CPU time is measured with the thread CPU clock around every poll, so
+/// busy-looping agents are caught even when the host is loaded and wall
+/// time is not representative. Enforcement happens between polls; a task
+/// that never yields cannot be interrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuBudget {
+    limit: Duration,
+    action: CpuBudgetAction,
+    #[serde(default)]
+    demoted_priority: u32,
+}
+
+impl CpuBudget {
+    /// Create a budget that cancels the task when exceeded
+    pub fn new(limit: Duration) -> Self {
+        Self {
+            limit,
+            action: CpuBudgetAction::Cancel,
+            demoted_priority: 0,
+        }
+    }
+
+    /// Create a budget that is never exceeded, for measuring CPU time only
+    pub fn unlimited() -> Self {
+        Self::new(Duration::MAX)
+    }
+
+    /// Demote the task to priority 0 instead of cancelling it
+    pub fn demote(self) -> Self {
+        self.demote_to(0)
+    }
+
+    /// Demote the task to `priority` instead of cancelling it
+    ///
+    /// A task already below `priority` keeps its own. `Scheduler::run`
+    /// preempts a demoted task for waiting work by its lowered priority, and
+    /// requeues or retries it at that priority.
+    pub fn demote_to(mut self, priority: u32) -> Self {
+        self.action = CpuBudgetAction::Demote;
+        self.demoted_priority = priority;
+        self
+    }
+
+    /// Get CPU-time limit
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    /// Get action taken when the limit is exceeded
+    pub fn action(&self) -> CpuBudgetAction {
+        self.action
+    }
+
+    /// Get priority a demoted task runs at
+    pub fn demoted_priority(&self) -> u32 {
+        self.demoted_priority
+    }
+
+    /// Get priority a task of `priority` has after using `used` CPU time
+    pub fn priority_after(&self, priority: u32, used: Duration) -> u32 {
+        if self.action == CpuBudgetAction::Demote && used > self.limit {
+            priority.min(self.demoted_priority)
+        } else {
+            priority
+        }
+    }
+
+    /// Enforce the budget on a task's future
+    pub fn enforce<F, T>(&self, future: F) -> CpuBudgeted<F>
+    where
+        F: Future<Output = Result<T, RuntimeError>>,
+    {
+        CpuBudgeted {
+            future: Box::pin(future),
+            budget: *self,
+            used: Duration::ZERO,
+            yielded: false,
+            demoted: None,
+        }
+    }
+}
+
+/// Future running under a [`CpuBudget`]
+pub struct CpuBudgeted<F> {
+    future: Pin<Box<F>>,
+    budget: CpuBudget,
+    used: Duration,
+    yielded: bool,
+    demoted: Option<Arc<AtomicBool>>,
+}
+
+impl<F> CpuBudgeted<F> {
+    /// Raise `demoted` once a demoting budget is exceeded
+    pub(crate) fn signal_demotion(mut self, demoted: Arc<AtomicBool>) -> Self {
+        self.demoted = Some(demoted);
+        self
+    }
+
+    /// Get CPU time used so far
+    pub fn used(&self) -> Duration {
+        self.used
+    }
+
+    /// Check if the budget has been exceeded
+    pub fn is_exceeded(&self) -> bool {
+        self.used > self.budget.limit
+    }
+}
+
+impl<F, T> Future for CpuBudgeted<F>
+where
+    F: Future<Output = Result<T, RuntimeError>>,
+{
+    type Output = Result<T, RuntimeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if this.is_exceeded() && this.budget.action == CpuBudgetAction::Demote {
+            if let Some(demoted) = this.demoted.take() {
+                demoted.store(true, Ordering::Relaxed);
+            }
+        }
+        if this.is_exceeded() && this.budget.action == CpuBudgetAction::Demote && !this.yielded {
+            this.yielded = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        this.yielded = false;
+
+        let cpu_before = thread_cpu_time();
+        let wall_before = Instant::now();
+        let poll = this.future.as_mut().poll(cx);
+        this.used += match (cpu_before, thread_cpu_time()) {
+            (Some(before), Some(after)) => after.saturating_sub(before),
+            _ => wall_before.elapsed(),
+        };
+
+        if poll.is_pending() && this.is_exceeded() && this.budget.action == CpuBudgetAction::Cancel
+        {
+            return Poll::Ready(Err(SchedulerError::CpuBudgetExceeded {
+                used: this.used,
+                budget: this.budget.limit,
+            }
+            .into()));
+        }
+        poll
+    }
+}
@@ -0,0 +1,210 @@
+//! Cron expressions
+//!
+//! A [`CronExpr`] is a five-field cron expression (minute, hour, day of
+//! month, month, day of week), evaluated in UTC. Fire tasks on one with
+//! [`RecurringSchedule::cron`](super::RecurringSchedule::cron), which catches
+//! up on ticks missed while the runtime was down according to the
+//! schedule's [`MisfirePolicy`](super::MisfirePolicy).
+
+use crate::RuntimeError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Minutes searched for the next tick before giving up (about five years)
+const MAX_SEARCH_MINUTES: u64 = 5 * 366 * 24 * 60;
+
+/// Parsed cron expression
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronExpr {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl CronExpr {
+    /// Parse an expression such as `*/5 * * * *` or `0 9 * * mon-fri`
+    pub fn parse(expr: &str) -> Result<Self, RuntimeError> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(expr, "expected five fields"));
+        };
+        let mut weekdays =
+            parse_field(weekday, 0, 7, &WEEKDAYS, 0).map_err(|e| invalid(expr, &e))?;
+        // Both 0 and 7 mean Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            source: expr.to_string(),
+            minutes: parse_field(minute, 0, 59, &[], 0).map_err(|e| invalid(expr, &e))?,
+            hours: parse_field(hour, 0, 23, &[], 0).map_err(|e| invalid(expr, &e))?,
+            days: parse_field(day, 1, 31, &[], 0).map_err(|e| invalid(expr, &e))?,
+            months: parse_field(month, 1, 12, &MONTHS, 1).map_err(|e| invalid(expr, &e))?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    /// Get the expression as written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Get the first tick strictly after `time`
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        let mut minute = seconds / 60 + 1;
+        let limit = minute + MAX_SEARCH_MINUTES;
+        while minute < limit {
+            let days = minute / (24 * 60);
+            let (month, day) = civil_from_days(days);
+            if !bit(self.months, month) || !self.matches_day(day, (days + 4) % 7) {
+                minute = (days + 1) * 24 * 60;
+                continue;
+            }
+            let hour = (minute / 60) % 24;
+            if !bit(self.hours, hour) {
+                minute = (minute / 60 + 1) * 60;
+                continue;
+            }
+            if bit(self.minutes, minute % 60) {
+                return Some(UNIX_EPOCH + Duration::from_secs(minute * 60));
+            }
+            minute += 1;
+        }
+        None
+    }
+
+    /// Day-of-month and day-of-week match either one when both are restricted
+    fn matches_day(&self, day: u64, weekday: u64) -> bool {
+        let day_matches = bit(self.days, day);
+        let weekday_matches = bit(self.weekdays, weekday);
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        }
+    }
+}
+
+impl FromStr for CronExpr {
+    type Err = RuntimeError;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        Self::parse(expr)
+    }
+}
+
+impl TryFrom<String> for CronExpr {
+    type Error = RuntimeError;
+
+    fn try_from(expr: String) -> Result<Self, Self::Error> {
+        Self::parse(&expr)
+    }
+}
+
+impl From<CronExpr> for String {
+    fn from(expr: CronExpr) -> Self {
+        expr.source
+    }
+}
+
+impl fmt::Display for CronExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn invalid(expr: &str, reason: &str) -> RuntimeError {
+    RuntimeError::ConfigError(format!("invalid cron expression {:?}: {}", expr, reason))
+}
+
+fn bit(mask: u64, value: u64) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Parse one comma-separated field into a bit mask of allowed values
+fn parse_field(
+    field: &str,
+    min: u64,
+    max: u64,
+    names: &[&str],
+    offset: u64,
+) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u64, String> {
+        let lower = text.to_ascii_lowercase();
+        let parsed = match names.iter().position(|name| *name == lower) {
+            Some(index) => index as u64 + offset,
+            None => text
+                .parse()
+                .map_err(|_| format!("{:?} is not a number", text))?,
+        };
+        if parsed < min || parsed > max {
+            return Err(format!("{} is outside {}-{}", parsed, min, max));
+        }
+        Ok(parsed)
+    };
+
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u64 = step
+                    .parse()
+                    .map_err(|_| format!("{:?} is not a step", step))?;
+                if step == 0 {
+                    return Err("step must be positive".into());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let single = value(range)?;
+                    (single, single)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("range {:?} is reversed", range));
+        }
+        for allowed in (start..=end).step_by(step as usize) {
+            mask |= 1 << allowed;
+        }
+    }
+    Ok(mask)
+}
+
+/// Convert days since the Unix epoch to the (month, day) of the date
+fn civil_from_days(days: u64) -> (u64, u64) {
+    let day_of_era = (days as i64 + 719_468).rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u64;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u64;
+    (month, day)
+}
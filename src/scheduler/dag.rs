@@ -0,0 +1,116 @@
+//! Task dependency graph
+//!
+//! Tasks declared with [`Task::after`] wait in the scheduler's
+//! [`DependencyGraph`] while any of their predecessors is unfinished. A
+//! completed predecessor releases the dependents left with nothing to wait
+//! for; a predecessor that fails for good takes its dependents, and theirs,
+//! down with it.
+
+use super::{Task, TaskId};
+use std::collections::{HashMap, HashSet};
+
+/// Task waiting for predecessors
+#[derive(Debug)]
+struct Waiting {
+    task: Task,
+    remaining: HashSet<TaskId>,
+}
+
+/// Tasks waiting for predecessors, and who waits for whom
+#[derive(Debug, Default)]
+pub(crate) struct DependencyGraph {
+    waiting: HashMap<TaskId, Waiting>,
+    dependents: HashMap<TaskId, Vec<TaskId>>,
+    /// Dispatched tasks that have not completed
+    dispatched: HashSet<TaskId>,
+}
+
+impl DependencyGraph {
+    /// Check if `task` would end up waiting for itself
+    pub(crate) fn creates_cycle(&self, task: &Task) -> bool {
+        let mut stack: Vec<TaskId> = task.dependencies().to_vec();
+        let mut seen = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if id == task.id() {
+                return true;
+            }
+            if !seen.insert(id) {
+                continue;
+            }
+            if let Some(waiting) = self.waiting.get(&id) {
+                stack.extend(waiting.remaining.iter().copied());
+            }
+        }
+        false
+    }
+
+    /// Hold a task until `remaining` predecessors complete
+    pub(crate) fn park(&mut self, task: Task, remaining: HashSet<TaskId>) {
+        for predecessor in &remaining {
+            self.dependents
+                .entry(*predecessor)
+                .or_default()
+                .push(task.id());
+        }
+        self.waiting.insert(task.id(), Waiting { task, remaining });
+    }
+
+    /// Check if a task waits for predecessors
+    pub(crate) fn is_waiting(&self, id: &TaskId) -> bool {
+        self.waiting.contains_key(id)
+    }
+
+    /// Remember a dispatched task until it completes
+    pub(crate) fn dispatch(&mut self, id: TaskId) {
+        self.dispatched.insert(id);
+    }
+
+    /// Check if a dispatched task has not completed
+    pub(crate) fn is_dispatched(&self, id: &TaskId) -> bool {
+        self.dispatched.contains(id)
+    }
+
+    /// Record that a task completed, returning the dependents it released
+    pub(crate) fn complete(&mut self, id: TaskId) -> Vec<Task> {
+        self.dispatched.remove(&id);
+        let mut released = Vec::new();
+        for dependent in self.dependents.remove(&id).unwrap_or_default() {
+            let Some(waiting) = self.waiting.get_mut(&dependent) else {
+                continue;
+            };
+            waiting.remaining.remove(&id);
+            if waiting.remaining.is_empty() {
+                if let Some(waiting) = self.waiting.remove(&dependent) {
+                    released.push(waiting.task);
+                }
+            }
+        }
+        released
+    }
+
+    /// Record that a task failed for good, returning every task that waited on it
+    pub(crate) fn fail(&mut self, id: TaskId) -> Vec<Task> {
+        let mut failed = vec![id];
+        let mut dropped = Vec::new();
+        while let Some(id) = failed.pop() {
+            self.dispatched.remove(&id);
+            for dependent in self.dependents.remove(&id).unwrap_or_default() {
+                if let Some(waiting) = self.waiting.remove(&dependent) {
+                    failed.push(dependent);
+                    dropped.push(waiting.task);
+                }
+            }
+        }
+        dropped
+    }
+
+    /// Iterate over waiting tasks
+    pub(crate) fn waiting(&self) -> impl Iterator<Item = &Task> {
+        self.waiting.values().map(|waiting| &waiting.task)
+    }
+
+    /// Get number of waiting tasks
+    pub(crate) fn len(&self) -> usize {
+        self.waiting.len()
+    }
+}
@@ -0,0 +1,182 @@
+//! Dead-letter queue for tasks that could not complete
+//!
+//! Tasks that fail for good, because their retries ran out, their error is
+//! not retryable, or their agent does not exist or is over a rejecting
+//! runtime cap, are kept in a bounded [`DeadLetterQueue`] instead of being
+//! dropped. Entries can be inspected, replayed through
+//! [`super::Scheduler::replay_dead_letter`], or purged.
+
+use super::Task;
+use crate::{ErrorClass, RuntimeError};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// Default number of dead letters kept
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Why a task was dead-lettered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeadLetterReason {
+    /// The task failed on its last allowed attempt
+    RetriesExhausted,
+    /// The failure is never retried
+    NotRetryable,
+    /// The task's agent does not exist
+    AgentNotFound,
+    /// The task could not be queued again for a retry
+    Undeliverable,
+    /// A task it depended on failed for good
+    DependencyFailed,
+    /// Its agent was over a runtime cap that rejects tasks
+    RuntimeCapExceeded,
+}
+
+impl DeadLetterReason {
+    /// Classify a failed task's error
+    pub(crate) fn for_failure(error: &RuntimeError, class: &ErrorClass) -> Self {
+        match (error, class) {
+            (RuntimeError::AgentNotFound(_), _) => DeadLetterReason::AgentNotFound,
+            (_, ErrorClass::Permanent) => DeadLetterReason::NotRetryable,
+            _ => DeadLetterReason::RetriesExhausted,
+        }
+    }
+}
+
+/// Task kept in the dead-letter queue
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    /// Entry ID, unique within the queue
+    pub id: u64,
+    /// Task as of its last attempt
+    pub task: Task,
+    /// Why the task was dead-lettered
+    pub reason: DeadLetterReason,
+    /// Last error, if the task failed
+    pub error: Option<String>,
+    /// Time the task entered the queue
+    pub dead_at: SystemTime,
+}
+
+/// Bounded queue of dead-lettered tasks, oldest first
+#[derive(Debug, Clone)]
+pub struct DeadLetterQueue {
+    entries: VecDeque<DeadLetter>,
+    capacity: usize,
+    next_id: u64,
+    evicted: u64,
+}
+
+impl DeadLetterQueue {
+    /// Create a queue keeping at most `capacity` entries
+    ///
+    /// The oldest entry is evicted to make room.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: capacity.max(1),
+            next_id: 0,
+            evicted: 0,
+        }
+    }
+
+    /// Add a task dead-lettered at `now`, returning its entry ID
+    pub fn push(
+        &mut self,
+        task: Task,
+        reason: DeadLetterReason,
+        error: Option<String>,
+        now: SystemTime,
+    ) -> u64 {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+            self.evicted += 1;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        tracing::warn!(
+            "Dead-lettered task for agent {}: {:?}",
+            task.agent_id(),
+            reason
+        );
+        self.entries.push_back(DeadLetter {
+            id,
+            task,
+            reason,
+            error,
+            dead_at: now,
+        });
+        id
+    }
+
+    /// Iterate over entries, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &DeadLetter> {
+        self.entries.iter()
+    }
+
+    /// Get an entry
+    pub fn get(&self, id: u64) -> Option<&DeadLetter> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Remove and return an entry
+    pub fn take(&mut self, id: u64) -> Option<DeadLetter> {
+        let index = self.entries.iter().position(|entry| entry.id == id)?;
+        self.entries.remove(index)
+    }
+
+    /// Put a taken or checkpointed entry back, keeping its ID
+    pub(crate) fn restore(&mut self, entry: DeadLetter) {
+        self.next_id = self.next_id.max(entry.id + 1);
+        let index = self.entries.partition_point(|other| other.id < entry.id);
+        self.entries.insert(index, entry);
+    }
+
+    /// Remove and return all entries
+    pub fn drain(&mut self) -> Vec<DeadLetter> {
+        self.entries.drain(..).collect()
+    }
+
+    /// Remove all entries, returning how many were removed
+    pub fn purge(&mut self) -> usize {
+        let purged = self.entries.len();
+        self.entries.clear();
+        purged
+    }
+
+    /// Remove entries dead-lettered more than `age` before `now`
+    pub fn purge_older_than(&mut self, age: Duration, now: SystemTime) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|entry| {
+            !now.duration_since(entry.dead_at)
+                .is_ok_and(|elapsed| elapsed > age)
+        });
+        before - self.entries.len()
+    }
+
+    /// Get number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get capacity
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get number of entries evicted to make room
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+}
+
+impl Default for DeadLetterQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
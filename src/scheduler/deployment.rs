@@ -0,0 +1,201 @@
+use agentropic_core::AgentId;
+use serde::{Deserialize, Serialize};
+
+/// Phase of a blue-green deployment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeploymentState {
+    /// Traffic is split between both versions
+    Shifting,
+    /// All traffic goes to the candidate
+    Promoted,
+    /// All traffic goes back to the stable version
+    RolledBack,
+}
+
+/// Result of comparing error rates of both versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeploymentVerdict {
+    /// Not enough outcomes of either version yet
+    Undecided,
+    /// Candidate is no worse than stable
+    Promote,
+    /// Candidate's error rate is too high
+    RollBack,
+}
+
+/// Successes and failures seen by one version
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outcomes {
+    /// Successful tasks
+    pub successes: u64,
+    /// Failed tasks
+    pub failures: u64,
+}
+
+impl Outcomes {
+    /// Get total tasks
+    pub fn total(&self) -> u64 {
+        self.successes + self.failures
+    }
+
+    /// Get failure fraction, 0 when empty
+    pub fn error_rate(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.failures as f64 / total as f64,
+        }
+    }
+
+    fn record(&mut self, success: bool) {
+        if success {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+    }
+}
+
+/// Blue-green upgrade shifting a fraction of an agent's tasks to a new version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deployment {
+    stable: AgentId,
+    candidate: AgentId,
+    fraction: f64,
+    routed: u64,
+    shifted: u64,
+    stable_outcomes: Outcomes,
+    candidate_outcomes: Outcomes,
+    state: DeploymentState,
+    auto_decision: Option<(u64, f64)>,
+}
+
+impl Deployment {
+    /// Shift `fraction` (0.0 - 1.0) of tasks for `stable` to `candidate`
+    pub fn new(stable: AgentId, candidate: AgentId, fraction: f64) -> Self {
+        Self {
+            stable,
+            candidate,
+            fraction: fraction.clamp(0.0, 1.0),
+            routed: 0,
+            shifted: 0,
+            stable_outcomes: Outcomes::default(),
+            candidate_outcomes: Outcomes::default(),
+            state: DeploymentState::Shifting,
+            auto_decision: None,
+        }
+    }
+
+    /// Promote or roll back automatically once both versions have `min_samples` outcomes
+    ///
+    /// The candidate is rolled back if its error rate exceeds the stable
+    /// version's by more than `tolerance`.
+    pub fn with_auto_decision(mut self, min_samples: u64, tolerance: f64) -> Self {
+        self.auto_decision = Some((min_samples, tolerance));
+        self
+    }
+
+    /// Get stable agent
+    pub fn stable(&self) -> &AgentId {
+        &self.stable
+    }
+
+    /// Get candidate agent
+    pub fn candidate(&self) -> &AgentId {
+        &self.candidate
+    }
+
+    /// Get fraction of tasks shifted to the candidate
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    /// Change fraction of tasks shifted to the candidate
+    pub fn set_fraction(&mut self, fraction: f64) {
+        self.fraction = fraction.clamp(0.0, 1.0);
+    }
+
+    /// Get state
+    pub fn state(&self) -> DeploymentState {
+        self.state
+    }
+
+    /// Get outcomes of the stable version
+    pub fn stable_outcomes(&self) -> Outcomes {
+        self.stable_outcomes
+    }
+
+    /// Get outcomes of the candidate
+    pub fn candidate_outcomes(&self) -> Outcomes {
+        self.candidate_outcomes
+    }
+
+    /// Compare error rates of both versions
+    ///
+    /// Undecided until each version has at least `min_samples` outcomes, so
+    /// the candidate is never judged against an empty baseline.
+    pub fn evaluate(&self, min_samples: u64, tolerance: f64) -> DeploymentVerdict {
+        let min_samples = min_samples.max(1);
+        if self.candidate_outcomes.total() < min_samples
+            || self.stable_outcomes.total() < min_samples
+        {
+            DeploymentVerdict::Undecided
+        } else if self.candidate_outcomes.error_rate()
+            > self.stable_outcomes.error_rate() + tolerance
+        {
+            DeploymentVerdict::RollBack
+        } else {
+            DeploymentVerdict::Promote
+        }
+    }
+
+    /// Send all tasks to the candidate
+    pub fn promote(&mut self) {
+        self.state = DeploymentState::Promoted;
+    }
+
+    /// Send all tasks back to the stable version
+    ///
+    /// Use [`Scheduler::roll_back`](super::Scheduler::roll_back) on a running
+    /// deployment so tasks already queued for the candidate move back too.
+    pub fn roll_back(&mut self) {
+        self.state = DeploymentState::RolledBack;
+    }
+
+    /// Pick the version for the next task
+    pub(crate) fn route(&mut self) -> AgentId {
+        match self.state {
+            DeploymentState::Promoted => self.candidate,
+            DeploymentState::RolledBack => self.stable,
+            DeploymentState::Shifting => {
+                self.routed += 1;
+                if (self.shifted as f64) < self.routed as f64 * self.fraction {
+                    self.shifted += 1;
+                    self.candidate
+                } else {
+                    self.stable
+                }
+            }
+        }
+    }
+
+    /// Record a task outcome for either version, applying the auto decision
+    pub(crate) fn record(&mut self, agent_id: &AgentId, success: bool) {
+        if *agent_id == self.candidate {
+            self.candidate_outcomes.record(success);
+        } else if *agent_id == self.stable {
+            self.stable_outcomes.record(success);
+        } else {
+            return;
+        }
+
+        if let (DeploymentState::Shifting, Some((min_samples, tolerance))) =
+            (self.state, self.auto_decision)
+        {
+            match self.evaluate(min_samples, tolerance) {
+                DeploymentVerdict::Promote => self.promote(),
+                DeploymentVerdict::RollBack => self.roll_back(),
+                DeploymentVerdict::Undecided => {}
+            }
+        }
+    }
+}
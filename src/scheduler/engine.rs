@@ -1,18 +1,921 @@
-use super::{SchedulingPolicy, TaskQueue};
+use super::accounting::Accounting;
+use super::conversation::Conversations;
+use super::cost::CostWindow;
+use super::dag::DependencyGraph;
+use super::gang::{Gangs, TaskGroup};
+use super::idempotency::{self, IdempotencyKeys};
+use super::overhead::{self, OverheadProfile};
+use super::provider::ProviderPool;
+use super::results::{self, ResultStore, TaskFailure, TaskHandle, TaskResult};
+use super::runtime_cap::{FuelWindow, RuntimeWindow};
+use super::speculation::{ReplicaOutcome, SpeculationStats, Speculations};
+use super::state::{DeadLetterRecord, Now};
+use super::tie_break::Lottery;
+use super::{
+    AgentPool, AgentStats, CapAction, CostBudget, CpuBudget, DeadLetterQueue, DeadLetterReason,
+    Deployment, DeploymentState, FairShareScheduler, OverflowPolicy, OverrunAction, PolicyEngine,
+    PolicyType, PreemptionHandle, PriorityAging, PriorityQuota, ProviderLimits, ProviderUsage,
+    QuotaOverflow, RateLimiter, RateLimiterRecord, RecurringSchedule, RuntimeCap, SchedulerEvent,
+    SchedulerSnapshot, SchedulerState, SchedulingPolicy, ShadowScheduler, Task, TaskId, TaskQueue,
+    TaskRecord, TieBreak, WakeReason, Wakeup,
+};
+use crate::budget::BudgetUsage;
+use crate::lifecycle::{AgentLifecycle, LifecycleState};
+use crate::mailbox::Mailboxes;
+#[cfg(feature = "metrics")]
+use crate::metrics::{Collector, LatencyHistogram, Metric, MetricType};
+use crate::resources::{QuotaUsage, ResourceQuota};
+#[cfg(feature = "supervisor")]
+use crate::supervisor::Supervisor;
+use crate::timer::{self, SharedClock};
+use crate::{
+    DefaultClassifier, EngineTuning, ErrorClassifier, QueueError, RuntimeError, SchedulerError,
+    SizeLimit, WakeupStrategy,
+};
+use agentropic_core::AgentId;
+use agentropic_runtime_core::HealthStatus;
+use futures::{Future, FutureExt, TryFutureExt};
+use semver::{Version, VersionReq};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{watch, Mutex, Notify};
+use tokio::task::{JoinError, JoinSet};
+use tokio_util::sync::CancellationToken;
+
+/// Task held back because its agent is not dispatchable
+#[derive(Debug)]
+struct HeldTask {
+    task: Task,
+    since: Instant,
+    reason: HoldReason,
+}
+
+/// Why a task was held back instead of dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HoldReason {
+    /// Its agent is unhealthy, not ready, or not schedulable
+    Health,
+    /// Its agent is cordoned
+    Cordoned,
+    /// Its agent used up its runtime cap or fuel budget
+    RuntimeCap,
+    /// Its agent is running as many tasks as its quota allows
+    Concurrency,
+    /// An agent it must not run alongside is running
+    AntiAffinity,
+    /// Its agent is rate-limited
+    RateLimit,
+    /// Its provider is at its limit
+    Provider,
+    /// Its agent's cost budget is used up
+    Budget,
+    /// Its affinity key is pinned to another worker
+    Affinity,
+}
+
+impl HoldReason {
+    #[cfg(feature = "metrics")]
+    const ALL: [HoldReason; 9] = [
+        HoldReason::Health,
+        HoldReason::Cordoned,
+        HoldReason::RuntimeCap,
+        HoldReason::Concurrency,
+        HoldReason::AntiAffinity,
+        HoldReason::RateLimit,
+        HoldReason::Provider,
+        HoldReason::Budget,
+        HoldReason::Affinity,
+    ];
+
+    /// Get the label used in metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HoldReason::Health => "health",
+            HoldReason::Cordoned => "cordoned",
+            HoldReason::RuntimeCap => "runtime_cap",
+            HoldReason::Concurrency => "concurrency",
+            HoldReason::AntiAffinity => "anti_affinity",
+            HoldReason::RateLimit => "rate_limit",
+            HoldReason::Provider => "provider",
+            HoldReason::Budget => "budget",
+            HoldReason::Affinity => "affinity",
+        }
+    }
+}
+
+/// Task [`Scheduler::run`] is executing
+#[derive(Debug)]
+struct Flight {
+    task: Task,
+    started: Instant,
+    /// Raised once the task exceeds a demoting CPU budget
+    demoted: Arc<AtomicBool>,
+}
+
+impl Flight {
+    /// Get the task's priority, lowered if its CPU budget demoted it
+    fn priority(&self) -> u32 {
+        match self.task.cpu_budget() {
+            Some(budget) if self.demoted.load(Ordering::Relaxed) => {
+                self.task.priority().min(budget.demoted_priority())
+            }
+            _ => self.task.priority(),
+        }
+    }
+}
+
+/// Agents with new mail, awaiting a task
+#[derive(Debug)]
+struct MailTasks {
+    priority: u32,
+    arrived: Arc<std::sync::Mutex<Vec<AgentId>>>,
+}
 
 /// Task scheduler
 pub struct Scheduler {
     policy: SchedulingPolicy,
+    policy_engine: Option<Box<dyn PolicyEngine>>,
     queue: TaskQueue,
+    tie_break: TieBreak,
+    lottery: Lottery,
+    fair_share: Option<FairShareScheduler>,
+    accounting: Accounting,
+    health: HashMap<AgentId, HealthStatus>,
+    not_ready: HashSet<AgentId>,
+    cordoned: HashSet<AgentId>,
+    /// Work of suspended agents, in submission order
+    parked: HashMap<AgentId, Vec<Task>>,
+    /// Failed attempts resubmitted for a retry
+    retried: u64,
+    classifier: Arc<dyn ErrorClassifier>,
+    rivals: HashMap<AgentId, HashSet<AgentId>>,
+    pins: HashMap<String, String>,
+    /// Worker asking for a task in [`Scheduler::next_task_on`]
+    dispatching_to: Option<String>,
+    deployments: HashMap<AgentId, Deployment>,
+    versions: HashMap<AgentId, Version>,
+    shadow: Option<ShadowScheduler>,
+    providers: HashMap<String, ProviderPool>,
+    rate_limiter: RateLimiter,
+    lifecycle: Option<Arc<AgentLifecycle>>,
+    mail: Option<MailTasks>,
+    dead_letters: DeadLetterQueue,
+    resource_quotas: HashMap<AgentId, ResourceQuota>,
+    running: HashMap<AgentId, usize>,
+    schedules: Vec<RecurringSchedule>,
+    pools: Vec<AgentPool>,
+    held: VecDeque<HeldTask>,
+    /// Time dispatched tasks spent held, by reason
+    held_time: HashMap<HoldReason, Duration>,
+    caps: HashMap<AgentId, RuntimeCap>,
+    runtime: HashMap<AgentId, RuntimeWindow>,
+    fuel: HashMap<AgentId, FuelWindow>,
+    cost_budgets: HashMap<AgentId, CostBudget>,
+    costs: HashMap<AgentId, CostWindow>,
+    tripped: HashSet<AgentId>,
+    capacity: Option<(usize, OverflowPolicy)>,
+    room: Arc<Notify>,
+    payload_limit: Option<SizeLimit>,
+    result_limit: Option<SizeLimit>,
+    quotas: HashMap<u32, PriorityQuota>,
+    aging: Option<PriorityAging>,
+    max_wait: Option<Duration>,
+    events: Vec<SchedulerEvent>,
+    tuning: EngineTuning,
+    overhead: OverheadProfile,
+    clock: SharedClock,
+    /// Date tasks by the clock on submission; set with a custom clock
+    dates_tasks: bool,
+    wakeup: Arc<Wakeup>,
+    draining: CancellationToken,
+    aborting: CancellationToken,
+    in_flight: HashMap<u64, Flight>,
+    dependencies: DependencyGraph,
+    conversations: Conversations,
+    gangs: Gangs,
+    speculations: Speculations,
+    results: Arc<ResultStore>,
+    idempotency: IdempotencyKeys,
+    next_flight: u64,
+    max_in_flight: Option<usize>,
+    preemptions: u64,
+    flights: watch::Sender<usize>,
+    #[cfg(feature = "metrics")]
+    queue_wait: LatencyHistogram,
+    #[cfg(feature = "metrics")]
+    run_time: LatencyHistogram,
 }
 
+/// Flight, task, output and execution time of an attempt dispatched by
+/// [`Scheduler::run_with_results`]
+type FinishedAttempt = (u64, Task, Result<Vec<u8>, RuntimeError>, Duration);
+
 impl Scheduler {
     /// Create a new scheduler
     pub fn new(policy: SchedulingPolicy) -> Self {
+        let clock: SharedClock = timer::TimerService::shared();
         Self {
             policy,
+            policy_engine: None,
             queue: TaskQueue::new(),
+            tie_break: TieBreak::Fifo,
+            lottery: Lottery::new(),
+            fair_share: None,
+            accounting: Accounting::default(),
+            health: HashMap::new(),
+            not_ready: HashSet::new(),
+            cordoned: HashSet::new(),
+            parked: HashMap::new(),
+            retried: 0,
+            classifier: Arc::new(DefaultClassifier),
+            rivals: HashMap::new(),
+            pins: HashMap::new(),
+            dispatching_to: None,
+            deployments: HashMap::new(),
+            versions: HashMap::new(),
+            shadow: None,
+            providers: HashMap::new(),
+            rate_limiter: RateLimiter::new(),
+            lifecycle: None,
+            mail: None,
+            dead_letters: DeadLetterQueue::default(),
+            resource_quotas: HashMap::new(),
+            running: HashMap::new(),
+            schedules: Vec::new(),
+            pools: Vec::new(),
+            held: VecDeque::new(),
+            held_time: HashMap::new(),
+            caps: HashMap::new(),
+            runtime: HashMap::new(),
+            fuel: HashMap::new(),
+            cost_budgets: HashMap::new(),
+            costs: HashMap::new(),
+            tripped: HashSet::new(),
+            capacity: None,
+            room: Arc::new(Notify::new()),
+            payload_limit: None,
+            result_limit: None,
+            quotas: HashMap::new(),
+            aging: None,
+            max_wait: None,
+            events: Vec::new(),
+            tuning: EngineTuning::default(),
+            overhead: OverheadProfile::new(),
+            wakeup: Arc::new(Wakeup::new(clock.clone())),
+            draining: CancellationToken::new(),
+            aborting: CancellationToken::new(),
+            in_flight: HashMap::new(),
+            dependencies: DependencyGraph::default(),
+            conversations: Conversations::default(),
+            gangs: Gangs::default(),
+            speculations: Speculations::default(),
+            results: Arc::new(ResultStore::new(
+                results::DEFAULT_CAPACITY,
+                results::DEFAULT_TTL,
+                clock.clone(),
+            )),
+            idempotency: IdempotencyKeys::new(idempotency::DEFAULT_WINDOW),
+            next_flight: 0,
+            max_in_flight: None,
+            preemptions: 0,
+            flights: watch::Sender::new(0),
+            clock,
+            dates_tasks: false,
+            #[cfg(feature = "metrics")]
+            queue_wait: LatencyHistogram::new(),
+            #[cfg(feature = "metrics")]
+            run_time: LatencyHistogram::new(),
+        }
+    }
+
+    /// Set engine tuning, usually from [`crate::RuntimeConfig::tuning`]
+    pub fn with_tuning(mut self, tuning: EngineTuning) -> Self {
+        self.tuning = tuning;
+        self
+    }
+
+    /// Change engine tuning, e.g. after a configuration reload
+    pub fn set_tuning(&mut self, tuning: EngineTuning) {
+        self.tuning = tuning;
+    }
+
+    /// Time the scheduler's own phases, see [`Scheduler::overhead`]
+    pub fn with_overhead_profiling(mut self) -> Self {
+        self.overhead = OverheadProfile::enabled();
+        self
+    }
+
+    /// Get time spent in scheduler phases
+    pub fn overhead(&self) -> &OverheadProfile {
+        &self.overhead
+    }
+
+    /// Limit the size of task payloads accepted by [`Scheduler::submit`]
+    pub fn with_payload_limit(mut self, limit: SizeLimit) -> Self {
+        self.payload_limit = Some(limit);
+        self
+    }
+
+    /// Limit the size of results stored by [`Scheduler::accept_result`]
+    pub fn with_result_limit(mut self, limit: SizeLimit) -> Self {
+        self.result_limit = Some(limit);
+        self
+    }
+
+    /// Get the result limit
+    pub fn result_limit(&self) -> Option<&SizeLimit> {
+        self.result_limit.as_ref()
+    }
+
+    /// Cap how many tasks of one priority may be queued
+    pub fn with_priority_quota(mut self, priority: u32, quota: PriorityQuota) -> Self {
+        self.quotas.insert(priority, quota);
+        self
+    }
+
+    /// Age queued tasks under [`PolicyType::Priority`] so they cannot starve
+    pub fn with_priority_aging(mut self, aging: PriorityAging) -> Self {
+        self.aging = Some(aging);
+        self
+    }
+
+    /// Get priority aging
+    pub fn priority_aging(&self) -> Option<&PriorityAging> {
+        self.aging.as_ref()
+    }
+
+    /// Promote any task queued longer than `bound` ahead of policy order
+    ///
+    /// Each promotion records a [`SchedulerEvent::WaitBoundExceeded`].
+    pub fn with_max_wait(mut self, bound: Duration) -> Self {
+        self.max_wait = Some(bound);
+        self
+    }
+
+    /// Get maximum wait bound
+    pub fn max_wait(&self) -> Option<Duration> {
+        self.max_wait
+    }
+
+    /// Set the error classifier deciding which failed tasks are retried and
+    /// why tasks are dead-lettered
+    pub fn with_classifier(mut self, classifier: impl ErrorClassifier + 'static) -> Self {
+        self.classifier = Arc::new(classifier);
+        self
+    }
+
+    /// Take events recorded since the last call
+    pub fn take_events(&mut self) -> Vec<SchedulerEvent> {
+        let tripped: Vec<_> = self.tripped.iter().copied().collect();
+        for agent_id in tripped {
+            self.refresh_budget(agent_id);
+        }
+        std::mem::take(&mut self.events)
+    }
+
+    /// Get number of queued tasks with a priority
+    pub fn queued_at(&self, priority: u32) -> usize {
+        self.queue
+            .iter()
+            .filter(|task| task.priority() == priority)
+            .count()
+    }
+
+    /// Queue a task, enforcing the payload limit and priority quotas
+    ///
+    /// The returned [`TaskHandle`] resolves to the task's result once it
+    /// completes or fails for good; dropping it leaves the result in the
+    /// cache for [`Scheduler::task_result`].
+    ///
+    /// Non-critical tasks for an agent whose [`CostBudget`] breaker is
+    /// tripped fail with [`SchedulerError::BudgetExceeded`].
+    ///
+    /// Tasks for an agent being replaced by a [`Deployment`] may be moved to
+    /// the new version. Tasks with a version requirement their agent does
+    /// not meet move to a compatible pool sibling, or fail if there is none.
+    ///
+    /// A task over its level's quota is rejected, or with
+    /// [`QuotaOverflow::Demote`] moved to the highest lower level with room.
+    ///
+    /// With a queue capacity, a task submitted while the queue is full is
+    /// handled by the [`OverflowPolicy`]: it fails with [`QueueError::Full`]
+    /// under `Block` and `Reject`, and dropped tasks are reported as
+    /// [`SchedulerEvent::TaskDropped`]. Use [`Scheduler::submit_async`] to
+    /// wait for room instead.
+    ///
+    /// A task declared with [`Task::after`] waits while any of its
+    /// dependencies is queued, waiting, or running, and goes through these
+    /// checks once released. Dependencies the scheduler does not hold count
+    /// as complete, so submit dependents before their dependencies finish.
+    /// A task that would wait for itself fails with
+    /// [`SchedulerError::DependencyCycle`].
+    ///
+    /// Once [`Scheduler::drain`] is called, every submission fails with
+    /// [`SchedulerError::Draining`].
+    ///
+    /// A task with the same [`Task::with_idempotency_key`] as one submitted
+    /// within [`Scheduler::with_idempotency_window`] is dropped, and the
+    /// handle of the earlier task is returned instead.
+    pub fn submit(&mut self, mut task: Task) -> Result<TaskHandle, RuntimeError> {
+        if self.is_draining() {
+            return Err(SchedulerError::Draining.into());
+        }
+        if self.dates_tasks {
+            task.date(self.clock.instant());
+        }
+        let Some(key) = task.idempotency_key().map(str::to_string) else {
+            return self.enqueue(task);
+        };
+        let now = self.clock.instant();
+        if let Some(first) = self.idempotency.duplicate_of(&key, task.id(), now) {
+            tracing::debug!(
+                "Dropping task {} as a duplicate of task {} with key {}",
+                task.id(),
+                first,
+                key
+            );
+            return Ok(self.task_handle(first));
+        }
+        let id = task.id();
+        let handle = self.enqueue(task)?;
+        self.idempotency.remember(&key, id, now);
+        Ok(handle)
+    }
+
+    /// Queue a dated task, or park it until its dependencies finish
+    fn enqueue(&mut self, mut task: Task) -> Result<TaskHandle, RuntimeError> {
+        let handle = self.task_handle(task.id());
+        if !task.dependencies().is_empty() {
+            if self.dependencies.creates_cycle(&task) {
+                return Err(SchedulerError::DependencyCycle {
+                    task: task.id().to_string(),
+                }
+                .into());
+            }
+            if let Some(failed) = self.failed_dependency(&task) {
+                self.fail_dependent(task, failed);
+                return Ok(handle);
+            }
+            let remaining: HashSet<TaskId> = task
+                .dependencies()
+                .iter()
+                .filter(|id| self.is_unfinished(id))
+                .copied()
+                .collect();
+            if !remaining.is_empty() {
+                self.dependencies.park(task, remaining);
+                return Ok(handle);
+            }
+        }
+        if let Some(limit) = &self.payload_limit {
+            task.limit_payload(limit)?;
+        }
+        if let Some(deployment) = self.deployments.get_mut(task.agent_id()) {
+            task.reassign(deployment.route());
+        }
+        if let Some(parked) = self.parked.get_mut(task.agent_id()) {
+            parked.push(task);
+            return Ok(handle);
+        }
+        self.refresh_budget(*task.agent_id());
+        if let Some(budget) = self.cost_budgets.get(task.agent_id()) {
+            if self.tripped.contains(task.agent_id()) && !budget.is_critical(task.priority()) {
+                return Err(SchedulerError::BudgetExceeded {
+                    agent: task.agent_id().to_string(),
+                    spent: self.cost_spent(task.agent_id()),
+                    limit: budget.limit(),
+                }
+                .into());
+            }
+        }
+        if !self.is_compatible(task.agent_id(), task.version_req()) {
+            let compatible = self
+                .pool_members(task.agent_id())
+                .find(|member| self.is_compatible(member, task.version_req()))
+                .copied();
+            match compatible {
+                Some(agent_id) => task.reassign(agent_id),
+                None => {
+                    return Err(SchedulerError::NoCompatibleAgent {
+                        agent: task.agent_id().to_string(),
+                        requirement: task
+                            .version_req()
+                            .map(ToString::to_string)
+                            .unwrap_or_default(),
+                    }
+                    .into())
+                }
+            }
+        }
+        while let Some(quota) = self.quotas.get(&task.priority()) {
+            if self.queued_at(task.priority()) < quota.max_queued() {
+                break;
+            }
+            match (quota.overflow(), task.priority().checked_sub(1)) {
+                (QuotaOverflow::Demote, Some(lower)) => task.set_priority(lower),
+                _ => {
+                    return Err(QueueError::PriorityFull {
+                        priority: task.priority(),
+                        capacity: quota.max_queued(),
+                    }
+                    .into())
+                }
+            }
+        }
+        if let Some((capacity, overflow)) = self.capacity {
+            if self.is_full() {
+                match overflow {
+                    OverflowPolicy::DropOldest => match self.take_oldest() {
+                        Some(oldest) => self.drop_task(oldest, overflow),
+                        None => return Err(QueueError::Full { capacity }.into()),
+                    },
+                    OverflowPolicy::DropNewest => {
+                        self.drop_task(task, overflow);
+                        return Ok(handle);
+                    }
+                    _ => return Err(QueueError::Full { capacity }.into()),
+                }
+            }
         }
+        if let Some(shadow) = &mut self.shadow {
+            shadow.observe(&task);
+        }
+        if let Some(task) = self.conversations.admit(task) {
+            self.queue.push(task);
+            self.wakeup.wake(WakeReason::Push);
+        }
+        Ok(handle)
+    }
+
+    /// Submit tasks that must be dispatched together
+    ///
+    /// The group waits until every member's agent is dispatchable and, with
+    /// [`Scheduler::with_preemption`], until there are slots to run all
+    /// members at once; then its members are dispatched ahead of other
+    /// tasks. While a group waits only for slots, other tasks are held back
+    /// so running ones can make room. Members go to their own agents, not
+    /// pool siblings, and do not wait for dependencies or conversations.
+    pub fn submit_group(&mut self, mut group: TaskGroup) -> Result<(), RuntimeError> {
+        if self.is_draining() {
+            return Err(SchedulerError::Draining.into());
+        }
+        for task in group.tasks_mut() {
+            if self.dates_tasks {
+                task.date(self.clock.instant());
+            }
+            if let Some(limit) = &self.payload_limit {
+                task.limit_payload(limit)?;
+            }
+        }
+        if group.is_empty() {
+            return Ok(());
+        }
+        self.gangs.push(group);
+        self.wakeup.wake(WakeReason::Push);
+        Ok(())
+    }
+
+    /// Get number of task groups waiting to be dispatched
+    pub fn waiting_groups(&self) -> usize {
+        self.gangs.waiting()
+    }
+
+    /// Take the next member of a task group that may be dispatched
+    fn take_gang_member(&mut self) -> Option<Task> {
+        if let Some(task) = self.gangs.take_released() {
+            return Some(task);
+        }
+        let head = self.gangs.head()?;
+        if self.gang_is_dispatchable(head) && self.has_slots_for(head) {
+            self.gangs.release_head();
+        }
+        self.gangs.take_released()
+    }
+
+    /// Check if every member of a group could be dispatched now
+    fn gang_is_dispatchable(&self, group: &TaskGroup) -> bool {
+        let mut wanted: HashMap<AgentId, usize> = HashMap::new();
+        for task in group.tasks() {
+            if !self.is_dispatchable(task.agent_id())
+                || !self.fits_worker(task)
+                || !self.has_provider_capacity(task)
+                || !self.within_budget(task)
+            {
+                return false;
+            }
+            *wanted.entry(*task.agent_id()).or_default() += 1;
+        }
+        wanted.iter().all(|(agent_id, wanted)| {
+            self.resource_quotas
+                .get(agent_id)
+                .and_then(ResourceQuota::max_concurrent)
+                .is_none_or(|limit| {
+                    self.running.get(agent_id).copied().unwrap_or(0) + wanted <= limit
+                })
+        })
+    }
+
+    /// Check if the limit of running tasks leaves room for a whole group
+    fn has_slots_for(&self, group: &TaskGroup) -> bool {
+        self.max_in_flight.is_none_or(|limit| {
+            self.running.values().sum::<usize>() + self.gangs.released() + group.len() <= limit
+        })
+    }
+
+    /// Check if the next group has its agents but waits for slots
+    fn gang_awaits_slots(&self) -> bool {
+        self.gangs
+            .head()
+            .is_some_and(|group| self.gang_is_dispatchable(group) && !self.has_slots_for(group))
+    }
+
+    /// Submit a task, waiting for room while the queue is full under
+    /// [`OverflowPolicy::Block`]
+    ///
+    /// The scheduler is unlocked while waiting, so producers slow down to
+    /// the rate tasks are dispatched. Other failures are returned as from
+    /// [`Scheduler::submit`].
+    pub async fn submit_async(
+        scheduler: &Mutex<Scheduler>,
+        task: Task,
+    ) -> Result<TaskHandle, RuntimeError> {
+        loop {
+            let mut guard = scheduler.lock().await;
+            let blocks = matches!(guard.capacity, Some((_, OverflowPolicy::Block)));
+            if !blocks || !guard.is_full() {
+                return guard.submit(task);
+            }
+            let room = guard.room.clone();
+            let notified = room.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            drop(guard);
+            notified.await;
+        }
+    }
+
+    /// Bound the number of queued tasks, applying `overflow` to tasks
+    /// submitted while the queue is full
+    ///
+    /// Tasks held for their agent or waiting for earlier tasks of their
+    /// conversation count towards the bound; tasks waiting for dependencies
+    /// do not.
+    pub fn with_queue_capacity(mut self, capacity: usize, overflow: OverflowPolicy) -> Self {
+        self.capacity = Some((capacity.max(1), overflow));
+        self
+    }
+
+    /// Change or remove the queue bound, keeping queued tasks
+    ///
+    /// Tasks already over a lowered bound stay queued; only new submissions
+    /// see it.
+    pub fn set_queue_capacity(&mut self, capacity: Option<(usize, OverflowPolicy)>) {
+        self.capacity = capacity.map(|(capacity, overflow)| (capacity.max(1), overflow));
+        self.room.notify_waiters();
+    }
+
+    /// Get the overflow policy of a bounded queue
+    pub fn overflow_policy(&self) -> Option<OverflowPolicy> {
+        self.capacity.map(|(_, overflow)| overflow)
+    }
+
+    /// Get the queue capacity, if bounded
+    pub fn queue_capacity(&self) -> Option<usize> {
+        self.capacity.map(|(capacity, _)| capacity)
+    }
+
+    /// Check if the queue is at its capacity
+    pub fn is_full(&self) -> bool {
+        self.capacity.is_some_and(|(capacity, _)| {
+            self.queue.len() + self.held.len() + self.conversations.len() >= capacity
+        })
+    }
+
+    /// Take the longest-waiting queued or held task to make room
+    ///
+    /// Tasks waiting for their conversation are never taken, so the order
+    /// of a conversation is kept.
+    fn take_oldest(&mut self) -> Option<Task> {
+        let queued = self
+            .queue
+            .iter()
+            .enumerate()
+            .map(|(index, task)| (index, task.created_at()))
+            .min_by_key(|(_, created_at)| *created_at);
+        let held = self
+            .held
+            .iter()
+            .enumerate()
+            .map(|(index, held)| (index, held.task.created_at()))
+            .min_by_key(|(_, created_at)| *created_at);
+        match (queued, held) {
+            (Some((_, queued_at)), Some((index, held_at))) if held_at < queued_at => {
+                self.held.remove(index).map(|held| held.task)
+            }
+            (Some((index, _)), _) => self.queue.remove(index),
+            (None, Some((index, _))) => self.held.remove(index).map(|held| held.task),
+            (None, None) => None,
+        }
+    }
+
+    /// Discard a task to make room, failing its dependents
+    fn drop_task(&mut self, task: Task, overflow: OverflowPolicy) {
+        tracing::warn!(
+            "Dropped task for agent {}: queue full ({:?})",
+            task.agent_id(),
+            overflow
+        );
+        self.events.push(SchedulerEvent::TaskDropped {
+            agent_id: *task.agent_id(),
+            task: task.id(),
+            overflow,
+        });
+        let capacity = self.queue_capacity().unwrap_or_default();
+        let error = RuntimeError::from(QueueError::Full { capacity });
+        self.store_result(task.id(), Err(TaskFailure::from(&error)));
+        self.complete_task(task.id(), false);
+    }
+
+    /// Store a task's result, enforcing the result limit
+    pub fn accept_result(&self, task: &mut Task, result: Vec<u8>) -> Result<(), RuntimeError> {
+        task.set_result(result, self.result_limit.as_ref())
+    }
+
+    /// Keep at most `capacity` results of finished tasks, each for at most
+    /// `ttl`, unless a [`TaskHandle`] still awaits it
+    pub fn with_result_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.results = Arc::new(ResultStore::new(capacity, ttl, self.clock.clone()));
+        self
+    }
+
+    /// Get a handle awaiting the result of a submitted task
+    pub fn task_handle(&self, id: TaskId) -> TaskHandle {
+        TaskHandle::new(id, self.results.clone())
+    }
+
+    /// Get the result of a finished task, if still cached
+    pub fn task_result(&self, id: TaskId) -> Option<TaskResult> {
+        self.results.get(&id)
+    }
+
+    /// Get number of cached results
+    pub fn cached_results(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Get number of results evicted from the cache
+    pub fn evicted_results(&self) -> u64 {
+        self.results.evicted()
+    }
+
+    /// Record the outcome of a task taken with [`Scheduler::next_task`],
+    /// waking its handles
+    ///
+    /// [`Scheduler::run_with_results`] records this itself.
+    pub fn store_result(&mut self, id: TaskId, result: TaskResult) {
+        self.results.store(id, result);
+    }
+
+    /// Time runtime caps, cost budgets, provider and rate limits, holds, and
+    /// task waits with a clock other than the system clock
+    ///
+    /// Tasks are dated by this clock when first submitted rather than when
+    /// created, and deadlines are checked against it, so set deadlines from
+    /// the same clock. Coalesced wakeup windows also pass on this clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.wakeup = Arc::new(Wakeup::new(clock.clone()));
+        self.results = Arc::new(self.results.with_clock(clock.clone()));
+        self.clock = clock;
+        self.dates_tasks = true;
+        self
+    }
+
+    /// Get the signal a dispatch loop waits on when no task is dispatchable
+    ///
+    /// Submissions, agents becoming dispatchable, and recorded outcomes wake
+    /// it. Wait with [`Scheduler::next_wakeup_at`] as the deadline so held
+    /// tasks are retried once time-based limits expire.
+    pub fn wakeup(&self) -> Arc<Wakeup> {
+        self.wakeup.clone()
+    }
+
+    /// Get the earliest time a held task may become dispatchable without a signal
+    ///
+    /// This covers runtime-cap windows, cost and fuel budget windows, provider
+    /// limits, rate limits, and recurring schedules coming due; `None` means
+    /// only a signal can make more work dispatchable.
+    pub fn next_wakeup_at(&self) -> Option<Instant> {
+        let now = self.clock.instant();
+        let held = self
+            .held
+            .iter()
+            .filter_map(|held| {
+                let agent_id = held.task.agent_id();
+                let cap_reset = self
+                    .caps
+                    .get(agent_id)
+                    .filter(|_| self.is_capped(agent_id))
+                    .zip(self.runtime.get(agent_id))
+                    .map(|(cap, usage)| usage.resets_at(cap.window(), now));
+                let provider_release = held
+                    .task
+                    .provider()
+                    .and_then(|demand| self.providers.get(demand.provider()))
+                    .and_then(|pool| pool.next_release(now));
+                let rate_release = self
+                    .rate_limiter
+                    .available_at(agent_id, now)
+                    .filter(|release| *release > now);
+                let budget_reset = self
+                    .cost_budgets
+                    .get(agent_id)
+                    .filter(|_| self.is_budget_exhausted(agent_id))
+                    .zip(self.costs.get(agent_id))
+                    .map(|(budget, costs)| costs.resets_at(budget.window()));
+                let fuel_reset = self
+                    .resource_quotas
+                    .get(agent_id)
+                    .and_then(ResourceQuota::fuel_budget)
+                    .filter(|_| self.is_out_of_fuel(agent_id))
+                    .zip(self.fuel.get(agent_id))
+                    .map(|((_, window), fuel)| fuel.resets_at(window, now));
+                cap_reset
+                    .into_iter()
+                    .chain(budget_reset)
+                    .chain(fuel_reset)
+                    .chain(provider_release)
+                    .chain(rate_release)
+                    .min()
+            })
+            .min();
+        held.into_iter().chain(self.next_fire_at()).min()
+    }
+
+    /// Wait until work may be dispatchable, after [`Scheduler::next_task`] returned `None`
+    pub async fn wait_for_work(&self) -> WakeReason {
+        self.wakeup.wait(self.next_wakeup_at()).await
+    }
+
+    /// Set how tasks of equal priority are ordered
+    ///
+    /// The scheduler serves the priority of the task at the head of the
+    /// queue, choosing among all queued tasks of that priority.
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Seed the random draws of [`TieBreak::WeightedLottery`], for reproducible runs
+    pub fn with_lottery_seed(mut self, seed: u64) -> Self {
+        self.lottery = Lottery::with_seed(seed);
+        self
+    }
+
+    /// Get tie-breaking within a priority level
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
+
+    /// Set an agent's share for [`TieBreak::WeightedLottery`], 1.0 by default
+    pub fn set_agent_share(&mut self, agent_id: AgentId, share: f64) {
+        self.lottery.set_share(agent_id, share);
+    }
+
+    /// Divide dispatches among share groups under [`PolicyType::FairShare`]
+    ///
+    /// Agents outside the share group tree are served only when no agent in
+    /// it has queued work. Dispatches are counted against the tree whatever
+    /// the policy, so its usage can be inspected.
+    pub fn with_fair_share(mut self, fair_share: FairShareScheduler) -> Self {
+        self.fair_share = Some(fair_share);
+        self
+    }
+
+    /// Get fair share scheduler
+    pub fn fair_share(&self) -> Option<&FairShareScheduler> {
+        self.fair_share.as_ref()
+    }
+
+    /// Evaluate a candidate policy alongside the active one without dispatching by it
+    ///
+    /// The shadow sees tasks queued with [`Scheduler::submit`] and makes a
+    /// decision for every task the scheduler dispatches.
+    pub fn with_shadow_policy(mut self, policy: SchedulingPolicy) -> Self {
+        self.shadow = Some(ShadowScheduler::new(policy));
+        self
+    }
+
+    /// Get the shadow scheduler, if one is running
+    pub fn shadow(&self) -> Option<&ShadowScheduler> {
+        self.shadow.as_ref()
+    }
+
+    /// Get engine tuning
+    pub fn tuning(&self) -> &EngineTuning {
+        &self.tuning
     }
 
     /// Get scheduling policy
@@ -20,6 +923,34 @@ impl Scheduler {
         &self.policy
     }
 
+    /// Switch to another built-in policy, keeping queued tasks
+    ///
+    /// Replaces any [`PolicyEngine`] set before.
+    pub fn set_policy(&mut self, policy: SchedulingPolicy) {
+        tracing::info!("Switching scheduling policy to {:?}", policy.policy_type());
+        self.policy = policy;
+        self.policy_engine = None;
+    }
+
+    /// Pick tasks with a custom policy engine instead of the built-in policy
+    pub fn with_policy_engine(mut self, engine: impl PolicyEngine + 'static) -> Self {
+        self.set_policy_engine(engine);
+        self
+    }
+
+    /// Switch to a custom policy engine, keeping queued tasks
+    ///
+    /// Held tasks, quotas, and other dispatch checks still apply to the
+    /// tasks it picks.
+    pub fn set_policy_engine(&mut self, engine: impl PolicyEngine + 'static) {
+        self.policy_engine = Some(Box::new(engine));
+    }
+
+    /// Check if a custom policy engine picks tasks
+    pub fn has_policy_engine(&self) -> bool {
+        self.policy_engine.is_some()
+    }
+
     /// Get task queue
     pub fn queue(&self) -> &TaskQueue {
         &self.queue
@@ -29,4 +960,2054 @@ impl Scheduler {
     pub fn queue_mut(&mut self) -> &mut TaskQueue {
         &mut self.queue
     }
+
+    /// Add an agent pool used to reroute tasks away from gated agents
+    pub fn add_pool(&mut self, pool: AgentPool) {
+        self.pools.push(pool);
+    }
+
+    /// Get agent pools
+    pub fn pools(&self) -> &[AgentPool] {
+        &self.pools
+    }
+
+    /// Update the health of an agent
+    pub fn set_agent_health(&mut self, agent_id: AgentId, status: HealthStatus) {
+        let previous = self.health.insert(agent_id, status);
+        if previous == Some(HealthStatus::Unhealthy) && status != HealthStatus::Unhealthy {
+            self.wakeup.wake(WakeReason::Capacity);
+        }
+    }
+
+    /// Update the readiness of an agent
+    pub fn set_agent_ready(&mut self, agent_id: AgentId, ready: bool) {
+        if ready {
+            if self.not_ready.remove(&agent_id) {
+                self.wakeup.wake(WakeReason::Capacity);
+            }
+        } else {
+            self.not_ready.insert(agent_id);
+        }
+    }
+
+    /// Stop dispatching new tasks to an agent, e.g. for maintenance
+    ///
+    /// Tasks already dispatched keep running; queued tasks are rerouted to a
+    /// pool sibling or held until [`Scheduler::uncordon`].
+    pub fn cordon(&mut self, agent_id: AgentId) {
+        self.cordoned.insert(agent_id);
+    }
+
+    /// Resume dispatching to a cordoned agent
+    pub fn uncordon(&mut self, agent_id: &AgentId) {
+        if self.cordoned.remove(agent_id) {
+            self.wakeup.wake(WakeReason::Capacity);
+        }
+    }
+
+    /// Check if an agent is cordoned
+    pub fn is_cordoned(&self, agent_id: &AgentId) -> bool {
+        self.cordoned.contains(agent_id)
+    }
+
+    /// Set aside an agent's queued work and stop dispatching to it
+    ///
+    /// Queued and held tasks of the agent move to a parked buffer, as do
+    /// tasks submitted for it until [`Scheduler::unpark`]. Tasks already
+    /// dispatched keep running. Returns the number of tasks parked.
+    pub fn park(&mut self, agent_id: AgentId) -> usize {
+        let mut parked = Vec::new();
+        let held = std::mem::take(&mut self.held);
+        for held in held {
+            if held.task.agent_id() == &agent_id {
+                parked.push(held.task);
+            } else {
+                self.held.push_back(held);
+            }
+        }
+        let mut index = 0;
+        while index < self.queue.len() {
+            if self.queue.iter().nth(index).map(Task::agent_id) == Some(&agent_id) {
+                parked.extend(self.queue.remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        let buffer = self.parked.entry(agent_id).or_default();
+        buffer.extend(parked);
+        tracing::info!("Parked {} tasks of agent {}", buffer.len(), agent_id);
+        self.room.notify_waiters();
+        buffer.len()
+    }
+
+    /// Queue a parked agent's work again and resume dispatching to it
+    ///
+    /// Returns the number of tasks requeued; tasks that cannot be submitted
+    /// again are dead-lettered.
+    pub fn unpark(&mut self, agent_id: &AgentId) -> usize {
+        let parked = self.parked.remove(agent_id).unwrap_or_default();
+        let count = parked.len();
+        for task in parked {
+            self.resubmit(task);
+        }
+        self.wakeup.wake(WakeReason::Capacity);
+        count
+    }
+
+    /// Check if an agent's work is parked
+    pub fn is_parked(&self, agent_id: &AgentId) -> bool {
+        self.parked.contains_key(agent_id)
+    }
+
+    /// Get number of an agent's parked tasks
+    pub fn parked(&self, agent_id: &AgentId) -> usize {
+        self.parked.get(agent_id).map_or(0, Vec::len)
+    }
+
+    /// Never run two agents at the same time
+    ///
+    /// Tasks of either agent are held while the other has a task running.
+    pub fn set_anti_affinity(&mut self, a: AgentId, b: AgentId) {
+        if a != b {
+            self.rivals.entry(a).or_default().insert(b);
+            self.rivals.entry(b).or_default().insert(a);
+        }
+    }
+
+    /// Let two agents run at the same time again
+    pub fn clear_anti_affinity(&mut self, a: &AgentId, b: &AgentId) {
+        if let Some(rivals) = self.rivals.get_mut(a) {
+            rivals.remove(b);
+        }
+        if let Some(rivals) = self.rivals.get_mut(b) {
+            rivals.remove(a);
+        }
+        self.wakeup.wake(WakeReason::Capacity);
+    }
+
+    /// Check if an agent has a rival with a task running
+    fn has_running_rival(&self, agent_id: &AgentId) -> bool {
+        self.rivals.get(agent_id).is_some_and(|rivals| {
+            rivals
+                .iter()
+                .any(|rival| self.running.get(rival).is_some_and(|running| *running > 0))
+        })
+    }
+
+    /// Get the worker tasks with an affinity key are pinned to
+    pub fn pinned_worker(&self, key: &str) -> Option<&str> {
+        self.pins.get(key).map(String::as_str)
+    }
+
+    /// Unpin an affinity key, letting the next worker to ask take it
+    pub fn unpin(&mut self, key: &str) -> Option<String> {
+        self.pins.remove(key)
+    }
+
+    /// Unpin every affinity key pinned to a worker, e.g. one that was lost
+    ///
+    /// Returns how many keys were unpinned.
+    pub fn unpin_worker(&mut self, worker: &str) -> usize {
+        let before = self.pins.len();
+        self.pins.retain(|_, pinned| pinned != worker);
+        before - self.pins.len()
+    }
+
+    /// Check if a task may go to the worker asking for one
+    ///
+    /// Tasks without an affinity key, and tasks taken by [`Scheduler::next_task`]
+    /// on behalf of no particular worker, fit anywhere.
+    fn fits_worker(&self, task: &Task) -> bool {
+        match (task.affinity(), &self.dispatching_to) {
+            (Some(key), Some(worker)) => self.pins.get(key).is_none_or(|pinned| pinned == worker),
+            _ => true,
+        }
+    }
+
+    /// Label an agent with the version it runs
+    pub fn set_agent_version(&mut self, agent_id: AgentId, version: Version) {
+        self.versions.insert(agent_id, version);
+    }
+
+    /// Get an agent's version label
+    pub fn agent_version(&self, agent_id: &AgentId) -> Option<&Version> {
+        self.versions.get(agent_id)
+    }
+
+    /// Check if an agent satisfies a version requirement
+    ///
+    /// Unlabeled agents only satisfy tasks without a requirement.
+    pub fn is_compatible(&self, agent_id: &AgentId, req: Option<&VersionReq>) -> bool {
+        req.is_none_or(|req| {
+            self.versions
+                .get(agent_id)
+                .is_some_and(|version| req.matches(version))
+        })
+    }
+
+    /// Start shifting tasks for a deployment's stable agent to its candidate
+    pub fn deploy(&mut self, deployment: Deployment) {
+        self.deployments.insert(*deployment.stable(), deployment);
+    }
+
+    /// Get the deployment replacing a stable agent
+    pub fn deployment(&self, stable: &AgentId) -> Option<&Deployment> {
+        self.deployments.get(stable)
+    }
+
+    /// Get the deployment replacing a stable agent mutably, e.g. to promote it
+    pub fn deployment_mut(&mut self, stable: &AgentId) -> Option<&mut Deployment> {
+        self.deployments.get_mut(stable)
+    }
+
+    /// Roll a deployment back, moving tasks queued or held for its candidate to the stable agent
+    ///
+    /// Returns the number of tasks moved, or `None` if no deployment replaces
+    /// `stable`.
+    pub fn roll_back(&mut self, stable: &AgentId) -> Option<usize> {
+        let deployment = self.deployments.get_mut(stable)?;
+        deployment.roll_back();
+        let candidate = *deployment.candidate();
+        Some(self.reroute(&candidate, *stable))
+    }
+
+    /// Move queued and held tasks of one agent to another
+    fn reroute(&mut self, from: &AgentId, to: AgentId) -> usize {
+        let mut moved = 0;
+        for held in self
+            .held
+            .iter_mut()
+            .filter(|held| held.task.agent_id() == from)
+        {
+            held.task.reassign(to);
+            moved += 1;
+        }
+        let mut tasks = Vec::new();
+        let mut index = 0;
+        while index < self.queue.len() {
+            if self.queue.iter().nth(index).map(Task::agent_id) == Some(from) {
+                tasks.extend(self.queue.remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        moved += tasks.len();
+        for mut task in tasks {
+            task.reassign(to);
+            self.queue.push(task);
+        }
+        if moved > 0 {
+            tracing::info!("Rerouted {} tasks from agent {} to {}", moved, from, to);
+            self.wakeup.wake(WakeReason::Capacity);
+        }
+        moved
+    }
+
+    /// Remove a deployment, returning it
+    pub fn finish_deployment(&mut self, stable: &AgentId) -> Option<Deployment> {
+        self.deployments.remove(stable)
+    }
+
+    /// Record whether a task run by an agent succeeded, for deployment decisions
+    ///
+    /// A deployment rolled back by its auto decision has its candidate's
+    /// queued tasks moved to the stable agent.
+    pub fn record_outcome(&mut self, agent_id: &AgentId, success: bool) {
+        let mut rolled_back = Vec::new();
+        for deployment in self.deployments.values_mut() {
+            let before = deployment.state();
+            deployment.record(agent_id, success);
+            if before != DeploymentState::RolledBack
+                && deployment.state() == DeploymentState::RolledBack
+            {
+                rolled_back.push((*deployment.candidate(), *deployment.stable()));
+            }
+        }
+        for (candidate, stable) in rolled_back {
+            self.reroute(&candidate, stable);
+        }
+        self.release(agent_id);
+    }
+
+    /// Free the slot of a task that stopped running
+    fn release(&mut self, agent_id: &AgentId) {
+        if let Some(running) = self.running.get_mut(agent_id) {
+            *running = running.saturating_sub(1);
+        }
+        self.wakeup.wake(WakeReason::Completion);
+    }
+
+    /// Copy health of all supervised agents from a supervisor
+    #[cfg(feature = "supervisor")]
+    pub fn sync_health(&mut self, supervisor: &Supervisor) {
+        for (agent_id, status) in supervisor.health_statuses() {
+            self.set_agent_health(*agent_id, status);
+        }
+    }
+
+    /// Limit the cost an agent may spend per window
+    ///
+    /// Setting a higher limit closes a tripped breaker immediately.
+    pub fn set_cost_budget(&mut self, agent_id: AgentId, budget: CostBudget) {
+        self.cost_budgets.insert(agent_id, budget);
+        self.refresh_budget(agent_id);
+    }
+
+    /// Remove an agent's cost budget
+    pub fn clear_cost_budget(&mut self, agent_id: &AgentId) {
+        self.cost_budgets.remove(agent_id);
+        self.refresh_budget(*agent_id);
+    }
+
+    /// Get an agent's cost budget
+    pub fn cost_budget(&self, agent_id: &AgentId) -> Option<&CostBudget> {
+        self.cost_budgets.get(agent_id)
+    }
+
+    /// Record cost spent by an agent, counted against its budget
+    pub fn record_cost(&mut self, agent_id: AgentId, cost: u64) {
+        self.record_usage(agent_id, BudgetUsage::new(0, cost));
+    }
+
+    /// Record tokens and cost spent by an agent, counted against its budget
+    ///
+    /// Returns true if this spend tripped the agent's budget breaker.
+    pub fn record_usage(&mut self, agent_id: AgentId, usage: BudgetUsage) -> bool {
+        let window = self.cost_budgets.get(&agent_id).map(CostBudget::window);
+        let now = self.clock.instant();
+        self.costs
+            .entry(agent_id)
+            .or_insert_with(|| CostWindow::new(now))
+            .record(usage, window, now);
+        let was_tripped = self.tripped.contains(&agent_id);
+        self.refresh_budget(agent_id);
+        !was_tripped && self.tripped.contains(&agent_id)
+    }
+
+    /// Get cost spent by an agent in its current window
+    pub fn cost_spent(&self, agent_id: &AgentId) -> u64 {
+        self.budget_usage(agent_id).cost
+    }
+
+    /// Get tokens and cost spent by an agent in its current window
+    pub fn budget_usage(&self, agent_id: &AgentId) -> BudgetUsage {
+        let window = self.cost_budgets.get(agent_id).map(CostBudget::window);
+        self.costs
+            .get(agent_id)
+            .map_or_else(BudgetUsage::default, |costs| {
+                costs.spent(window, self.clock.instant())
+            })
+    }
+
+    /// Check if an agent has spent its cost or token budget for the current window
+    pub fn is_budget_exhausted(&self, agent_id: &AgentId) -> bool {
+        self.cost_budgets
+            .get(agent_id)
+            .is_some_and(|budget| budget.is_exhausted_by(&self.budget_usage(agent_id)))
+    }
+
+    /// Check if a task may run under its agent's budget
+    ///
+    /// Non-critical tasks of an agent that spent its budget are held until
+    /// the window resets or the limit is raised.
+    fn within_budget(&self, task: &Task) -> bool {
+        self.cost_budgets.get(task.agent_id()).is_none_or(|budget| {
+            budget.is_critical(task.priority()) || !self.is_budget_exhausted(task.agent_id())
+        })
+    }
+
+    /// Trip or close an agent's budget breaker, emitting an event on change
+    fn refresh_budget(&mut self, agent_id: AgentId) {
+        if self.is_budget_exhausted(&agent_id) {
+            if self.tripped.insert(agent_id) {
+                self.events.push(SchedulerEvent::BudgetTripped {
+                    agent_id,
+                    spent: self.cost_spent(&agent_id),
+                    limit: self.cost_budgets[&agent_id].limit(),
+                });
+            }
+        } else if self.tripped.remove(&agent_id) {
+            self.events.push(SchedulerEvent::BudgetReset { agent_id });
+        }
+    }
+
+    /// Throttle dispatch per agent and globally
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    /// Get rate limiter
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Get mutable rate limiter, to change limits while running
+    pub fn rate_limiter_mut(&mut self) -> &mut RateLimiter {
+        &mut self.rate_limiter
+    }
+
+    /// Only dispatch to agents the lifecycle reports as running
+    ///
+    /// Held tasks are reconsidered whenever an agent starts running.
+    pub fn with_lifecycle(mut self, lifecycle: Arc<AgentLifecycle>) -> Self {
+        let wakeup = self.wakeup.clone();
+        lifecycle.on_transition(move |transition| {
+            if transition.to == LifecycleState::Running {
+                wakeup.wake(WakeReason::Capacity);
+            }
+        });
+        self.lifecycle = Some(lifecycle);
+        self
+    }
+
+    /// Get agent lifecycle
+    pub fn lifecycle(&self) -> Option<&Arc<AgentLifecycle>> {
+        self.lifecycle.as_ref()
+    }
+
+    /// Run at most `max_in_flight` tasks at once in [`Scheduler::run`], preempting for urgent ones
+    ///
+    /// Once the limit is reached, a waiting task that outranks a running one
+    /// signals the lowest-priority task's [`PreemptionHandle`], after that
+    /// task ran for the tuning profile's time slice.
+    pub fn with_preemption(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight.max(1));
+        self
+    }
+
+    /// Get number of running tasks asked to yield
+    pub fn preemptions(&self) -> u64 {
+        self.preemptions
+    }
+
+    /// Keep at most `capacity` dead-lettered tasks
+    pub fn with_dead_letter_capacity(mut self, capacity: usize) -> Self {
+        self.dead_letters = DeadLetterQueue::new(capacity);
+        self
+    }
+
+    /// Get tasks that failed for good or could not be delivered
+    pub fn dead_letters(&self) -> &DeadLetterQueue {
+        &self.dead_letters
+    }
+
+    /// Get mutable dead-letter queue, to purge entries
+    pub fn dead_letters_mut(&mut self) -> &mut DeadLetterQueue {
+        &mut self.dead_letters
+    }
+
+    /// Remove dead letters older than `age` by the scheduler's clock,
+    /// returning how many were removed
+    pub fn purge_dead_letters_older_than(&mut self, age: Duration) -> usize {
+        self.dead_letters
+            .purge_older_than(age, self.clock.wall_time())
+    }
+
+    /// Submit a dead-lettered task again with a fresh retry allowance
+    ///
+    /// Returns `Ok(false)` if there is no such entry. An entry that cannot be
+    /// submitted stays in the dead-letter queue.
+    pub fn replay_dead_letter(&mut self, id: u64) -> Result<bool, RuntimeError> {
+        let Some(entry) = self.dead_letters.take(id) else {
+            return Ok(false);
+        };
+        let mut task = entry.task.clone();
+        task.reset_attempts();
+        if let Err(error) = self.submit(task) {
+            self.dead_letters.restore(entry);
+            return Err(error);
+        }
+        Ok(true)
+    }
+
+    /// Replay every dead-lettered task, returning how many were submitted
+    pub fn replay_dead_letters(&mut self) -> usize {
+        let ids: Vec<u64> = self.dead_letters.iter().map(|entry| entry.id).collect();
+        ids.into_iter()
+            .filter(|id| matches!(self.replay_dead_letter(*id), Ok(true)))
+            .count()
+    }
+
+    /// Queue a task at `priority` for agents receiving mail
+    ///
+    /// One task is queued per batch of mail: a new one only after the agent
+    /// drained its mailbox.
+    pub fn with_mailboxes(mut self, mailboxes: &Mailboxes, priority: u32) -> Self {
+        let arrived = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let wakeup = self.wakeup.clone();
+        mailboxes.on_mail({
+            let arrived = arrived.clone();
+            move |agent_id| {
+                arrived
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(*agent_id);
+                wakeup.wake(WakeReason::Push);
+            }
+        });
+        self.mail = Some(MailTasks { priority, arrived });
+        self
+    }
+
+    /// Register a rate-limited provider shared by all agents
+    pub fn add_provider(&mut self, name: impl Into<String>, limits: ProviderLimits) {
+        self.providers
+            .insert(name.into(), ProviderPool::new(limits));
+    }
+
+    /// Get a provider's usage within the last minute
+    pub fn provider_usage(&self, name: &str) -> Option<ProviderUsage> {
+        self.providers
+            .get(name)
+            .map(|pool| pool.usage(self.clock.instant()))
+    }
+
+    /// Check if a task's declared provider usage fits the provider's limits
+    ///
+    /// Tasks naming an unregistered provider are not limited.
+    fn has_provider_capacity(&self, task: &Task) -> bool {
+        task.provider().is_none_or(|demand| {
+            self.providers
+                .get(demand.provider())
+                .is_none_or(|pool| pool.admits(demand.tokens(), self.clock.instant()))
+        })
+    }
+
+    /// Add a recurring schedule, replacing any with the same name
+    pub fn add_schedule(&mut self, schedule: RecurringSchedule) {
+        self.schedules
+            .retain(|existing| existing.name() != schedule.name());
+        self.schedules.push(schedule);
+    }
+
+    /// Remove a recurring schedule
+    pub fn remove_schedule(&mut self, name: &str) -> Option<RecurringSchedule> {
+        let index = self
+            .schedules
+            .iter()
+            .position(|schedule| schedule.name() == name)?;
+        Some(self.schedules.remove(index))
+    }
+
+    /// Get recurring schedules
+    pub fn schedules(&self) -> &[RecurringSchedule] {
+        &self.schedules
+    }
+
+    /// Submit tasks for schedules due at `now`, applying each schedule's misfire policy
+    ///
+    /// [`Scheduler::run`] calls this whenever a schedule comes due; loops
+    /// dispatching by hand call it with the clock's wall time. A fire that
+    /// cannot be submitted is dead-lettered as
+    /// [`DeadLetterReason::Undeliverable`], from where it can be retried, and
+    /// the remaining fires still go out. Returns the number of tasks
+    /// submitted.
+    pub fn fire_due(&mut self, now: SystemTime) -> usize {
+        let mut due = Vec::new();
+        for schedule in &mut self.schedules {
+            let fires = schedule.due(now);
+            due.extend((0..fires).map(|_| (schedule.name().to_string(), schedule.task())));
+        }
+        let mut fired = 0;
+        for (name, task) in due {
+            match self.submit(task.clone()) {
+                Ok(_) => fired += 1,
+                Err(error) => {
+                    tracing::warn!("Could not submit a fire of schedule {}: {}", name, error);
+                    self.dead_letters.push(
+                        task,
+                        DeadLetterReason::Undeliverable,
+                        Some(error.to_string()),
+                        self.clock.wall_time(),
+                    );
+                }
+            }
+        }
+        fired
+    }
+
+    /// Get when the next recurring schedule comes due
+    fn next_fire_at(&self) -> Option<Instant> {
+        let next_fire = self
+            .schedules
+            .iter()
+            .filter_map(RecurringSchedule::next_fire)
+            .min()?;
+        let wait = next_fire
+            .duration_since(self.clock.wall_time())
+            .unwrap_or_default();
+        Some(self.clock.instant() + wait)
+    }
+
+    /// Cap an agent's cumulative execution time per window
+    pub fn set_runtime_cap(&mut self, agent_id: AgentId, cap: RuntimeCap) {
+        self.caps.insert(agent_id, cap);
+    }
+
+    /// Remove an agent's runtime cap
+    pub fn clear_runtime_cap(&mut self, agent_id: &AgentId) {
+        if self.caps.remove(agent_id).is_some() {
+            self.wakeup.wake(WakeReason::Capacity);
+        }
+    }
+
+    /// Limit an agent's concurrent tasks and CPU time
+    pub fn with_resource_quota(mut self, agent_id: AgentId, quota: ResourceQuota) -> Self {
+        self.set_resource_quota(agent_id, quota);
+        self
+    }
+
+    /// Set an agent's resource quota
+    ///
+    /// Tasks count as running from dispatch until [`Scheduler::record_outcome`].
+    /// A CPU time limit replaces the agent's runtime cap, deferring its tasks
+    /// to the next window once used up; a fuel budget likewise holds them once
+    /// the fuel reported with [`Scheduler::record_fuel`] uses it up. Memory is
+    /// enforced by the supervisor.
+    pub fn set_resource_quota(&mut self, agent_id: AgentId, quota: ResourceQuota) {
+        if let Some(previous) = self.resource_quotas.remove(&agent_id) {
+            self.remove_quota_cap(&agent_id, &previous);
+        }
+        if let Some((budget, window)) = quota.cpu_time() {
+            self.set_runtime_cap(agent_id, RuntimeCap::new(budget, window));
+        }
+        self.resource_quotas.insert(agent_id, quota);
+        self.wakeup.wake(WakeReason::Capacity);
+    }
+
+    /// Remove an agent's resource quota
+    ///
+    /// The runtime cap its CPU time limit installed goes with it; a cap set
+    /// since with [`Scheduler::set_runtime_cap`] stays.
+    pub fn clear_resource_quota(&mut self, agent_id: &AgentId) {
+        let Some(quota) = self.resource_quotas.remove(agent_id) else {
+            return;
+        };
+        self.remove_quota_cap(agent_id, &quota);
+        self.wakeup.wake(WakeReason::Capacity);
+    }
+
+    /// Remove the runtime cap a quota installed, if it is still in place
+    fn remove_quota_cap(&mut self, agent_id: &AgentId, quota: &ResourceQuota) {
+        let installed = quota
+            .cpu_time()
+            .map(|(budget, window)| RuntimeCap::new(budget, window));
+        if installed.is_some() && self.caps.get(agent_id) == installed.as_ref() {
+            self.clear_runtime_cap(agent_id);
+        }
+    }
+
+    /// Get an agent's resource quota
+    pub fn resource_quota(&self, agent_id: &AgentId) -> Option<&ResourceQuota> {
+        self.resource_quotas.get(agent_id)
+    }
+
+    /// Get an agent's running tasks, and CPU time and fuel used in their windows
+    pub fn resource_usage(&self, agent_id: &AgentId) -> QuotaUsage {
+        QuotaUsage {
+            running: self.running.get(agent_id).copied().unwrap_or(0),
+            cpu_time: self.agent_stats(agent_id).window_runtime,
+            memory: 0,
+            fuel: self.fuel_burned(agent_id),
+        }
+    }
+
+    /// Record WebAssembly fuel burned by an agent's task, counted against its fuel budget
+    ///
+    /// The `wasm` feature's `WasmExecutor` reports this
+    /// itself once given the scheduler.
+    pub fn record_fuel(&mut self, agent_id: AgentId, fuel: u64) {
+        let window = self.fuel_window(&agent_id);
+        let now = self.clock.instant();
+        self.fuel
+            .entry(agent_id)
+            .or_insert_with(|| FuelWindow::new(now))
+            .record(fuel, window, now);
+        if !self.is_out_of_fuel(&agent_id) {
+            self.wakeup.wake(WakeReason::Capacity);
+        }
+    }
+
+    /// Check if an agent has burned its fuel budget for the current window
+    pub fn is_out_of_fuel(&self, agent_id: &AgentId) -> bool {
+        self.resource_quotas
+            .get(agent_id)
+            .and_then(ResourceQuota::fuel_budget)
+            .is_some_and(|(budget, _)| self.fuel_burned(agent_id) >= budget)
+    }
+
+    fn fuel_window(&self, agent_id: &AgentId) -> Option<Duration> {
+        self.resource_quotas
+            .get(agent_id)
+            .and_then(ResourceQuota::fuel_budget)
+            .map(|(_, window)| window)
+    }
+
+    fn fuel_burned(&self, agent_id: &AgentId) -> u64 {
+        self.fuel.get(agent_id).map_or(0, |fuel| {
+            fuel.burned(self.fuel_window(agent_id), self.clock.instant())
+        })
+    }
+
+    /// Check if an agent runs as many tasks as its quota allows
+    fn at_concurrency_limit(&self, agent_id: &AgentId) -> bool {
+        self.resource_quotas
+            .get(agent_id)
+            .and_then(ResourceQuota::max_concurrent)
+            .is_some_and(|limit| self.running.get(agent_id).copied().unwrap_or(0) >= limit)
+    }
+
+    /// Record execution time used by an agent, counted against its cap
+    pub fn record_runtime(&mut self, agent_id: AgentId, runtime: Duration) {
+        #[cfg(feature = "metrics")]
+        self.run_time.record(runtime);
+        let window = self.caps.get(&agent_id).map(RuntimeCap::window);
+        let now = self.clock.instant();
+        self.runtime
+            .entry(agent_id)
+            .or_insert_with(|| RuntimeWindow::new(now))
+            .record(runtime, window, now);
+    }
+
+    /// Check if an agent has used up its runtime cap for the current window
+    pub fn is_capped(&self, agent_id: &AgentId) -> bool {
+        match (self.caps.get(agent_id), self.runtime.get(agent_id)) {
+            (Some(cap), Some(usage)) => {
+                usage.used(Some(cap.window()), self.clock.instant()) >= cap.limit()
+            }
+            _ => false,
+        }
+    }
+
+    /// Account a settled execution of a task in its agent's [`AgentStats`]
+    ///
+    /// [`Scheduler::run`] records this itself, along with the run time. With
+    /// a fair share scheduler charging runtime, the execution's `used` time
+    /// is also charged against its agent.
+    pub fn record_execution(&mut self, task: &Task, used: Duration, success: bool) {
+        let latency = self
+            .clock
+            .instant()
+            .saturating_duration_since(task.created_at());
+        self.accounting.record(*task.agent_id(), latency, success);
+        if let Some(fair_share) = self
+            .fair_share
+            .as_mut()
+            .filter(|fair_share| fair_share.charges_runtime())
+        {
+            let slots = u64::try_from(used.as_millis()).unwrap_or(u64::MAX);
+            fair_share.record(task.agent_id(), slots.max(1));
+        }
+    }
+
+    /// Get an agent's run time, runtime cap state, and execution totals
+    pub fn agent_stats(&self, agent_id: &AgentId) -> AgentStats {
+        let window = self.caps.get(agent_id).map(RuntimeCap::window);
+        let usage = self.runtime.get(agent_id);
+        let now = self.clock.instant();
+        let executions = self.accounting.get(agent_id);
+        AgentStats {
+            window_runtime: usage.map_or(Duration::ZERO, |usage| usage.used(window, now)),
+            total_runtime: usage.map_or(Duration::ZERO, RuntimeWindow::total),
+            capped: self.is_capped(agent_id),
+            window_resets_at: window
+                .zip(usage)
+                .map(|(window, usage)| usage.resets_at(window, now)),
+            rejected: usage.map_or(0, RuntimeWindow::rejected),
+            tasks: executions.tasks,
+            failures: executions.failures,
+            total_latency: executions.total_latency,
+        }
+    }
+
+    /// Check if tasks may be dispatched to an agent
+    ///
+    /// Agents with unknown health are dispatchable; cordoned or parked
+    /// agents, agents over their runtime cap, rate limit, fuel budget, or
+    /// concurrency quota, agents with an anti-affinity rival running, and
+    /// agents the lifecycle does not report as running are not.
+    pub fn is_dispatchable(&self, agent_id: &AgentId) -> bool {
+        !self.not_ready.contains(agent_id)
+            && self
+                .lifecycle
+                .as_ref()
+                .is_none_or(|lifecycle| lifecycle.is_schedulable(agent_id))
+            && !self.cordoned.contains(agent_id)
+            && !self.parked.contains_key(agent_id)
+            && self.health.get(agent_id) != Some(&HealthStatus::Unhealthy)
+            && !self.is_capped(agent_id)
+            && !self.is_out_of_fuel(agent_id)
+            && !self.at_concurrency_limit(agent_id)
+            && !self.has_running_rival(agent_id)
+            && self.rate_limiter.admits(agent_id, self.clock.instant())
+    }
+
+    /// Tell why a task that cannot be routed is held
+    fn hold_reason(&self, task: &Task) -> HoldReason {
+        let agent_id = task.agent_id();
+        if !self.fits_worker(task) {
+            HoldReason::Affinity
+        } else if !self.has_provider_capacity(task) {
+            HoldReason::Provider
+        } else if !self.within_budget(task) {
+            HoldReason::Budget
+        } else if self.not_ready.contains(agent_id)
+            || self.health.get(agent_id) == Some(&HealthStatus::Unhealthy)
+            || self
+                .lifecycle
+                .as_ref()
+                .is_some_and(|lifecycle| !lifecycle.is_schedulable(agent_id))
+        {
+            HoldReason::Health
+        } else if self.cordoned.contains(agent_id) {
+            HoldReason::Cordoned
+        } else if self.is_capped(agent_id) || self.is_out_of_fuel(agent_id) {
+            HoldReason::RuntimeCap
+        } else if self.at_concurrency_limit(agent_id) {
+            HoldReason::Concurrency
+        } else if self.has_running_rival(agent_id) {
+            HoldReason::AntiAffinity
+        } else {
+            HoldReason::RateLimit
+        }
+    }
+
+    /// Check if an agent's tasks should be rejected rather than held
+    fn rejects(&self, agent_id: &AgentId) -> bool {
+        self.is_capped(agent_id)
+            && self.caps.get(agent_id).map(RuntimeCap::action) == Some(CapAction::Reject)
+    }
+
+    /// Find a dispatchable agent in the same pool
+    fn sibling_for(&self, task: &Task) -> Option<AgentId> {
+        self.pool_members(task.agent_id())
+            .find(|member| {
+                *member != task.agent_id()
+                    && self.is_dispatchable(member)
+                    && self.is_compatible(member, task.version_req())
+            })
+            .copied()
+    }
+
+    /// Iterate over members of every pool containing an agent
+    fn pool_members<'a>(&'a self, agent_id: &'a AgentId) -> impl Iterator<Item = &'a AgentId> {
+        self.pools
+            .iter()
+            .filter(move |pool| pool.contains(agent_id))
+            .flat_map(|pool| pool.members())
+    }
+
+    /// Route a task to its agent or a pool sibling, if either is dispatchable
+    fn route(&self, task: &mut Task) -> bool {
+        if !self.fits_worker(task) || !self.has_provider_capacity(task) || !self.within_budget(task)
+        {
+            return false;
+        }
+        if self.is_dispatchable(task.agent_id()) {
+            return true;
+        }
+        match self.sibling_for(task) {
+            Some(sibling) => {
+                task.reassign(sibling);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take the next task that can be dispatched
+    ///
+    /// Tasks for unhealthy or not-ready agents are rerouted to a pool sibling
+    /// when possible, and otherwise held until the agent recovers. Tasks for
+    /// agents over their runtime cap are held until the next window or
+    /// rejected, depending on the cap's [`CapAction`]. Tasks whose provider
+    /// is at its limit are held until the provider has capacity again, and
+    /// tasks of rate-limited agents until the [`RateLimiter`] admits them.
+    /// Nothing is dispatched once the scheduler is draining.
+    pub fn next_task(&mut self) -> Option<Task> {
+        if self.is_draining() {
+            return None;
+        }
+        self.submit_mail_tasks();
+        let task = self.take_dispatchable()?;
+        self.speculate(&task);
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "task.dispatch",
+            agent_id = %task.agent_id(),
+            priority = task.priority(),
+            attempt = task.attempts() + 1,
+            policy = ?self.policy.policy_type(),
+        )
+        .entered();
+        let started = self.overhead.start();
+        self.dependencies.dispatch(task.id());
+        *self.running.entry(*task.agent_id()).or_default() += 1;
+        self.rate_limiter
+            .charge(*task.agent_id(), self.clock.instant());
+        if let Some(fair_share) = self
+            .fair_share
+            .as_mut()
+            .filter(|fair_share| !fair_share.charges_runtime())
+        {
+            fair_share.record(task.agent_id(), 1);
+        }
+        if let Some(demand) = task.provider() {
+            if let Some(pool) = self.providers.get_mut(demand.provider()) {
+                pool.charge(demand.tokens(), self.clock.instant());
+            }
+        }
+        #[cfg(feature = "metrics")]
+        self.queue_wait.record(
+            self.clock
+                .instant()
+                .saturating_duration_since(task.created_at()),
+        );
+        if let Some(shadow) = &mut self.shadow {
+            shadow.dispatch(&task);
+        }
+        self.overhead.stop(overhead::ACCOUNTING, started);
+        self.room.notify_waiters();
+        Some(task)
+    }
+
+    /// Queue replicas of a speculative task for dispatchable pool siblings
+    fn speculate(&mut self, task: &Task) {
+        if task.replicas() <= 1 || self.speculations.is_active(&task.id()) {
+            return;
+        }
+        let mut agents = vec![*task.agent_id()];
+        for member in self.pool_members(task.agent_id()) {
+            if agents.len() >= task.replicas() as usize {
+                break;
+            }
+            if !agents.contains(member)
+                && self.is_dispatchable(member)
+                && self.is_compatible(member, task.version_req())
+            {
+                agents.push(*member);
+            }
+        }
+        let replicas = agents[1..]
+            .iter()
+            .map(|agent_id| {
+                let mut replica = task.clone();
+                replica.reassign(*agent_id);
+                replica
+            })
+            .collect();
+        self.speculations.launch(task, replicas);
+    }
+
+    /// Get speculative execution counters
+    pub fn speculation_stats(&self) -> SpeculationStats {
+        self.speculations.stats()
+    }
+
+    /// Get the token cancelling a speculative task's losing replicas
+    pub(crate) fn speculation_token(&self, task: &Task) -> Option<CancellationToken> {
+        self.speculations.token(&task.id())
+    }
+
+    /// Take the next task that can be dispatched to `worker`
+    ///
+    /// Works like [`Scheduler::next_task`], but also holds tasks whose
+    /// affinity key is pinned to another worker. A task with a key nobody
+    /// holds pins the key to `worker`.
+    pub fn next_task_on(&mut self, worker: &str) -> Option<Task> {
+        self.dispatching_to = Some(worker.to_string());
+        let task = self.next_task();
+        self.dispatching_to = None;
+        if let Some(key) = task.as_ref().and_then(Task::affinity) {
+            self.pins
+                .entry(key.to_string())
+                .or_insert_with(|| worker.to_string());
+        }
+        task
+    }
+
+    fn take_dispatchable(&mut self) -> Option<Task> {
+        if let Some(replica) = self.speculations.take_replica() {
+            return Some(replica);
+        }
+        if let Some(task) = self.take_gang_member() {
+            return Some(task);
+        }
+        if self.gang_awaits_slots() {
+            return None;
+        }
+        if let Some(task) = self.take_overdue() {
+            return Some(task);
+        }
+
+        for index in 0..self.held.len() {
+            let started = self.overhead.start();
+            let mut task = self.held[index].task.clone();
+            self.overhead.stop(overhead::SELECTION, started);
+
+            let started = self.overhead.start();
+            let routed = self.route(&mut task);
+            self.overhead.stop(overhead::DISPATCH, started);
+
+            if routed {
+                let started = self.overhead.start();
+                let held = self.held.remove(index)?;
+                *self.held_time.entry(held.reason).or_default() +=
+                    self.clock.instant().saturating_duration_since(held.since);
+                self.overhead.stop(overhead::BOOKKEEPING, started);
+                return Some(task);
+            }
+        }
+
+        loop {
+            let started = self.overhead.start();
+            let next = self.pop_next();
+            self.overhead.stop(overhead::SELECTION, started);
+            let mut task = next?;
+
+            let started = self.overhead.start();
+            let routed = self.route(&mut task);
+            self.overhead.stop(overhead::DISPATCH, started);
+            if routed {
+                return Some(task);
+            }
+
+            let started = self.overhead.start();
+            if self.rejects(task.agent_id()) {
+                if let Some(usage) = self.runtime.get_mut(task.agent_id()) {
+                    usage.record_rejected();
+                }
+                let id = task.id();
+                let error = RuntimeError::ResourceExhausted(format!(
+                    "runtime cap of agent {} exceeded",
+                    task.agent_id()
+                ));
+                self.store_result(id, Err(TaskFailure::from(&error)));
+                self.dead_letters.push(
+                    task,
+                    DeadLetterReason::RuntimeCapExceeded,
+                    Some(error.to_string()),
+                    self.clock.wall_time(),
+                );
+                self.complete_task(id, false);
+                self.room.notify_waiters();
+                self.overhead.stop(overhead::BOOKKEEPING, started);
+                continue;
+            }
+            let reason = self.hold_reason(&task);
+            self.held.push_back(HeldTask {
+                task,
+                since: self.clock.instant(),
+                reason,
+            });
+            self.overhead.stop(overhead::BOOKKEEPING, started);
+        }
+    }
+
+    /// Pop the next queued task, breaking ties within the chosen priority
+    ///
+    /// Under [`PolicyType::Priority`] the highest effective priority is
+    /// served; otherwise the priority of the task at the head of the queue.
+    /// Under [`PolicyType::FairShare`] the share group tree picks the agent.
+    fn pop_next(&mut self) -> Option<Task> {
+        if let Some(engine) = &mut self.policy_engine {
+            return engine.next(&mut self.queue);
+        }
+        if self.policy.policy_type() == PolicyType::EarliestDeadlineFirst {
+            let (index, _) = self.queue.iter().enumerate().min_by_key(|(_, task)| {
+                let deadline = task.deadline();
+                (deadline.is_none(), deadline)
+            })?;
+            return self.queue.remove(index);
+        }
+        if self.policy.policy_type() == PolicyType::FairShare {
+            let queue = &self.queue;
+            let picked = self.fair_share.as_ref().and_then(|fair_share| {
+                fair_share.pick(|agent_id| queue.iter().any(|task| task.agent_id() == agent_id))
+            });
+            if let Some(agent_id) = picked {
+                let index = queue.iter().position(|task| task.agent_id() == &agent_id)?;
+                return self.queue.remove(index);
+            }
+        }
+        let by_priority = self.policy.policy_type() == PolicyType::Priority;
+        if self.tie_break == TieBreak::Fifo && !by_priority {
+            return self.queue.pop();
+        }
+        let aging = self.aging.filter(|_| by_priority);
+        let now = self.clock.instant();
+        let level = |task: &Task| match &aging {
+            Some(aging) => aging.effective_priority(
+                task.priority(),
+                now.saturating_duration_since(task.created_at()),
+            ),
+            None => task.priority(),
+        };
+        let priority = if by_priority {
+            self.queue.iter().map(level).max()?
+        } else {
+            self.queue.iter().next()?.priority()
+        };
+        let candidates = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| level(task) == priority);
+        let index = self.tie_break.pick(candidates, &mut self.lottery)?;
+        self.queue.remove(index)
+    }
+
+    /// Take the longest-waiting dispatchable task past the wait bound
+    fn take_overdue(&mut self) -> Option<Task> {
+        let bound = self.max_wait?;
+        let started = self.overhead.start();
+        let now = self.clock.instant();
+        let waited = |task: &Task| now.saturating_duration_since(task.created_at());
+        let overdue = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| waited(task) > bound)
+            .filter(|(_, task)| {
+                self.fits_worker(task)
+                    && self.has_provider_capacity(task)
+                    && self.within_budget(task)
+            })
+            .filter(|(_, task)| {
+                self.is_dispatchable(task.agent_id()) || self.sibling_for(task).is_some()
+            })
+            .max_by_key(|(_, task)| waited(task))
+            .map(|(index, _)| index);
+        self.overhead.stop(overhead::SELECTION, started);
+
+        let mut task = self.queue.remove(overdue?)?;
+        let started = self.overhead.start();
+        self.route(&mut task);
+        self.overhead.stop(overhead::DISPATCH, started);
+
+        let waited = waited(&task);
+        tracing::warn!(
+            "Task for agent {} waited {:?}, over the bound of {:?}",
+            task.agent_id(),
+            waited,
+            bound
+        );
+        self.events.push(SchedulerEvent::WaitBoundExceeded {
+            agent_id: *task.agent_id(),
+            priority: task.priority(),
+            waited,
+            bound,
+        });
+        Some(task)
+    }
+
+    /// Capture queued, held, parked, and grouped tasks, runtime accounting,
+    /// and recurring schedules
+    ///
+    /// Add tasks that are running elsewhere with [`SchedulerState::add_in_flight`].
+    pub fn state(&self) -> SchedulerState {
+        let now = Now::of(&*self.clock);
+        let record = |task: &Task| TaskRecord::at(task, now);
+        SchedulerState {
+            queued: self
+                .held
+                .iter()
+                .map(|held| &held.task)
+                .chain(self.queue.iter())
+                .chain(self.dependencies.waiting())
+                .chain(self.conversations.waiting())
+                .map(record)
+                .collect(),
+            in_flight: self.in_flight().map(record).collect(),
+            runtime: self
+                .runtime
+                .iter()
+                .map(|(agent_id, usage)| (*agent_id, usage.to_record(now)))
+                .collect(),
+            schedules: self.schedules.clone(),
+            fair_share: self.fair_share.clone(),
+            rate_limiter: self.rate_limiter.to_record(now),
+            parked: self
+                .parked
+                .iter()
+                .map(|(agent_id, tasks)| (*agent_id, tasks.iter().map(record).collect()))
+                .collect(),
+            groups: self
+                .gangs
+                .waiting_groups()
+                .map(|group| {
+                    (
+                        group.name().to_string(),
+                        group.tasks().iter().map(record).collect(),
+                    )
+                })
+                .collect(),
+            released: self.gangs.released_tasks().map(record).collect(),
+        }
+    }
+
+    /// Restore state captured by [`Scheduler::state`], typically after a crash
+    ///
+    /// In-flight tasks are queued ahead of previously queued ones, since
+    /// their execution was interrupted. Parked agents stay suspended, and
+    /// task groups are still dispatched together. A captured fair-share
+    /// scheduler and rate limiter replace the ones configured here, keeping
+    /// the usage counted against them. Restored tasks are held to the
+    /// payload limit; ones it rejects are dead-lettered.
+    pub fn warm_start(&mut self, state: SchedulerState) {
+        let now = Now::of(&*self.clock);
+        let tasks: Vec<Task> = state
+            .in_flight
+            .into_iter()
+            .chain(state.queued)
+            .filter_map(|record| self.restore_task(record, now))
+            .collect();
+        let restored: HashSet<TaskId> = tasks.iter().map(Task::id).collect();
+        for task in tasks {
+            let remaining: HashSet<TaskId> = task
+                .dependencies()
+                .iter()
+                .filter(|id| restored.contains(id) || self.is_unfinished(id))
+                .copied()
+                .collect();
+            if remaining.is_empty() {
+                self.queue.push(task);
+            } else {
+                self.dependencies.park(task, remaining);
+            }
+        }
+        for (agent_id, records) in state.parked {
+            let tasks: Vec<Task> = records
+                .into_iter()
+                .filter_map(|record| self.restore_task(record, now))
+                .collect();
+            self.parked.entry(agent_id).or_default().extend(tasks);
+        }
+        for (name, records) in state.groups {
+            let group = records
+                .into_iter()
+                .filter_map(|record| self.restore_task(record, now))
+                .fold(TaskGroup::new(name), TaskGroup::with_task);
+            self.gangs.push(group);
+        }
+        for record in state.released {
+            if let Some(task) = self.restore_task(record, now) {
+                self.gangs.restore_released(task);
+            }
+        }
+        for (agent_id, record) in state.runtime {
+            self.runtime
+                .insert(agent_id, RuntimeWindow::from_record(record, now));
+        }
+        for schedule in state.schedules {
+            self.add_schedule(schedule);
+        }
+        if let Some(fair_share) = state.fair_share {
+            self.fair_share = Some(fair_share);
+        }
+        if state.rate_limiter != RateLimiterRecord::default() {
+            self.rate_limiter = RateLimiter::from_record(state.rate_limiter, now);
+        }
+        if !self.queue.is_empty() || self.gangs.waiting() > 0 || self.gangs.released() > 0 {
+            self.wakeup.wake(WakeReason::Push);
+        }
+    }
+
+    /// Rebuild a checkpointed task, enforcing the payload limit on it
+    ///
+    /// A task whose payload the limit rejects is dead-lettered instead.
+    fn restore_task(&mut self, record: TaskRecord, now: Now) -> Option<Task> {
+        let mut task = record.into_task_at(now);
+        let Some(limit) = &self.payload_limit else {
+            return Some(task);
+        };
+        match task.limit_payload(limit) {
+            Ok(()) => Some(task),
+            Err(error) => {
+                tracing::warn!(
+                    "Dead-lettering restored task for agent {}: {}",
+                    task.agent_id(),
+                    error
+                );
+                let id = task.id();
+                self.dead_letters.push(
+                    task,
+                    DeadLetterReason::NotRetryable,
+                    Some(error.to_string()),
+                    self.clock.wall_time(),
+                );
+                self.store_result(id, Err(TaskFailure::from(&error)));
+                None
+            }
+        }
+    }
+
+    /// Checkpoint the scheduler, including tasks [`Scheduler::run`] is executing
+    pub fn snapshot(&self) -> SchedulerSnapshot {
+        let now = Now::of(&*self.clock);
+        SchedulerSnapshot {
+            policy: self.policy.clone(),
+            state: self.state(),
+            resource_quotas: self
+                .resource_quotas
+                .iter()
+                .map(|(agent_id, quota)| (*agent_id, *quota))
+                .collect(),
+            dead_letters: self
+                .dead_letters
+                .iter()
+                .map(|entry| DeadLetterRecord::at(entry, now))
+                .collect(),
+            dead_letter_capacity: self.dead_letters.capacity(),
+            caps: self
+                .caps
+                .iter()
+                .map(|(agent_id, cap)| (*agent_id, *cap))
+                .collect(),
+            providers: self
+                .providers
+                .iter()
+                .map(|(name, pool)| (name.clone(), pool.to_record(now)))
+                .collect(),
+            cost_budgets: self
+                .cost_budgets
+                .iter()
+                .map(|(agent_id, budget)| (*agent_id, *budget))
+                .collect(),
+            costs: self
+                .costs
+                .iter()
+                .map(|(agent_id, window)| (*agent_id, window.to_record(now)))
+                .collect(),
+            tripped: self.tripped.iter().copied().collect(),
+            health: self
+                .health
+                .iter()
+                .map(|(agent_id, status)| (*agent_id, *status))
+                .collect(),
+            not_ready: self.not_ready.iter().copied().collect(),
+            cordoned: self.cordoned.iter().copied().collect(),
+            deployments: self.deployments.values().cloned().collect(),
+            versions: self
+                .versions
+                .iter()
+                .map(|(agent_id, version)| (*agent_id, version.clone()))
+                .collect(),
+            tuning: self.tuning,
+            payload_limit: self.payload_limit,
+            result_limit: self.result_limit,
+            taken_at: now.wall,
+        }
+    }
+
+    /// Rebuild a scheduler from a checkpoint taken by [`Scheduler::snapshot`]
+    ///
+    /// Settings the snapshot does not capture, such as the clock, lifecycle,
+    /// custom policy engine, and event hooks, start from their defaults and
+    /// can be set afterwards.
+    pub fn restore_from(snapshot: SchedulerSnapshot) -> Self {
+        let mut scheduler = Self::new(snapshot.policy)
+            .with_dead_letter_capacity(snapshot.dead_letter_capacity)
+            .with_tuning(snapshot.tuning);
+        scheduler.payload_limit = snapshot.payload_limit;
+        scheduler.result_limit = snapshot.result_limit;
+        let now = Now::of(&*scheduler.clock);
+        for (agent_id, quota) in snapshot.resource_quotas {
+            scheduler.set_resource_quota(agent_id, quota);
+        }
+        // Caps installed by quotas were captured too, and win over them
+        scheduler.caps = snapshot.caps.into_iter().collect();
+        scheduler.providers = snapshot
+            .providers
+            .into_iter()
+            .map(|(name, record)| (name, ProviderPool::from_record(record, now)))
+            .collect();
+        scheduler.cost_budgets = snapshot.cost_budgets.into_iter().collect();
+        scheduler.costs = snapshot
+            .costs
+            .into_iter()
+            .map(|(agent_id, record)| (agent_id, CostWindow::from_record(record, now)))
+            .collect();
+        scheduler.tripped = snapshot.tripped.into_iter().collect();
+        scheduler.health = snapshot.health.into_iter().collect();
+        scheduler.not_ready = snapshot.not_ready.into_iter().collect();
+        scheduler.cordoned = snapshot.cordoned.into_iter().collect();
+        scheduler.deployments = snapshot
+            .deployments
+            .into_iter()
+            .map(|deployment| (*deployment.stable(), deployment))
+            .collect();
+        scheduler.versions = snapshot.versions.into_iter().collect();
+        for record in snapshot.dead_letters {
+            scheduler
+                .dead_letters
+                .restore(record.into_dead_letter_at(now));
+        }
+        scheduler.warm_start(snapshot.state);
+        scheduler
+    }
+
+    /// Stop accepting and dispatching tasks, ahead of shutting down
+    ///
+    /// Queued tasks stay queued, so [`Scheduler::state`] still captures them.
+    /// [`Scheduler::run`] stops dispatching and waits for in-flight tasks.
+    pub fn drain(&mut self) {
+        self.draining.cancel();
+        self.wakeup.wake(WakeReason::Capacity);
+    }
+
+    /// Check if the scheduler is draining
+    pub fn is_draining(&self) -> bool {
+        self.draining.is_cancelled()
+    }
+
+    /// Abort tasks [`Scheduler::run`] is still executing
+    ///
+    /// Aborted tasks stay listed by [`Scheduler::in_flight`].
+    pub fn abort_in_flight(&mut self) {
+        self.aborting.cancel();
+    }
+
+    /// Iterate over tasks waiting to be dispatched, including held and
+    /// parked ones and ones waiting for their dependencies, conversation, or
+    /// task group
+    pub fn queued(&self) -> impl Iterator<Item = &Task> {
+        self.held
+            .iter()
+            .map(|held| &held.task)
+            .chain(self.queue.iter())
+            .chain(self.dependencies.waiting())
+            .chain(self.conversations.waiting())
+            .chain(self.gangs.tasks())
+            .chain(self.parked.values().flatten())
+    }
+
+    /// Drop all queued work and recurring schedules, e.g. after another
+    /// instance took over as leader
+    #[cfg(feature = "cluster")]
+    pub(crate) fn relinquish(&mut self) {
+        self.held.clear();
+        self.queue.clear();
+        self.parked.values_mut().for_each(Vec::clear);
+        self.dependencies = DependencyGraph::default();
+        self.conversations = Conversations::default();
+        self.gangs = Gangs::default();
+        self.speculations = Speculations::default();
+        self.schedules.clear();
+        self.room.notify_waiters();
+    }
+
+    /// Get number of failed attempts resubmitted for a retry
+    pub fn retried(&self) -> u64 {
+        self.retried
+    }
+
+    /// Remember idempotency keys for `window` after their first submission
+    ///
+    /// Defaults to ten minutes. Keys are remembered even after their task
+    /// finished, so a redelivered event resolves to the earlier result while
+    /// it is cached.
+    pub fn with_idempotency_window(mut self, window: Duration) -> Self {
+        self.idempotency.set_window(window);
+        self
+    }
+
+    /// Get time idempotency keys are remembered
+    pub fn idempotency_window(&self) -> Duration {
+        self.idempotency.window()
+    }
+
+    /// Get number of idempotency keys remembered
+    pub fn idempotency_keys(&self) -> usize {
+        self.idempotency.len()
+    }
+
+    /// Get number of submissions dropped as duplicates of an earlier task
+    pub fn deduplicated(&self) -> u64 {
+        self.idempotency.deduplicated()
+    }
+
+    /// Get number of tasks waiting for their dependencies
+    pub fn waiting_on_dependencies(&self) -> usize {
+        self.dependencies.len()
+    }
+
+    /// Record that a task taken with [`Scheduler::next_task`] finished
+    ///
+    /// Completing it releases the tasks that waited only for it; failing it
+    /// for good dead-letters every task that depended on it, directly or
+    /// not. [`Scheduler::run`] records this itself.
+    pub fn complete_task(&mut self, id: TaskId, success: bool) {
+        self.continue_conversation(id);
+        if success {
+            for task in self.dependencies.complete(id) {
+                self.resubmit(task);
+            }
+            return;
+        }
+        for task in self.dependencies.fail(id) {
+            self.fail_dependent(task, id);
+        }
+    }
+
+    /// Find a dependency of a task that already failed for good
+    ///
+    /// Failures are known while their result is cached or their task is
+    /// dead-lettered.
+    fn failed_dependency(&self, task: &Task) -> Option<TaskId> {
+        task.dependencies().iter().copied().find(|id| {
+            matches!(self.results.get(id), Some(Err(_)))
+                || self.dead_letters.iter().any(|entry| entry.task.id() == *id)
+        })
+    }
+
+    /// Dead-letter a task whose dependency `failed`
+    fn fail_dependent(&mut self, task: Task, failed: TaskId) {
+        tracing::warn!(
+            "Dead-lettering {} for agent {}: dependency {} failed",
+            task.id(),
+            task.agent_id(),
+            failed
+        );
+        let error = RuntimeError::ExecutionFailed(format!("dependency {} failed", failed));
+        self.store_result(task.id(), Err(TaskFailure::from(&error)));
+        self.dead_letters.push(
+            task,
+            DeadLetterReason::DependencyFailed,
+            Some(format!("dependency {} failed", failed)),
+            self.clock.wall_time(),
+        );
+    }
+
+    /// Queue the next task of a finished task's conversation
+    fn continue_conversation(&mut self, id: TaskId) {
+        if let Some(next) = self.conversations.finish(id) {
+            self.queue.push(next);
+            self.wakeup.wake(WakeReason::Push);
+        }
+    }
+
+    /// Get the task a conversation is running or has queued
+    pub fn conversation_head(&self, conversation: &str) -> Option<TaskId> {
+        self.conversations.active(conversation)
+    }
+
+    /// Get number of tasks waiting for earlier tasks of their conversation
+    pub fn waiting_on_conversations(&self) -> usize {
+        self.conversations.len()
+    }
+
+    /// Check if a task is queued, held, waiting, or running
+    fn is_unfinished(&self, id: &TaskId) -> bool {
+        self.dependencies.is_waiting(id)
+            || self.dependencies.is_dispatched(id)
+            || self.queued().any(|task| task.id() == *id)
+            || self.in_flight().any(|task| task.id() == *id)
+    }
+
+    /// Iterate over tasks [`Scheduler::run`] is executing
+    pub fn in_flight(&self) -> impl Iterator<Item = &Task> {
+        self.in_flight.values().map(|flight| &flight.task)
+    }
+
+    /// Get number of tasks [`Scheduler::run`] is executing
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Watch the number of tasks [`Scheduler::run`] is executing
+    pub fn watch_in_flight(&self) -> watch::Receiver<usize> {
+        self.flights.subscribe()
+    }
+
+    pub(crate) fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Queue tasks for agents that received mail
+    fn submit_mail_tasks(&mut self) {
+        let Some(mail) = &self.mail else {
+            return;
+        };
+        let priority = mail.priority;
+        let arrived = std::mem::take(&mut *mail.arrived.lock().unwrap_or_else(|e| e.into_inner()));
+        for agent_id in arrived {
+            if let Err(error) = self.submit(Task::new(agent_id, priority)) {
+                tracing::warn!(
+                    "Failed to queue mail task for agent {}: {}",
+                    agent_id,
+                    error
+                );
+            }
+        }
+    }
+
+    /// Track a task as running, returning its flight number
+    pub(crate) fn begin_flight(&mut self, task: &mut Task) -> u64 {
+        if self.max_in_flight.is_some() {
+            task.set_preemption(Some(PreemptionHandle::new()));
+        }
+        let flight = self.next_flight;
+        self.next_flight += 1;
+        self.in_flight.insert(
+            flight,
+            Flight {
+                task: task.clone(),
+                started: self.clock.instant(),
+                demoted: Arc::new(AtomicBool::new(false)),
+            },
+        );
+        self.flights.send_replace(self.in_flight.len());
+        flight
+    }
+
+    /// Get the flag raised once a running task's CPU budget demotes it
+    fn demotion(&self, flight: u64) -> Arc<AtomicBool> {
+        self.in_flight
+            .get(&flight)
+            .map_or_else(Default::default, |flight| flight.demoted.clone())
+    }
+
+    /// Signal the lowest-priority running task if a waiting task outranks it
+    ///
+    /// Only applies while [`Scheduler::run`] is at its preemption limit, and
+    /// to tasks that ran for at least the time slice. Returns when the next
+    /// task becomes eligible, if one is not yet.
+    fn preempt_for_waiting(&mut self) -> Option<Instant> {
+        let limit = self.max_in_flight?;
+        let yielding = self.in_flight.values().any(|flight| {
+            flight
+                .task
+                .preemption()
+                .is_some_and(PreemptionHandle::is_preempted)
+        });
+        if self.in_flight.len() < limit || yielding {
+            return None;
+        }
+        let waiting = self.queue.iter().map(Task::priority).max()?;
+        let (_, victim) = self
+            .in_flight
+            .iter()
+            .filter(|(_, flight)| flight.priority() < waiting)
+            .min_by_key(|(id, flight)| (flight.priority(), flight.started, **id))?;
+        let eligible_at = victim.started + self.tuning.time_slice;
+        if eligible_at > self.clock.instant() {
+            return Some(eligible_at);
+        }
+        if let Some(handle) = victim.task.preemption() {
+            tracing::debug!(
+                "Preempting task for agent {} for a priority {} task",
+                victim.task.agent_id(),
+                waiting
+            );
+            handle.preempt();
+            self.preemptions += 1;
+        }
+        None
+    }
+
+    fn end_flight(&mut self, flight: u64) {
+        self.in_flight.remove(&flight);
+        self.flights.send_replace(self.in_flight.len());
+    }
+
+    /// Dispatch tasks to `execute` on the tokio runtime until `shutdown` is cancelled
+    ///
+    /// The scheduler stays shared so tasks can be submitted while it runs.
+    /// Each task runs under its CPU budget and is cancelled once it exceeds its
+    /// maximum run time; its run time and outcome are recorded, and failed
+    /// tasks with retries left are resubmitted. Recurring schedules fire as
+    /// they come due. With a coalesced [`WakeupStrategy`], a loop woken by a
+    /// submission waits out the window before taking a batch. On
+    /// shutdown or [`Scheduler::drain`], dispatch stops and the call returns
+    /// once in-flight tasks finish or [`Scheduler::abort_in_flight`] is called.
+    pub async fn run<F, Fut>(
+        scheduler: Arc<Mutex<Scheduler>>,
+        execute: F,
+        shutdown: CancellationToken,
+    ) where
+        F: Fn(&Task) -> Fut,
+        Fut: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+    {
+        Self::run_with_results(
+            scheduler,
+            move |task: &Task| execute(task).map_ok(|()| Vec::new()),
+            shutdown,
+        )
+        .await
+    }
+
+    /// Dispatch tasks like [`Scheduler::run`] to an `execute` returning
+    /// each task's output
+    ///
+    /// Outputs are stored under the result limit and resolve the tasks'
+    /// [`TaskHandle`]s; final failures resolve them with the last error.
+    pub async fn run_with_results<F, Fut>(
+        scheduler: Arc<Mutex<Scheduler>>,
+        execute: F,
+        shutdown: CancellationToken,
+    ) where
+        F: Fn(&Task) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, RuntimeError>> + Send + 'static,
+    {
+        let (wakeup, clock, draining, aborting) = {
+            let scheduler = scheduler.lock().await;
+            (
+                scheduler.wakeup(),
+                scheduler.clock.clone(),
+                scheduler.draining.clone(),
+                scheduler.aborting.clone(),
+            )
+        };
+        #[cfg(feature = "tracing")]
+        let policy = scheduler.lock().await.policy.policy_type();
+        let mut running = JoinSet::new();
+
+        while !shutdown.is_cancelled() && !draining.is_cancelled() {
+            let (batch, wake_at, coalesce) = {
+                let mut scheduler = scheduler.lock().await;
+                scheduler.fire_due(clock.wall_time());
+                let batch: Vec<_> = scheduler
+                    .next_batch()
+                    .into_iter()
+                    .map(|mut task| {
+                        let flight = scheduler.begin_flight(&mut task);
+                        let demoted = scheduler.demotion(flight);
+                        (flight, scheduler.speculation_token(&task), demoted, task)
+                    })
+                    .collect();
+                let preempt_at = scheduler.preempt_for_waiting();
+                let wake_at = scheduler
+                    .next_wakeup_at()
+                    .into_iter()
+                    .chain(preempt_at)
+                    .min();
+                (batch, wake_at, scheduler.tuning.wakeup)
+            };
+            if !batch.is_empty() {
+                for (flight, lost, demoted, mut task) in batch {
+                    task.record_attempt();
+                    let budget = task.cpu_budget().unwrap_or_else(CpuBudget::unlimited);
+                    let mut execution = budget.enforce(execute(&task)).signal_demotion(demoted);
+                    let clock = clock.clone();
+                    #[cfg(feature = "tracing")]
+                    let span = tracing::info_span!(
+                        "task.run",
+                        agent_id = %task.agent_id(),
+                        priority = task.priority(),
+                        attempt = task.attempts(),
+                        policy = ?policy,
+                    );
+                    let run = async move {
+                        let attempt = AssertUnwindSafe(&mut execution).catch_unwind();
+                        // Losing replicas of a speculative task are cancelled
+                        let attempt = async {
+                            match &lost {
+                                Some(lost) => tokio::select! {
+                                    outcome = attempt => outcome,
+                                    _ = lost.cancelled() => {
+                                        Ok(Err(SchedulerError::SpeculationLost.into()))
+                                    }
+                                },
+                                None => attempt.await,
+                            }
+                        };
+                        let outcome = match task.max_runtime() {
+                            Some(limit) => tokio::select! {
+                                outcome = attempt => Some(outcome),
+                                _ = clock.sleep(limit) => None,
+                            },
+                            None => Some(attempt.await),
+                        };
+                        let result = match outcome {
+                            Some(Ok(result)) => result,
+                            Some(Err(panic)) => Err(RuntimeError::ExecutionFailed(format!(
+                                "task panicked: {}",
+                                crate::error::panic_message(panic)
+                            ))),
+                            None => Err(SchedulerError::MaxRuntimeExceeded {
+                                max_runtime: task.max_runtime().unwrap_or_default(),
+                            }
+                            .into()),
+                        };
+                        (flight, task, result, execution.used())
+                    };
+                    #[cfg(feature = "tracing")]
+                    let run = tracing::Instrument::instrument(run, span);
+                    running.spawn(run);
+                }
+                continue;
+            }
+
+            tokio::select! {
+                biased;
+                _ = shutdown.cancelled() => break,
+                _ = draining.cancelled() => break,
+                Some(finished) = running.join_next(), if !running.is_empty() => {
+                    Self::finish(&scheduler, finished).await;
+                }
+                reason = wakeup.wait(wake_at) => {
+                    // Let a burst of submissions gather into one batch
+                    if let (WakeReason::Push, WakeupStrategy::Coalesced(window)) = (reason, coalesce) {
+                        tokio::select! {
+                            _ = shutdown.cancelled() => break,
+                            _ = draining.cancelled() => break,
+                            _ = clock.sleep(window) => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = aborting.cancelled() => {
+                    running.abort_all();
+                    break;
+                }
+                finished = running.join_next() => match finished {
+                    Some(finished) => Self::finish(&scheduler, finished).await,
+                    None => break,
+                },
+            }
+        }
+        // Tasks that finished before the abort took effect are still recorded
+        while let Some(finished) = running.join_next().await {
+            Self::finish(&scheduler, finished).await;
+        }
+    }
+
+    /// Record a task dispatched by [`Scheduler::run`], resubmitting it if it may retry
+    async fn finish(scheduler: &Mutex<Scheduler>, finished: Result<FinishedAttempt, JoinError>) {
+        let Ok((flight, mut task, result, used)) = finished else {
+            return;
+        };
+        let mut scheduler = scheduler.lock().await;
+        let result = result.and_then(|output| scheduler.accept_result(&mut task, output));
+        scheduler.settle(flight, task, result, used);
+    }
+
+    /// Record how a running task ended, resubmitting it if it may retry
+    pub(crate) fn settle(
+        &mut self,
+        flight: u64,
+        mut task: Task,
+        result: Result<(), RuntimeError>,
+        used: Duration,
+    ) {
+        self.end_flight(flight);
+        self.record_runtime(*task.agent_id(), used);
+        // A task demoted by its CPU budget is requeued or retried at the lower priority
+        if let Some(budget) = task.cpu_budget() {
+            task.set_priority(budget.priority_after(task.priority(), used));
+        }
+        if let Err(RuntimeError::Scheduler(SchedulerError::Preempted)) = result {
+            self.release(task.agent_id());
+            task.forget_attempt();
+            if let Some(remaining) = task.preemption().and_then(PreemptionHandle::take_remaining) {
+                task = task.with_payload(remaining);
+            }
+            task.set_preemption(None);
+            tracing::debug!("Requeueing preempted task for agent {}", task.agent_id());
+            self.resubmit(task);
+            return;
+        }
+        if self.speculations.finish(task.id(), &result) == ReplicaOutcome::Discard {
+            self.release(task.agent_id());
+            return;
+        }
+        self.record_execution(&task, used, result.is_ok());
+        self.record_outcome(task.agent_id(), result.is_ok());
+        if result.is_ok() {
+            let output = task.result().unwrap_or_default().to_vec();
+            self.store_result(task.id(), Ok(output));
+            self.complete_task(task.id(), true);
+        }
+        if let Err(error) = result {
+            let class = self.classifier.classify(&error);
+            let overran = matches!(
+                error,
+                RuntimeError::Scheduler(SchedulerError::MaxRuntimeExceeded { .. })
+            );
+            if overran
+                && task.overrun_action() == OverrunAction::Reschedule
+                && task.can_meet_deadline_at(Duration::ZERO, self.clock.instant())
+            {
+                tracing::warn!("Rescheduling task for agent {}: {}", task.agent_id(), error);
+                task.forget_attempt();
+                self.resubmit(task);
+            } else if task.should_retry_at(&class, Duration::ZERO, self.clock.instant()) {
+                self.retried += 1;
+                self.resubmit(task);
+            } else {
+                tracing::warn!("Task for agent {} failed: {}", task.agent_id(), error);
+                let reason = DeadLetterReason::for_failure(&error, &class);
+                let id = task.id();
+                self.dead_letters.push(
+                    task,
+                    reason,
+                    Some(error.to_string()),
+                    self.clock.wall_time(),
+                );
+                self.store_result(id, Err(TaskFailure::from(&error)));
+                self.complete_task(id, false);
+            }
+        }
+    }
+
+    /// Queue a running task again without counting the attempt, e.g. after
+    /// the node running it was lost
+    #[cfg(feature = "cluster")]
+    pub(crate) fn reassign(&mut self, flight: u64, mut task: Task) {
+        self.end_flight(flight);
+        self.release(task.agent_id());
+        task.forget_attempt();
+        self.resubmit(task);
+    }
+
+    /// Queue a task again for a retry or after its dependencies completed,
+    /// dead-lettering it if that fails
+    fn resubmit(&mut self, task: Task) {
+        if let Err(error) = self.submit(task.clone()) {
+            tracing::warn!("Could not resubmit task for retry: {}", error);
+            let id = task.id();
+            self.dead_letters.push(
+                task,
+                DeadLetterReason::Undeliverable,
+                Some(error.to_string()),
+                self.clock.wall_time(),
+            );
+            self.store_result(id, Err(TaskFailure::from(&error)));
+            self.complete_task(id, false);
+        }
+    }
+
+    /// Take up to one batch of dispatchable tasks, sized by the tuning profile
+    ///
+    /// With preemption, the batch also stops at the limit of running tasks.
+    pub fn next_batch(&mut self) -> Vec<Task> {
+        let size = self.max_in_flight.map_or(self.tuning.batch_size, |limit| {
+            limit
+                .saturating_sub(self.in_flight.len())
+                .min(self.tuning.batch_size)
+        });
+        let mut batch = Vec::with_capacity(size);
+        while batch.len() < size {
+            match self.next_task() {
+                Some(task) => batch.push(task),
+                None => break,
+            }
+        }
+        batch
+    }
+
+    /// Get number of tasks held because their agent or provider cannot
+    /// take them
+    pub fn held_count(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Get total time tasks have spent held due to agent health or readiness
+    pub fn held_due_to_health(&self) -> Duration {
+        self.held_for(HoldReason::Health)
+    }
+
+    /// Get total time tasks have spent held for a reason
+    pub fn held_for(&self, reason: HoldReason) -> Duration {
+        let now = self.clock.instant();
+        self.held_time.get(&reason).copied().unwrap_or_default()
+            + self
+                .held
+                .iter()
+                .filter(|held| held.reason == reason)
+                .map(|held| now.saturating_duration_since(held.since))
+                .sum::<Duration>()
+    }
+
+    /// Get histogram of time tasks waited before dispatch
+    #[cfg(feature = "metrics")]
+    pub fn queue_wait_histogram(&self) -> &LatencyHistogram {
+        &self.queue_wait
+    }
+
+    /// Get histogram of task run times recorded with [`Scheduler::record_runtime`]
+    #[cfg(feature = "metrics")]
+    pub fn run_time_histogram(&self) -> &LatencyHistogram {
+        &self.run_time
+    }
+
+    /// Record scheduler metrics into a collector
+    #[cfg(feature = "metrics")]
+    pub fn collect_metrics(&self, collector: &mut Collector) {
+        collector.record(Metric::new(
+            "scheduler_held_due_to_health_seconds",
+            MetricType::Counter,
+            self.held_due_to_health().as_secs_f64(),
+        ));
+        for reason in HoldReason::ALL {
+            collector.record(
+                Metric::new(
+                    "scheduler_held_seconds",
+                    MetricType::Counter,
+                    self.held_for(reason).as_secs_f64(),
+                )
+                .with_label("reason", reason.as_str()),
+            );
+        }
+        collector.record(Metric::new(
+            "scheduler_held_tasks",
+            MetricType::Gauge,
+            self.held_count() as f64,
+        ));
+        for (reason, count) in self.wakeup.counts() {
+            collector.record(
+                Metric::new("scheduler_wakeups_total", MetricType::Counter, count as f64)
+                    .with_label("reason", reason.as_str()),
+            );
+        }
+        collector.record(Metric::new(
+            "scheduler_queue_depth",
+            MetricType::Gauge,
+            self.queued().count() as f64,
+        ));
+        collector.record(Metric::new(
+            "scheduler_in_flight_tasks",
+            MetricType::Gauge,
+            self.in_flight_count() as f64,
+        ));
+        collector.record(Metric::new(
+            "scheduler_dead_letters",
+            MetricType::Gauge,
+            self.dead_letters.len() as f64,
+        ));
+        collector.record(Metric::new(
+            "scheduler_retries_total",
+            MetricType::Counter,
+            self.retried as f64,
+        ));
+        collector.record(Metric::new(
+            "scheduler_deduplicated_total",
+            MetricType::Counter,
+            self.deduplicated() as f64,
+        ));
+        let speculation = self.speculation_stats();
+        for (name, value) in [
+            ("scheduler_speculative_tasks_total", speculation.launched),
+            ("scheduler_speculative_replicas_total", speculation.replicas),
+            (
+                "scheduler_speculative_cancelled_total",
+                speculation.cancelled,
+            ),
+            (
+                "scheduler_speculative_discarded_total",
+                speculation.discarded,
+            ),
+        ] {
+            collector.record(Metric::new(name, MetricType::Counter, value as f64));
+        }
+        self.queue_wait.collect("scheduler_queue_wait", collector);
+        self.run_time.collect("scheduler_run_time", collector);
+    }
 }
@@ -0,0 +1,42 @@
+use super::{OverflowPolicy, TaskId};
+use agentropic_core::AgentId;
+use std::time::Duration;
+
+/// Notable scheduler occurrence, collected with `Scheduler::take_events`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedulerEvent {
+    /// A task waited longer than the configured bound and was promoted
+    WaitBoundExceeded {
+        /// Agent the task was queued for
+        agent_id: AgentId,
+        /// Task priority
+        priority: u32,
+        /// Time the task spent queued
+        waited: Duration,
+        /// Configured maximum wait
+        bound: Duration,
+    },
+    /// An agent spent its cost budget and its non-critical tasks are rejected
+    BudgetTripped {
+        /// Agent over budget
+        agent_id: AgentId,
+        /// Cost spent in the current window
+        spent: u64,
+        /// Budget limit
+        limit: u64,
+    },
+    /// An agent's budget breaker closed after its window reset or limit was raised
+    BudgetReset {
+        /// Agent accepting tasks again
+        agent_id: AgentId,
+    },
+    /// A task was dropped because the queue was full
+    TaskDropped {
+        /// Agent the task was queued for
+        agent_id: AgentId,
+        /// Dropped task
+        task: TaskId,
+        /// Overflow policy that dropped it
+        overflow: OverflowPolicy,
+    },
+}
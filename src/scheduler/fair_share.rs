@@ -1,12 +1,52 @@
+//! Fair share scheduling
+//!
+//! A [`ShareGroup`] tree divides scheduling slots hierarchically, such as
+//! tenant, then team, then agent. Each node is entitled to its weight's
+//! fraction of its parent's slots; picking walks down the tree to the node
+//! furthest below its entitlement among those with work waiting.
+
+use super::policy::PolicyEngine;
+use super::{Task, TaskQueue};
+use agentropic_core::AgentId;
+use serde::{Deserialize, Serialize};
+
 /// Fair share scheduler
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FairShareScheduler {
     shares: f64,
+    groups: Option<ShareGroup>,
+    charges_runtime: bool,
 }
 
 impl FairShareScheduler {
     /// Create a new fair share scheduler
     pub fn new(shares: f64) -> Self {
-        Self { shares }
+        Self {
+            shares,
+            groups: None,
+            charges_runtime: false,
+        }
+    }
+
+    /// Divide slots according to a tree of share groups
+    pub fn with_groups(mut self, root: ShareGroup) -> Self {
+        self.groups = Some(root);
+        self
+    }
+
+    /// Charge agents a slot per millisecond of execution instead of per dispatch
+    ///
+    /// The [`super::Scheduler`] then charges each execution when it settles,
+    /// so agents running long tasks get fewer dispatches than agents running
+    /// short ones.
+    pub fn with_runtime_charging(mut self) -> Self {
+        self.charges_runtime = true;
+        self
+    }
+
+    /// Check if agents are charged by execution time
+    pub fn charges_runtime(&self) -> bool {
+        self.charges_runtime
     }
 
     /// Get shares
@@ -18,6 +58,44 @@ impl FairShareScheduler {
     pub fn set_shares(&mut self, shares: f64) {
         self.shares = shares;
     }
+
+    /// Get share group tree
+    pub fn groups(&self) -> Option<&ShareGroup> {
+        self.groups.as_ref()
+    }
+
+    /// Get mutable share group tree
+    pub fn groups_mut(&mut self) -> Option<&mut ShareGroup> {
+        self.groups.as_mut()
+    }
+
+    /// Pick the agent to serve next among those with work waiting
+    ///
+    /// Returns `None` when no ready agent is in the share group tree.
+    pub fn pick(&self, mut ready: impl FnMut(&AgentId) -> bool) -> Option<AgentId> {
+        self.groups.as_ref()?.pick(&mut ready)
+    }
+
+    /// Count slots consumed by an agent
+    pub fn record(&mut self, agent_id: &AgentId, slots: u64) {
+        if let Some(groups) = &mut self.groups {
+            groups.charge(agent_id, slots);
+        }
+    }
+}
+
+/// Oldest task of the agent furthest below its entitlement, charging it
+/// one slot; oldest first for agents outside the share group tree
+impl PolicyEngine for FairShareScheduler {
+    fn next(&mut self, queue: &mut TaskQueue) -> Option<Task> {
+        let picked = self.pick(|agent_id| queue.iter().any(|task| task.agent_id() == agent_id));
+        let index = picked
+            .and_then(|agent_id| queue.iter().position(|task| task.agent_id() == &agent_id))
+            .unwrap_or(0);
+        let task = queue.remove(index)?;
+        self.record(task.agent_id(), 1);
+        Some(task)
+    }
 }
 
 impl Default for FairShareScheduler {
@@ -25,3 +103,234 @@ impl Default for FairShareScheduler {
         Self::new(1.0)
     }
 }
+
+/// Node of a share group tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum ShareNode {
+    Group(ShareGroup),
+    Agent {
+        agent_id: AgentId,
+        weight: f64,
+        consumed: u64,
+    },
+}
+
+impl ShareNode {
+    fn weight(&self) -> f64 {
+        match self {
+            ShareNode::Group(group) => group.weight,
+            ShareNode::Agent { weight, .. } => *weight,
+        }
+    }
+
+    fn consumed(&self) -> u64 {
+        match self {
+            ShareNode::Group(group) => group.consumed,
+            ShareNode::Agent { consumed, .. } => *consumed,
+        }
+    }
+
+    fn pick(&self, ready: &mut dyn FnMut(&AgentId) -> bool) -> Option<AgentId> {
+        match self {
+            ShareNode::Group(group) => group.pick(ready),
+            ShareNode::Agent { agent_id, .. } => ready(agent_id).then_some(*agent_id),
+        }
+    }
+}
+
+/// Slots consumed by one group or agent
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareUsage {
+    /// Group names from the root, ending with the agent ID for agents
+    pub path: Vec<String>,
+    /// Fraction of all slots the node is entitled to
+    pub entitled: f64,
+    /// Slots consumed
+    pub consumed: u64,
+    /// Fraction of all consumed slots
+    pub consumed_fraction: f64,
+}
+
+/// Weighted group of agents and nested groups
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareGroup {
+    name: String,
+    weight: f64,
+    consumed: u64,
+    children: Vec<ShareNode>,
+}
+
+impl ShareGroup {
+    /// Create an empty group with a weight relative to its siblings
+    pub fn new(name: impl Into<String>, weight: f64) -> Self {
+        Self {
+            name: name.into(),
+            weight,
+            consumed: 0,
+            children: Vec::new(),
+        }
+    }
+
+    /// Add a nested group
+    pub fn with_group(mut self, group: ShareGroup) -> Self {
+        self.children.push(ShareNode::Group(group));
+        self
+    }
+
+    /// Add an agent with a weight relative to its siblings
+    pub fn with_agent(mut self, agent_id: AgentId, weight: f64) -> Self {
+        self.children.push(ShareNode::Agent {
+            agent_id,
+            weight,
+            consumed: 0,
+        });
+        self
+    }
+
+    /// Get group name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get weight
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Get slots consumed by agents in this group
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Get nested groups
+    pub fn groups(&self) -> impl Iterator<Item = &ShareGroup> {
+        self.children.iter().filter_map(|child| match child {
+            ShareNode::Group(group) => Some(group),
+            ShareNode::Agent { .. } => None,
+        })
+    }
+
+    /// Find a nested group by name, at any depth
+    pub fn group(&self, name: &str) -> Option<&ShareGroup> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.groups().find_map(|group| group.group(name))
+    }
+
+    /// Check if an agent is in this group or a nested one
+    pub fn contains(&self, agent_id: &AgentId) -> bool {
+        self.entitlement(agent_id).is_some()
+    }
+
+    /// Get the fraction of this group's slots an agent is entitled to
+    pub fn entitlement(&self, agent_id: &AgentId) -> Option<f64> {
+        let total = self.total_weight();
+        self.children.iter().find_map(|child| {
+            let fraction = match child {
+                ShareNode::Group(group) => group.entitlement(agent_id)?,
+                ShareNode::Agent { agent_id: id, .. } if id == agent_id => 1.0,
+                ShareNode::Agent { .. } => return None,
+            };
+            Some(fraction * child.weight() / total)
+        })
+    }
+
+    /// Count slots consumed by an agent against it and every enclosing group
+    ///
+    /// Returns `false` if the agent is not in this group.
+    pub fn charge(&mut self, agent_id: &AgentId, slots: u64) -> bool {
+        let charged = self.children.iter_mut().any(|child| match child {
+            ShareNode::Group(group) => group.charge(agent_id, slots),
+            ShareNode::Agent {
+                agent_id: id,
+                consumed,
+                ..
+            } if id == agent_id => {
+                *consumed += slots;
+                true
+            }
+            ShareNode::Agent { .. } => false,
+        });
+        if charged {
+            self.consumed += slots;
+        }
+        charged
+    }
+
+    /// Forget consumed slots, starting a new accounting period
+    pub fn reset_usage(&mut self) {
+        self.consumed = 0;
+        for child in &mut self.children {
+            match child {
+                ShareNode::Group(group) => group.reset_usage(),
+                ShareNode::Agent { consumed, .. } => *consumed = 0,
+            }
+        }
+    }
+
+    /// Get consumption and entitlement of every nested group and agent
+    pub fn usage(&self) -> Vec<ShareUsage> {
+        let mut usage = Vec::new();
+        self.collect_usage(&mut Vec::new(), 1.0, self.consumed, &mut usage);
+        usage
+    }
+
+    fn collect_usage(
+        &self,
+        path: &mut Vec<String>,
+        entitled: f64,
+        total: u64,
+        usage: &mut Vec<ShareUsage>,
+    ) {
+        let fraction = |consumed: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                consumed as f64 / total as f64
+            }
+        };
+        path.push(self.name.clone());
+        usage.push(ShareUsage {
+            path: path.clone(),
+            entitled,
+            consumed: self.consumed,
+            consumed_fraction: fraction(self.consumed),
+        });
+        let weights = self.total_weight();
+        for child in &self.children {
+            let entitled = entitled * child.weight() / weights;
+            match child {
+                ShareNode::Group(group) => group.collect_usage(path, entitled, total, usage),
+                ShareNode::Agent {
+                    agent_id, consumed, ..
+                } => {
+                    let mut path = path.clone();
+                    path.push(agent_id.to_string());
+                    usage.push(ShareUsage {
+                        path,
+                        entitled,
+                        consumed: *consumed,
+                        consumed_fraction: fraction(*consumed),
+                    });
+                }
+            }
+        }
+        path.pop();
+    }
+
+    fn total_weight(&self) -> f64 {
+        self.children.iter().map(ShareNode::weight).sum()
+    }
+
+    /// Descend to the child furthest below its share that has a ready agent
+    fn pick(&self, ready: &mut dyn FnMut(&AgentId) -> bool) -> Option<AgentId> {
+        let mut order: Vec<_> = self.children.iter().collect();
+        order.sort_by(|a, b| {
+            let a = a.consumed() as f64 / a.weight();
+            let b = b.consumed() as f64 / b.weight();
+            a.total_cmp(&b)
+        });
+        order.into_iter().find_map(|child| child.pick(ready))
+    }
+}
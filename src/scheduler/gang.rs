@@ -0,0 +1,132 @@
+//! Gang scheduling of cooperating agents
+//!
+//! Some workflows, such as a debate or a map-reduce team, only make
+//! progress when all their agents run at once. A [`TaskGroup`] submitted
+//! with [`super::Scheduler::submit_group`] is dispatched as a unit: only
+//! when every member's agent is dispatchable and, with a limit on running
+//! tasks, there are slots for all of them. Members never hold slots while
+//! waiting for the rest, so two partially started groups cannot deadlock
+//! each other.
+
+use super::{Task, TaskId};
+use std::collections::VecDeque;
+
+/// Tasks that must be dispatched together
+#[derive(Debug, Clone)]
+pub struct TaskGroup {
+    name: String,
+    tasks: Vec<Task>,
+}
+
+impl TaskGroup {
+    /// Create an empty group
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Add a member task
+    pub fn with_task(mut self, task: Task) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Get name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get member tasks
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    /// Get IDs of member tasks
+    pub fn task_ids(&self) -> Vec<TaskId> {
+        self.tasks.iter().map(Task::id).collect()
+    }
+
+    /// Get number of members
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Check if the group has no members
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub(crate) fn tasks_mut(&mut self) -> &mut [Task] {
+        &mut self.tasks
+    }
+}
+
+/// Groups waiting for slots, and members of released groups
+#[derive(Debug, Default)]
+pub(crate) struct Gangs {
+    waiting: VecDeque<TaskGroup>,
+    /// Members of a released group, dispatched ahead of other tasks
+    released: VecDeque<Task>,
+}
+
+impl Gangs {
+    pub(crate) fn push(&mut self, group: TaskGroup) {
+        self.waiting.push_back(group);
+    }
+
+    /// Get the group next in line
+    pub(crate) fn head(&self) -> Option<&TaskGroup> {
+        self.waiting.front()
+    }
+
+    /// Release the group next in line for dispatch
+    pub(crate) fn release_head(&mut self) {
+        if let Some(group) = self.waiting.pop_front() {
+            tracing::debug!(
+                "Dispatching task group {} with {} members",
+                group.name,
+                group.len()
+            );
+            self.released.extend(group.tasks);
+        }
+    }
+
+    /// Take the next member of a released group
+    pub(crate) fn take_released(&mut self) -> Option<Task> {
+        self.released.pop_front()
+    }
+
+    /// Get number of released members not dispatched yet
+    pub(crate) fn released(&self) -> usize {
+        self.released.len()
+    }
+
+    /// Iterate over members of groups not dispatched yet
+    pub(crate) fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.released
+            .iter()
+            .chain(self.waiting.iter().flat_map(|group| group.tasks.iter()))
+    }
+
+    /// Iterate over groups waiting for slots
+    pub(crate) fn waiting_groups(&self) -> impl Iterator<Item = &TaskGroup> {
+        self.waiting.iter()
+    }
+
+    /// Iterate over released members not dispatched yet
+    pub(crate) fn released_tasks(&self) -> impl Iterator<Item = &Task> {
+        self.released.iter()
+    }
+
+    /// Put back a member of a released group, e.g. on warm start
+    pub(crate) fn restore_released(&mut self, task: Task) {
+        self.released.push_back(task);
+    }
+
+    /// Get number of groups waiting for slots
+    pub(crate) fn waiting(&self) -> usize {
+        self.waiting.len()
+    }
+}
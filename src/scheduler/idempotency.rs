@@ -0,0 +1,89 @@
+//! Deduplication of task submissions by idempotency key
+//!
+//! Upstream systems such as webhooks may deliver the same event more than
+//! once. A task submitted with [`super::Task::with_idempotency_key`] is
+//! remembered under its key for a time window; duplicates submitted within
+//! it are dropped in favor of the first task.
+
+use super::TaskId;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Default time keys are remembered
+pub(crate) const DEFAULT_WINDOW: Duration = Duration::from_secs(600);
+
+/// Recently seen keys, oldest first
+#[derive(Debug)]
+pub(crate) struct IdempotencyKeys {
+    keys: HashMap<String, (TaskId, Instant)>,
+    order: VecDeque<(String, Instant)>,
+    window: Duration,
+    deduplicated: u64,
+}
+
+impl IdempotencyKeys {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            keys: HashMap::new(),
+            order: VecDeque::new(),
+            window,
+            deduplicated: 0,
+        }
+    }
+
+    pub(crate) fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    pub(crate) fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Get the task first submitted with a key, other than `id` itself
+    ///
+    /// Retries resubmit a task under its own key, so they are not
+    /// duplicates.
+    pub(crate) fn duplicate_of(&mut self, key: &str, id: TaskId, now: Instant) -> Option<TaskId> {
+        self.prune(now);
+        let (first, _) = self.keys.get(key).filter(|(first, _)| *first != id)?;
+        self.deduplicated += 1;
+        Some(*first)
+    }
+
+    /// Remember the task submitted with a key
+    pub(crate) fn remember(&mut self, key: &str, id: TaskId, now: Instant) {
+        if self.keys.contains_key(key) {
+            return;
+        }
+        self.keys.insert(key.to_string(), (id, now));
+        self.order.push_back((key.to_string(), now));
+    }
+
+    /// Get number of keys remembered
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub(crate) fn deduplicated(&self) -> u64 {
+        self.deduplicated
+    }
+
+    /// Forget keys older than the window
+    fn prune(&mut self, now: Instant) {
+        while let Some((key, seen)) = self.order.front() {
+            if now.saturating_duration_since(*seen) < self.window {
+                break;
+            }
+            if self.keys.get(key).is_some_and(|(_, at)| at == seen) {
+                self.keys.remove(key);
+            }
+            self.order.pop_front();
+        }
+    }
+}
+
+impl Default for IdempotencyKeys {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
+    }
+}
@@ -0,0 +1,201 @@
+//! Multi-level feedback queue
+//!
+//! [`MlfqScheduler`] keeps one FIFO queue per level, level 0 first. New
+//! tasks start at the top, so short interactive work is served quickly. A
+//! task that uses up its level's quantum, across however many runs, is
+//! demoted one level, so long batch work sinks to the lower levels with
+//! longer quanta. Every boost interval all tasks move back to the top,
+//! which keeps batch work from starving.
+
+use super::Task;
+use crate::timer::{self, SharedClock};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Quantum of the top level by default; each lower level doubles it
+const DEFAULT_QUANTUM: Duration = Duration::from_millis(10);
+
+/// Task queued at a level, with the run time it used there
+#[derive(Debug)]
+struct Entry {
+    task: Task,
+    used: Duration,
+}
+
+/// Task taken from an [`MlfqScheduler`], to be run for at most its quantum
+#[derive(Debug)]
+pub struct MlfqDispatch {
+    /// Task to run
+    pub task: Task,
+    /// Level the task was taken from
+    pub level: usize,
+    /// Run time left before the task is demoted
+    pub quantum: Duration,
+    used: Duration,
+}
+
+/// Multi-level feedback queue scheduler
+#[derive(Debug)]
+pub struct MlfqScheduler {
+    levels: Vec<VecDeque<Entry>>,
+    quanta: Vec<Duration>,
+    boost_interval: Duration,
+    last_boost: Instant,
+    boosts: u64,
+    demotions: u64,
+    clock: SharedClock,
+}
+
+impl MlfqScheduler {
+    /// Create a scheduler with `levels` queues, boosting every second
+    ///
+    /// The top level's quantum is 10ms and each lower level doubles it.
+    pub fn new(levels: usize) -> Self {
+        let levels = levels.max(1);
+        let clock = timer::system_clock();
+        Self {
+            levels: (0..levels).map(|_| VecDeque::new()).collect(),
+            quanta: (0..levels)
+                .map(|level| DEFAULT_QUANTUM * 2u32.saturating_pow(level as u32))
+                .collect(),
+            boost_interval: Duration::from_secs(1),
+            last_boost: clock.instant(),
+            boosts: 0,
+            demotions: 0,
+            clock,
+        }
+    }
+
+    /// Set the run time a task may use at `level` before it is demoted
+    pub fn with_quantum(mut self, level: usize, quantum: Duration) -> Self {
+        if let Some(slot) = self.quanta.get_mut(level) {
+            *slot = quantum;
+        }
+        self
+    }
+
+    /// Set how often all tasks move back to the top level
+    pub fn with_boost_interval(mut self, interval: Duration) -> Self {
+        self.boost_interval = interval;
+        self
+    }
+
+    /// Time boosts with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.last_boost = clock.instant();
+        self.clock = clock;
+        self
+    }
+
+    /// Queue a new task at the top level
+    pub fn submit(&mut self, task: Task) {
+        self.levels[0].push_back(Entry {
+            task,
+            used: Duration::ZERO,
+        });
+    }
+
+    /// Take the next task from the highest non-empty level
+    ///
+    /// Boosts first if the boost interval has passed.
+    pub fn next_task(&mut self) -> Option<MlfqDispatch> {
+        if self
+            .clock
+            .instant()
+            .saturating_duration_since(self.last_boost)
+            >= self.boost_interval
+        {
+            self.boost();
+        }
+        let (level, entry) = self
+            .levels
+            .iter_mut()
+            .enumerate()
+            .find_map(|(level, queue)| Some((level, queue.pop_front()?)))?;
+        Some(MlfqDispatch {
+            task: entry.task,
+            level,
+            quantum: self.quanta[level].saturating_sub(entry.used),
+            used: entry.used,
+        })
+    }
+
+    /// Queue a task again after it ran for `ran` without finishing
+    ///
+    /// A task that used up its quantum at its level is demoted one level;
+    /// one that yielded early keeps its level and the time it used.
+    pub fn requeue(&mut self, dispatch: MlfqDispatch, ran: Duration) {
+        let used = dispatch.used + ran;
+        let (level, used) = if used < self.quanta[dispatch.level] {
+            (dispatch.level, used)
+        } else if dispatch.level + 1 < self.levels.len() {
+            self.demotions += 1;
+            (dispatch.level + 1, Duration::ZERO)
+        } else {
+            (dispatch.level, Duration::ZERO)
+        };
+        self.levels[level].push_back(Entry {
+            task: dispatch.task,
+            used,
+        });
+    }
+
+    /// Move every task to the top level and forget the time it used
+    pub fn boost(&mut self) {
+        let (top, lower) = self.levels.split_at_mut(1);
+        for queue in lower {
+            top[0].extend(queue.drain(..));
+        }
+        for entry in &mut top[0] {
+            entry.used = Duration::ZERO;
+        }
+        self.last_boost = self.clock.instant();
+        self.boosts += 1;
+    }
+
+    /// Get number of levels
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Get quantum of a level
+    pub fn quantum(&self, level: usize) -> Option<Duration> {
+        self.quanta.get(level).copied()
+    }
+
+    /// Get boost interval
+    pub fn boost_interval(&self) -> Duration {
+        self.boost_interval
+    }
+
+    /// Get number of tasks queued at a level
+    pub fn level_len(&self, level: usize) -> usize {
+        self.levels.get(level).map_or(0, VecDeque::len)
+    }
+
+    /// Get number of queued tasks
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(VecDeque::len).sum()
+    }
+
+    /// Check if no tasks are queued
+    pub fn is_empty(&self) -> bool {
+        self.levels.iter().all(VecDeque::is_empty)
+    }
+
+    /// Get number of boosts so far
+    pub fn boosts(&self) -> u64 {
+        self.boosts
+    }
+
+    /// Get number of demotions so far
+    pub fn demotions(&self) -> u64 {
+        self.demotions
+    }
+}
+
+impl Default for MlfqScheduler {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
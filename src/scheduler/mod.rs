@@ -1,21 +1,109 @@
 //! Task scheduling and policies
 
+/// Per-agent execution accounting
+pub mod accounting;
+/// Keyed-serial execution of conversations
+mod conversation;
+/// Cost budgets and budget breakers
+pub mod cost;
+/// CPU-time budgets
+pub mod cpu_budget;
+/// Cron-style periodic tasks
+pub mod cron;
+/// Task dependency graph
+mod dag;
+/// Dead-letter queue for failed tasks
+pub mod dead_letter;
+/// Blue-green deployments
+pub mod deployment;
 /// Scheduler engine
 pub mod engine;
+/// Scheduler events
+pub mod event;
 /// Fair share scheduling
 pub mod fair_share;
+/// Gang scheduling of task groups
+pub mod gang;
+/// Deduplication by idempotency key
+mod idempotency;
+/// Multi-level feedback queues
+pub mod mlfq;
+/// Scheduler overhead profiling
+pub mod overhead;
 /// Scheduling policies
 pub mod policy;
+/// Agent pools
+pub mod pool;
+/// Cooperative preemption
+pub mod preemption;
 /// Priority scheduling
 pub mod priority;
+/// Rate limit pools for external providers
+pub mod provider;
+/// Per-agent and global dispatch rate limiting
+pub mod rate_limit;
+/// Recurring tasks
+pub mod recurring;
+/// Task results awaitable by task ID
+pub mod results;
+/// Rolling restarts
+pub mod rollout;
 /// Round robin scheduling
 pub mod round_robin;
+/// Per-agent runtime caps
+pub mod runtime_cap;
+/// Shadow policy evaluation
+pub mod shadow;
+/// Speculative execution with first-wins cancellation
+pub mod speculation;
+/// Scheduler state for warm starts
+pub mod state;
 /// Task queue
 pub mod task_queue;
+/// Tie-breaking within a priority level
+pub mod tie_break;
+/// Event-driven dispatch wakeups
+pub mod wakeup;
+/// Work-stealing task queues
+pub mod work_stealing;
 
-pub use engine::Scheduler;
-pub use fair_share::FairShareScheduler;
-pub use policy::{PolicyType, SchedulingPolicy};
-pub use priority::PriorityScheduler;
+pub use accounting::AgentStats;
+pub use cost::CostBudget;
+pub use cpu_budget::{CpuBudget, CpuBudgetAction, CpuBudgeted};
+pub use cron::CronExpr;
+pub use dead_letter::{DeadLetter, DeadLetterQueue, DeadLetterReason};
+pub use deployment::{Deployment, DeploymentState, DeploymentVerdict, Outcomes};
+pub use engine::{HoldReason, Scheduler};
+pub use event::SchedulerEvent;
+pub use fair_share::{FairShareScheduler, ShareGroup, ShareUsage};
+pub use gang::TaskGroup;
+pub use mlfq::{MlfqDispatch, MlfqScheduler};
+pub use overhead::OverheadProfile;
+pub use policy::{PolicyEngine, PolicyType, SchedulingPolicy};
+pub use pool::AgentPool;
+pub use preemption::PreemptionHandle;
+pub use priority::{PriorityAging, PriorityBand, PriorityQuota, PriorityScheduler, QuotaOverflow};
+pub use provider::{ProviderDemand, ProviderLimits, ProviderUsage};
+pub use rate_limit::{RateLimit, RateLimiter, RateLimiterRecord};
+pub use recurring::{MisfirePolicy, RecurringSchedule, ScheduleTrigger};
+pub use results::{TaskFailure, TaskHandle, TaskResult};
+pub use rollout::{HealthGate, RolloutReport};
 pub use round_robin::RoundRobinScheduler;
-pub use task_queue::{Task, TaskQueue};
+pub use runtime_cap::{CapAction, RuntimeCap};
+pub use semver::{Version, VersionReq};
+pub use shadow::{ShadowDispatch, ShadowReport, ShadowScheduler};
+pub use speculation::SpeculationStats;
+pub use state::{
+    CostRecord, DeadLetterRecord, ProviderRecord, RuntimeRecord, SchedulerSnapshot, SchedulerState,
+    TaskRecord,
+};
+#[cfg(feature = "persistence")]
+pub use task_queue::FileQueueStore;
+pub use task_queue::{
+    BoundedTaskQueue, MemoryQueueStore, OverflowPolicy, OverrunAction, PersistentTaskQueue,
+    PushOutcome, QueueStore, Task, TaskId, TaskQueue,
+};
+pub use tie_break::TieBreak;
+pub use tokio_util::sync::CancellationToken;
+pub use wakeup::{WakeReason, Wakeup, WakeupStats};
+pub use work_stealing::{WorkStealingScheduler, WorkerQueue};
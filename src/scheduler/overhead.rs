@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Choosing the next task from the held list or queue
+pub const SELECTION: &str = "scheduler;next_task;selection";
+/// Routing a task to its agent or a pool sibling
+pub const DISPATCH: &str = "scheduler;next_task;dispatch";
+/// Recording queue wait and other statistics
+pub const ACCOUNTING: &str = "scheduler;next_task;accounting";
+/// Maintaining the held list
+pub const BOOKKEEPING: &str = "scheduler;next_task;bookkeeping";
+
+/// Time the scheduler spends in its own phases
+///
+/// Disabled by default, since timing every phase costs a clock read per phase.
+#[derive(Debug, Clone, Default)]
+pub struct OverheadProfile {
+    enabled: bool,
+    phases: BTreeMap<&'static str, Duration>,
+}
+
+impl OverheadProfile {
+    /// Create a disabled profile
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an enabled profile
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            phases: BTreeMap::new(),
+        }
+    }
+
+    /// Check if phases are being timed
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start timing a phase
+    pub(crate) fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    /// Stop timing a phase started with [`start`](Self::start)
+    pub(crate) fn stop(&mut self, stack: &'static str, started: Option<Instant>) {
+        if let Some(started) = started {
+            *self.phases.entry(stack).or_default() += started.elapsed();
+        }
+    }
+
+    /// Get time spent in a phase
+    pub fn phase(&self, stack: &str) -> Duration {
+        self.phases.get(stack).copied().unwrap_or_default()
+    }
+
+    /// Get time spent in all phases
+    pub fn total(&self) -> Duration {
+        self.phases.values().sum()
+    }
+
+    /// Export in folded-stack format with nanosecond weights
+    ///
+    /// The output can be fed to `inferno-flamegraph` or `flamegraph.pl`.
+    pub fn to_folded(&self) -> String {
+        let mut folded = String::new();
+        for (stack, elapsed) in &self.phases {
+            let _ = writeln!(folded, "{} {}", stack, elapsed.as_nanos());
+        }
+        folded
+    }
+
+    /// Clear recorded time
+    pub fn reset(&mut self) {
+        self.phases.clear();
+    }
+}
@@ -1,47 +1,46 @@
-use serde::{Deserialize, Serialize};
+//! Scheduling policies
+//!
+//! A [`SchedulingPolicy`] names one of the built-in orderings the
+//! [`super::Scheduler`] applies itself, along with its aging, tie-breaking,
+//! and fair-share settings. A [`PolicyEngine`] replaces that ordering with
+//! custom code; both can be swapped while the scheduler runs, and queued
+//! tasks stay where they are.
 
-/// Type of scheduling policy
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub enum PolicyType {
-    /// Fair share scheduling
-    FairShare,
-    /// Priority-based scheduling
-    Priority,
-    /// Round robin scheduling
-    RoundRobin,
-    /// First-come-first-served
-    FCFS,
-}
-
-/// Scheduling policy
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SchedulingPolicy {
-    policy_type: PolicyType,
-    parameters: Vec<(String, f64)>,
-}
+use super::{Task, TaskQueue};
+use std::cmp::Reverse;
 
-impl SchedulingPolicy {
-    /// Create a new scheduling policy
-    pub fn new(policy_type: PolicyType) -> Self {
-        Self {
-            policy_type,
-            parameters: Vec::new(),
-        }
-    }
+pub use agentropic_runtime_core::{PolicyType, SchedulingPolicy};
 
-    /// Add parameter
-    pub fn with_parameter(mut self, name: impl Into<String>, value: f64) -> Self {
-        self.parameters.push((name.into(), value));
-        self
-    }
+/// Picks the next task to dispatch from the queue
+pub trait PolicyEngine: Send + Sync {
+    /// Remove and return the task to dispatch next, if any
+    fn next(&mut self, queue: &mut TaskQueue) -> Option<Task>;
+}
 
-    /// Get policy type
-    pub fn policy_type(&self) -> PolicyType {
-        self.policy_type
-    }
+/// Index of the oldest task with the highest `key`
+pub(crate) fn oldest_max_by_key<K: Ord>(
+    queue: &TaskQueue,
+    key: impl Fn(&Task) -> K,
+) -> Option<usize> {
+    queue
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, task)| (key(task), Reverse(*index)))
+        .map(|(index, _)| index)
+}
 
-    /// Get parameters
-    pub fn parameters(&self) -> &[(String, f64)] {
-        &self.parameters
+/// The built-in ordering without the scheduler's aging, tie-breaking, or
+/// fair-share settings: highest priority first for `Priority`, earliest
+/// deadline first for `EarliestDeadlineFirst`, and oldest first otherwise
+impl PolicyEngine for SchedulingPolicy {
+    fn next(&mut self, queue: &mut TaskQueue) -> Option<Task> {
+        let index = match self.policy_type() {
+            PolicyType::Priority => oldest_max_by_key(queue, Task::priority)?,
+            PolicyType::EarliestDeadlineFirst => {
+                oldest_max_by_key(queue, |task| task.deadline().map(Reverse))?
+            }
+            _ => 0,
+        };
+        queue.remove(index)
     }
 }
@@ -0,0 +1,125 @@
+use super::{HealthGate, RolloutReport};
+use crate::timer::{self, SharedClock};
+use crate::RuntimeError;
+use agentropic_core::AgentId;
+use futures::Future;
+use std::time::Duration;
+
+/// Group of interchangeable agents
+#[derive(Debug, Clone)]
+pub struct AgentPool {
+    name: String,
+    members: Vec<AgentId>,
+    clock: SharedClock,
+}
+
+impl AgentPool {
+    /// Create a new agent pool
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            members: Vec::new(),
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Time the pause between rolling restart batches with `clock`
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Add member
+    pub fn with_member(mut self, agent_id: AgentId) -> Self {
+        self.add_member(agent_id);
+        self
+    }
+
+    /// Add member
+    pub fn add_member(&mut self, agent_id: AgentId) {
+        if !self.members.contains(&agent_id) {
+            self.members.push(agent_id);
+        }
+    }
+
+    /// Remove member
+    pub fn remove_member(&mut self, agent_id: &AgentId) {
+        self.members.retain(|member| member != agent_id);
+    }
+
+    /// Check if agent is a member
+    pub fn contains(&self, agent_id: &AgentId) -> bool {
+        self.members.contains(agent_id)
+    }
+
+    /// Get pool name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get members
+    pub fn members(&self) -> &[AgentId] {
+        &self.members
+    }
+
+    /// Get pool size
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Restart members in batches, waiting for readiness between batches
+    ///
+    /// `restart` restarts one instance. After each batch every restarted
+    /// instance must pass `health_gate`; the rollout pauses for `pause` and
+    /// continues, or aborts once more instances regressed than the gate
+    /// tolerates.
+    pub async fn rolling_restart<R, Fut>(
+        &self,
+        batch_size: usize,
+        pause: Duration,
+        health_gate: &HealthGate,
+        mut restart: R,
+    ) -> RolloutReport
+    where
+        R: FnMut(AgentId) -> Fut,
+        Fut: Future<Output = Result<(), RuntimeError>>,
+    {
+        let mut report = RolloutReport::default();
+        let batches: Vec<_> = self.members.chunks(batch_size.max(1)).collect();
+
+        for (index, batch) in batches.iter().enumerate() {
+            for agent_id in batch.iter() {
+                if let Err(error) = restart(*agent_id).await {
+                    tracing::warn!("Rolling restart of agent {} failed: {}", agent_id, error);
+                    report.regressed.push(*agent_id);
+                }
+                report.restarted.push(*agent_id);
+            }
+            for agent_id in batch.iter() {
+                if !report.regressed.contains(agent_id) && !health_gate.wait_ready(*agent_id).await
+                {
+                    report.regressed.push(*agent_id);
+                }
+            }
+
+            if report.regressed.len() > health_gate.max_regressions() {
+                report.aborted = index + 1 < batches.len();
+                tracing::warn!(
+                    "Aborting rolling restart of pool {}: {} instances regressed",
+                    self.name,
+                    report.regressed.len()
+                );
+                break;
+            }
+            if index + 1 < batches.len() {
+                self.clock.sleep(pause).await;
+            }
+        }
+        report
+    }
+}
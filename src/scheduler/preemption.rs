@@ -0,0 +1,57 @@
+//! Cooperative preemption
+//!
+//! A scheduler built with `Scheduler::with_preemption` runs a bounded number
+//! of tasks at once. When a waiting task outranks a running one that has run
+//! for at least the engine's time slice, the running task's
+//! [`PreemptionHandle`] is signalled. The task should stop at a safe point,
+//! save the work it has left with [`PreemptionHandle::save_remaining`], and
+//! return `SchedulerError::Preempted`; it is then queued again with the
+//! remaining work as its payload, without using up an attempt.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio_util::sync::CancellationToken;
+
+/// Preemption signal handed to a running task through `Task::preemption`
+#[derive(Debug, Clone, Default)]
+pub struct PreemptionHandle {
+    signal: CancellationToken,
+    remaining: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl PreemptionHandle {
+    /// Create a handle that has not been signalled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if the task was asked to yield
+    pub fn is_preempted(&self) -> bool {
+        self.signal.is_cancelled()
+    }
+
+    /// Wait until the task is asked to yield
+    pub async fn preempted(&self) {
+        self.signal.cancelled().await
+    }
+
+    /// Save the work left, to become the requeued task's payload
+    pub fn save_remaining(&self, payload: impl Into<Vec<u8>>) {
+        *self.lock_remaining() = Some(payload.into());
+    }
+
+    /// Ask the task to yield
+    pub(crate) fn preempt(&self) {
+        self.signal.cancel();
+    }
+
+    /// Take the work saved by the task, if any
+    pub(crate) fn take_remaining(&self) -> Option<Vec<u8>> {
+        self.lock_remaining().take()
+    }
+
+    fn lock_remaining(&self) -> MutexGuard<'_, Option<Vec<u8>>> {
+        self.remaining
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
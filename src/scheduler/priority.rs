@@ -1,18 +1,105 @@
+use super::policy::PolicyEngine;
+use super::{Task, TaskQueue};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+/// How a priority level competes with the levels below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityBand {
+    /// Always served before lower levels
+    Strict,
+    /// Shares dispatches by weight with adjacent proportional levels
+    ///
+    /// Consecutive proportional levels form one group that ranks as its
+    /// highest level; weights below 1 count as 1.
+    Proportional(u32),
+}
+
 /// Priority scheduler
+///
+/// Levels are strict unless configured otherwise, so by default higher
+/// priorities always go first. Making levels proportional mixes them
+/// instead: with level 2 strict and levels 1 and 0 weighted 70 and 30,
+/// system tasks at level 2 preempt user tasks, which split the remaining
+/// dispatches 70/30 while both levels have tasks queued.
 pub struct PriorityScheduler {
     levels: u32,
+    bands: BTreeMap<u32, PriorityBand>,
+    /// Smooth weighted round-robin credit of proportional levels with tasks
+    credits: HashMap<u32, i64>,
 }
 
 impl PriorityScheduler {
     /// Create a new priority scheduler
     pub fn new(levels: u32) -> Self {
-        Self { levels }
+        Self {
+            levels,
+            bands: BTreeMap::new(),
+            credits: HashMap::new(),
+        }
+    }
+
+    /// Configure how a level competes with lower ones
+    pub fn with_band(mut self, level: u32, band: PriorityBand) -> Self {
+        self.bands.insert(level, band);
+        self
     }
 
     /// Get priority levels
     pub fn levels(&self) -> u32 {
         self.levels
     }
+
+    /// Get how a level competes with lower ones
+    pub fn band(&self, level: u32) -> PriorityBand {
+        self.bands
+            .get(&level)
+            .copied()
+            .unwrap_or(PriorityBand::Strict)
+    }
+
+    /// Pick a level of the proportional group headed by `highest`
+    fn share(&mut self, highest: u32, queued: &BTreeSet<u32>) -> u32 {
+        self.credits.retain(|level, _| queued.contains(level));
+        let group: Vec<(u32, i64)> = (0..=highest)
+            .rev()
+            .map_while(|level| match self.band(level) {
+                PriorityBand::Proportional(weight) => Some((level, i64::from(weight.max(1)))),
+                PriorityBand::Strict => None,
+            })
+            .filter(|(level, _)| queued.contains(level))
+            .collect();
+        let total: i64 = group.iter().map(|(_, weight)| weight).sum();
+        for (level, weight) in &group {
+            *self.credits.entry(*level).or_default() += weight;
+        }
+        let (level, _) = group
+            .iter()
+            .max_by_key(|(level, _)| (self.credits[level], *level))
+            .copied()
+            .unwrap_or((highest, 0));
+        *self.credits.entry(level).or_default() -= total;
+        level
+    }
+}
+
+/// Highest priority first, oldest first within a level; priorities above
+/// the top level count as the top level, and proportional levels take
+/// turns by weight
+impl PolicyEngine for PriorityScheduler {
+    fn next(&mut self, queue: &mut TaskQueue) -> Option<Task> {
+        let top = self.levels.saturating_sub(1);
+        let queued: BTreeSet<u32> = queue.iter().map(|task| task.priority().min(top)).collect();
+        let highest = *queued.last()?;
+        let level = match self.band(highest) {
+            PriorityBand::Strict => highest,
+            PriorityBand::Proportional(_) => self.share(highest, &queued),
+        };
+        let index = queue
+            .iter()
+            .position(|task| task.priority().min(top) == level)?;
+        queue.remove(index)
+    }
 }
 
 impl Default for PriorityScheduler {
@@ -20,3 +107,110 @@ impl Default for PriorityScheduler {
         Self::new(5)
     }
 }
+
+/// What to do with a task submitted to a full priority level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaOverflow {
+    /// Fail the submission
+    Reject,
+    /// Lower the task's priority until it reaches a level with room
+    Demote,
+}
+
+/// Cap on how many tasks one priority level may hold in the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityQuota {
+    max_queued: usize,
+    overflow: QuotaOverflow,
+}
+
+impl PriorityQuota {
+    /// Create a quota that rejects overflow
+    pub fn new(max_queued: usize) -> Self {
+        Self {
+            max_queued,
+            overflow: QuotaOverflow::Reject,
+        }
+    }
+
+    /// Demote overflow to lower priorities instead of rejecting it
+    pub fn demote(mut self) -> Self {
+        self.overflow = QuotaOverflow::Demote;
+        self
+    }
+
+    /// Get maximum queued tasks
+    pub fn max_queued(&self) -> usize {
+        self.max_queued
+    }
+
+    /// Get overflow handling
+    pub fn overflow(&self) -> QuotaOverflow {
+        self.overflow
+    }
+}
+
+/// Raises the priority of queued tasks the longer they wait
+///
+/// Without aging, a steady stream of high-priority tasks keeps lower ones
+/// queued indefinitely. With aging, a task gains one level per `interval`
+/// waited, so a task of priority `p` outranks fresh tasks of priority `q`
+/// after waiting `(q - p + 1) * interval`. A starvation bound additionally
+/// puts any task waiting longer than the bound ahead of all others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriorityAging {
+    interval: Duration,
+    max_boost: Option<u32>,
+    starvation_bound: Option<Duration>,
+}
+
+impl PriorityAging {
+    /// Raise a task's priority by one level for every `interval` it waits
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_boost: None,
+            starvation_bound: None,
+        }
+    }
+
+    /// Limit how many levels a task can gain by waiting
+    pub fn with_max_boost(mut self, levels: u32) -> Self {
+        self.max_boost = Some(levels);
+        self
+    }
+
+    /// Serve any task waiting longer than `bound` before all other tasks
+    pub fn with_starvation_bound(mut self, bound: Duration) -> Self {
+        self.starvation_bound = Some(bound);
+        self
+    }
+
+    /// Get wait that earns one level
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Get maximum levels gained
+    pub fn max_boost(&self) -> Option<u32> {
+        self.max_boost
+    }
+
+    /// Get starvation bound
+    pub fn starvation_bound(&self) -> Option<Duration> {
+        self.starvation_bound
+    }
+
+    /// Get the priority a task is scheduled at after waiting `waited`
+    pub fn effective_priority(&self, priority: u32, waited: Duration) -> u32 {
+        if self.starvation_bound.is_some_and(|bound| waited > bound) {
+            return u32::MAX;
+        }
+        let steps = waited.as_nanos() / self.interval.as_nanos().max(1);
+        let mut boost = u32::try_from(steps).unwrap_or(u32::MAX);
+        if let Some(max_boost) = self.max_boost {
+            boost = boost.min(max_boost);
+        }
+        priority.saturating_add(boost)
+    }
+}
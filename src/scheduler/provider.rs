@@ -0,0 +1,184 @@
+//! Rate limit pools for external providers
+//!
+//! LLM backends limit requests and tokens per minute across every caller
+//! sharing a key. Registering each provider with the [`super::Scheduler`] and
+//! declaring usage on tasks lets the scheduler hold work back fleet-wide
+//! instead of each agent discovering the limit through errors.
+
+use super::state::{Now, ProviderRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Window provider limits apply to
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Provider capacity a task declares it will use
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderDemand {
+    provider: String,
+    tokens: u64,
+}
+
+impl ProviderDemand {
+    /// Create a demand for one request using `tokens` tokens
+    pub fn new(provider: impl Into<String>, tokens: u64) -> Self {
+        Self {
+            provider: provider.into(),
+            tokens,
+        }
+    }
+
+    /// Get provider name
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    /// Get tokens used
+    pub fn tokens(&self) -> u64 {
+        self.tokens
+    }
+}
+
+/// Per-minute limits of a provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderLimits {
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u64>,
+}
+
+impl ProviderLimits {
+    /// Create unlimited provider limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit requests per minute
+    pub fn with_requests_per_minute(mut self, requests: u32) -> Self {
+        self.requests_per_minute = Some(requests);
+        self
+    }
+
+    /// Limit tokens per minute
+    pub fn with_tokens_per_minute(mut self, tokens: u64) -> Self {
+        self.tokens_per_minute = Some(tokens);
+        self
+    }
+
+    /// Get requests per minute limit
+    pub fn requests_per_minute(&self) -> Option<u32> {
+        self.requests_per_minute
+    }
+
+    /// Get tokens per minute limit
+    pub fn tokens_per_minute(&self) -> Option<u64> {
+        self.tokens_per_minute
+    }
+}
+
+/// Provider usage within the last minute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProviderUsage {
+    /// Requests dispatched
+    pub requests: u32,
+    /// Tokens declared by dispatched tasks
+    pub tokens: u64,
+}
+
+/// Sliding-window usage of one provider
+#[derive(Debug)]
+pub(crate) struct ProviderPool {
+    limits: ProviderLimits,
+    dispatched: VecDeque<(Instant, u64)>,
+}
+
+impl ProviderPool {
+    pub(crate) fn new(limits: ProviderLimits) -> Self {
+        Self {
+            limits,
+            dispatched: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn usage(&self, now: Instant) -> ProviderUsage {
+        self.dispatched
+            .iter()
+            .filter(|(at, _)| now.saturating_duration_since(*at) < WINDOW)
+            .fold(ProviderUsage::default(), |usage, (_, tokens)| {
+                ProviderUsage {
+                    requests: usage.requests + 1,
+                    tokens: usage.tokens + tokens,
+                }
+            })
+    }
+
+    /// Check if a request using `tokens` fits in the current window
+    ///
+    /// A request larger than the token limit is admitted once the window is
+    /// empty, so it cannot be starved forever. A request limit of zero
+    /// admits nothing.
+    pub(crate) fn admits(&self, tokens: u64, now: Instant) -> bool {
+        let usage = self.usage(now);
+        if self
+            .limits
+            .requests_per_minute
+            .is_some_and(|limit| usage.requests >= limit)
+        {
+            return false;
+        }
+        usage.requests == 0
+            || self
+                .limits
+                .tokens_per_minute
+                .is_none_or(|limit| usage.tokens + tokens <= limit)
+    }
+
+    /// When the oldest request in the window stops counting
+    pub(crate) fn next_release(&self, now: Instant) -> Option<Instant> {
+        self.dispatched
+            .iter()
+            .map(|(at, _)| *at + WINDOW)
+            .find(|release| *release > now)
+    }
+
+    pub(crate) fn to_record(&self, now: Now) -> ProviderRecord {
+        ProviderRecord {
+            limits: self.limits,
+            dispatched_ago: self
+                .dispatched
+                .iter()
+                .map(|(at, tokens)| (now.instant.saturating_duration_since(*at), *tokens))
+                .collect(),
+            captured_at: Some(now.wall),
+        }
+    }
+
+    pub(crate) fn from_record(record: ProviderRecord, now: Now) -> Self {
+        let elapsed = now.since(record.captured_at);
+        Self {
+            limits: record.limits,
+            dispatched: record
+                .dispatched_ago
+                .into_iter()
+                .map(|(ago, tokens)| {
+                    let at = now
+                        .instant
+                        .checked_sub(ago + elapsed)
+                        .unwrap_or(now.instant);
+                    (at, tokens)
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn charge(&mut self, tokens: u64, now: Instant) {
+        while self
+            .dispatched
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= WINDOW)
+        {
+            self.dispatched.pop_front();
+        }
+        self.dispatched.push_back((now, tokens));
+    }
+}
@@ -0,0 +1,329 @@
+//! Rate limiting for task dispatch
+//!
+//! Agents driven by LLM APIs need throttling both individually and across
+//! the fleet. A [`RateLimiter`] combines an optional global limit with
+//! per-agent limits and a default for agents without one of their own. The
+//! [`super::Scheduler`] consults it before dispatching a task and holds the
+//! task until its agent is admitted again.
+
+use super::state::Now;
+use agentropic_core::AgentId;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Limit on how often tasks are dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RateLimit {
+    /// Allow bursts of up to `capacity` dispatches, refilled at `per_second`
+    TokenBucket {
+        /// Maximum dispatches in a burst
+        capacity: u32,
+        /// Dispatches regained per second
+        per_second: f64,
+    },
+    /// Allow at most `limit` dispatches within any `window`
+    SlidingWindow {
+        /// Maximum dispatches per window
+        limit: u32,
+        /// Window length
+        window: Duration,
+    },
+}
+
+impl RateLimit {
+    /// Create a token bucket limit
+    pub fn token_bucket(capacity: u32, per_second: f64) -> Self {
+        RateLimit::TokenBucket {
+            capacity,
+            per_second,
+        }
+    }
+
+    /// Create a sliding window limit
+    pub fn sliding_window(limit: u32, window: Duration) -> Self {
+        RateLimit::SlidingWindow { limit, window }
+    }
+}
+
+/// Usage tracked against one limit
+#[derive(Debug, Clone)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Option<Instant>,
+    dispatched: VecDeque<Instant>,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit) -> Self {
+        let tokens = match limit {
+            RateLimit::TokenBucket { capacity, .. } => f64::from(capacity),
+            RateLimit::SlidingWindow { .. } => 0.0,
+        };
+        Self {
+            limit,
+            tokens,
+            updated: None,
+            dispatched: VecDeque::new(),
+        }
+    }
+
+    /// Tokens available at `now` after refilling
+    fn tokens_at(&self, capacity: u32, per_second: f64, now: Instant) -> f64 {
+        let refilled = self.updated.map_or(0.0, |updated| {
+            now.saturating_duration_since(updated).as_secs_f64() * per_second
+        });
+        (self.tokens + refilled).min(f64::from(capacity))
+    }
+
+    /// Dispatches counted within the window ending at `now`
+    fn in_window(&self, window: Duration, now: Instant) -> impl Iterator<Item = &Instant> {
+        self.dispatched
+            .iter()
+            .filter(move |at| now.saturating_duration_since(**at) < window)
+    }
+
+    fn admits(&self, now: Instant) -> bool {
+        match self.limit {
+            RateLimit::TokenBucket {
+                capacity,
+                per_second,
+            } => self.tokens_at(capacity, per_second, now) >= 1.0,
+            RateLimit::SlidingWindow { limit, window } => {
+                self.in_window(window, now).count() < limit as usize
+            }
+        }
+    }
+
+    fn charge(&mut self, now: Instant) {
+        match self.limit {
+            RateLimit::TokenBucket {
+                capacity,
+                per_second,
+            } => {
+                self.tokens = (self.tokens_at(capacity, per_second, now) - 1.0).max(0.0);
+                self.updated = Some(now);
+            }
+            RateLimit::SlidingWindow { window, .. } => {
+                while self
+                    .dispatched
+                    .front()
+                    .is_some_and(|at| now.saturating_duration_since(*at) >= window)
+                {
+                    self.dispatched.pop_front();
+                }
+                self.dispatched.push_back(now);
+            }
+        }
+    }
+
+    /// When the next dispatch is admitted, or `None` if never
+    fn available_at(&self, now: Instant) -> Option<Instant> {
+        if self.admits(now) {
+            return Some(now);
+        }
+        match self.limit {
+            RateLimit::TokenBucket {
+                capacity,
+                per_second,
+            } => {
+                if capacity == 0 || per_second <= 0.0 {
+                    return None;
+                }
+                let missing = 1.0 - self.tokens_at(capacity, per_second, now);
+                Some(now + Duration::from_secs_f64(missing / per_second))
+            }
+            RateLimit::SlidingWindow { limit, window } => {
+                let counted = self.in_window(window, now).count();
+                let excess = counted.checked_sub(limit as usize)?;
+                self.in_window(window, now)
+                    .nth(excess)
+                    .map(|oldest| *oldest + window)
+            }
+        }
+    }
+}
+
+/// Serializable form of a [`Bucket`], with times as ages at capture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BucketRecord {
+    limit: RateLimit,
+    tokens: f64,
+    updated_ago: Option<Duration>,
+    dispatched_ago: Vec<Duration>,
+}
+
+impl Bucket {
+    fn to_record(&self, now: Instant) -> BucketRecord {
+        BucketRecord {
+            limit: self.limit,
+            tokens: self.tokens,
+            updated_ago: self
+                .updated
+                .map(|updated| now.saturating_duration_since(updated)),
+            dispatched_ago: self
+                .dispatched
+                .iter()
+                .map(|at| now.saturating_duration_since(*at))
+                .collect(),
+        }
+    }
+
+    fn from_record(record: BucketRecord, now: Instant, elapsed: Duration) -> Self {
+        let at = |ago: Duration| now.checked_sub(ago + elapsed).unwrap_or(now);
+        Self {
+            limit: record.limit,
+            tokens: record.tokens,
+            updated: record.updated_ago.map(at),
+            dispatched: record.dispatched_ago.into_iter().map(at).collect(),
+        }
+    }
+}
+
+/// Serializable form of a [`RateLimiter`] and the dispatches counted
+/// against its limits
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimiterRecord {
+    global: Option<BucketRecord>,
+    default_limit: Option<RateLimit>,
+    agents: Vec<(AgentId, BucketRecord)>,
+    captured_at: Option<SystemTime>,
+}
+
+/// Admission control for dispatching tasks, per agent and globally
+///
+/// A task is admitted only when both the global limit and its agent's limit
+/// allow it. Agents without a limit of their own use the default agent
+/// limit, if any, each with a separate allowance.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    global: Option<Bucket>,
+    default_limit: Option<RateLimit>,
+    agents: HashMap<AgentId, Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a limiter that admits everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit dispatches across all agents
+    pub fn with_global_limit(mut self, limit: RateLimit) -> Self {
+        self.global = Some(Bucket::new(limit));
+        self
+    }
+
+    /// Limit each agent that has no limit of its own
+    pub fn with_default_agent_limit(mut self, limit: RateLimit) -> Self {
+        self.default_limit = Some(limit);
+        self
+    }
+
+    /// Limit one agent
+    pub fn with_agent_limit(mut self, agent_id: AgentId, limit: RateLimit) -> Self {
+        self.set_agent_limit(agent_id, limit);
+        self
+    }
+
+    /// Set an agent's limit, starting it with a fresh allowance
+    pub fn set_agent_limit(&mut self, agent_id: AgentId, limit: RateLimit) {
+        self.agents.insert(agent_id, Bucket::new(limit));
+    }
+
+    /// Remove an agent's limit, falling back to the default agent limit
+    pub fn clear_agent_limit(&mut self, agent_id: &AgentId) {
+        self.agents.remove(agent_id);
+    }
+
+    /// Get global limit
+    pub fn global_limit(&self) -> Option<RateLimit> {
+        self.global.as_ref().map(|bucket| bucket.limit)
+    }
+
+    /// Get the limit applying to an agent
+    pub fn agent_limit(&self, agent_id: &AgentId) -> Option<RateLimit> {
+        self.agents
+            .get(agent_id)
+            .map(|bucket| bucket.limit)
+            .or(self.default_limit)
+    }
+
+    /// Check if a task for an agent may be dispatched at `now`
+    pub fn admits(&self, agent_id: &AgentId, now: Instant) -> bool {
+        self.global.as_ref().is_none_or(|bucket| bucket.admits(now))
+            && self
+                .agents
+                .get(agent_id)
+                .is_none_or(|bucket| bucket.admits(now))
+    }
+
+    /// Admit and count a dispatch if the limits allow it
+    pub fn try_acquire(&mut self, agent_id: AgentId, now: Instant) -> bool {
+        if !self.admits(&agent_id, now) {
+            return false;
+        }
+        self.charge(agent_id, now);
+        true
+    }
+
+    /// Get when a task for an agent is next admitted, or `None` if never
+    pub fn available_at(&self, agent_id: &AgentId, now: Instant) -> Option<Instant> {
+        let global = match &self.global {
+            Some(bucket) => bucket.available_at(now)?,
+            None => now,
+        };
+        let agent = match self.agents.get(agent_id) {
+            Some(bucket) => bucket.available_at(now)?,
+            None => now,
+        };
+        Some(global.max(agent))
+    }
+
+    pub(crate) fn to_record(&self, now: Now) -> RateLimiterRecord {
+        RateLimiterRecord {
+            global: self
+                .global
+                .as_ref()
+                .map(|bucket| bucket.to_record(now.instant)),
+            default_limit: self.default_limit,
+            agents: self
+                .agents
+                .iter()
+                .map(|(agent_id, bucket)| (*agent_id, bucket.to_record(now.instant)))
+                .collect(),
+            captured_at: Some(now.wall),
+        }
+    }
+
+    pub(crate) fn from_record(record: RateLimiterRecord, now: Now) -> Self {
+        let elapsed = now.since(record.captured_at);
+        let restore = |bucket| Bucket::from_record(bucket, now.instant, elapsed);
+        Self {
+            global: record.global.map(restore),
+            default_limit: record.default_limit,
+            agents: record
+                .agents
+                .into_iter()
+                .map(|(agent_id, bucket)| (agent_id, restore(bucket)))
+                .collect(),
+        }
+    }
+
+    /// Count a dispatch against the global and agent limits
+    pub(crate) fn charge(&mut self, agent_id: AgentId, now: Instant) {
+        if let Some(bucket) = &mut self.global {
+            bucket.charge(now);
+        }
+        let default_limit = self.default_limit;
+        let bucket = match self.agents.entry(agent_id) {
+            Entry::Occupied(entry) => Some(entry.into_mut()),
+            Entry::Vacant(entry) => default_limit.map(|limit| entry.insert(Bucket::new(limit))),
+        };
+        if let Some(bucket) = bucket {
+            bucket.charge(now);
+        }
+    }
+}
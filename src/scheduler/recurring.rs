@@ -0,0 +1,154 @@
+//! Recurring tasks and misfire handling
+//!
+//! A [`RecurringSchedule`] submits a copy of its template task at a fixed
+//! interval or on the ticks of a [`CronExpr`]. Fire times are wall-clock, so
+//! a schedule restored from [`SchedulerState`](super::SchedulerState) after
+//! downtime can tell how many fires it missed and apply its
+//! [`MisfirePolicy`].
+
+use super::{CronExpr, Task, TaskRecord};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+/// Most missed cron ticks replayed by [`MisfirePolicy::FireAllMissed`]
+const MAX_CATCH_UP: u32 = 1024;
+
+/// What to do with fires missed while the runtime was down or overloaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MisfirePolicy {
+    /// Drop missed fires and wait for the next on-time one
+    Skip,
+    /// Fire once now for all missed fires
+    #[default]
+    FireOnceNow,
+    /// Fire once for every missed fire
+    FireAllMissed,
+}
+
+/// When a recurring schedule fires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleTrigger {
+    /// At a fixed interval
+    Every(Duration),
+    /// On each tick of a cron expression, evaluated in UTC
+    Cron(CronExpr),
+}
+
+/// Task submitted at a fixed interval or on a cron schedule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringSchedule {
+    name: String,
+    template: TaskRecord,
+    trigger: ScheduleTrigger,
+    next_fire: Option<SystemTime>,
+    misfire: MisfirePolicy,
+    misfire_threshold: Duration,
+}
+
+impl RecurringSchedule {
+    /// Create a schedule firing every `interval`, first at `first_fire`
+    pub fn new(
+        name: impl Into<String>,
+        task: &Task,
+        interval: Duration,
+        first_fire: SystemTime,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            template: TaskRecord::template(task),
+            trigger: ScheduleTrigger::Every(interval),
+            next_fire: Some(first_fire),
+            misfire: MisfirePolicy::default(),
+            misfire_threshold: Duration::from_secs(1),
+        }
+    }
+
+    /// Create a schedule firing on the ticks of `expr`, first at its next
+    /// tick after `after`
+    pub fn cron(name: impl Into<String>, task: &Task, expr: CronExpr, after: SystemTime) -> Self {
+        Self {
+            name: name.into(),
+            template: TaskRecord::template(task),
+            next_fire: expr.next_after(after),
+            trigger: ScheduleTrigger::Cron(expr),
+            misfire: MisfirePolicy::default(),
+            misfire_threshold: Duration::from_secs(1),
+        }
+    }
+
+    /// Set misfire policy
+    pub fn with_misfire_policy(mut self, policy: MisfirePolicy) -> Self {
+        self.misfire = policy;
+        self
+    }
+
+    /// Set how late a fire may be before it counts as misfired
+    pub fn with_misfire_threshold(mut self, threshold: Duration) -> Self {
+        self.misfire_threshold = threshold;
+        self
+    }
+
+    /// Get name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get trigger
+    pub fn trigger(&self) -> &ScheduleTrigger {
+        &self.trigger
+    }
+
+    /// Get misfire policy
+    pub fn misfire_policy(&self) -> MisfirePolicy {
+        self.misfire
+    }
+
+    /// Get next fire time, `None` once a cron expression has no more ticks
+    pub fn next_fire(&self) -> Option<SystemTime> {
+        self.next_fire
+    }
+
+    /// Advance past `now`, returning how many tasks to submit
+    pub(crate) fn due(&mut self, now: SystemTime) -> u32 {
+        let Some(first) = self.next_fire.filter(|fire| *fire <= now) else {
+            return 0;
+        };
+        let (missed, latest) = match &self.trigger {
+            ScheduleTrigger::Every(interval) => {
+                let interval = (*interval).max(Duration::from_millis(1));
+                let late = now.duration_since(first).unwrap_or_default();
+                let missed = (late.as_nanos() / interval.as_nanos()) as u32;
+                self.next_fire = Some(first + interval * (missed + 1));
+                (missed, first + interval * missed)
+            }
+            ScheduleTrigger::Cron(expr) => {
+                let (mut missed, mut latest) = (0, first);
+                self.next_fire = expr.next_after(first);
+                while let Some(tick) = self.next_fire.filter(|tick| *tick <= now) {
+                    if missed == MAX_CATCH_UP {
+                        self.next_fire = expr.next_after(now);
+                        break;
+                    }
+                    (missed, latest) = (missed + 1, tick);
+                    self.next_fire = expr.next_after(tick);
+                }
+                (missed, latest)
+            }
+        };
+        let late = |fire: SystemTime| now.duration_since(fire).unwrap_or_default();
+
+        if missed == 0 && late(first) <= self.misfire_threshold {
+            return 1;
+        }
+        match self.misfire {
+            MisfirePolicy::Skip => u32::from(late(latest) <= self.misfire_threshold),
+            MisfirePolicy::FireOnceNow => 1,
+            MisfirePolicy::FireAllMissed => missed + 1,
+        }
+    }
+
+    /// Build one task from the template
+    pub(crate) fn task(&self) -> Task {
+        self.template.clone().into_task().renew_id()
+    }
+}
@@ -0,0 +1,254 @@
+//! Task results awaitable by task ID
+//!
+//! [`super::Scheduler::submit`] returns a [`TaskHandle`] that resolves to
+//! the task's [`TaskResult`] once it completes or fails for good. Results are
+//! kept in a bounded cache, so fire-and-forget callers can still look them
+//! up by [`TaskId`] later; results nobody awaits are evicted once the cache
+//! is full or they outlive its time to live.
+
+use super::TaskId;
+use crate::timer::SharedClock;
+use crate::RuntimeError;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::IntoFuture;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Default number of results kept
+pub(crate) const DEFAULT_CAPACITY: usize = 1024;
+
+/// Default time results are kept
+pub(crate) const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// Why a task failed for good
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskFailure {
+    code: String,
+    message: String,
+}
+
+impl TaskFailure {
+    /// Get the [`RuntimeError::code`] of the last error
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Get the message of the last error
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for TaskFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for TaskFailure {}
+
+impl From<&RuntimeError> for TaskFailure {
+    fn from(error: &RuntimeError) -> Self {
+        Self {
+            code: error.code().to_string(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<TaskFailure> for RuntimeError {
+    fn from(failure: TaskFailure) -> Self {
+        RuntimeError::ExecutionFailed(failure.message)
+    }
+}
+
+/// Output of a completed task, or why it failed
+pub type TaskResult = Result<Vec<u8>, TaskFailure>;
+
+#[derive(Debug, Default)]
+struct Results {
+    results: HashMap<TaskId, (TaskResult, Instant)>,
+    /// Stored tasks, oldest first
+    order: VecDeque<TaskId>,
+    /// Live handles per task; their results are never evicted
+    watched: HashMap<TaskId, usize>,
+    evicted: u64,
+}
+
+/// Bounded cache of task results
+pub(crate) struct ResultStore {
+    results: Mutex<Results>,
+    stored: Notify,
+    capacity: usize,
+    ttl: Duration,
+    clock: SharedClock,
+}
+
+impl ResultStore {
+    pub(crate) fn new(capacity: usize, ttl: Duration, clock: SharedClock) -> Self {
+        Self {
+            results: Mutex::new(Results::default()),
+            stored: Notify::new(),
+            capacity: capacity.max(1),
+            ttl,
+            clock,
+        }
+    }
+
+    /// Create an empty store with the same bounds, timed by another clock
+    pub(crate) fn with_clock(&self, clock: SharedClock) -> Self {
+        Self::new(self.capacity, self.ttl, clock)
+    }
+
+    /// Store a task's result, replacing an earlier one, and wake its handles
+    pub(crate) fn store(&self, id: TaskId, result: TaskResult) {
+        let mut results = self.lock_results();
+        if results
+            .results
+            .insert(id, (result, self.clock.instant()))
+            .is_some()
+        {
+            results.order.retain(|stored| *stored != id);
+        }
+        results.order.push_back(id);
+        self.prune(&mut results);
+        drop(results);
+        self.stored.notify_waiters();
+    }
+
+    /// Get a task's result, if stored and not evicted
+    pub(crate) fn get(&self, id: &TaskId) -> Option<TaskResult> {
+        let mut results = self.lock_results();
+        self.prune(&mut results);
+        results.results.get(id).map(|(result, _)| result.clone())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        let mut results = self.lock_results();
+        self.prune(&mut results);
+        results.results.len()
+    }
+
+    pub(crate) fn evicted(&self) -> u64 {
+        self.lock_results().evicted
+    }
+
+    fn watch(&self, id: TaskId) {
+        *self.lock_results().watched.entry(id).or_default() += 1;
+    }
+
+    fn unwatch(&self, id: &TaskId) {
+        let mut results = self.lock_results();
+        if let Some(handles) = results.watched.get_mut(id) {
+            *handles -= 1;
+            if *handles == 0 {
+                results.watched.remove(id);
+            }
+        }
+    }
+
+    /// Evict expired results, then the oldest while over capacity
+    fn prune(&self, results: &mut Results) {
+        let now = self.clock.instant();
+        let mut excess = results.results.len().saturating_sub(self.capacity);
+        let Results {
+            results,
+            order,
+            watched,
+            evicted,
+        } = results;
+        order.retain(|id| {
+            if watched.contains_key(id) {
+                return true;
+            }
+            let Some((_, stored_at)) = results.get(id) else {
+                return false;
+            };
+            let expired = now.saturating_duration_since(*stored_at) >= self.ttl;
+            if !expired && excess == 0 {
+                return true;
+            }
+            results.remove(id);
+            excess = excess.saturating_sub(1);
+            *evicted += 1;
+            false
+        });
+    }
+
+    fn lock_results(&self) -> MutexGuard<'_, Results> {
+        self.results
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl std::fmt::Debug for ResultStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResultStore")
+            .field("results", &self.lock_results().results.len())
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+/// Handle to await a submitted task's result
+///
+/// Awaiting it resolves once the task completes or fails for good. While a
+/// handle is alive, its task's result is kept in the cache.
+#[derive(Debug)]
+pub struct TaskHandle {
+    id: TaskId,
+    store: Arc<ResultStore>,
+}
+
+impl TaskHandle {
+    pub(crate) fn new(id: TaskId, store: Arc<ResultStore>) -> Self {
+        store.watch(id);
+        Self { id, store }
+    }
+
+    /// Get the task's ID
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Get the result without waiting, if the task finished
+    pub fn try_result(&self) -> Option<TaskResult> {
+        self.store.get(&self.id)
+    }
+}
+
+impl Clone for TaskHandle {
+    fn clone(&self) -> Self {
+        Self::new(self.id, self.store.clone())
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.store.unwatch(&self.id);
+    }
+}
+
+impl IntoFuture for TaskHandle {
+    type Output = TaskResult;
+    type IntoFuture = BoxFuture<'static, TaskResult>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            loop {
+                let notified = self.store.stored.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+                if let Some(result) = self.try_result() {
+                    return result;
+                }
+                notified.await;
+            }
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use crate::timer::{self, SharedClock};
+use agentropic_core::AgentId;
+use agentropic_runtime_core::HealthStatus;
+use futures::future::BoxFuture;
+use futures::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Readiness check used between batches of a rolling restart
+#[derive(Clone)]
+pub struct HealthGate {
+    check: Arc<dyn Fn(AgentId) -> BoxFuture<'static, HealthStatus> + Send + Sync>,
+    timeout: Duration,
+    poll_interval: Duration,
+    max_regressions: usize,
+    clock: SharedClock,
+}
+
+impl HealthGate {
+    /// Create a gate that aborts the rollout on the first instance not healthy within 30 seconds
+    pub fn new<F, Fut>(check: F) -> Self
+    where
+        F: Fn(AgentId) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HealthStatus> + Send + 'static,
+    {
+        Self {
+            check: Arc::new(move |agent_id| Box::pin(check(agent_id))),
+            timeout: Duration::from_secs(30),
+            poll_interval: Duration::from_millis(100),
+            max_regressions: 0,
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Set how long a restarted instance may take to become healthy
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set interval between readiness checks
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Set how many instances may fail to become healthy before aborting
+    pub fn with_max_regressions(mut self, regressions: usize) -> Self {
+        self.max_regressions = regressions;
+        self
+    }
+
+    /// Time the readiness timeout and polling with `clock`
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get maximum tolerated regressions
+    pub fn max_regressions(&self) -> usize {
+        self.max_regressions
+    }
+
+    /// Wait until an instance is healthy, returning false on timeout
+    pub(crate) async fn wait_ready(&self, agent_id: AgentId) -> bool {
+        let ready = async {
+            loop {
+                if (self.check)(agent_id).await == HealthStatus::Healthy {
+                    return;
+                }
+                self.clock.sleep(self.poll_interval).await;
+            }
+        };
+        tokio::select! {
+            biased;
+            _ = ready => true,
+            _ = self.clock.sleep(self.timeout) => false,
+        }
+    }
+}
+
+/// Outcome of a rolling restart
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RolloutReport {
+    /// Instances restarted, in order
+    pub restarted: Vec<AgentId>,
+    /// Restarted instances that failed to restart or become healthy
+    pub regressed: Vec<AgentId>,
+    /// Whether the rollout stopped before restarting every instance
+    pub aborted: bool,
+}
+
+impl RolloutReport {
+    /// Check if every instance was restarted and became healthy
+    pub fn is_success(&self) -> bool {
+        !self.aborted && self.regressed.is_empty()
+    }
+}
@@ -1,9 +1,13 @@
+use super::policy::PolicyEngine;
+use super::{Task, TaskQueue};
+use agentropic_core::AgentId;
 use std::time::Duration;
 
 /// Round robin scheduler
 pub struct RoundRobinScheduler {
     time_slice: Duration,
     current_index: usize,
+    last: Option<AgentId>,
 }
 
 impl RoundRobinScheduler {
@@ -12,6 +16,7 @@ impl RoundRobinScheduler {
         Self {
             time_slice,
             current_index: 0,
+            last: None,
         }
     }
 
@@ -33,6 +38,22 @@ impl RoundRobinScheduler {
     /// Reset index
     pub fn reset(&mut self) {
         self.current_index = 0;
+        self.last = None;
+    }
+}
+
+/// Oldest task of an agent other than the one served last, so agents with
+/// many queued tasks take turns with the rest
+impl PolicyEngine for RoundRobinScheduler {
+    fn next(&mut self, queue: &mut TaskQueue) -> Option<Task> {
+        let index = queue
+            .iter()
+            .position(|task| Some(task.agent_id()) != self.last.as_ref())
+            .unwrap_or(0);
+        let task = queue.remove(index)?;
+        self.last = Some(*task.agent_id());
+        self.current_index += 1;
+        Some(task)
     }
 }
 
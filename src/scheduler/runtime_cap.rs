@@ -0,0 +1,189 @@
+use super::state::Now;
+use super::RuntimeRecord;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// What happens to tasks of an agent over its runtime cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapAction {
+    /// Hold tasks until the next window
+    Defer,
+    /// Fail tasks, dead-lettering them with
+    /// [`DeadLetterReason::RuntimeCapExceeded`](super::DeadLetterReason::RuntimeCapExceeded)
+    Reject,
+}
+
+/// Cap on an agent's cumulative execution time per window
+///
+/// For example, `RuntimeCap::new(Duration::from_secs(600), Duration::from_secs(3600))`
+/// allows 10 CPU-minutes per hour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeCap {
+    limit: Duration,
+    window: Duration,
+    action: CapAction,
+}
+
+impl RuntimeCap {
+    /// Create a cap that defers tasks once exceeded
+    pub fn new(limit: Duration, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            action: CapAction::Defer,
+        }
+    }
+
+    /// Reject tasks instead of deferring them
+    pub fn reject(mut self) -> Self {
+        self.action = CapAction::Reject;
+        self
+    }
+
+    /// Get execution time allowed per window
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    /// Get window length
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Get action taken once the cap is reached
+    pub fn action(&self) -> CapAction {
+        self.action
+    }
+}
+
+/// Execution time used by an agent in its current window
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RuntimeWindow {
+    started: Instant,
+    used: Duration,
+    total: Duration,
+    rejected: u64,
+}
+
+impl RuntimeWindow {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            used: Duration::ZERO,
+            total: Duration::ZERO,
+            rejected: 0,
+        }
+    }
+
+    /// Start a new window if the current one has ended
+    fn roll(&mut self, window: Duration, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= window {
+            // Align to window boundaries so idle periods do not shift them
+            let windows = elapsed.as_nanos() / window.as_nanos().max(1);
+            self.started += window.saturating_mul(windows.min(u32::MAX as u128) as u32);
+            self.used = Duration::ZERO;
+        }
+    }
+
+    pub(crate) fn record(&mut self, runtime: Duration, window: Option<Duration>, now: Instant) {
+        if let Some(window) = window {
+            self.roll(window, now);
+        }
+        self.used += runtime;
+        self.total += runtime;
+    }
+
+    pub(crate) fn record_rejected(&mut self) {
+        self.rejected += 1;
+    }
+
+    /// Execution time used in the window that is current now
+    pub(crate) fn used(&self, window: Option<Duration>, now: Instant) -> Duration {
+        match window {
+            Some(window) if now.saturating_duration_since(self.started) >= window => Duration::ZERO,
+            _ => self.used,
+        }
+    }
+
+    pub(crate) fn total(&self) -> Duration {
+        self.total
+    }
+
+    pub(crate) fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    pub(crate) fn to_record(self, now: Now) -> RuntimeRecord {
+        RuntimeRecord {
+            window_elapsed: now.instant.saturating_duration_since(self.started),
+            window_runtime: self.used,
+            total_runtime: self.total,
+            rejected: self.rejected,
+            captured_at: Some(now.wall),
+        }
+    }
+
+    pub(crate) fn from_record(record: RuntimeRecord, now: Now) -> Self {
+        let elapsed = record.window_elapsed + now.since(record.captured_at);
+        Self {
+            started: now.instant.checked_sub(elapsed).unwrap_or(now.instant),
+            used: record.window_runtime,
+            total: record.total_runtime,
+            rejected: record.rejected,
+        }
+    }
+
+    pub(crate) fn resets_at(&self, window: Duration, now: Instant) -> Instant {
+        let mut rolled = *self;
+        rolled.roll(window, now);
+        rolled.started + window
+    }
+}
+
+/// WebAssembly fuel burned by an agent in its current fuel budget window
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FuelWindow {
+    started: Instant,
+    burned: u64,
+}
+
+impl FuelWindow {
+    pub(crate) fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            burned: 0,
+        }
+    }
+
+    /// Start a new window if the current one has ended
+    fn roll(&mut self, window: Duration, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= window {
+            let windows = elapsed.as_nanos() / window.as_nanos().max(1);
+            self.started += window.saturating_mul(windows.min(u32::MAX as u128) as u32);
+            self.burned = 0;
+        }
+    }
+
+    pub(crate) fn record(&mut self, fuel: u64, window: Option<Duration>, now: Instant) {
+        if let Some(window) = window {
+            self.roll(window, now);
+        }
+        self.burned = self.burned.saturating_add(fuel);
+    }
+
+    /// Fuel burned in the window that is current now
+    pub(crate) fn burned(&self, window: Option<Duration>, now: Instant) -> u64 {
+        match window {
+            Some(window) if now.saturating_duration_since(self.started) >= window => 0,
+            _ => self.burned,
+        }
+    }
+
+    pub(crate) fn resets_at(&self, window: Duration, now: Instant) -> Instant {
+        let mut rolled = *self;
+        rolled.roll(window, now);
+        rolled.started + window
+    }
+}
@@ -0,0 +1,168 @@
+use super::{PolicyType, SchedulingPolicy, Task};
+use agentropic_core::AgentId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Task as seen by the shadow policy
+#[derive(Debug, Clone)]
+struct ShadowEntry {
+    agent_id: AgentId,
+    priority: u32,
+    deadline: Option<Instant>,
+    created_at: Instant,
+}
+
+/// Dispatch the shadow policy would have made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowDispatch {
+    /// Agent the shadow policy picked
+    pub agent_id: AgentId,
+    /// Priority of the picked task
+    pub priority: u32,
+    /// How long the picked task had waited
+    pub wait: Duration,
+    /// Whether the active policy picked a task for the same agent and priority
+    pub agrees: bool,
+}
+
+/// Comparison of the active and shadow policies
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShadowReport {
+    /// Dispatches compared
+    pub dispatches: u64,
+    /// Fraction of dispatches where both policies picked alike
+    pub agreement: f64,
+    /// Mean queue wait under the active policy
+    pub active_mean_wait: Duration,
+    /// Mean queue wait under the shadow policy
+    pub shadow_mean_wait: Duration,
+    /// Dispatches per agent under the active policy
+    pub active_per_agent: HashMap<AgentId, u64>,
+    /// Dispatches per agent under the shadow policy
+    pub shadow_per_agent: HashMap<AgentId, u64>,
+}
+
+/// Candidate policy evaluated on the live submission stream without dispatching
+#[derive(Debug)]
+pub struct ShadowScheduler {
+    policy: SchedulingPolicy,
+    pending: Vec<ShadowEntry>,
+    dispatched: Vec<ShadowDispatch>,
+    last_agent: Option<AgentId>,
+    report: ShadowReport,
+    active_wait: Duration,
+    shadow_wait: Duration,
+    agreed: u64,
+}
+
+impl ShadowScheduler {
+    /// Create a shadow running `policy`
+    pub fn new(policy: SchedulingPolicy) -> Self {
+        Self {
+            policy,
+            pending: Vec::new(),
+            dispatched: Vec::new(),
+            last_agent: None,
+            report: ShadowReport::default(),
+            active_wait: Duration::ZERO,
+            shadow_wait: Duration::ZERO,
+            agreed: 0,
+        }
+    }
+
+    /// Get shadow policy
+    pub fn policy(&self) -> &SchedulingPolicy {
+        &self.policy
+    }
+
+    /// Get dispatches the shadow policy would have made, oldest first
+    pub fn dispatched(&self) -> &[ShadowDispatch] {
+        &self.dispatched
+    }
+
+    /// Get comparison with the active policy so far
+    pub fn report(&self) -> &ShadowReport {
+        &self.report
+    }
+
+    /// Observe a submitted task
+    pub(crate) fn observe(&mut self, task: &Task) {
+        self.pending.push(ShadowEntry {
+            agent_id: *task.agent_id(),
+            priority: task.priority(),
+            deadline: task.deadline(),
+            created_at: task.created_at(),
+        });
+    }
+
+    /// Make the shadow's dispatch decision alongside an active dispatch of `active`
+    pub(crate) fn dispatch(&mut self, active: &Task) {
+        let Some(index) = self.select() else {
+            return;
+        };
+        let entry = self.pending.remove(index);
+        let wait = entry.created_at.elapsed();
+        let agrees = entry.agent_id == *active.agent_id() && entry.priority == active.priority();
+        self.last_agent = Some(entry.agent_id);
+
+        self.active_wait += active.created_at().elapsed();
+        self.shadow_wait += wait;
+        self.agreed += u64::from(agrees);
+        let report = &mut self.report;
+        report.dispatches += 1;
+        report.agreement = self.agreed as f64 / report.dispatches as f64;
+        report.active_mean_wait = self.active_wait / report.dispatches as u32;
+        report.shadow_mean_wait = self.shadow_wait / report.dispatches as u32;
+        *report
+            .active_per_agent
+            .entry(*active.agent_id())
+            .or_default() += 1;
+        *report.shadow_per_agent.entry(entry.agent_id).or_default() += 1;
+
+        self.dispatched.push(ShadowDispatch {
+            agent_id: entry.agent_id,
+            priority: entry.priority,
+            wait,
+            agrees,
+        });
+    }
+
+    /// Pick the pending entry the shadow policy would dispatch next
+    fn select(&self) -> Option<usize> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let indices = 0..self.pending.len();
+        match self.policy.policy_type() {
+            PolicyType::FCFS => Some(0),
+            // Highest priority, oldest first among equals
+            PolicyType::Priority => indices
+                .rev()
+                .max_by_key(|index| self.pending[*index].priority),
+            // First agent after the last one served, in submission order
+            PolicyType::RoundRobin => {
+                let after_last = self.last_agent.and_then(|last| {
+                    let position = self.pending.iter().position(|e| e.agent_id == last)?;
+                    self.pending[position..]
+                        .iter()
+                        .position(|e| e.agent_id != last)
+                        .map(|offset| position + offset)
+                });
+                after_last.or(Some(0))
+            }
+            // Earliest deadline, oldest first among equals
+            PolicyType::EarliestDeadlineFirst => indices.min_by_key(|index| {
+                let deadline = self.pending[*index].deadline;
+                (deadline.is_none(), deadline)
+            }),
+            // Agent with the fewest shadow dispatches so far
+            PolicyType::FairShare => indices.min_by_key(|index| {
+                self.report
+                    .shadow_per_agent
+                    .get(&self.pending[*index].agent_id)
+                    .copied()
+                    .unwrap_or_default()
+            }),
+        }
+    }
+}
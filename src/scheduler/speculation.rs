@@ -0,0 +1,141 @@
+//! Speculative execution of latency-critical tasks
+//!
+//! A task declared with [`Task::with_speculation`] is dispatched to its agent
+//! and to dispatchable pool siblings at once. The first replica to succeed
+//! settles the task; the others are cancelled through their shared token and
+//! their results, including late successes, are discarded. The task is only
+//! retried or dead-lettered once every replica failed.
+
+use super::{Task, TaskId};
+use crate::{RuntimeError, SchedulerError};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tokio_util::sync::CancellationToken;
+
+/// Speculative execution counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpeculationStats {
+    /// Tasks dispatched with more than one replica
+    pub launched: u64,
+    /// Extra replicas dispatched beyond the first
+    pub replicas: u64,
+    /// Replicas cancelled because another one succeeded first
+    pub cancelled: u64,
+    /// Replica results discarded, such as late successes
+    pub discarded: u64,
+}
+
+#[derive(Debug)]
+struct Speculation {
+    /// Replicas dispatched or about to be, whose result is outstanding
+    outstanding: usize,
+    won: bool,
+    token: CancellationToken,
+}
+
+/// What to do with a replica's result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReplicaOutcome {
+    /// Settle the task with this result
+    Settle,
+    /// Drop the result; another replica settles the task
+    Discard,
+}
+
+/// Running speculations and replicas waiting to be dispatched
+#[derive(Debug, Default)]
+pub(crate) struct Speculations {
+    active: HashMap<TaskId, Speculation>,
+    replicas: VecDeque<Task>,
+    stats: SpeculationStats,
+}
+
+impl Speculations {
+    /// Check if a task's replicas were already launched
+    pub(crate) fn is_active(&self, id: &TaskId) -> bool {
+        self.active.contains_key(id)
+    }
+
+    /// Start speculating on a dispatched task with copies for other agents
+    pub(crate) fn launch(&mut self, task: &Task, replicas: Vec<Task>) {
+        if replicas.is_empty() {
+            return;
+        }
+        tracing::debug!(
+            "Running task {} speculatively on {} agents",
+            task.id(),
+            replicas.len() + 1
+        );
+        self.stats.launched += 1;
+        self.stats.replicas += replicas.len() as u64;
+        self.active.insert(
+            task.id(),
+            Speculation {
+                outstanding: replicas.len() + 1,
+                won: false,
+                token: CancellationToken::new(),
+            },
+        );
+        self.replicas.extend(replicas);
+    }
+
+    /// Take the next replica to dispatch, skipping those already beaten
+    pub(crate) fn take_replica(&mut self) -> Option<Task> {
+        while let Some(replica) = self.replicas.pop_front() {
+            if self.active.get(&replica.id()).is_some_and(|spec| !spec.won) {
+                return Some(replica);
+            }
+            self.stats.cancelled += 1;
+            self.forget(replica.id());
+        }
+        None
+    }
+
+    /// Get the token cancelling a task's replicas once one of them won
+    pub(crate) fn token(&self, id: &TaskId) -> Option<CancellationToken> {
+        self.active.get(id).map(|spec| spec.token.clone())
+    }
+
+    /// Record a replica's result, deciding whether it settles the task
+    pub(crate) fn finish(
+        &mut self,
+        id: TaskId,
+        result: &Result<(), RuntimeError>,
+    ) -> ReplicaOutcome {
+        let Some(spec) = self.active.get_mut(&id) else {
+            return ReplicaOutcome::Settle;
+        };
+        let last = spec.outstanding <= 1;
+        let outcome = match result {
+            Ok(()) if !spec.won => {
+                spec.won = true;
+                spec.token.cancel();
+                ReplicaOutcome::Settle
+            }
+            Err(RuntimeError::Scheduler(SchedulerError::SpeculationLost)) => {
+                self.stats.cancelled += 1;
+                ReplicaOutcome::Discard
+            }
+            Err(_) if last && !spec.won => ReplicaOutcome::Settle,
+            _ => {
+                self.stats.discarded += 1;
+                ReplicaOutcome::Discard
+            }
+        };
+        self.forget(id);
+        outcome
+    }
+
+    fn forget(&mut self, id: TaskId) {
+        if let Some(spec) = self.active.get_mut(&id) {
+            spec.outstanding = spec.outstanding.saturating_sub(1);
+            if spec.outstanding == 0 {
+                self.active.remove(&id);
+            }
+        }
+    }
+
+    pub(crate) fn stats(&self) -> SpeculationStats {
+        self.stats
+    }
+}
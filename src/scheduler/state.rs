@@ -0,0 +1,380 @@
+//! Scheduler state for warm starts
+//!
+//! [`SchedulerState`] captures queued and in-flight tasks, per-agent
+//! runtime accounting, fair-share consumption, rate limiter usage, and
+//! recurring schedules. Restoring it with `Scheduler::warm_start` after a
+//! crash keeps queued work and fairness instead of starting from zero. A
+//! [`SchedulerSnapshot`] checkpoints the rest of the scheduler along with it.
+//!
+//! Records note the wall-clock time they were captured at. Times within a
+//! record are relative to that moment, and restoring a record ages them by
+//! the wall-clock time since, so deadlines, waits, and windows keep running
+//! while the runtime is down.
+
+use super::rate_limit::RateLimiterRecord;
+use super::{
+    CostBudget, CpuBudget, DeadLetter, DeadLetterReason, Deployment, FairShareScheduler,
+    OverrunAction, ProviderDemand, ProviderLimits, RecurringSchedule, RuntimeCap, SchedulingPolicy,
+    Task, TaskId,
+};
+use crate::config::EngineTuning;
+use crate::resources::ResourceQuota;
+use crate::timer::RuntimeClock;
+use crate::SizeLimit;
+use agentropic_core::AgentId;
+use agentropic_runtime_core::HealthStatus;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Reading of a clock, pairing its monotonic and wall-clock time
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Now {
+    pub(crate) instant: Instant,
+    pub(crate) wall: SystemTime,
+}
+
+impl Now {
+    /// Read the system clock
+    pub(crate) fn system() -> Self {
+        Self {
+            instant: Instant::now(),
+            wall: SystemTime::now(),
+        }
+    }
+
+    /// Read a runtime clock
+    pub(crate) fn of(clock: &dyn RuntimeClock) -> Self {
+        Self {
+            instant: clock.instant(),
+            wall: clock.wall_time(),
+        }
+    }
+
+    /// Get wall-clock time elapsed since a record was captured at `then`
+    ///
+    /// Records without a capture time, such as task templates, are taken as
+    /// captured just now.
+    pub(crate) fn since(&self, then: Option<SystemTime>) -> Duration {
+        then.and_then(|then| self.wall.duration_since(then).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Serializable form of a [`Task`]
+///
+/// Instants are stored relative to the wall-clock time of capture.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskRecord {
+    #[serde(default)]
+    pub(crate) id: Option<TaskId>,
+    #[serde(default)]
+    pub(crate) after: Vec<TaskId>,
+    pub(crate) agent_id: AgentId,
+    pub(crate) priority: u32,
+    pub(crate) max_retries: u32,
+    pub(crate) attempts: u32,
+    pub(crate) waited: Duration,
+    pub(crate) deadline_in: Option<Duration>,
+    pub(crate) expected_runtime: Duration,
+    #[serde(default)]
+    pub(crate) max_runtime: Option<Duration>,
+    #[serde(default)]
+    pub(crate) overrun: OverrunAction,
+    pub(crate) cpu_budget: Option<CpuBudget>,
+    pub(crate) payload: Vec<u8>,
+    #[serde(default)]
+    pub(crate) version_req: Option<VersionReq>,
+    #[serde(default)]
+    pub(crate) provider: Option<ProviderDemand>,
+    #[serde(default)]
+    pub(crate) affinity: Option<String>,
+    #[serde(default)]
+    pub(crate) conversation: Option<String>,
+    #[serde(default)]
+    pub(crate) replicas: u32,
+    #[serde(default)]
+    pub(crate) idempotency_key: Option<String>,
+    #[serde(default)]
+    pub(crate) truncated: bool,
+    #[serde(default)]
+    pub(crate) captured_at: Option<SystemTime>,
+}
+
+impl TaskRecord {
+    /// Capture a task, measuring its wait and deadline by the system clock
+    pub fn new(task: &Task) -> Self {
+        Self::at(task, Now::system())
+    }
+
+    /// Capture a task, measuring its wait and deadline from `now`
+    pub(crate) fn at(task: &Task, now: Now) -> Self {
+        let mut record = task.to_record(now.instant);
+        record.captured_at = Some(now.wall);
+        record
+    }
+
+    /// Capture a task as a template for later runs, whose wait and deadline
+    /// start over each time it is rebuilt
+    pub(crate) fn template(task: &Task) -> Self {
+        task.to_record(Instant::now())
+    }
+
+    /// Rebuild the task, dating its creation back by the recorded wait and
+    /// the time since capture by the system clock
+    pub fn into_task(self) -> Task {
+        self.into_task_at(Now::system())
+    }
+
+    /// Rebuild the task as of `now`
+    pub(crate) fn into_task_at(mut self, now: Now) -> Task {
+        let elapsed = now.since(self.captured_at.take());
+        self.waited += elapsed;
+        self.deadline_in = self
+            .deadline_in
+            .map(|remaining| remaining.saturating_sub(elapsed));
+        Task::from_record(self, now.instant)
+    }
+
+    /// Get agent ID
+    pub fn agent_id(&self) -> &AgentId {
+        &self.agent_id
+    }
+}
+
+/// Runtime accounting for one agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeRecord {
+    pub(crate) window_elapsed: Duration,
+    pub(crate) window_runtime: Duration,
+    pub(crate) total_runtime: Duration,
+    pub(crate) rejected: u64,
+    #[serde(default)]
+    pub(crate) captured_at: Option<SystemTime>,
+}
+
+/// Cost and tokens spent by one agent in its current budget window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostRecord {
+    pub(crate) window_elapsed: Duration,
+    pub(crate) spent: u64,
+    #[serde(default)]
+    pub(crate) tokens: u64,
+    pub(crate) captured_at: Option<SystemTime>,
+}
+
+/// Limits of one provider and the requests counted against them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderRecord {
+    pub(crate) limits: ProviderLimits,
+    pub(crate) dispatched_ago: Vec<(Duration, u64)>,
+    pub(crate) captured_at: Option<SystemTime>,
+}
+
+/// Snapshot of scheduler state
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchedulerState {
+    /// Tasks waiting in the queue, held for their agent, or waiting for
+    /// their dependencies or conversation
+    pub queued: Vec<TaskRecord>,
+    /// Tasks dispatched but not yet finished
+    pub in_flight: Vec<TaskRecord>,
+    /// Per-agent runtime accounting
+    pub runtime: Vec<(AgentId, RuntimeRecord)>,
+    /// Recurring schedules with their next fire times
+    #[serde(default)]
+    pub schedules: Vec<RecurringSchedule>,
+    /// Fair-share scheduler with the slots each group and agent consumed
+    #[serde(default)]
+    pub fair_share: Option<FairShareScheduler>,
+    /// Rate limits with the dispatches counted against them
+    #[serde(default)]
+    pub rate_limiter: RateLimiterRecord,
+    /// Suspended agents with their parked tasks, in submission order
+    #[serde(default)]
+    pub parked: Vec<(AgentId, Vec<TaskRecord>)>,
+    /// Task groups waiting to be dispatched together, by name
+    #[serde(default)]
+    pub groups: Vec<(String, Vec<TaskRecord>)>,
+    /// Members of released task groups not dispatched yet
+    #[serde(default)]
+    pub released: Vec<TaskRecord>,
+}
+
+impl SchedulerState {
+    /// Record a dispatched task that has not finished, so it is re-queued on warm start
+    pub fn add_in_flight(&mut self, task: &Task) {
+        self.in_flight.push(TaskRecord::new(task));
+    }
+
+    /// Write the state as JSON, replacing the file atomically
+    #[cfg(feature = "persistence")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), crate::RuntimeError> {
+        save_json(path.as_ref(), self, "scheduler state", scheduler_error)
+    }
+
+    /// Read state written by [`SchedulerState::save`], or `None` if the file does not exist
+    #[cfg(feature = "persistence")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Option<Self>, crate::RuntimeError> {
+        load_json(path.as_ref(), "scheduler state", scheduler_error)
+    }
+}
+
+/// Serializable form of a [`DeadLetter`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetterRecord {
+    pub(crate) id: u64,
+    pub(crate) task: TaskRecord,
+    pub(crate) reason: DeadLetterReason,
+    pub(crate) error: Option<String>,
+    pub(crate) dead_at: SystemTime,
+}
+
+impl DeadLetterRecord {
+    /// Capture a dead letter by the system clock
+    pub fn new(entry: &DeadLetter) -> Self {
+        Self::at(entry, Now::system())
+    }
+
+    /// Capture a dead letter as of `now`
+    pub(crate) fn at(entry: &DeadLetter, now: Now) -> Self {
+        Self {
+            id: entry.id,
+            task: TaskRecord::at(&entry.task, now),
+            reason: entry.reason,
+            error: entry.error.clone(),
+            dead_at: entry.dead_at,
+        }
+    }
+
+    /// Rebuild the dead letter by the system clock
+    pub fn into_dead_letter(self) -> DeadLetter {
+        self.into_dead_letter_at(Now::system())
+    }
+
+    /// Rebuild the dead letter as of `now`
+    pub(crate) fn into_dead_letter_at(self, now: Now) -> DeadLetter {
+        DeadLetter {
+            id: self.id,
+            task: self.task.into_task_at(now),
+            reason: self.reason,
+            error: self.error,
+            dead_at: self.dead_at,
+        }
+    }
+}
+
+/// Checkpoint of a whole scheduler, taken by `Scheduler::snapshot`
+///
+/// Besides the [`SchedulerState`] used for warm starts, it keeps the
+/// policy, limits, budgets and what was spent of them, agent health and
+/// cordons, deployments, engine tuning, and dead letters, so
+/// `Scheduler::restore_from` rebuilds an equivalent scheduler after a crash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerSnapshot {
+    /// Scheduling policy
+    pub policy: SchedulingPolicy,
+    /// Queued and in-flight tasks, runtime accounting, and schedules
+    pub state: SchedulerState,
+    /// Per-agent resource quotas
+    #[serde(default)]
+    pub resource_quotas: Vec<(AgentId, ResourceQuota)>,
+    /// Dead-lettered tasks, oldest first
+    #[serde(default)]
+    pub dead_letters: Vec<DeadLetterRecord>,
+    /// Dead letters kept at most
+    pub dead_letter_capacity: usize,
+    /// Per-agent runtime caps
+    #[serde(default)]
+    pub caps: Vec<(AgentId, RuntimeCap)>,
+    /// Rate-limited providers with their usage, by name
+    #[serde(default)]
+    pub providers: Vec<(String, ProviderRecord)>,
+    /// Per-agent cost budgets
+    #[serde(default)]
+    pub cost_budgets: Vec<(AgentId, CostBudget)>,
+    /// Cost each agent spent in its current window
+    #[serde(default)]
+    pub costs: Vec<(AgentId, CostRecord)>,
+    /// Agents whose budget breaker is tripped
+    #[serde(default)]
+    pub tripped: Vec<AgentId>,
+    /// Last known health of agents
+    #[serde(default)]
+    pub health: Vec<(AgentId, HealthStatus)>,
+    /// Agents that have not passed a readiness check
+    #[serde(default)]
+    pub not_ready: Vec<AgentId>,
+    /// Cordoned agents
+    #[serde(default)]
+    pub cordoned: Vec<AgentId>,
+    /// Blue-green deployments with their outcomes
+    #[serde(default)]
+    pub deployments: Vec<Deployment>,
+    /// Agent versions
+    #[serde(default)]
+    pub versions: Vec<(AgentId, Version)>,
+    /// Engine tuning
+    #[serde(default)]
+    pub tuning: EngineTuning,
+    /// Limit on task payloads, also enforced on the restored tasks
+    #[serde(default)]
+    pub payload_limit: Option<SizeLimit>,
+    /// Limit on task results
+    #[serde(default)]
+    pub result_limit: Option<SizeLimit>,
+    /// Time the snapshot was taken
+    pub taken_at: SystemTime,
+}
+
+impl SchedulerSnapshot {
+    /// Write the snapshot as JSON, replacing the file atomically
+    #[cfg(feature = "persistence")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), crate::RuntimeError> {
+        save_json(path.as_ref(), self, "scheduler snapshot", scheduler_error)
+    }
+
+    /// Read a snapshot written by [`SchedulerSnapshot::save`], or `None` if the file does not exist
+    #[cfg(feature = "persistence")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Option<Self>, crate::RuntimeError> {
+        load_json(path.as_ref(), "scheduler snapshot", scheduler_error)
+    }
+}
+
+#[cfg(feature = "persistence")]
+fn scheduler_error(message: String) -> crate::RuntimeError {
+    crate::SchedulerError::State(message).into()
+}
+
+/// Write `value` as JSON to `path` through a temporary file
+#[cfg(feature = "persistence")]
+pub(crate) fn save_json<T: Serialize>(
+    path: &std::path::Path,
+    value: &T,
+    what: &str,
+    error: fn(String) -> crate::RuntimeError,
+) -> Result<(), crate::RuntimeError> {
+    let json = serde_json::to_vec(value)
+        .map_err(|e| error(format!("failed to encode {}: {}", what, e)))?;
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, json)
+        .and_then(|_| std::fs::rename(&temp, path))
+        .map_err(|e| error(format!("failed to save {}: {}", what, e)))
+}
+
+/// Read JSON written by [`save_json`], or `None` if the file does not exist
+#[cfg(feature = "persistence")]
+pub(crate) fn load_json<T: serde::de::DeserializeOwned>(
+    path: &std::path::Path,
+    what: &str,
+    error: fn(String) -> crate::RuntimeError,
+) -> Result<Option<T>, crate::RuntimeError> {
+    let json = match std::fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(error(format!("failed to load {}: {}", what, e))),
+    };
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| error(format!("failed to decode {}: {}", what, e)))
+}
@@ -1,17 +1,149 @@
+use super::{CpuBudget, PreemptionHandle, ProviderDemand, TaskRecord};
+use crate::{ErrorClass, RuntimeError, SizeLimit};
 use agentropic_core::AgentId;
-use std::collections::VecDeque;
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Source of task IDs, unique within the process
+static NEXT_TASK_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Task identifier, assigned when the task is created
+///
+/// Retries and clones of a task keep its ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    /// Allocate a new ID
+    pub(crate) fn next() -> Self {
+        Self(NEXT_TASK_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Get the numeric ID
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Rebuild an ID from [`TaskId::as_u64`], e.g. one handed to Python
+    #[cfg(feature = "python")]
+    pub(crate) fn from_u64(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl std::fmt::Display for TaskId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "task-{}", self.0)
+    }
+}
+
+/// What happens to a task that runs longer than its maximum run time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverrunAction {
+    /// Cancel the attempt and fail it with `SchedulerError::MaxRuntimeExceeded`,
+    /// retrying under the task's usual retry limit
+    #[default]
+    Fail,
+    /// Cancel the attempt and queue the task again without counting a retry,
+    /// as long as its deadline can still be met
+    Reschedule,
+}
 
 /// Task in the queue
 #[derive(Debug, Clone)]
 pub struct Task {
+    id: TaskId,
+    after: Vec<TaskId>,
     agent_id: AgentId,
     priority: u32,
+    max_retries: u32,
+    attempts: u32,
+    deadline: Option<Instant>,
+    expected_runtime: Duration,
+    max_runtime: Option<Duration>,
+    overrun: OverrunAction,
+    created_at: Instant,
+    dated: bool,
+    cpu_budget: Option<CpuBudget>,
+    payload: Vec<u8>,
+    result: Option<Vec<u8>>,
+    truncated: bool,
+    version_req: Option<VersionReq>,
+    provider: Option<ProviderDemand>,
+    affinity: Option<String>,
+    conversation: Option<String>,
+    replicas: u32,
+    idempotency_key: Option<String>,
+    preemption: Option<PreemptionHandle>,
 }
 
 impl Task {
     /// Create a new task
     pub fn new(agent_id: AgentId, priority: u32) -> Self {
-        Self { agent_id, priority }
+        Self {
+            id: TaskId::next(),
+            after: Vec::new(),
+            agent_id,
+            priority,
+            max_retries: 0,
+            attempts: 0,
+            deadline: None,
+            expected_runtime: Duration::ZERO,
+            max_runtime: None,
+            overrun: OverrunAction::Fail,
+            created_at: Instant::now(),
+            dated: false,
+            cpu_budget: None,
+            payload: Vec::new(),
+            result: None,
+            truncated: false,
+            version_req: None,
+            provider: None,
+            affinity: None,
+            conversation: None,
+            replicas: 1,
+            idempotency_key: None,
+            preemption: None,
+        }
+    }
+
+    /// Run only after the given tasks completed
+    ///
+    /// The scheduler holds the task back while any of them is still queued,
+    /// waiting, or running, and dead-letters it if one of them fails for
+    /// good.
+    pub fn after(mut self, tasks: &[TaskId]) -> Self {
+        for id in tasks {
+            if !self.after.contains(id) {
+                self.after.push(*id);
+            }
+        }
+        self
+    }
+
+    /// Give the task a new ID, as for another run from the same template
+    pub(crate) fn renew_id(mut self) -> Self {
+        self.id = TaskId::next();
+        self
+    }
+
+    /// Get task ID
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Get tasks that must complete first
+    pub fn dependencies(&self) -> &[TaskId] {
+        &self.after
+    }
+
+    /// Set max retries
+    pub fn with_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
     }
 
     /// Get agent ID
@@ -19,54 +151,654 @@ impl Task {
         &self.agent_id
     }
 
+    /// Set deadline
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set expected run time, used to judge whether a deadline can be met
+    pub fn with_expected_runtime(mut self, runtime: Duration) -> Self {
+        self.expected_runtime = runtime;
+        self
+    }
+
+    /// Cancel attempts running longer than `limit`, taking `action` afterwards
+    pub fn with_max_runtime(mut self, limit: Duration, action: OverrunAction) -> Self {
+        self.max_runtime = Some(limit);
+        self.overrun = action;
+        self
+    }
+
+    /// Get maximum run time per attempt
+    pub fn max_runtime(&self) -> Option<Duration> {
+        self.max_runtime
+    }
+
+    /// Get action taken when an attempt exceeds the maximum run time
+    pub fn overrun_action(&self) -> OverrunAction {
+        self.overrun
+    }
+
+    /// Set CPU-time budget
+    pub fn with_cpu_budget(mut self, budget: CpuBudget) -> Self {
+        self.cpu_budget = Some(budget);
+        self
+    }
+
+    /// Get CPU-time budget
+    pub fn cpu_budget(&self) -> Option<CpuBudget> {
+        self.cpu_budget
+    }
+
+    /// Require an agent version matching `req`, e.g. `">=2.1"`
+    pub fn with_version_req(mut self, req: VersionReq) -> Self {
+        self.version_req = Some(req);
+        self
+    }
+
+    /// Get required agent version
+    pub fn version_req(&self) -> Option<&VersionReq> {
+        self.version_req.as_ref()
+    }
+
+    /// Declare usage of a rate-limited provider
+    pub fn with_provider(mut self, demand: ProviderDemand) -> Self {
+        self.provider = Some(demand);
+        self
+    }
+
+    /// Get declared provider usage
+    pub fn provider(&self) -> Option<&ProviderDemand> {
+        self.provider.as_ref()
+    }
+
+    /// Run the task on the same worker as every other task with affinity `key`
+    ///
+    /// The first worker dispatched a task with the key keeps it, for cache or
+    /// session locality, until the key is unpinned or the worker is lost.
+    pub fn with_affinity(mut self, key: impl Into<String>) -> Self {
+        self.affinity = Some(key.into());
+        self
+    }
+
+    /// Get affinity key
+    pub fn affinity(&self) -> Option<&str> {
+        self.affinity.as_deref()
+    }
+
+    /// Run the task after every earlier task of conversation `id`, and
+    /// before every later one
+    ///
+    /// Tasks of one conversation run one at a time in submission order,
+    /// and on one worker unless given an affinity key of their own.
+    pub fn with_conversation(mut self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        self.affinity.get_or_insert_with(|| id.clone());
+        self.conversation = Some(id);
+        self
+    }
+
+    /// Get conversation ID
+    pub fn conversation(&self) -> Option<&str> {
+        self.conversation.as_deref()
+    }
+
+    /// Run up to `replicas` copies of the task at once, on different agents
+    /// of its pool, keeping the first success and cancelling the rest
+    ///
+    /// For latency-critical tasks that must be safe to run more than once.
+    pub fn with_speculation(mut self, replicas: u32) -> Self {
+        self.replicas = replicas.max(1);
+        self
+    }
+
+    /// Get number of copies run speculatively, 1 if not speculative
+    pub fn replicas(&self) -> u32 {
+        self.replicas
+    }
+
+    /// Deduplicate submissions carrying the same key
+    ///
+    /// While the scheduler remembers the key, submitting another task with
+    /// it queues nothing and returns the handle of the first task, so
+    /// redelivered events run once.
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Get idempotency key
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    /// Get the preemption signal of a task dispatched by a preempting scheduler
+    pub fn preemption(&self) -> Option<&PreemptionHandle> {
+        self.preemption.as_ref()
+    }
+
+    /// Attach or detach a preemption signal
+    pub(crate) fn set_preemption(&mut self, preemption: Option<PreemptionHandle>) {
+        self.preemption = preemption;
+    }
+
+    /// Set payload handed to the agent
+    pub fn with_payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Get payload
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Get result produced by the agent, if any
+    pub fn result(&self) -> Option<&[u8]> {
+        self.result.as_deref()
+    }
+
+    /// Check if the payload or result was truncated by a size limit
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Enforce a size limit on the payload
+    pub fn limit_payload(&mut self, limit: &SizeLimit) -> Result<(), RuntimeError> {
+        self.truncated |= limit.apply(&mut self.payload)?;
+        Ok(())
+    }
+
+    /// Store a result, enforcing a size limit on it
+    pub fn set_result(
+        &mut self,
+        mut result: Vec<u8>,
+        limit: Option<&SizeLimit>,
+    ) -> Result<(), RuntimeError> {
+        if let Some(limit) = limit {
+            self.truncated |= limit.apply(&mut result)?;
+        }
+        self.result = Some(result);
+        Ok(())
+    }
+
+    /// Change priority
+    pub(crate) fn set_priority(&mut self, priority: u32) {
+        self.priority = priority;
+    }
+
+    /// Date the task's creation by the scheduler's clock, on its first submission
+    pub(crate) fn date(&mut self, now: Instant) {
+        if !self.dated {
+            self.created_at = now;
+            self.dated = true;
+        }
+    }
+
+    pub(crate) fn to_record(&self, now: Instant) -> TaskRecord {
+        TaskRecord {
+            id: Some(self.id),
+            after: self.after.clone(),
+            agent_id: self.agent_id,
+            priority: self.priority,
+            max_retries: self.max_retries,
+            attempts: self.attempts,
+            waited: now.saturating_duration_since(self.created_at),
+            deadline_in: self
+                .deadline
+                .map(|deadline| deadline.saturating_duration_since(now)),
+            expected_runtime: self.expected_runtime,
+            max_runtime: self.max_runtime,
+            overrun: self.overrun,
+            cpu_budget: self.cpu_budget,
+            payload: self.payload.clone(),
+            version_req: self.version_req.clone(),
+            provider: self.provider.clone(),
+            affinity: self.affinity.clone(),
+            conversation: self.conversation.clone(),
+            replicas: self.replicas,
+            idempotency_key: self.idempotency_key.clone(),
+            truncated: self.truncated,
+            captured_at: None,
+        }
+    }
+
+    pub(crate) fn from_record(record: TaskRecord, now: Instant) -> Self {
+        Self {
+            id: record.id.unwrap_or_else(TaskId::next),
+            after: record.after,
+            agent_id: record.agent_id,
+            priority: record.priority,
+            max_retries: record.max_retries,
+            attempts: record.attempts,
+            deadline: record.deadline_in.map(|remaining| now + remaining),
+            expected_runtime: record.expected_runtime,
+            max_runtime: record.max_runtime,
+            overrun: record.overrun,
+            created_at: now.checked_sub(record.waited).unwrap_or(now),
+            dated: true,
+            cpu_budget: record.cpu_budget,
+            payload: record.payload,
+            result: None,
+            truncated: record.truncated,
+            version_req: record.version_req,
+            provider: record.provider,
+            affinity: record.affinity,
+            conversation: record.conversation,
+            replicas: record.replicas.max(1),
+            idempotency_key: record.idempotency_key,
+            preemption: None,
+        }
+    }
+
+    /// Undo the latest attempt, for attempts cancelled without counting as a retry
+    pub(crate) fn forget_attempt(&mut self) {
+        self.attempts = self.attempts.saturating_sub(1);
+    }
+
+    /// Start counting attempts from zero, such as for a replayed task
+    pub(crate) fn reset_attempts(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// Move the task to another agent
+    pub(crate) fn reassign(&mut self, agent_id: AgentId) {
+        self.agent_id = agent_id;
+    }
+
     /// Get priority
     pub fn priority(&self) -> u32 {
         self.priority
     }
+
+    /// Get max retries
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Get number of execution attempts so far
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Record an execution attempt
+    pub fn record_attempt(&mut self) {
+        self.attempts += 1;
+    }
+
+    /// Get deadline
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Get time the task was created
+    pub fn created_at(&self) -> Instant {
+        self.created_at
+    }
+
+    /// Get expected run time
+    pub fn expected_runtime(&self) -> Duration {
+        self.expected_runtime
+    }
+
+    /// Check if a run started `backoff` after `now` can finish before the deadline
+    pub fn can_meet_deadline_at(&self, backoff: Duration, now: Instant) -> bool {
+        self.deadline
+            .is_none_or(|deadline| now + backoff + self.expected_runtime <= deadline)
+    }
+
+    /// Reclassify a failure as `DeadlineExceeded` if a retry `backoff` after
+    /// `now` cannot finish in time
+    pub fn retry_class_at(
+        &self,
+        class: &ErrorClass,
+        backoff: Duration,
+        now: Instant,
+    ) -> ErrorClass {
+        if self.can_meet_deadline_at(backoff, now) {
+            *class
+        } else {
+            ErrorClass::DeadlineExceeded
+        }
+    }
+
+    /// Check if the task should be retried after a failure of the given
+    /// class, waiting `backoff` from `now`
+    pub fn should_retry_at(&self, class: &ErrorClass, backoff: Duration, now: Instant) -> bool {
+        self.retry_class_at(class, backoff, now).is_retryable() && self.attempts <= self.max_retries
+    }
 }
 
 /// Task queue
-#[derive(Debug)]
-pub struct TaskQueue {
-    tasks: VecDeque<Task>,
+pub type TaskQueue = agentropic_runtime_core::TaskQueue<Task>;
+
+/// Task queue with a fixed capacity and overflow policy
+pub type BoundedTaskQueue = agentropic_runtime_core::BoundedTaskQueue<Task>;
+
+pub use agentropic_runtime_core::{OverflowPolicy, PushOutcome};
+
+/// Durable storage behind a [`PersistentTaskQueue`]
+///
+/// A store is a write-ahead log: every push and acknowledgement is recorded
+/// before the in-memory queue changes, and [`QueueStore::recover`] replays
+/// the log into the tasks still outstanding.
+pub trait QueueStore: Send {
+    /// Durably record a pushed task
+    fn append(&mut self, id: u64, task: &TaskRecord) -> Result<(), RuntimeError>;
+
+    /// Durably record that a task finished and will not be needed again
+    fn remove(&mut self, id: u64) -> Result<(), RuntimeError>;
+
+    /// Load outstanding tasks, in push order
+    fn recover(&mut self) -> Result<Vec<(u64, TaskRecord)>, RuntimeError>;
+
+    /// Rewrite the log to hold only `live` tasks
+    fn compact(&mut self, live: &[(u64, TaskRecord)]) -> Result<(), RuntimeError> {
+        let _ = live;
+        Ok(())
+    }
+}
+
+/// In-memory store, for tests and for queues that need not survive restarts
+#[derive(Debug, Default)]
+pub struct MemoryQueueStore {
+    entries: Vec<(u64, TaskRecord)>,
 }
 
-impl TaskQueue {
-    /// Create a new task queue
+impl MemoryQueueStore {
+    /// Create an empty store
     pub fn new() -> Self {
-        Self {
-            tasks: VecDeque::new(),
+        Self::default()
+    }
+}
+
+impl QueueStore for MemoryQueueStore {
+    fn append(&mut self, id: u64, task: &TaskRecord) -> Result<(), RuntimeError> {
+        self.entries.push((id, task.clone()));
+        Ok(())
+    }
+
+    fn remove(&mut self, id: u64) -> Result<(), RuntimeError> {
+        self.entries.retain(|(existing, _)| *existing != id);
+        Ok(())
+    }
+
+    fn recover(&mut self) -> Result<Vec<(u64, TaskRecord)>, RuntimeError> {
+        Ok(self.entries.clone())
+    }
+}
+
+/// Operation in a [`FileQueueStore`] log
+#[cfg(feature = "persistence")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogEntry {
+    Push { id: u64, task: Box<TaskRecord> },
+    Remove { id: u64 },
+}
+
+/// Store keeping a write-ahead log of JSON lines in a file
+///
+/// Each append is synced to disk before it returns, unless syncing is
+/// disabled. A partially written last line, left by a crash mid-write, is
+/// cut off on recovery; an unreadable line anywhere else fails recovery
+/// rather than silently dropping the tasks after it.
+#[cfg(feature = "persistence")]
+#[derive(Debug)]
+pub struct FileQueueStore {
+    path: std::path::PathBuf,
+    file: std::fs::File,
+    sync: bool,
+}
+
+#[cfg(feature = "persistence")]
+impl FileQueueStore {
+    /// Open or create a log file
+    pub fn open(path: impl Into<std::path::PathBuf>) -> Result<Self, RuntimeError> {
+        let path = path.into();
+        let file = Self::open_log(&path)?;
+        Ok(Self {
+            path,
+            file,
+            sync: true,
+        })
+    }
+
+    /// Skip syncing each write to disk, trading durability for throughput
+    pub fn without_sync(mut self) -> Self {
+        self.sync = false;
+        self
+    }
+
+    /// Get log file path
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    fn open_log(path: &std::path::Path) -> Result<std::fs::File, RuntimeError> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| storage_error("open", e))
+    }
+
+    fn write(&mut self, entry: &LogEntry) -> Result<(), RuntimeError> {
+        use std::io::Write;
+
+        let mut line = serde_json::to_vec(entry).map_err(|e| storage_error("encode", e))?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .map_err(|e| storage_error("write", e))?;
+        if self.sync {
+            self.file
+                .sync_data()
+                .map_err(|e| storage_error("sync", e))?;
         }
+        Ok(())
     }
+}
+
+#[cfg(feature = "persistence")]
+fn storage_error(action: &str, error: impl std::fmt::Display) -> RuntimeError {
+    crate::QueueError::Storage(format!("failed to {} queue log: {}", action, error)).into()
+}
 
-    /// Push a task
-    pub fn push(&mut self, task: Task) {
-        self.tasks.push_back(task);
+#[cfg(feature = "persistence")]
+impl QueueStore for FileQueueStore {
+    fn append(&mut self, id: u64, task: &TaskRecord) -> Result<(), RuntimeError> {
+        self.write(&LogEntry::Push {
+            id,
+            task: Box::new(task.clone()),
+        })
     }
 
-    /// Pop a task
-    pub fn pop(&mut self) -> Option<Task> {
-        self.tasks.pop_front()
+    fn remove(&mut self, id: u64) -> Result<(), RuntimeError> {
+        self.write(&LogEntry::Remove { id })
     }
 
-    /// Check if empty
-    pub fn is_empty(&self) -> bool {
-        self.tasks.is_empty()
+    fn recover(&mut self) -> Result<Vec<(u64, TaskRecord)>, RuntimeError> {
+        let log = std::fs::read(&self.path).map_err(|e| storage_error("read", e))?;
+        let mut live = Vec::new();
+        let mut offset = 0;
+        for (number, line) in log.split_inclusive(|byte| *byte == b'\n').enumerate() {
+            let start = offset;
+            offset += line.len();
+            let entry = line.strip_suffix(b"\n").unwrap_or(line);
+            if entry.is_empty() {
+                continue;
+            }
+            match serde_json::from_slice(entry) {
+                Ok(LogEntry::Push { id, task }) => live.push((id, *task)),
+                Ok(LogEntry::Remove { id }) => live.retain(|(existing, _)| *existing != id),
+                Err(e) if offset == log.len() => {
+                    // Cut it off so the next entry starts on a line of its own
+                    tracing::warn!(
+                        "Cutting torn last entry off queue log {:?}: {}",
+                        self.path,
+                        e
+                    );
+                    self.file
+                        .set_len(start as u64)
+                        .map_err(|e| storage_error("truncate", e))?;
+                }
+                Err(e) => {
+                    return Err(crate::QueueError::Storage(format!(
+                        "queue log {:?} is corrupt at line {}: {}",
+                        self.path,
+                        number + 1,
+                        e
+                    ))
+                    .into());
+                }
+            }
+        }
+        Ok(live)
+    }
+
+    fn compact(&mut self, live: &[(u64, TaskRecord)]) -> Result<(), RuntimeError> {
+        use std::io::Write;
+
+        let temp = self.path.with_extension("compact");
+        let mut contents = Vec::new();
+        for (id, task) in live {
+            let entry = LogEntry::Push {
+                id: *id,
+                task: Box::new(task.clone()),
+            };
+            serde_json::to_writer(&mut contents, &entry).map_err(|e| storage_error("encode", e))?;
+            contents.push(b'\n');
+        }
+        let mut file = std::fs::File::create(&temp).map_err(|e| storage_error("compact", e))?;
+        file.write_all(&contents)
+            .and_then(|_| file.sync_all())
+            .and_then(|_| std::fs::rename(&temp, &self.path))
+            .and_then(|_| sync_parent(&self.path))
+            .map_err(|e| storage_error("compact", e))?;
+        self.file = Self::open_log(&self.path)?;
+        Ok(())
     }
+}
+
+/// Sync the directory holding `path`, so a rename into it survives a crash
+#[cfg(all(feature = "persistence", unix))]
+fn sync_parent(path: &std::path::Path) -> std::io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    std::fs::File::open(parent)?.sync_all()
+}
 
-    /// Get queue length
+/// Directories cannot be opened for syncing on this platform, and renames
+/// are durable once they return
+#[cfg(all(feature = "persistence", not(unix)))]
+fn sync_parent(_path: &std::path::Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Task queue whose tasks survive process restarts
+///
+/// Popped tasks stay in the log until acknowledged with
+/// [`PersistentTaskQueue::ack`], so a crash while a task runs re-queues it on
+/// the next [`PersistentTaskQueue::open`].
+pub struct PersistentTaskQueue<S: QueueStore> {
+    store: S,
+    queue: VecDeque<(u64, Task)>,
+    in_flight: HashMap<u64, Task>,
+    next_id: u64,
+    removed: usize,
+}
+
+impl<S: QueueStore> PersistentTaskQueue<S> {
+    /// Open a queue, recovering outstanding tasks from the store
+    ///
+    /// Tasks that were popped but never acknowledged are queued again.
+    pub fn open(mut store: S) -> Result<Self, RuntimeError> {
+        let recovered = store.recover()?;
+        let next_id = recovered.iter().map(|(id, _)| id + 1).max().unwrap_or(1);
+        store.compact(&recovered)?;
+        Ok(Self {
+            store,
+            queue: recovered
+                .into_iter()
+                .map(|(id, record)| (id, record.into_task()))
+                .collect(),
+            in_flight: HashMap::new(),
+            next_id,
+            removed: 0,
+        })
+    }
+
+    /// Log and queue a task, returning its ID
+    pub fn push(&mut self, task: Task) -> Result<u64, RuntimeError> {
+        let id = self.next_id;
+        self.store.append(id, &TaskRecord::new(&task))?;
+        self.next_id += 1;
+        self.queue.push_back((id, task));
+        Ok(id)
+    }
+
+    /// Take the oldest task, keeping it logged until acknowledged
+    pub fn pop(&mut self) -> Option<(u64, Task)> {
+        let (id, task) = self.queue.pop_front()?;
+        self.in_flight.insert(id, task.clone());
+        Some((id, task))
+    }
+
+    /// Mark a popped task as finished, removing it from the log
+    pub fn ack(&mut self, id: u64) -> Result<(), RuntimeError> {
+        if self.in_flight.remove(&id).is_none() {
+            return Ok(());
+        }
+        self.store.remove(id)?;
+        self.removed += 1;
+        if self.removed > 64 && self.removed > 2 * self.len() {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Return a popped task to the front of the queue
+    pub fn nack(&mut self, id: u64) {
+        if let Some(task) = self.in_flight.remove(&id) {
+            self.queue.push_front((id, task));
+        }
+    }
+
+    /// Rewrite the log to hold only queued and in-flight tasks
+    pub fn compact(&mut self) -> Result<(), RuntimeError> {
+        let mut live: Vec<_> = self
+            .in_flight
+            .iter()
+            .chain(self.queue.iter().map(|(id, task)| (id, task)))
+            .map(|(id, task)| (*id, TaskRecord::new(task)))
+            .collect();
+        live.sort_by_key(|(id, _)| *id);
+        self.store.compact(&live)?;
+        self.removed = 0;
+        Ok(())
+    }
+
+    /// Get number of queued tasks, excluding in-flight ones
     pub fn len(&self) -> usize {
-        self.tasks.len()
+        self.queue.len()
     }
 
-    /// Clear the queue
-    pub fn clear(&mut self) {
-        self.tasks.clear();
+    /// Check if no tasks are queued
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Get number of popped tasks not yet acknowledged
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
     }
-}
 
-impl Default for TaskQueue {
-    fn default() -> Self {
-        Self::new()
+    /// Get the store
+    pub fn store(&self) -> &S {
+        &self.store
     }
 }
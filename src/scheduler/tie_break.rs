@@ -0,0 +1,82 @@
+use super::Task;
+use crate::rng::SplitMix64;
+use agentropic_core::AgentId;
+use std::collections::HashMap;
+
+/// How the scheduler picks among queued tasks of equal priority
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Oldest task first
+    #[default]
+    Fifo,
+    /// Task with the smallest expected run time first, oldest among equals
+    SmallestCostFirst,
+    /// Random task, weighted by its agent's share
+    WeightedLottery,
+}
+
+impl TieBreak {
+    /// Pick one of `candidates`, given as queue index and task
+    pub(crate) fn pick<'a>(
+        &self,
+        candidates: impl Iterator<Item = (usize, &'a Task)>,
+        lottery: &mut Lottery,
+    ) -> Option<usize> {
+        match self {
+            TieBreak::Fifo => candidates.map(|(index, _)| index).next(),
+            TieBreak::SmallestCostFirst => candidates
+                .min_by_key(|(index, task)| (task.expected_runtime(), *index))
+                .map(|(index, _)| index),
+            TieBreak::WeightedLottery => lottery.draw(candidates),
+        }
+    }
+}
+
+/// Weighted random draw over agent shares
+#[derive(Debug)]
+pub(crate) struct Lottery {
+    shares: HashMap<AgentId, f64>,
+    rng: SplitMix64,
+}
+
+impl Lottery {
+    pub(crate) fn new() -> Self {
+        Self {
+            shares: HashMap::new(),
+            rng: SplitMix64::new(),
+        }
+    }
+
+    pub(crate) fn with_seed(seed: u64) -> Self {
+        Self {
+            shares: HashMap::new(),
+            rng: SplitMix64::with_seed(seed),
+        }
+    }
+
+    pub(crate) fn set_share(&mut self, agent_id: AgentId, share: f64) {
+        self.shares.insert(agent_id, share.max(0.0));
+    }
+
+    pub(crate) fn share(&self, agent_id: &AgentId) -> f64 {
+        self.shares.get(agent_id).copied().unwrap_or(1.0)
+    }
+
+    fn draw<'a>(&mut self, candidates: impl Iterator<Item = (usize, &'a Task)>) -> Option<usize> {
+        let weighted: Vec<_> = candidates
+            .map(|(index, task)| (index, self.share(task.agent_id())))
+            .collect();
+        let total: f64 = weighted.iter().map(|(_, share)| share).sum();
+        if total <= 0.0 {
+            return weighted.first().map(|(index, _)| *index);
+        }
+        let mut ticket = self.rng.next_unit() * total;
+        for (index, share) in &weighted {
+            if ticket < *share {
+                return Some(*index);
+            }
+            ticket -= share;
+        }
+        weighted.last().map(|(index, _)| *index)
+    }
+}
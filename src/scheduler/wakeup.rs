@@ -0,0 +1,143 @@
+//! Event-driven dispatch wakeups
+//!
+//! A dispatch loop drains [`super::Scheduler::next_task`] and then waits on
+//! the scheduler's [`Wakeup`] instead of polling. The scheduler signals it
+//! when tasks are pushed, when agents regain capacity, and when tasks
+//! complete; the loop's deadline covers runtime-cap windows and provider
+//! limits that free up with time.
+
+use crate::timer::SharedClock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::Notify;
+
+/// Why a dispatch loop woke up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WakeReason {
+    /// A task was submitted
+    Push,
+    /// An agent became dispatchable again
+    Capacity,
+    /// A task completed
+    Completion,
+    /// A time-based limit expired
+    Timer,
+}
+
+impl WakeReason {
+    const ALL: [WakeReason; 4] = [
+        WakeReason::Push,
+        WakeReason::Capacity,
+        WakeReason::Completion,
+        WakeReason::Timer,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Get the label used in metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WakeReason::Push => "push",
+            WakeReason::Capacity => "capacity",
+            WakeReason::Completion => "completion",
+            WakeReason::Timer => "timer",
+        }
+    }
+}
+
+/// Wakeup counts by reason
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WakeupStats {
+    /// Wakeups after a submission
+    pub push: u64,
+    /// Wakeups after an agent became dispatchable
+    pub capacity: u64,
+    /// Wakeups after a completion
+    pub completion: u64,
+    /// Wakeups after a deadline expired
+    pub timer: u64,
+}
+
+impl WakeupStats {
+    /// Get total wakeups
+    pub fn total(&self) -> u64 {
+        self.push + self.capacity + self.completion + self.timer
+    }
+}
+
+/// Signal waking a dispatch loop when work may have become dispatchable
+///
+/// Signals raised while nobody waits are kept, so a loop never misses work
+/// submitted between draining the queue and waiting.
+#[derive(Debug)]
+pub struct Wakeup {
+    notify: Notify,
+    reason: Mutex<Option<WakeReason>>,
+    counts: [AtomicU64; 4],
+    clock: SharedClock,
+}
+
+impl Wakeup {
+    pub(crate) fn new(clock: SharedClock) -> Self {
+        Self {
+            notify: Notify::new(),
+            reason: Mutex::new(None),
+            counts: Default::default(),
+            clock,
+        }
+    }
+
+    /// Signal the dispatch loop
+    pub fn wake(&self, reason: WakeReason) {
+        self.reason
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert(reason);
+        self.notify.notify_one();
+    }
+
+    /// Wait for a signal, or until the deadline if one is given
+    pub async fn wait(&self, deadline: Option<Instant>) -> WakeReason {
+        let reason = match deadline {
+            Some(deadline) => tokio::select! {
+                _ = self.notify.notified() => self.take_reason(),
+                _ = self.clock.sleep_until(deadline) => WakeReason::Timer,
+            },
+            None => {
+                self.notify.notified().await;
+                self.take_reason()
+            }
+        };
+        self.counts[reason.index()].fetch_add(1, Ordering::Relaxed);
+        reason
+    }
+
+    fn take_reason(&self) -> WakeReason {
+        self.reason
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .unwrap_or(WakeReason::Push)
+    }
+
+    /// Get wakeup counts so far
+    pub fn stats(&self) -> WakeupStats {
+        let count = |reason: WakeReason| self.counts[reason.index()].load(Ordering::Relaxed);
+        WakeupStats {
+            push: count(WakeReason::Push),
+            capacity: count(WakeReason::Capacity),
+            completion: count(WakeReason::Completion),
+            timer: count(WakeReason::Timer),
+        }
+    }
+
+    /// Iterate over wakeup counts by reason
+    pub fn counts(&self) -> impl Iterator<Item = (WakeReason, u64)> + '_ {
+        WakeReason::ALL
+            .into_iter()
+            .map(|reason| (reason, self.counts[reason.index()].load(Ordering::Relaxed)))
+    }
+}
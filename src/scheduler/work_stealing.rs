@@ -0,0 +1,199 @@
+//! Work-stealing task queues
+//!
+//! [`WorkStealingScheduler`] gives every worker its own deque and shares a
+//! global injector queue for submissions. A worker takes from its own deque
+//! first, then refills from the injector in batches, and finally steals a
+//! batch from another worker. Workers only contend when they run dry, so the
+//! queue scales with the worker count instead of serializing on one lock.
+//! Tasks with an affinity key always go to the worker the key hashes to,
+//! and are never stolen.
+
+use super::Task;
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Default number of tasks moved per steal
+const DEFAULT_STEAL_BATCH: usize = 16;
+
+#[derive(Debug)]
+struct Shared {
+    injector: Injector<Task>,
+    /// Per-worker queues of tasks pinned by their affinity key
+    pinned: Vec<Injector<Task>>,
+    stealers: Vec<Stealer<Task>>,
+    steal_batch: AtomicUsize,
+    steals: AtomicU64,
+}
+
+/// Scheduler with per-worker deques and a global injector queue
+#[derive(Debug)]
+pub struct WorkStealingScheduler {
+    shared: Arc<Shared>,
+    workers: Mutex<Vec<Option<Worker<Task>>>>,
+}
+
+impl WorkStealingScheduler {
+    /// Create a scheduler with `workers` local deques
+    pub fn new(workers: usize) -> Self {
+        let locals: Vec<_> = (0..workers.max(1)).map(|_| Worker::new_fifo()).collect();
+        Self {
+            shared: Arc::new(Shared {
+                injector: Injector::new(),
+                pinned: locals.iter().map(|_| Injector::new()).collect(),
+                stealers: locals.iter().map(Worker::stealer).collect(),
+                steal_batch: AtomicUsize::new(DEFAULT_STEAL_BATCH),
+                steals: AtomicU64::new(0),
+            }),
+            workers: Mutex::new(locals.into_iter().map(Some).collect()),
+        }
+    }
+
+    /// Create a scheduler with one worker per CPU
+    pub fn with_cpu_workers() -> Self {
+        Self::new(num_cpus::get())
+    }
+
+    /// Set the maximum number of tasks moved per steal
+    pub fn with_steal_batch(self, batch: usize) -> Self {
+        self.shared
+            .steal_batch
+            .store(batch.max(1), Ordering::Relaxed);
+        self
+    }
+
+    /// Get number of workers
+    pub fn workers(&self) -> usize {
+        self.shared.stealers.len()
+    }
+
+    /// Get the maximum number of tasks moved per steal
+    pub fn steal_batch(&self) -> usize {
+        self.shared.steal_batch.load(Ordering::Relaxed)
+    }
+
+    /// Submit a task to the global injector queue, or to its pinned worker
+    /// if it has an affinity key
+    pub fn submit(&self, task: Task) {
+        match task.affinity().map(|key| self.worker_for(key)) {
+            Some(worker) => self.shared.pinned[worker].push(task),
+            None => self.shared.injector.push(task),
+        }
+    }
+
+    /// Get the index of the worker tasks with an affinity key are pinned to
+    pub fn worker_for(&self, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.shared.pinned.len() as u64) as usize
+    }
+
+    /// Take the queue of a worker, to be moved onto the worker's thread
+    ///
+    /// Each worker queue can be taken once.
+    pub fn take_worker(&self, index: usize) -> Option<WorkerQueue> {
+        let local = self
+            .workers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get_mut(index)?
+            .take()?;
+        Some(WorkerQueue {
+            index,
+            local,
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Get number of queued tasks across the injector and all workers
+    pub fn len(&self) -> usize {
+        self.shared.injector.len()
+            + self.shared.pinned.iter().map(Injector::len).sum::<usize>()
+            + self.shared.stealers.iter().map(Stealer::len).sum::<usize>()
+    }
+
+    /// Check if no tasks are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get number of successful steals from other workers
+    pub fn steals(&self) -> u64 {
+        self.shared.steals.load(Ordering::Relaxed)
+    }
+}
+
+/// Local queue owned by one worker of a [`WorkStealingScheduler`]
+#[derive(Debug)]
+pub struct WorkerQueue {
+    index: usize,
+    local: Worker<Task>,
+    shared: Arc<Shared>,
+}
+
+impl WorkerQueue {
+    /// Get worker index
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Push a task onto this worker's deque, such as one spawned by a running task
+    pub fn push(&self, task: Task) {
+        self.local.push(task);
+    }
+
+    /// Get number of tasks in this worker's deque
+    pub fn len(&self) -> usize {
+        self.local.len()
+    }
+
+    /// Check if this worker's deque is empty
+    pub fn is_empty(&self) -> bool {
+        self.local.is_empty()
+    }
+
+    /// Take the next task: local first, then pinned to this worker, then the
+    /// injector, then other workers
+    pub fn pop(&self) -> Option<Task> {
+        if let Some(task) = self.local.pop() {
+            return Some(task);
+        }
+        if let Some(task) = std::iter::repeat_with(|| self.shared.pinned[self.index].steal())
+            .find(|steal| !steal.is_retry())
+            .and_then(Steal::success)
+        {
+            return Some(task);
+        }
+        let batch = self.shared.steal_batch.load(Ordering::Relaxed);
+        loop {
+            let mut retry = false;
+            match self
+                .shared
+                .injector
+                .steal_batch_with_limit_and_pop(&self.local, batch)
+            {
+                Steal::Success(task) => return Some(task),
+                Steal::Retry => retry = true,
+                Steal::Empty => {}
+            }
+
+            let workers = self.shared.stealers.len();
+            for offset in 1..workers {
+                let victim = &self.shared.stealers[(self.index + offset) % workers];
+                match victim.steal_batch_with_limit_and_pop(&self.local, batch) {
+                    Steal::Success(task) => {
+                        self.shared.steals.fetch_add(1, Ordering::Relaxed);
+                        return Some(task);
+                    }
+                    Steal::Retry => retry = true,
+                    Steal::Empty => {}
+                }
+            }
+            if !retry {
+                return None;
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Deterministic simulation
+//!
+//! A [`SimRuntime`] is a [`Runtime`] whose scheduler and supervisor read a
+//! [`VirtualClock`]. Time only moves when the test calls
+//! [`SimRuntime::advance`], which steps through every sleep deadline on the
+//! way and lets woken tasks run before moving on, so backoff delays, holds,
+//! health checks, and cron schedules play out the same way on every run.
+
+use crate::timer::{RuntimeClock, SharedClock, VirtualClock};
+use crate::{Runtime, RuntimeBuilder};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Times tasks are given to run after each step of the clock
+const SETTLE_YIELDS: usize = 64;
+
+/// Runtime driven by a virtual clock
+pub struct SimRuntime {
+    runtime: Runtime,
+    clock: Arc<VirtualClock>,
+}
+
+impl SimRuntime {
+    /// Build a runtime on a new virtual clock
+    pub fn new(builder: RuntimeBuilder) -> Self {
+        Self::with_clock(builder, Arc::new(VirtualClock::new()))
+    }
+
+    /// Build a runtime on an existing virtual clock, such as one with a fixed epoch
+    pub fn with_clock(builder: RuntimeBuilder, clock: Arc<VirtualClock>) -> Self {
+        Self {
+            runtime: builder.with_clock(clock.clone()).build(),
+            clock,
+        }
+    }
+
+    /// Get the runtime
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Get the virtual clock
+    pub fn clock(&self) -> &Arc<VirtualClock> {
+        &self.clock
+    }
+
+    /// Get the virtual clock as a shared clock, e.g. for health check runners
+    pub fn shared_clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Get elapsed virtual time
+    pub fn elapsed(&self) -> Duration {
+        agentropic_runtime_core::Clock::now(&*self.clock)
+    }
+
+    /// Get the virtual wall-clock time
+    pub fn wall_time(&self) -> SystemTime {
+        self.clock.wall_time()
+    }
+
+    /// Let woken tasks run without moving the clock
+    pub async fn settle(&self) {
+        for _ in 0..SETTLE_YIELDS {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Move the clock forward by `by`
+    ///
+    /// The clock stops at each sleep deadline within reach and lets the
+    /// woken tasks run, so sleeps they start in turn are also honored.
+    pub async fn advance(&self, by: Duration) {
+        let target = self.clock.instant() + by;
+        loop {
+            self.settle().await;
+            let now = self.clock.instant();
+            match self
+                .clock
+                .next_deadline()
+                .filter(|deadline| *deadline <= target)
+            {
+                Some(deadline) => self.clock.advance(deadline.saturating_duration_since(now)),
+                None => {
+                    self.clock.advance(target.saturating_duration_since(now));
+                    break;
+                }
+            }
+        }
+        self.settle().await;
+    }
+}
+
+impl std::fmt::Debug for SimRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimRuntime")
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
@@ -0,0 +1,296 @@
+//! Bulkhead isolation
+//!
+//! A [`Bulkhead`] caps the executions running at once in one isolation
+//! group, such as every call to one external API. Callers over the cap wait
+//! in a bounded queue, highest priority first, for at most the bulkhead's
+//! timeout, so a misbehaving downstream ties up its own group's slots
+//! instead of the whole runtime's concurrency. Agents of one group share a
+//! bulkhead by registering clones of it with `Supervisor::set_bulkhead`.
+
+use crate::timer::{self, SharedClock};
+use crate::{RuntimeError, SupervisorError};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Caller waiting for a bulkhead slot
+struct Waiter {
+    priority: u32,
+    sequence: u64,
+    sender: oneshot::Sender<BulkheadPermit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    // Highest priority first, then first come first served
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+/// Lock bulkhead state, recovering it if a holder panicked
+fn lock(state: &Mutex<BulkheadState>) -> MutexGuard<'_, BulkheadState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct BulkheadState {
+    max_concurrent: usize,
+    in_use: usize,
+    sequence: u64,
+    waiters: BinaryHeap<Waiter>,
+    max_waiting: Option<usize>,
+    timeout: Option<Duration>,
+    rejected: u64,
+    timed_out: u64,
+}
+
+/// Caps concurrent executions, admitting waiters by priority
+#[derive(Clone)]
+pub struct Bulkhead {
+    group: Arc<str>,
+    state: Arc<Mutex<BulkheadState>>,
+    clock: SharedClock,
+}
+
+impl Bulkhead {
+    /// Create a new bulkhead
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            group: Arc::from("default"),
+            state: Arc::new(Mutex::new(BulkheadState {
+                max_concurrent: max_concurrent.max(1),
+                in_use: 0,
+                sequence: 0,
+                waiters: BinaryHeap::new(),
+                max_waiting: None,
+                timeout: None,
+                rejected: 0,
+                timed_out: 0,
+            })),
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Name the isolation group, as reported in errors
+    pub fn with_group(mut self, group: impl Into<String>) -> Self {
+        self.group = Arc::from(group.into());
+        self
+    }
+
+    /// Reject callers with [`SupervisorError::BulkheadFull`] once `max`
+    /// callers are waiting
+    pub fn with_max_waiting(self, max: usize) -> Self {
+        self.lock_state().max_waiting = Some(max);
+        self
+    }
+
+    /// Give up waiting with [`SupervisorError::BulkheadTimeout`] after `timeout`
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.lock_state().timeout = Some(timeout);
+        self
+    }
+
+    /// Time waits with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, BulkheadState> {
+        lock(&self.state)
+    }
+
+    /// Get the isolation group
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Acquire a slot if one is free
+    pub fn try_acquire(&self) -> Option<BulkheadPermit> {
+        let mut state = self.lock_state();
+        if state.in_use < state.max_concurrent {
+            state.in_use += 1;
+            Some(self.permit())
+        } else {
+            None
+        }
+    }
+
+    /// Acquire a slot, waiting behind higher-priority callers when full
+    pub async fn acquire(&self, priority: u32) -> BulkheadPermit {
+        let receiver = {
+            let mut state = self.lock_state();
+            if state.in_use < state.max_concurrent {
+                state.in_use += 1;
+                return self.permit();
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let sequence = state.sequence;
+            state.sequence += 1;
+            state.waiters.push(Waiter {
+                priority,
+                sequence,
+                sender,
+            });
+            receiver
+        };
+
+        receiver
+            .await
+            .expect("bulkhead permits are always handed over before waiters are dropped")
+    }
+
+    /// Acquire a slot within the waiting queue's bound and timeout
+    ///
+    /// Fails with [`SupervisorError::BulkheadFull`] if the queue is full and
+    /// [`SupervisorError::BulkheadTimeout`] if no slot frees up in time.
+    pub async fn admit(&self, priority: u32) -> Result<BulkheadPermit, RuntimeError> {
+        let (receiver, sequence, timeout) = {
+            let mut state = self.lock_state();
+            if state.in_use < state.max_concurrent {
+                state.in_use += 1;
+                return Ok(self.permit());
+            }
+            if let Some(max) = state.max_waiting {
+                if state.waiters.len() >= max {
+                    state.rejected += 1;
+                    return Err(SupervisorError::BulkheadFull {
+                        group: self.group.to_string(),
+                        max_waiting: max,
+                    }
+                    .into());
+                }
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let sequence = state.sequence;
+            state.sequence += 1;
+            state.waiters.push(Waiter {
+                priority,
+                sequence,
+                sender,
+            });
+            (receiver, sequence, state.timeout)
+        };
+
+        let Some(timeout) = timeout else {
+            return Ok(receiver
+                .await
+                .expect("bulkhead permits are always handed over before waiters are dropped"));
+        };
+        tokio::select! {
+            permit = receiver => {
+                Ok(permit.expect("bulkhead permits are always handed over before waiters are dropped"))
+            }
+            _ = self.clock.sleep(timeout) => {
+                let mut state = self.lock_state();
+                state.waiters.retain(|waiter| waiter.sequence != sequence);
+                state.timed_out += 1;
+                Err(SupervisorError::BulkheadTimeout {
+                    group: self.group.to_string(),
+                    waited: timeout,
+                }
+                .into())
+            }
+        }
+    }
+
+    /// Run an operation in a slot acquired with [`Bulkhead::admit`]
+    pub async fn run<F, T>(&self, priority: u32, operation: F) -> Result<T, RuntimeError>
+    where
+        F: Future<Output = Result<T, RuntimeError>>,
+    {
+        let _permit = self.admit(priority).await?;
+        operation.await
+    }
+
+    fn permit(&self) -> BulkheadPermit {
+        BulkheadPermit {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Get maximum concurrent executions
+    pub fn max_concurrent(&self) -> usize {
+        self.lock_state().max_concurrent
+    }
+
+    /// Get number of slots in use
+    pub fn in_use(&self) -> usize {
+        self.lock_state().in_use
+    }
+
+    /// Get number of waiting callers
+    pub fn waiting(&self) -> usize {
+        self.lock_state().waiters.len()
+    }
+
+    /// Get number of callers turned away by a full waiting queue
+    pub fn rejected(&self) -> u64 {
+        self.lock_state().rejected
+    }
+
+    /// Get number of callers that gave up waiting
+    pub fn timed_out(&self) -> u64 {
+        self.lock_state().timed_out
+    }
+}
+
+impl std::fmt::Debug for Bulkhead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.lock_state();
+        f.debug_struct("Bulkhead")
+            .field("group", &self.group)
+            .field("max_concurrent", &state.max_concurrent)
+            .field("in_use", &state.in_use)
+            .field("waiting", &state.waiters.len())
+            .finish()
+    }
+}
+
+/// Slot in a bulkhead, released on drop
+pub struct BulkheadPermit {
+    state: Arc<Mutex<BulkheadState>>,
+}
+
+impl Drop for BulkheadPermit {
+    fn drop(&mut self) {
+        let waiter = {
+            let mut state = lock(&self.state);
+            let waiter = state.waiters.pop();
+            if waiter.is_none() {
+                state.in_use -= 1;
+            }
+            waiter
+        };
+
+        // The slot moves to the waiter; if it gave up, dropping the returned
+        // permit passes the slot on again.
+        if let Some(waiter) = waiter {
+            let _ = waiter.sender.send(BulkheadPermit {
+                state: self.state.clone(),
+            });
+        }
+    }
+}
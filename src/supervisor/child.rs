@@ -0,0 +1,314 @@
+use super::{FailureKind, RestartPolicy, ShutdownHooks, StopHandle, Watchdog};
+use crate::{ErrorClass, RuntimeError};
+use agentropic_core::AgentId;
+use futures::future::BoxFuture;
+use futures::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Reason a supervised child stopped abnormally
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChildFailure {
+    /// Start function returned an error
+    Error {
+        /// Error message
+        message: String,
+        /// Error classification
+        class: ErrorClass,
+        /// How the supervisor treats the failure
+        kind: FailureKind,
+    },
+    /// Start function panicked
+    Panic(String),
+}
+
+impl ChildFailure {
+    /// Get failure message
+    pub fn message(&self) -> &str {
+        match self {
+            ChildFailure::Error { message, .. } | ChildFailure::Panic(message) => message,
+        }
+    }
+
+    /// Get error classification (panics are treated as transient)
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            ChildFailure::Error { class, .. } => *class,
+            ChildFailure::Panic(_) => ErrorClass::Transient,
+        }
+    }
+
+    /// Get how the supervisor treats the failure (panics are crashes)
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            ChildFailure::Error { kind, .. } => *kind,
+            ChildFailure::Panic(_) => FailureKind::Crash,
+        }
+    }
+
+    /// Check if the failure was a panic
+    pub fn is_panic(&self) -> bool {
+        matches!(self, ChildFailure::Panic(_))
+    }
+}
+
+/// Context handed to a child's start function
+#[derive(Debug, Clone)]
+pub struct RestartContext {
+    agent_id: AgentId,
+    attempt: u32,
+    last_failure: Option<ChildFailure>,
+    last_failure_at: Option<Instant>,
+    watchdog: Option<Watchdog>,
+}
+
+impl RestartContext {
+    /// Create a context for the first start of a child
+    pub fn new(agent_id: AgentId) -> Self {
+        Self {
+            agent_id,
+            attempt: 1,
+            last_failure: None,
+            last_failure_at: None,
+            watchdog: None,
+        }
+    }
+
+    /// Continue counting attempts from an earlier run, such as one restored from a snapshot
+    pub(crate) fn resumed(agent_id: AgentId, attempt: u32) -> Self {
+        Self {
+            attempt: attempt.max(1),
+            ..Self::new(agent_id)
+        }
+    }
+
+    /// Create the context for the next attempt after a failure
+    pub(crate) fn after_failure(&self, failure: ChildFailure, now: Instant) -> Self {
+        Self {
+            agent_id: self.agent_id,
+            attempt: self.attempt + 1,
+            last_failure: Some(failure),
+            last_failure_at: Some(now),
+            watchdog: None,
+        }
+    }
+
+    /// Create the context for the next attempt after a clean exit
+    pub(crate) fn after_success(&self) -> Self {
+        Self {
+            agent_id: self.agent_id,
+            attempt: self.attempt + 1,
+            last_failure: self.last_failure.clone(),
+            last_failure_at: self.last_failure_at,
+            watchdog: None,
+        }
+    }
+
+    /// Hand the attempt a watchdog to pet
+    pub(crate) fn with_watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Get the watchdog this attempt must pet, if the child has one
+    pub fn watchdog(&self) -> Option<&Watchdog> {
+        self.watchdog.as_ref()
+    }
+
+    /// Get agent ID
+    pub fn agent_id(&self) -> &AgentId {
+        &self.agent_id
+    }
+
+    /// Get attempt number (1 for the initial start)
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Check if this start is a restart
+    pub fn is_restart(&self) -> bool {
+        self.attempt > 1
+    }
+
+    /// Get the previous failure, if any
+    pub fn last_failure(&self) -> Option<&ChildFailure> {
+        self.last_failure.as_ref()
+    }
+
+    /// Get the previous error message, if the child returned an error
+    pub fn error(&self) -> Option<&str> {
+        match &self.last_failure {
+            Some(ChildFailure::Error { message, .. }) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Get the previous panic message, if the child panicked
+    pub fn panic_message(&self) -> Option<&str> {
+        match &self.last_failure {
+            Some(ChildFailure::Panic(message)) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// Get time elapsed since the previous failure
+    pub fn time_since_last_failure(&self) -> Option<Duration> {
+        self.last_failure_at.map(|at| at.elapsed())
+    }
+}
+
+/// Start function of a supervised child
+pub type StartFn =
+    Arc<dyn Fn(RestartContext) -> BoxFuture<'static, Result<(), RuntimeError>> + Send + Sync>;
+
+/// Specification of a supervised child
+#[derive(Clone)]
+pub struct ChildSpec {
+    agent_id: AgentId,
+    policy: RestartPolicy,
+    start: StartFn,
+    deadline: Option<Instant>,
+    expected_runtime: Duration,
+    memory_limit: Option<u64>,
+    watchdog: Option<Duration>,
+    shutdown_hooks: Option<Arc<dyn ShutdownHooks>>,
+    grace_period: Option<Duration>,
+    stop: StopHandle,
+}
+
+impl ChildSpec {
+    /// Create a new child specification
+    pub fn new<F, Fut>(agent_id: AgentId, policy: RestartPolicy, start: F) -> Self
+    where
+        F: Fn(RestartContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), RuntimeError>> + Send + 'static,
+    {
+        Self {
+            agent_id,
+            policy,
+            start: Arc::new(move |ctx| Box::pin(start(ctx))),
+            deadline: None,
+            expected_runtime: Duration::ZERO,
+            memory_limit: None,
+            watchdog: None,
+            shutdown_hooks: None,
+            grace_period: None,
+            stop: StopHandle::default(),
+        }
+    }
+
+    /// Set deadline after which the child is no longer restarted
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set expected run time, used to judge whether a restart can finish in time
+    pub fn with_expected_runtime(mut self, runtime: Duration) -> Self {
+        self.expected_runtime = runtime;
+        self
+    }
+
+    /// Stop the child with an [`ErrorClass::OutOfMemory`] failure once it holds more than `bytes`
+    ///
+    /// Requires [`TrackingAllocator`](super::TrackingAllocator) as the global allocator.
+    pub fn with_memory_limit(mut self, bytes: u64) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Kill and restart the child if it goes `interval` without petting the
+    /// [`Watchdog`] in its [`RestartContext`]
+    pub fn with_watchdog(mut self, interval: Duration) -> Self {
+        self.watchdog = Some(interval);
+        self
+    }
+
+    /// Get watchdog interval
+    pub fn watchdog(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    /// Run hooks to shut down a running attempt before it is killed
+    pub fn with_shutdown_hooks(mut self, hooks: Arc<dyn ShutdownHooks>) -> Self {
+        self.shutdown_hooks = Some(hooks);
+        self
+    }
+
+    /// Set time the shutdown hooks and attempt get before it is killed,
+    /// instead of the supervisor's default
+    pub fn with_grace_period(mut self, grace: Duration) -> Self {
+        self.grace_period = Some(grace);
+        self
+    }
+
+    /// Get shutdown hooks
+    pub fn shutdown_hooks(&self) -> Option<&Arc<dyn ShutdownHooks>> {
+        self.shutdown_hooks.as_ref()
+    }
+
+    /// Get grace period
+    pub fn grace_period(&self) -> Option<Duration> {
+        self.grace_period
+    }
+
+    /// Get a handle asking the child to stop, shared by clones of this spec
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// Get memory limit in bytes
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit
+    }
+
+    /// Get deadline
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Check if a restart `delay` after `now` can finish before the deadline
+    ///
+    /// Read `now` from the supervisor's clock.
+    pub fn can_meet_deadline(&self, delay: Duration, now: Instant) -> bool {
+        self.deadline
+            .is_none_or(|deadline| now + delay + self.expected_runtime <= deadline)
+    }
+
+    /// Get agent ID
+    pub fn agent_id(&self) -> &AgentId {
+        &self.agent_id
+    }
+
+    /// Get restart policy
+    pub fn policy(&self) -> &RestartPolicy {
+        &self.policy
+    }
+
+    /// Replace the restart policy
+    pub(crate) fn set_policy(&mut self, policy: RestartPolicy) {
+        self.policy = policy;
+    }
+
+    /// Get start function
+    pub fn start_fn(&self) -> &StartFn {
+        &self.start
+    }
+}
+
+/// Outcome of running a supervised child
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChildExit {
+    /// Child finished without needing another restart
+    Completed {
+        /// Number of starts performed
+        attempts: u32,
+    },
+    /// Restart policy gave up on the child
+    GaveUp {
+        /// Number of starts performed
+        attempts: u32,
+        /// Failure that ended supervision
+        last_failure: ChildFailure,
+    },
+}
@@ -1,87 +1,40 @@
-use std::time::{Duration, Instant};
-
-/// Circuit breaker state
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum CircuitState {
-    /// Closed - normal operation
-    Closed,
-    /// Open - failing, rejecting requests
-    Open,
-    /// Half-Open - testing if recovered
-    HalfOpen,
-}
-
-/// Circuit breaker for fault tolerance
-pub struct CircuitBreaker {
-    state: CircuitState,
-    failure_count: u32,
-    failure_threshold: u32,
-    timeout: Duration,
-    last_failure: Option<Instant>,
-}
-
-impl CircuitBreaker {
-    /// Create a new circuit breaker
-    pub fn new(failure_threshold: u32, timeout: Duration) -> Self {
-        Self {
-            state: CircuitState::Closed,
-            failure_count: 0,
-            failure_threshold,
-            timeout,
-            last_failure: None,
-        }
-    }
-
-    /// Record success
-    pub fn record_success(&mut self) {
-        self.failure_count = 0;
-        self.state = CircuitState::Closed;
-        self.last_failure = None;
-    }
-
-    /// Record failure
-    pub fn record_failure(&mut self) {
-        self.failure_count += 1;
-        self.last_failure = Some(Instant::now());
-
-        if self.failure_count >= self.failure_threshold {
-            self.state = CircuitState::Open;
-        }
-    }
-
-    /// Check if request is allowed
-    pub fn is_allowed(&mut self) -> bool {
-        match self.state {
-            CircuitState::Closed => true,
-            CircuitState::Open => {
-                if let Some(last_failure) = self.last_failure {
-                    if last_failure.elapsed() >= self.timeout {
-                        self.state = CircuitState::HalfOpen;
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            CircuitState::HalfOpen => true,
-        }
+use crate::timer::{system_clock, SharedClock};
+use agentropic_runtime_core::Clock;
+use std::time::Duration;
+
+pub use agentropic_runtime_core::{CircuitSnapshot, CircuitState, CircuitStats, CircuitTransition};
+
+/// Circuit breaker for fault tolerance, timed by a [`BreakerClock`]
+///
+/// A breaker handed to [`Supervisor::set_circuit_breaker`](super::Supervisor::set_circuit_breaker)
+/// is switched over to the supervisor's clock.
+pub type CircuitBreaker = agentropic_runtime_core::CircuitBreaker<BreakerClock>;
+
+/// Clock of a [`CircuitBreaker`], the system clock unless another is injected
+#[derive(Debug, Clone)]
+pub struct BreakerClock(SharedClock);
+
+impl BreakerClock {
+    /// Create a breaker clock reading the given clock
+    pub fn new(clock: SharedClock) -> Self {
+        Self(clock)
     }
+}
 
-    /// Get state
-    pub fn state(&self) -> CircuitState {
-        self.state
+impl Default for BreakerClock {
+    fn default() -> Self {
+        Self(system_clock())
     }
+}
 
-    /// Get failure count
-    pub fn failure_count(&self) -> u32 {
-        self.failure_count
+impl From<SharedClock> for BreakerClock {
+    fn from(clock: SharedClock) -> Self {
+        Self(clock)
     }
 }
 
-impl Default for CircuitBreaker {
-    fn default() -> Self {
-        Self::new(5, Duration::from_secs(60))
+impl Clock for BreakerClock {
+    fn now(&self) -> Duration {
+        self.0.now()
     }
 }
@@ -0,0 +1,237 @@
+use super::ExponentialBackoff;
+use crate::timer::{self, SharedClock};
+use crate::{DefaultClassifier, ErrorClassifier, RuntimeError};
+use futures::Future;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Total time allowed for a logical operation, across all retries
+#[derive(Debug, Clone)]
+pub struct TimeBudget {
+    total: Duration,
+    started_at: Instant,
+    clock: SharedClock,
+}
+
+impl TimeBudget {
+    /// Create a budget starting now
+    pub fn new(total: Duration) -> Self {
+        let clock = timer::system_clock();
+        Self {
+            total,
+            started_at: clock.instant(),
+            clock,
+        }
+    }
+
+    /// Read time from a clock other than the system clock
+    ///
+    /// The budget restarts from the clock's current time.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.started_at = clock.instant();
+        self.clock = clock;
+        self
+    }
+
+    /// Get total budget
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Get deadline
+    pub fn deadline(&self) -> Instant {
+        self.started_at + self.total
+    }
+
+    /// Get time spent so far
+    pub fn elapsed(&self) -> Duration {
+        self.clock
+            .instant()
+            .saturating_duration_since(self.started_at)
+    }
+
+    /// Get remaining time
+    pub fn remaining(&self) -> Duration {
+        self.total.saturating_sub(self.elapsed())
+    }
+
+    /// Check if the budget is used up
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining().is_zero()
+    }
+}
+
+/// One attempt recorded against a time budget
+#[derive(Debug, Clone)]
+pub struct AttemptRecord {
+    /// Attempt number, starting at 1
+    pub attempt: u32,
+    /// Time the attempt took
+    pub duration: Duration,
+    /// Error the attempt ended with
+    pub error: String,
+}
+
+/// How a time budget was spent
+#[derive(Debug, Clone, Default)]
+pub struct BudgetReport {
+    /// Total budget
+    pub total: Duration,
+    /// Failed attempts
+    pub attempts: Vec<AttemptRecord>,
+    /// Time spent waiting between attempts
+    pub backoff: Duration,
+}
+
+impl BudgetReport {
+    /// Get time spent running attempts
+    pub fn attempt_time(&self) -> Duration {
+        self.attempts.iter().map(|attempt| attempt.duration).sum()
+    }
+}
+
+impl fmt::Display for BudgetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} attempts took {:?}, backoff took {:?}, budget {:?}",
+            self.attempts.len(),
+            self.attempt_time(),
+            self.backoff,
+            self.total
+        )?;
+        if let Some(last) = self.attempts.last() {
+            write!(f, "; last error: {}", last.error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Retries an operation without exceeding a time budget
+///
+/// Each attempt is bounded by the remaining budget, and a backoff that would
+/// run past the deadline ends the retries early.
+pub struct DeadlineRetry {
+    budget: TimeBudget,
+    backoff: ExponentialBackoff,
+    max_attempts: Option<u32>,
+    expected_runtime: Duration,
+    classifier: Arc<dyn ErrorClassifier>,
+    clock: SharedClock,
+}
+
+impl DeadlineRetry {
+    /// Create a retry loop with the given budget
+    pub fn new(budget: TimeBudget) -> Self {
+        Self {
+            budget,
+            backoff: ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(10)),
+            max_attempts: None,
+            expected_runtime: Duration::ZERO,
+            classifier: Arc::new(DefaultClassifier),
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Set backoff between attempts
+    pub fn with_backoff(mut self, backoff: ExponentialBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set maximum number of attempts
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts.max(1));
+        self
+    }
+
+    /// Set expected run time of one attempt
+    ///
+    /// Retries are skipped when the remaining budget cannot cover the backoff
+    /// plus this run time.
+    pub fn with_expected_runtime(mut self, runtime: Duration) -> Self {
+        self.expected_runtime = runtime;
+        self
+    }
+
+    /// Set the classifier deciding which errors are retried
+    pub fn with_classifier(mut self, classifier: impl ErrorClassifier + 'static) -> Self {
+        self.classifier = Arc::new(classifier);
+        self
+    }
+
+    /// Time attempts, backoff sleeps, and the budget with a clock other than
+    /// the system clock
+    ///
+    /// The budget restarts from the clock's current time.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.budget = self.budget.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Get time budget
+    pub fn budget(&self) -> &TimeBudget {
+        &self.budget
+    }
+
+    /// Run the operation until it succeeds, fails permanently, or the budget runs out
+    ///
+    /// The operation receives the attempt number, starting at 1.
+    pub async fn run<T, F, Fut>(mut self, mut operation: F) -> Result<T, RuntimeError>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, RuntimeError>>,
+    {
+        let mut report = BudgetReport {
+            total: self.budget.total(),
+            ..BudgetReport::default()
+        };
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let remaining = self.budget.remaining();
+            if remaining.is_zero() {
+                return Err(RuntimeError::DeadlineExceeded(report));
+            }
+
+            let started = self.clock.instant();
+            let attempt_run = operation(attempt);
+            tokio::pin!(attempt_run);
+            let error = tokio::select! {
+                result = &mut attempt_run => match result {
+                    Ok(value) => return Ok(value),
+                    Err(error) => error,
+                },
+                _ = self.clock.sleep(remaining) => {
+                    report.attempts.push(AttemptRecord {
+                        attempt,
+                        duration: self.clock.instant().saturating_duration_since(started),
+                        error: "attempt timed out".to_string(),
+                    });
+                    return Err(RuntimeError::DeadlineExceeded(report));
+                }
+            };
+
+            let class = self.classifier.classify(&error);
+            report.attempts.push(AttemptRecord {
+                attempt,
+                duration: self.clock.instant().saturating_duration_since(started),
+                error: error.to_string(),
+            });
+
+            if !class.is_retryable() || self.max_attempts.is_some_and(|max| attempt >= max) {
+                return Err(error);
+            }
+
+            let delay = class.retry_delay(self.backoff.next_delay());
+            if delay + self.expected_runtime >= self.budget.remaining() {
+                return Err(RuntimeError::DeadlineExceeded(report));
+            }
+            self.clock.sleep(delay).await;
+            report.backoff += delay;
+        }
+    }
+}
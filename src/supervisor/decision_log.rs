@@ -0,0 +1,157 @@
+use crate::ErrorClass;
+use agentropic_core::AgentId;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+/// Default number of decisions kept
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// What the supervisor decided after a child stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecisionAction {
+    /// Start the child again after a delay
+    Restart,
+    /// Stop supervising the child after a failure
+    GiveUp,
+    /// Stop supervising the child after a clean exit
+    Complete,
+    /// Stop the supervision tree and pass the failure to its parent
+    Escalate,
+}
+
+/// Policy clause that produced a decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestartClause {
+    /// Strategy is `Never`
+    StrategyNever,
+    /// Strategy is `Always`
+    StrategyAlways,
+    /// Strategy restarts on failure and the child failed
+    RestartOnFailure,
+    /// Strategy restarts on failure and the child exited cleanly
+    CleanExit,
+    /// Restart limit reached
+    MaxRetriesReached {
+        /// Configured limit
+        max_retries: u32,
+    },
+    /// Too many restarts within the policy's intensity window
+    IntensityExceeded {
+        /// Restarts allowed per window
+        max_restarts: u32,
+        /// Length of the window
+        window: Duration,
+    },
+    /// Failure class is never retried
+    NotRetryable {
+        /// Failure classification
+        class: ErrorClass,
+    },
+    /// The failure was classified as fatal
+    Fatal,
+    /// A restart could not finish before the child's deadline
+    DeadlineUnreachable,
+    /// A sibling failed and the tree's escalation restarts this child with it
+    SiblingFailed {
+        /// Sibling that failed
+        sibling: AgentId,
+    },
+    /// The child's agent was stopped through its lifecycle
+    Stopped,
+}
+
+/// One supervisor decision
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    /// Supervised agent
+    pub agent_id: AgentId,
+    /// Time of the decision
+    pub at: SystemTime,
+    /// Number of starts performed so far
+    pub attempt: u32,
+    /// Chosen action
+    pub action: DecisionAction,
+    /// Clause that applied
+    pub clause: RestartClause,
+    /// Delay before the restart, for `Restart`
+    pub delay: Option<Duration>,
+    /// Failure message, if the child failed
+    pub failure: Option<String>,
+}
+
+/// Bounded, serializable log of supervisor decisions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionLog {
+    capacity: usize,
+    decisions: VecDeque<Decision>,
+}
+
+impl DecisionLog {
+    /// Create a log keeping the most recent `capacity` decisions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            decisions: VecDeque::new(),
+        }
+    }
+
+    /// Append a decision, dropping the oldest when full
+    pub fn record(&mut self, decision: Decision) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.decisions.len() == self.capacity {
+            self.decisions.pop_front();
+        }
+        self.decisions.push_back(decision);
+    }
+
+    /// Iterate over decisions, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &Decision> {
+        self.decisions.iter()
+    }
+
+    /// Iterate over decisions for one agent
+    pub fn for_agent<'a>(&'a self, agent_id: &'a AgentId) -> impl Iterator<Item = &'a Decision> {
+        self.decisions
+            .iter()
+            .filter(move |decision| decision.agent_id == *agent_id)
+    }
+
+    /// Iterate over decisions made at or after `time`
+    pub fn since(&self, time: SystemTime) -> impl Iterator<Item = &Decision> {
+        self.decisions
+            .iter()
+            .filter(move |decision| decision.at >= time)
+    }
+
+    /// Get most recent decision for an agent
+    pub fn last_for(&self, agent_id: &AgentId) -> Option<&Decision> {
+        self.decisions
+            .iter()
+            .rev()
+            .find(|decision| decision.agent_id == *agent_id)
+    }
+
+    /// Get number of decisions kept
+    pub fn len(&self) -> usize {
+        self.decisions.len()
+    }
+
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.decisions.is_empty()
+    }
+
+    /// Remove all decisions
+    pub fn clear(&mut self) {
+        self.decisions.clear();
+    }
+}
+
+impl Default for DecisionLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
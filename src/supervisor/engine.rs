@@ -1,12 +1,66 @@
-use super::{HealthCheck, RestartPolicy};
+use super::events::{self, EVENT_CAPACITY};
+use super::restart_policy::RestartHistory;
+use super::termination::{self, ShutdownReason};
+use super::{
+    Bulkhead, ChildExit, ChildFailure, ChildSpec, CircuitBreaker, CircuitState, Decision,
+    DecisionAction, DecisionLog, FailureClassifier, FailureKind, HealthCheck, HealthCheckRunner,
+    HealthStatus, MemoryAccount, RestartClause, RestartContext, RestartPolicy, SupervisorEvent,
+    SupervisorSnapshot, Watchdog,
+};
+#[cfg(feature = "scheduler")]
+use crate::budget::BudgetUsage;
+use crate::error::panic_message;
+use crate::lifecycle::{AgentLifecycle, LifecycleState};
+#[cfg(feature = "metrics")]
+use crate::metrics::{Collector, LatencyHistogram, Metric, MetricType};
+use crate::notify::notifier::Publisher;
+#[cfg(feature = "webhooks")]
+use crate::notify::WebhookSink;
+use crate::notify::{Notifier, RuntimeEvent};
+use crate::profiling::Profiler;
+use crate::resources::{QuotaUsage, QuotaViolation, ResourceQuota};
+#[cfg(feature = "scheduler")]
+use crate::scheduler::Scheduler;
+use crate::timer::{self, SharedClock};
+use crate::{DefaultClassifier, ErrorClass, ErrorClassifier, RuntimeError, SupervisorError};
 use agentropic_core::AgentId;
+use futures::Stream;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::{JoinError, JoinHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
 
 /// Supervisor for agent fault tolerance
 pub struct Supervisor {
     name: String,
     policies: HashMap<AgentId, RestartPolicy>,
     health_checks: HashMap<AgentId, HealthCheck>,
+    children: HashMap<AgentId, ChildSpec>,
+    classifier: Arc<dyn ErrorClassifier>,
+    failure_classifier: Option<Arc<dyn FailureClassifier>>,
+    profiler: Option<Arc<Profiler>>,
+    memory: HashMap<AgentId, Arc<MemoryAccount>>,
+    quotas: HashMap<AgentId, ResourceQuota>,
+    restarts: HashMap<AgentId, u32>,
+    restart_history: HashMap<AgentId, RestartHistory>,
+    breakers: HashMap<AgentId, Arc<Mutex<CircuitBreaker>>>,
+    bulkheads: HashMap<AgentId, Bulkhead>,
+    decisions: DecisionLog,
+    notifications: Publisher,
+    clock: SharedClock,
+    grace_period: Duration,
+    lifecycle: Option<Arc<AgentLifecycle>>,
+    events: broadcast::Sender<SupervisorEvent>,
+    #[cfg(feature = "hot-reload")]
+    reloadable: HashMap<AgentId, Arc<super::ReloadableAgent>>,
+    #[cfg(feature = "metrics")]
+    run_time: LatencyHistogram,
+    #[cfg(feature = "metrics")]
+    restart_delay: LatencyHistogram,
+    #[cfg(feature = "metrics")]
+    restarts_total: HashMap<AgentId, u64>,
 }
 
 impl Supervisor {
@@ -16,13 +70,1007 @@ impl Supervisor {
             name: name.into(),
             policies: HashMap::new(),
             health_checks: HashMap::new(),
+            children: HashMap::new(),
+            classifier: Arc::new(DefaultClassifier),
+            failure_classifier: None,
+            profiler: None,
+            memory: HashMap::new(),
+            quotas: HashMap::new(),
+            restarts: HashMap::new(),
+            restart_history: HashMap::new(),
+            breakers: HashMap::new(),
+            bulkheads: HashMap::new(),
+            decisions: DecisionLog::default(),
+            notifications: Publisher::default(),
+            clock: timer::system_clock(),
+            grace_period: termination::DEFAULT_GRACE_PERIOD,
+            lifecycle: None,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            #[cfg(feature = "hot-reload")]
+            reloadable: HashMap::new(),
+            #[cfg(feature = "metrics")]
+            run_time: LatencyHistogram::new(),
+            #[cfg(feature = "metrics")]
+            restart_delay: LatencyHistogram::new(),
+            #[cfg(feature = "metrics")]
+            restarts_total: HashMap::new(),
         }
     }
 
+    /// Rebuild a supervisor from a checkpoint taken by [`Supervisor::snapshot`]
+    ///
+    /// Children are not captured and must be added again; each resumes from
+    /// its recorded restart count when run. Circuit breakers lose their
+    /// transition hooks.
+    pub fn restore_from(snapshot: SupervisorSnapshot) -> Self {
+        let mut supervisor = Self::new(snapshot.name.clone());
+        supervisor.restore(snapshot);
+        supervisor
+    }
+
+    /// Take over restart counters, health, circuit breakers, quotas, and
+    /// decisions from a checkpoint, keeping this supervisor's name and
+    /// children
+    pub fn restore(&mut self, snapshot: SupervisorSnapshot) {
+        self.restarts = snapshot.restarts.into_iter().collect();
+        self.quotas = snapshot.resource_quotas.into_iter().collect();
+        self.decisions = snapshot.decisions;
+        for (agent_id, circuit) in snapshot.circuits {
+            self.breakers.insert(
+                agent_id,
+                Arc::new(Mutex::new(CircuitBreaker::from_snapshot_with_clock(
+                    &circuit,
+                    self.clock.clone().into(),
+                ))),
+            );
+        }
+        for (agent_id, status) in snapshot.health {
+            let mut health = HealthCheck::new().with_clock(self.clock.clone());
+            health.set_status(status);
+            self.health_checks.insert(agent_id, health);
+        }
+    }
+
+    /// Checkpoint restart counters, health, circuit breakers, and decisions
+    pub fn snapshot(&self) -> SupervisorSnapshot {
+        SupervisorSnapshot {
+            name: self.name.clone(),
+            restarts: self
+                .restarts
+                .iter()
+                .map(|(agent_id, restarts)| (*agent_id, *restarts))
+                .collect(),
+            health: self
+                .health_statuses()
+                .map(|(agent_id, status)| (*agent_id, status))
+                .collect(),
+            circuits: self
+                .breakers
+                .iter()
+                .map(|(agent_id, breaker)| (*agent_id, lock_breaker(breaker).snapshot()))
+                .collect(),
+            resource_quotas: self
+                .quotas
+                .iter()
+                .map(|(agent_id, quota)| (*agent_id, *quota))
+                .collect(),
+            decisions: self.decisions.clone(),
+            taken_at: self.clock.wall_time(),
+        }
+    }
+
+    /// Sort child errors into transient, crash, and fatal failures
+    ///
+    /// Transient failures restart after the policy's backoff, crashes
+    /// restart immediately, and fatal failures are never restarted. Without
+    /// one, the kind follows the error classifier's class as in
+    /// [`FailureKind::of_class`].
+    pub fn with_failure_classifier(mut self, classifier: impl FailureClassifier + 'static) -> Self {
+        self.failure_classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    /// Set the error classifier used for restart decisions
+    pub fn with_classifier(mut self, classifier: impl ErrorClassifier + 'static) -> Self {
+        self.classifier = Arc::new(classifier);
+        self
+    }
+
+    /// Attribute child execution time to agents through a profiler
+    pub fn with_profiler(mut self, profiler: Arc<Profiler>) -> Self {
+        self.profiler = Some(profiler);
+        self
+    }
+
+    /// Keep the most recent `capacity` restart decisions
+    pub fn with_decision_log_capacity(mut self, capacity: usize) -> Self {
+        self.decisions = DecisionLog::new(capacity);
+        self
+    }
+
+    /// Time restart delays with a clock other than the system clock
+    ///
+    /// Health checks of agents supervised from then on read the same clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Get the clock restart delays and decisions are timed with
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Set time children with shutdown hooks get to shut down before they
+    /// are killed, unless their spec sets its own
+    ///
+    /// Defaults to five seconds.
+    pub fn with_grace_period(mut self, grace: Duration) -> Self {
+        self.grace_period = grace;
+        self
+    }
+
+    /// Move children through an agent lifecycle as they start and stop
+    ///
+    /// Each start runs the lifecycle's start hooks, and children whose agent
+    /// was stopped through the lifecycle are not restarted.
+    pub fn with_lifecycle(mut self, lifecycle: Arc<AgentLifecycle>) -> Self {
+        self.lifecycle = Some(lifecycle);
+        self
+    }
+
+    /// Record tokens and cost an agent spent against its [`CostBudget`](crate::scheduler::CostBudget)
+    ///
+    /// When this trips the agent's budget breaker, a
+    /// [`SupervisorEvent::BudgetExceeded`] is emitted and, if the budget asks
+    /// for it and a lifecycle is set, the agent is suspended after its
+    /// shutdown hooks prepare for it; resume it through the lifecycle once
+    /// more budget is available.
+    #[cfg(feature = "scheduler")]
+    pub async fn record_usage(
+        &self,
+        agent_id: AgentId,
+        usage: BudgetUsage,
+        scheduler: &mut Scheduler,
+    ) -> Result<(), RuntimeError> {
+        if !scheduler.record_usage(agent_id, usage) {
+            return Ok(());
+        }
+        let suspends = scheduler
+            .cost_budget(&agent_id)
+            .is_some_and(|budget| budget.suspends());
+        let suspension = match &self.lifecycle {
+            Some(lifecycle) if suspends => {
+                self.prepare_suspend(agent_id).await;
+                Some(lifecycle.suspend(agent_id).await)
+            }
+            _ => None,
+        };
+        tracing::warn!("Agent {} exhausted its budget", agent_id);
+        self.emit(SupervisorEvent::BudgetExceeded {
+            agent_id,
+            usage: scheduler.budget_usage(&agent_id),
+            suspended: matches!(suspension, Some(Ok(()))),
+        });
+        suspension.unwrap_or(Ok(()))
+    }
+
+    /// Escalate events such as agents being given up on to a notifier
+    ///
+    /// Events are delivered in the background, so a slow notifier never
+    /// delays restarts; see [`Supervisor::flush_notifications`].
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifications.add(notifier);
+        self
+    }
+
+    /// Wait until events raised so far were delivered to the notifiers
+    pub async fn flush_notifications(&self) {
+        self.notifications.flush().await
+    }
+
+    /// Send events such as agents being given up on to a webhook
+    #[cfg(feature = "webhooks")]
+    pub fn with_webhook(self, webhook: Arc<WebhookSink>) -> Self {
+        self.with_notifier(webhook)
+    }
+
+    /// Subscribe to starts, failures, restarts, opened circuits, exhausted
+    /// budgets, and health changes
+    ///
+    /// The stream only sees events emitted after it was created, and skips
+    /// events it fell too far behind on.
+    pub fn subscribe(&self) -> impl Stream<Item = SupervisorEvent> + Send + 'static {
+        events::stream(self.events.subscribe())
+    }
+
+    fn emit(&self, event: SupervisorEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event);
+    }
+
+    /// Update an agent's health check, announcing a change of status
+    fn update_health(&mut self, agent_id: &AgentId, update: impl FnOnce(&mut HealthCheck)) {
+        let Some(health) = self.health_checks.get_mut(agent_id) else {
+            return;
+        };
+        let from = health.status();
+        update(health);
+        let to = health.status();
+        if from != to {
+            self.emit(SupervisorEvent::HealthChanged {
+                agent_id: *agent_id,
+                from,
+                to,
+            });
+        }
+    }
+
+    /// Announce that supervision of a child ended
+    fn exit(&self, agent_id: &AgentId, exit: ChildExit) -> ChildExit {
+        if let ChildExit::GaveUp {
+            attempts,
+            last_failure,
+        } = &exit
+        {
+            let event = RuntimeEvent::AgentGaveUp {
+                supervisor: self.name.clone(),
+                agent_id: *agent_id,
+                attempts: *attempts,
+                failure: last_failure.message().to_string(),
+                class: last_failure.class(),
+            };
+            self.notifications.publish(event);
+        }
+        exit
+    }
+
+    /// Register an agent whose behavior can be hot reloaded
+    #[cfg(feature = "hot-reload")]
+    pub fn add_reloadable(&mut self, agent_id: AgentId, agent: Arc<super::ReloadableAgent>) {
+        self.reloadable.insert(agent_id, agent);
+    }
+
+    /// Swap a registered agent's behavior, returning its new generation
+    #[cfg(feature = "hot-reload")]
+    pub async fn hot_reload(
+        &self,
+        agent_id: &AgentId,
+        loader: &dyn super::BehaviorLoader,
+    ) -> Result<u64, RuntimeError> {
+        let agent = self
+            .reloadable
+            .get(agent_id)
+            .ok_or_else(|| SupervisorError::NotReloadable(agent_id.to_string()))?;
+        let result = agent.reload(loader).await;
+        match &result {
+            Ok(generation) => {
+                tracing::info!("Reloaded agent {} as generation {}", agent_id, generation)
+            }
+            Err(error) => tracing::warn!("Reload of agent {} failed: {}", agent_id, error),
+        }
+        result
+    }
+
+    /// Get log of restart decisions
+    pub fn decision_log(&self) -> &DecisionLog {
+        &self.decisions
+    }
+
+    fn record_decision(
+        &mut self,
+        agent_id: &AgentId,
+        attempt: u32,
+        action: DecisionAction,
+        clause: RestartClause,
+        delay: Option<Duration>,
+        failure: Option<&ChildFailure>,
+    ) {
+        self.decisions.record(Decision {
+            agent_id: *agent_id,
+            at: self.clock.wall_time(),
+            attempt,
+            action,
+            clause,
+            delay,
+            failure: failure.map(|failure| failure.message().to_string()),
+        });
+    }
+
     /// Add agent to supervision
     pub fn supervise(&mut self, agent_id: AgentId, policy: RestartPolicy) {
         self.policies.insert(agent_id, policy);
-        self.health_checks.insert(agent_id, HealthCheck::new());
+        let clock = self.clock.clone();
+        self.health_checks
+            .entry(agent_id)
+            .or_insert_with(|| HealthCheck::new().with_clock(clock));
+    }
+
+    /// Change a supervised agent's restart policy, including its child's
+    ///
+    /// Takes effect at the child's next restart decision. Returns false if
+    /// the agent is not supervised.
+    pub fn set_restart_policy(&mut self, agent_id: &AgentId, policy: RestartPolicy) -> bool {
+        let Some(current) = self.policies.get_mut(agent_id) else {
+            return false;
+        };
+        *current = policy.clone();
+        if let Some(child) = self.children.get_mut(agent_id) {
+            child.set_policy(policy);
+        }
+        true
+    }
+
+    /// Add a child with a start function to supervision
+    pub fn add_child(&mut self, spec: ChildSpec) {
+        self.supervise(*spec.agent_id(), spec.policy().clone());
+        self.children.insert(*spec.agent_id(), spec);
+    }
+
+    /// Get child specification
+    pub fn get_child(&self, agent_id: &AgentId) -> Option<&ChildSpec> {
+        self.children.get(agent_id)
+    }
+
+    /// Suspend a child for inspection without stopping it
+    ///
+    /// The child's queued tasks are parked in the scheduler, which stops
+    /// dispatching to it; its mailbox is left as is. A child with shutdown
+    /// hooks first gets to flush state in their `prepare_shutdown`, and with
+    /// a lifecycle, a running child is then suspended through it. Returns the
+    /// number of tasks parked.
+    #[cfg(feature = "scheduler")]
+    pub async fn suspend(
+        &mut self,
+        agent_id: AgentId,
+        scheduler: &mut Scheduler,
+    ) -> Result<usize, RuntimeError> {
+        if !self.children.contains_key(&agent_id) {
+            return Err(SupervisorError::ChildNotFound(agent_id.to_string()).into());
+        }
+        self.prepare_suspend(agent_id).await;
+        if let Some(lifecycle) = &self.lifecycle {
+            if lifecycle.state(&agent_id) == Some(LifecycleState::Running) {
+                lifecycle.suspend(agent_id).await?;
+            }
+        }
+        let parked = scheduler.park(agent_id);
+        self.emit(SupervisorEvent::ChildSuspended { agent_id, parked });
+        Ok(parked)
+    }
+
+    /// Let a child's shutdown hooks flush state before it is suspended,
+    /// within its grace period
+    #[cfg(feature = "scheduler")]
+    async fn prepare_suspend(&self, agent_id: AgentId) {
+        let Some(spec) = self.children.get(&agent_id) else {
+            return;
+        };
+        let Some(hooks) = spec.shutdown_hooks() else {
+            return;
+        };
+        let grace = spec.grace_period().unwrap_or(self.grace_period);
+        let deadline = self.clock.instant() + grace;
+        let reason = ShutdownReason::Suspended;
+        termination::prepare(hooks.as_ref(), agent_id, reason, deadline, &self.clock).await;
+    }
+
+    /// Resume a child suspended with [`Supervisor::suspend`]
+    ///
+    /// Parked tasks are queued again and dispatching to the child resumes.
+    /// With a lifecycle, a suspended child is also resumed through it.
+    /// Returns the number of tasks requeued.
+    #[cfg(feature = "scheduler")]
+    pub async fn resume(
+        &mut self,
+        agent_id: AgentId,
+        scheduler: &mut Scheduler,
+    ) -> Result<usize, RuntimeError> {
+        if !self.children.contains_key(&agent_id) {
+            return Err(SupervisorError::ChildNotFound(agent_id.to_string()).into());
+        }
+        if let Some(lifecycle) = &self.lifecycle {
+            if lifecycle.state(&agent_id) == Some(LifecycleState::Suspended) {
+                lifecycle.resume(agent_id).await?;
+            }
+        }
+        let requeued = scheduler.unpark(&agent_id);
+        self.emit(SupervisorEvent::ChildResumed { agent_id, requeued });
+        Ok(requeued)
+    }
+
+    /// Run a child until its restart policy stops restarting it
+    ///
+    /// Each restart receives a [`RestartContext`] describing the previous
+    /// failure, so the child can adapt instead of repeating it. A stop
+    /// requested through [`ChildSpec::stop_handle`] shuts the running attempt
+    /// down, or cuts a restart delay short, and ends the run without a
+    /// restart.
+    pub async fn run_child(&mut self, agent_id: &AgentId) -> Result<ChildExit, RuntimeError> {
+        let (spec, mut ctx) = self.begin_run(agent_id)?;
+        let terminator = self.terminator();
+        loop {
+            let mut attempt = self.start_attempt(&spec, &ctx);
+            let outcome = terminator.wait(&spec, &mut attempt).await;
+            let (number, failure, delay) = match self.settle(&spec, attempt, outcome).await {
+                Settled::Exit(exit) => return Ok(exit),
+                Settled::Restart {
+                    attempt,
+                    failure,
+                    delay,
+                } => (attempt, failure, delay),
+            };
+            if !terminator.delay(&spec, delay).await {
+                return Ok(self.stopped(&spec, number, failure).await);
+            }
+            ctx = match failure {
+                Some(failure) => ctx.after_failure(failure, self.clock.instant()),
+                None => ctx.after_success(),
+            };
+        }
+    }
+
+    /// Run every child concurrently until each stops restarting or
+    /// `shutdown` is cancelled, returning how each child exited
+    ///
+    /// The supervisor stays shared and is locked only between attempts, so
+    /// children run side by side while it can be inspected and changed. On
+    /// shutdown every child is stopped through its [`ChildSpec::stop_handle`],
+    /// giving children with shutdown hooks their grace period to exit.
+    /// Cancelling `kill` aborts the children still running, which then exit
+    /// with [`SupervisorError::Killed`]. Children added after the call are
+    /// not run.
+    pub async fn run(
+        supervisor: Arc<tokio::sync::Mutex<Supervisor>>,
+        shutdown: CancellationToken,
+        kill: CancellationToken,
+    ) -> Vec<(AgentId, Result<ChildExit, RuntimeError>)> {
+        // Runs begin before any can be stopped, so no stop request is
+        // cleared by a run starting late
+        let runs: Vec<_> = {
+            let supervisor = supervisor.lock().await;
+            supervisor
+                .children
+                .keys()
+                .filter_map(|agent_id| supervisor.begin_run(agent_id).ok())
+                .map(|(spec, ctx)| (spec, ctx, supervisor.terminator()))
+                .collect()
+        };
+        let stops: Vec<_> = runs.iter().map(|(spec, ..)| spec.stop_handle()).collect();
+        let mut children = JoinSet::new();
+        let mut running = HashMap::new();
+        for (spec, ctx, terminator) in runs {
+            let agent_id = *spec.agent_id();
+            let supervisor = supervisor.clone();
+            let task = children.spawn(async move {
+                let exit = Self::run_shared(supervisor, spec, ctx, terminator).await;
+                (agent_id, Ok(exit))
+            });
+            running.insert(task.id(), agent_id);
+        }
+        let mut exits = Vec::with_capacity(stops.len());
+        let (mut stopping, mut killing) = (false, false);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled(), if !stopping => {
+                    stopping = true;
+                    for stop in &stops {
+                        stop.stop();
+                    }
+                }
+                _ = kill.cancelled(), if !killing => {
+                    killing = true;
+                    children.abort_all();
+                }
+                joined = children.join_next_with_id() => match joined {
+                    Some(Ok((_, exit))) => exits.push(exit),
+                    Some(Err(error)) => {
+                        let Some(agent_id) = running.get(&error.id()).copied() else {
+                            continue;
+                        };
+                        if error.is_panic() {
+                            tracing::error!(agent = %agent_id, "Supervising a child panicked: {}", error);
+                            let error = RuntimeError::SupervisionError(error.to_string());
+                            exits.push((agent_id, Err(error)));
+                        } else {
+                            tracing::warn!(agent = %agent_id, "Child killed after the shutdown grace period");
+                            let error = SupervisorError::Killed(agent_id.to_string());
+                            exits.push((agent_id, Err(error.into())));
+                        }
+                    }
+                    None => break,
+                },
+            }
+        }
+        exits
+    }
+
+    /// Run a child like [`Supervisor::run_child`], locking the shared
+    /// supervisor only between attempts
+    async fn run_shared(
+        supervisor: Arc<tokio::sync::Mutex<Supervisor>>,
+        spec: ChildSpec,
+        mut ctx: RestartContext,
+        terminator: Terminator,
+    ) -> ChildExit {
+        loop {
+            let mut attempt = supervisor.lock().await.start_attempt(&spec, &ctx);
+            let outcome = terminator.wait(&spec, &mut attempt).await;
+            let settled = supervisor
+                .lock()
+                .await
+                .settle(&spec, attempt, outcome)
+                .await;
+            let (number, failure, delay) = match settled {
+                Settled::Exit(exit) => return exit,
+                Settled::Restart {
+                    attempt,
+                    failure,
+                    delay,
+                } => (attempt, failure, delay),
+            };
+            if !terminator.delay(&spec, delay).await {
+                return supervisor
+                    .lock()
+                    .await
+                    .stopped(&spec, number, failure)
+                    .await;
+            }
+            ctx = match failure {
+                Some(failure) => ctx.after_failure(failure, terminator.clock.instant()),
+                None => ctx.after_success(),
+            };
+        }
+    }
+
+    /// Look up a child to run and the context of its first attempt
+    fn begin_run(&self, agent_id: &AgentId) -> Result<(ChildSpec, RestartContext), RuntimeError> {
+        let spec = self
+            .children
+            .get(agent_id)
+            .cloned()
+            .ok_or_else(|| SupervisorError::ChildNotFound(agent_id.to_string()))?;
+        let restarts = self.restarts.get(agent_id).copied().unwrap_or(0);
+        spec.stop_handle().reset();
+        Ok((spec, RestartContext::resumed(*agent_id, restarts + 1)))
+    }
+
+    fn terminator(&self) -> Terminator {
+        Terminator {
+            clock: self.clock.clone(),
+            grace_period: self.grace_period,
+            events: self.events.clone(),
+        }
+    }
+
+    /// Start an attempt of a child with its limits, lifecycle, and
+    /// instrumentation applied
+    fn start_attempt(&mut self, spec: &ChildSpec, ctx: &RestartContext) -> Attempt {
+        let agent_id = spec.agent_id();
+        let number = ctx.attempt();
+        if ctx.is_restart() {
+            self.update_health(agent_id, HealthCheck::reset);
+        }
+        self.emit(SupervisorEvent::ChildStarted {
+            agent_id: *agent_id,
+            attempt: number,
+        });
+        #[cfg(feature = "metrics")]
+        let started = self.clock.instant();
+        let watchdog = spec
+            .watchdog()
+            .map(|interval| Watchdog::with_clock(interval, self.clock.clone()));
+        let attempt_ctx = match &watchdog {
+            Some(watchdog) => ctx.clone().with_watchdog(watchdog.clone()),
+            None => ctx.clone(),
+        };
+        let mut execution = (spec.start_fn())(attempt_ctx);
+        if let Some(lifecycle) = self.lifecycle.clone() {
+            let agent_id = *agent_id;
+            execution = Box::pin(async move {
+                lifecycle.start(agent_id).await?;
+                execution.await
+            });
+        }
+        if let Some(bulkhead) = self.bulkheads.get(agent_id).cloned() {
+            execution = Box::pin(async move {
+                let _permit = bulkhead.admit(0).await?;
+                execution.await
+            });
+        }
+        let quota_limit = self
+            .quotas
+            .get(agent_id)
+            .and_then(ResourceQuota::max_memory);
+        let memory_limit = match (spec.memory_limit(), quota_limit) {
+            (Some(spec), Some(quota)) => Some(spec.min(quota)),
+            (spec, quota) => spec.or(quota),
+        };
+        let mut memory = None;
+        if let Some(limit) = memory_limit {
+            let account = Arc::new(MemoryAccount::new());
+            self.memory.insert(*agent_id, account.clone());
+            execution = Box::pin(account.flag(limit, execution));
+            memory = Some((account, limit));
+        }
+        if let Some(profiler) = &self.profiler {
+            execution = Box::pin(profiler.instrument(*agent_id, execution));
+        }
+        #[cfg(feature = "tracing")]
+        if ctx.is_restart() {
+            let span = tracing::info_span!(
+                "agent.restart",
+                supervisor = %self.name,
+                agent_id = %agent_id,
+                attempt = number,
+                policy = ?spec.policy().strategy(),
+            );
+            execution = Box::pin(tracing::Instrument::instrument(execution, span));
+        }
+        spec.stop_handle().clear_kill();
+        Attempt {
+            number,
+            watchdog,
+            memory,
+            running: tokio::spawn(execution),
+            #[cfg(feature = "metrics")]
+            started,
+        }
+    }
+
+    /// Record how an attempt ended and decide whether the child restarts
+    async fn settle(
+        &mut self,
+        spec: &ChildSpec,
+        mut attempt: Attempt,
+        outcome: Result<Result<(), RuntimeError>, JoinError>,
+    ) -> Settled {
+        let agent_id = spec.agent_id();
+        let (number, watchdog) = (attempt.number, attempt.watchdog.take());
+        #[cfg(feature = "metrics")]
+        self.run_time.record(
+            self.clock
+                .instant()
+                .saturating_duration_since(attempt.started),
+        );
+
+        let failure = match outcome {
+            Ok(Ok(())) => None,
+            Ok(Err(error)) => {
+                let class = self.classifier.classify(&error);
+                let kind = match &self.failure_classifier {
+                    Some(classifier) => classifier.classify(&error),
+                    None => FailureKind::of_class(&class),
+                };
+                Some(ChildFailure::Error {
+                    class,
+                    kind,
+                    message: error.to_string(),
+                })
+            }
+            Err(join_error) if join_error.is_panic() => {
+                Some(ChildFailure::Panic(panic_message(join_error.into_panic())))
+            }
+            Err(join_error) => Some(ChildFailure::Error {
+                message: join_error.to_string(),
+                class: ErrorClass::Transient,
+                kind: FailureKind::Transient,
+            }),
+        };
+
+        if let Some(watchdog) = watchdog.filter(Watchdog::has_fired) {
+            self.emit(SupervisorEvent::WatchdogExpired {
+                agent_id: *agent_id,
+                attempt: number,
+                interval: watchdog.interval(),
+            });
+        }
+        if let Some(failure) = &failure {
+            self.emit(SupervisorEvent::ChildFailed {
+                agent_id: *agent_id,
+                attempt: number,
+                error: failure.message().to_string(),
+                class: failure.class(),
+            });
+        }
+        self.update_health(agent_id, |health| match failure {
+            Some(_) => health.record_unhealthy(),
+            None => health.record_healthy(),
+        });
+        if let Some(breaker) = self.breakers.get(agent_id) {
+            let mut breaker = lock_breaker(breaker);
+            let was_open = breaker.state() == CircuitState::Open;
+            match &failure {
+                Some(failure) => breaker.record_error(&failure.class()),
+                None => breaker.record_success(),
+            }
+            let opened = !was_open && breaker.state() == CircuitState::Open;
+            drop(breaker);
+            if opened {
+                self.emit(SupervisorEvent::CircuitOpened {
+                    agent_id: *agent_id,
+                });
+            }
+        }
+
+        let lifecycle_stopped = self.lifecycle.as_ref().is_some_and(|lifecycle| {
+            lifecycle
+                .state(agent_id)
+                .is_some_and(LifecycleState::is_stopped)
+        });
+        if let (Some(lifecycle), false) = (&self.lifecycle, lifecycle_stopped) {
+            match failure {
+                Some(_) => lifecycle.fail(*agent_id),
+                None => {
+                    if let Err(error) = lifecycle.stop(*agent_id).await {
+                        tracing::warn!("Stopping agent {} failed: {}", agent_id, error);
+                    }
+                }
+            }
+        }
+
+        let stopped = lifecycle_stopped || spec.stop_handle().is_stop_requested();
+        let (mut restart, mut clause) = if stopped {
+            (false, RestartClause::Stopped)
+        } else {
+            spec.policy().decide(failure.as_ref(), number)
+        };
+        if restart {
+            let now = self.clock.instant();
+            if let Some(exceeded) = self
+                .restart_history
+                .entry(*agent_id)
+                .or_default()
+                .admit(spec.policy(), now)
+            {
+                restart = false;
+                clause = exceeded;
+            }
+        }
+        if !restart {
+            let action = match failure {
+                Some(_) => DecisionAction::GiveUp,
+                None => DecisionAction::Complete,
+            };
+            self.record_decision(agent_id, number, action, clause, None, failure.as_ref());
+            let exit = match failure {
+                Some(last_failure) => ChildExit::GaveUp {
+                    attempts: number,
+                    last_failure,
+                },
+                None => ChildExit::Completed { attempts: number },
+            };
+            self.restarts.remove(agent_id);
+            return Settled::Exit(self.exit(agent_id, exit));
+        }
+
+        let mut delay = spec.policy().restart_delay(number);
+        if let Some(failure) = &failure {
+            delay = match failure.kind() {
+                FailureKind::Crash => Duration::ZERO,
+                _ => failure.class().retry_delay(delay),
+            };
+        }
+
+        if !spec.can_meet_deadline(delay, self.clock.instant()) {
+            let action = match failure {
+                Some(_) => DecisionAction::GiveUp,
+                None => DecisionAction::Complete,
+            };
+            self.record_decision(
+                agent_id,
+                number,
+                action,
+                RestartClause::DeadlineUnreachable,
+                Some(delay),
+                failure.as_ref(),
+            );
+            let exit = match failure {
+                Some(failure) => ChildExit::GaveUp {
+                    attempts: number,
+                    last_failure: ChildFailure::Error {
+                        message: failure.message().to_string(),
+                        class: ErrorClass::DeadlineExceeded,
+                        kind: FailureKind::Fatal,
+                    },
+                },
+                None => ChildExit::Completed { attempts: number },
+            };
+            self.restarts.remove(agent_id);
+            return Settled::Exit(self.exit(agent_id, exit));
+        }
+
+        if let Some(failure) = &failure {
+            tracing::warn!(
+                "Restarting agent {} after failure: {}",
+                agent_id,
+                failure.message()
+            );
+        }
+        self.record_decision(
+            agent_id,
+            number,
+            DecisionAction::Restart,
+            clause,
+            Some(delay),
+            failure.as_ref(),
+        );
+        self.emit(SupervisorEvent::ChildRestarted {
+            agent_id: *agent_id,
+            attempt: number + 1,
+            delay,
+        });
+        #[cfg(feature = "metrics")]
+        {
+            self.restart_delay.record(delay);
+            *self.restarts_total.entry(*agent_id).or_default() += 1;
+        }
+        self.restarts.insert(*agent_id, number);
+        Settled::Restart {
+            attempt: number,
+            failure,
+            delay,
+        }
+    }
+
+    /// End the run of a child stopped while waiting to restart
+    ///
+    /// Its restart count is kept, so a later run resumes from it.
+    async fn stopped(
+        &mut self,
+        spec: &ChildSpec,
+        attempt: u32,
+        failure: Option<ChildFailure>,
+    ) -> ChildExit {
+        let agent_id = spec.agent_id();
+        let action = match failure {
+            Some(_) => DecisionAction::GiveUp,
+            None => DecisionAction::Complete,
+        };
+        self.record_decision(
+            agent_id,
+            attempt,
+            action,
+            RestartClause::Stopped,
+            None,
+            failure.as_ref(),
+        );
+        let exit = match failure {
+            Some(last_failure) => ChildExit::GaveUp {
+                attempts: attempt,
+                last_failure,
+            },
+            None => ChildExit::Completed { attempts: attempt },
+        };
+        self.exit(agent_id, exit)
+    }
+
+    /// Get histogram of child attempt run times
+    #[cfg(feature = "metrics")]
+    pub fn run_time_histogram(&self) -> &LatencyHistogram {
+        &self.run_time
+    }
+
+    /// Get histogram of delays before restarts
+    #[cfg(feature = "metrics")]
+    pub fn restart_delay_histogram(&self) -> &LatencyHistogram {
+        &self.restart_delay
+    }
+
+    /// Record supervisor metrics into a collector
+    #[cfg(feature = "metrics")]
+    pub fn collect_metrics(&self, collector: &mut Collector) {
+        self.run_time.collect("supervisor_run_time", collector);
+        self.restart_delay
+            .collect("supervisor_restart_delay", collector);
+        for (agent_id, restarts) in &self.restarts_total {
+            collector.record(
+                Metric::new(
+                    "supervisor_restarts_total",
+                    MetricType::Counter,
+                    *restarts as f64,
+                )
+                .with_label("agent_id", agent_id.to_string()),
+            );
+        }
+        for (agent_id, health) in &self.health_checks {
+            collector.record(
+                Metric::new(
+                    "supervisor_health_check_failures_total",
+                    MetricType::Counter,
+                    health.total_failures() as f64,
+                )
+                .with_label("agent_id", agent_id.to_string()),
+            );
+        }
+        let mut circuits = [
+            (CircuitState::Closed, "closed", 0),
+            (CircuitState::Open, "open", 0),
+            (CircuitState::HalfOpen, "half_open", 0),
+        ];
+        for breaker in self.breakers.values() {
+            let state = lock_breaker(breaker).state();
+            if let Some((_, _, count)) = circuits.iter_mut().find(|(other, ..)| *other == state) {
+                *count += 1;
+            }
+        }
+        for (_, state, count) in circuits {
+            collector.record(
+                Metric::new(
+                    "supervisor_circuit_breakers",
+                    MetricType::Gauge,
+                    count as f64,
+                )
+                .with_label("state", state),
+            );
+        }
+    }
+
+    /// Set a child's resource quota
+    ///
+    /// Its memory ceiling applies along with the child's own memory limit; a
+    /// child going over it fails with [`RuntimeError::MemoryLimitExceeded`].
+    pub fn set_resource_quota(&mut self, agent_id: AgentId, quota: ResourceQuota) {
+        self.quotas.insert(agent_id, quota);
+    }
+
+    /// Get a child's resource quota
+    pub fn resource_quota(&self, agent_id: &AgentId) -> Option<&ResourceQuota> {
+        self.quotas.get(agent_id)
+    }
+
+    /// Check a child's latest attempt against its memory quota
+    pub fn quota_violation(&self, agent_id: &AgentId) -> Option<QuotaViolation> {
+        let quota = self.quotas.get(agent_id)?;
+        let usage = QuotaUsage {
+            memory: self.memory.get(agent_id)?.current(),
+            ..QuotaUsage::default()
+        };
+        quota.check(&usage)
+    }
+
+    /// Get memory charged to a child's latest attempt, if it has a memory limit
+    pub fn memory_usage(&self, agent_id: &AgentId) -> Option<&MemoryAccount> {
+        self.memory.get(agent_id).map(Arc::as_ref)
+    }
+
+    /// Get restarts of a child that is still supervised
+    pub fn restart_count(&self, agent_id: &AgentId) -> u32 {
+        self.restarts.get(agent_id).copied().unwrap_or(0)
+    }
+
+    /// Track a child's failures in a circuit breaker, returning a handle for callers routing work to it
+    ///
+    /// The breaker's cooldown is timed by the supervisor's clock from then on.
+    pub fn set_circuit_breaker(
+        &mut self,
+        agent_id: AgentId,
+        mut breaker: CircuitBreaker,
+    ) -> Arc<Mutex<CircuitBreaker>> {
+        breaker.set_clock(self.clock.clone().into());
+        let breaker = Arc::new(Mutex::new(breaker));
+        self.breakers.insert(agent_id, breaker.clone());
+        breaker
+    }
+
+    /// Run a child's attempts in a bulkhead slot
+    ///
+    /// Register clones of one bulkhead for every agent of an isolation
+    /// group. An attempt that cannot get a slot fails with
+    /// [`SupervisorError::BulkheadFull`] or [`SupervisorError::BulkheadTimeout`]
+    /// and is restarted under the child's policy.
+    pub fn set_bulkhead(&mut self, agent_id: AgentId, bulkhead: Bulkhead) {
+        self.bulkheads.insert(agent_id, bulkhead);
+    }
+
+    /// Get a child's bulkhead
+    pub fn bulkhead(&self, agent_id: &AgentId) -> Option<&Bulkhead> {
+        self.bulkheads.get(agent_id)
+    }
+
+    /// Get a child's circuit breaker
+    pub fn circuit_breaker(&self, agent_id: &AgentId) -> Option<Arc<Mutex<CircuitBreaker>>> {
+        self.breakers.get(agent_id).cloned()
     }
 
     /// Get restart policy
@@ -40,6 +1088,30 @@ impl Supervisor {
         self.health_checks.get_mut(agent_id)
     }
 
+    /// Adopt the health decided by a probe runner for supervised agents
+    ///
+    /// Agents the runner does not probe keep their current health.
+    pub fn sync_health(&mut self, runner: &HealthCheckRunner) {
+        for (agent_id, status) in runner.statuses() {
+            if status == HealthStatus::Unknown {
+                continue;
+            }
+            self.update_health(&agent_id, |health| health.set_status(status));
+        }
+    }
+
+    /// Get health status of all supervised agents
+    pub fn health_statuses(&self) -> impl Iterator<Item = (&AgentId, HealthStatus)> {
+        self.health_checks
+            .iter()
+            .map(|(agent_id, health)| (agent_id, health.status()))
+    }
+
+    /// Get agent lifecycle
+    pub fn lifecycle(&self) -> Option<&Arc<AgentLifecycle>> {
+        self.lifecycle.as_ref()
+    }
+
     /// Get supervisor name
     pub fn name(&self) -> &str {
         &self.name
@@ -50,3 +1122,160 @@ impl Supervisor {
         self.policies.len()
     }
 }
+
+fn lock_breaker(breaker: &Mutex<CircuitBreaker>) -> MutexGuard<'_, CircuitBreaker> {
+    breaker
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Running attempt of a child
+struct Attempt {
+    number: u32,
+    watchdog: Option<Watchdog>,
+    memory: Option<(Arc<MemoryAccount>, u64)>,
+    running: JoinHandle<Result<(), RuntimeError>>,
+    #[cfg(feature = "metrics")]
+    started: std::time::Instant,
+}
+
+impl Drop for Attempt {
+    fn drop(&mut self) {
+        // Supervision of the attempt ended, so it must not keep running
+        // unsupervised
+        self.running.abort();
+    }
+}
+
+/// What follows a finished attempt
+enum Settled {
+    /// Supervision of the child ended
+    Exit(ChildExit),
+    /// The child restarts after `delay`
+    Restart {
+        attempt: u32,
+        failure: Option<ChildFailure>,
+        delay: Duration,
+    },
+}
+
+/// What it takes to wait for and shut down attempts, without holding the
+/// supervisor
+#[derive(Clone)]
+struct Terminator {
+    clock: SharedClock,
+    grace_period: Duration,
+    events: broadcast::Sender<SupervisorEvent>,
+}
+
+impl Terminator {
+    /// Wait until an attempt exits, or shut it down once its watchdog
+    /// expires, it goes over its memory limit, or a stop or kill is
+    /// requested
+    async fn wait(
+        &self,
+        spec: &ChildSpec,
+        attempt: &mut Attempt,
+    ) -> Result<Result<(), RuntimeError>, JoinError> {
+        let stop = spec.stop_handle();
+        let expired = async {
+            match &attempt.watchdog {
+                Some(watchdog) => watchdog.expired().await,
+                None => std::future::pending().await,
+            }
+        };
+        let over_limit = async {
+            match &attempt.memory {
+                Some((account, _)) => account.exceeded().await,
+                None => std::future::pending().await,
+            }
+        };
+        let killed = async {
+            tokio::select! {
+                _ = expired => ShutdownReason::WatchdogExpired,
+                _ = over_limit => ShutdownReason::MemoryLimitExceeded,
+                reason = stop.requested() => reason,
+            }
+        };
+        let reason = tokio::select! {
+            outcome = &mut attempt.running => {
+                // An attempt that exits before its shutdown went over its
+                // limit all the same
+                let exceeded = attempt
+                    .memory
+                    .as_ref()
+                    .and_then(|(account, limit)| Some((account.exceeded_by()?, *limit)));
+                return match exceeded {
+                    Some((used, limit)) => Ok(Err(RuntimeError::MemoryLimitExceeded { used, limit })),
+                    None => outcome,
+                };
+            }
+            reason = killed => reason,
+        };
+        self.terminate(spec, attempt, reason).await
+    }
+
+    /// Shut down a running attempt, returning its outcome
+    ///
+    /// With shutdown hooks, the attempt gets its grace period to exit before
+    /// it is killed; without, it is killed at once.
+    async fn terminate(
+        &self,
+        spec: &ChildSpec,
+        attempt: &mut Attempt,
+        reason: ShutdownReason,
+    ) -> Result<Result<(), RuntimeError>, JoinError> {
+        let agent_id = *spec.agent_id();
+        let exited = match spec.shutdown_hooks() {
+            Some(hooks) => {
+                let grace = spec.grace_period().unwrap_or(self.grace_period);
+                let deadline = self.clock.instant() + grace;
+                let exited = termination::shut_down(
+                    hooks.as_ref(),
+                    agent_id,
+                    reason,
+                    deadline,
+                    &self.clock,
+                    &mut attempt.running,
+                )
+                .await;
+                // Sending only fails when nobody is subscribed
+                let _ = self.events.send(SupervisorEvent::ChildTerminated {
+                    agent_id,
+                    attempt: attempt.number,
+                    reason,
+                    forced: exited.is_none(),
+                });
+                exited
+            }
+            None => {
+                attempt.running.abort();
+                None
+            }
+        };
+        match (reason, &attempt.watchdog, &attempt.memory) {
+            (ShutdownReason::WatchdogExpired, Some(watchdog), _) => {
+                Ok(Err(watchdog.fire(agent_id)))
+            }
+            (ShutdownReason::MemoryLimitExceeded, _, Some((account, limit))) => {
+                Ok(Err(RuntimeError::MemoryLimitExceeded {
+                    used: account.exceeded_by().unwrap_or_default(),
+                    limit: *limit,
+                }))
+            }
+            (ShutdownReason::Killed, ..) => {
+                Ok(Err(SupervisorError::Killed(agent_id.to_string()).into()))
+            }
+            _ => exited.unwrap_or(Ok(Ok(()))),
+        }
+    }
+
+    /// Wait out a restart delay, returning false if a stop was requested
+    async fn delay(&self, spec: &ChildSpec, delay: Duration) -> bool {
+        let stop = spec.stop_handle();
+        tokio::select! {
+            _ = self.clock.sleep(delay) => !stop.is_stop_requested(),
+            _ = stop.stopped() => false,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Supervisor event stream
+//!
+//! `Supervisor::subscribe` returns a stream of [`SupervisorEvent`]s, one
+//! for every start, failure, termination, and restart of a child, every expired
+//! watchdog, every circuit breaker that opens, every exhausted budget, and every change of an agent's
+//! health. Subscribers that fall behind miss the oldest events rather than
+//! slowing the supervisor down.
+
+use super::{HealthStatus, ShutdownReason};
+use crate::budget::BudgetUsage;
+use crate::ErrorClass;
+use agentropic_core::AgentId;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Events buffered for each subscriber before the oldest are dropped
+pub(crate) const EVENT_CAPACITY: usize = 256;
+
+/// Fault-tolerance event of a supervisor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SupervisorEvent {
+    /// A child was started, including on restarts
+    ChildStarted {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Start attempt, 1 for the first start
+        attempt: u32,
+    },
+    /// A child's attempt failed
+    ChildFailed {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Attempt that failed
+        attempt: u32,
+        /// Failure message
+        error: String,
+        /// Failure classification
+        class: ErrorClass,
+    },
+    /// The supervisor decided to restart a child
+    ChildRestarted {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Attempt the restart will be
+        attempt: u32,
+        /// Delay before the restart
+        delay: Duration,
+    },
+    /// A child stopped petting its watchdog and was killed
+    WatchdogExpired {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Attempt that was killed
+        attempt: u32,
+        /// Interval the child had to pet within
+        interval: Duration,
+    },
+    /// A running attempt was shut down through its shutdown hooks
+    ChildTerminated {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Attempt that was shut down
+        attempt: u32,
+        /// Why it was shut down
+        reason: ShutdownReason,
+        /// Whether it was killed before it exited on its own
+        forced: bool,
+    },
+    /// A child's circuit breaker opened
+    CircuitOpened {
+        /// Supervised agent
+        agent_id: AgentId,
+    },
+    /// An agent exhausted its token or cost budget
+    BudgetExceeded {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Spend in the current period
+        usage: BudgetUsage,
+        /// Whether the agent was suspended
+        suspended: bool,
+    },
+    /// A child was suspended and its queued work parked
+    ChildSuspended {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Tasks parked
+        parked: usize,
+    },
+    /// A suspended child was resumed and its parked work requeued
+    ChildResumed {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Tasks requeued
+        requeued: usize,
+    },
+    /// An agent's health status changed
+    HealthChanged {
+        /// Supervised agent
+        agent_id: AgentId,
+        /// Previous status
+        from: HealthStatus,
+        /// New status
+        to: HealthStatus,
+    },
+}
+
+impl SupervisorEvent {
+    /// Get the agent the event is about
+    pub fn agent_id(&self) -> &AgentId {
+        match self {
+            SupervisorEvent::ChildStarted { agent_id, .. }
+            | SupervisorEvent::ChildFailed { agent_id, .. }
+            | SupervisorEvent::ChildRestarted { agent_id, .. }
+            | SupervisorEvent::WatchdogExpired { agent_id, .. }
+            | SupervisorEvent::ChildTerminated { agent_id, .. }
+            | SupervisorEvent::CircuitOpened { agent_id }
+            | SupervisorEvent::BudgetExceeded { agent_id, .. }
+            | SupervisorEvent::ChildSuspended { agent_id, .. }
+            | SupervisorEvent::ChildResumed { agent_id, .. }
+            | SupervisorEvent::HealthChanged { agent_id, .. } => agent_id,
+        }
+    }
+}
+
+/// Turn a broadcast receiver into a stream, skipping over missed events
+pub(crate) fn stream(
+    receiver: broadcast::Receiver<SupervisorEvent>,
+) -> impl Stream<Item = SupervisorEvent> + Send + 'static {
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::debug!("Supervisor event subscriber missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
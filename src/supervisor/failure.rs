@@ -0,0 +1,61 @@
+//! Failure classification for restart decisions
+//!
+//! A [`FailureClassifier`] sorts a child's errors into [`FailureKind`]s:
+//! transient errors are retried after the policy's backoff, crashes are
+//! restarted right away, and fatal errors, such as bad configuration, stop
+//! the child for good whatever its restart strategy. Panics always count as
+//! crashes.
+
+use crate::{DefaultClassifier, ErrorClass, ErrorClassifier, RuntimeError};
+use serde::{Deserialize, Serialize};
+
+/// How the supervisor treats a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FailureKind {
+    /// Retry after the restart policy's backoff
+    Transient,
+    /// Restart immediately, without backoff
+    Crash,
+    /// Never restart
+    Fatal,
+}
+
+impl FailureKind {
+    /// Get the kind implied by an error class: fatal if never retried,
+    /// transient otherwise
+    pub fn of_class(class: &ErrorClass) -> Self {
+        if class.is_retryable() {
+            FailureKind::Transient
+        } else {
+            FailureKind::Fatal
+        }
+    }
+}
+
+/// Sorts a child's errors into failure kinds
+pub trait FailureClassifier: Send + Sync {
+    /// Classify an error returned by a child
+    fn classify(&self, error: &RuntimeError) -> FailureKind;
+}
+
+impl<F> FailureClassifier for F
+where
+    F: Fn(&RuntimeError) -> FailureKind + Send + Sync,
+{
+    fn classify(&self, error: &RuntimeError) -> FailureKind {
+        self(error)
+    }
+}
+
+/// Classifier deriving the kind from [`DefaultClassifier`]'s error class
+///
+/// A supervisor without a failure classifier derives the kind the same way
+/// from its own [`ErrorClassifier`] instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultFailureClassifier;
+
+impl FailureClassifier for DefaultFailureClassifier {
+    fn classify(&self, error: &RuntimeError) -> FailureKind {
+        FailureKind::of_class(&DefaultClassifier.classify(error))
+    }
+}
@@ -0,0 +1,168 @@
+use crate::timer::{self, SharedClock};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Phi-accrual failure detector
+///
+/// Instead of a fixed number of missed heartbeats, it keeps a window of
+/// heartbeat inter-arrival times and reports a suspicion level (phi) that
+/// grows the longer the current silence is compared to what has been
+/// observed. Jittery links widen the distribution, so they are tolerated
+/// without flapping.
+#[derive(Debug, Clone)]
+pub struct PhiAccrualDetector {
+    threshold: f64,
+    max_sample_size: usize,
+    min_std_deviation: Duration,
+    acceptable_pause: Duration,
+    first_heartbeat_estimate: Duration,
+    intervals: VecDeque<f64>,
+    last_heartbeat: Option<Instant>,
+    clock: SharedClock,
+}
+
+impl PhiAccrualDetector {
+    /// Create a new detector with the given phi threshold
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            max_sample_size: 200,
+            min_std_deviation: Duration::from_millis(100),
+            acceptable_pause: Duration::ZERO,
+            first_heartbeat_estimate: Duration::from_secs(1),
+            intervals: VecDeque::new(),
+            last_heartbeat: None,
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Read the current instant from a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set number of intervals kept in the sliding window
+    pub fn with_max_sample_size(mut self, size: usize) -> Self {
+        self.max_sample_size = size.max(1);
+        self
+    }
+
+    /// Set minimum standard deviation used in the calculation
+    pub fn with_min_std_deviation(mut self, deviation: Duration) -> Self {
+        self.min_std_deviation = deviation;
+        self
+    }
+
+    /// Set pause tolerated on top of the observed mean interval
+    pub fn with_acceptable_pause(mut self, pause: Duration) -> Self {
+        self.acceptable_pause = pause;
+        self
+    }
+
+    /// Set expected interval used before any heartbeat history exists
+    pub fn with_first_heartbeat_estimate(mut self, estimate: Duration) -> Self {
+        self.first_heartbeat_estimate = estimate;
+        self
+    }
+
+    /// Record a heartbeat now
+    pub fn heartbeat(&mut self) {
+        self.heartbeat_at(self.clock.instant());
+    }
+
+    /// Record a heartbeat at the given instant
+    pub fn heartbeat_at(&mut self, at: Instant) {
+        match self.last_heartbeat {
+            Some(last) => self.push_interval(at.saturating_duration_since(last).as_secs_f64()),
+            None => {
+                // Seed the window so the first silence is judged against the estimate
+                let estimate = self.first_heartbeat_estimate.as_secs_f64();
+                self.push_interval(estimate - estimate / 4.0);
+                self.push_interval(estimate + estimate / 4.0);
+            }
+        }
+        self.last_heartbeat = Some(at);
+    }
+
+    fn push_interval(&mut self, interval: f64) {
+        if self.intervals.len() >= self.max_sample_size {
+            self.intervals.pop_front();
+        }
+        self.intervals.push_back(interval);
+    }
+
+    /// Get current suspicion level
+    pub fn phi(&self) -> f64 {
+        self.phi_at(self.clock.instant())
+    }
+
+    /// Get suspicion level at the given instant
+    pub fn phi_at(&self, at: Instant) -> f64 {
+        let Some(last) = self.last_heartbeat else {
+            return 0.0;
+        };
+
+        let elapsed = at.saturating_duration_since(last).as_secs_f64();
+        let count = self.intervals.len() as f64;
+        let mean = self.intervals.iter().sum::<f64>() / count;
+        let variance = self
+            .intervals
+            .iter()
+            .map(|interval| (interval - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        let std_deviation = variance
+            .sqrt()
+            .max(self.min_std_deviation.as_secs_f64())
+            .max(f64::EPSILON);
+
+        phi(
+            elapsed,
+            mean + self.acceptable_pause.as_secs_f64(),
+            std_deviation,
+        )
+    }
+
+    /// Check if the monitored agent is considered available
+    pub fn is_available(&self) -> bool {
+        self.is_available_at(self.clock.instant())
+    }
+
+    /// Check availability at the given instant
+    pub fn is_available_at(&self, at: Instant) -> bool {
+        self.phi_at(at) < self.threshold
+    }
+
+    /// Get phi threshold
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Get time of the last heartbeat
+    pub fn last_heartbeat(&self) -> Option<Instant> {
+        self.last_heartbeat
+    }
+}
+
+impl Default for PhiAccrualDetector {
+    fn default() -> Self {
+        Self::new(8.0)
+    }
+}
+
+/// Logistic approximation of the normal CDF, as used by Akka and Cassandra
+fn phi(elapsed: f64, mean: f64, std_deviation: f64) -> f64 {
+    let y = (elapsed - mean) / std_deviation;
+    let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+    let phi = if elapsed > mean {
+        -(e / (1.0 + e)).log10()
+    } else {
+        -(1.0 - 1.0 / (1.0 + e)).log10()
+    };
+    if phi.is_finite() {
+        phi
+    } else {
+        f64::MAX
+    }
+}
@@ -0,0 +1,130 @@
+use super::{CircuitBreaker, CircuitState};
+use crate::notify::notifier::Publisher;
+#[cfg(feature = "webhooks")]
+use crate::notify::WebhookSink;
+use crate::notify::{Notifier, RuntimeEvent};
+use crate::{DefaultClassifier, ErrorClassifier, RuntimeError};
+use futures::future::BoxFuture;
+use futures::Future;
+use std::sync::Arc;
+
+/// Fallback invoked instead of a protected operation while its breaker is open
+pub type FallbackFn<T> = Arc<dyn Fn() -> BoxFuture<'static, Result<T, RuntimeError>> + Send + Sync>;
+
+/// Operation guarded by a circuit breaker, with an optional fallback
+pub struct ProtectedOperation<T> {
+    name: String,
+    breaker: CircuitBreaker,
+    fallback: Option<FallbackFn<T>>,
+    classifier: Arc<dyn ErrorClassifier>,
+    fallback_count: u64,
+    notifications: Publisher,
+}
+
+impl<T: Send + 'static> ProtectedOperation<T> {
+    /// Create a new protected operation
+    pub fn new(name: impl Into<String>, breaker: CircuitBreaker) -> Self {
+        Self {
+            name: name.into(),
+            breaker,
+            fallback: None,
+            classifier: Arc::new(DefaultClassifier),
+            fallback_count: 0,
+            notifications: Publisher::default(),
+        }
+    }
+
+    /// Set fallback, e.g. a cached response or an alternative agent
+    pub fn with_fallback<F, Fut>(mut self, fallback: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, RuntimeError>> + Send + 'static,
+    {
+        self.fallback = Some(Arc::new(move || Box::pin(fallback())));
+        self
+    }
+
+    /// Set the classifier deciding which errors count against the breaker
+    pub fn with_classifier(mut self, classifier: impl ErrorClassifier + 'static) -> Self {
+        self.classifier = Arc::new(classifier);
+        self
+    }
+
+    /// Notify whenever the breaker opens
+    ///
+    /// Events are delivered in the background, so a slow notifier never adds
+    /// latency to calls; see [`ProtectedOperation::flush_notifications`].
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifications.add(notifier);
+        self
+    }
+
+    /// Wait until events raised so far were delivered to the notifiers
+    pub async fn flush_notifications(&self) {
+        self.notifications.flush().await
+    }
+
+    /// Send an event to a webhook whenever the breaker opens
+    #[cfg(feature = "webhooks")]
+    pub fn with_webhook(self, webhook: Arc<WebhookSink>) -> Self {
+        self.with_notifier(webhook)
+    }
+
+    /// Run the operation, or the fallback if the breaker is open
+    ///
+    /// Without a fallback an open breaker yields [`RuntimeError::CircuitOpen`].
+    pub async fn call<F, Fut>(&mut self, operation: F) -> Result<T, RuntimeError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, RuntimeError>>,
+    {
+        if !self.breaker.is_allowed() {
+            return match &self.fallback {
+                Some(fallback) => {
+                    self.fallback_count += 1;
+                    fallback().await
+                }
+                None => Err(RuntimeError::CircuitOpen(self.name.clone())),
+            };
+        }
+
+        let result = operation().await;
+        let was_open = self.breaker.state() == CircuitState::Open;
+        match &result {
+            Ok(_) => self.breaker.record_success(),
+            Err(error) => self.breaker.record_error(&self.classifier.classify(error)),
+        }
+        if !was_open && self.breaker.state() == CircuitState::Open {
+            let event = RuntimeEvent::BreakerOpened {
+                name: self.name.clone(),
+            };
+            self.notifications.publish(event);
+        }
+        result
+    }
+
+    /// Get operation name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get breaker state
+    pub fn state(&self) -> CircuitState {
+        self.breaker.state()
+    }
+
+    /// Get circuit breaker
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// Check if a fallback is registered
+    pub fn has_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    /// Get number of times the fallback was invoked
+    pub fn fallback_count(&self) -> u64 {
+        self.fallback_count
+    }
+}
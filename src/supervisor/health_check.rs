@@ -1,45 +1,158 @@
-use std::time::Instant;
-
-/// Health status
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum HealthStatus {
-    /// Healthy
-    Healthy,
-    /// Unhealthy
-    Unhealthy,
-    /// Unknown
-    Unknown,
-}
+use super::PhiAccrualDetector;
+use crate::timer::{self, SharedClock};
+use std::time::{Duration, Instant};
+
+pub use agentropic_runtime_core::HealthStatus;
 
 /// Health check
 pub struct HealthCheck {
     status: HealthStatus,
     last_check: Option<Instant>,
     failures: u32,
+    total_failures: u64,
+    successes: u32,
+    detector: Option<PhiAccrualDetector>,
+    started_at: Instant,
+    initial_delay: Duration,
+    success_threshold: u32,
+    failure_threshold: u32,
+    clock: SharedClock,
 }
 
 impl HealthCheck {
     /// Create a new health check
     pub fn new() -> Self {
+        let clock = timer::system_clock();
         Self {
             status: HealthStatus::Unknown,
             last_check: None,
             failures: 0,
+            total_failures: 0,
+            successes: 0,
+            detector: None,
+            started_at: clock.instant(),
+            initial_delay: Duration::ZERO,
+            success_threshold: 1,
+            failure_threshold: 1,
+            clock,
         }
     }
 
+    /// Read time from a clock other than the system clock
+    ///
+    /// The check restarts its initial delay from the clock's current time.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.started_at = clock.instant();
+        self.detector = self
+            .detector
+            .map(|detector| detector.with_clock(clock.clone()));
+        self.clock = clock;
+        self
+    }
+
+    /// Use a phi-accrual detector instead of explicit unhealthy reports
+    pub fn with_failure_detector(mut self, detector: PhiAccrualDetector) -> Self {
+        self.detector = Some(detector.with_clock(self.clock.clone()));
+        self
+    }
+
+    /// Set warm-up period during which failures are ignored
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Set consecutive successes required to become healthy
+    pub fn with_success_threshold(mut self, threshold: u32) -> Self {
+        self.success_threshold = threshold.max(1);
+        self
+    }
+
+    /// Set consecutive failures required to become unhealthy
+    pub fn with_failure_threshold(mut self, threshold: u32) -> Self {
+        self.failure_threshold = threshold.max(1);
+        self
+    }
+
+    /// Restart evaluation, e.g. after the agent was restarted
+    pub fn reset(&mut self) {
+        self.status = HealthStatus::Unknown;
+        self.failures = 0;
+        self.successes = 0;
+        self.started_at = self.clock.instant();
+    }
+
+    /// Check if the check is still within its initial delay
+    pub fn in_initial_delay(&self) -> bool {
+        self.clock
+            .instant()
+            .saturating_duration_since(self.started_at)
+            < self.initial_delay
+    }
+
     /// Record healthy check
     pub fn record_healthy(&mut self) {
-        self.status = HealthStatus::Healthy;
-        self.last_check = Some(Instant::now());
+        let now = self.clock.instant();
+        self.last_check = Some(now);
         self.failures = 0;
+        self.successes = self.successes.saturating_add(1);
+        if self.successes >= self.success_threshold {
+            self.status = HealthStatus::Healthy;
+        }
+        if let Some(detector) = &mut self.detector {
+            detector.heartbeat_at(now);
+        }
     }
 
     /// Record unhealthy check
+    ///
+    /// Failures during the initial delay are ignored.
     pub fn record_unhealthy(&mut self) {
-        self.status = HealthStatus::Unhealthy;
-        self.last_check = Some(Instant::now());
+        self.last_check = Some(self.clock.instant());
+        if self.in_initial_delay() {
+            return;
+        }
+
+        self.successes = 0;
         self.failures += 1;
+        self.total_failures += 1;
+        if self.failures >= self.failure_threshold {
+            self.status = HealthStatus::Unhealthy;
+        }
+    }
+
+    /// Re-evaluate status using the failure detector, if configured
+    pub fn evaluate(&mut self) -> HealthStatus {
+        self.evaluate_at(self.clock.instant())
+    }
+
+    /// Re-evaluate status at the given instant
+    pub fn evaluate_at(&mut self, at: Instant) -> HealthStatus {
+        let warming_up = at.saturating_duration_since(self.started_at) < self.initial_delay;
+        let suspected = self
+            .detector
+            .as_ref()
+            .is_some_and(|detector| !detector.is_available_at(at));
+
+        if suspected && !warming_up && self.status != HealthStatus::Unhealthy {
+            self.status = HealthStatus::Unhealthy;
+            self.last_check = Some(at);
+            self.successes = 0;
+            self.failures += 1;
+            self.total_failures += 1;
+        }
+        self.status
+    }
+
+    /// Adopt a status decided elsewhere, such as by a probe runner
+    pub(crate) fn set_status(&mut self, status: HealthStatus) {
+        self.status = status;
+        self.last_check = Some(self.clock.instant());
+    }
+
+    /// Get failure detector
+    pub fn failure_detector(&self) -> Option<&PhiAccrualDetector> {
+        self.detector.as_ref()
     }
 
     /// Get status
@@ -47,11 +160,26 @@ impl HealthCheck {
         self.status
     }
 
-    /// Get failure count
+    /// Get consecutive failure count
     pub fn failures(&self) -> u32 {
         self.failures
     }
 
+    /// Get failed checks since creation, across resets
+    pub fn total_failures(&self) -> u64 {
+        self.total_failures
+    }
+
+    /// Get consecutive success count
+    pub fn successes(&self) -> u32 {
+        self.successes
+    }
+
+    /// Get time of the last check
+    pub fn last_check(&self) -> Option<Instant> {
+        self.last_check
+    }
+
     /// Check if healthy
     pub fn is_healthy(&self) -> bool {
         self.status == HealthStatus::Healthy
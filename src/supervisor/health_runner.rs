@@ -0,0 +1,204 @@
+//! Active health checking
+//!
+//! A [`HealthCheckRunner`] executes each agent's [`HealthProbe`] on an
+//! interval and feeds the results into a [`HealthCheck`] per agent. A probe
+//! that does not answer within the timeout counts as unhealthy. An agent only
+//! turns unhealthy after the configured number of consecutive failures, so
+//! one slow probe does not make the supervisor or scheduler act.
+
+use super::{HealthCheck, HealthProbe, HealthStatus};
+use crate::timer::{self, SharedClock};
+use agentropic_core::AgentId;
+use futures::future::{self, Either};
+use futures::Future;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Probe registered for an agent
+struct ProbeEntry {
+    agent_id: AgentId,
+    probe: Arc<dyn HealthProbe>,
+}
+
+/// Runs health probes periodically and tracks each agent's health
+pub struct HealthCheckRunner {
+    probes: Vec<ProbeEntry>,
+    checks: Mutex<HashMap<AgentId, HealthCheck>>,
+    interval: Duration,
+    timeout: Duration,
+    unhealthy_threshold: u32,
+    healthy_threshold: u32,
+    clock: SharedClock,
+}
+
+impl HealthCheckRunner {
+    /// Create a runner probing every 10 seconds with a 1 second timeout
+    ///
+    /// Agents turn unhealthy after 3 consecutive failed probes.
+    pub fn new() -> Self {
+        Self {
+            probes: Vec::new(),
+            checks: Mutex::new(HashMap::new()),
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(1),
+            unhealthy_threshold: 3,
+            healthy_threshold: 1,
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Set time between probe rounds
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set how long a probe may take before it counts as unhealthy
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set consecutive failed probes before an agent is unhealthy
+    pub fn with_unhealthy_threshold(mut self, threshold: u32) -> Self {
+        self.unhealthy_threshold = threshold.max(1);
+        self
+    }
+
+    /// Set consecutive successful probes before an agent is healthy again
+    pub fn with_healthy_threshold(mut self, threshold: u32) -> Self {
+        self.healthy_threshold = threshold.max(1);
+        self
+    }
+
+    /// Time intervals and timeouts with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Probe an agent
+    pub fn with_probe(mut self, agent_id: AgentId, probe: impl HealthProbe + 'static) -> Self {
+        self.add_probe(agent_id, probe);
+        self
+    }
+
+    /// Probe an agent, in addition to any probes it already has
+    ///
+    /// An agent with several probes is healthy only if all of them pass.
+    pub fn add_probe(&mut self, agent_id: AgentId, probe: impl HealthProbe + 'static) {
+        self.probes.push(ProbeEntry {
+            agent_id,
+            probe: Arc::new(probe),
+        });
+        self.lock_checks().entry(agent_id).or_insert_with(|| {
+            HealthCheck::new()
+                .with_clock(self.clock.clone())
+                .with_failure_threshold(self.unhealthy_threshold)
+                .with_success_threshold(self.healthy_threshold)
+        });
+    }
+
+    /// Get interval
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Get timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Get an agent's health, `Unknown` if it is not probed
+    pub fn status(&self, agent_id: &AgentId) -> HealthStatus {
+        self.lock_checks()
+            .get(agent_id)
+            .map_or(HealthStatus::Unknown, HealthCheck::status)
+    }
+
+    /// Get the health of every probed agent
+    pub fn statuses(&self) -> Vec<(AgentId, HealthStatus)> {
+        self.lock_checks()
+            .iter()
+            .map(|(agent_id, check)| (*agent_id, check.status()))
+            .collect()
+    }
+
+    /// Get an agent's consecutive failed probes
+    pub fn failures(&self, agent_id: &AgentId) -> u32 {
+        self.lock_checks()
+            .get(agent_id)
+            .map_or(0, HealthCheck::failures)
+    }
+
+    /// Run every probe once, concurrently, and record the results
+    pub async fn check_once(&self) {
+        let results = future::join_all(self.probes.iter().map(|entry| async move {
+            let probe = entry.probe.probe();
+            let timeout = self.clock.sleep(self.timeout);
+            let status = match future::select(probe, timeout).await {
+                Either::Left((status, _)) => status,
+                Either::Right(_) => HealthStatus::Unhealthy,
+            };
+            (entry.agent_id, status)
+        }))
+        .await;
+
+        let mut round: HashMap<AgentId, HealthStatus> = HashMap::new();
+        for (agent_id, status) in results {
+            let combined = round.entry(agent_id).or_insert(HealthStatus::Healthy);
+            if status != HealthStatus::Healthy {
+                *combined = HealthStatus::Unhealthy;
+            }
+        }
+        let mut checks = self.lock_checks();
+        for (agent_id, status) in round {
+            let Some(check) = checks.get_mut(&agent_id) else {
+                continue;
+            };
+            let before = check.status();
+            match status {
+                HealthStatus::Healthy => check.record_healthy(),
+                _ => check.record_unhealthy(),
+            }
+            if check.status() != before {
+                tracing::info!(
+                    "Agent {} health changed from {:?} to {:?}",
+                    agent_id,
+                    before,
+                    check.status()
+                );
+            }
+        }
+    }
+
+    /// Probe every interval until `shutdown` completes
+    ///
+    /// Pass `token.cancelled()` to stop with a cancellation token.
+    pub async fn run_until(&self, shutdown: impl Future<Output = ()>) {
+        let mut shutdown = std::pin::pin!(shutdown);
+        loop {
+            let round = async {
+                self.check_once().await;
+                self.clock.sleep(self.interval).await;
+            };
+            if let Either::Left(_) = future::select(shutdown.as_mut(), std::pin::pin!(round)).await
+            {
+                return;
+            }
+        }
+    }
+
+    fn lock_checks(&self) -> MutexGuard<'_, HashMap<AgentId, HealthCheck>> {
+        self.checks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for HealthCheckRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,119 @@
+use crate::timer::{self, SharedClock};
+use crate::RuntimeError;
+use futures::Future;
+use std::time::Duration;
+
+/// Issues a duplicate request when the primary is slow, keeping the first success
+///
+/// The losing request is cancelled by dropping its future, so both requests
+/// must be safe to cancel at any await point.
+#[derive(Debug, Clone)]
+pub struct Hedger {
+    delay: Duration,
+    max_hedge_ratio: f64,
+    requests: u64,
+    hedges: u64,
+    hedge_wins: u64,
+    clock: SharedClock,
+}
+
+impl Hedger {
+    /// Create a new hedger that hedges after the given delay
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            max_hedge_ratio: 0.1,
+            requests: 0,
+            hedges: 0,
+            hedge_wins: 0,
+            clock: timer::system_clock(),
+        }
+    }
+
+    /// Time the hedge delay with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Set the maximum fraction of requests that may be hedged
+    pub fn with_max_hedge_ratio(mut self, ratio: f64) -> Self {
+        self.max_hedge_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Check if the hedge budget allows another hedge
+    fn hedge_allowed(&self) -> bool {
+        (self.hedges + 1) as f64 <= self.max_hedge_ratio * self.requests as f64
+    }
+
+    /// Run the primary request, hedging with the secondary if it is slow
+    pub async fn run<T, P, S, SF>(&mut self, primary: P, secondary: S) -> Result<T, RuntimeError>
+    where
+        P: Future<Output = Result<T, RuntimeError>>,
+        S: FnOnce() -> SF,
+        SF: Future<Output = Result<T, RuntimeError>>,
+    {
+        self.requests += 1;
+        tokio::pin!(primary);
+
+        tokio::select! {
+            result = &mut primary => return result,
+            _ = self.clock.sleep(self.delay) => {}
+        }
+
+        if !self.hedge_allowed() {
+            return primary.await;
+        }
+
+        self.hedges += 1;
+        let hedge = secondary();
+        tokio::pin!(hedge);
+
+        tokio::select! {
+            result = &mut primary => match result {
+                Ok(value) => Ok(value),
+                Err(_) => {
+                    let result = hedge.await;
+                    if result.is_ok() {
+                        self.hedge_wins += 1;
+                    }
+                    result
+                }
+            },
+            result = &mut hedge => match result {
+                Ok(value) => {
+                    self.hedge_wins += 1;
+                    Ok(value)
+                }
+                Err(_) => primary.await,
+            },
+        }
+    }
+
+    /// Get hedge delay
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Get number of requests
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// Get number of hedged requests
+    pub fn hedges(&self) -> u64 {
+        self.hedges
+    }
+
+    /// Get number of requests won by the hedge
+    pub fn hedge_wins(&self) -> u64 {
+        self.hedge_wins
+    }
+}
+
+impl Default for Hedger {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500))
+    }
+}
@@ -0,0 +1,217 @@
+//! Per-agent memory accounting
+//!
+//! Install [`TrackingAllocator`] as the global allocator to attribute heap
+//! allocations to the agent whose future is being polled. Memory is counted
+//! net of frees made while that agent runs, so buffers handed to other agents
+//! stay charged to the allocating agent until the next restart.
+
+use crate::RuntimeError;
+use futures::Future;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::Notify;
+
+thread_local! {
+    // Account charged for allocations on this thread, set only while polling
+    static CURRENT: Cell<*const MemoryAccount> = const { Cell::new(ptr::null()) };
+}
+
+/// Global allocator wrapper charging allocations to the running agent
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: TrackingAllocator = TrackingAllocator::new(std::alloc::System);
+/// ```
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> TrackingAllocator<A> {
+    /// Wrap an allocator
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+fn charge(bytes: usize, allocated: bool) {
+    let _ = CURRENT.try_with(|current| {
+        // SAFETY: the pointer is only set while the owning `MemoryLimited`
+        // holds an `Arc` to the account and is being polled on this thread
+        if let Some(account) = unsafe { current.get().as_ref() } {
+            if allocated {
+                account.allocated(bytes as u64);
+            } else {
+                account.freed(bytes as u64);
+            }
+        }
+    });
+}
+
+// SAFETY: all allocation is delegated to the inner allocator
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            charge(layout.size(), true);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        charge(layout.size(), false);
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            charge(layout.size(), true);
+        }
+        ptr
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            charge(layout.size(), false);
+            charge(new_size, true);
+        }
+        new_ptr
+    }
+}
+
+/// Memory charged to one agent execution
+#[derive(Debug, Default)]
+pub struct MemoryAccount {
+    current: AtomicU64,
+    peak: AtomicU64,
+    // Bytes charged when a flagged future went over its limit, or 0
+    exceeded: AtomicU64,
+    over_limit: Notify,
+}
+
+impl MemoryAccount {
+    /// Create an empty account
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get bytes currently charged
+    pub fn current(&self) -> u64 {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    /// Get the highest number of bytes charged at once
+    pub fn peak(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    fn allocated(&self, bytes: u64) {
+        let current = self.current.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak.fetch_max(current, Ordering::Relaxed);
+    }
+
+    fn freed(&self, bytes: u64) {
+        let _ = self
+            .current
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                Some(current.saturating_sub(bytes))
+            });
+    }
+
+    /// Charge allocations made while polling `future` to this account
+    ///
+    /// The future is dropped and fails with
+    /// [`RuntimeError::MemoryLimitExceeded`] once the account exceeds `limit`
+    /// bytes after a poll.
+    pub fn limit<F>(self: &Arc<Self>, limit: u64, future: F) -> MemoryLimited<F> {
+        MemoryLimited {
+            future: Some(Box::pin(future)),
+            account: self.clone(),
+            limit,
+            drops: true,
+        }
+    }
+
+    /// Charge allocations like [`MemoryAccount::limit`], but keep polling
+    /// `future` past `limit` and only flag the account, so the supervisor
+    /// can shut the attempt down through its hooks
+    pub(crate) fn flag<F>(self: &Arc<Self>, limit: u64, future: F) -> MemoryLimited<F> {
+        MemoryLimited {
+            drops: false,
+            ..self.limit(limit, future)
+        }
+    }
+
+    /// Wait until a future flagged with [`MemoryAccount::flag`] goes over
+    /// its limit, returning the bytes charged then
+    pub(crate) async fn exceeded(&self) -> u64 {
+        loop {
+            let notified = self.over_limit.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if let Some(used) = self.exceeded_by() {
+                return used;
+            }
+            notified.await;
+        }
+    }
+
+    /// Get the bytes charged when a flagged future went over its limit, if
+    /// it did
+    pub(crate) fn exceeded_by(&self) -> Option<u64> {
+        Some(self.exceeded.load(Ordering::Relaxed)).filter(|used| *used > 0)
+    }
+}
+
+/// Future whose allocations are charged to a [`MemoryAccount`]
+pub struct MemoryLimited<F> {
+    future: Option<Pin<Box<F>>>,
+    account: Arc<MemoryAccount>,
+    limit: u64,
+    drops: bool,
+}
+
+impl<F> MemoryLimited<F> {
+    /// Get the account charged for this future
+    pub fn account(&self) -> &Arc<MemoryAccount> {
+        &self.account
+    }
+}
+
+impl<F, T> Future for MemoryLimited<F>
+where
+    F: Future<Output = Result<T, RuntimeError>>,
+{
+    type Output = Result<T, RuntimeError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let Some(future) = this.future.as_mut() else {
+            return Poll::Pending;
+        };
+
+        let previous = CURRENT.with(|current| current.replace(Arc::as_ptr(&this.account)));
+        let poll = future.as_mut().poll(cx);
+        CURRENT.with(|current| current.set(previous));
+
+        let used = this.account.current();
+        if poll.is_pending() && used > this.limit && !this.drops {
+            if this.account.exceeded.swap(used, Ordering::Relaxed) == 0 {
+                this.account.over_limit.notify_waiters();
+            }
+        } else if poll.is_pending() && used > this.limit {
+            this.future = None;
+            return Poll::Ready(Err(RuntimeError::MemoryLimitExceeded {
+                used,
+                limit: this.limit,
+            }));
+        }
+        poll
+    }
+}
@@ -2,17 +2,76 @@
 
 /// Exponential backoff
 pub mod backoff;
+/// Bulkhead isolation
+pub mod bulkhead;
+/// Supervised children
+pub mod child;
 /// Circuit breaker
 pub mod circuit_breaker;
+/// Time budgets across retries
+pub mod deadline;
+/// Supervisor decision log
+pub mod decision_log;
 /// Supervisor engine
 pub mod engine;
+/// Supervisor event stream
+pub mod events;
+/// Failure classification
+pub mod failure;
+/// Phi-accrual failure detection
+pub mod failure_detector;
+/// Circuit breaker fallbacks
+pub mod fallback;
 /// Health checks
 pub mod health_check;
+/// Active health checking
+pub mod health_runner;
+/// Hedged requests
+pub mod hedge;
+/// Per-agent memory accounting
+pub mod memory;
+/// Built-in health probes
+pub mod probe;
+/// Hot reload of agent behavior
+#[cfg(feature = "hot-reload")]
+pub mod reload;
 /// Restart policies
 pub mod restart_policy;
+/// Supervisor checkpoints
+pub mod snapshot;
+/// Two-phase termination of children
+pub mod termination;
+/// Supervision trees
+pub mod tree;
+/// Watchdog timers
+pub mod watchdog;
 
-pub use backoff::ExponentialBackoff;
-pub use circuit_breaker::{CircuitBreaker, CircuitState};
+pub use backoff::{BackoffStrategy, ExponentialBackoff, FibonacciBackoff, Jitter};
+pub use bulkhead::{Bulkhead, BulkheadPermit};
+pub use child::{ChildExit, ChildFailure, ChildSpec, RestartContext, StartFn};
+pub use circuit_breaker::{
+    BreakerClock, CircuitBreaker, CircuitSnapshot, CircuitState, CircuitStats, CircuitTransition,
+};
+pub use deadline::{AttemptRecord, BudgetReport, DeadlineRetry, TimeBudget};
+pub use decision_log::{Decision, DecisionAction, DecisionLog, RestartClause};
 pub use engine::Supervisor;
+pub use events::SupervisorEvent;
+pub use failure::{DefaultFailureClassifier, FailureClassifier, FailureKind};
+pub use failure_detector::PhiAccrualDetector;
+pub use fallback::{FallbackFn, ProtectedOperation};
 pub use health_check::{HealthCheck, HealthStatus};
+pub use health_runner::HealthCheckRunner;
+pub use hedge::Hedger;
+pub use memory::{MemoryAccount, MemoryLimited, TrackingAllocator};
+#[cfg(feature = "probes")]
+pub use probe::{CommandProbe, HttpProbe, TcpProbe};
+pub use probe::{HealthProbe, ProbeConfig};
+#[cfg(feature = "hot-reload")]
+pub use reload::{
+    Behavior, BehaviorConstructor, BehaviorLoader, DylibLoader, LoadedBehavior, ReloadableAgent,
+};
 pub use restart_policy::{RestartPolicy, RestartStrategy};
+pub use snapshot::SupervisorSnapshot;
+pub use termination::{ShutdownHooks, ShutdownReason, StopHandle};
+pub use tree::{Escalation, SupervisionTree, TreeExit};
+pub use watchdog::Watchdog;
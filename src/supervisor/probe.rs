@@ -0,0 +1,284 @@
+use super::HealthStatus;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "probes")]
+use {
+    std::time::Duration,
+    tokio::io::{AsyncReadExt, AsyncWriteExt},
+    tokio::net::TcpStream,
+    tokio::process::Command,
+    tokio::time::timeout,
+};
+
+/// Health probe executed against an agent or its dependencies
+#[async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// Run the probe
+    async fn probe(&self) -> HealthStatus;
+}
+
+#[cfg(feature = "probes")]
+/// Probe that succeeds when a TCP connection can be opened
+#[derive(Debug, Clone)]
+pub struct TcpProbe {
+    address: String,
+    timeout: Duration,
+}
+
+#[cfg(feature = "probes")]
+impl TcpProbe {
+    /// Create a new TCP probe
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Set connect timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get address
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+}
+
+#[cfg(feature = "probes")]
+#[async_trait]
+impl HealthProbe for TcpProbe {
+    async fn probe(&self) -> HealthStatus {
+        match timeout(self.timeout, TcpStream::connect(&self.address)).await {
+            Ok(Ok(_)) => HealthStatus::Healthy,
+            _ => HealthStatus::Unhealthy,
+        }
+    }
+}
+
+#[cfg(feature = "probes")]
+/// Probe that issues a plain HTTP GET and checks the response status
+///
+/// Only `http://` URLs are supported.
+#[derive(Debug, Clone)]
+pub struct HttpProbe {
+    url: String,
+    expected_status: u16,
+    timeout: Duration,
+}
+
+#[cfg(feature = "probes")]
+impl HttpProbe {
+    /// Create a new HTTP probe expecting status 200
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            expected_status: 200,
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Set expected response status
+    pub fn with_expected_status(mut self, status: u16) -> Self {
+        self.expected_status = status;
+        self
+    }
+
+    /// Set request timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Split the URL into (host:port, host, path)
+    fn target(&self) -> Option<(String, String, String)> {
+        let rest = self.url.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        if authority.is_empty() {
+            return None;
+        }
+        let address = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{}:80", authority)
+        };
+        Some((address, authority.to_string(), path.to_string()))
+    }
+
+    async fn request(&self) -> Option<u16> {
+        let (address, host, path) = self.target()?;
+        let mut stream = TcpStream::connect(address).await.ok()?;
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            path, host
+        );
+        stream.write_all(request.as_bytes()).await.ok()?;
+
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 256];
+        while !buffer.windows(2).any(|window| window == b"\r\n") {
+            let read = stream.read(&mut chunk).await.ok()?;
+            if read == 0 {
+                break;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+
+        let status_line = String::from_utf8_lossy(&buffer);
+        status_line.split_whitespace().nth(1)?.parse().ok()
+    }
+}
+
+#[cfg(feature = "probes")]
+#[async_trait]
+impl HealthProbe for HttpProbe {
+    async fn probe(&self) -> HealthStatus {
+        match timeout(self.timeout, self.request()).await {
+            Ok(Some(status)) if status == self.expected_status => HealthStatus::Healthy,
+            _ => HealthStatus::Unhealthy,
+        }
+    }
+}
+
+#[cfg(feature = "probes")]
+/// Probe that runs a command and checks for a zero exit status
+#[derive(Debug, Clone)]
+pub struct CommandProbe {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+#[cfg(feature = "probes")]
+impl CommandProbe {
+    /// Create a new command probe
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Add argument
+    pub fn with_arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Set execution timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get program
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+}
+
+#[cfg(feature = "probes")]
+#[async_trait]
+impl HealthProbe for CommandProbe {
+    async fn probe(&self) -> HealthStatus {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .kill_on_drop(true)
+            .status();
+
+        match timeout(self.timeout, status).await {
+            Ok(Ok(status)) if status.success() => HealthStatus::Healthy,
+            _ => HealthStatus::Unhealthy,
+        }
+    }
+}
+
+/// Probe configuration as found in the runtime configuration file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProbeConfig {
+    /// TCP connect probe
+    Tcp {
+        /// Address to connect to (host:port)
+        address: String,
+        /// Connect timeout in milliseconds
+        #[serde(default = "default_probe_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// HTTP GET probe
+    Http {
+        /// URL to request
+        url: String,
+        /// Expected response status
+        #[serde(default = "default_expected_status")]
+        expected_status: u16,
+        /// Request timeout in milliseconds
+        #[serde(default = "default_probe_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// Command probe
+    Command {
+        /// Program to execute
+        program: String,
+        /// Program arguments
+        #[serde(default)]
+        args: Vec<String>,
+        /// Execution timeout in milliseconds
+        #[serde(default = "default_probe_timeout_ms")]
+        timeout_ms: u64,
+    },
+}
+
+fn default_probe_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_expected_status() -> u16 {
+    200
+}
+
+#[cfg(feature = "probes")]
+impl ProbeConfig {
+    /// Build the configured probe
+    pub fn build(&self) -> Box<dyn HealthProbe> {
+        match self {
+            ProbeConfig::Tcp {
+                address,
+                timeout_ms,
+            } => Box::new(
+                TcpProbe::new(address.clone()).with_timeout(Duration::from_millis(*timeout_ms)),
+            ),
+            ProbeConfig::Http {
+                url,
+                expected_status,
+                timeout_ms,
+            } => Box::new(
+                HttpProbe::new(url.clone())
+                    .with_expected_status(*expected_status)
+                    .with_timeout(Duration::from_millis(*timeout_ms)),
+            ),
+            ProbeConfig::Command {
+                program,
+                args,
+                timeout_ms,
+            } => Box::new(
+                args.iter()
+                    .fold(CommandProbe::new(program.clone()), |probe, arg| {
+                        probe.with_arg(arg.clone())
+                    })
+                    .with_timeout(Duration::from_millis(*timeout_ms)),
+            ),
+        }
+    }
+}
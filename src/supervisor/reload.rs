@@ -0,0 +1,153 @@
+//! Hot reload of agent behavior
+//!
+//! A [`ReloadableAgent`] runs a swappable [`Behavior`]. Reloading quiesces
+//! the agent by waiting for the in-flight call, checkpoints the old
+//! behavior, loads the replacement, restores the checkpoint into it, and
+//! resumes. If loading or restoring fails the old behavior stays in place.
+
+use crate::{RuntimeError, SupervisorError};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Agent logic that can be replaced at runtime
+pub trait Behavior: Send {
+    /// Handle one input
+    fn handle(&mut self, input: &[u8]) -> Result<Vec<u8>, RuntimeError>;
+
+    /// Capture state to carry over to a replacement
+    fn checkpoint(&self) -> Result<Vec<u8>, RuntimeError> {
+        Ok(Vec::new())
+    }
+
+    /// Restore state captured from the previous implementation
+    fn restore(&mut self, _checkpoint: &[u8]) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+/// Behavior along with the library it was loaded from, if any
+pub struct LoadedBehavior {
+    // Dropped before the library that contains its code
+    behavior: Box<dyn Behavior>,
+    _library: Option<Arc<libloading::Library>>,
+}
+
+impl LoadedBehavior {
+    /// Wrap a behavior that is not backed by a dynamic library
+    pub fn new(behavior: Box<dyn Behavior>) -> Self {
+        Self {
+            behavior,
+            _library: None,
+        }
+    }
+}
+
+/// Source of behavior implementations
+pub trait BehaviorLoader: Send + Sync {
+    /// Load a fresh behavior
+    fn load(&self) -> Result<LoadedBehavior, RuntimeError>;
+}
+
+impl<F> BehaviorLoader for F
+where
+    F: Fn() -> Result<Box<dyn Behavior>, RuntimeError> + Send + Sync,
+{
+    fn load(&self) -> Result<LoadedBehavior, RuntimeError> {
+        self().map(LoadedBehavior::new)
+    }
+}
+
+/// Constructor exported by behavior libraries as `agentropic_behavior`
+pub type BehaviorConstructor = fn() -> Box<dyn Behavior>;
+
+/// Loader for behaviors in dynamic libraries
+///
+/// The library must export `agentropic_behavior` with the
+/// [`BehaviorConstructor`] signature and be built with the same compiler
+/// version as the host, since `dyn Behavior` uses the Rust ABI.
+#[derive(Debug, Clone)]
+pub struct DylibLoader {
+    path: PathBuf,
+}
+
+impl DylibLoader {
+    /// Create a loader for the library at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Get library path
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+impl BehaviorLoader for DylibLoader {
+    fn load(&self) -> Result<LoadedBehavior, RuntimeError> {
+        let error = |e: libloading::Error| {
+            RuntimeError::from(SupervisorError::LoadFailed(format!(
+                "{}: {}",
+                self.path.display(),
+                e
+            )))
+        };
+        // SAFETY: loading runs the library's initializers; callers opt in by
+        // pointing the loader at a trusted behavior library
+        let library = unsafe { libloading::Library::new(&self.path) }.map_err(error)?;
+        // SAFETY: the exported symbol is required to have this signature
+        let constructor = unsafe { library.get::<BehaviorConstructor>(b"agentropic_behavior\0") }
+            .map_err(error)?;
+        let behavior = constructor();
+        Ok(LoadedBehavior {
+            behavior,
+            _library: Some(Arc::new(library)),
+        })
+    }
+}
+
+struct Slot {
+    loaded: LoadedBehavior,
+    generation: u64,
+}
+
+/// Agent whose behavior can be swapped without restarting it
+pub struct ReloadableAgent {
+    slot: Mutex<Slot>,
+}
+
+impl ReloadableAgent {
+    /// Create an agent running `behavior` as generation 1
+    pub fn new(behavior: LoadedBehavior) -> Self {
+        Self {
+            slot: Mutex::new(Slot {
+                loaded: behavior,
+                generation: 1,
+            }),
+        }
+    }
+
+    /// Handle one input with the current behavior
+    pub async fn handle(&self, input: &[u8]) -> Result<Vec<u8>, RuntimeError> {
+        self.slot.lock().await.loaded.behavior.handle(input)
+    }
+
+    /// Get the number of behaviors loaded so far, including the current one
+    pub async fn generation(&self) -> u64 {
+        self.slot.lock().await.generation
+    }
+
+    /// Quiesce, checkpoint, swap in a behavior from `loader`, restore, and resume
+    ///
+    /// Returns the new generation. On failure the previous behavior keeps running.
+    pub async fn reload(&self, loader: &dyn BehaviorLoader) -> Result<u64, RuntimeError> {
+        let mut slot = self.slot.lock().await;
+        let checkpoint = slot.loaded.behavior.checkpoint()?;
+        let mut replacement = loader.load()?;
+        replacement.behavior.restore(&checkpoint)?;
+
+        slot.loaded = replacement;
+        slot.generation += 1;
+        Ok(slot.generation)
+    }
+}
@@ -1,4 +1,11 @@
+use super::{BackoffStrategy, ChildFailure, FailureKind, RestartClause};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Upper bound for exponential restart delays
+const MAX_BACKOFF_SECONDS: u64 = 60;
 
 /// Restart strategy
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,6 +26,25 @@ pub struct RestartPolicy {
     strategy: RestartStrategy,
     max_retries: Option<u32>,
     backoff_seconds: u64,
+    #[serde(default)]
+    intensity: Option<(u32, Duration)>,
+    #[serde(skip)]
+    backoff: Option<Arc<dyn BackoffStrategy>>,
+}
+
+/// Policies are equal when their settings match and they share the same
+/// custom backoff curve, if any
+impl PartialEq for RestartPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        self.strategy == other.strategy
+            && self.max_retries == other.max_retries
+            && self.backoff_seconds == other.backoff_seconds
+            && self.intensity == other.intensity
+            && match (&self.backoff, &other.backoff) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
 }
 
 impl RestartPolicy {
@@ -28,6 +54,8 @@ impl RestartPolicy {
             strategy,
             max_retries: None,
             backoff_seconds: 1,
+            intensity: None,
+            backoff: None,
         }
     }
 
@@ -43,6 +71,24 @@ impl RestartPolicy {
         self
     }
 
+    /// Give up once a child needs more than `restarts` restarts within `window`
+    ///
+    /// Unlike max retries, restarts that fall out of the window no longer
+    /// count, so a child that fails rarely is restarted indefinitely.
+    pub fn with_max_restarts_in_window(mut self, restarts: u32, window: Duration) -> Self {
+        self.intensity = Some((restarts, window));
+        self
+    }
+
+    /// Compute restart delays with a custom curve instead of the strategy's
+    ///
+    /// Custom curves are not serialized; a deserialized policy falls back to
+    /// its strategy's delays.
+    pub fn with_backoff(mut self, backoff: impl BackoffStrategy + 'static) -> Self {
+        self.backoff = Some(Arc::new(backoff));
+        self
+    }
+
     /// Get strategy
     pub fn strategy(&self) -> RestartStrategy {
         self.strategy
@@ -57,6 +103,98 @@ impl RestartPolicy {
     pub fn backoff_seconds(&self) -> u64 {
         self.backoff_seconds
     }
+
+    /// Get restart intensity limit and its window
+    pub fn max_restarts_in_window(&self) -> Option<(u32, Duration)> {
+        self.intensity
+    }
+
+    /// Decide whether a child should be started again
+    ///
+    /// `failure` is `None` when the child exited cleanly; `attempts` is the
+    /// number of starts performed so far. Permanent failures and failures
+    /// classified as [`FailureKind::Fatal`] are never restarted.
+    pub fn should_restart(&self, failure: Option<&ChildFailure>, attempts: u32) -> bool {
+        self.decide(failure, attempts).0
+    }
+
+    /// Decide whether a child should be started again, and which clause applied
+    pub fn decide(&self, failure: Option<&ChildFailure>, attempts: u32) -> (bool, RestartClause) {
+        if let Some(class) = failure
+            .map(ChildFailure::class)
+            .filter(|class| !class.is_retryable())
+        {
+            return (false, RestartClause::NotRetryable { class });
+        }
+        if failure.is_some_and(|failure| failure.kind() == FailureKind::Fatal) {
+            return (false, RestartClause::Fatal);
+        }
+
+        let (wants_restart, clause) = match self.strategy {
+            RestartStrategy::Never => (false, RestartClause::StrategyNever),
+            RestartStrategy::Always => (true, RestartClause::StrategyAlways),
+            RestartStrategy::OnFailure | RestartStrategy::ExponentialBackoff => match failure {
+                Some(_) => (true, RestartClause::RestartOnFailure),
+                None => (false, RestartClause::CleanExit),
+            },
+        };
+
+        let restarts = attempts.saturating_sub(1);
+        match self.max_retries {
+            Some(max_retries) if wants_restart && restarts >= max_retries => {
+                (false, RestartClause::MaxRetriesReached { max_retries })
+            }
+            _ => (wants_restart, clause),
+        }
+    }
+
+    /// Get delay before the given restart (1 for the first restart)
+    pub fn restart_delay(&self, restart: u32) -> Duration {
+        if let Some(backoff) = &self.backoff {
+            return backoff.delay(restart);
+        }
+        match self.strategy {
+            RestartStrategy::ExponentialBackoff => {
+                let exponent = restart.saturating_sub(1).min(32);
+                let seconds = self
+                    .backoff_seconds
+                    .saturating_mul(1u64 << exponent)
+                    .min(MAX_BACKOFF_SECONDS.max(self.backoff_seconds));
+                Duration::from_secs(seconds)
+            }
+            _ => Duration::from_secs(self.backoff_seconds),
+        }
+    }
+}
+
+/// Times of a child's recent restarts, for enforcing restart intensity
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RestartHistory {
+    restarts: VecDeque<Instant>,
+}
+
+impl RestartHistory {
+    /// Record a restart at `now` unless it would exceed the policy's intensity
+    ///
+    /// Returns the clause to give up with when the limit is reached.
+    pub(crate) fn admit(&mut self, policy: &RestartPolicy, now: Instant) -> Option<RestartClause> {
+        let (max_restarts, window) = policy.intensity?;
+        while self
+            .restarts
+            .front()
+            .is_some_and(|restart| now.saturating_duration_since(*restart) >= window)
+        {
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= max_restarts as usize {
+            return Some(RestartClause::IntensityExceeded {
+                max_restarts,
+                window,
+            });
+        }
+        self.restarts.push_back(now);
+        None
+    }
 }
 
 impl Default for RestartPolicy {
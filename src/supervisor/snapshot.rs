@@ -0,0 +1,52 @@
+//! Supervisor checkpoints
+//!
+//! A [`SupervisorSnapshot`] captures what a supervisor learned while
+//! running: restart counters, agent health, circuit breaker states, and its
+//! decision log. `Supervisor::restore_from` rebuilds a supervisor from it
+//! after a crash, so children resume their restart budget instead of
+//! starting over. Start functions are code and are added again as usual.
+
+use super::{CircuitSnapshot, DecisionLog, HealthStatus};
+use crate::resources::ResourceQuota;
+use agentropic_core::AgentId;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Checkpoint of a supervisor, taken by `Supervisor::snapshot`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisorSnapshot {
+    /// Supervisor name
+    pub name: String,
+    /// Restarts of children that were still supervised
+    pub restarts: Vec<(AgentId, u32)>,
+    /// Health of supervised agents
+    pub health: Vec<(AgentId, HealthStatus)>,
+    /// Per-agent circuit breakers
+    pub circuits: Vec<(AgentId, CircuitSnapshot)>,
+    /// Per-agent resource quotas
+    #[serde(default)]
+    pub resource_quotas: Vec<(AgentId, ResourceQuota)>,
+    /// Restart decisions
+    pub decisions: DecisionLog,
+    /// Time the snapshot was taken
+    pub taken_at: SystemTime,
+}
+
+impl SupervisorSnapshot {
+    /// Write the snapshot as JSON, replacing the file atomically
+    #[cfg(feature = "persistence")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), crate::RuntimeError> {
+        crate::scheduler::state::save_json(path.as_ref(), self, "supervisor snapshot", state_error)
+    }
+
+    /// Read a snapshot written by [`SupervisorSnapshot::save`], or `None` if the file does not exist
+    #[cfg(feature = "persistence")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Option<Self>, crate::RuntimeError> {
+        crate::scheduler::state::load_json(path.as_ref(), "supervisor snapshot", state_error)
+    }
+}
+
+#[cfg(feature = "persistence")]
+fn state_error(message: String) -> crate::RuntimeError {
+    crate::SupervisorError::State(message).into()
+}
@@ -0,0 +1,199 @@
+//! Two-phase termination of supervised children
+//!
+//! When the supervisor ends an attempt that is still running, because its
+//! watchdog expired, it went over its memory limit or quota, or a
+//! [`StopHandle`] asked it to stop or to be killed, a child declared with
+//! [`ChildSpec::with_shutdown_hooks`](super::ChildSpec::with_shutdown_hooks)
+//! is not dropped at once. Its hooks first get to flush state in
+//! [`ShutdownHooks::prepare_shutdown`], then are told to stop in
+//! [`ShutdownHooks::terminate`], and the attempt may then exit on its own.
+//! Whatever is left at the end of the grace period is killed. Children
+//! without hooks are dropped at once. A child being suspended keeps running,
+//! so only its `prepare_shutdown` hook runs.
+
+use crate::timer::SharedClock;
+use crate::RuntimeError;
+use agentropic_core::AgentId;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::{JoinError, JoinHandle};
+
+/// Default time a child gets to shut down before it is killed
+pub(crate) const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Why a running child is being shut down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ShutdownReason {
+    /// The child stopped petting its watchdog and will be restarted
+    WatchdogExpired,
+    /// The child was asked to stop through its [`StopHandle`]
+    Requested,
+    /// The child went over its memory limit or quota and will be restarted
+    MemoryLimitExceeded,
+    /// The child was killed through [`StopHandle::kill`], as chaos testing
+    /// does, and will be restarted
+    Killed,
+    /// The child is being suspended and keeps running
+    Suspended,
+}
+
+/// Callbacks run before a running child is killed or suspended
+///
+/// Both hooks default to doing nothing. Errors are logged and do not stop
+/// the shutdown.
+#[async_trait]
+pub trait ShutdownHooks: Send + Sync {
+    /// Flush state before the child is stopped, finishing by `deadline`
+    async fn prepare_shutdown(
+        &self,
+        _agent_id: AgentId,
+        _reason: ShutdownReason,
+        _deadline: Instant,
+    ) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+
+    /// Tell the running attempt to exit
+    async fn terminate(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct StopSignal {
+    requested: AtomicBool,
+    killed: AtomicBool,
+    notify: Notify,
+}
+
+/// Handle asking a supervised child to stop
+///
+/// Get it from [`ChildSpec::stop_handle`](super::ChildSpec::stop_handle).
+/// A stop request ends the current run of the child: the running attempt is
+/// shut down and not restarted. Each run starts with no request pending.
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    signal: Arc<StopSignal>,
+}
+
+impl StopHandle {
+    /// Ask the child to stop
+    pub fn stop(&self) {
+        self.signal.requested.store(true, Ordering::Relaxed);
+        self.signal.notify.notify_waiters();
+    }
+
+    /// Check if a stop was requested
+    pub fn is_stop_requested(&self) -> bool {
+        self.signal.requested.load(Ordering::Relaxed)
+    }
+
+    /// Kill the running attempt, which fails with
+    /// [`SupervisorError::Killed`](crate::SupervisorError::Killed) and is
+    /// restarted as its restart policy decides
+    ///
+    /// The attempt is shut down through its hooks like any other. A kill
+    /// asked for between attempts does nothing.
+    pub fn kill(&self) {
+        self.signal.killed.store(true, Ordering::Relaxed);
+        self.signal.notify.notify_waiters();
+    }
+
+    /// Wait until a stop is requested
+    pub async fn stopped(&self) {
+        loop {
+            let notified = self.signal.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_stop_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Wait until a stop or kill is requested, returning which
+    pub(crate) async fn requested(&self) -> ShutdownReason {
+        loop {
+            let notified = self.signal.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_stop_requested() {
+                return ShutdownReason::Requested;
+            }
+            if self.signal.killed.swap(false, Ordering::Relaxed) {
+                return ShutdownReason::Killed;
+            }
+            notified.await;
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.signal.requested.store(false, Ordering::Relaxed);
+        self.clear_kill();
+    }
+
+    pub(crate) fn clear_kill(&self) {
+        self.signal.killed.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Run the `prepare_shutdown` hook of a child that keeps running, until
+/// `deadline`
+#[cfg(feature = "scheduler")]
+pub(crate) async fn prepare(
+    hooks: &dyn ShutdownHooks,
+    agent_id: AgentId,
+    reason: ShutdownReason,
+    deadline: Instant,
+    clock: &SharedClock,
+) {
+    tokio::select! {
+        prepared = hooks.prepare_shutdown(agent_id, reason, deadline) => {
+            if let Err(error) = prepared {
+                tracing::warn!("Preparing agent {} for {:?} failed: {}", agent_id, reason, error);
+            }
+        }
+        _ = clock.sleep_until(deadline) => {
+            tracing::warn!("Preparing agent {} for {:?} ran past its grace period", agent_id, reason);
+        }
+    }
+}
+
+/// Run both shutdown hooks and wait for the attempt to exit, until `deadline`
+///
+/// Returns the attempt's outcome, or `None` if it was killed.
+pub(crate) async fn shut_down(
+    hooks: &dyn ShutdownHooks,
+    agent_id: AgentId,
+    reason: ShutdownReason,
+    deadline: Instant,
+    clock: &SharedClock,
+    running: &mut JoinHandle<Result<(), RuntimeError>>,
+) -> Option<Result<Result<(), RuntimeError>, JoinError>> {
+    let graceful = async {
+        if let Err(error) = hooks.prepare_shutdown(agent_id, reason, deadline).await {
+            tracing::warn!(
+                "Preparing agent {} for shutdown failed: {}",
+                agent_id,
+                error
+            );
+        }
+        if let Err(error) = hooks.terminate(agent_id).await {
+            tracing::warn!("Terminating agent {} failed: {}", agent_id, error);
+        }
+        (&mut *running).await
+    };
+    tokio::select! {
+        outcome = graceful => Some(outcome),
+        _ = clock.sleep_until(deadline) => {
+            tracing::warn!("Killing agent {} after its grace period ran out", agent_id);
+            running.abort();
+            None
+        }
+    }
+}
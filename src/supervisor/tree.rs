@@ -0,0 +1,379 @@
+//! Supervision trees
+//!
+//! A [`SupervisionTree`] runs its children concurrently. When a child fails
+//! and its restart policy allows a restart, the tree's [`Escalation`] decides
+//! which siblings restart with it. When the policy gives up, the tree stops
+//! all children and escalates the failure to its parent, which treats the
+//! whole subtree as one failed child.
+
+use super::restart_policy::RestartHistory;
+use super::{
+    ChildFailure, ChildSpec, Decision, DecisionAction, DecisionLog, FailureKind, RestartClause,
+    RestartContext, RestartPolicy,
+};
+use crate::error::panic_message;
+use crate::timer::{self, SharedClock};
+use crate::{DefaultClassifier, ErrorClassifier};
+use agentropic_core::AgentId;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::{AbortHandle, Id, JoinSet};
+
+/// Which children restart when one of them fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Escalation {
+    /// Restart only the failed child
+    #[default]
+    OneForOne,
+    /// Restart every running child
+    OneForAll,
+    /// Restart the failed child and every child added after it
+    RestForOne,
+}
+
+/// How a supervision tree stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeExit {
+    /// Every child completed
+    Completed,
+    /// A child failed beyond its restart policy and the tree gave up
+    Escalated {
+        /// Child that failed
+        agent_id: AgentId,
+        /// Failure passed to the parent
+        failure: ChildFailure,
+    },
+}
+
+/// Child of a supervision tree
+#[derive(Clone)]
+enum TreeChild {
+    Worker(ChildSpec),
+    Subtree {
+        tree: Arc<SupervisionTree>,
+        policy: RestartPolicy,
+    },
+}
+
+impl TreeChild {
+    fn agent_id(&self) -> AgentId {
+        match self {
+            TreeChild::Worker(spec) => *spec.agent_id(),
+            TreeChild::Subtree { tree, .. } => tree.id,
+        }
+    }
+
+    fn policy(&self) -> &RestartPolicy {
+        match self {
+            TreeChild::Worker(spec) => spec.policy(),
+            TreeChild::Subtree { policy, .. } => policy,
+        }
+    }
+
+    fn can_meet_deadline(&self, delay: Duration, now: Instant) -> bool {
+        match self {
+            TreeChild::Worker(spec) => spec.can_meet_deadline(delay, now),
+            TreeChild::Subtree { .. } => true,
+        }
+    }
+
+    fn start(
+        &self,
+        ctx: RestartContext,
+        delay: Duration,
+        clock: SharedClock,
+        classifier: Arc<dyn ErrorClassifier>,
+    ) -> BoxFuture<'static, Result<(), ChildFailure>> {
+        match self.clone() {
+            TreeChild::Worker(spec) => Box::pin(async move {
+                clock.sleep(delay).await;
+                (spec.start_fn())(ctx).await.map_err(|error| {
+                    let class = classifier.classify(&error);
+                    ChildFailure::Error {
+                        kind: FailureKind::of_class(&class),
+                        class,
+                        message: error.to_string(),
+                    }
+                })
+            }),
+            TreeChild::Subtree { tree, .. } => Box::pin(async move {
+                clock.sleep(delay).await;
+                match tree.run().await {
+                    TreeExit::Completed => Ok(()),
+                    TreeExit::Escalated { failure, .. } => Err(failure),
+                }
+            }),
+        }
+    }
+}
+
+/// Supervisor whose children may themselves be supervisors
+#[derive(Clone)]
+pub struct SupervisionTree {
+    id: AgentId,
+    name: String,
+    escalation: Escalation,
+    children: Vec<TreeChild>,
+    classifier: Arc<dyn ErrorClassifier>,
+    clock: SharedClock,
+    decisions: Arc<Mutex<DecisionLog>>,
+}
+
+impl SupervisionTree {
+    /// Create an empty tree restarting children one for one
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: AgentId::new(),
+            name: name.into(),
+            escalation: Escalation::default(),
+            children: Vec::new(),
+            classifier: Arc::new(DefaultClassifier),
+            clock: timer::system_clock(),
+            decisions: Arc::new(Mutex::new(DecisionLog::default())),
+        }
+    }
+
+    /// Set which siblings restart with a failed child
+    pub fn with_escalation(mut self, escalation: Escalation) -> Self {
+        self.escalation = escalation;
+        self
+    }
+
+    /// Set the error classifier used for restart decisions
+    pub fn with_classifier(mut self, classifier: impl ErrorClassifier + 'static) -> Self {
+        self.classifier = Arc::new(classifier);
+        self
+    }
+
+    /// Time restart delays with a clock other than the system clock
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Add a worker child
+    pub fn with_child(mut self, spec: ChildSpec) -> Self {
+        self.children.push(TreeChild::Worker(spec));
+        self
+    }
+
+    /// Add a nested tree, restarted as a whole according to `policy`
+    pub fn with_subtree(mut self, tree: SupervisionTree, policy: RestartPolicy) -> Self {
+        self.children.push(TreeChild::Subtree {
+            tree: Arc::new(tree),
+            policy,
+        });
+        self
+    }
+
+    /// Get the ID identifying this tree in its parent's decisions
+    pub fn id(&self) -> &AgentId {
+        &self.id
+    }
+
+    /// Get tree name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get escalation strategy
+    pub fn escalation(&self) -> Escalation {
+        self.escalation
+    }
+
+    /// Get number of direct children
+    pub fn child_count(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Get a copy of this tree's restart decisions
+    pub fn decision_log(&self) -> DecisionLog {
+        self.lock_decisions().clone()
+    }
+
+    fn lock_decisions(&self) -> std::sync::MutexGuard<'_, DecisionLog> {
+        self.decisions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn record_decision(
+        &self,
+        agent_id: AgentId,
+        attempt: u32,
+        action: DecisionAction,
+        clause: RestartClause,
+        delay: Option<Duration>,
+        failure: Option<&ChildFailure>,
+    ) {
+        self.lock_decisions().record(Decision {
+            agent_id,
+            at: self.clock.wall_time(),
+            attempt,
+            action,
+            clause,
+            delay,
+            failure: failure.map(|failure| failure.message().to_string()),
+        });
+    }
+
+    fn spawn_child(
+        &self,
+        running: &mut JoinSet<Result<(), ChildFailure>>,
+        tasks: &mut HashMap<Id, usize>,
+        index: usize,
+        ctx: RestartContext,
+        delay: Duration,
+    ) -> AbortHandle {
+        let child = &self.children[index];
+        let handle =
+            running.spawn(child.start(ctx, delay, self.clock.clone(), self.classifier.clone()));
+        tasks.insert(handle.id(), index);
+        handle
+    }
+
+    /// Run all children until they complete or a failure escalates past this tree
+    pub async fn run(&self) -> TreeExit {
+        let mut running = JoinSet::new();
+        let mut tasks = HashMap::new();
+        let mut handles: Vec<Option<AbortHandle>> = vec![None; self.children.len()];
+        let mut contexts: Vec<_> = self
+            .children
+            .iter()
+            .map(|child| RestartContext::new(child.agent_id()))
+            .collect();
+        let mut histories = vec![RestartHistory::default(); self.children.len()];
+        for (index, ctx) in contexts.iter().enumerate() {
+            handles[index] = Some(self.spawn_child(
+                &mut running,
+                &mut tasks,
+                index,
+                ctx.clone(),
+                Duration::ZERO,
+            ));
+        }
+
+        while let Some(joined) = running.join_next_with_id().await {
+            let (id, outcome) = match joined {
+                Ok((id, outcome)) => (id, outcome),
+                Err(error) if error.is_panic() => {
+                    let id = error.id();
+                    (
+                        id,
+                        Err(ChildFailure::Panic(panic_message(error.into_panic()))),
+                    )
+                }
+                Err(_) => continue,
+            };
+            // Aborted siblings have already been forgotten
+            let Some(index) = tasks.remove(&id) else {
+                continue;
+            };
+            handles[index] = None;
+            let child = &self.children[index];
+            let agent_id = child.agent_id();
+            let attempt = contexts[index].attempt();
+            let failure = outcome.err();
+
+            let (restart, mut clause) = child.policy().decide(failure.as_ref(), attempt);
+            let mut delay = child.policy().restart_delay(attempt);
+            if let Some(failure) = &failure {
+                delay = failure.class().retry_delay(delay);
+            }
+            if restart && !child.can_meet_deadline(delay, self.clock.instant()) {
+                clause = RestartClause::DeadlineUnreachable;
+            } else if restart {
+                if let Some(exceeded) = histories[index].admit(child.policy(), self.clock.instant())
+                {
+                    clause = exceeded;
+                }
+            }
+
+            if !restart
+                || matches!(
+                    clause,
+                    RestartClause::DeadlineUnreachable | RestartClause::IntensityExceeded { .. }
+                )
+            {
+                let Some(failure) = failure else {
+                    self.record_decision(
+                        agent_id,
+                        attempt,
+                        DecisionAction::Complete,
+                        clause,
+                        None,
+                        None,
+                    );
+                    continue;
+                };
+                self.record_decision(
+                    agent_id,
+                    attempt,
+                    DecisionAction::Escalate,
+                    clause,
+                    None,
+                    Some(&failure),
+                );
+                tracing::warn!(
+                    "Supervision tree {} escalating failure of {}: {}",
+                    self.name,
+                    agent_id,
+                    failure.message()
+                );
+                running.abort_all();
+                return TreeExit::Escalated { agent_id, failure };
+            }
+
+            let group = match self.escalation {
+                Escalation::OneForOne => index..index + 1,
+                Escalation::OneForAll => 0..self.children.len(),
+                Escalation::RestForOne => index..self.children.len(),
+            };
+            for member in group {
+                if member == index {
+                    self.record_decision(
+                        agent_id,
+                        attempt,
+                        DecisionAction::Restart,
+                        clause,
+                        Some(delay),
+                        failure.as_ref(),
+                    );
+                    contexts[index] = match &failure {
+                        Some(failure) => {
+                            contexts[index].after_failure(failure.clone(), self.clock.instant())
+                        }
+                        None => contexts[index].after_success(),
+                    };
+                } else {
+                    // Siblings that already completed stay completed
+                    let Some(handle) = handles[member].take() else {
+                        continue;
+                    };
+                    handle.abort();
+                    tasks.remove(&handle.id());
+                    self.record_decision(
+                        self.children[member].agent_id(),
+                        contexts[member].attempt(),
+                        DecisionAction::Restart,
+                        RestartClause::SiblingFailed { sibling: agent_id },
+                        Some(delay),
+                        None,
+                    );
+                    contexts[member] = contexts[member].after_success();
+                }
+                handles[member] = Some(self.spawn_child(
+                    &mut running,
+                    &mut tasks,
+                    member,
+                    contexts[member].clone(),
+                    delay,
+                ));
+            }
+        }
+        TreeExit::Completed
+    }
+}
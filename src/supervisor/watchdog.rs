@@ -0,0 +1,114 @@
+//! Watchdog timers
+//!
+//! A child declared with [`ChildSpec::with_watchdog`](super::ChildSpec::with_watchdog)
+//! finds a [`Watchdog`] in its [`RestartContext`](super::RestartContext) and
+//! must pet it at least once per interval. A child that stops petting is
+//! treated as hung: the supervisor kills the attempt with
+//! [`SupervisorError::WatchdogExpired`] and applies the restart policy. This
+//! catches livelocks that a health check, which only sees that the agent
+//! still answers, would miss.
+
+use crate::timer::{self, SharedClock};
+use crate::{RuntimeError, SupervisorError};
+use agentropic_core::AgentId;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct Inner {
+    interval: Duration,
+    last_pet: Mutex<Instant>,
+    pets: AtomicU64,
+    fired: AtomicBool,
+    clock: SharedClock,
+}
+
+/// Handle a running child pets to show it is making progress
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    inner: Arc<Inner>,
+}
+
+impl Watchdog {
+    /// Create a watchdog that expires `interval` after the last pet
+    pub fn new(interval: Duration) -> Self {
+        Self::with_clock(interval, timer::system_clock())
+    }
+
+    /// Create a watchdog timed by a clock other than the system clock
+    pub fn with_clock(interval: Duration, clock: SharedClock) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                interval,
+                last_pet: Mutex::new(clock.instant()),
+                pets: AtomicU64::new(0),
+                fired: AtomicBool::new(false),
+                clock,
+            }),
+        }
+    }
+
+    fn lock_last_pet(&self) -> std::sync::MutexGuard<'_, Instant> {
+        self.inner
+            .last_pet
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Show the child is making progress, restarting the interval
+    pub fn pet(&self) {
+        *self.lock_last_pet() = self.inner.clock.instant();
+        self.inner.pets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get the interval within which the child must pet
+    pub fn interval(&self) -> Duration {
+        self.inner.interval
+    }
+
+    /// Get number of pets so far
+    pub fn pets(&self) -> u64 {
+        self.inner.pets.load(Ordering::Relaxed)
+    }
+
+    /// Check if the interval passed without a pet
+    pub fn is_overdue(&self) -> bool {
+        self.inner.clock.instant() >= self.deadline()
+    }
+
+    /// Check if the watchdog killed the attempt it guarded
+    pub fn has_fired(&self) -> bool {
+        self.inner.fired.load(Ordering::Relaxed)
+    }
+
+    fn deadline(&self) -> Instant {
+        *self.lock_last_pet() + self.inner.interval
+    }
+
+    /// Wait until the interval passes without a pet
+    pub async fn expired(&self) {
+        loop {
+            let deadline = self.deadline();
+            self.inner.clock.sleep_until(deadline).await;
+            if self.is_overdue() {
+                return;
+            }
+        }
+    }
+
+    /// Mark the attempt it guarded as killed, returning its error
+    pub(crate) fn fire(&self, agent_id: AgentId) -> RuntimeError {
+        self.inner.fired.store(true, Ordering::Relaxed);
+        tracing::warn!(
+            "Watchdog of agent {} expired after {:?} without a pet",
+            agent_id,
+            self.inner.interval
+        );
+        SupervisorError::WatchdogExpired {
+            agent: agent_id.to_string(),
+            interval: self.inner.interval,
+        }
+        .into()
+    }
+}
@@ -0,0 +1,190 @@
+use agentropic_runtime_core::Clock;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Clock shared by the runtime's time-dependent modules
+///
+/// Extends [`Clock`] with instants and sleeping, so the scheduler,
+/// supervisor, retries, health checks, and timers can all be driven by a
+/// [`VirtualClock`] in tests and simulations.
+pub trait RuntimeClock: Clock + Send + Sync + std::fmt::Debug {
+    /// Get the current instant
+    fn instant(&self) -> Instant;
+
+    /// Sleep until the deadline
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'_, ()>;
+
+    /// Sleep for the duration
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+        self.sleep_until(self.instant() + duration)
+    }
+
+    /// Get the current wall-clock time, such as for cron schedules and timestamps
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Shared handle to a [`RuntimeClock`]
+pub type SharedClock = Arc<dyn RuntimeClock>;
+
+/// Get a shared handle to the system clock
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock::new())
+}
+
+/// Clock reading the monotonic system time
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    /// Create a new clock with its origin at the current instant
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+
+    /// Convert a clock reading back into an instant
+    pub fn instant(&self, at: Duration) -> Instant {
+        self.origin + at
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+impl RuntimeClock for SystemClock {
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'_, ()> {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
+
+#[derive(Debug, Default)]
+struct VirtualState {
+    now: Duration,
+    sleepers: Vec<(Instant, Waker)>,
+}
+
+/// Thread-safe clock advanced by hand
+///
+/// Sleeps complete only when the clock is advanced past their deadline, so
+/// long timeouts and backoffs can be exercised instantly.
+#[derive(Debug)]
+pub struct VirtualClock {
+    origin: Instant,
+    epoch: SystemTime,
+    state: Mutex<VirtualState>,
+}
+
+impl VirtualClock {
+    /// Create a new clock at its origin, with wall-clock time starting now
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+
+    /// Create a new clock whose wall-clock time starts at `epoch`
+    ///
+    /// A fixed epoch makes cron schedules and timestamps reproducible.
+    pub fn starting_at(epoch: SystemTime) -> Self {
+        Self {
+            origin: Instant::now(),
+            epoch,
+            state: Mutex::new(VirtualState::default()),
+        }
+    }
+
+    /// Move the clock forward, waking sleepers whose deadline passed
+    pub fn advance(&self, by: Duration) {
+        let sleepers = {
+            let mut state = self.lock();
+            state.now += by;
+            std::mem::take(&mut state.sleepers)
+        };
+        sleepers.into_iter().for_each(|(_, waker)| waker.wake());
+    }
+
+    /// Get number of sleeps waiting for the clock to advance
+    pub fn sleepers(&self) -> usize {
+        self.lock().sleepers.len()
+    }
+
+    /// Get the earliest deadline of the waiting sleeps
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.lock()
+            .sleepers
+            .iter()
+            .map(|(deadline, _)| *deadline)
+            .min()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VirtualState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.lock().now
+    }
+}
+
+impl RuntimeClock for VirtualClock {
+    fn instant(&self) -> Instant {
+        self.origin + self.now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.epoch + self.now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'_, ()> {
+        Box::pin(VirtualSleep {
+            clock: self,
+            deadline,
+        })
+    }
+}
+
+struct VirtualSleep<'a> {
+    clock: &'a VirtualClock,
+    deadline: Instant,
+}
+
+impl Future for VirtualSleep<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.clock.lock();
+        if self.clock.origin + state.now >= self.deadline {
+            return Poll::Ready(());
+        }
+        state.sleepers.push((self.deadline, cx.waker().clone()));
+        Poll::Pending
+    }
+}
@@ -0,0 +1,170 @@
+//! Timers and scheduler wakeups
+//!
+//! With the `io-uring` feature on Linux, all timers are driven by a single
+//! thread waiting on io_uring timeouts instead of one tokio sleep per timer.
+//! Elsewhere, or if the ring cannot be created, timers fall back to tokio.
+//! Schedulers wait for work on [`TimerService::shared`] unless given another
+//! clock.
+
+mod clock;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
+
+pub use agentropic_runtime_core::{Clock, ManualClock};
+pub use clock::{system_clock, RuntimeClock, SharedClock, SystemClock, VirtualClock};
+
+use futures::future::BoxFuture;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Timer backend in use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerBackend {
+    /// One tokio sleep per timer
+    Tokio,
+    /// Shared io_uring timeout driver
+    IoUring,
+}
+
+enum Driver {
+    Tokio,
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    IoUring(uring::UringDriver),
+}
+
+/// Timer service used for sleeps and scheduler wakeups
+///
+/// It is itself a [`RuntimeClock`], so it can be handed to anything taking a
+/// [`SharedClock`].
+pub struct TimerService {
+    driver: Driver,
+    clock: Option<SharedClock>,
+    system: SystemClock,
+}
+
+impl TimerService {
+    /// Create a timer service with the best available backend
+    pub fn new() -> Self {
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        match uring::UringDriver::start() {
+            Ok(driver) => {
+                return Self {
+                    driver: Driver::IoUring(driver),
+                    clock: None,
+                    system: SystemClock::new(),
+                }
+            }
+            Err(error) => {
+                tracing::debug!("io_uring timers unavailable, using tokio: {}", error);
+            }
+        }
+
+        Self::tokio()
+    }
+
+    /// Create a timer service backed by tokio sleeps
+    pub fn tokio() -> Self {
+        Self {
+            driver: Driver::Tokio,
+            clock: None,
+            system: SystemClock::new(),
+        }
+    }
+
+    /// Get the process-wide timer service with the best available backend
+    ///
+    /// Created on first use and shared by every scheduler without a clock of
+    /// its own, so a single driver serves all their wakeups.
+    pub fn shared() -> Arc<Self> {
+        static SHARED: OnceLock<Arc<TimerService>> = OnceLock::new();
+        SHARED.get_or_init(|| Arc::new(Self::new())).clone()
+    }
+
+    /// Drive sleeps from a clock instead of the backend
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Get the backend in use
+    pub fn backend(&self) -> TimerBackend {
+        match self.driver {
+            Driver::Tokio => TimerBackend::Tokio,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Driver::IoUring(_) => TimerBackend::IoUring,
+        }
+    }
+
+    /// Sleep until the deadline
+    pub async fn sleep_until(&self, deadline: Instant) {
+        if let Some(clock) = &self.clock {
+            return clock.sleep_until(deadline).await;
+        }
+        match &self.driver {
+            Driver::Tokio => tokio::time::sleep_until(deadline.into()).await,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Driver::IoUring(driver) => {
+                let _ = driver.register(deadline).await;
+            }
+        }
+    }
+
+    /// Sleep for the duration
+    pub async fn sleep(&self, duration: Duration) {
+        self.sleep_until(RuntimeClock::instant(self) + duration)
+            .await;
+    }
+
+    /// Get number of timers waiting in the shared driver
+    pub fn pending(&self) -> usize {
+        match &self.driver {
+            Driver::Tokio => 0,
+            #[cfg(all(feature = "io-uring", target_os = "linux"))]
+            Driver::IoUring(driver) => driver.pending(),
+        }
+    }
+}
+
+impl Default for TimerService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for TimerService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimerService")
+            .field("backend", &self.backend())
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
+
+impl Clock for TimerService {
+    fn now(&self) -> Duration {
+        match &self.clock {
+            Some(clock) => clock.now(),
+            None => self.system.now(),
+        }
+    }
+}
+
+impl RuntimeClock for TimerService {
+    fn instant(&self) -> Instant {
+        match &self.clock {
+            Some(clock) => clock.instant(),
+            None => Instant::now(),
+        }
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'_, ()> {
+        Box::pin(TimerService::sleep_until(self, deadline))
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        match &self.clock {
+            Some(clock) => clock.wall_time(),
+            None => SystemTime::now(),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use io_uring::{opcode, types, IoUring};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+const TIMEOUT_TOKEN: u64 = 1;
+const WAKE_TOKEN: u64 = 2;
+
+/// Pending timer entry, ordered by deadline
+struct TimerEntry {
+    deadline: Instant,
+    sequence: u64,
+    sender: oneshot::Sender<()>,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    // Reversed so that a max-heap yields the earliest deadline first
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other
+            .deadline
+            .cmp(&self.deadline)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct Shared {
+    timers: Mutex<BinaryHeap<TimerEntry>>,
+    sequence: Mutex<u64>,
+    stopped: AtomicBool,
+    wake_fd: OwnedFd,
+}
+
+impl Shared {
+    fn wake(&self) {
+        let value: u64 = 1;
+        // SAFETY: writes 8 bytes from a live u64 to an eventfd we own
+        unsafe {
+            libc::write(
+                self.wake_fd.as_raw_fd(),
+                &value as *const u64 as *const libc::c_void,
+                std::mem::size_of::<u64>(),
+            );
+        }
+    }
+}
+
+/// Single thread driving all timers through io_uring timeouts
+pub(super) struct UringDriver {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl UringDriver {
+    pub(super) fn start() -> io::Result<Self> {
+        let ring = IoUring::new(8)?;
+
+        // SAFETY: eventfd returns a new descriptor or -1
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd is a freshly created descriptor owned by nobody else
+        let wake_fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let shared = Arc::new(Shared {
+            timers: Mutex::new(BinaryHeap::new()),
+            sequence: Mutex::new(0),
+            stopped: AtomicBool::new(false),
+            wake_fd,
+        });
+
+        let thread_shared = shared.clone();
+        let thread = std::thread::Builder::new()
+            .name("agentropic-timer".to_string())
+            .spawn(move || {
+                if let Err(error) = drive(ring, &thread_shared) {
+                    tracing::error!("io_uring timer driver stopped: {}", error);
+                }
+            })?;
+
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    pub(super) fn register(&self, deadline: Instant) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        let sequence = {
+            let mut sequence = self.shared.sequence.lock().unwrap();
+            *sequence += 1;
+            *sequence
+        };
+
+        let is_earliest = {
+            let mut timers = self.shared.timers.lock().unwrap();
+            // Sleeps dropped before their deadline leave their entries behind
+            timers.retain(|entry| !entry.sender.is_closed());
+            let is_earliest = timers.peek().is_none_or(|next| deadline < next.deadline);
+            timers.push(TimerEntry {
+                deadline,
+                sequence,
+                sender,
+            });
+            is_earliest
+        };
+
+        if is_earliest {
+            self.shared.wake();
+        }
+        receiver
+    }
+
+    pub(super) fn pending(&self) -> usize {
+        let mut timers = self.shared.timers.lock().unwrap();
+        timers.retain(|entry| !entry.sender.is_closed());
+        timers.len()
+    }
+}
+
+impl Drop for UringDriver {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        self.shared.wake();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn drive(mut ring: IoUring, shared: &Shared) -> io::Result<()> {
+    let mut wake_buffer = [0u8; 8];
+    let mut wake_pending = false;
+    let mut armed_deadline: Option<Instant> = None;
+
+    while !shared.stopped.load(Ordering::SeqCst) {
+        let next_deadline = fire_expired(shared);
+
+        if !wake_pending {
+            let read = opcode::Read::new(
+                types::Fd(shared.wake_fd.as_raw_fd()),
+                wake_buffer.as_mut_ptr(),
+                wake_buffer.len() as u32,
+            )
+            .build()
+            .user_data(WAKE_TOKEN);
+            // SAFETY: the buffer lives on this thread's stack for the whole loop
+            unsafe { ring.submission().push(&read) }
+                .map_err(|_| io::Error::other("submission queue full"))?;
+            wake_pending = true;
+        }
+
+        // Only arm a new timeout when the earliest deadline moved earlier
+        if let Some(deadline) = next_deadline {
+            if armed_deadline.is_none_or(|armed| deadline < armed) {
+                let timespec: types::Timespec =
+                    deadline.saturating_duration_since(Instant::now()).into();
+                let timeout = opcode::Timeout::new(&timespec)
+                    .build()
+                    .user_data(TIMEOUT_TOKEN);
+                // SAFETY: the kernel copies the timespec during submission below
+                unsafe { ring.submission().push(&timeout) }
+                    .map_err(|_| io::Error::other("submission queue full"))?;
+                ring.submit()?;
+                armed_deadline = Some(deadline);
+            }
+        }
+
+        match ring.submit_and_wait(1) {
+            Ok(_) => {}
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+
+        for entry in ring.completion() {
+            match entry.user_data() {
+                WAKE_TOKEN => wake_pending = false,
+                TIMEOUT_TOKEN => armed_deadline = None,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Complete all expired timers, drop cancelled ones, and return the next
+/// deadline
+fn fire_expired(shared: &Shared) -> Option<Instant> {
+    let now = Instant::now();
+    let mut timers = shared.timers.lock().unwrap();
+    timers.retain(|entry| !entry.sender.is_closed());
+    while let Some(next) = timers.peek() {
+        if next.deadline > now {
+            return Some(next.deadline);
+        }
+        if let Some(entry) = timers.pop() {
+            let _ = entry.sender.send(());
+        }
+    }
+    None
+}
@@ -1,4 +1,5 @@
 use agentropic_runtime::prelude::*;
+use std::time::Duration;
 
 #[tokio::test]
 async fn full_runtime_lifecycle() {
@@ -24,7 +25,7 @@ async fn full_runtime_lifecycle() {
     assert_eq!(handle.agent_count().await, 2);
 
     // Shutdown
-    runtime.shutdown().await.unwrap();
+    runtime.shutdown(Duration::from_secs(5)).await.unwrap();
 }
 
 #[tokio::test]
@@ -44,3 +44,292 @@ fn namespace() {
     assert_eq!(namespace.agent_id(), &agent_id);
     assert_eq!(namespace.name(), "test_namespace");
 }
+
+#[cfg(all(feature = "sandbox", unix))]
+#[tokio::test]
+async fn sandboxed_process_captures_output_and_enforces_timeout() {
+    use std::time::Duration;
+
+    let output = ProcessSandbox::new("/bin/sh")
+        .with_arg("-c")
+        .with_arg("read line; echo \"got $line\"; echo oops >&2; exit 3")
+        .with_output_limit(16)
+        .run_with_input(b"input\n")
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"got input\n");
+    assert_eq!(output.stderr, b"oops\n");
+
+    let result = ProcessSandbox::new("/bin/sh")
+        .with_arg("-c")
+        .with_arg("sleep 5")
+        .with_timeout(Duration::from_millis(100))
+        .run()
+        .await;
+    assert_eq!(result.unwrap_err().code(), "execution_failed");
+}
+
+#[cfg(all(feature = "sandbox", unix))]
+#[tokio::test]
+async fn sandboxed_process_runs_under_resource_limits() {
+    use std::time::Duration;
+
+    let output = ProcessSandbox::new("/bin/sh")
+        .with_arg("-c")
+        .with_arg("ulimit -n; ulimit -t")
+        .with_limits(ResourceLimits::new().with_max_file_descriptors(32))
+        .with_cpu_time(Duration::from_secs(2))
+        .run()
+        .await
+        .unwrap();
+    assert!(output.success());
+    assert_eq!(output.stdout, b"32\n2\n");
+}
+
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+#[tokio::test]
+async fn sandboxed_process_gets_its_own_network_namespace() {
+    let host = std::fs::read_link("/proc/self/ns/net").unwrap();
+    let result = ProcessSandbox::new("/bin/sh")
+        .with_arg("-c")
+        .with_arg("readlink /proc/self/ns/net; grep NoNewPrivs /proc/self/status")
+        .with_network_isolation(true)
+        .run()
+        .await;
+    let output = match result {
+        // Unprivileged user namespaces are disabled on this host
+        Err(error) if error.code() == "isolation_error" => return,
+        result => result.unwrap(),
+    };
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_ne!(lines.next(), host.to_str());
+    assert_eq!(lines.next(), Some("NoNewPrivs:\t1"));
+}
+
+#[cfg(all(feature = "sandbox", unix))]
+#[tokio::test]
+async fn sandboxed_process_is_supervised() {
+    let agent_id = AgentId::new();
+    let spec = ProcessSandbox::new("/bin/sh")
+        .with_arg("-c")
+        .with_arg("exit 1")
+        .with_network_isolation(true)
+        .with_best_effort_isolation(true)
+        .child_spec(
+            agent_id,
+            RestartPolicy::new(RestartStrategy::OnFailure)
+                .with_max_retries(1)
+                .with_backoff_seconds(0),
+        );
+    let mut supervisor = Supervisor::new("tools");
+    supervisor.add_child(spec);
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+    assert!(matches!(exit, ChildExit::GaveUp { attempts: 2, .. }));
+}
+
+#[cfg(feature = "wasm")]
+#[tokio::test]
+async fn wasm_executor_runs_sandboxed_handlers_within_fuel() {
+    let echo = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "handle") (param i32 i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32))
+                    (i64.extend_i32_u (local.get 1)))))"#,
+    )
+    .unwrap();
+    let spin = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 0)
+            (func (export "handle") (param i32 i32) (result i64)
+                (loop br 0)
+                i64.const 0))"#,
+    )
+    .unwrap();
+    let (echoer, spinner) = (AgentId::new(), AgentId::new());
+    let mut executor = WasmExecutor::new()
+        .unwrap()
+        .with_resource_quota(spinner, ResourceQuota::new().with_max_fuel(10_000));
+    executor.load(echoer, &echo).unwrap();
+    executor.load(spinner, &spin).unwrap();
+
+    let task = Task::new(echoer, 1).with_payload(b"hello".to_vec());
+    assert_eq!(executor.execute(&task).await.unwrap(), b"hello");
+    assert!(executor.fuel_used(&echoer) > 0);
+
+    // A runaway handler burns its quota's fuel and no more
+    let error = executor.execute(&Task::new(spinner, 1)).await.unwrap_err();
+    assert_eq!(error.code(), "resource_exhausted");
+    assert_eq!(executor.fuel_used(&spinner), 10_000);
+
+    let missing = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+    let error = executor.load(AgentId::new(), &missing).unwrap_err();
+    assert_eq!(error.code(), "isolation_error");
+}
+
+#[cfg(feature = "wasm")]
+#[tokio::test]
+async fn wasm_executor_bounds_memory_and_outputs() {
+    let echo = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "handle") (param i32 i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32))
+                    (i64.extend_i32_u (local.get 1)))))"#,
+    )
+    .unwrap();
+    // Claims a 4 GiB output in a one-page memory
+    let liar = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 0)
+            (func (export "handle") (param i32 i32) (result i64)
+                i64.const 0xffffffff))"#,
+    )
+    .unwrap();
+    // Grows its memory to 128 MiB
+    let grower = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 0)
+            (func (export "handle") (param i32 i32) (result i64)
+                (drop (memory.grow (i32.const 2047)))
+                i64.const 0))"#,
+    )
+    .unwrap();
+    let (echoer, liar_id, grower_id) = (AgentId::new(), AgentId::new(), AgentId::new());
+    let mut executor = WasmExecutor::new()
+        .unwrap()
+        .with_output_limit(SizeLimit::new(4));
+    executor.load(echoer, &echo).unwrap();
+    executor.load(liar_id, &liar).unwrap();
+    executor.load(grower_id, &grower).unwrap();
+
+    let task = Task::new(echoer, 1).with_payload(b"hello".to_vec());
+    let error = executor.execute(&task).await.unwrap_err();
+    assert_eq!(error.code(), "payload_too_large");
+    let error = executor.execute(&Task::new(liar_id, 1)).await.unwrap_err();
+    assert_eq!(error.code(), "execution_failed");
+    let error = executor
+        .execute(&Task::new(grower_id, 1))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "execution_failed");
+
+    // A quota raises the default memory cap
+    let executor = executor.with_resource_quota(
+        grower_id,
+        ResourceQuota::new().with_max_memory(256 * 1024 * 1024),
+    );
+    assert!(executor.execute(&Task::new(grower_id, 1)).await.is_ok());
+}
+
+#[cfg(feature = "wasm")]
+#[tokio::test]
+async fn preempted_wasm_tasks_resume_and_report_fuel_to_the_scheduler() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use tokio_util::sync::CancellationToken;
+
+    let count = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 0)
+            (func (export "handle") (param i32 i32) (result i64) (local i32)
+                (loop
+                    (local.set 2 (i32.add (local.get 2) (i32.const 1)))
+                    (br_if 0 (i32.lt_u (local.get 2) (i32.const 20000000))))
+                i64.const 0))"#,
+    )
+    .unwrap();
+    let echo = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "handle") (param i32 i32) (result i64)
+                (i64.or
+                    (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32))
+                    (i64.extend_i32_u (local.get 1)))))"#,
+    )
+    .unwrap();
+    let (counter, echoer) = (AgentId::new(), AgentId::new());
+
+    // Fuel burned by the counting handler when it runs uninterrupted
+    let mut reference = WasmExecutor::new().unwrap();
+    reference.load(counter, &count).unwrap();
+    reference.execute(&Task::new(counter, 1)).await.unwrap();
+    let fuel = reference.fuel_used(&counter);
+
+    let tuning = EngineTuning {
+        time_slice: Duration::ZERO,
+        ..EngineProfile::Latency.tuning()
+    };
+    let scheduler = Arc::new(Mutex::new(
+        Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+            .with_tuning(tuning)
+            .with_preemption(1)
+            .with_resource_quota(
+                counter,
+                ResourceQuota::new().with_fuel_budget(fuel, Duration::from_secs(3600)),
+            ),
+    ));
+    let mut executor = WasmExecutor::new()
+        .unwrap()
+        .with_fuel_slice(10_000)
+        .with_scheduler(scheduler.clone());
+    executor.load(counter, &count).unwrap();
+    executor.load(echoer, &echo).unwrap();
+    let executor = Arc::new(executor);
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run_with_results(
+        scheduler.clone(),
+        {
+            let executor = executor.clone();
+            move |task: &Task| executor.execute(task)
+        },
+        shutdown.clone(),
+    ));
+
+    let counted = scheduler
+        .lock()
+        .await
+        .submit(Task::new(counter, 1))
+        .unwrap();
+    while scheduler.lock().await.resource_usage(&counter).running == 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    let echoed = scheduler
+        .lock()
+        .await
+        .submit(Task::new(echoer, 9).with_payload(b"urgent".to_vec()))
+        .unwrap();
+    assert_eq!(echoed.await.unwrap(), b"urgent");
+    assert_eq!(counted.await.unwrap(), b"");
+    shutdown.cancel();
+    driver.await.unwrap();
+
+    // The preempted run picked up where it stopped instead of starting over
+    let scheduler = scheduler.lock().await;
+    assert_eq!(scheduler.preemptions(), 1);
+    assert_eq!(executor.fuel_used(&counter), fuel);
+    let usage = scheduler.resource_usage(&counter);
+    assert_eq!(usage.fuel, fuel);
+    assert!(scheduler.is_out_of_fuel(&counter));
+    assert!(!scheduler.is_dispatchable(&counter));
+    assert_eq!(
+        scheduler.resource_quota(&counter).unwrap().check(&usage),
+        Some(QuotaViolation::Fuel {
+            used: fuel,
+            limit: fuel
+        })
+    );
+}
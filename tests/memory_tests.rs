@@ -0,0 +1,141 @@
+use agentropic_runtime::prelude::*;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator::new(std::alloc::System);
+
+#[tokio::test]
+async fn child_over_memory_limit_fails_as_out_of_memory() {
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("memory");
+    supervisor.add_child(
+        ChildSpec::new(
+            agent_id,
+            RestartPolicy::new(RestartStrategy::Never),
+            |_| async {
+                let buffer = vec![1u8; 4 * 1024 * 1024];
+                tokio::task::yield_now().await;
+                std::hint::black_box(buffer);
+                Ok(())
+            },
+        )
+        .with_memory_limit(1024 * 1024),
+    );
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+
+    let ChildExit::GaveUp { last_failure, .. } = exit else {
+        panic!("child should have been stopped");
+    };
+    assert_eq!(last_failure.class(), ErrorClass::OutOfMemory);
+    assert!(supervisor.memory_usage(&agent_id).unwrap().peak() >= 4 * 1024 * 1024);
+}
+
+#[tokio::test]
+async fn child_within_memory_limit_completes() {
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("memory");
+    supervisor.add_child(
+        ChildSpec::new(
+            agent_id,
+            RestartPolicy::new(RestartStrategy::Never),
+            |_| async {
+                let buffer = vec![1u8; 64 * 1024];
+                tokio::task::yield_now().await;
+                std::hint::black_box(buffer);
+                Ok(())
+            },
+        )
+        .with_memory_limit(1024 * 1024),
+    );
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+
+    assert_eq!(exit, ChildExit::Completed { attempts: 1 });
+}
+
+#[tokio::test]
+async fn resource_quota_memory_ceiling_stops_child() {
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("memory");
+    supervisor.add_child(ChildSpec::new(
+        agent_id,
+        RestartPolicy::new(RestartStrategy::Never),
+        |_| async {
+            let buffer = vec![1u8; 4 * 1024 * 1024];
+            tokio::task::yield_now().await;
+            std::hint::black_box(buffer);
+            Ok(())
+        },
+    ));
+    supervisor.set_resource_quota(agent_id, ResourceQuota::new().with_max_memory(1024 * 1024));
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+
+    let ChildExit::GaveUp { last_failure, .. } = exit else {
+        panic!("child should have been stopped");
+    };
+    assert_eq!(last_failure.class(), ErrorClass::OutOfMemory);
+}
+
+#[tokio::test]
+async fn child_over_memory_limit_is_shut_down_through_its_hooks() {
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::Notify;
+
+    #[derive(Default)]
+    struct Hooks {
+        reasons: Mutex<Vec<ShutdownReason>>,
+        exit: Notify,
+    }
+
+    #[async_trait::async_trait]
+    impl ShutdownHooks for Hooks {
+        async fn prepare_shutdown(
+            &self,
+            _agent_id: AgentId,
+            reason: ShutdownReason,
+            _deadline: std::time::Instant,
+        ) -> Result<(), RuntimeError> {
+            self.reasons.lock().unwrap().push(reason);
+            Ok(())
+        }
+
+        async fn terminate(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+            self.exit.notify_one();
+            Ok(())
+        }
+    }
+
+    let agent_id = AgentId::new();
+    let hooks = Arc::new(Hooks::default());
+    let exit = hooks.clone();
+    let mut supervisor = Supervisor::new("memory");
+    supervisor.add_child(
+        ChildSpec::new(
+            agent_id,
+            RestartPolicy::new(RestartStrategy::Never),
+            move |_| {
+                let exit = exit.clone();
+                async move {
+                    let buffer = vec![1u8; 4 * 1024 * 1024];
+                    exit.exit.notified().await;
+                    std::hint::black_box(buffer);
+                    Ok(())
+                }
+            },
+        )
+        .with_memory_limit(1024 * 1024)
+        .with_shutdown_hooks(hooks.clone()),
+    );
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+
+    let ChildExit::GaveUp { last_failure, .. } = exit else {
+        panic!("child should have been stopped");
+    };
+    assert_eq!(last_failure.class(), ErrorClass::OutOfMemory);
+    assert_eq!(
+        *hooks.reasons.lock().unwrap(),
+        [ShutdownReason::MemoryLimitExceeded]
+    );
+}
@@ -1,4 +1,5 @@
 use agentropic_runtime::prelude::*;
+use std::time::Duration;
 
 #[tokio::test]
 async fn create_runtime() {
@@ -18,6 +19,197 @@ async fn start_stop_runtime() {
     assert!(!runtime.is_running().await);
 }
 
+#[tokio::test]
+async fn builder_wires_scheduler_and_supervisor() {
+    use tokio::sync::mpsc;
+
+    let agent_id = AgentId::new();
+    let (ran, mut child_ran) = mpsc::unbounded_channel();
+    let (executed, mut task_executed) = mpsc::unbounded_channel();
+    let runtime = Runtime::builder()
+        .with_config(RuntimeConfig::new().with_profile(EngineProfile::Latency))
+        .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_executor(move |task: &Task| {
+            let _ = executed.send(*task.agent_id());
+            async { Ok(b"done".to_vec()) }
+        })
+        .with_child(ChildSpec::new(
+            agent_id,
+            RestartPolicy::new(RestartStrategy::Never),
+            move |_| {
+                let _ = ran.send(());
+                async { Ok(()) }
+            },
+        ))
+        .build();
+
+    runtime.start().await.unwrap();
+    assert!(runtime.has_agent(&agent_id).await);
+    child_ran.recv().await.unwrap();
+
+    let scheduler = runtime.scheduler().unwrap();
+    assert_eq!(scheduler.lock().await.tuning().batch_size, 1);
+    let handle = scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 1))
+        .unwrap();
+    assert_eq!(task_executed.recv().await, Some(agent_id));
+    assert_eq!(handle.await.unwrap(), b"done".to_vec());
+
+    let report = runtime.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert_eq!(report.abandoned(), 0);
+}
+
+#[cfg(feature = "admin")]
+#[tokio::test]
+async fn admin_endpoint_serves_health_agents_and_metrics() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn get(address: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    let agent_id = AgentId::new();
+    let runtime = Runtime::builder()
+        .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_agent(agent_id, "planner")
+        .with_admin("127.0.0.1:0".parse().unwrap())
+        .build();
+    assert_eq!(runtime.admin_address().await, None);
+    runtime.start().await.unwrap();
+    let address = runtime.admin_address().await.unwrap();
+
+    assert!(get(address, "/health").await.starts_with("HTTP/1.1 200"));
+    let agents = get(address, "/agents").await;
+    assert!(agents.contains(&agent_id.to_string()));
+    assert!(agents.contains("\"planner\""));
+    let metrics = get(address, "/metrics").await;
+    assert!(metrics.starts_with("HTTP/1.1 200"));
+    assert!(metrics.contains("scheduler"));
+    assert!(get(address, "/missing").await.starts_with("HTTP/1.1 404"));
+
+    runtime.shutdown(Duration::from_secs(1)).await.unwrap();
+    assert!(tokio::net::TcpStream::connect(address).await.is_err());
+}
+
+#[tokio::test]
+async fn shutdown_abandons_tasks_past_grace_period() {
+    let runtime = Runtime::builder()
+        .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+        .build();
+    runtime.start().await.unwrap();
+    let scheduler = runtime.scheduler().unwrap();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        |_: &Task| std::future::pending::<Result<(), RuntimeError>>(),
+        CancellationToken::new(),
+    ));
+    let agent_id = AgentId::new();
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 1))
+        .unwrap();
+    while scheduler.lock().await.in_flight_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let report = runtime.shutdown(Duration::from_millis(20)).await.unwrap();
+    assert!(report.timed_out);
+    assert_eq!(report.abandoned(), 1);
+    assert_eq!(report.abandoned_in_flight[0].agent_id(), &agent_id);
+    driver.await.unwrap();
+
+    let rejected = scheduler.lock().await.submit(Task::new(agent_id, 1));
+    assert_eq!(
+        rejected.unwrap_err().code(),
+        SchedulerError::Draining.code()
+    );
+}
+
+#[cfg(feature = "persistence")]
+#[tokio::test]
+async fn builder_persists_scheduler_state_across_restarts() {
+    let path = std::env::temp_dir().join(format!("agentropic-builder-{}.json", std::process::id()));
+    let worker = AgentId::new();
+    let build = || {
+        Runtime::builder()
+            .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+            .with_child(ChildSpec::new(
+                worker,
+                RestartPolicy::new(RestartStrategy::Never),
+                |_| async { Ok(()) },
+            ))
+            .with_state_path(&path)
+            .build()
+    };
+
+    let runtime = build();
+    runtime.start().await.unwrap();
+    let scheduler = runtime.scheduler().unwrap();
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(AgentId::new(), 3))
+        .unwrap();
+    let report = runtime.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert_eq!(report.decisions.for_agent(&worker).count(), 1);
+
+    // The supervisor's decisions carry over next to the scheduler state
+    let restarted = build();
+    restarted.start().await.unwrap();
+    let scheduler = restarted.scheduler().unwrap();
+    assert_eq!(scheduler.lock().await.queued_at(3), 1);
+    let report = restarted.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert_eq!(report.decisions.for_agent(&worker).count(), 2);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(path.with_file_name(format!(
+        "agentropic-builder-{}.supervisor.json",
+        std::process::id()
+    )))
+    .unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_stops_children_and_kills_those_past_grace_period() {
+    use std::sync::Arc;
+
+    struct Ignore;
+
+    #[async_trait::async_trait]
+    impl ShutdownHooks for Ignore {}
+
+    let (steady, stubborn) = (AgentId::new(), AgentId::new());
+    let policy = || RestartPolicy::new(RestartStrategy::OnFailure);
+    let runtime = Runtime::builder()
+        .with_child(ChildSpec::new(steady, policy(), |_| {
+            std::future::pending::<Result<(), RuntimeError>>()
+        }))
+        .with_child(
+            ChildSpec::new(stubborn, policy(), |_| {
+                std::future::pending::<Result<(), RuntimeError>>()
+            })
+            .with_shutdown_hooks(Arc::new(Ignore))
+            .with_grace_period(Duration::from_secs(60)),
+        )
+        .build();
+    runtime.start().await.unwrap();
+
+    let report = runtime.shutdown(Duration::from_millis(50)).await.unwrap();
+    assert!(report.timed_out);
+    assert_eq!(
+        report.children,
+        vec![(steady, ChildExit::Completed { attempts: 1 })]
+    );
+    assert_eq!(report.killed_children, vec![stubborn]);
+}
+
 #[tokio::test]
 async fn spawn_agent() {
     let runtime = Runtime::new();
@@ -48,3 +240,786 @@ async fn runtime_with_config() {
     assert_eq!(runtime.config().max_workers, 4);
     assert_eq!(runtime.config().default_timeout_ms, 5000);
 }
+
+#[test]
+fn numa_pinned_executor_pool() {
+    let topology = NumaTopology::new(vec![
+        NumaNode::new(0, CpuSet::new([0])),
+        NumaNode::new(1, CpuSet::parse("1-3").unwrap()),
+    ]);
+    assert_eq!(topology.node_of_cpu(2).unwrap().id(), 1);
+    assert_eq!(CpuSet::parse("3-1"), None);
+
+    let pool = ExecutorPool::start_with_topology(
+        PoolConfig::new("numa0").with_workers(1).with_numa_node(0),
+        &topology,
+    )
+    .unwrap();
+    assert_eq!(pool.cpus(), Some(&CpuSet::new([0])));
+    let value = pool.handle().block_on(pool.spawn(async { 42 })).unwrap();
+    assert_eq!(value, 42);
+
+    let unknown =
+        ExecutorPool::start_with_topology(PoolConfig::new("bad").with_numa_node(7), &topology);
+    assert!(unknown.is_err());
+}
+
+#[test]
+fn exclusive_pool_keeps_shared_pools_off_its_cores() {
+    let topology = NumaTopology::new(vec![NumaNode::new(0, CpuSet::parse("0-3").unwrap())]);
+    let config = RuntimeConfig::new()
+        .with_executor_pool(
+            PoolConfig::new("voice")
+                .with_cpus(CpuSet::new([3]))
+                .with_exclusive(true),
+        )
+        .with_executor_pool(PoolConfig::new("batch").with_workers(2));
+
+    let pools = PoolSet::start_with_topology(config.executor_pools, &topology).unwrap();
+
+    assert_eq!(pools.reserved_cpus(), &CpuSet::new([3]));
+    assert_eq!(pools.shared_cpus(), &CpuSet::new([0, 1, 2]));
+    assert_eq!(
+        pools.get("batch").unwrap().cpus(),
+        Some(&CpuSet::new([0, 1, 2]))
+    );
+    assert_eq!(pools.get("voice").unwrap().cpus(), Some(&CpuSet::new([3])));
+
+    // A shared pool left without cores is a configuration error, not an unpinned pool
+    let starved = PoolSet::start_with_topology(
+        vec![
+            PoolConfig::new("voice")
+                .with_cpus(CpuSet::new([2, 3]))
+                .with_exclusive(true),
+            PoolConfig::new("batch").with_cpus(CpuSet::new([3])),
+        ],
+        &topology,
+    );
+    assert_eq!(starved.err().unwrap().code(), "config_error");
+}
+
+#[tokio::test]
+async fn timer_service_sleeps() {
+    use std::time::Instant;
+
+    let timers = TimerService::new();
+    let start = Instant::now();
+    let (first, second) = tokio::join!(
+        timers.sleep(Duration::from_millis(30)),
+        timers.sleep(Duration::from_millis(10))
+    );
+    let _ = (first, second);
+
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    assert_eq!(timers.pending(), 0);
+    assert_eq!(TimerService::tokio().backend(), TimerBackend::Tokio);
+}
+
+#[tokio::test]
+async fn cancelled_timer_sleeps_are_not_kept() {
+    use futures::FutureExt;
+
+    let timers = TimerService::new();
+    for _ in 0..1000 {
+        assert!(timers
+            .sleep(Duration::from_secs(3600))
+            .now_or_never()
+            .is_none());
+    }
+    assert_eq!(timers.pending(), 0);
+}
+
+#[tokio::test]
+async fn virtual_clock_drives_timer_sleeps() {
+    use std::sync::Arc;
+
+    let clock = Arc::new(VirtualClock::new());
+    let timers = Arc::new(TimerService::tokio().with_clock(clock.clone()));
+    let sleeper = tokio::spawn({
+        let timers = timers.clone();
+        async move { timers.sleep(Duration::from_secs(3600)).await }
+    });
+
+    while clock.sleepers() == 0 {
+        tokio::task::yield_now().await;
+    }
+    assert!(!sleeper.is_finished());
+    clock.advance(Duration::from_secs(3600));
+    sleeper.await.unwrap();
+    assert_eq!(clock.now(), Duration::from_secs(3600));
+}
+
+#[tokio::test]
+async fn scheduler_wakeups_wait_on_the_timer_service() {
+    use std::time::Instant;
+
+    // Without a clock of its own, a scheduler's timed wakeups go through
+    // the shared timer service
+    let timers = TimerService::shared();
+    let scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    let wakeup = scheduler.wakeup();
+    let deadline = Instant::now() + Duration::from_millis(200);
+    let waiter = tokio::spawn(async move { wakeup.wait(Some(deadline)).await });
+    if timers.backend() == TimerBackend::IoUring {
+        while timers.pending() == 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+    assert_eq!(waiter.await.unwrap(), WakeReason::Timer);
+    assert!(Instant::now() >= deadline);
+
+    // A timer service is a clock, so it can time a scheduler directly
+    let clock = std::sync::Arc::new(VirtualClock::new());
+    let timers = std::sync::Arc::new(TimerService::tokio().with_clock(clock.clone()));
+    let scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_clock(timers);
+    let wakeup = scheduler.wakeup();
+    let deadline = clock.instant() + Duration::from_secs(60);
+    let waiter = tokio::spawn(async move { wakeup.wait(Some(deadline)).await });
+    while clock.sleepers() == 0 {
+        tokio::task::yield_now().await;
+    }
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(waiter.await.unwrap(), WakeReason::Timer);
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn capi_submit_and_poll() {
+    use agentropic_runtime::capi::*;
+
+    unsafe {
+        let runtime = agentropic_runtime_new();
+        assert!(!runtime.is_null());
+
+        let payload = b"hello";
+        let task_id = agentropic_runtime_submit(runtime, 7, 3, payload.as_ptr(), payload.len());
+        assert_ne!(task_id, 0);
+
+        let mut event = std::mem::MaybeUninit::<AgentropicEvent>::uninit();
+        assert_eq!(
+            agentropic_runtime_poll_event(runtime, event.as_mut_ptr()),
+            1
+        );
+        let event = event.assume_init();
+        assert_eq!(event.kind, AGENTROPIC_EVENT_TASK_READY);
+        assert_eq!(event.task_id, task_id);
+        assert_eq!(event.agent, 7);
+        assert_eq!(
+            std::slice::from_raw_parts(event.payload, event.payload_len),
+            payload
+        );
+
+        let first = agentropic_runtime_submit(runtime, 7, 1, b"a".as_ptr(), 1);
+        let second = agentropic_runtime_submit(runtime, 7, 1, b"bb".as_ptr(), 2);
+        for (task_id, payload) in [(first, &b"a"[..]), (second, &b"bb"[..])] {
+            let mut event = std::mem::MaybeUninit::<AgentropicEvent>::uninit();
+            assert_eq!(
+                agentropic_runtime_poll_event(runtime, event.as_mut_ptr()),
+                1
+            );
+            let event = event.assume_init();
+            assert_eq!(event.task_id, task_id);
+            assert_eq!(
+                std::slice::from_raw_parts(event.payload, event.payload_len),
+                payload
+            );
+        }
+
+        let mut none = std::mem::MaybeUninit::<AgentropicEvent>::uninit();
+        assert_eq!(agentropic_runtime_poll_event(runtime, none.as_mut_ptr()), 0);
+
+        agentropic_runtime_shutdown(runtime);
+    }
+}
+
+#[test]
+fn latency_histograms_merge_across_runtimes() {
+    let mut local = LatencyHistogram::new();
+    for millis in 1..=90 {
+        local.record(Duration::from_millis(millis));
+    }
+    let mut remote = LatencyHistogram::new();
+    for millis in 91..=100 {
+        remote.record(Duration::from_millis(millis));
+    }
+
+    let remote = LatencyHistogram::from_bytes(&remote.to_bytes().unwrap()).unwrap();
+    local.merge(&remote);
+
+    assert_eq!(local.count(), 100);
+    let p99 = local.quantile(0.99);
+    assert!(p99 >= Duration::from_millis(98) && p99 <= Duration::from_millis(100));
+    assert!(local.max() >= Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn linked_agents_share_fate_and_monitors_get_down_signals() {
+    let runtime = Runtime::new();
+    let (a, b, c, watcher) = (
+        AgentId::new(),
+        AgentId::new(),
+        AgentId::new(),
+        AgentId::new(),
+    );
+    for agent_id in [a, b, c, watcher] {
+        runtime.spawn(agent_id, "peer").await.unwrap();
+    }
+    runtime.link(a, b).await.unwrap();
+    runtime.link(b, c).await.unwrap();
+    let monitor = runtime.monitor(watcher, c).await;
+
+    let terminated = runtime.exit(a, ExitReason::Failed("crashed".into())).await;
+    assert_eq!(terminated, vec![b, c]);
+    assert_eq!(runtime.agent_count().await, 1);
+    assert_eq!(
+        runtime.take_signals(&watcher).await,
+        vec![PeerSignal::Down {
+            monitor,
+            agent_id: c,
+            reason: ExitReason::Linked(b),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn trapping_exits_turns_linked_death_into_a_signal() {
+    let runtime = Runtime::new();
+    let (a, b) = (AgentId::new(), AgentId::new());
+    runtime.spawn(a, "a").await.unwrap();
+    runtime.spawn(b, "b").await.unwrap();
+    runtime.link(a, b).await.unwrap();
+    runtime.trap_exits(b, true).await;
+
+    assert!(runtime.exit(a, ExitReason::Killed).await.is_empty());
+    assert!(runtime.has_agent(&b).await);
+    assert_eq!(
+        runtime.take_signals(&b).await,
+        vec![PeerSignal::Exit {
+            from: a,
+            reason: ExitReason::Killed,
+        }]
+    );
+
+    let missing = AgentId::new();
+    assert!(runtime.link(b, missing).await.is_err());
+    runtime.monitor(b, missing).await;
+    assert!(matches!(
+        runtime.take_signals(&b).await.as_slice(),
+        [PeerSignal::Down {
+            reason: ExitReason::NoAgent,
+            ..
+        }]
+    ));
+}
+
+#[tokio::test]
+async fn lifecycle_hooks_gate_scheduling() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    struct CountingHooks(Arc<AtomicU32>);
+
+    #[async_trait::async_trait]
+    impl LifecycleHooks for CountingHooks {
+        async fn on_suspend(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    let suspends = Arc::new(AtomicU32::new(0));
+    let lifecycle = Arc::new(AgentLifecycle::new().with_hooks(CountingHooks(suspends.clone())));
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_lifecycle(lifecycle.clone());
+    let agent_id = AgentId::new();
+
+    lifecycle.start(agent_id).await.unwrap();
+    lifecycle.suspend(agent_id).await.unwrap();
+    assert_eq!(lifecycle.state(&agent_id), Some(LifecycleState::Suspended));
+    assert_eq!(suspends.load(Ordering::SeqCst), 1);
+    let invalid = lifecycle.suspend(agent_id).await.unwrap_err();
+    assert_eq!(invalid.code(), "invalid_transition");
+
+    scheduler.submit(Task::new(agent_id, 1)).unwrap();
+    assert!(scheduler.next_task().is_none());
+    lifecycle.resume(agent_id).await.unwrap();
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &agent_id);
+}
+
+#[tokio::test]
+async fn mailbox_backpressure() {
+    use std::sync::Arc;
+
+    let mailboxes = Arc::new(Mailboxes::new());
+    let (sender, receiver) = (AgentId::new(), AgentId::new());
+    mailboxes.register(receiver, MailboxConfig::bounded(1));
+
+    let message = move |payload: &str| Message::new(receiver, payload).with_sender(sender);
+    mailboxes.try_send(message("first")).unwrap();
+    let full = mailboxes.try_send(message("second")).unwrap_err();
+    assert_eq!(full.code(), "queue.mailbox_full");
+
+    let blocked = tokio::spawn({
+        let mailboxes = mailboxes.clone();
+        async move { mailboxes.send(message("second")).await }
+    });
+    let first = mailboxes.recv(&receiver).await.unwrap();
+    assert_eq!(first.payload(), b"first");
+    assert_eq!(first.sender(), Some(&sender));
+    blocked.await.unwrap().unwrap();
+    assert_eq!(
+        mailboxes.recv(&receiver).await.unwrap().payload(),
+        b"second"
+    );
+
+    mailboxes.register(
+        receiver,
+        MailboxConfig::bounded(1).with_backpressure(Backpressure::DropOldest),
+    );
+    mailboxes.try_send(message("old")).unwrap();
+    mailboxes.try_send(message("new")).unwrap();
+    assert_eq!(mailboxes.dropped(&receiver), 1);
+    assert_eq!(mailboxes.try_recv(&receiver).unwrap().payload(), b"new");
+    assert!(mailboxes
+        .try_send(Message::new(AgentId::new(), "lost"))
+        .is_err());
+}
+
+#[test]
+fn mail_queues_one_task_per_batch() {
+    let mailboxes = Mailboxes::new().with_default_config(MailboxConfig::unbounded());
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_mailboxes(&mailboxes, 4);
+    let agent_id = AgentId::new();
+
+    mailboxes.try_send(Message::new(agent_id, "a")).unwrap();
+    mailboxes.try_send(Message::new(agent_id, "b")).unwrap();
+    let task = scheduler.next_task().unwrap();
+    assert_eq!((task.agent_id(), task.priority()), (&agent_id, 4));
+    assert!(scheduler.next_task().is_none());
+
+    while mailboxes.try_recv(&agent_id).is_some() {}
+    mailboxes.try_send(Message::new(agent_id, "c")).unwrap();
+    assert!(scheduler.next_task().is_some());
+}
+
+#[test]
+fn mailbox_payload_limit_rejects_or_truncates_messages() {
+    let agent_id = AgentId::new();
+    let strict = Mailboxes::new()
+        .with_default_config(MailboxConfig::unbounded())
+        .with_payload_limit(SizeLimit::new(4));
+    let error = strict
+        .try_send(Message::new(agent_id, "oversized"))
+        .unwrap_err();
+    assert_eq!(error.code(), "payload_too_large");
+    assert_eq!(strict.len(&agent_id), 0);
+
+    let lenient = Mailboxes::new()
+        .with_default_config(MailboxConfig::unbounded())
+        .with_payload_limit(SizeLimit::new(4).truncate());
+    lenient
+        .try_send(Message::new(agent_id, "oversized"))
+        .unwrap();
+    let message = lenient.try_recv(&agent_id).unwrap();
+    assert_eq!(message.payload(), b"over");
+    assert!(message.is_truncated());
+}
+
+#[cfg(feature = "bus")]
+#[test]
+fn bus_delivers_topic_events_through_mailboxes() {
+    use std::sync::Arc;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Quote {
+        symbol: String,
+        price: u32,
+    }
+
+    let mailboxes = Arc::new(Mailboxes::new());
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_mailboxes(&mailboxes, 2);
+    let bus = EventBus::new(mailboxes.clone());
+    let (trader, auditor, publisher) = (AgentId::new(), AgentId::new(), AgentId::new());
+    mailboxes.register(trader, MailboxConfig::bounded(1));
+    mailboxes.register(auditor, MailboxConfig::bounded(1));
+    bus.configure(
+        "audit",
+        TopicConfig::new()
+            .with_retention(2)
+            .with_delivery(DeliveryMode::AtLeastOnce),
+    );
+    bus.subscribe(trader, "quotes");
+    let quote = |price| Quote {
+        symbol: "ACME".into(),
+        price,
+    };
+
+    // Subscribers are woken by a scheduled task and decode the payload
+    assert_eq!(bus.publish_as(publisher, "quotes", &quote(10)).unwrap(), 0);
+    let task = scheduler.next_task().unwrap();
+    assert_eq!(task.agent_id(), &trader);
+    let event = Event::from_message(&mailboxes.try_recv(&trader).unwrap()).unwrap();
+    assert_eq!(
+        (event.topic(), event.sequence(), event.publisher()),
+        ("quotes", 0, Some(&publisher))
+    );
+    assert_eq!(event.decode::<Quote>().unwrap(), quote(10));
+
+    // At most once: an event that does not fit the mailbox is dropped
+    bus.publish("quotes", &quote(11)).unwrap();
+    bus.publish("quotes", &quote(12)).unwrap();
+    assert_eq!(bus.dropped("quotes"), 1);
+
+    // Late subscribers get retained events; unacknowledged ones are resent
+    for price in 1..=3 {
+        bus.publish("audit", &quote(price)).unwrap();
+    }
+    assert_eq!(bus.retained("audit").len(), 2);
+    assert_eq!(bus.subscribe(auditor, "audit"), 2);
+    assert_eq!(bus.unacked(&auditor), 2);
+    let first = Event::from_message(&mailboxes.try_recv(&auditor).unwrap()).unwrap();
+    assert_eq!(first.decode::<Quote>().unwrap().price, 2);
+    assert!(bus.ack(&auditor, "audit", first.sequence()));
+    assert_eq!(bus.redeliver(), 1);
+    let resent = Event::from_message(&mailboxes.try_recv(&auditor).unwrap()).unwrap();
+    assert_eq!(resent.sequence(), 2);
+    assert!(bus.ack(&auditor, "audit", 2));
+    assert_eq!(bus.unacked(&auditor), 0);
+    assert_eq!(bus.dropped("audit"), 0);
+}
+
+#[tokio::test]
+async fn sim_runtime_plays_out_backoff_and_cron_in_virtual_time() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let agent_id = AgentId::new();
+    let starts = Arc::new(AtomicU32::new(0));
+    let counted = starts.clone();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure).with_backoff_seconds(5);
+    let builder = Runtime::builder()
+        .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_child(ChildSpec::new(agent_id, policy, move |_| {
+            let attempt = counted.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if attempt < 3 {
+                    Err(RuntimeError::ExecutionFailed("boom".into()))
+                } else {
+                    Ok(())
+                }
+            }
+        }));
+    let epoch = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let sim = SimRuntime::with_clock(builder, Arc::new(VirtualClock::starting_at(epoch)));
+
+    let supervisor = sim.runtime().supervisor().unwrap();
+    let run = tokio::spawn(async move { supervisor.lock().await.run_child(&agent_id).await });
+
+    sim.advance(Duration::from_secs(4)).await;
+    assert_eq!(starts.load(Ordering::SeqCst), 1);
+    sim.advance(Duration::from_secs(1)).await;
+    assert_eq!(starts.load(Ordering::SeqCst), 2);
+    sim.advance(Duration::from_secs(5)).await;
+    assert_eq!(
+        run.await.unwrap().unwrap(),
+        ChildExit::Completed { attempts: 3 }
+    );
+    assert_eq!(sim.elapsed(), Duration::from_secs(10));
+
+    let supervisor = sim.runtime().supervisor().unwrap();
+    let supervisor = supervisor.lock().await;
+    let restarted_at: Vec<SystemTime> = supervisor
+        .decision_log()
+        .for_agent(&agent_id)
+        .map(|decision| decision.at)
+        .collect();
+    assert_eq!(
+        restarted_at,
+        vec![
+            epoch,
+            epoch + Duration::from_secs(5),
+            epoch + Duration::from_secs(10)
+        ]
+    );
+    drop(supervisor);
+
+    // The scheduler loop fires cron ticks as the virtual clock reaches them
+    let scheduler = sim.runtime().scheduler().unwrap();
+    scheduler.lock().await.add_schedule(RecurringSchedule::cron(
+        "every-five",
+        &Task::new(agent_id, 0),
+        CronExpr::parse("*/5 * * * *").unwrap(),
+        sim.wall_time(),
+    ));
+    let (sender, mut fired) = tokio::sync::mpsc::unbounded_channel();
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    let run = tokio::spawn(Scheduler::run(
+        scheduler,
+        move |_: &Task| {
+            let _ = sender.send(());
+            async { Ok(()) }
+        },
+        shutdown.clone(),
+    ));
+    sim.advance(Duration::from_secs(10 * 60)).await;
+    shutdown.cancel();
+    run.await.unwrap();
+    assert_eq!(std::iter::from_fn(|| fired.try_recv().ok()).count(), 2);
+}
+
+#[cfg(all(feature = "config-toml", feature = "config-yaml"))]
+#[tokio::test]
+async fn config_file_loads_validates_and_applies_live() {
+    let dir = std::env::temp_dir().join(format!("agentropic-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("runtime.toml");
+    std::fs::write(
+        &path,
+        r#"
+        max_workers = 4
+
+        [scheduler]
+        policy = "Priority"
+        queue_capacity = 100
+        overflow = "Reject"
+
+        [restart_policies.writer]
+        strategy = "OnFailure"
+        max_retries = 7
+        backoff_seconds = 2
+        "#,
+    )
+    .unwrap();
+    let config = RuntimeConfig::load(&path).unwrap();
+    assert_eq!(config.scheduler.queue_capacity, Some(100));
+    assert_eq!(
+        config.default_timeout_ms,
+        RuntimeConfig::new().default_timeout_ms
+    );
+    let yaml =
+        RuntimeConfig::from_yaml("max_workers: 4\nscheduler:\n  policy: Priority\n").unwrap();
+    assert_eq!(yaml.scheduler.policy, PolicyType::Priority);
+    let invalid = RuntimeConfig::from_toml("[scheduler]\nqueue_capacity = 0").unwrap_err();
+    assert_eq!(invalid.code(), "config_error");
+
+    let writer = AgentId::new();
+    let mut supervisor = Supervisor::new("main");
+    supervisor.supervise(writer, RestartPolicy::default());
+    let mut runtime = Runtime::builder()
+        .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_supervisor(supervisor)
+        .with_agent(writer, "writer")
+        .build();
+    runtime.start().await.unwrap();
+
+    let changes = runtime.apply_config(config).await.unwrap();
+    assert!(changes.contains(&ConfigChange::Policy {
+        from: PolicyType::FCFS,
+        to: PolicyType::Priority,
+    }));
+    assert!(changes.contains(&ConfigChange::RestartPolicy {
+        agent: "writer".into()
+    }));
+    assert!(changes.contains(&ConfigChange::RequiresRestart {
+        field: "max_workers"
+    }));
+    let scheduler = runtime.scheduler().unwrap();
+    let scheduler = scheduler.lock().await;
+    assert_eq!(scheduler.policy().policy_type(), PolicyType::Priority);
+    assert_eq!(scheduler.overflow_policy(), Some(OverflowPolicy::Reject));
+    let supervisor = runtime.supervisor().unwrap();
+    let supervisor = supervisor.lock().await;
+    assert_eq!(
+        supervisor.get_policy(&writer).unwrap().max_retries(),
+        Some(7)
+    );
+    drop((scheduler, supervisor));
+
+    // Dropping the policy restores the agent's own; health settings and the
+    // default timeout only take effect on restart
+    let mut reverted = runtime.config().clone().with_timeout(5_000);
+    reverted.restart_policies.clear();
+    reverted.health_interval_ms = 500;
+    let changes = runtime.apply_config(reverted).await.unwrap();
+    for field in ["default_timeout_ms", "health_interval_ms"] {
+        assert!(changes.contains(&ConfigChange::RequiresRestart { field }));
+    }
+    let supervisor = runtime.supervisor().unwrap();
+    assert_eq!(
+        supervisor.lock().await.get_policy(&writer),
+        Some(&RestartPolicy::default())
+    );
+
+    // The watcher reports the next change to the file
+    let mut watcher = ConfigWatcher::new(&path).with_interval(Duration::from_millis(10));
+    std::fs::write(&path, "max_workers = 2").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + Duration::from_secs(5))
+        .unwrap();
+    let reloaded = tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.max_workers, 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn registry_lists_agents_by_tag_state_and_task() {
+    let planner = AgentId::new();
+    let writer = AgentId::new();
+    let runtime = Runtime::builder()
+        .with_agent(planner, "planner")
+        .with_agent(writer, "writer")
+        .with_lifecycle(AgentLifecycle::new())
+        .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+        .build();
+    runtime.start().await.unwrap();
+    let registry = runtime.registry();
+    assert!(registry.tag(&planner, "llm").await);
+    assert!(registry.tag(&writer, "llm").await);
+    assert!(registry.tag(&writer, "io").await);
+    assert!(!registry.tag(&AgentId::new(), "llm").await);
+    runtime.lifecycle().unwrap().start(planner).await.unwrap();
+
+    // Keep a task of the writer in flight
+    let scheduler = runtime.scheduler().unwrap();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        |_: &Task| std::future::pending::<Result<(), RuntimeError>>(),
+        CancellationToken::new(),
+    ));
+    let task = Task::new(writer, 1);
+    let task_id = task.id();
+    scheduler.lock().await.submit(task).unwrap();
+    while scheduler.lock().await.in_flight_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let all = registry.list(&RegistryFilter::new()).await;
+    let names: Vec<_> = all.iter().map(|info| info.name.as_str()).collect();
+    assert_eq!(names, ["planner", "writer"]);
+
+    let io = registry
+        .list(&RegistryFilter::new().with_tag("llm").with_tag("io"))
+        .await;
+    assert_eq!(io.len(), 1);
+    assert_eq!(io[0].id, writer);
+    assert_eq!(io[0].current_task, Some(task_id));
+    assert_eq!(io[0].restarts, 0);
+
+    let running = registry
+        .list(&RegistryFilter::new().with_state(LifecycleState::Running))
+        .await;
+    assert_eq!(running.len(), 1);
+    assert_eq!(running[0].name, "planner");
+    assert!(!running[0].is_busy());
+
+    let idle = registry.list(&RegistryFilter::new().with_busy(false)).await;
+    assert_eq!(idle.len(), 1);
+    assert_eq!(idle[0].id, planner);
+
+    assert!(registry.untag(&writer, "io").await);
+    assert!(!registry.get(&writer).await.unwrap().has_tag("io"));
+
+    runtime.shutdown(Duration::from_millis(20)).await.unwrap();
+    driver.await.unwrap();
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test]
+async fn otlp_exporter_pushes_runtime_spans_and_metrics() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut chunk = [0u8; 4096];
+            let (path, body) = loop {
+                let read = stream.read(&mut chunk).await.unwrap();
+                request.extend_from_slice(&chunk[..read]);
+                let text = String::from_utf8_lossy(&request).into_owned();
+                let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                    continue;
+                };
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                if body.len() >= length {
+                    let path = head.split_whitespace().nth(1).unwrap().to_string();
+                    break (
+                        path,
+                        serde_json::from_str::<serde_json::Value>(body).unwrap(),
+                    );
+                }
+            };
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            requests.push((path, body));
+        }
+        requests
+    });
+
+    let exporter = OtlpExporter::new(endpoint)
+        .with_instance_id("runtime-7")
+        .with_node_name("node-a");
+    let runtime = Runtime::builder()
+        .with_policy(SchedulingPolicy::new(PolicyType::FCFS))
+        .build();
+    let scheduler = runtime.scheduler().unwrap();
+    {
+        let _subscriber =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(exporter.layer()));
+        let mut scheduler = scheduler.lock().await;
+        scheduler.submit(Task::new(AgentId::new(), 3)).unwrap();
+        scheduler.next_task().unwrap();
+    }
+    assert_eq!(exporter.queued_spans(), 1);
+
+    exporter.push(&runtime).await.unwrap();
+    assert_eq!(exporter.queued_spans(), 0);
+    let requests = server.await.unwrap();
+
+    let (path, metrics) = &requests[0];
+    assert_eq!(path, "/v1/metrics");
+    let resource = &metrics["resourceMetrics"][0]["resource"]["attributes"];
+    assert!(resource.as_array().unwrap().iter().any(|attribute| {
+        attribute["key"] == "host.name" && attribute["value"]["stringValue"] == "node-a"
+    }));
+    let names: Vec<_> = metrics["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|metric| metric["name"].as_str().unwrap().to_string())
+        .collect();
+    assert!(names.iter().any(|name| name == "agentropic.queue.depth"));
+    assert!(names.iter().any(|name| name == "agentropic.retry.count"));
+
+    let (path, traces) = &requests[1];
+    assert_eq!(path, "/v1/traces");
+    let span = &traces["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+    assert_eq!(span["name"], "task.dispatch");
+    assert!(span["attributes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|attribute| {
+            attribute["key"] == "agentropic.task.priority" && attribute["value"]["intValue"] == "3"
+        }));
+}
@@ -49,3 +49,3141 @@ fn round_robin_scheduler() {
     scheduler.reset();
     assert_eq!(scheduler.current_index(), 0);
 }
+
+#[test]
+fn task_retry_consults_error_class() {
+    use std::time::{Duration, Instant};
+
+    let mut task = Task::new(AgentId::new(), 1).with_max_retries(2);
+    task.record_attempt();
+    let now = Instant::now();
+
+    assert!(task.should_retry_at(&ErrorClass::Transient, Duration::ZERO, now));
+    assert!(!task.should_retry_at(&ErrorClass::Permanent, Duration::ZERO, now));
+
+    task.record_attempt();
+    task.record_attempt();
+    assert!(!task.should_retry_at(&ErrorClass::Transient, Duration::ZERO, now));
+}
+
+#[test]
+fn unhealthy_agent_tasks_are_held() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    let agent_id = AgentId::new();
+
+    scheduler.set_agent_health(agent_id, HealthStatus::Unhealthy);
+    scheduler.queue_mut().push(Task::new(agent_id, 1));
+
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(scheduler.held_count(), 1);
+
+    scheduler.set_agent_health(agent_id, HealthStatus::Healthy);
+    let task = scheduler.next_task().unwrap();
+    assert_eq!(task.agent_id(), &agent_id);
+    assert_eq!(scheduler.held_count(), 0);
+
+    let mut collector = Collector::new();
+    scheduler.collect_metrics(&mut collector);
+    assert_eq!(
+        collector.metrics()[0].name(),
+        "scheduler_held_due_to_health_seconds"
+    );
+}
+
+#[test]
+fn held_time_is_recorded_by_reason() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_clock(clock.clone());
+    let (unhealthy, capped) = (AgentId::new(), AgentId::new());
+    scheduler.set_agent_health(unhealthy, HealthStatus::Unhealthy);
+    scheduler.set_runtime_cap(
+        capped,
+        RuntimeCap::new(Duration::from_secs(1), Duration::from_secs(3600)),
+    );
+    scheduler.record_runtime(capped, Duration::from_secs(1));
+    scheduler.submit(Task::new(unhealthy, 1)).unwrap();
+    scheduler.submit(Task::new(capped, 1)).unwrap();
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(scheduler.held_count(), 2);
+
+    clock.advance(Duration::from_secs(5));
+    scheduler.set_agent_health(unhealthy, HealthStatus::Healthy);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &unhealthy);
+    clock.advance(Duration::from_secs(5));
+    // Only health holds count as held due to health
+    assert_eq!(scheduler.held_due_to_health(), Duration::from_secs(5));
+    assert_eq!(
+        scheduler.held_for(HoldReason::RuntimeCap),
+        Duration::from_secs(10)
+    );
+
+    let mut collector = Collector::new();
+    scheduler.collect_metrics(&mut collector);
+    let capped_seconds = collector
+        .metrics()
+        .iter()
+        .find(|metric| {
+            metric.name() == "scheduler_held_seconds"
+                && metric.labels() == [("reason".to_string(), "runtime_cap".to_string())]
+        })
+        .unwrap();
+    assert_eq!(capped_seconds.value(), 10.0);
+}
+
+#[test]
+fn gated_tasks_reroute_to_pool_sibling() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    let primary = AgentId::new();
+    let sibling = AgentId::new();
+
+    scheduler.add_pool(
+        AgentPool::new("workers")
+            .with_member(primary)
+            .with_member(sibling),
+    );
+    scheduler.set_agent_ready(primary, false);
+    scheduler.queue_mut().push(Task::new(primary, 1));
+
+    let task = scheduler.next_task().unwrap();
+    assert_eq!(task.agent_id(), &sibling);
+}
+
+#[test]
+fn task_retry_suppressed_when_deadline_unreachable() {
+    use std::time::{Duration, Instant};
+
+    let now = Instant::now();
+    let mut task = Task::new(AgentId::new(), 1)
+        .with_max_retries(5)
+        .with_deadline(now + Duration::from_secs(10))
+        .with_expected_runtime(Duration::from_secs(4));
+    task.record_attempt();
+
+    assert!(task.should_retry_at(&ErrorClass::Transient, Duration::from_secs(1), now));
+    assert!(!task.should_retry_at(&ErrorClass::Transient, Duration::from_secs(8), now));
+    assert_eq!(
+        task.retry_class_at(&ErrorClass::Transient, Duration::from_secs(8), now),
+        ErrorClass::DeadlineExceeded
+    );
+    // A later reading of the clock leaves less room for the retry
+    assert!(!task.should_retry_at(
+        &ErrorClass::Transient,
+        Duration::from_secs(1),
+        now + Duration::from_secs(6)
+    ));
+}
+
+#[test]
+fn engine_profile_sets_batch_size() {
+    let config = RuntimeConfig::new().with_profile(EngineProfile::Latency);
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_tuning(config.tuning());
+
+    for _ in 0..3 {
+        scheduler.queue_mut().push(Task::new(AgentId::new(), 1));
+    }
+    assert_eq!(scheduler.next_batch().len(), 1);
+
+    let tuning = EngineProfile::Throughput.tuning();
+    assert!(tuning.batch_size > EngineProfile::Balanced.tuning().batch_size);
+    assert_eq!(RuntimeConfig::default().profile, EngineProfile::Balanced);
+}
+
+#[test]
+fn blocking_scheduler_runs_until_idle() {
+    let policy = SchedulingPolicy::new(PolicyType::FCFS);
+    let mut scheduler = BlockingScheduler::new(Scheduler::new(policy)).unwrap();
+    scheduler.submit(Task::new(AgentId::new(), 1)).unwrap();
+    scheduler.submit(Task::new(AgentId::new(), 2)).unwrap();
+
+    let results = scheduler.run_until_idle(|task| {
+        let priority = task.priority();
+        async move {
+            tokio::task::yield_now().await;
+            if priority == 2 {
+                Err(RuntimeError::ExecutionFailed("failed".into()))
+            } else {
+                Ok(())
+            }
+        }
+    });
+
+    assert_eq!(results.len(), 2);
+    assert!(results[0].1.is_ok());
+    assert!(results[1].1.is_err());
+    assert_eq!(results[1].0.attempts(), 1);
+    assert!(scheduler.scheduler().queue().is_empty());
+}
+
+#[test]
+fn scheduler_records_queue_wait() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.queue_mut().push(Task::new(AgentId::new(), 1));
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    scheduler.next_task().unwrap();
+
+    let histogram = scheduler.queue_wait_histogram();
+    assert_eq!(histogram.count(), 1);
+    assert!(histogram.quantile(0.5) >= std::time::Duration::from_millis(5));
+
+    let mut collector = Collector::new();
+    scheduler.collect_metrics(&mut collector);
+    assert!(collector
+        .metrics()
+        .iter()
+        .any(|metric| metric.name() == "scheduler_queue_wait_seconds"));
+    let depth = collector
+        .metrics()
+        .iter()
+        .find(|metric| metric.name() == "scheduler_queue_depth")
+        .unwrap();
+    assert_eq!(depth.value(), 0.0);
+}
+
+#[test]
+fn scheduler_exports_overhead_as_folded_stacks() {
+    let policy = SchedulingPolicy::new(PolicyType::FCFS);
+    let mut scheduler = Scheduler::new(policy).with_overhead_profiling();
+    let healthy = AgentId::new();
+    let unhealthy = AgentId::new();
+    scheduler.set_agent_health(unhealthy, HealthStatus::Unhealthy);
+    scheduler.queue_mut().push(Task::new(unhealthy, 1));
+    scheduler.queue_mut().push(Task::new(healthy, 1));
+
+    assert!(scheduler.next_task().is_some());
+
+    let folded = scheduler.overhead().to_folded();
+    for phase in ["selection", "dispatch", "bookkeeping", "accounting"] {
+        assert!(
+            folded.contains(&format!("scheduler;next_task;{} ", phase)),
+            "missing {} in {}",
+            phase,
+            folded
+        );
+    }
+    assert!(Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .overhead()
+        .to_folded()
+        .is_empty());
+}
+
+fn spin(duration: std::time::Duration) {
+    let until = std::time::Instant::now() + duration;
+    while std::time::Instant::now() < until {
+        std::hint::spin_loop();
+    }
+}
+
+#[tokio::test]
+async fn cpu_budget_cancels_busy_task() {
+    use std::time::Duration;
+
+    let budget = CpuBudget::new(Duration::from_millis(10));
+    let result = budget
+        .enforce(async {
+            for _ in 0..1000 {
+                spin(Duration::from_millis(5));
+                tokio::task::yield_now().await;
+            }
+            Ok::<(), RuntimeError>(())
+        })
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(RuntimeError::Scheduler(
+            SchedulerError::CpuBudgetExceeded { .. }
+        ))
+    ));
+}
+
+#[tokio::test]
+async fn cpu_budget_demotes_instead_of_cancelling() {
+    use std::time::Duration;
+
+    let budget = CpuBudget::new(Duration::from_millis(5)).demote();
+    assert_eq!(budget.action(), CpuBudgetAction::Demote);
+
+    let mut budgeted = budget.enforce(async {
+        for _ in 0..4 {
+            spin(Duration::from_millis(5));
+            tokio::task::yield_now().await;
+        }
+        Ok::<u32, RuntimeError>(7)
+    });
+
+    assert_eq!((&mut budgeted).await.unwrap(), 7);
+    assert!(budgeted.is_exceeded());
+    assert_eq!(budget.priority_after(5, budgeted.used()), 0);
+}
+
+#[tokio::test]
+async fn cpu_budget_demotion_lowers_the_task_priority() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    let scheduler = Arc::new(tokio::sync::Mutex::new(
+        Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+            .with_tuning(EngineProfile::Latency.tuning()),
+    ));
+    let priorities = Arc::new(Mutex::new(Vec::new()));
+    let log = priorities.clone();
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        move |task: &Task| {
+            let first = log.lock().unwrap().is_empty();
+            log.lock().unwrap().push(task.priority());
+            async move {
+                spin(Duration::from_millis(10));
+                tokio::task::yield_now().await;
+                if first {
+                    return Err(RuntimeError::ExecutionFailed("flaky".into()));
+                }
+                Ok(())
+            }
+        },
+        shutdown.clone(),
+    ));
+
+    let budget = CpuBudget::new(Duration::from_millis(5)).demote_to(2);
+    scheduler
+        .lock()
+        .await
+        .submit(
+            Task::new(AgentId::new(), 7)
+                .with_cpu_budget(budget)
+                .with_max_retries(1),
+        )
+        .unwrap();
+    while priorities.lock().unwrap().len() < 2 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    shutdown.cancel();
+    driver.await.unwrap();
+
+    // The retry of the attempt that overran its budget runs demoted
+    assert_eq!(*priorities.lock().unwrap(), vec![7, 2]);
+}
+
+#[test]
+fn runtime_cap_defers_until_next_window() {
+    use std::time::Duration;
+
+    let agent = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.set_runtime_cap(
+        agent,
+        RuntimeCap::new(Duration::from_millis(10), Duration::from_millis(50)),
+    );
+    scheduler.record_runtime(agent, Duration::from_millis(15));
+    scheduler.queue_mut().push(Task::new(agent, 1));
+
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(scheduler.held_count(), 1);
+    let stats = scheduler.agent_stats(&agent);
+    assert!(stats.capped);
+    assert_eq!(stats.window_runtime, Duration::from_millis(15));
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(!scheduler.agent_stats(&agent).capped);
+    assert!(scheduler.next_task().is_some());
+    assert_eq!(
+        scheduler.agent_stats(&agent).total_runtime,
+        Duration::from_millis(15)
+    );
+}
+
+#[test]
+fn runtime_cap_rejects_when_configured() {
+    use std::time::Duration;
+
+    let agent = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.set_runtime_cap(
+        agent,
+        RuntimeCap::new(Duration::from_millis(10), Duration::from_secs(3600)).reject(),
+    );
+    scheduler.record_runtime(agent, Duration::from_millis(10));
+    let capped = Task::new(agent, 1);
+    let capped_id = capped.id();
+    scheduler.submit(capped).unwrap();
+    scheduler
+        .submit(Task::new(AgentId::new(), 1).after(&[capped_id]))
+        .unwrap();
+    scheduler.submit(Task::new(AgentId::new(), 1)).unwrap();
+
+    let task = scheduler.next_task().unwrap();
+    assert_ne!(task.agent_id(), &agent);
+    assert_eq!(scheduler.held_count(), 0);
+    assert_eq!(scheduler.agent_stats(&agent).rejected, 1);
+    // The rejected task is dead-lettered and takes its dependent with it
+    let reasons: Vec<_> = scheduler
+        .dead_letters()
+        .iter()
+        .map(|entry| entry.reason)
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            DeadLetterReason::RuntimeCapExceeded,
+            DeadLetterReason::DependencyFailed
+        ]
+    );
+    assert_eq!(scheduler.waiting_on_dependencies(), 0);
+}
+
+#[test]
+fn payload_limit_rejects_oversized_task() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_payload_limit(SizeLimit::new(4));
+
+    let result = scheduler.submit(Task::new(AgentId::new(), 1).with_payload(vec![0; 8]));
+
+    assert!(matches!(
+        result,
+        Err(RuntimeError::PayloadTooLarge { size: 8, limit: 4 })
+    ));
+    assert!(scheduler.queue().is_empty());
+}
+
+#[test]
+fn payload_limit_applies_to_restored_checkpoints() {
+    let agent_id = AgentId::new();
+    let mut unlimited = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    unlimited
+        .submit(Task::new(agent_id, 1).with_payload(vec![0; 8]))
+        .unwrap();
+    unlimited
+        .submit(Task::new(agent_id, 1).with_payload(vec![0; 2]))
+        .unwrap();
+
+    // An oversized task in the checkpoint is dead-lettered rather than queued
+    let mut limited = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_payload_limit(SizeLimit::new(4));
+    limited.warm_start(unlimited.state());
+    assert_eq!(limited.queue().len(), 1);
+    assert_eq!(limited.dead_letters().len(), 1);
+
+    // Snapshots carry the limits, and truncated tasks keep their flag
+    let mut truncating = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_payload_limit(SizeLimit::new(4).truncate());
+    truncating.warm_start(unlimited.state());
+    let restored = Scheduler::restore_from(truncating.snapshot());
+    let payloads: Vec<_> = restored
+        .queued()
+        .map(|task| (task.payload().len(), task.is_truncated()))
+        .collect();
+    assert_eq!(payloads, vec![(4, true), (2, false)]);
+}
+
+#[test]
+fn result_limit_truncates_and_flags() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_result_limit(SizeLimit::new(4).truncate());
+    scheduler.submit(Task::new(AgentId::new(), 1)).unwrap();
+    let mut task = scheduler.next_task().unwrap();
+
+    scheduler
+        .accept_result(&mut task, b"oversized".to_vec())
+        .unwrap();
+
+    assert_eq!(task.result(), Some(&b"over"[..]));
+    assert!(task.is_truncated());
+}
+
+#[test]
+fn priority_quota_rejects_or_demotes_overflow() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+        .with_priority_quota(9, PriorityQuota::new(1).demote())
+        .with_priority_quota(8, PriorityQuota::new(1));
+
+    scheduler.submit(Task::new(AgentId::new(), 9)).unwrap();
+    scheduler.submit(Task::new(AgentId::new(), 9)).unwrap();
+    let overflow = scheduler.submit(Task::new(AgentId::new(), 9));
+
+    assert_eq!(scheduler.queued_at(9), 1);
+    assert_eq!(scheduler.queued_at(8), 1);
+    assert!(matches!(
+        overflow,
+        Err(RuntimeError::Queue(QueueError::PriorityFull {
+            priority: 8,
+            capacity: 1
+        }))
+    ));
+}
+
+#[test]
+fn max_wait_promotes_overdue_task() {
+    use std::time::Duration;
+
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+        .with_max_wait(Duration::from_millis(10));
+    let starving = Task::new(AgentId::new(), 1);
+    std::thread::sleep(Duration::from_millis(20));
+    scheduler.submit(Task::new(AgentId::new(), 9)).unwrap();
+    scheduler.submit(starving).unwrap();
+
+    let task = scheduler.next_task().unwrap();
+
+    assert_eq!(task.priority(), 1);
+    let events = scheduler.take_events();
+    assert!(matches!(
+        events.as_slice(),
+        [SchedulerEvent::WaitBoundExceeded { priority: 1, .. }]
+    ));
+}
+
+#[test]
+fn priority_aging_lets_waiting_tasks_overtake() {
+    use std::time::Duration;
+
+    let aging = PriorityAging::new(Duration::from_millis(5)).with_max_boost(3);
+    assert_eq!(aging.effective_priority(1, Duration::from_millis(12)), 3);
+    assert_eq!(aging.effective_priority(1, Duration::from_secs(60)), 4);
+
+    let low = AgentId::new();
+    let mut plain = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority));
+    let mut aged = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+        .with_priority_aging(PriorityAging::new(Duration::from_millis(5)));
+    plain.submit(Task::new(low, 1)).unwrap();
+    aged.submit(Task::new(low, 1)).unwrap();
+    std::thread::sleep(Duration::from_millis(40));
+    for _ in 0..3 {
+        plain.submit(Task::new(AgentId::new(), 5)).unwrap();
+        aged.submit(Task::new(AgentId::new(), 5)).unwrap();
+    }
+
+    assert_eq!(plain.next_task().unwrap().priority(), 5);
+    assert_eq!(aged.next_task().unwrap().agent_id(), &low);
+}
+
+#[test]
+fn starvation_bound_limits_wait_under_high_priority_flood() {
+    use std::time::{Duration, Instant};
+
+    let bound = Duration::from_millis(20);
+    let low = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+        .with_priority_aging(
+            PriorityAging::new(Duration::from_secs(3600)).with_starvation_bound(bound),
+        );
+    let submitted = Instant::now();
+    scheduler.submit(Task::new(low, 1)).unwrap();
+
+    // Two high-priority tasks arrive for every one dispatched
+    let waited = loop {
+        scheduler.submit(Task::new(AgentId::new(), 9)).unwrap();
+        scheduler.submit(Task::new(AgentId::new(), 9)).unwrap();
+        let task = scheduler.next_task().unwrap();
+        if task.agent_id() == &low {
+            break submitted.elapsed();
+        }
+        assert!(
+            submitted.elapsed() < Duration::from_secs(5),
+            "low task starved"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    assert!(waited > bound);
+    assert_eq!(scheduler.next_task().unwrap().priority(), 9);
+}
+
+#[test]
+fn warm_start_restores_tasks_and_accounting() {
+    use std::time::Duration;
+
+    let agent = AgentId::new();
+    let mut before = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    before
+        .submit(Task::new(agent, 3).with_payload(b"queued".to_vec()))
+        .unwrap();
+    before.record_runtime(agent, Duration::from_millis(40));
+    let mut state = before.state();
+    state.add_in_flight(&Task::new(agent, 7));
+
+    let json = serde_json::to_string(&state).unwrap();
+    let mut after = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    after.warm_start(serde_json::from_str(&json).unwrap());
+
+    assert_eq!(after.next_task().unwrap().priority(), 7);
+    assert_eq!(after.next_task().unwrap().payload(), b"queued");
+    assert_eq!(
+        after.agent_stats(&agent).total_runtime,
+        Duration::from_millis(40)
+    );
+}
+
+#[tokio::test]
+async fn state_keeps_in_flight_tasks_and_ages_times_by_downtime() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    let clock = Arc::new(VirtualClock::new());
+    let agent = AgentId::new();
+    let configure = |scheduler: Scheduler| {
+        scheduler
+            .with_clock(clock.clone())
+            .with_tuning(EngineProfile::Latency.tuning())
+            .with_fair_share(
+                FairShareScheduler::new(1.0)
+                    .with_groups(ShareGroup::new("root", 1.0).with_agent(agent, 1.0)),
+            )
+            .with_rate_limiter(
+                RateLimiter::new()
+                    .with_agent_limit(agent, RateLimit::sliding_window(1, Duration::from_secs(60))),
+            )
+    };
+    let scheduler = Arc::new(tokio::sync::Mutex::new(configure(Scheduler::new(
+        SchedulingPolicy::new(PolicyType::FCFS),
+    ))));
+    {
+        let mut scheduler = scheduler.lock().await;
+        scheduler.submit(Task::new(agent, 1)).unwrap();
+        let deadline = clock.instant() + Duration::from_secs(30);
+        scheduler
+            .submit(Task::new(agent, 1).with_deadline(deadline))
+            .unwrap();
+    }
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        |_task: &Task| std::future::pending(),
+        shutdown.clone(),
+    ));
+    while scheduler.lock().await.in_flight_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+    let state = scheduler.lock().await.state();
+    shutdown.cancel();
+    scheduler.lock().await.abort_in_flight();
+    driver.await.unwrap();
+    assert_eq!(state.in_flight.len(), 1);
+    assert_eq!(state.queued.len(), 1);
+
+    // The runtime is down for 20 seconds
+    clock.advance(Duration::from_secs(20));
+    let json = serde_json::to_string(&state).unwrap();
+    let mut restored =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_clock(clock.clone());
+    restored.warm_start(serde_json::from_str(&json).unwrap());
+
+    let deadline = restored.queued().find_map(Task::deadline).unwrap();
+    assert_eq!(deadline - clock.instant(), Duration::from_secs(10));
+    let usage = restored.fair_share().unwrap().groups().unwrap().usage();
+    assert!(usage.iter().any(|node| node.consumed == 1));
+    assert!(!restored.rate_limiter().admits(&agent, clock.instant()));
+    clock.advance(Duration::from_secs(41));
+    assert!(restored.rate_limiter().admits(&agent, clock.instant()));
+}
+
+#[cfg(feature = "persistence")]
+#[test]
+fn scheduler_state_saves_and_loads() {
+    let path = std::env::temp_dir().join(format!("scheduler-state-{}.json", std::process::id()));
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.submit(Task::new(AgentId::new(), 1)).unwrap();
+
+    scheduler.state().save(&path).unwrap();
+    let loaded = SchedulerState::load(&path).unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.queued.len(), 1);
+    assert!(SchedulerState::load(&path).unwrap().is_none());
+}
+
+#[test]
+fn cordoned_agent_is_drained_until_uncordoned() {
+    let agent = AgentId::new();
+    let sibling = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.add_pool(AgentPool::new("workers").with_member(agent));
+    scheduler.cordon(agent);
+    scheduler.submit(Task::new(agent, 1)).unwrap();
+
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(scheduler.held_count(), 1);
+
+    scheduler.add_pool(
+        AgentPool::new("backup")
+            .with_member(agent)
+            .with_member(sibling),
+    );
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &sibling);
+
+    scheduler.submit(Task::new(agent, 1)).unwrap();
+    scheduler.uncordon(&agent);
+    assert!(!scheduler.is_cordoned(&agent));
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &agent);
+}
+
+#[tokio::test]
+async fn rolling_restart_aborts_on_health_regression() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    let members: Vec<_> = (0..6).map(|_| AgentId::new()).collect();
+    let broken = members[2];
+    let mut pool = AgentPool::new("workers");
+    for member in &members {
+        pool.add_member(*member);
+    }
+    let gate = HealthGate::new(move |agent_id| async move {
+        if agent_id == broken {
+            HealthStatus::Unhealthy
+        } else {
+            HealthStatus::Healthy
+        }
+    })
+    .with_timeout(Duration::from_millis(20))
+    .with_poll_interval(Duration::from_millis(5));
+
+    let restarted = Arc::new(Mutex::new(Vec::new()));
+    let log = restarted.clone();
+    let report = pool
+        .rolling_restart(2, Duration::from_millis(1), &gate, move |agent_id| {
+            log.lock().unwrap().push(agent_id);
+            async { Ok(()) }
+        })
+        .await;
+
+    assert!(report.aborted);
+    assert_eq!(report.regressed, [broken]);
+    assert_eq!(*restarted.lock().unwrap(), members[..4]);
+    assert!(!report.is_success());
+}
+
+#[tokio::test]
+async fn rolling_restart_follows_injected_clock() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let members: Vec<_> = (0..4).map(|_| AgentId::new()).collect();
+    let broken = members[2];
+    let mut pool = AgentPool::new("workers").with_clock(clock.clone());
+    for member in &members {
+        pool.add_member(*member);
+    }
+    let gate = HealthGate::new(move |agent_id| async move {
+        if agent_id == broken {
+            HealthStatus::Unhealthy
+        } else {
+            HealthStatus::Healthy
+        }
+    })
+    .with_timeout(Duration::from_secs(30))
+    .with_poll_interval(Duration::from_secs(5))
+    .with_clock(clock.clone());
+
+    let rollout = tokio::spawn(async move {
+        pool.rolling_restart(2, Duration::from_secs(60), &gate, |_| async { Ok(()) })
+            .await
+    });
+    while !rollout.is_finished() {
+        tokio::task::yield_now().await;
+        clock.advance(Duration::from_secs(1));
+    }
+
+    let report = rollout.await.unwrap();
+    assert_eq!(report.regressed, [broken]);
+    // One pause between the batches, then the broken instance's timeout
+    assert!(clock.now() >= Duration::from_secs(90));
+}
+
+#[test]
+fn deployment_shifts_fraction_and_rolls_back_on_errors() {
+    let stable = AgentId::new();
+    let candidate = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.deploy(Deployment::new(stable, candidate, 0.25).with_auto_decision(2, 0.1));
+
+    for _ in 0..8 {
+        scheduler.submit(Task::new(stable, 1)).unwrap();
+    }
+    let agents: Vec<_> = std::iter::from_fn(|| scheduler.next_task())
+        .map(|task| *task.agent_id())
+        .collect();
+    assert_eq!(
+        agents.iter().filter(|agent| **agent == candidate).count(),
+        2
+    );
+
+    for _ in 0..8 {
+        scheduler.submit(Task::new(stable, 1)).unwrap();
+    }
+    scheduler.record_outcome(&candidate, false);
+    scheduler.record_outcome(&candidate, false);
+    scheduler.record_outcome(&stable, true);
+    // Undecided until the stable version has as many outcomes to compare against
+    assert_eq!(
+        scheduler.deployment(&stable).unwrap().state(),
+        DeploymentState::Shifting
+    );
+    scheduler.record_outcome(&stable, true);
+
+    let deployment = scheduler.deployment(&stable).unwrap();
+    assert_eq!(deployment.state(), DeploymentState::RolledBack);
+    // Tasks queued for the candidate before the rollback go to the stable version
+    let agents: Vec<_> = std::iter::from_fn(|| scheduler.next_task())
+        .map(|task| *task.agent_id())
+        .collect();
+    assert_eq!(agents, vec![stable; 8]);
+    scheduler.submit(Task::new(stable, 1)).unwrap();
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &stable);
+
+    // A manual rollback moves queued candidate tasks too
+    let (stable, candidate) = (AgentId::new(), AgentId::new());
+    scheduler.deploy(Deployment::new(stable, candidate, 1.0));
+    scheduler.submit(Task::new(stable, 1)).unwrap();
+    assert_eq!(scheduler.roll_back(&stable), Some(1));
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &stable);
+}
+
+#[test]
+fn version_requirement_routes_to_compatible_agent() {
+    use agentropic_runtime::scheduler::{Version, VersionReq};
+
+    let old = AgentId::new();
+    let new = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.add_pool(AgentPool::new("parsers").with_member(old).with_member(new));
+    scheduler.set_agent_version(old, Version::new(2, 0, 3));
+    scheduler.set_agent_version(new, Version::new(2, 1, 0));
+    let req = VersionReq::parse(">=2.1").unwrap();
+
+    scheduler
+        .submit(Task::new(old, 1).with_version_req(req.clone()))
+        .unwrap();
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &new);
+
+    let strict = VersionReq::parse(">=3").unwrap();
+    let result = scheduler.submit(Task::new(old, 1).with_version_req(strict));
+    let error = result.unwrap_err();
+    assert_eq!(error.code(), "scheduler.no_compatible_agent");
+    assert!(matches!(
+        error,
+        RuntimeError::Scheduler(SchedulerError::NoCompatibleAgent { requirement, .. })
+            if requirement == ">=3"
+    ));
+}
+
+#[test]
+fn shadow_policy_records_would_be_dispatches() {
+    let low = AgentId::new();
+    let high = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_shadow_policy(SchedulingPolicy::new(PolicyType::Priority));
+    scheduler.submit(Task::new(low, 1)).unwrap();
+    scheduler.submit(Task::new(high, 9)).unwrap();
+
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &low);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &high);
+
+    let shadow = scheduler.shadow().unwrap();
+    let picked: Vec<_> = shadow.dispatched().iter().map(|d| d.agent_id).collect();
+    assert_eq!(picked, [high, low]);
+    assert_eq!(shadow.report().dispatches, 2);
+    assert_eq!(shadow.report().agreement, 0.0);
+}
+
+#[test]
+fn provider_pool_limits_dispatch_across_agents() {
+    let first = AgentId::new();
+    let second = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.add_provider(
+        "openai",
+        ProviderLimits::new()
+            .with_requests_per_minute(10)
+            .with_tokens_per_minute(1_000),
+    );
+    scheduler
+        .submit(Task::new(first, 1).with_provider(ProviderDemand::new("openai", 600)))
+        .unwrap();
+    scheduler
+        .submit(Task::new(second, 1).with_provider(ProviderDemand::new("openai", 600)))
+        .unwrap();
+    scheduler.submit(Task::new(second, 1)).unwrap();
+
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &first);
+    // Second provider task would exceed the token limit and is held
+    let next = scheduler.next_task().unwrap();
+    assert!(next.provider().is_none());
+    assert!(scheduler.next_task().is_none());
+
+    let usage = scheduler.provider_usage("openai").unwrap();
+    assert_eq!(usage.requests, 1);
+    assert_eq!(usage.tokens, 600);
+}
+
+#[test]
+fn provider_pool_without_requests_admits_nothing() {
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.add_provider("openai", ProviderLimits::new().with_requests_per_minute(0));
+    scheduler
+        .submit(Task::new(agent_id, 1).with_provider(ProviderDemand::new("openai", 10)))
+        .unwrap();
+
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(scheduler.provider_usage("openai").unwrap().requests, 0);
+}
+
+#[test]
+fn rate_limiter_combines_token_bucket_and_sliding_window() {
+    use std::time::{Duration, Instant};
+
+    let agent = AgentId::new();
+    let other = AgentId::new();
+    let mut limiter = RateLimiter::new()
+        .with_global_limit(RateLimit::sliding_window(3, Duration::from_secs(10)))
+        .with_agent_limit(agent, RateLimit::token_bucket(2, 1.0));
+    let now = Instant::now();
+
+    assert!(limiter.try_acquire(agent, now));
+    assert!(limiter.try_acquire(agent, now));
+    // Burst used up; one token returns per second
+    assert!(!limiter.admits(&agent, now));
+    assert_eq!(
+        limiter.available_at(&agent, now),
+        Some(now + Duration::from_secs(1))
+    );
+    assert!(limiter.try_acquire(other, now));
+
+    // The global window is full until the first dispatches age out
+    let later = now + Duration::from_secs(2);
+    assert!(!limiter.admits(&agent, later));
+    assert!(!limiter.admits(&other, later));
+    assert_eq!(
+        limiter.available_at(&other, later),
+        Some(now + Duration::from_secs(10))
+    );
+    assert!(limiter.try_acquire(agent, now + Duration::from_secs(10)));
+}
+
+#[test]
+fn scheduler_holds_tasks_of_rate_limited_agents() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let limited = AgentId::new();
+    let free = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_clock(clock.clone())
+        .with_rate_limiter(RateLimiter::new().with_agent_limit(
+            limited,
+            RateLimit::sliding_window(1, Duration::from_secs(60)),
+        ));
+    scheduler.submit(Task::new(limited, 1)).unwrap();
+    scheduler.submit(Task::new(limited, 1)).unwrap();
+    scheduler.submit(Task::new(free, 1)).unwrap();
+
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &limited);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &free);
+    assert!(scheduler.next_task().is_none());
+    assert!(!scheduler.is_dispatchable(&limited));
+
+    let release = scheduler.next_wakeup_at().unwrap();
+    clock.advance(Duration::from_secs(60));
+    assert!(clock.instant() >= release);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &limited);
+}
+
+#[test]
+fn budget_breaker_rejects_non_critical_tasks_until_raised() {
+    use std::time::Duration;
+
+    let agent = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.set_cost_budget(
+        agent,
+        CostBudget::new(100, Duration::from_secs(3600)).with_critical_priority(10),
+    );
+    scheduler.record_cost(agent, 120);
+
+    let error = scheduler.submit(Task::new(agent, 1)).unwrap_err();
+    assert!(matches!(
+        error,
+        RuntimeError::Scheduler(SchedulerError::BudgetExceeded {
+            spent: 120,
+            limit: 100,
+            ..
+        })
+    ));
+    assert!(scheduler.submit(Task::new(agent, 10)).is_ok());
+
+    scheduler.set_cost_budget(agent, CostBudget::new(500, Duration::from_secs(3600)));
+    assert!(scheduler.submit(Task::new(agent, 1)).is_ok());
+    assert_eq!(
+        scheduler.take_events(),
+        [
+            SchedulerEvent::BudgetTripped {
+                agent_id: agent,
+                spent: 120,
+                limit: 100
+            },
+            SchedulerEvent::BudgetReset { agent_id: agent },
+        ]
+    );
+}
+
+#[test]
+fn tie_break_orders_within_priority() {
+    use std::time::Duration;
+
+    let agent = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+        .with_tie_break(TieBreak::SmallestCostFirst);
+    for seconds in [30, 5, 10] {
+        scheduler
+            .submit(Task::new(agent, 1).with_expected_runtime(Duration::from_secs(seconds)))
+            .unwrap();
+    }
+
+    let order: Vec<_> = std::iter::from_fn(|| scheduler.next_task())
+        .map(|task| task.expected_runtime().as_secs())
+        .collect();
+    assert_eq!(order, [5, 10, 30]);
+}
+
+#[test]
+fn weighted_lottery_favours_larger_shares() {
+    let heavy = AgentId::new();
+    let light = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+        .with_tie_break(TieBreak::WeightedLottery)
+        .with_lottery_seed(7);
+    scheduler.set_agent_share(heavy, 9.0);
+    scheduler.set_agent_share(light, 1.0);
+
+    let mut heavy_first = 0;
+    for _ in 0..200 {
+        scheduler.submit(Task::new(light, 1)).unwrap();
+        scheduler.submit(Task::new(heavy, 1)).unwrap();
+        heavy_first += usize::from(scheduler.next_task().unwrap().agent_id() == &heavy);
+        scheduler.next_task().unwrap();
+    }
+    assert!(heavy_first > 150, "heavy agent won {heavy_first} of 200");
+}
+
+#[test]
+fn execution_accounting_feeds_runtime_fair_share() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let (slow, quick) = (AgentId::new(), AgentId::new());
+    let root = ShareGroup::new("root", 1.0)
+        .with_agent(slow, 1.0)
+        .with_agent(quick, 1.0);
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FairShare))
+        .with_fair_share(
+            FairShareScheduler::default()
+                .with_groups(root)
+                .with_runtime_charging(),
+        )
+        .with_clock(clock.clone());
+    for agent in [slow, quick, slow, quick] {
+        scheduler.submit(Task::new(agent, 1)).unwrap();
+    }
+
+    // Each agent runs once; the slow one uses ten times the time
+    for (used, success) in [
+        (Duration::from_millis(100), true),
+        (Duration::from_millis(10), false),
+    ] {
+        let task = scheduler.next_task().unwrap();
+        clock.advance(used);
+        scheduler.record_runtime(*task.agent_id(), used);
+        scheduler.record_execution(&task, used, success);
+        scheduler.record_outcome(task.agent_id(), success);
+    }
+
+    let stats = scheduler.agent_stats(&slow);
+    assert_eq!((stats.tasks, stats.failures), (1, 0));
+    assert_eq!(stats.total_runtime, Duration::from_millis(100));
+    assert_eq!(stats.average_runtime(), Duration::from_millis(100));
+    assert_eq!(stats.average_latency(), Duration::from_millis(100));
+    let stats = scheduler.agent_stats(&quick);
+    assert_eq!(stats.failure_rate(), 1.0);
+    assert_eq!(stats.average_latency(), Duration::from_millis(110));
+    assert_eq!(
+        scheduler.agent_stats(&AgentId::new()),
+        AgentStats::default()
+    );
+
+    // Charged by time used, the quick agent is served next
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &quick);
+}
+
+#[test]
+fn share_groups_divide_slots_between_tenants() {
+    let big = AgentId::new();
+    let research = AgentId::new();
+    let support = AgentId::new();
+    let root = ShareGroup::new("root", 1.0)
+        .with_group(ShareGroup::new("acme", 3.0).with_agent(big, 1.0))
+        .with_group(
+            ShareGroup::new("globex", 1.0)
+                .with_group(ShareGroup::new("research", 1.0).with_agent(research, 1.0))
+                .with_group(ShareGroup::new("support", 1.0).with_agent(support, 1.0)),
+        );
+    assert_eq!(root.entitlement(&big), Some(0.75));
+    assert_eq!(root.entitlement(&support), Some(0.125));
+
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FairShare))
+        .with_fair_share(FairShareScheduler::default().with_groups(root));
+    for _ in 0..40 {
+        for agent in [research, support, big] {
+            scheduler.submit(Task::new(agent, 1)).unwrap();
+        }
+    }
+    let mut served = std::collections::HashMap::new();
+    for _ in 0..40 {
+        *served
+            .entry(*scheduler.next_task().unwrap().agent_id())
+            .or_insert(0) += 1;
+    }
+    assert_eq!(served[&big], 30);
+    assert_eq!(served[&research], 5);
+    assert_eq!(served[&support], 5);
+
+    let groups = scheduler.fair_share().unwrap().groups().unwrap();
+    assert_eq!(groups.group("globex").unwrap().consumed(), 10);
+    let usage = groups.usage();
+    let acme = usage
+        .iter()
+        .find(|usage| usage.path == ["root", "acme"])
+        .unwrap();
+    assert_eq!(acme.consumed, 30);
+    assert_eq!(acme.consumed_fraction, 0.75);
+}
+
+#[test]
+fn runtime_cap_windows_follow_injected_clock() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let agent = AgentId::new();
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_clock(clock.clone());
+    scheduler.set_runtime_cap(
+        agent,
+        RuntimeCap::new(Duration::from_secs(60), Duration::from_secs(3600)),
+    );
+    scheduler.record_runtime(agent, Duration::from_secs(90));
+    scheduler.submit(Task::new(agent, 1)).unwrap();
+
+    assert!(scheduler.next_task().is_none());
+    clock.advance(Duration::from_secs(3600));
+    assert!(!scheduler.is_capped(&agent));
+    assert!(scheduler.next_task().is_some());
+}
+
+#[test]
+fn dead_letters_are_timed_by_the_scheduler_clock() {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    let epoch = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let clock = Arc::new(VirtualClock::starting_at(epoch));
+    let agent = AgentId::new();
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_clock(clock.clone());
+    scheduler.set_runtime_cap(
+        agent,
+        RuntimeCap::new(Duration::from_secs(60), Duration::from_secs(3600)).reject(),
+    );
+    scheduler.record_runtime(agent, Duration::from_secs(60));
+    scheduler.submit(Task::new(agent, 1)).unwrap();
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(
+        scheduler.dead_letters().iter().next().unwrap().dead_at,
+        epoch
+    );
+
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(
+        scheduler.purge_dead_letters_older_than(Duration::from_secs(60)),
+        0
+    );
+    clock.advance(Duration::from_secs(31));
+    assert_eq!(
+        scheduler.purge_dead_letters_older_than(Duration::from_secs(60)),
+        1
+    );
+    assert!(scheduler.dead_letters().is_empty());
+}
+
+#[tokio::test]
+async fn dispatch_loop_wakes_on_events_not_polling() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let agent = AgentId::new();
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_clock(clock.clone());
+
+    scheduler.submit(Task::new(agent, 1)).unwrap();
+    assert_eq!(scheduler.wait_for_work().await, WakeReason::Push);
+    assert!(scheduler.next_task().is_some());
+
+    // A capped agent's task is retried when its window resets
+    scheduler.set_runtime_cap(
+        agent,
+        RuntimeCap::new(Duration::from_secs(1), Duration::from_secs(60)),
+    );
+    scheduler.record_runtime(agent, Duration::from_secs(2));
+    scheduler.submit(Task::new(agent, 1)).unwrap();
+    scheduler.wait_for_work().await;
+    assert!(scheduler.next_task().is_none());
+    let reset = scheduler.next_wakeup_at().unwrap();
+
+    let wakeup = scheduler.wakeup();
+    let waiter = tokio::spawn(async move { wakeup.wait(Some(reset)).await });
+    while clock.sleepers() == 0 {
+        tokio::task::yield_now().await;
+    }
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(waiter.await.unwrap(), WakeReason::Timer);
+    assert!(scheduler.next_task().is_some());
+
+    let stats = scheduler.wakeup().stats();
+    assert_eq!((stats.push, stats.timer), (2, 1));
+}
+
+#[tokio::test]
+async fn coalesced_wakeups_wait_out_the_window_before_dispatching() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, Mutex};
+    use tokio_util::sync::CancellationToken;
+
+    let clock = Arc::new(VirtualClock::new());
+    let window = Duration::from_secs(60);
+    let tuning = EngineTuning {
+        wakeup: WakeupStrategy::Coalesced(window),
+        ..EngineProfile::Balanced.tuning()
+    };
+    let scheduler = Arc::new(Mutex::new(
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+            .with_clock(clock.clone())
+            .with_tuning(tuning),
+    ));
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(AgentId::new(), 1))
+        .unwrap();
+
+    let (ran, mut runs) = mpsc::unbounded_channel();
+    let shutdown = CancellationToken::new();
+    let dispatcher = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        move |task: &Task| {
+            let _ = ran.send(task.id());
+            async { Ok(()) }
+        },
+        shutdown.clone(),
+    ));
+
+    // The first task is dispatched at once; its submission's wakeup then
+    // opens a window that holds the next one
+    runs.recv().await.unwrap();
+    while clock.sleepers() == 0 {
+        tokio::task::yield_now().await;
+    }
+    let second = Task::new(AgentId::new(), 1);
+    let id = second.id();
+    scheduler.lock().await.submit(second).unwrap();
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+    assert!(runs.try_recv().is_err());
+
+    clock.advance(window);
+    assert_eq!(runs.recv().await, Some(id));
+    shutdown.cancel();
+    dispatcher.await.unwrap();
+}
+
+#[test]
+fn recurring_schedules_apply_misfire_policies() {
+    use std::time::{Duration, SystemTime};
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let minute = Duration::from_secs(60);
+    let task = Task::new(AgentId::new(), 1);
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.add_schedule(
+        RecurringSchedule::new("skip", &task, minute, start)
+            .with_misfire_policy(MisfirePolicy::Skip),
+    );
+    scheduler.add_schedule(RecurringSchedule::new("once", &task, minute, start));
+    scheduler.add_schedule(
+        RecurringSchedule::new("all", &task, minute, start)
+            .with_misfire_policy(MisfirePolicy::FireAllMissed),
+    );
+
+    assert_eq!(scheduler.fire_due(start - minute), 0);
+    assert_eq!(scheduler.fire_due(start), 3);
+
+    // Down for three and a half intervals: three fires missed
+    let after_downtime = start + minute * 3 + Duration::from_secs(30);
+    assert_eq!(scheduler.fire_due(after_downtime), 1 + 3);
+    assert!(scheduler
+        .schedules()
+        .iter()
+        .all(|schedule| schedule.next_fire() == Some(start + minute * 4)));
+}
+
+#[test]
+fn recurring_schedules_survive_warm_start() {
+    use std::time::{Duration, SystemTime};
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let mut before = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    before.add_schedule(
+        RecurringSchedule::new(
+            "all",
+            &Task::new(AgentId::new(), 1),
+            Duration::from_secs(10),
+            start,
+        )
+        .with_misfire_policy(MisfirePolicy::FireAllMissed),
+    );
+    let json = serde_json::to_string(&before.state()).unwrap();
+
+    let mut after = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    after.warm_start(serde_json::from_str(&json).unwrap());
+    assert_eq!(
+        after.schedules()[0].misfire_policy(),
+        MisfirePolicy::FireAllMissed
+    );
+    assert_eq!(after.fire_due(start + Duration::from_secs(25)), 3);
+    assert_eq!(after.queue().len(), 3);
+}
+
+#[test]
+fn recurring_fires_that_cannot_be_queued_are_dead_lettered() {
+    use std::time::{Duration, SystemTime};
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_queue_capacity(1, OverflowPolicy::Reject);
+    scheduler.add_schedule(
+        RecurringSchedule::new(
+            "all",
+            &Task::new(AgentId::new(), 1),
+            Duration::from_secs(10),
+            start,
+        )
+        .with_misfire_policy(MisfirePolicy::FireAllMissed),
+    );
+
+    // The first fire fills the queue; the other two still go somewhere
+    assert_eq!(scheduler.fire_due(start + Duration::from_secs(25)), 1);
+    assert_eq!(scheduler.queue().len(), 1);
+    assert_eq!(scheduler.dead_letters().len(), 2);
+    assert!(scheduler
+        .dead_letters()
+        .iter()
+        .all(|entry| entry.reason == DeadLetterReason::Undeliverable));
+    assert_eq!(
+        scheduler.schedules()[0].next_fire(),
+        Some(start + Duration::from_secs(30))
+    );
+}
+
+#[tokio::test]
+async fn run_fires_recurring_schedules_as_they_come_due() {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+    use tokio::sync::Mutex;
+    use tokio_util::sync::CancellationToken;
+
+    let agent_id = AgentId::new();
+    let clock = Arc::new(VirtualClock::starting_at(
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_000),
+    ));
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_clock(clock.clone())
+        .with_tuning(EngineProfile::Latency.tuning());
+    scheduler.add_schedule(RecurringSchedule::new(
+        "tick",
+        &Task::new(agent_id, 1),
+        Duration::from_secs(60),
+        clock.wall_time() + Duration::from_secs(60),
+    ));
+    assert_eq!(
+        scheduler.next_wakeup_at(),
+        Some(clock.instant() + Duration::from_secs(60))
+    );
+    let scheduler = Arc::new(Mutex::new(scheduler));
+    let (sender, mut fired) = tokio::sync::mpsc::unbounded_channel();
+    let shutdown = CancellationToken::new();
+    let run = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        move |task: &Task| {
+            let _ = sender.send(*task.agent_id());
+            async { Ok(()) }
+        },
+        shutdown.clone(),
+    ));
+
+    for _ in 0..2 {
+        while clock.sleepers() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(fired.try_recv().is_err());
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(fired.recv().await, Some(agent_id));
+    }
+    shutdown.cancel();
+    run.await.unwrap();
+}
+
+#[tokio::test]
+async fn run_dispatches_retries_and_shuts_down_gracefully() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    let agent_id = AgentId::new();
+    let scheduler = Arc::new(Mutex::new(Scheduler::new(SchedulingPolicy::new(
+        PolicyType::FCFS,
+    ))));
+    let runs = Arc::new(AtomicU32::new(0));
+    let shutdown = CancellationToken::new();
+
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        {
+            let runs = runs.clone();
+            move |task: &Task| {
+                let runs = runs.clone();
+                let first_attempt = task.attempts() == 1;
+                async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    if first_attempt {
+                        Err(RuntimeError::ExecutionFailed("transient".into()))
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+        },
+        shutdown.clone(),
+    ));
+
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 1).with_max_retries(1))
+        .unwrap();
+    while runs.load(Ordering::SeqCst) < 2 {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+
+    shutdown.cancel();
+    driver.await.unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert!(scheduler.lock().await.queue().is_empty());
+}
+
+#[tokio::test]
+async fn run_consults_the_scheduler_error_classifier_for_retries() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    struct RetryNothing;
+
+    impl ErrorClassifier for RetryNothing {
+        fn classify(&self, _error: &RuntimeError) -> ErrorClass {
+            ErrorClass::Permanent
+        }
+    }
+
+    // An execution failure is retried by default, but not with this classifier
+    let agent_id = AgentId::new();
+    let scheduler = Arc::new(Mutex::new(
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+            .with_tuning(EngineProfile::Latency.tuning())
+            .with_classifier(RetryNothing),
+    ));
+    let runs = Arc::new(AtomicU32::new(0));
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        {
+            let runs = runs.clone();
+            move |_task: &Task| {
+                let runs = runs.clone();
+                async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    Err(RuntimeError::ExecutionFailed("transient".into()))
+                }
+            }
+        },
+        shutdown.clone(),
+    ));
+
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 1).with_max_retries(3))
+        .unwrap();
+    while scheduler.lock().await.dead_letters().is_empty() {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+
+    shutdown.cancel();
+    driver.await.unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    let scheduler = scheduler.lock().await;
+    assert_eq!(scheduler.retried(), 0);
+    let reasons: Vec<_> = scheduler
+        .dead_letters()
+        .iter()
+        .map(|entry| entry.reason)
+        .collect();
+    assert_eq!(reasons, [DeadLetterReason::NotRetryable]);
+}
+
+#[test]
+fn earliest_deadline_first_dispatches_by_deadline() {
+    use std::time::{Duration, Instant};
+
+    let now = Instant::now();
+    let (late, soon, none) = (AgentId::new(), AgentId::new(), AgentId::new());
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::EarliestDeadlineFirst));
+    scheduler.submit(Task::new(none, 1)).unwrap();
+    scheduler
+        .submit(Task::new(late, 1).with_deadline(now + Duration::from_secs(60)))
+        .unwrap();
+    scheduler
+        .submit(Task::new(soon, 1).with_deadline(now + Duration::from_secs(5)))
+        .unwrap();
+
+    let order: Vec<_> = std::iter::from_fn(|| scheduler.next_task())
+        .map(|task| *task.agent_id())
+        .collect();
+    assert_eq!(order, vec![soon, late, none]);
+}
+
+#[tokio::test]
+async fn run_cancels_and_reschedules_tasks_over_max_runtime() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    let scheduler = Arc::new(Mutex::new(Scheduler::new(SchedulingPolicy::new(
+        PolicyType::FCFS,
+    ))));
+    let runs = Arc::new(AtomicU32::new(0));
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        {
+            let runs = runs.clone();
+            move |_: &Task| {
+                let first = runs.fetch_add(1, Ordering::SeqCst) == 0;
+                async move {
+                    if first {
+                        std::future::pending::<()>().await;
+                    }
+                    Ok(())
+                }
+            }
+        },
+        shutdown.clone(),
+    ));
+
+    scheduler
+        .lock()
+        .await
+        .submit(
+            Task::new(AgentId::new(), 1)
+                .with_max_runtime(Duration::from_millis(20), OverrunAction::Reschedule),
+        )
+        .unwrap();
+    while runs.load(Ordering::SeqCst) < 2 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    shutdown.cancel();
+    driver.await.unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn work_stealing_workers_refill_from_injector_and_steal() {
+    let scheduler = WorkStealingScheduler::new(2).with_steal_batch(4);
+    for priority in 0..16 {
+        scheduler.submit(Task::new(AgentId::new(), priority));
+    }
+    let busy = scheduler.take_worker(0).unwrap();
+    let idle = scheduler.take_worker(1).unwrap();
+    assert!(scheduler.take_worker(0).is_none());
+
+    // Refills pull a batch from the injector into the local deque
+    assert_eq!(busy.pop().unwrap().priority(), 0);
+    assert_eq!(busy.len(), 3);
+    assert_eq!(idle.pop().unwrap().priority(), 4);
+    assert_eq!(idle.len(), 3);
+
+    while idle.pop().is_some() {}
+    assert!(busy.len() < 3, "idle worker stole from the busy one");
+    assert!(scheduler.steals() > 0);
+    while busy.pop().is_some() {}
+    assert!(scheduler.is_empty());
+}
+
+#[test]
+fn affinity_pins_keyed_tasks_to_the_first_worker() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    let agent = AgentId::new();
+    for _ in 0..3 {
+        scheduler
+            .submit(Task::new(agent, 1).with_affinity("session-1"))
+            .unwrap();
+    }
+    scheduler.submit(Task::new(agent, 1)).unwrap();
+
+    assert!(scheduler.next_task_on("a").is_some());
+    assert_eq!(scheduler.pinned_worker("session-1"), Some("a"));
+    let other = scheduler.next_task_on("b").unwrap();
+    assert_eq!(other.affinity(), None);
+    assert!(scheduler.next_task_on("b").is_none());
+    assert_eq!(
+        scheduler.next_task_on("a").unwrap().affinity(),
+        Some("session-1")
+    );
+
+    // A lost worker's keys move to the next worker that asks
+    assert_eq!(scheduler.unpin_worker("a"), 1);
+    assert!(scheduler.next_task_on("b").is_some());
+    assert_eq!(scheduler.pinned_worker("session-1"), Some("b"));
+
+    let pool = WorkStealingScheduler::new(4);
+    let worker = pool.worker_for("session-1");
+    pool.submit(Task::new(agent, 1).with_affinity("session-1"));
+    let thief = pool.take_worker((worker + 1) % 4).unwrap();
+    assert!(thief.pop().is_none());
+    assert!(pool.take_worker(worker).unwrap().pop().is_some());
+}
+
+#[test]
+fn anti_affinity_holds_rival_until_it_finishes() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    let (writer, auditor) = (AgentId::new(), AgentId::new());
+    scheduler.set_anti_affinity(writer, auditor);
+    scheduler.submit(Task::new(writer, 1)).unwrap();
+    scheduler.submit(Task::new(auditor, 1)).unwrap();
+
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &writer);
+    assert!(scheduler.next_task().is_none());
+    scheduler.record_outcome(&writer, true);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &auditor);
+}
+
+#[test]
+fn conversation_tasks_run_one_at_a_time_in_order() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::Priority));
+    let agent = AgentId::new();
+    let first = Task::new(agent, 1).with_conversation("chat-1");
+    let second = Task::new(agent, 9).with_conversation("chat-1");
+    let other = Task::new(agent, 5).with_conversation("chat-2");
+    let (first_id, second_id) = (first.id(), second.id());
+    scheduler.submit(first).unwrap();
+    scheduler.submit(second).unwrap();
+    scheduler.submit(other).unwrap();
+    assert_eq!(scheduler.waiting_on_conversations(), 1);
+
+    // The later, higher-priority task waits; other conversations do not
+    let batch = scheduler.next_batch();
+    assert_eq!(batch.len(), 2);
+    assert!(batch.iter().any(|task| task.id() == first_id));
+    assert_eq!(batch[0].affinity(), batch[0].conversation());
+    assert!(scheduler.next_task().is_none());
+
+    scheduler.record_outcome(&agent, true);
+    scheduler.complete_task(first_id, true);
+    assert_eq!(scheduler.conversation_head("chat-1"), Some(second_id));
+    assert_eq!(scheduler.next_task().unwrap().id(), second_id);
+}
+
+#[test]
+fn task_group_dispatches_all_members_or_none() {
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_preemption(3);
+    let (solo, moderator, pro, con) = (
+        AgentId::new(),
+        AgentId::new(),
+        AgentId::new(),
+        AgentId::new(),
+    );
+    scheduler.submit(Task::new(solo, 1)).unwrap();
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &solo);
+
+    // Two of three slots are free: the group and later tasks wait
+    let debate = TaskGroup::new("debate")
+        .with_task(Task::new(moderator, 1))
+        .with_task(Task::new(pro, 1))
+        .with_task(Task::new(con, 1));
+    let members = debate.task_ids();
+    scheduler.submit_group(debate).unwrap();
+    scheduler.submit(Task::new(solo, 1)).unwrap();
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(scheduler.waiting_groups(), 1);
+
+    scheduler.record_outcome(&solo, true);
+    let dispatched: Vec<_> = (0..3)
+        .map(|_| scheduler.next_task().unwrap().id())
+        .collect();
+    assert_eq!(dispatched, members);
+    assert_eq!(scheduler.waiting_groups(), 0);
+
+    // A group with an undispatchable member does not block other tasks
+    for agent in [moderator, pro, con] {
+        scheduler.record_outcome(&agent, true);
+    }
+    scheduler.cordon(con);
+    scheduler
+        .submit_group(
+            TaskGroup::new("rematch")
+                .with_task(Task::new(pro, 1))
+                .with_task(Task::new(con, 1)),
+        )
+        .unwrap();
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &solo);
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(scheduler.queued().count(), 2);
+    scheduler.uncordon(&con);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &pro);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &con);
+}
+
+#[tokio::test]
+async fn speculative_task_keeps_first_success_and_cancels_replicas() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    let (fast, slow, stuck) = (AgentId::new(), AgentId::new(), AgentId::new());
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.add_pool(
+        AgentPool::new("replicas")
+            .with_member(slow)
+            .with_member(fast)
+            .with_member(stuck),
+    );
+    let scheduler = Arc::new(Mutex::new(scheduler));
+    let runs = Arc::new(AtomicU32::new(0));
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        {
+            let runs = runs.clone();
+            move |task: &Task| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                let agent = *task.agent_id();
+                async move {
+                    if agent == fast {
+                        Ok(())
+                    } else {
+                        std::future::pending().await
+                    }
+                }
+            }
+        },
+        shutdown.clone(),
+    ));
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(slow, 1).with_speculation(3))
+        .unwrap();
+
+    let stats = loop {
+        let stats = scheduler.lock().await.speculation_stats();
+        if stats.cancelled == 2 {
+            break stats;
+        }
+        tokio::task::yield_now().await;
+    };
+    assert_eq!((stats.launched, stats.replicas, stats.discarded), (1, 2, 0));
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    let scheduler_ref = scheduler.lock().await;
+    assert_eq!(scheduler_ref.in_flight_count(), 0);
+    assert!(scheduler_ref.dead_letters().is_empty());
+    drop(scheduler_ref);
+    shutdown.cancel();
+    driver.await.unwrap();
+}
+
+#[tokio::test]
+async fn task_handles_resolve_to_results_and_cache_evicts_unawaited() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    let clock = Arc::new(VirtualClock::new());
+    let (ok, failing) = (AgentId::new(), AgentId::new());
+    let scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_result_cache(8, Duration::from_secs(10))
+        .with_clock(clock.clone())
+        .with_tuning(EngineProfile::Latency.tuning());
+    let scheduler = Arc::new(Mutex::new(scheduler));
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run_with_results(
+        scheduler.clone(),
+        move |task: &Task| {
+            let agent = *task.agent_id();
+            async move {
+                if agent == ok {
+                    Ok(b"done".to_vec())
+                } else {
+                    Err(RuntimeError::ExecutionFailed("boom".into()))
+                }
+            }
+        },
+        shutdown.clone(),
+    ));
+
+    let (done, failed, forgotten) = {
+        let mut scheduler = scheduler.lock().await;
+        (
+            scheduler.submit(Task::new(ok, 1)).unwrap(),
+            scheduler.submit(Task::new(failing, 1)).unwrap(),
+            scheduler.submit(Task::new(ok, 1)).unwrap().id(),
+        )
+    };
+    assert_eq!(done.await.unwrap(), b"done");
+    let failure = failed.await.unwrap_err();
+    assert_eq!(failure.code(), "execution_failed");
+
+    // Fire-and-forget results stay reachable by ID until they expire
+    let handle = scheduler.lock().await.task_handle(forgotten);
+    assert_eq!(handle.await.unwrap(), b"done");
+    let scheduler_ref = scheduler.lock().await;
+    assert_eq!(
+        scheduler_ref.task_result(forgotten),
+        Some(Ok(b"done".to_vec()))
+    );
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(scheduler_ref.task_result(forgotten), None);
+    assert_eq!(scheduler_ref.cached_results(), 0);
+    assert_eq!(scheduler_ref.evicted_results(), 3);
+    drop(scheduler_ref);
+    shutdown.cancel();
+    driver.await.unwrap();
+}
+
+#[test]
+fn work_stealing_drains_across_threads() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let scheduler = Arc::new(WorkStealingScheduler::new(4));
+    for _ in 0..1000 {
+        scheduler.submit(Task::new(AgentId::new(), 1));
+    }
+    let done = Arc::new(AtomicUsize::new(0));
+    let threads: Vec<_> = (0..scheduler.workers())
+        .map(|index| {
+            let queue = scheduler.take_worker(index).unwrap();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                while queue.pop().is_some() {
+                    done.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(done.load(Ordering::Relaxed), 1000);
+}
+
+#[cfg(feature = "persistence")]
+#[test]
+fn persistent_queue_recovers_unacknowledged_tasks() {
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join(format!("agentropic-wal-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let agents: Vec<_> = (0..3).map(|_| AgentId::new()).collect();
+
+    let mut queue = PersistentTaskQueue::open(FileQueueStore::open(&path).unwrap()).unwrap();
+    for agent_id in &agents {
+        queue.push(Task::new(*agent_id, 1)).unwrap();
+    }
+    let (first, _) = queue.pop().unwrap();
+    queue.ack(first).unwrap();
+    queue.pop().unwrap();
+    drop(queue);
+
+    // A crash mid-write leaves a torn last line
+    let mut log = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    log.write_all(b"{\"op\":\"push\",\"id\":9").unwrap();
+    drop(log);
+
+    let mut queue = PersistentTaskQueue::open(FileQueueStore::open(&path).unwrap()).unwrap();
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop().unwrap().1.agent_id(), &agents[1]);
+    assert_eq!(queue.pop().unwrap().1.agent_id(), &agents[2]);
+    let id = queue.push(Task::new(agents[0], 1)).unwrap();
+    assert_eq!(id, 4);
+    drop(queue);
+
+    // Damage before the last line is corruption, not a torn write
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, format!("{{\"op\":\"pu\n{}", log)).unwrap();
+    let error = FileQueueStore::open(&path).unwrap().recover().unwrap_err();
+    assert_eq!(error.code(), "queue.storage");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn cron_expressions_find_next_ticks() {
+    use std::time::{Duration, SystemTime};
+
+    // 2024-01-01T00:00:00Z was a Monday
+    let monday = SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+    let minutes = |m: u64| monday + Duration::from_secs(m * 60);
+
+    let every_five = CronExpr::parse("*/5 * * * *").unwrap();
+    assert_eq!(every_five.next_after(monday), Some(minutes(5)));
+    assert_eq!(every_five.next_after(minutes(7)), Some(minutes(10)));
+
+    let weekday_mornings = CronExpr::parse("30 9 * * tue-fri").unwrap();
+    assert_eq!(
+        weekday_mornings.next_after(monday),
+        Some(minutes(24 * 60 + 9 * 60 + 30))
+    );
+
+    assert!(CronExpr::parse("61 * * * *").is_err());
+    assert!(CronExpr::parse("* * *").is_err());
+}
+
+#[test]
+fn cron_schedules_fire_ticks_and_catch_up() {
+    use std::time::{Duration, SystemTime};
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.add_schedule(
+        RecurringSchedule::cron(
+            "every-five",
+            &Task::new(agent_id, 0),
+            CronExpr::parse("*/5 * * * *").unwrap(),
+            start,
+        )
+        .with_misfire_policy(MisfirePolicy::FireAllMissed),
+    );
+
+    assert_eq!(scheduler.fire_due(start), 0);
+    let tick = start + Duration::from_secs(5 * 60);
+    assert_eq!(scheduler.fire_due(tick), 1);
+
+    // Restored after 20 minutes of downtime: four ticks were missed
+    let json = serde_json::to_string(&scheduler.state()).unwrap();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    scheduler.warm_start(serde_json::from_str(&json).unwrap());
+    let later = tick + Duration::from_secs(20 * 60 + 30);
+    assert_eq!(scheduler.fire_due(later), 4);
+    assert_eq!(scheduler.queue().len(), 5);
+    assert_eq!(
+        scheduler.schedules()[0].next_fire(),
+        Some(tick + Duration::from_secs(25 * 60))
+    );
+}
+
+#[tokio::test]
+async fn failed_tasks_are_dead_lettered_and_replayed() {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    let (missing, flaky) = (AgentId::new(), AgentId::new());
+    let scheduler = Arc::new(Mutex::new(Scheduler::new(SchedulingPolicy::new(
+        PolicyType::FCFS,
+    ))));
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        move |task: &Task| {
+            let error = if task.agent_id() == &missing {
+                RuntimeError::AgentNotFound(missing.to_string())
+            } else {
+                RuntimeError::ExecutionFailed("flaky".into())
+            };
+            async move { Err(error) }
+        },
+        shutdown.clone(),
+    ));
+
+    {
+        let mut scheduler = scheduler.lock().await;
+        scheduler.submit(Task::new(missing, 1)).unwrap();
+        scheduler
+            .submit(Task::new(flaky, 1).with_max_retries(1))
+            .unwrap();
+    }
+    while scheduler.lock().await.dead_letters().len() < 2 {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    shutdown.cancel();
+    driver.await.unwrap();
+
+    let mut scheduler = scheduler.lock().await;
+    let mut reasons: Vec<_> = scheduler
+        .dead_letters()
+        .iter()
+        .map(|entry| (entry.reason, entry.task.attempts()))
+        .collect();
+    reasons.sort_by_key(|(_, attempts)| *attempts);
+    assert_eq!(
+        reasons,
+        vec![
+            (DeadLetterReason::AgentNotFound, 1),
+            (DeadLetterReason::RetriesExhausted, 2)
+        ]
+    );
+
+    let id = scheduler.dead_letters().iter().next().unwrap().id;
+    assert!(scheduler.replay_dead_letter(id).unwrap());
+    assert!(!scheduler.replay_dead_letter(id).unwrap());
+    assert_eq!(scheduler.next_task().unwrap().attempts(), 0);
+    assert_eq!(scheduler.dead_letters_mut().purge(), 1);
+}
+
+#[test]
+fn resource_quota_limits_concurrent_tasks() {
+    use std::time::Duration;
+
+    let agent_id = AgentId::new();
+    let quota = ResourceQuota::new()
+        .with_max_concurrent(1)
+        .with_cpu_time(Duration::from_secs(1), Duration::from_secs(60));
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_resource_quota(agent_id, quota);
+    for _ in 0..3 {
+        scheduler.submit(Task::new(agent_id, 1)).unwrap();
+    }
+
+    assert!(scheduler.next_task().is_some());
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(
+        quota.check(&scheduler.resource_usage(&agent_id)),
+        Some(QuotaViolation::Concurrency {
+            running: 1,
+            limit: 1
+        })
+    );
+
+    scheduler.record_runtime(agent_id, Duration::from_millis(400));
+    scheduler.record_outcome(&agent_id, true);
+    assert!(scheduler.next_task().is_some());
+    scheduler.record_runtime(agent_id, Duration::from_millis(600));
+    scheduler.record_outcome(&agent_id, true);
+    assert!(scheduler.next_task().is_none());
+    assert!(matches!(
+        quota.check(&scheduler.resource_usage(&agent_id)),
+        Some(QuotaViolation::CpuTime { .. })
+    ));
+}
+
+#[test]
+fn clearing_resource_quota_keeps_separate_runtime_cap() {
+    use std::time::Duration;
+
+    let agent_id = AgentId::new();
+    let quota = ResourceQuota::new().with_cpu_time(Duration::from_secs(1), Duration::from_secs(60));
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_resource_quota(agent_id, quota);
+    scheduler.record_runtime(agent_id, Duration::from_secs(1));
+    scheduler.submit(Task::new(agent_id, 1)).unwrap();
+    assert!(scheduler.next_task().is_none());
+
+    // The cap the quota installed goes with it
+    scheduler.clear_resource_quota(&agent_id);
+    assert!(scheduler.next_task().is_some());
+
+    // A cap set after the quota outlives it
+    scheduler.set_resource_quota(agent_id, quota);
+    scheduler.set_runtime_cap(
+        agent_id,
+        RuntimeCap::new(Duration::from_millis(500), Duration::from_secs(60)),
+    );
+    scheduler.submit(Task::new(agent_id, 1)).unwrap();
+    scheduler.clear_resource_quota(&agent_id);
+    assert!(scheduler.next_task().is_none());
+}
+
+#[test]
+fn scheduler_snapshot_restores_queue_quotas_and_dead_letters() {
+    let agent_id = AgentId::new();
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::Priority)).with_dead_letter_capacity(8);
+    scheduler.set_resource_quota(agent_id, ResourceQuota::new().with_max_concurrent(1));
+    scheduler
+        .submit(Task::new(agent_id, 3).with_payload(b"queued".to_vec()))
+        .unwrap();
+    scheduler.dead_letters_mut().push(
+        Task::new(agent_id, 1),
+        DeadLetterReason::NotRetryable,
+        None,
+        std::time::SystemTime::now(),
+    );
+
+    let json = serde_json::to_string(&scheduler.snapshot()).unwrap();
+    let snapshot: SchedulerSnapshot = serde_json::from_str(&json).unwrap();
+    let mut restored = Scheduler::restore_from(snapshot);
+
+    assert_eq!(
+        restored.resource_quota(&agent_id).unwrap().max_concurrent(),
+        Some(1)
+    );
+    assert_eq!(restored.dead_letters().capacity(), 8);
+    let id = restored.dead_letters_mut().push(
+        Task::new(agent_id, 1),
+        DeadLetterReason::Undeliverable,
+        None,
+        std::time::SystemTime::now(),
+    );
+    assert_eq!(id, 1);
+    assert_eq!(restored.next_task().unwrap().payload(), b"queued");
+}
+
+#[test]
+fn scheduler_snapshot_restores_limits_health_deployments_and_parked_work() {
+    use agentropic_runtime::scheduler::Version;
+    use std::time::Duration;
+
+    let (parked, limited) = (AgentId::new(), AgentId::new());
+    let (cordoned, candidate) = (AgentId::new(), AgentId::new());
+    let tuning = EngineProfile::Throughput.tuning();
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::Priority)).with_tuning(tuning);
+    scheduler.set_runtime_cap(
+        limited,
+        RuntimeCap::new(Duration::from_secs(1), Duration::from_secs(60)).reject(),
+    );
+    scheduler.record_runtime(limited, Duration::from_secs(2));
+    scheduler.set_cost_budget(limited, CostBudget::new(10, Duration::from_secs(60)));
+    scheduler.record_cost(limited, 10);
+    scheduler.add_provider("llm", ProviderLimits::new().with_requests_per_minute(5));
+    scheduler.set_agent_health(cordoned, HealthStatus::Healthy);
+    scheduler.cordon(cordoned);
+    scheduler.deploy(Deployment::new(cordoned, candidate, 0.25));
+    scheduler.set_agent_version(candidate, Version::new(2, 0, 0));
+    scheduler
+        .submit(Task::new(parked, 1).with_payload(b"parked".to_vec()))
+        .unwrap();
+    scheduler.park(parked);
+    scheduler
+        .submit_group(TaskGroup::new("debate").with_task(Task::new(cordoned, 1)))
+        .unwrap();
+
+    let json = serde_json::to_string(&scheduler.snapshot()).unwrap();
+    let mut restored = Scheduler::restore_from(serde_json::from_str(&json).unwrap());
+
+    assert_eq!(restored.tuning(), &tuning);
+    assert!(restored.agent_stats(&limited).capped);
+    assert!(restored.is_budget_exhausted(&limited));
+    assert!(restored.provider_usage("llm").is_some());
+    assert!(restored.is_cordoned(&cordoned));
+    assert_eq!(restored.deployment(&cordoned).unwrap().fraction(), 0.25);
+    assert_eq!(
+        restored.agent_version(&candidate),
+        Some(&Version::new(2, 0, 0))
+    );
+    assert!(restored.is_parked(&parked));
+    assert_eq!(restored.parked(&parked), 1);
+    assert_eq!(restored.waiting_groups(), 1);
+    assert!(restored.next_task().is_none());
+
+    restored.uncordon(&cordoned);
+    assert_eq!(restored.next_task().unwrap().agent_id(), &cordoned);
+    assert_eq!(restored.unpark(&parked), 1);
+    assert_eq!(restored.next_task().unwrap().payload(), b"parked");
+}
+
+/// Records the name and fields of every span created
+#[cfg(feature = "tracing")]
+#[derive(Clone, Default)]
+struct SpanRecorder(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+#[cfg(feature = "tracing")]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanRecorder {
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        _id: &tracing::span::Id,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        struct Fields(String);
+        impl tracing::field::Visit for Fields {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0.push_str(&format!(" {}={:?}", field.name(), value));
+            }
+        }
+        let mut fields = Fields(attrs.metadata().name().to_string());
+        attrs.record(&mut fields);
+        self.0.lock().unwrap().push(fields.0);
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn task_spans_carry_agent_priority_and_attempt() {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let recorder = SpanRecorder::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    let agent_id = AgentId::new();
+    let scheduler = Arc::new(Mutex::new(Scheduler::new(SchedulingPolicy::new(
+        PolicyType::Priority,
+    ))));
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 4))
+        .unwrap();
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        |_: &Task| async { Ok(()) },
+        shutdown.clone(),
+    ));
+    while recorder.0.lock().unwrap().len() < 2 {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    shutdown.cancel();
+    driver.await.unwrap();
+
+    let spans = recorder.0.lock().unwrap().clone();
+    let fields = format!("agent_id={} priority=4 attempt=1 policy=Priority", agent_id);
+    assert!(spans.contains(&format!("task.dispatch {}", fields)));
+    assert!(spans.contains(&format!("task.run {}", fields)));
+}
+
+#[tokio::test]
+async fn urgent_task_preempts_running_low_priority_task() {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    let agent_id = AgentId::new();
+    let tuning = EngineTuning {
+        time_slice: std::time::Duration::ZERO,
+        ..EngineProfile::Latency.tuning()
+    };
+    let scheduler = Arc::new(Mutex::new(
+        Scheduler::new(SchedulingPolicy::new(PolicyType::Priority))
+            .with_tuning(tuning)
+            .with_preemption(1),
+    ));
+    let runs = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = runs.clone();
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        move |task: &Task| {
+            log.lock()
+                .unwrap()
+                .push((task.priority(), task.payload().to_vec()));
+            let handle = task.preemption().cloned();
+            let yields = task.payload() == b"all";
+            async move {
+                if let (Some(handle), true) = (handle, yields) {
+                    handle.preempted().await;
+                    handle.save_remaining(b"rest".to_vec());
+                    return Err(SchedulerError::Preempted.into());
+                }
+                Ok(())
+            }
+        },
+        shutdown.clone(),
+    ));
+
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 1).with_payload(b"all".to_vec()))
+        .unwrap();
+    while runs.lock().unwrap().is_empty() {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 9).with_payload(b"urgent".to_vec()))
+        .unwrap();
+    while runs.lock().unwrap().len() < 3 {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    shutdown.cancel();
+    driver.await.unwrap();
+
+    assert_eq!(
+        *runs.lock().unwrap(),
+        vec![
+            (1, b"all".to_vec()),
+            (9, b"urgent".to_vec()),
+            (1, b"rest".to_vec())
+        ]
+    );
+    assert_eq!(scheduler.lock().await.preemptions(), 1);
+}
+
+#[test]
+fn mlfq_demotes_cpu_bound_tasks_and_boosts_periodically() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let mut mlfq = MlfqScheduler::new(3)
+        .with_quantum(0, Duration::from_millis(10))
+        .with_quantum(1, Duration::from_millis(40))
+        .with_boost_interval(Duration::from_secs(1))
+        .with_clock(clock.clone());
+    let (batch, interactive) = (AgentId::new(), AgentId::new());
+    mlfq.submit(Task::new(batch, 1));
+
+    let dispatch = mlfq.next_task().unwrap();
+    assert_eq!(
+        (dispatch.level, dispatch.quantum),
+        (0, Duration::from_millis(10))
+    );
+    mlfq.requeue(dispatch, Duration::from_millis(10));
+    assert_eq!(mlfq.level_len(1), 1);
+
+    mlfq.submit(Task::new(interactive, 1));
+    let dispatch = mlfq.next_task().unwrap();
+    assert_eq!(dispatch.task.agent_id(), &interactive);
+    mlfq.requeue(dispatch, Duration::from_millis(2));
+    let dispatch = mlfq.next_task().unwrap();
+    assert_eq!(
+        (dispatch.level, dispatch.quantum),
+        (0, Duration::from_millis(8))
+    );
+    drop(dispatch);
+
+    clock.advance(Duration::from_secs(1));
+    let dispatch = mlfq.next_task().unwrap();
+    assert_eq!((dispatch.task.agent_id(), dispatch.level), (&batch, 0));
+    assert_eq!((mlfq.boosts(), mlfq.demotions()), (1, 1));
+}
+
+#[test]
+fn dependent_tasks_wait_for_predecessors() {
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    let fetch = Task::new(agent_id, 1);
+    let parse = Task::new(agent_id, 1).after(&[fetch.id()]);
+    let report = Task::new(agent_id, 1).after(&[fetch.id(), parse.id()]);
+    let (fetch_id, parse_id, report_id) = (fetch.id(), parse.id(), report.id());
+    for task in [fetch, parse, report] {
+        scheduler.submit(task).unwrap();
+    }
+    assert_eq!(scheduler.waiting_on_dependencies(), 2);
+
+    assert_eq!(scheduler.next_task().unwrap().id(), fetch_id);
+    assert!(scheduler.next_task().is_none());
+    scheduler.complete_task(fetch_id, true);
+    assert_eq!(scheduler.next_task().unwrap().id(), parse_id);
+    assert!(scheduler.next_task().is_none());
+    scheduler.complete_task(parse_id, true);
+    assert_eq!(scheduler.next_task().unwrap().id(), report_id);
+    assert_eq!(scheduler.waiting_on_dependencies(), 0);
+
+    // A failed predecessor dead-letters its dependents
+    let upload = Task::new(agent_id, 1);
+    let notify = Task::new(agent_id, 1).after(&[upload.id()]);
+    let upload_id = upload.id();
+    scheduler.submit(upload).unwrap();
+    scheduler.submit(notify).unwrap();
+    scheduler.next_task().unwrap();
+    scheduler.complete_task(upload_id, false);
+    let dead: Vec<_> = scheduler.dead_letters().iter().collect();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(dead[0].reason, DeadLetterReason::DependencyFailed);
+}
+
+#[test]
+fn late_dependents_wait_for_running_predecessors() {
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    let fetch = Task::new(agent_id, 1);
+    let fetch_id = fetch.id();
+    scheduler.submit(fetch).unwrap();
+    assert_eq!(scheduler.next_task().unwrap().id(), fetch_id);
+
+    // Submitted after its predecessor was dispatched, before it completed
+    let parse = Task::new(agent_id, 1).after(&[fetch_id]);
+    let parse_id = parse.id();
+    scheduler.submit(parse).unwrap();
+    assert_eq!(scheduler.waiting_on_dependencies(), 1);
+    assert!(scheduler.next_task().is_none());
+    scheduler.complete_task(fetch_id, true);
+    assert_eq!(scheduler.next_task().unwrap().id(), parse_id);
+}
+
+#[test]
+fn dependents_of_failed_predecessors_are_dead_lettered() {
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+
+    // A predecessor whose failure is in the result store
+    let upload = Task::new(agent_id, 1);
+    let upload_id = upload.id();
+    scheduler.submit(upload).unwrap();
+    scheduler.next_task().unwrap();
+    let error = RuntimeError::ExecutionFailed("upload failed".into());
+    scheduler.store_result(upload_id, Err(TaskFailure::from(&error)));
+    scheduler.complete_task(upload_id, false);
+    let notify = Task::new(agent_id, 1).after(&[upload_id]);
+    let notify_id = notify.id();
+    scheduler.submit(notify).unwrap();
+    assert!(scheduler.next_task().is_none());
+    assert!(matches!(scheduler.task_result(notify_id), Some(Err(_))));
+
+    // A predecessor in the dead-letter queue
+    let fetch = Task::new(agent_id, 1);
+    let fetch_id = fetch.id();
+    scheduler.dead_letters_mut().push(
+        fetch,
+        DeadLetterReason::NotRetryable,
+        None,
+        std::time::SystemTime::now(),
+    );
+    scheduler
+        .submit(Task::new(agent_id, 1).after(&[fetch_id]))
+        .unwrap();
+    assert!(scheduler.next_task().is_none());
+    let reasons: Vec<_> = scheduler
+        .dead_letters()
+        .iter()
+        .map(|entry| entry.reason)
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            DeadLetterReason::DependencyFailed,
+            DeadLetterReason::NotRetryable,
+            DeadLetterReason::DependencyFailed
+        ]
+    );
+}
+
+#[test]
+fn task_depending_on_itself_is_rejected() {
+    let task = Task::new(AgentId::new(), 1);
+    let id = task.id();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+
+    let error = scheduler.submit(task.after(&[id])).unwrap_err();
+
+    assert_eq!(error.code(), "scheduler.dependency_cycle");
+    assert_eq!(scheduler.queued().count(), 0);
+}
+
+#[cfg(feature = "cluster")]
+#[tokio::test]
+async fn cluster_reassigns_tasks_of_lost_worker() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    let done = Arc::new(AtomicUsize::new(0));
+    let counter = |done: Arc<AtomicUsize>| {
+        InProcessTransport::new(move |_task| {
+            let done = done.clone();
+            async move {
+                done.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+    };
+    let lost = counter(done.clone());
+    lost.disconnect();
+    let cluster = Arc::new(
+        Cluster::new()
+            .with_heartbeat_interval(Duration::from_millis(10))
+            .with_heartbeat_timeout(Duration::from_millis(40)),
+    );
+    cluster.join(WorkerNode::new("a", 1, Arc::new(lost)));
+    cluster.join(WorkerNode::new("b", 1, Arc::new(counter(done.clone()))));
+
+    let scheduler = Arc::new(tokio::sync::Mutex::new(Scheduler::new(
+        SchedulingPolicy::new(PolicyType::FCFS),
+    )));
+    for _ in 0..3 {
+        scheduler
+            .lock()
+            .await
+            .submit(Task::new(AgentId::new(), 1))
+            .unwrap();
+    }
+    let shutdown = CancellationToken::new();
+    let run = tokio::spawn({
+        let (cluster, scheduler, shutdown) = (cluster.clone(), scheduler.clone(), shutdown.clone());
+        async move { cluster.run(scheduler, shutdown).await }
+    });
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while done.load(Ordering::SeqCst) < 3 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+    shutdown.cancel();
+    run.await.unwrap();
+
+    let workers = cluster.workers();
+    assert_eq!(workers.len(), 1);
+    assert_eq!(workers[0].id, "b");
+    assert_eq!(cluster.reassigned(), 1);
+    assert!(scheduler.lock().await.dead_letters().is_empty());
+}
+
+#[cfg(feature = "cluster")]
+#[tokio::test]
+async fn cordoned_cluster_workers_take_no_new_tasks() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    let ran = Arc::new(Mutex::new(Vec::new()));
+    let worker = |name: &'static str| {
+        let ran = ran.clone();
+        InProcessTransport::new(move |_task| {
+            ran.lock().unwrap().push(name);
+            async { Ok(()) }
+        })
+    };
+    let cluster = Arc::new(Cluster::new().with_heartbeat_interval(Duration::from_millis(10)));
+    cluster.join(WorkerNode::new("a", 4, Arc::new(worker("a"))));
+    cluster.join(WorkerNode::new("b", 1, Arc::new(worker("b"))));
+    cluster.cordon("a");
+    assert_eq!(cluster.free_capacity(), 1);
+
+    let scheduler = Arc::new(tokio::sync::Mutex::new(Scheduler::new(
+        SchedulingPolicy::new(PolicyType::FCFS),
+    )));
+    for _ in 0..3 {
+        scheduler
+            .lock()
+            .await
+            .submit(Task::new(AgentId::new(), 1))
+            .unwrap();
+    }
+    let shutdown = CancellationToken::new();
+    let run = tokio::spawn({
+        let (cluster, scheduler, shutdown) = (cluster.clone(), scheduler.clone(), shutdown.clone());
+        async move { cluster.run(scheduler, shutdown).await }
+    });
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while ran.lock().unwrap().len() < 3 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(*ran.lock().unwrap(), vec!["b", "b", "b"]);
+    let workers = cluster.workers();
+    assert!(workers[0].cordoned && !workers[1].cordoned);
+
+    // Once uncordoned, the node takes tasks again
+    cluster.uncordon("a");
+    assert!(!cluster.is_cordoned("a"));
+    for _ in 0..4 {
+        scheduler
+            .lock()
+            .await
+            .submit(Task::new(AgentId::new(), 1))
+            .unwrap();
+    }
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !ran.lock().unwrap().contains(&"a") {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+    shutdown.cancel();
+    run.await.unwrap();
+}
+
+#[cfg(feature = "cluster")]
+#[tokio::test]
+async fn tcp_worker_runs_tasks_and_reports_failures() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    let server = WorkerServer::new(|task: Task| async move {
+        match task.payload() {
+            b"ok" => Ok(()),
+            _ => Err(RuntimeError::ExecutionFailed("bad payload".into())),
+        }
+    })
+    .with_token("secret")
+    .with_max_frame(1024);
+    let serving = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { server.serve(listener, shutdown).await }
+    });
+
+    let transport = TcpTransport::new(address.clone()).with_token("secret");
+    let agent_id = AgentId::new();
+    transport.heartbeat().await.unwrap();
+    transport
+        .execute(TaskRecord::new(
+            &Task::new(agent_id, 1).with_payload(b"ok".to_vec()),
+        ))
+        .await
+        .unwrap();
+    let error = transport
+        .execute(TaskRecord::new(
+            &Task::new(agent_id, 1).with_payload(b"no".to_vec()),
+        ))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("bad payload"));
+
+    // Requests without the token and oversized frames are refused
+    let intruder = TcpTransport::new(address.clone()).with_token("guess");
+    let error = intruder.heartbeat().await.unwrap_err();
+    assert_eq!(error.code(), "cluster.unauthorized");
+    let error = transport
+        .execute(TaskRecord::new(
+            &Task::new(agent_id, 1).with_payload(vec![0; 4096]),
+        ))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("exceeds limit of 1024"));
+
+    shutdown.cancel();
+    serving.await.unwrap();
+}
+
+#[cfg(feature = "cluster")]
+#[tokio::test]
+async fn tcp_worker_stops_tasks_its_coordinator_gave_up_on() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    let finished = Arc::new(AtomicUsize::new(0));
+    let server = WorkerServer::new({
+        let finished = finished.clone();
+        move |_task: Task| {
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+    })
+    .with_fence_timeout(Some(Duration::from_millis(100)));
+    let serving = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { server.serve(listener, shutdown).await }
+    });
+    let transport = TcpTransport::new(address);
+    let record = || TaskRecord::new(&Task::new(AgentId::new(), 1));
+
+    // The coordinator hangs up
+    let abandoned = tokio::time::timeout(Duration::from_millis(50), transport.execute(record()));
+    assert!(abandoned.await.is_err());
+    // The coordinator stops sending heartbeats
+    transport.heartbeat().await.unwrap();
+    tokio::spawn({
+        let transport = transport.clone();
+        async move { transport.execute(record()).await }
+    });
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(finished.load(Ordering::SeqCst), 0);
+
+    shutdown.cancel();
+    serving.await.unwrap();
+}
+
+#[cfg(feature = "quic")]
+#[tokio::test]
+async fn quic_worker_accepts_only_coordinators_with_cluster_certificates() {
+    use agentropic_runtime::cluster::quic::{
+        client_config, server_config, CertificateDer, PrivateKeyDer,
+    };
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+
+    let authority = || {
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap()
+    };
+    let issue = |ca: &CertifiedIssuer<'static, KeyPair>, name: &str| {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .signed_by(&key, ca)
+            .unwrap();
+        (
+            vec![cert.der().clone()],
+            PrivateKeyDer::Pkcs8(key.serialize_der().into()),
+        )
+    };
+    let ca = authority();
+    let roots: Vec<CertificateDer<'static>> = vec![ca.der().clone()];
+
+    let (chain, key) = issue(&ca, "worker.cluster");
+    let config = server_config(chain, key, &roots).unwrap();
+    let server_endpoint = quinn::Endpoint::server(config, "127.0.0.1:0".parse().unwrap()).unwrap();
+    let address = server_endpoint.local_addr().unwrap();
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    let server = WorkerServer::new(|task: Task| async move {
+        match task.payload() {
+            b"ok" => Ok(()),
+            _ => Err(RuntimeError::ExecutionFailed("bad payload".into())),
+        }
+    });
+    let serving = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { server.serve_quic(server_endpoint, shutdown).await }
+    });
+
+    let endpoint = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+    let (chain, key) = issue(&ca, "coordinator.cluster");
+    let config = client_config(&roots, chain, key).unwrap();
+    let transport = QuicTransport::new(endpoint.clone(), config, address, "worker.cluster");
+    transport.heartbeat().await.unwrap();
+    transport
+        .execute(TaskRecord::new(
+            &Task::new(AgentId::new(), 1).with_payload(b"ok".to_vec()),
+        ))
+        .await
+        .unwrap();
+    let error = transport
+        .execute(TaskRecord::new(&Task::new(AgentId::new(), 1)))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("bad payload"));
+
+    // A coordinator with a certificate from another CA is turned away
+    let (chain, key) = issue(&authority(), "intruder.cluster");
+    let config = client_config(&roots, chain, key).unwrap();
+    let intruder = QuicTransport::new(endpoint, config, address, "worker.cluster");
+    let error = intruder
+        .execute(TaskRecord::new(
+            &Task::new(AgentId::new(), 1).with_payload(b"ok".to_vec()),
+        ))
+        .await
+        .unwrap_err();
+    assert!(error.code().starts_with("cluster."));
+
+    shutdown.cancel();
+    serving.await.unwrap();
+}
+
+#[cfg(feature = "cluster")]
+#[tokio::test]
+async fn slow_heartbeats_do_not_hold_up_dispatch() {
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    struct Unresponsive;
+
+    #[async_trait]
+    impl WorkerTransport for Unresponsive {
+        async fn execute(&self, _task: TaskRecord) -> Result<(), RuntimeError> {
+            std::future::pending().await
+        }
+
+        async fn heartbeat(&self) -> Result<(), RuntimeError> {
+            std::future::pending().await
+        }
+    }
+
+    let done = Arc::new(AtomicUsize::new(0));
+    let fast = InProcessTransport::new({
+        let done = done.clone();
+        move |_task| {
+            let done = done.clone();
+            async move {
+                done.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+    });
+    let cluster = Arc::new(
+        Cluster::new()
+            .with_heartbeat_interval(Duration::from_secs(1))
+            .with_heartbeat_timeout(Duration::from_secs(60)),
+    );
+    cluster.join(WorkerNode::new("fast", 4, Arc::new(fast)));
+    cluster.join(WorkerNode::new("slow", 1, Arc::new(Unresponsive)));
+
+    let scheduler = Arc::new(tokio::sync::Mutex::new(Scheduler::new(
+        SchedulingPolicy::new(PolicyType::FCFS),
+    )));
+    let shutdown = CancellationToken::new();
+    let run = tokio::spawn({
+        let (cluster, scheduler, shutdown) = (cluster.clone(), scheduler.clone(), shutdown.clone());
+        async move { cluster.run(scheduler, shutdown).await }
+    });
+
+    // Submit while the slow worker's heartbeat is outstanding
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    for _ in 0..3 {
+        scheduler
+            .lock()
+            .await
+            .submit(Task::new(AgentId::new(), 1))
+            .unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(done.load(Ordering::SeqCst) >= 2);
+
+    shutdown.cancel();
+    run.await.unwrap();
+}
+
+#[cfg(feature = "cluster")]
+#[tokio::test]
+async fn standby_takes_over_queue_when_leader_misses_renewals() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    let clock = Arc::new(VirtualClock::new());
+    let store: Arc<dyn LeaseStore> = Arc::new(InMemoryLeaseStore::new());
+    let candidate = |id: &str| {
+        LeaderElection::new(id, store.clone())
+            .with_lease_ttl(Duration::from_secs(3))
+            .with_clock(clock.clone())
+    };
+    let (primary, standby) = (candidate("primary"), candidate("standby"));
+    let scheduler = || Mutex::new(Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)));
+    let (primary_queue, standby_queue) = (scheduler(), scheduler());
+    for _ in 0..2 {
+        primary_queue
+            .lock()
+            .await
+            .submit(Task::new(AgentId::new(), 1))
+            .unwrap();
+    }
+
+    assert_eq!(
+        primary.step(&primary_queue).await,
+        ElectionRole::Leader { term: 1 }
+    );
+    assert_eq!(
+        standby.step(&standby_queue).await,
+        ElectionRole::Standby {
+            leader: Some("primary".into())
+        }
+    );
+    assert_eq!(standby_queue.lock().await.queued().count(), 0);
+
+    // The primary stalls past its lease
+    clock.advance(Duration::from_secs(4));
+    assert_eq!(
+        standby.step(&standby_queue).await,
+        ElectionRole::Leader { term: 2 }
+    );
+    assert_eq!(standby_queue.lock().await.queued().count(), 2);
+
+    assert!(!primary.step(&primary_queue).await.is_leader());
+    assert_eq!(primary_queue.lock().await.queued().count(), 0);
+    let error = store
+        .save_state(1, SchedulerState::default())
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "scheduler.not_leader");
+}
+
+#[cfg(feature = "cluster")]
+#[tokio::test]
+async fn file_lease_store_hands_over_between_separate_stores() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    let dir = std::env::temp_dir().join(format!("agentropic-lease-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let clock = Arc::new(VirtualClock::new());
+    // Each candidate opens the directory itself, as separate processes would
+    let candidate = |id: &str| {
+        let store: Arc<dyn LeaseStore> = Arc::new(FileLeaseStore::new(&dir).unwrap());
+        LeaderElection::new(id, store)
+            .with_lease_ttl(Duration::from_secs(3))
+            .with_clock(clock.clone())
+    };
+    let (primary, standby) = (candidate("primary"), candidate("standby"));
+    let scheduler = || Mutex::new(Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)));
+    let (primary_queue, standby_queue) = (scheduler(), scheduler());
+    primary_queue
+        .lock()
+        .await
+        .submit(Task::new(AgentId::new(), 1))
+        .unwrap();
+
+    assert_eq!(
+        primary.step(&primary_queue).await,
+        ElectionRole::Leader { term: 1 }
+    );
+    assert!(!standby.step(&standby_queue).await.is_leader());
+
+    clock.advance(Duration::from_secs(4));
+    assert_eq!(
+        standby.step(&standby_queue).await,
+        ElectionRole::Leader { term: 2 }
+    );
+    assert_eq!(standby_queue.lock().await.queued().count(), 1);
+
+    let error = FileLeaseStore::new(&dir)
+        .unwrap()
+        .save_state(1, SchedulerState::default())
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "scheduler.not_leader");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn bounded_queue_applies_backpressure_to_producers() {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    let agent_id = AgentId::new();
+    let scheduler = Arc::new(Mutex::new(
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+            .with_queue_capacity(1, OverflowPolicy::Block),
+    ));
+    Scheduler::submit_async(&scheduler, Task::new(agent_id, 1))
+        .await
+        .unwrap();
+    let error = scheduler
+        .lock()
+        .await
+        .submit(Task::new(agent_id, 1))
+        .unwrap_err();
+    assert_eq!(error.code(), "queue.full");
+
+    let producer = tokio::spawn({
+        let scheduler = scheduler.clone();
+        async move { Scheduler::submit_async(&scheduler, Task::new(agent_id, 2)).await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!producer.is_finished());
+    assert_eq!(scheduler.lock().await.next_task().unwrap().priority(), 1);
+    producer.await.unwrap().unwrap();
+    assert_eq!(scheduler.lock().await.next_task().unwrap().priority(), 2);
+
+    // Dropping the oldest task makes room without failing the producer
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_queue_capacity(1, OverflowPolicy::DropOldest);
+    let oldest = Task::new(agent_id, 1);
+    let oldest_id = oldest.id();
+    scheduler.submit(oldest).unwrap();
+    scheduler.submit(Task::new(agent_id, 2)).unwrap();
+    assert_eq!(
+        scheduler.take_events(),
+        vec![SchedulerEvent::TaskDropped {
+            agent_id,
+            task: oldest_id,
+            overflow: OverflowPolicy::DropOldest,
+        }]
+    );
+    assert_eq!(scheduler.next_task().unwrap().priority(), 2);
+}
+
+#[tokio::test]
+async fn bounded_queue_counts_held_and_conversation_tasks() {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    // A task held for its agent is the oldest one dropped
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_queue_capacity(1, OverflowPolicy::DropOldest);
+    scheduler.set_agent_ready(agent_id, false);
+    let held = Task::new(agent_id, 1);
+    let held_id = held.id();
+    scheduler.submit(held).unwrap();
+    assert!(scheduler.next_task().is_none());
+    scheduler.submit(Task::new(agent_id, 2)).unwrap();
+    assert_eq!(
+        scheduler.take_events(),
+        vec![SchedulerEvent::TaskDropped {
+            agent_id,
+            task: held_id,
+            overflow: OverflowPolicy::DropOldest,
+        }]
+    );
+    assert_eq!(scheduler.held_count(), 0);
+    assert_eq!(scheduler.queue().len(), 1);
+
+    // Tasks waiting for their conversation count against the bound
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_queue_capacity(2, OverflowPolicy::Reject);
+    scheduler
+        .submit(Task::new(agent_id, 1).with_conversation("chat"))
+        .unwrap();
+    scheduler
+        .submit(Task::new(agent_id, 1).with_conversation("chat"))
+        .unwrap();
+    assert!(scheduler.is_full());
+
+    // Removing held tasks wakes blocked producers
+    let scheduler = Arc::new(Mutex::new(
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+            .with_queue_capacity(1, OverflowPolicy::Block),
+    ));
+    {
+        let mut scheduler = scheduler.lock().await;
+        scheduler.set_agent_ready(agent_id, false);
+        scheduler.submit(Task::new(agent_id, 1)).unwrap();
+        assert!(scheduler.next_task().is_none());
+    }
+    let other = AgentId::new();
+    let producer = tokio::spawn({
+        let scheduler = scheduler.clone();
+        async move { Scheduler::submit_async(&scheduler, Task::new(other, 2)).await }
+    });
+    tokio::task::yield_now().await;
+    assert!(!producer.is_finished());
+    assert_eq!(scheduler.lock().await.park(agent_id), 1);
+    producer.await.unwrap().unwrap();
+}
+
+#[test]
+fn policy_swaps_keep_queued_tasks() {
+    struct Newest;
+
+    impl PolicyEngine for Newest {
+        fn next(&mut self, queue: &mut TaskQueue) -> Option<Task> {
+            queue.remove(queue.len().checked_sub(1)?)
+        }
+    }
+
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    for priority in [1, 3, 2, 5, 4] {
+        scheduler.submit(Task::new(agent_id, priority)).unwrap();
+    }
+    assert_eq!(scheduler.next_task().unwrap().priority(), 1);
+
+    scheduler.set_policy_engine(PriorityScheduler::new(4));
+    // Priorities above the top level count as the top level, oldest first
+    assert_eq!(scheduler.next_task().unwrap().priority(), 3);
+
+    scheduler.set_policy_engine(Newest);
+    assert_eq!(scheduler.next_task().unwrap().priority(), 4);
+
+    scheduler.set_policy(SchedulingPolicy::new(PolicyType::Priority));
+    assert!(!scheduler.has_policy_engine());
+    assert_eq!(scheduler.next_task().unwrap().priority(), 5);
+    assert_eq!(scheduler.next_task().unwrap().priority(), 2);
+}
+
+#[test]
+fn priority_bands_mix_strict_and_proportional_levels() {
+    let agent_id = AgentId::new();
+    let mut queue = TaskQueue::new();
+    for priority in [0, 1].repeat(10).into_iter().chain([2]) {
+        queue.push(Task::new(agent_id, priority));
+    }
+    let mut scheduler = PriorityScheduler::new(3)
+        .with_band(1, PriorityBand::Proportional(70))
+        .with_band(0, PriorityBand::Proportional(30));
+    assert_eq!(scheduler.band(2), PriorityBand::Strict);
+
+    let served: Vec<_> = std::iter::from_fn(|| PolicyEngine::next(&mut scheduler, &mut queue))
+        .map(|task| task.priority())
+        .collect();
+
+    // System tasks preempt; user levels then split dispatches 70/30
+    assert_eq!(served[0], 2);
+    let user = &served[1..11];
+    assert_eq!(user.iter().filter(|priority| **priority == 1).count(), 7);
+    assert_eq!(served.len(), 21);
+}
+
+#[test]
+fn round_robin_engine_alternates_agents() {
+    let (busy, quiet) = (AgentId::new(), AgentId::new());
+    let mut queue = TaskQueue::new();
+    for agent_id in [busy, busy, busy, quiet] {
+        queue.push(Task::new(agent_id, 1));
+    }
+    let mut round_robin = RoundRobinScheduler::default();
+
+    let served: Vec<_> = std::iter::from_fn(|| PolicyEngine::next(&mut round_robin, &mut queue))
+        .map(|task| *task.agent_id())
+        .collect();
+
+    assert_eq!(served, vec![busy, quiet, busy, busy]);
+    assert_eq!(round_robin.current_index(), 4);
+}
+
+#[test]
+fn idempotency_keys_drop_redelivered_submissions() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let clock = Arc::new(VirtualClock::new());
+    let agent_id = AgentId::new();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS))
+        .with_idempotency_window(Duration::from_secs(30))
+        .with_clock(clock.clone());
+
+    let first = scheduler
+        .submit(Task::new(agent_id, 1).with_idempotency_key("event-42"))
+        .unwrap();
+    let redelivered = scheduler
+        .submit(Task::new(agent_id, 1).with_idempotency_key("event-42"))
+        .unwrap();
+    assert_eq!(redelivered.id(), first.id());
+    assert_eq!(scheduler.queue().len(), 1);
+    assert_eq!(scheduler.deduplicated(), 1);
+
+    // Once the window passes, the key is forgotten
+    clock.advance(Duration::from_secs(30));
+    let late = scheduler
+        .submit(Task::new(agent_id, 1).with_idempotency_key("event-42"))
+        .unwrap();
+    assert_ne!(late.id(), first.id());
+    assert_eq!(scheduler.queue().len(), 2);
+    assert_eq!(scheduler.idempotency_keys(), 1);
+}
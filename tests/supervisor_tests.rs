@@ -42,6 +42,18 @@ fn exponential_backoff() {
     assert_eq!(backoff.retries(), 2);
 }
 
+#[test]
+fn restart_policy_uses_custom_backoff_curve() {
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure).with_backoff(
+        FibonacciBackoff::new(Duration::from_secs(1), Duration::from_secs(10)),
+    );
+
+    let delays: Vec<_> = (1..=7)
+        .map(|restart| policy.restart_delay(restart).as_secs())
+        .collect();
+    assert_eq!(delays, [1, 1, 2, 3, 5, 8, 10]);
+}
+
 #[test]
 fn health_check() {
     let mut health = HealthCheck::new();
@@ -74,3 +86,1696 @@ fn circuit_breaker() {
     breaker.record_success();
     assert_eq!(breaker.state(), CircuitState::Closed);
 }
+
+#[tokio::test]
+async fn restarted_child_receives_failure_context() {
+    let mut supervisor = Supervisor::new("main");
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(3)
+        .with_backoff_seconds(0);
+
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |ctx| async move {
+        match ctx.attempt() {
+            1 => Err(RuntimeError::ExecutionFailed("tool crashed".into())),
+            2 => {
+                assert_eq!(ctx.error(), Some("Execution failed: tool crashed"));
+                assert!(ctx.time_since_last_failure().is_some());
+                panic!("second failure");
+            }
+            _ => {
+                assert_eq!(ctx.panic_message(), Some("second failure"));
+                Ok(())
+            }
+        }
+    }));
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+    assert_eq!(exit, ChildExit::Completed { attempts: 3 });
+    assert!(supervisor.get_health_check(&agent_id).unwrap().is_healthy());
+}
+
+#[tokio::test]
+async fn child_gives_up_after_max_retries() {
+    let mut supervisor = Supervisor::new("main");
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |_| async {
+        Err(RuntimeError::ExecutionFailed("boom".into()))
+    }));
+
+    match supervisor.run_child(&agent_id).await.unwrap() {
+        ChildExit::GaveUp {
+            attempts,
+            last_failure,
+        } => {
+            assert_eq!(attempts, 2);
+            assert!(!last_failure.is_panic());
+        }
+        exit => panic!("unexpected exit: {:?}", exit),
+    }
+}
+
+#[tokio::test]
+async fn watchdog_kills_hung_child_and_restarts_it() {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let mut supervisor = Supervisor::new("main");
+    let events = supervisor.subscribe();
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+
+    let spec = ChildSpec::new(agent_id, policy, |ctx| async move {
+        let watchdog = ctx.watchdog().cloned().unwrap();
+        if ctx.attempt() == 1 {
+            // Livelocked: busy, but never making progress
+            std::future::pending::<()>().await;
+        }
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            watchdog.pet();
+        }
+        Ok(())
+    })
+    .with_watchdog(Duration::from_millis(50));
+    supervisor.add_child(spec);
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+    assert_eq!(exit, ChildExit::Completed { attempts: 2 });
+    let events: Vec<_> = events.take(4).collect().await;
+    assert!(matches!(
+        events[1],
+        SupervisorEvent::WatchdogExpired { attempt: 1, interval, .. }
+            if interval == Duration::from_millis(50)
+    ));
+    assert!(matches!(
+        events[2],
+        SupervisorEvent::ChildFailed { attempt: 1, .. }
+    ));
+}
+
+#[tokio::test]
+async fn failure_classifier_restarts_crashes_at_once_and_stops_on_fatal() {
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::Always).with_backoff_seconds(60);
+    let mut supervisor =
+        Supervisor::new("main").with_failure_classifier(|error: &RuntimeError| match error {
+            RuntimeError::ExecutionFailed(message) if message == "segfault" => FailureKind::Crash,
+            RuntimeError::ExecutionFailed(message) if message == "bad api key" => {
+                FailureKind::Fatal
+            }
+            _ => FailureKind::Transient,
+        });
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |ctx| async move {
+        match ctx.attempt() {
+            1 => Err(RuntimeError::ExecutionFailed("segfault".into())),
+            _ => Err(RuntimeError::ExecutionFailed("bad api key".into())),
+        }
+    }));
+
+    // A 60s backoff would time out; the crash restarts immediately
+    let exit = tokio::time::timeout(Duration::from_secs(5), supervisor.run_child(&agent_id))
+        .await
+        .unwrap()
+        .unwrap();
+    match exit {
+        ChildExit::GaveUp {
+            attempts,
+            last_failure,
+        } => {
+            assert_eq!(attempts, 2);
+            assert_eq!(last_failure.kind(), FailureKind::Fatal);
+        }
+        exit => panic!("unexpected exit: {:?}", exit),
+    }
+    let last = supervisor.decision_log().last_for(&agent_id).unwrap();
+    assert_eq!(last.clause, RestartClause::Fatal);
+}
+
+#[tokio::test]
+async fn failure_kind_follows_the_supervisor_error_classifier() {
+    struct RetryEverything;
+
+    impl ErrorClassifier for RetryEverything {
+        fn classify(&self, _error: &RuntimeError) -> ErrorClass {
+            ErrorClass::Transient
+        }
+    }
+
+    // A configuration error is fatal by default, but this classifier retries it
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(2)
+        .with_backoff_seconds(0);
+    let mut supervisor = Supervisor::new("main").with_classifier(RetryEverything);
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |_| async {
+        Err(RuntimeError::ConfigError("missing key".into()))
+    }));
+
+    match supervisor.run_child(&agent_id).await.unwrap() {
+        ChildExit::GaveUp {
+            attempts,
+            last_failure,
+        } => {
+            assert_eq!(attempts, 3);
+            assert_eq!(last_failure.kind(), FailureKind::Transient);
+        }
+        exit => panic!("unexpected exit: {:?}", exit),
+    }
+}
+
+#[test]
+fn error_classification() {
+    let classifier = DefaultClassifier;
+
+    assert_eq!(
+        classifier.classify(&RuntimeError::ConfigError("bad".into())),
+        ErrorClass::Permanent
+    );
+    let class = classifier.classify(&RuntimeError::RateLimited {
+        message: "slow down".into(),
+        retry_after: Some(Duration::from_secs(30)),
+    });
+    assert_eq!(
+        class.retry_delay(Duration::from_secs(1)),
+        Duration::from_secs(30)
+    );
+
+    let mut breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+    breaker.record_error(&ErrorClass::Permanent);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    breaker.record_error(&ErrorClass::Transient);
+    assert_eq!(breaker.state(), CircuitState::Open);
+}
+
+#[tokio::test]
+async fn permanent_failure_is_not_restarted() {
+    let mut supervisor = Supervisor::new("main");
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(5)
+        .with_backoff_seconds(0);
+
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |_| async {
+        Err(RuntimeError::ConfigError("missing api key".into()))
+    }));
+
+    match supervisor.run_child(&agent_id).await.unwrap() {
+        ChildExit::GaveUp { attempts, .. } => assert_eq!(attempts, 1),
+        exit => panic!("unexpected exit: {:?}", exit),
+    }
+}
+
+#[test]
+fn phi_accrual_detector() {
+    let mut detector = PhiAccrualDetector::new(8.0);
+    let start = std::time::Instant::now();
+
+    for i in 0..10 {
+        detector.heartbeat_at(start + Duration::from_secs(i));
+    }
+    let last = start + Duration::from_secs(9);
+
+    assert!(detector.is_available_at(last + Duration::from_millis(1100)));
+    assert!(!detector.is_available_at(last + Duration::from_secs(10)));
+    assert!(detector.phi_at(last + Duration::from_secs(3)) > detector.phi_at(last));
+}
+
+#[tokio::test]
+async fn detectors_retries_and_hedges_read_the_injected_clock() {
+    use std::sync::Arc;
+
+    let clock = Arc::new(VirtualClock::new());
+    let mut detector = PhiAccrualDetector::new(8.0).with_clock(clock.clone());
+    for _ in 0..10 {
+        detector.heartbeat();
+        clock.advance(Duration::from_secs(1));
+    }
+    assert!(detector.is_available());
+    clock.advance(Duration::from_secs(10));
+    assert!(!detector.is_available());
+
+    let retry = DeadlineRetry::new(TimeBudget::new(Duration::from_secs(60)))
+        .with_backoff(ExponentialBackoff::new(Duration::ZERO, Duration::ZERO))
+        .with_clock(clock.clone());
+    let attempt_clock = clock.clone();
+    let result: Result<(), _> = retry
+        .run(|_| {
+            attempt_clock.advance(Duration::from_secs(25));
+            async { Err(RuntimeError::ExecutionFailed("slow".into())) }
+        })
+        .await;
+    match result {
+        Err(RuntimeError::DeadlineExceeded(report)) => {
+            assert_eq!(report.attempts.len(), 3);
+            assert_eq!(report.attempt_time(), Duration::from_secs(75));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut hedger = Hedger::new(Duration::from_secs(3600))
+        .with_max_hedge_ratio(1.0)
+        .with_clock(clock.clone());
+    let (result, ()) = futures::join!(
+        hedger.run(futures::future::pending(), || async { Ok("secondary") }),
+        async {
+            tokio::task::yield_now().await;
+            clock.advance(Duration::from_secs(3600));
+        }
+    );
+    assert_eq!(result.unwrap(), "secondary");
+}
+
+#[tokio::test]
+async fn tcp_and_http_probes() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 512];
+            let _ = socket.read(&mut buffer).await;
+            let _ = socket.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await;
+        }
+    });
+
+    assert_eq!(TcpProbe::new(&address).probe().await, HealthStatus::Healthy);
+
+    let url = format!("http://{}/health", address);
+    let probe = HttpProbe::new(&url).with_expected_status(204);
+    assert_eq!(probe.probe().await, HealthStatus::Healthy);
+    let probe = HttpProbe::new(&url);
+    assert_eq!(probe.probe().await, HealthStatus::Unhealthy);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn command_probe() {
+    assert_eq!(
+        CommandProbe::new("true").probe().await,
+        HealthStatus::Healthy
+    );
+    assert_eq!(
+        CommandProbe::new("false").probe().await,
+        HealthStatus::Unhealthy
+    );
+}
+
+#[test]
+fn probe_config_from_file() {
+    let config: RuntimeConfig = serde_json::from_str(
+        r#"{
+            "max_workers": 2,
+            "enable_metrics": true,
+            "enable_tracing": false,
+            "default_timeout_ms": 1000,
+            "health_probes": {
+                "web": { "type": "http", "url": "http://localhost:8080/health" },
+                "db": { "type": "tcp", "address": "localhost:5432", "timeout_ms": 250 }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.health_probes["db"],
+        ProbeConfig::Tcp {
+            address: "localhost:5432".into(),
+            timeout_ms: 250
+        }
+    );
+    assert!(matches!(
+        config.health_probes["web"],
+        ProbeConfig::Http {
+            expected_status: 200,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn health_check_thresholds() {
+    let mut health = HealthCheck::new()
+        .with_success_threshold(2)
+        .with_failure_threshold(3);
+
+    health.record_healthy();
+    assert_eq!(health.status(), HealthStatus::Unknown);
+    health.record_healthy();
+    assert!(health.is_healthy());
+
+    health.record_unhealthy();
+    health.record_unhealthy();
+    assert!(health.is_healthy());
+    health.record_unhealthy();
+    assert_eq!(health.status(), HealthStatus::Unhealthy);
+}
+
+#[test]
+fn health_check_initial_delay() {
+    let mut health = HealthCheck::new().with_initial_delay(Duration::from_secs(60));
+
+    assert!(health.in_initial_delay());
+    health.record_unhealthy();
+    assert_eq!(health.status(), HealthStatus::Unknown);
+    assert_eq!(health.failures(), 0);
+}
+
+#[tokio::test]
+async fn fallback_when_circuit_open() {
+    let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+    let mut operation =
+        ProtectedOperation::new("llm", breaker).with_fallback(|| async { Ok("cached") });
+
+    let result = operation
+        .call(|| async { Err(RuntimeError::ExecutionFailed("timeout".into())) })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(operation.state(), CircuitState::Open);
+
+    let result = operation.call(|| async { Ok("fresh") }).await;
+    assert_eq!(result.unwrap(), "cached");
+    assert_eq!(operation.fallback_count(), 1);
+}
+
+#[tokio::test]
+async fn open_circuit_without_fallback_is_rejected() {
+    let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+    let mut operation = ProtectedOperation::<()>::new("llm", breaker);
+
+    let _ = operation
+        .call(|| async { Err(RuntimeError::ExecutionFailed("timeout".into())) })
+        .await;
+    let result = operation.call(|| async { Ok(()) }).await;
+    assert!(matches!(result, Err(RuntimeError::CircuitOpen(name)) if name == "llm"));
+}
+
+#[tokio::test]
+async fn hedged_request_takes_first_success() {
+    let mut hedger = Hedger::new(Duration::from_millis(10)).with_max_hedge_ratio(1.0);
+
+    let result = hedger
+        .run(
+            async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok("primary")
+            },
+            || async { Ok("secondary") },
+        )
+        .await;
+
+    assert_eq!(result.unwrap(), "secondary");
+    assert_eq!(hedger.hedges(), 1);
+    assert_eq!(hedger.hedge_wins(), 1);
+}
+
+#[tokio::test]
+async fn hedge_rate_is_capped() {
+    let mut hedger = Hedger::new(Duration::from_millis(1)).with_max_hedge_ratio(0.0);
+
+    let result = hedger
+        .run(
+            async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok("primary")
+            },
+            || async { Ok("secondary") },
+        )
+        .await;
+
+    assert_eq!(result.unwrap(), "primary");
+    assert_eq!(hedger.hedges(), 0);
+}
+
+#[tokio::test]
+async fn retries_stop_at_time_budget() {
+    let retry = DeadlineRetry::new(TimeBudget::new(Duration::from_millis(100))).with_backoff(
+        ExponentialBackoff::new(Duration::from_millis(40), Duration::from_secs(1)),
+    );
+
+    let result: Result<(), _> = retry
+        .run(|_| async { Err(RuntimeError::ExecutionFailed("flaky".into())) })
+        .await;
+
+    match result {
+        Err(RuntimeError::DeadlineExceeded(report)) => {
+            assert_eq!(report.attempts.len(), 2);
+            assert_eq!(report.backoff, Duration::from_millis(40));
+            assert!(report.to_string().contains("last error"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn retries_succeed_within_budget() {
+    let retry = DeadlineRetry::new(TimeBudget::new(Duration::from_secs(1))).with_backoff(
+        ExponentialBackoff::new(Duration::from_millis(1), Duration::from_millis(10)),
+    );
+
+    let result = retry
+        .run(|attempt| async move {
+            if attempt < 3 {
+                Err(RuntimeError::ExecutionFailed("flaky".into()))
+            } else {
+                Ok(attempt)
+            }
+        })
+        .await;
+
+    assert_eq!(result.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn restart_skipped_when_deadline_unreachable() {
+    let mut supervisor = Supervisor::new("main");
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(5)
+        .with_backoff_seconds(0);
+
+    supervisor.add_child(
+        ChildSpec::new(agent_id, policy, |_| async {
+            Err(RuntimeError::ExecutionFailed("slow tool".into()))
+        })
+        .with_deadline(std::time::Instant::now() + Duration::from_secs(1))
+        .with_expected_runtime(Duration::from_secs(5)),
+    );
+
+    match supervisor.run_child(&agent_id).await.unwrap() {
+        ChildExit::GaveUp {
+            attempts,
+            last_failure,
+        } => {
+            assert_eq!(attempts, 1);
+            assert_eq!(last_failure.class(), ErrorClass::DeadlineExceeded);
+        }
+        exit => panic!("unexpected exit: {:?}", exit),
+    }
+}
+
+#[tokio::test]
+async fn bulkhead_admits_by_priority() {
+    let bulkhead = Bulkhead::new(1);
+    let permit = bulkhead.acquire(0).await;
+    let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    for priority in [1, 9, 5] {
+        let bulkhead = bulkhead.clone();
+        let order_tx = order_tx.clone();
+        tokio::spawn(async move {
+            let _permit = bulkhead.acquire(priority).await;
+            order_tx.send(priority).unwrap();
+        });
+        tokio::task::yield_now().await;
+    }
+    while bulkhead.waiting() < 3 {
+        tokio::task::yield_now().await;
+    }
+
+    drop(permit);
+    let mut order = Vec::new();
+    for _ in 0..3 {
+        order.push(order_rx.recv().await.unwrap());
+    }
+    assert_eq!(order, vec![9, 5, 1]);
+    assert_eq!(bulkhead.in_use(), 0);
+}
+
+#[tokio::test]
+async fn bulkhead_group_rejects_and_times_out_waiters() {
+    let bulkhead = Bulkhead::new(1)
+        .with_group("search-api")
+        .with_max_waiting(1)
+        .with_timeout(Duration::from_millis(30));
+    let _permit = bulkhead.admit(0).await.unwrap();
+
+    let waiter = tokio::spawn({
+        let bulkhead = bulkhead.clone();
+        async move { bulkhead.admit(0).await.map(drop) }
+    });
+    while bulkhead.waiting() < 1 {
+        tokio::task::yield_now().await;
+    }
+    let full = bulkhead.run(0, async { Ok(()) }).await.unwrap_err();
+    assert_eq!(full.code(), "supervisor.bulkhead_full");
+
+    let timed_out = waiter.await.unwrap().unwrap_err();
+    assert_eq!(timed_out.code(), "supervisor.bulkhead_timeout");
+    assert_eq!(
+        DefaultClassifier.classify(&timed_out),
+        ErrorClass::ResourceExhausted
+    );
+    assert_eq!(bulkhead.waiting(), 0);
+    assert_eq!((bulkhead.rejected(), bulkhead.timed_out()), (1, 1));
+    assert_eq!(bulkhead.in_use(), 1);
+}
+
+#[test]
+fn half_open_breaker_sheds_low_priority() {
+    let mut breaker = CircuitBreaker::new(1, Duration::ZERO)
+        .with_half_open_max_calls(1)
+        .with_half_open_min_priority(5);
+
+    breaker.record_failure();
+    assert!(!breaker.is_allowed_for(1));
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    assert!(breaker.is_allowed_for(8));
+    assert!(!breaker.is_allowed_for(9));
+
+    breaker.record_success();
+    assert!(breaker.is_allowed_for(1));
+}
+
+#[test]
+fn half_open_probes_must_succeed_before_closing() {
+    use std::sync::{Arc, Mutex};
+
+    let transitions = Arc::new(Mutex::new(Vec::new()));
+    let record = |transitions: &Arc<Mutex<Vec<CircuitState>>>| {
+        let transitions = transitions.clone();
+        move |transition: &CircuitTransition| transitions.lock().unwrap().push(transition.to)
+    };
+    let mut breaker = CircuitBreaker::new(1, Duration::ZERO)
+        .with_half_open_probes(2)
+        .with_success_threshold(2)
+        .on_open(record(&transitions))
+        .on_half_open(record(&transitions))
+        .on_close(record(&transitions));
+
+    breaker.record_failure();
+    assert!(breaker.is_allowed());
+    assert!(breaker.is_allowed());
+    // Both probes are out
+    assert!(!breaker.is_allowed());
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::Closed);
+
+    assert_eq!(
+        *transitions.lock().unwrap(),
+        [
+            CircuitState::Open,
+            CircuitState::HalfOpen,
+            CircuitState::Closed
+        ]
+    );
+    let stats = breaker.stats();
+    assert_eq!((stats.opened, stats.half_opened, stats.closed), (1, 1, 1));
+    assert_eq!(stats.rejected, 1);
+}
+
+#[test]
+fn failed_probe_reopens_circuit() {
+    let mut breaker = CircuitBreaker::new(1, Duration::ZERO).with_success_threshold(3);
+
+    breaker.record_failure();
+    assert!(breaker.is_allowed());
+    breaker.record_success();
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+    breaker.record_failure();
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert_eq!(breaker.stats().opened, 2);
+}
+
+#[test]
+fn blocking_supervisor_runs_child() {
+    let mut supervisor = BlockingSupervisor::new(Supervisor::new("main")).unwrap();
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |ctx| async move {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        match ctx.attempt() {
+            1 => Err(RuntimeError::ExecutionFailed("flaky".into())),
+            _ => Ok(()),
+        }
+    }));
+
+    let exit = supervisor.run_child(&agent_id).unwrap();
+    assert_eq!(exit, ChildExit::Completed { attempts: 2 });
+}
+
+#[tokio::test]
+async fn profiler_attributes_cpu_time_to_agents() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    // CPU time of the calling thread, so busy work is measured the way the
+    // profiler measures it even on a loaded machine
+    #[cfg(unix)]
+    fn thread_cpu() -> Duration {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `ts` is a valid, writable timespec
+        unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+
+    #[cfg(not(unix))]
+    fn thread_cpu() -> Duration {
+        static ORIGIN: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        ORIGIN.get_or_init(std::time::Instant::now).elapsed()
+    }
+
+    #[derive(Default)]
+    struct Counts {
+        started: AtomicU32,
+        stopped: AtomicU32,
+    }
+
+    struct CountingHook(Arc<Counts>);
+
+    impl ProfilingHook for CountingHook {
+        fn on_start(&self, _agent_id: &AgentId) {
+            self.0.started.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_stop(&self, _agent_id: &AgentId, _usage: &CumulativeCpuTime) {
+            self.0.stopped.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let counts = Arc::new(Counts::default());
+    let profiler = Arc::new(Profiler::new().with_hook(CountingHook(counts.clone())));
+    let mut supervisor = Supervisor::new("main").with_profiler(profiler.clone());
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |ctx| async move {
+        let started = thread_cpu();
+        while thread_cpu() - started < Duration::from_millis(20) {
+            std::hint::spin_loop();
+        }
+        match ctx.attempt() {
+            1 => Err(RuntimeError::ExecutionFailed("retry".into())),
+            _ => Ok(()),
+        }
+    }));
+    supervisor.run_child(&agent_id).await.unwrap();
+
+    let usage = profiler.cpu_time(&agent_id);
+    assert_eq!(usage.executions(), 2);
+    assert!(usage.cpu() >= Duration::from_millis(30));
+    assert!(usage.wall() >= usage.cpu() / 2);
+    assert_eq!(counts.started.load(Ordering::SeqCst), 2);
+    assert_eq!(counts.stopped.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn decision_log_records_restarts_and_give_up() {
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+    let mut supervisor = Supervisor::new("decisions");
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |_| async {
+        Err(RuntimeError::ExecutionFailed("boom".into()))
+    }));
+
+    supervisor.run_child(&agent_id).await.unwrap();
+
+    let decisions: Vec<_> = supervisor.decision_log().for_agent(&agent_id).collect();
+    assert_eq!(decisions.len(), 2);
+    assert_eq!(decisions[0].action, DecisionAction::Restart);
+    assert_eq!(decisions[0].clause, RestartClause::RestartOnFailure);
+    assert_eq!(decisions[1].action, DecisionAction::GiveUp);
+    assert_eq!(
+        decisions[1].clause,
+        RestartClause::MaxRetriesReached { max_retries: 1 }
+    );
+    assert_eq!(
+        decisions[1].failure.as_deref(),
+        Some("Execution failed: boom")
+    );
+    assert!(serde_json::to_string(supervisor.decision_log()).is_ok());
+}
+
+#[cfg(feature = "webhooks")]
+#[tokio::test]
+async fn webhook_retries_and_signs_gave_up_event() {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for status in ["500 Internal Server Error", "200 OK"] {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let read = stream.read(&mut request).await.unwrap();
+            requests.push(String::from_utf8_lossy(&request[..read]).into_owned());
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+        requests
+    });
+
+    let webhook = WebhookSink::new(url)
+        .with_secret("s3cret")
+        .with_events(["agent_gave_up"])
+        .with_backoff(Duration::from_millis(1), Duration::from_millis(5));
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("hooks").with_webhook(Arc::new(webhook));
+    supervisor.add_child(ChildSpec::new(
+        agent_id,
+        RestartPolicy::new(RestartStrategy::Never),
+        |_| async { Err(RuntimeError::ExecutionFailed("boom".into())) },
+    ));
+
+    supervisor.run_child(&agent_id).await.unwrap();
+    let requests = server.await.unwrap();
+
+    let request = &requests[1];
+    let (head, body) = request.split_once("\r\n\r\n").unwrap();
+    assert!(body.contains("\"type\":\"agent_gave_up\""));
+    let header = |name: &str| {
+        head.lines()
+            .find_map(|line| line.strip_prefix(name))
+            .unwrap()
+            .to_string()
+    };
+    let timestamp: u64 = header("X-Agentropic-Timestamp: ").parse().unwrap();
+    assert_eq!(
+        header("X-Agentropic-Signature: "),
+        WebhookSink::signature(b"s3cret", timestamp, body.as_bytes())
+    );
+}
+
+#[tokio::test]
+async fn notification_router_filters_by_severity() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    #[async_trait::async_trait]
+    impl Notifier for Recorder {
+        async fn notify(&self, event: &RuntimeEvent) -> Result<(), RuntimeError> {
+            self.0.lock().unwrap().push(event.kind().to_string());
+            Ok(())
+        }
+    }
+
+    let pager = Arc::new(Recorder::default());
+    let chat = Arc::new(Recorder::default());
+    let router = NotificationRouter::new()
+        .route(Route::new(pager.clone()).with_min_severity(Severity::Critical))
+        .route(Route::new(chat.clone()));
+    let router = Arc::new(router);
+
+    let mut operation = ProtectedOperation::<()>::new(
+        "llm",
+        CircuitBreaker::new(1, std::time::Duration::from_secs(60)),
+    )
+    .with_notifier(router.clone());
+    let _ = operation
+        .call(|| async { Err(RuntimeError::ExecutionFailed("timeout".into())) })
+        .await;
+    operation.flush_notifications().await;
+
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("notify").with_notifier(router);
+    supervisor.add_child(ChildSpec::new(
+        agent_id,
+        RestartPolicy::new(RestartStrategy::Never),
+        |_| async { Err(RuntimeError::ExecutionFailed("boom".into())) },
+    ));
+    supervisor.run_child(&agent_id).await.unwrap();
+    supervisor.flush_notifications().await;
+
+    assert_eq!(*pager.0.lock().unwrap(), ["agent_gave_up"]);
+    assert_eq!(*chat.0.lock().unwrap(), ["breaker_opened", "agent_gave_up"]);
+}
+
+#[tokio::test]
+async fn slow_notifiers_do_not_hold_up_supervision() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Semaphore;
+
+    struct Gated {
+        gate: Semaphore,
+        delivered: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Notifier for Gated {
+        async fn notify(&self, _event: &RuntimeEvent) -> Result<(), RuntimeError> {
+            self.gate.acquire().await.unwrap().forget();
+            self.delivered.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    let notifier = Arc::new(Gated {
+        gate: Semaphore::new(0),
+        delivered: AtomicUsize::new(0),
+    });
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("slow").with_notifier(notifier.clone());
+    supervisor.add_child(ChildSpec::new(
+        agent_id,
+        RestartPolicy::new(RestartStrategy::Never),
+        |_| async { Err(RuntimeError::ExecutionFailed("boom".into())) },
+    ));
+
+    // The child exits while its gave-up event still waits on the notifier
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+    assert!(matches!(exit, ChildExit::GaveUp { .. }));
+    assert_eq!(notifier.delivered.load(Ordering::SeqCst), 0);
+
+    notifier.gate.add_permits(1);
+    supervisor.flush_notifications().await;
+    assert_eq!(notifier.delivered.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "hot-reload")]
+#[tokio::test]
+async fn hot_reload_carries_state_to_new_behavior() {
+    use std::sync::Arc;
+
+    struct Counter {
+        step: u32,
+        count: u32,
+    }
+
+    impl Behavior for Counter {
+        fn handle(&mut self, _input: &[u8]) -> Result<Vec<u8>, RuntimeError> {
+            self.count += self.step;
+            Ok(self.count.to_le_bytes().to_vec())
+        }
+
+        fn checkpoint(&self) -> Result<Vec<u8>, RuntimeError> {
+            Ok(self.count.to_le_bytes().to_vec())
+        }
+
+        fn restore(&mut self, checkpoint: &[u8]) -> Result<(), RuntimeError> {
+            self.count = u32::from_le_bytes(checkpoint.try_into().unwrap());
+            Ok(())
+        }
+    }
+
+    let agent_id = AgentId::new();
+    let agent = Arc::new(ReloadableAgent::new(LoadedBehavior::new(Box::new(
+        Counter { step: 1, count: 0 },
+    ))));
+    let mut supervisor = Supervisor::new("reload");
+    supervisor.add_reloadable(agent_id, agent.clone());
+    agent.handle(b"").await.unwrap();
+    agent.handle(b"").await.unwrap();
+
+    let v2 = || Ok(Box::new(Counter { step: 10, count: 0 }) as Box<dyn Behavior>);
+    assert_eq!(supervisor.hot_reload(&agent_id, &v2).await.unwrap(), 2);
+    assert_eq!(agent.handle(b"").await.unwrap(), 12u32.to_le_bytes());
+
+    let broken = || Err::<Box<dyn Behavior>, _>(RuntimeError::SpawnFailed("missing".into()));
+    assert!(supervisor.hot_reload(&agent_id, &broken).await.is_err());
+    assert_eq!(agent.generation().await, 2);
+}
+
+#[tokio::test]
+async fn unknown_child_error_is_structured() {
+    let mut supervisor = Supervisor::new("root");
+    let error = supervisor.run_child(&AgentId::new()).await.unwrap_err();
+
+    assert!(matches!(
+        error,
+        RuntimeError::Supervisor(SupervisorError::ChildNotFound(_))
+    ));
+    assert_eq!(error.code(), "supervisor.child_not_found");
+    assert_eq!(DefaultClassifier.classify(&error), ErrorClass::Permanent);
+}
+
+#[tokio::test]
+async fn supervision_tree_restarts_siblings_one_for_all() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let (flaky, steady) = (AgentId::new(), AgentId::new());
+    let steady_starts = Arc::new(AtomicU32::new(0));
+    let starts = steady_starts.clone();
+    let policy = || {
+        RestartPolicy::new(RestartStrategy::OnFailure)
+            .with_max_retries(3)
+            .with_backoff_seconds(0)
+    };
+    let tree = SupervisionTree::new("session")
+        .with_escalation(Escalation::OneForAll)
+        .with_child(ChildSpec::new(flaky, policy(), |ctx| async move {
+            if ctx.attempt() < 2 {
+                Err(RuntimeError::ExecutionFailed("flaky".into()))
+            } else {
+                Ok(())
+            }
+        }))
+        .with_child(ChildSpec::new(steady, policy(), move |_| {
+            let starts = starts.clone();
+            async move {
+                starts.fetch_add(1, Ordering::SeqCst);
+                std::future::pending::<()>().await;
+                Ok(())
+            }
+        }));
+
+    let run = tokio::time::timeout(std::time::Duration::from_millis(200), tree.run()).await;
+    assert!(run.is_err(), "steady child keeps running");
+    assert_eq!(steady_starts.load(Ordering::SeqCst), 2);
+    let log = tree.decision_log();
+    assert_eq!(
+        log.last_for(&steady).unwrap().clause,
+        RestartClause::SiblingFailed { sibling: flaky }
+    );
+}
+
+#[tokio::test]
+async fn supervision_tree_escalates_to_parent() {
+    let worker = AgentId::new();
+    let never = || RestartPolicy::new(RestartStrategy::Never);
+    let subtree =
+        SupervisionTree::new("tools").with_child(ChildSpec::new(worker, never(), |_| async {
+            Err(RuntimeError::ExecutionFailed("tool crashed".into()))
+        }));
+    let subtree_id = *subtree.id();
+    let root = SupervisionTree::new("root").with_subtree(
+        subtree,
+        RestartPolicy::new(RestartStrategy::OnFailure)
+            .with_max_retries(1)
+            .with_backoff_seconds(0),
+    );
+
+    let exit = root.run().await;
+    match exit {
+        TreeExit::Escalated { agent_id, failure } => {
+            assert_eq!(agent_id, subtree_id);
+            assert_eq!(failure.message(), "Execution failed: tool crashed");
+        }
+        TreeExit::Completed => panic!("failure should escalate"),
+    }
+    let actions: Vec<_> = root
+        .decision_log()
+        .iter()
+        .map(|decision| decision.action)
+        .collect();
+    assert_eq!(
+        actions,
+        vec![DecisionAction::Restart, DecisionAction::Escalate]
+    );
+}
+
+#[tokio::test]
+async fn health_runner_marks_unhealthy_after_threshold() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct Flag(Arc<AtomicBool>);
+
+    #[async_trait::async_trait]
+    impl HealthProbe for Flag {
+        async fn probe(&self) -> HealthStatus {
+            if self.0.load(Ordering::SeqCst) {
+                HealthStatus::Healthy
+            } else {
+                HealthStatus::Unhealthy
+            }
+        }
+    }
+
+    struct Hung;
+
+    #[async_trait::async_trait]
+    impl HealthProbe for Hung {
+        async fn probe(&self) -> HealthStatus {
+            futures::future::pending().await
+        }
+    }
+
+    let healthy = Arc::new(AtomicBool::new(true));
+    let agent = AgentId::new();
+    let stuck = AgentId::new();
+    let runner = HealthCheckRunner::new()
+        .with_timeout(Duration::from_millis(20))
+        .with_unhealthy_threshold(2)
+        .with_probe(agent, Flag(healthy.clone()))
+        .with_probe(stuck, Hung);
+
+    runner.check_once().await;
+    assert_eq!(runner.status(&agent), HealthStatus::Healthy);
+
+    healthy.store(false, Ordering::SeqCst);
+    runner.check_once().await;
+    assert_eq!(runner.status(&agent), HealthStatus::Healthy);
+    assert_eq!(runner.failures(&agent), 1);
+    runner.check_once().await;
+    assert_eq!(runner.status(&agent), HealthStatus::Unhealthy);
+    // A probe that never answers times out and counts as failed
+    assert_eq!(runner.status(&stuck), HealthStatus::Unhealthy);
+
+    let mut supervisor = Supervisor::new("probed");
+    supervisor.supervise(agent, RestartPolicy::default());
+    supervisor.sync_health(&runner);
+    assert_eq!(
+        supervisor.get_health_check(&agent).unwrap().status(),
+        HealthStatus::Unhealthy
+    );
+}
+
+#[tokio::test]
+async fn lifecycle_stop_prevents_restart() {
+    use std::sync::Arc;
+
+    let lifecycle = Arc::new(AgentLifecycle::new());
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("main").with_lifecycle(lifecycle.clone());
+    supervisor.add_child(ChildSpec::new(
+        agent_id,
+        RestartPolicy::new(RestartStrategy::Always),
+        move |_| {
+            let lifecycle = lifecycle.clone();
+            async move {
+                assert_eq!(lifecycle.state(&agent_id), Some(LifecycleState::Running));
+                lifecycle.stop(agent_id).await
+            }
+        },
+    ));
+
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+    assert_eq!(exit, ChildExit::Completed { attempts: 1 });
+    let decision = supervisor.decision_log().last_for(&agent_id).unwrap();
+    assert_eq!(decision.clause, RestartClause::Stopped);
+}
+
+#[tokio::test]
+async fn supervisor_snapshot_resumes_restart_budget() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+    let mut supervisor = Supervisor::new("main");
+    supervisor.set_circuit_breaker(agent_id, CircuitBreaker::new(1, Duration::from_secs(60)));
+    supervisor.add_child(ChildSpec::new(
+        agent_id,
+        policy.clone().with_max_retries(0),
+        |_| async { Err(RuntimeError::ExecutionFailed("boom".into())) },
+    ));
+    supervisor.run_child(&agent_id).await.unwrap();
+
+    // As if the process crashed after the child's first restart
+    let mut snapshot = supervisor.snapshot();
+    snapshot.restarts = vec![(agent_id, 1)];
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let mut restored = Supervisor::restore_from(serde_json::from_str(&json).unwrap());
+
+    assert_eq!(restored.name(), "main");
+    assert_eq!(restored.decision_log().len(), 1);
+    assert_eq!(
+        restored
+            .circuit_breaker(&agent_id)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .state(),
+        CircuitState::Open
+    );
+
+    let runs = Arc::new(AtomicU32::new(0));
+    let counter = runs.clone();
+    restored.add_child(ChildSpec::new(agent_id, policy, move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async { Err(RuntimeError::ExecutionFailed("boom".into())) }
+    }));
+    match restored.run_child(&agent_id).await.unwrap() {
+        ChildExit::GaveUp { attempts, .. } => assert_eq!(attempts, 2),
+        exit => panic!("unexpected exit: {:?}", exit),
+    }
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    assert_eq!(restored.restart_count(&agent_id), 0);
+}
+
+#[cfg(feature = "prometheus")]
+#[tokio::test]
+async fn supervisor_metrics_encode_as_prometheus_text() {
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("main");
+    supervisor.set_circuit_breaker(agent_id, CircuitBreaker::new(1, Duration::from_secs(60)));
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |_| async {
+        Err(RuntimeError::ExecutionFailed("boom".into()))
+    }));
+    supervisor.run_child(&agent_id).await.unwrap();
+
+    let mut collector = Collector::new();
+    supervisor.collect_metrics(&mut collector);
+    let mut registry = MetricsRegistry::new();
+    registry.register("supervisor", collector);
+    let text = registry.encode_prometheus();
+
+    assert!(text.contains("# TYPE supervisor_restarts_total counter\n"));
+    assert!(text.contains(&format!(
+        "supervisor_restarts_total{{agent_id=\"{}\"}} 1\n",
+        agent_id
+    )));
+    assert!(text.contains(&format!(
+        "supervisor_health_check_failures_total{{agent_id=\"{}\"}} 2\n",
+        agent_id
+    )));
+    assert!(text.contains("supervisor_circuit_breakers{state=\"open\"} 1\n"));
+    assert!(text.contains("# TYPE supervisor_run_time_seconds summary\n"));
+    assert!(!text.contains("# TYPE supervisor_run_time_seconds_count"));
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn restarts_run_in_agent_restart_spans() {
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct SpanNames(Arc<Mutex<Vec<String>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanNames {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(attrs.metadata().name().to_string());
+        }
+    }
+
+    let names = SpanNames::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(names.clone()));
+
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(2)
+        .with_backoff_seconds(0);
+    let mut supervisor = Supervisor::new("main");
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |_| async {
+        Err(RuntimeError::ExecutionFailed("boom".into()))
+    }));
+    supervisor.run_child(&agent_id).await.unwrap();
+
+    let restarts = names
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|name| *name == "agent.restart")
+        .count();
+    assert_eq!(restarts, 2);
+}
+
+#[tokio::test]
+async fn restart_intensity_gives_up_on_bursts_only() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let clock = Arc::new(VirtualClock::new());
+    let mut supervisor = Supervisor::new("intensity").with_clock(clock.clone());
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_backoff_seconds(0)
+        .with_max_restarts_in_window(2, Duration::from_secs(60));
+
+    let bursty = AgentId::new();
+    supervisor.add_child(ChildSpec::new(bursty, policy.clone(), |_| async {
+        Err(RuntimeError::ExecutionFailed("boom".into()))
+    }));
+    match supervisor.run_child(&bursty).await.unwrap() {
+        ChildExit::GaveUp { attempts, .. } => assert_eq!(attempts, 3),
+        exit => panic!("unexpected exit: {:?}", exit),
+    }
+    let last = supervisor.decision_log().for_agent(&bursty).last().unwrap();
+    assert_eq!(last.action, DecisionAction::GiveUp);
+    assert_eq!(
+        last.clause,
+        RestartClause::IntensityExceeded {
+            max_restarts: 2,
+            window: Duration::from_secs(60),
+        }
+    );
+
+    // Failing every 40s keeps at most two restarts in any 60s window
+    let steady = AgentId::new();
+    let runs = Arc::new(AtomicU32::new(0));
+    let (child_clock, child_runs) = (clock.clone(), runs.clone());
+    supervisor.add_child(ChildSpec::new(steady, policy, move |_| {
+        let (clock, runs) = (child_clock.clone(), child_runs.clone());
+        async move {
+            clock.advance(Duration::from_secs(40));
+            if runs.fetch_add(1, Ordering::SeqCst) < 5 {
+                Err(RuntimeError::ExecutionFailed("boom".into()))
+            } else {
+                Ok(())
+            }
+        }
+    }));
+    assert_eq!(
+        supervisor.run_child(&steady).await.unwrap(),
+        ChildExit::Completed { attempts: 6 }
+    );
+}
+
+#[test]
+fn circuit_breakers_cool_down_on_the_supervisor_clock() {
+    use std::sync::Arc;
+
+    let clock = Arc::new(VirtualClock::new());
+    let mut supervisor = Supervisor::new("breakers").with_clock(clock.clone());
+    let agent_id = AgentId::new();
+    let breaker =
+        supervisor.set_circuit_breaker(agent_id, CircuitBreaker::new(1, Duration::from_secs(30)));
+    breaker.lock().unwrap().record_failure();
+    assert!(!breaker.lock().unwrap().is_allowed());
+
+    clock.advance(Duration::from_secs(29));
+    assert!(!breaker.lock().unwrap().is_allowed());
+    clock.advance(Duration::from_secs(1));
+    assert!(breaker.lock().unwrap().is_allowed());
+    assert_eq!(breaker.lock().unwrap().state(), CircuitState::HalfOpen);
+
+    // A restored breaker keeps cooling down on the virtual clock
+    breaker.lock().unwrap().record_failure();
+    let mut restored = Supervisor::new("breakers").with_clock(clock.clone());
+    restored.restore(supervisor.snapshot());
+    let breaker = restored.circuit_breaker(&agent_id).unwrap();
+    clock.advance(Duration::from_secs(10));
+    assert!(!breaker.lock().unwrap().is_allowed());
+    clock.advance(Duration::from_secs(20));
+    assert!(breaker.lock().unwrap().is_allowed());
+}
+
+#[tokio::test]
+async fn subscribers_see_starts_failures_and_restarts() {
+    use futures::StreamExt;
+
+    let agent_id = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+    let mut supervisor = Supervisor::new("events");
+    supervisor.add_child(ChildSpec::new(agent_id, policy, |_| async {
+        Err(RuntimeError::ExecutionFailed("boom".into()))
+    }));
+    supervisor.set_circuit_breaker(agent_id, CircuitBreaker::new(2, Duration::from_secs(60)));
+    let events = supervisor.subscribe();
+
+    supervisor.run_child(&agent_id).await.unwrap();
+    drop(supervisor);
+
+    let events: Vec<_> = events.collect().await;
+    let failed = |attempt| SupervisorEvent::ChildFailed {
+        agent_id,
+        attempt,
+        error: "Execution failed: boom".into(),
+        class: ErrorClass::Transient,
+    };
+    let health = |from, to| SupervisorEvent::HealthChanged { agent_id, from, to };
+    assert_eq!(
+        events,
+        vec![
+            SupervisorEvent::ChildStarted {
+                agent_id,
+                attempt: 1
+            },
+            failed(1),
+            health(HealthStatus::Unknown, HealthStatus::Unhealthy),
+            SupervisorEvent::ChildRestarted {
+                agent_id,
+                attempt: 2,
+                delay: Duration::ZERO,
+            },
+            health(HealthStatus::Unhealthy, HealthStatus::Unknown),
+            SupervisorEvent::ChildStarted {
+                agent_id,
+                attempt: 2
+            },
+            failed(2),
+            health(HealthStatus::Unknown, HealthStatus::Unhealthy),
+            SupervisorEvent::CircuitOpened { agent_id },
+        ]
+    );
+}
+
+#[cfg(feature = "scheduler")]
+#[tokio::test]
+async fn exhausted_budget_holds_tasks_and_suspends_agent() {
+    use futures::StreamExt;
+    use std::sync::Arc;
+
+    let agent_id = AgentId::new();
+    let clock = Arc::new(VirtualClock::new());
+    let lifecycle = Arc::new(AgentLifecycle::new());
+    lifecycle.start(agent_id).await.unwrap();
+    let supervisor = Supervisor::new("budgets").with_lifecycle(lifecycle.clone());
+    let mut scheduler =
+        Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS)).with_clock(clock.clone());
+    scheduler.set_cost_budget(
+        agent_id,
+        CostBudget::new(100, Duration::from_secs(60))
+            .with_max_tokens(1000)
+            .with_suspension(),
+    );
+    scheduler.submit(Task::new(agent_id, 1)).unwrap();
+    let events = supervisor.subscribe();
+
+    supervisor
+        .record_usage(agent_id, BudgetUsage::new(600, 3), &mut scheduler)
+        .await
+        .unwrap();
+    supervisor
+        .record_usage(agent_id, BudgetUsage::new(600, 3), &mut scheduler)
+        .await
+        .unwrap();
+    drop(supervisor);
+
+    let events: Vec<_> = events.collect().await;
+    assert_eq!(
+        events,
+        vec![SupervisorEvent::BudgetExceeded {
+            agent_id,
+            usage: BudgetUsage::new(1200, 6),
+            suspended: true,
+        }]
+    );
+    assert_eq!(lifecycle.state(&agent_id), Some(LifecycleState::Suspended));
+
+    // Spend does not reset by resuming, only by the window ending: queued
+    // tasks are held and new ones rejected until then
+    lifecycle.resume(agent_id).await.unwrap();
+    assert!(scheduler.submit(Task::new(agent_id, 1)).is_err());
+    assert!(scheduler.next_task().is_none());
+    assert_eq!(
+        scheduler.next_wakeup_at(),
+        Some(clock.instant() + Duration::from_secs(60))
+    );
+    clock.advance(Duration::from_secs(60));
+    assert!(scheduler.next_task().is_some());
+}
+
+#[cfg(all(feature = "chaos", feature = "scheduler"))]
+#[tokio::test]
+async fn chaos_injects_reproducible_faults() {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    // The same seed injects the same faults
+    let config = ChaosConfig::new().with_seed(7).with_health_flip(0.5);
+    let flips = |chaos: Chaos| -> Vec<bool> { (0..32).map(|_| chaos.flip_health(true)).collect() };
+    let first = flips(Chaos::new(config));
+    assert_eq!(first, flips(Chaos::new(config)));
+    assert!(first.contains(&true) && first.contains(&false));
+
+    let chaos = Chaos::new(
+        ChaosConfig::new()
+            .with_seed(1)
+            .with_health_flip(1.0)
+            .with_breaker_trip(1.0),
+    );
+    let mut breaker = CircuitBreaker::new(5, Duration::from_secs(30));
+    assert!(chaos.trip_breaker(&mut breaker));
+    assert_eq!(breaker.state(), CircuitState::Open);
+    let mut check = HealthCheck::new().with_failure_threshold(1);
+    chaos.record_health(&mut check, true);
+    assert!(!check.is_healthy());
+
+    // Injected failures reach the scheduler like real ones
+    let chaos = Arc::new(Chaos::new(
+        ChaosConfig::new().with_seed(3).with_task_failure(1.0),
+    ));
+    let scheduler = Arc::new(Mutex::new(Scheduler::new(SchedulingPolicy::new(
+        PolicyType::FCFS,
+    ))));
+    let shutdown = CancellationToken::new();
+    let driver = tokio::spawn(Scheduler::run(
+        scheduler.clone(),
+        chaos.wrap(|_: &Task| async { Ok(()) }),
+        shutdown.clone(),
+    ));
+    scheduler
+        .lock()
+        .await
+        .submit(Task::new(AgentId::new(), 1))
+        .unwrap();
+    while scheduler.lock().await.dead_letters().is_empty() {
+        tokio::task::yield_now().await;
+    }
+    let stats = chaos.stats();
+    assert_eq!((stats.tasks_failed, stats.breakers_tripped), (1, 0));
+    shutdown.cancel();
+    driver.await.unwrap();
+}
+
+#[cfg(feature = "scheduler")]
+#[tokio::test]
+async fn suspended_child_parks_work_until_resumed() {
+    use std::sync::Arc;
+
+    let lifecycle = Arc::new(AgentLifecycle::new());
+    let (agent_id, other) = (AgentId::new(), AgentId::new());
+    let mut supervisor = Supervisor::new("main").with_lifecycle(lifecycle.clone());
+    supervisor.add_child(ChildSpec::new(
+        agent_id,
+        RestartPolicy::new(RestartStrategy::Never),
+        |_| async { Ok(()) },
+    ));
+    lifecycle.start(agent_id).await.unwrap();
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    for (agent, priority) in [(agent_id, 1), (other, 2), (agent_id, 3)] {
+        scheduler.submit(Task::new(agent, priority)).unwrap();
+    }
+
+    assert_eq!(
+        supervisor.suspend(agent_id, &mut scheduler).await.unwrap(),
+        2
+    );
+    assert_eq!(lifecycle.state(&agent_id), Some(LifecycleState::Suspended));
+    scheduler.submit(Task::new(agent_id, 4)).unwrap();
+    assert_eq!(scheduler.parked(&agent_id), 3);
+    assert_eq!(scheduler.next_task().unwrap().agent_id(), &other);
+    assert!(scheduler.next_task().is_none());
+
+    assert_eq!(
+        supervisor.resume(agent_id, &mut scheduler).await.unwrap(),
+        3
+    );
+    assert_eq!(lifecycle.state(&agent_id), Some(LifecycleState::Running));
+    assert!(!scheduler.is_parked(&agent_id));
+    let resumed: Vec<_> = std::iter::from_fn(|| scheduler.next_task())
+        .map(|task| task.priority())
+        .collect();
+    assert_eq!(resumed, vec![1, 3, 4]);
+
+    let unknown = supervisor.suspend(other, &mut scheduler).await.unwrap_err();
+    assert_eq!(unknown.code(), "supervisor.child_not_found");
+}
+
+#[cfg(all(feature = "chaos", feature = "scheduler"))]
+#[tokio::test]
+async fn chaos_kills_and_suspensions_run_shutdown_hooks() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use tokio::sync::Notify;
+
+    #[derive(Default)]
+    struct Recorder {
+        reasons: Mutex<Vec<ShutdownReason>>,
+        exit: Notify,
+    }
+
+    #[async_trait::async_trait]
+    impl ShutdownHooks for Recorder {
+        async fn prepare_shutdown(
+            &self,
+            _agent_id: AgentId,
+            reason: ShutdownReason,
+            _deadline: Instant,
+        ) -> Result<(), RuntimeError> {
+            self.reasons.lock().unwrap().push(reason);
+            Ok(())
+        }
+
+        async fn terminate(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+            self.exit.notify_one();
+            Ok(())
+        }
+    }
+
+    let hooks = Arc::new(Recorder::default());
+    let agent_id = AgentId::new();
+    let mut supervisor = Supervisor::new("main");
+    let exit = hooks.clone();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+    let spec = ChildSpec::new(agent_id, policy, move |ctx| {
+        let exit = exit.clone();
+        async move {
+            if ctx.attempt() == 1 {
+                exit.exit.notified().await;
+            }
+            Ok(())
+        }
+    })
+    .with_shutdown_hooks(hooks.clone());
+    let stop = spec.stop_handle();
+    supervisor.add_child(spec);
+
+    // A chaos kill shuts the attempt down through its hooks and restarts it
+    let chaos = Chaos::new(ChaosConfig::new().with_seed(5).with_child_kill(1.0));
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(chaos.kill_child(&stop));
+        assert_eq!(chaos.stats().children_killed, 1);
+    });
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+    assert_eq!(exit, ChildExit::Completed { attempts: 2 });
+    let killed = supervisor
+        .decision_log()
+        .for_agent(&agent_id)
+        .next()
+        .unwrap();
+    assert_eq!(killed.action, DecisionAction::Restart);
+    assert!(killed.failure.as_deref().unwrap().contains("killed"));
+
+    // Suspending lets the hooks flush state while the child keeps running
+    let mut scheduler = Scheduler::new(SchedulingPolicy::new(PolicyType::FCFS));
+    supervisor.suspend(agent_id, &mut scheduler).await.unwrap();
+    assert_eq!(
+        *hooks.reasons.lock().unwrap(),
+        [ShutdownReason::Killed, ShutdownReason::Suspended]
+    );
+}
+
+#[tokio::test]
+async fn stopped_child_flushes_before_exit_and_hung_child_is_killed() {
+    use futures::StreamExt;
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use tokio::sync::Notify;
+
+    #[derive(Default)]
+    struct Flusher {
+        flushed: Mutex<Vec<ShutdownReason>>,
+        exit: Notify,
+    }
+
+    #[async_trait::async_trait]
+    impl ShutdownHooks for Flusher {
+        async fn prepare_shutdown(
+            &self,
+            _agent_id: AgentId,
+            reason: ShutdownReason,
+            deadline: Instant,
+        ) -> Result<(), RuntimeError> {
+            assert!(deadline > Instant::now());
+            self.flushed.lock().unwrap().push(reason);
+            Ok(())
+        }
+
+        async fn terminate(&self, _agent_id: AgentId) -> Result<(), RuntimeError> {
+            self.exit.notify_one();
+            Ok(())
+        }
+    }
+
+    // Cooperative child exits once terminated, and is not restarted
+    let hooks = Arc::new(Flusher::default());
+    let mut supervisor = Supervisor::new("main").with_grace_period(Duration::from_secs(5));
+    let events = supervisor.subscribe();
+    let agent_id = AgentId::new();
+    let exit = hooks.clone();
+    let spec = ChildSpec::new(
+        agent_id,
+        RestartPolicy::new(RestartStrategy::Always),
+        move |_| {
+            let exit = exit.clone();
+            async move {
+                exit.exit.notified().await;
+                Ok(())
+            }
+        },
+    )
+    .with_shutdown_hooks(hooks.clone());
+    let stop = spec.stop_handle();
+    supervisor.add_child(spec);
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        stop.stop();
+    });
+    let exit = supervisor.run_child(&agent_id).await.unwrap();
+    assert_eq!(exit, ChildExit::Completed { attempts: 1 });
+    assert_eq!(*hooks.flushed.lock().unwrap(), [ShutdownReason::Requested]);
+    let events: Vec<_> = events.take(2).collect().await;
+    assert!(matches!(
+        events[1],
+        SupervisorEvent::ChildTerminated {
+            reason: ShutdownReason::Requested,
+            forced: false,
+            ..
+        }
+    ));
+
+    // Hung child ignores termination and is killed after its grace period
+    let hooks = Arc::new(Flusher::default());
+    let events = supervisor.subscribe();
+    let hung = AgentId::new();
+    let policy = RestartPolicy::new(RestartStrategy::OnFailure)
+        .with_max_retries(1)
+        .with_backoff_seconds(0);
+    supervisor.add_child(
+        ChildSpec::new(hung, policy, |ctx| async move {
+            if ctx.attempt() == 1 {
+                std::future::pending::<()>().await;
+            }
+            Ok(())
+        })
+        .with_watchdog(Duration::from_millis(30))
+        .with_shutdown_hooks(hooks.clone())
+        .with_grace_period(Duration::from_millis(20)),
+    );
+    let exit = supervisor.run_child(&hung).await.unwrap();
+    assert_eq!(exit, ChildExit::Completed { attempts: 2 });
+    assert_eq!(
+        *hooks.flushed.lock().unwrap(),
+        [ShutdownReason::WatchdogExpired]
+    );
+    let events: Vec<_> = events.take(3).collect().await;
+    assert!(matches!(
+        events[1],
+        SupervisorEvent::ChildTerminated {
+            attempt: 1,
+            reason: ShutdownReason::WatchdogExpired,
+            forced: true,
+            ..
+        }
+    ));
+    assert!(matches!(events[2], SupervisorEvent::WatchdogExpired { .. }));
+}